@@ -0,0 +1,97 @@
+//! Inputs of the benchmarks, generated from a fixed seed so runs compare
+
+/// Size of every corpus
+pub const SIZE: usize = 4 << 20;
+
+/// Kind of text in a corpus
+#[derive(Clone, Copy, Debug)]
+pub enum Corpus {
+    /// English-like ASCII prose in lines of about 60 columns
+    Ascii,
+    /// Cyrillic, CJK and emoji text, most characters several bytes long
+    Utf8,
+    /// ASCII words in lines of about 64 KiB
+    LongLines,
+    /// Random bytes, mostly invalid UTF-8
+    Binary,
+}
+
+impl Corpus {
+    pub const ALL: [Corpus; 4] = [
+        Corpus::Ascii,
+        Corpus::Utf8,
+        Corpus::LongLines,
+        Corpus::Binary,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Corpus::Ascii => "ascii",
+            Corpus::Utf8 => "utf8",
+            Corpus::LongLines => "long-lines",
+            Corpus::Binary => "binary",
+        }
+    }
+
+    pub fn generate(self) -> Vec<u8> {
+        let mut rng = Rng(0x2545_f491_4f6c_dd1d);
+        let mut data = Vec::with_capacity(SIZE + 64);
+        match self {
+            Corpus::Binary => {
+                while data.len() < SIZE {
+                    data.extend(rng.next().to_le_bytes());
+                }
+            }
+            Corpus::Ascii | Corpus::LongLines => {
+                let line_len = match self {
+                    Corpus::Ascii => 60,
+                    _ => 64 * 1024,
+                };
+                let mut column = 0;
+                while data.len() < SIZE {
+                    let word_len = 1 + rng.below(9);
+                    data.extend((0..word_len).map(|_| b'a' + rng.below(26) as u8));
+                    column += word_len + 1;
+                    data.push(if column >= line_len {
+                        column = 0;
+                        b'\n'
+                    } else {
+                        b' '
+                    });
+                }
+            }
+            Corpus::Utf8 => {
+                const WORDS: &[&str] = &["привет", "мир", "日本語", "テキスト", "😺", "naïve", "€"];
+                let mut column = 0;
+                while data.len() < SIZE {
+                    let word = WORDS[rng.below(WORDS.len())];
+                    data.extend(word.as_bytes());
+                    column += word.chars().count() + 1;
+                    data.push(if column >= 60 {
+                        column = 0;
+                        b'\n'
+                    } else {
+                        b' '
+                    });
+                }
+            }
+        }
+        data
+    }
+}
+
+/// The xorshift64* generator
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+}
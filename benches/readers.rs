@@ -0,0 +1,77 @@
+//! Throughput of the shared input layers of core: splitting records and
+//! reading files mapped or through reads
+
+mod corpus;
+
+use corpus::Corpus;
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use nyaa_core::mmap::FileData;
+use nyaa_core::record::{Records, Terminator};
+use std::fs;
+use std::hint::black_box;
+use std::io::{self, BufReader, Read};
+
+fn records(c: &mut Criterion) {
+    let mut group = c.benchmark_group("records");
+    group.sample_size(20);
+    for corpus in Corpus::ALL {
+        let data = corpus.generate();
+        group.throughput(Throughput::Bytes(data.len() as u64));
+        group.bench_function(corpus.name(), |b| {
+            b.iter(|| {
+                let mut records = Records::new(black_box(&data[..]), Terminator::Newline);
+                let mut count = 0;
+                while let Some(record) = records.next_record().unwrap() {
+                    count += record.len();
+                }
+                count
+            })
+        });
+    }
+    group.finish();
+}
+
+/// Sum of the bytes, so every byte is touched
+fn checksum(data: &[u8]) -> u64 {
+    data.iter().map(|&b| b as u64).sum()
+}
+
+fn file_data(c: &mut Criterion) {
+    let data = Corpus::Ascii.generate();
+    let path = std::env::temp_dir().join(format!("nyaa-bench-{}", std::process::id()));
+    fs::write(&path, &data).unwrap();
+    let mut group = c.benchmark_group("file-data");
+    group.throughput(Throughput::Bytes(data.len() as u64));
+    group.bench_function("mapped", |b| {
+        b.iter(|| match FileData::open(&path).unwrap() {
+            FileData::Mapped(mapping) => checksum(&mapping),
+            FileData::Stream(_) => panic!("the file should be mapped"),
+        })
+    });
+    group.bench_function("read", |b| {
+        b.iter(|| {
+            let mut reader = BufReader::with_capacity(64 * 1024, fs::File::open(&path).unwrap());
+            let mut buf = [0; 64 * 1024];
+            let mut sum = 0;
+            loop {
+                match reader.read(&mut buf).unwrap() {
+                    0 => break sum,
+                    n => sum += checksum(&buf[..n]),
+                }
+            }
+        })
+    });
+    group.bench_function("mapped-read", |b| {
+        b.iter(|| {
+            let FileData::Mapped(mut mapping) = FileData::open(&path).unwrap() else {
+                panic!("the file should be mapped");
+            };
+            io::copy(&mut mapping, &mut io::sink()).unwrap()
+        })
+    });
+    group.finish();
+    let _ = fs::remove_file(&path);
+}
+
+criterion_group!(benches, records, file_data);
+criterion_main!(benches);
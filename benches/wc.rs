@@ -0,0 +1,82 @@
+//! Throughput of the counting loops of `wc`, on text held in memory like
+//! a mapped file and through a reader like a pipe
+
+mod corpus;
+
+use corpus::Corpus;
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use nyaa_wc::{Count, Options, Selection, WordRule};
+use std::hint::black_box;
+
+/// Selections of counts with their own loops
+const SELECTIONS: &[(&str, Selection)] = &[
+    (
+        "lines",
+        Selection {
+            lines: true,
+            ..NONE
+        },
+    ),
+    ("default", NONE),
+    (
+        "chars",
+        Selection {
+            chars: true,
+            ..NONE
+        },
+    ),
+    (
+        "max-line-length",
+        Selection {
+            max_line_length: true,
+            ..NONE
+        },
+    ),
+    (
+        "graphemes",
+        Selection {
+            graphemes: true,
+            ..NONE
+        },
+    ),
+];
+
+const NONE: Selection = Selection {
+    chars: false,
+    bytes: false,
+    lines: false,
+    words: false,
+    graphemes: false,
+    max_line_length: false,
+    sentences: false,
+    paragraphs: false,
+    occurrences: false,
+    code: false,
+};
+
+fn count(c: &mut Criterion) {
+    let options = Options {
+        word_rule: WordRule::Unicode,
+        is_posix: false,
+        ..Options::default()
+    };
+    for corpus in Corpus::ALL {
+        let data = corpus.generate();
+        let mut group = c.benchmark_group(format!("wc/{}", corpus.name()));
+        group.throughput(Throughput::Bytes(data.len() as u64));
+        group.sample_size(20);
+        for (name, selection) in SELECTIONS {
+            let cnt = Count::new(selection);
+            group.bench_with_input(BenchmarkId::new("slice", name), &data, |b, data| {
+                b.iter(|| nyaa_wc::count_slice(black_box(data), &cnt, &options).unwrap())
+            });
+            group.bench_with_input(BenchmarkId::new("reader", name), &data, |b, data| {
+                b.iter(|| nyaa_wc::count_reader(black_box(&data[..]), &cnt, &options).unwrap())
+            });
+        }
+        group.finish();
+    }
+}
+
+criterion_group!(benches, count);
+criterion_main!(benches);
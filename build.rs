@@ -0,0 +1,36 @@
+//! Generates the registration of the applets of the multicall binary from
+//! the enabled features, so a build has only the applets asked for
+
+use std::env;
+use std::fs;
+use std::path::Path;
+
+/// Every applet, each enabled by the feature of its name and implemented
+/// by the `cli` module of its `nyaa-NAME` crate
+const APPLETS: &[&str] = &[
+    "cat", "echo", "false", "grep", "paste", "printf", "seq", "sort", "tac", "tail", "tr", "true",
+    "uniq", "wc", "yes",
+];
+
+fn main() {
+    let mut names: Vec<&str> = APPLETS
+        .iter()
+        .copied()
+        .filter(|name| {
+            let feature = name.to_uppercase().replace('-', "_");
+            env::var_os(format!("CARGO_FEATURE_{feature}")).is_some()
+        })
+        .collect();
+    names.sort_unstable();
+    let mut code = String::from("&[\n");
+    for name in names {
+        let module = format!("nyaa_{}::cli", name.replace('-', "_"));
+        code += &format!(
+            "    Applet {{ name: {name:?}, main: {module}::main, command: {module}::command }},\n"
+        );
+    }
+    code += "]\n";
+    let out = env::var_os("OUT_DIR").expect("cargo sets OUT_DIR");
+    fs::write(Path::new(&out).join("applets.rs"), code).expect("failed to write applets.rs");
+    println!("cargo::rerun-if-changed=build.rs");
+}
@@ -0,0 +1,222 @@
+//! Command line interface of `cat`
+
+use crate::{Formatter, Numbering, Options};
+use clap::{ArgAction, CommandFactory, FromArgMatches, Parser};
+use nyaa_core::error::{Status, UError};
+use nyaa_core::i18n;
+use nyaa_core::output::{self, OutputSink};
+use nyaa_core::posix;
+use nyaa_core::response;
+use nyaa_core::sandbox::SandboxArg;
+use nyaa_core::trace;
+use nyaa_core::{Input, Reader};
+use std::borrow::Cow;
+use std::ffi::OsString;
+use std::io::{self, Read, Write};
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+const UTIL: &str = "cat";
+
+const BUF_SIZE: usize = 128 * 1024;
+
+#[derive(Parser)]
+#[command(name = "cat", version)]
+#[command(about = "concatenate files and print on the standard output", long_about = None)]
+#[command(disable_help_flag = true, disable_version_flag = true)]
+struct Args {
+    /// Equivalent to -vET
+    #[arg(short = 'A', long = "show-all")]
+    is_show_all: bool,
+
+    /// Number the nonempty output lines, overrides -n
+    #[arg(short = 'b', long = "number-nonblank")]
+    is_number_nonblank: bool,
+
+    /// Equivalent to -vE
+    #[arg(short = 'e')]
+    is_show_ends_nonprinting: bool,
+
+    /// Display $ at the end of each line
+    #[arg(short = 'E', long = "show-ends")]
+    is_show_ends: bool,
+
+    /// Number all output lines
+    #[arg(short = 'n', long = "number")]
+    is_number: bool,
+
+    /// Suppress repeated empty output lines
+    #[arg(short = 's', long = "squeeze-blank")]
+    is_squeeze_blank: bool,
+
+    /// Equivalent to -vT
+    #[arg(short = 't')]
+    is_show_tabs_nonprinting: bool,
+
+    /// Display TAB characters as ^I
+    #[arg(short = 'T', long = "show-tabs")]
+    is_show_tabs: bool,
+
+    /// Ignored, the output is never buffered longer than a read
+    #[arg(short = 'u')]
+    is_unbuffered: bool,
+
+    /// Use ^ and M- notation, except for LFD and TAB
+    #[arg(short = 'v', long = "show-nonprinting")]
+    is_show_nonprinting: bool,
+
+    #[command(flatten)]
+    sandbox: SandboxArg,
+
+    /// Input files. An argument @FILE stands for the arguments on the
+    /// lines of FILE, @@NAME for the file @NAME
+    files: Vec<PathBuf>,
+
+    /// Print help
+    #[arg(long, action=ArgAction::Help)]
+    help: (),
+
+    /// Print version
+    #[arg(long, action=ArgAction::Version)]
+    version: (),
+}
+
+impl From<&Args> for Options {
+    fn from(args: &Args) -> Self {
+        let numbering = if args.is_number_nonblank {
+            Numbering::NonBlank
+        } else if args.is_number {
+            Numbering::All
+        } else {
+            Numbering::None
+        };
+        Options {
+            numbering,
+            squeeze_blank: args.is_squeeze_blank,
+            show_ends: args.is_show_all || args.is_show_ends_nonprinting || args.is_show_ends,
+            show_tabs: args.is_show_all || args.is_show_tabs_nonprinting || args.is_show_tabs,
+            show_nonprinting: args.is_show_all
+                || args.is_show_ends_nonprinting
+                || args.is_show_tabs_nonprinting
+                || args.is_show_nonprinting,
+        }
+    }
+}
+
+/// Failure printing an input. The following inputs are still printed
+/// after a failure to read one, but not after one to write the output.
+enum Failure {
+    Input(io::Error),
+    Output(io::Error),
+}
+
+/// Whether `reader` reads the file the stdout writes to, from before its
+/// end, which would make `cat` append to its own input forever
+#[cfg(unix)]
+fn is_output(reader: &Reader) -> bool {
+    use std::os::fd::AsRawFd;
+    let fd = match reader {
+        Reader::Stdin(stdin) => stdin.as_raw_fd(),
+        Reader::File(file) => file.as_raw_fd(),
+    };
+    let fstat = |fd| {
+        // SAFETY: the stat is written by fstat(2) before it is read
+        unsafe {
+            let mut stat: libc::stat = std::mem::zeroed();
+            (libc::fstat(fd, &mut stat) == 0).then_some(stat)
+        }
+    };
+    let (Some(input), Some(output)) = (fstat(fd), fstat(libc::STDOUT_FILENO)) else {
+        return false;
+    };
+    if input.st_mode & libc::S_IFMT != libc::S_IFREG
+        || (input.st_dev, input.st_ino) != (output.st_dev, output.st_ino)
+    {
+        return false;
+    }
+    // SAFETY: seeking by nothing only reports the offset
+    let offset = unsafe { libc::lseek(fd, 0, libc::SEEK_CUR) };
+    offset >= 0 && offset < input.st_size
+}
+
+#[cfg(not(unix))]
+fn is_output(_reader: &Reader) -> bool {
+    false
+}
+
+/// Print `input` to `out` through `formatter`
+fn cat_input(
+    input: &Input,
+    formatter: &mut Formatter,
+    out: &mut OutputSink,
+) -> Result<(), Failure> {
+    let mut reader = input.open().map_err(Failure::Input)?;
+    if is_output(&reader) {
+        return Err(Failure::Input(io::Error::other(
+            "input file is output file",
+        )));
+    }
+    if formatter.options().is_plain() {
+        out.flush().map_err(Failure::Output)?;
+        if crate::kernel_copy(&reader).map_err(Failure::Input)? {
+            return Ok(());
+        }
+    }
+    let mut buf = vec![0; BUF_SIZE];
+    loop {
+        let n = match reader.read(&mut buf) {
+            Ok(0) => return Ok(()),
+            Ok(n) => n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(Failure::Input(e)),
+        };
+        formatter.write(&buf[..n], out).map_err(Failure::Output)?;
+        // Like `cat -u`, what was read is written before reading on,
+        // so slow input shows up as it comes
+        out.flush().map_err(Failure::Output)?;
+    }
+}
+
+pub fn command() -> clap::Command {
+    i18n::command(Args::command())
+}
+
+/// Run `cat` with the command line `args`, starting with the program name
+pub fn main(args: Vec<OsString>) -> ExitCode {
+    let mut status = Status::default();
+    let Some(args) = status.check(UTIL, response::expand(args)) else {
+        return status.into();
+    };
+    let args = posix::operands_last(&command(), args);
+    let matches = command().get_matches_from(args);
+    let args = Args::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+    trace::init(UTIL, false);
+    if status.check(UTIL, args.sandbox.apply()).is_none() {
+        return status.into();
+    }
+    let operands = if args.files.is_empty() {
+        vec![PathBuf::from("-")]
+    } else {
+        args.files.clone()
+    };
+    let mut formatter = Formatter::new(Options::from(&args));
+    let mut out = OutputSink::stdout();
+    for operand in &operands {
+        let input = Input::from_operand(operand);
+        let name = input.quoted_name().unwrap_or(Cow::Borrowed("-"));
+        match trace::operand(&name, || cat_input(&input, &mut formatter, &mut out)) {
+            Ok(()) => (),
+            Err(Failure::Input(e)) => status.report(UTIL, &UError::from(e).with_name(&name)),
+            Err(Failure::Output(e)) => {
+                status.report(UTIL, &output::write_error(e));
+                return status.into();
+            }
+        }
+    }
+    let result = formatter
+        .finish(&mut out)
+        .map_err(output::write_error)
+        .and_then(|()| out.finish());
+    status.check(UTIL, result);
+    status.into()
+}
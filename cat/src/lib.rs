@@ -0,0 +1,292 @@
+//! Concatenation with the output options of `cat`: numbering lines,
+//! squeezing blank ones and showing line ends, tabs and nonprinting
+//! characters.
+//!
+//! Input is formatted by a [`Formatter`], which carries the line number
+//! and whether a line is open from one input to the next, so the lines of
+//! all inputs are numbered in one sequence like `cat` does. Input that
+//! isn't formatted is copied to the stdout by [`kernel_copy`] where the
+//! files allow it, without passing through the process.
+//!
+//! ```
+//! let options = nyaa_cat::Options {
+//!     numbering: nyaa_cat::Numbering::All,
+//!     show_ends: true,
+//!     ..Default::default()
+//! };
+//! let mut formatter = nyaa_cat::Formatter::new(options);
+//! let mut out = Vec::new();
+//! formatter.write(b"one\n\ntw", &mut out)?;
+//! formatter.write(b"o\n", &mut out)?;
+//! formatter.finish(&mut out)?;
+//! assert_eq!(out, b"     1\tone$\n     2\t$\n     3\ttwo$\n");
+//! # Ok::<(), std::io::Error>(())
+//! ```
+
+pub mod cli;
+
+use nyaa_core::Reader;
+use std::io::{self, Write};
+
+/// Which lines get a number
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Numbering {
+    #[default]
+    None,
+    /// `-n`, every line
+    All,
+    /// `-b`, the lines that are not empty
+    NonBlank,
+}
+
+/// How the input is formatted, one field for each output option of `cat`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Options {
+    pub numbering: Numbering,
+    /// `-s`, print one empty line of several adjacent ones
+    pub squeeze_blank: bool,
+    /// `-E`, print `$` at the end of each line
+    pub show_ends: bool,
+    /// `-T`, print tabs as `^I`
+    pub show_tabs: bool,
+    /// `-v`, print control characters in `^` notation and bytes above
+    /// 127 in `M-` notation, except newlines and tabs
+    pub show_nonprinting: bool,
+}
+
+impl Options {
+    /// Whether the input is copied as is
+    pub fn is_plain(&self) -> bool {
+        *self == Options::default()
+    }
+}
+
+/// Formatter of the input of `cat`, fed the inputs one after another
+pub struct Formatter {
+    options: Options,
+    /// Number of the last numbered line
+    line: u64,
+    /// Newlines since the last other character: -1 inside a line, 0 at
+    /// the start of one and 1 or more after that many empty lines. Starts
+    /// at 0, as the output starts with a line.
+    newlines: i32,
+    /// A carriage return at the end of the input so far, which `-E`
+    /// shows as `^M` if the line ends after it
+    is_cr_pending: bool,
+}
+
+impl Formatter {
+    pub fn new(options: Options) -> Self {
+        Formatter {
+            options,
+            line: 0,
+            newlines: 0,
+            is_cr_pending: false,
+        }
+    }
+
+    pub fn options(&self) -> &Options {
+        &self.options
+    }
+
+    /// Format the next piece of the input to `out`
+    pub fn write(&mut self, mut data: &[u8], out: &mut impl Write) -> io::Result<()> {
+        if self.options.is_plain() {
+            return out.write_all(data);
+        }
+        while !data.is_empty() {
+            let end = memchr::memchr(b'\n', data);
+            let text = &data[..end.unwrap_or(data.len())];
+            if !text.is_empty() {
+                if self.is_cr_pending {
+                    self.is_cr_pending = false;
+                    out.write_all(b"\r")?;
+                }
+                if self.newlines >= 0 {
+                    self.newlines = -1;
+                    if self.options.numbering != Numbering::None {
+                        self.write_number(out)?;
+                    }
+                }
+                self.write_text(text, out)?;
+            }
+            let Some(end) = end else {
+                break;
+            };
+            self.end_line(out)?;
+            data = &data[end + 1..];
+        }
+        Ok(())
+    }
+
+    /// Write out what is held back at the end of the input
+    pub fn finish(&mut self, out: &mut impl Write) -> io::Result<()> {
+        if self.is_cr_pending {
+            self.is_cr_pending = false;
+            out.write_all(b"\r")?;
+        }
+        Ok(())
+    }
+
+    fn write_number(&mut self, out: &mut impl Write) -> io::Result<()> {
+        self.line += 1;
+        write!(out, "{:>6}\t", self.line)
+    }
+
+    fn end_line(&mut self, out: &mut impl Write) -> io::Result<()> {
+        self.newlines += 1;
+        if self.newlines > 0 {
+            // An empty line, squeezed after the first one
+            if self.newlines >= 2 {
+                self.newlines = 2;
+                if self.options.squeeze_blank {
+                    return Ok(());
+                }
+            }
+            if self.options.numbering == Numbering::All {
+                self.write_number(out)?;
+            }
+        }
+        if self.options.show_ends {
+            if self.is_cr_pending {
+                self.is_cr_pending = false;
+                out.write_all(b"^M")?;
+            }
+            out.write_all(b"$")?;
+        }
+        out.write_all(b"\n")
+    }
+
+    /// Write the text of a line without its newline
+    fn write_text(&mut self, mut text: &[u8], out: &mut impl Write) -> io::Result<()> {
+        let Options {
+            show_tabs,
+            show_nonprinting,
+            show_ends,
+            ..
+        } = self.options;
+        // Held back until it is known whether the line ends after it
+        if show_ends && !show_nonprinting && text.ends_with(b"\r") {
+            text = &text[..text.len() - 1];
+            self.is_cr_pending = true;
+        }
+        if !show_tabs && !show_nonprinting {
+            return out.write_all(text);
+        }
+        let mut buf = Vec::with_capacity(text.len() * 2);
+        for &b in text {
+            match b {
+                b'\t' if show_tabs => buf.extend_from_slice(b"^I"),
+                b'\t' => buf.push(b),
+                _ if !show_nonprinting => buf.push(b),
+                0..=31 => buf.extend_from_slice(&[b'^', b + 64]),
+                32..=126 => buf.push(b),
+                127 => buf.extend_from_slice(b"^?"),
+                128..=159 => buf.extend_from_slice(&[b'M', b'-', b'^', b - 128 + 64]),
+                160..=254 => buf.extend_from_slice(&[b'M', b'-', b - 128]),
+                255 => buf.extend_from_slice(b"M-^?"),
+            }
+        }
+        out.write_all(&buf)
+    }
+}
+
+/// Copy what is left of `input` to the stdout inside the kernel, by
+/// copy_file_range(2) between regular files and splice(2) from or to a
+/// pipe. `false` if the files allow neither, and nothing was copied.
+pub fn kernel_copy(input: &Reader) -> io::Result<bool> {
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    {
+        use std::os::fd::AsRawFd;
+        let fd = match input {
+            Reader::Stdin(stdin) => stdin.as_raw_fd(),
+            Reader::File(file) => file.as_raw_fd(),
+        };
+        sys::copy(fd, libc::STDOUT_FILENO)
+    }
+    #[cfg(not(any(target_os = "linux", target_os = "android")))]
+    {
+        let _ = input;
+        Ok(false)
+    }
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+mod sys {
+    use std::io;
+    use std::os::fd::RawFd;
+    use std::ptr;
+
+    /// Bytes asked for by one call, the kernel copies less at a time
+    const CHUNK: usize = 1 << 30;
+
+    fn file_type(fd: RawFd) -> io::Result<(libc::mode_t, libc::off_t)> {
+        // SAFETY: the stat is written by fstat(2) before it is read
+        unsafe {
+            let mut stat: libc::stat = std::mem::zeroed();
+            if libc::fstat(fd, &mut stat) < 0 {
+                return Err(io::Error::last_os_error());
+            }
+            Ok((stat.st_mode & libc::S_IFMT, stat.st_size))
+        }
+    }
+
+    /// Repeat `step` until it copies nothing more, `false` if the first
+    /// call fails because the files don't support it
+    fn transfer(mut step: impl FnMut() -> libc::ssize_t) -> io::Result<bool> {
+        let mut has_copied = false;
+        loop {
+            match step() {
+                0 => return Ok(true),
+                n if n > 0 => has_copied = true,
+                _ => {
+                    let err = io::Error::last_os_error();
+                    match err.raw_os_error() {
+                        Some(libc::EINTR) => (),
+                        Some(
+                            libc::EINVAL
+                            | libc::EXDEV
+                            | libc::ENOSYS
+                            | libc::EOPNOTSUPP
+                            | libc::EBADF,
+                        ) if !has_copied => return Ok(false),
+                        _ => return Err(err),
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn copy(from: RawFd, to: RawFd) -> io::Result<bool> {
+        let (from_type, from_size) = file_type(from)?;
+        let (to_type, _) = file_type(to)?;
+        // Files of procfs and sysfs claim to be empty, and copy nothing
+        if from_type == libc::S_IFREG && from_size > 0 && to_type == libc::S_IFREG {
+            nyaa_core::debug!("copying with copy_file_range");
+            // SAFETY: null offsets copy from and advance the offsets of
+            // the descriptors
+            let step = || unsafe {
+                libc::copy_file_range(from, ptr::null_mut(), to, ptr::null_mut(), CHUNK, 0)
+            };
+            if transfer(step)? {
+                return Ok(true);
+            }
+        }
+        if from_type == libc::S_IFIFO || to_type == libc::S_IFIFO {
+            nyaa_core::debug!("copying with splice");
+            // SAFETY: as for copy_file_range
+            let step = || unsafe {
+                libc::splice(
+                    from,
+                    ptr::null_mut(),
+                    to,
+                    ptr::null_mut(),
+                    CHUNK,
+                    libc::SPLICE_F_MOVE,
+                )
+            };
+            return transfer(step);
+        }
+        Ok(false)
+    }
+}
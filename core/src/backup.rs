@@ -0,0 +1,304 @@
+//! Backups of files about to be overwritten, with the options of GNU `cp`,
+//! `mv`, `ln` and `install`: `--backup[=CONTROL]`, `-b` and `-S SUFFIX`,
+//! defaulting to `VERSION_CONTROL` and `SIMPLE_BACKUP_SUFFIX`
+
+use crate::error::{UError, UResult};
+use crate::quote::quote;
+use std::ffi::OsString;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// How backups are named
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Mode {
+    /// No backups, `none` or `off`
+    None,
+    /// `FILE~`, `simple` or `never`
+    Simple,
+    /// `FILE.~N~`, `numbered` or `t`
+    Numbered,
+    /// Numbered if numbered backups of the file exist already, simple
+    /// otherwise, `existing` or `nil`
+    #[default]
+    Existing,
+}
+
+impl FromStr for Mode {
+    type Err = UError;
+
+    /// A CONTROL value, or any unambiguous abbreviation of one
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        const NAMES: [(&str, Mode); 8] = [
+            ("none", Mode::None),
+            ("off", Mode::None),
+            ("simple", Mode::Simple),
+            ("never", Mode::Simple),
+            ("existing", Mode::Existing),
+            ("nil", Mode::Existing),
+            ("numbered", Mode::Numbered),
+            ("t", Mode::Numbered),
+        ];
+        if let Some(&(_, mode)) = NAMES.iter().find(|(name, _)| *name == s) {
+            return Ok(mode);
+        }
+        let mut candidates = NAMES.iter().filter(|(name, _)| name.starts_with(s));
+        match (candidates.next(), candidates.next()) {
+            (Some(&(_, mode)), None) if !s.is_empty() => Ok(mode),
+            (Some(_), Some(_)) if !s.is_empty() => Err(UError::usage(format!(
+                "ambiguous argument '{s}' for 'backup type'"
+            ))),
+            _ => Err(UError::usage(format!(
+                "invalid argument '{s}' for 'backup type'"
+            ))),
+        }
+    }
+}
+
+/// The backup options, to flatten into the arguments of a utility
+#[derive(Clone, Debug, Default, clap::Args)]
+pub struct BackupArgs {
+    /// Make a backup of each existing destination file, named after
+    /// CONTROL: none, simple, numbered or existing
+    #[arg(
+        long = "backup",
+        value_name = "CONTROL",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = ""
+    )]
+    control: Option<String>,
+
+    /// Like --backup but does not accept an argument
+    #[arg(short = 'b')]
+    is_backup: bool,
+
+    /// Override the usual backup suffix, ~
+    #[arg(short = 'S', long = "suffix", value_name = "SUFFIX")]
+    suffix: Option<String>,
+}
+
+impl BackupArgs {
+    /// The backups asked for, `None` if none are
+    pub fn backup(&self) -> UResult<Option<Backup>> {
+        let control = match &self.control {
+            Some(control) if !control.is_empty() => Some(control.clone()),
+            Some(_) => std::env::var("VERSION_CONTROL").ok(),
+            None if self.is_backup || self.suffix.is_some() => {
+                std::env::var("VERSION_CONTROL").ok()
+            }
+            None => return Ok(None),
+        };
+        let mode = match control.filter(|control| !control.is_empty()) {
+            Some(control) => control.parse()?,
+            None => Mode::default(),
+        };
+        if mode == Mode::None {
+            return Ok(None);
+        }
+        let suffix = self
+            .suffix
+            .clone()
+            .or_else(|| std::env::var("SIMPLE_BACKUP_SUFFIX").ok());
+        Ok(Some(Backup::new(mode, suffix)))
+    }
+}
+
+/// Naming of the backups of files
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Backup {
+    mode: Mode,
+    suffix: String,
+}
+
+impl Backup {
+    /// Backups named after `mode`, with `suffix` for simple ones, `~` if
+    /// not given or if it would name a file in another directory
+    pub fn new(mode: Mode, suffix: Option<String>) -> Self {
+        let suffix = suffix
+            .filter(|suffix| !suffix.is_empty() && !suffix.contains('/'))
+            .unwrap_or_else(|| "~".to_string());
+        Backup { mode, suffix }
+    }
+
+    pub fn mode(&self) -> Mode {
+        self.mode
+    }
+
+    /// Name of the backup of `path`, `None` if no backups are made
+    pub fn path(&self, path: &Path) -> Option<PathBuf> {
+        let simple = || {
+            let mut name = OsString::from(path.as_os_str());
+            name.push(&self.suffix);
+            PathBuf::from(name)
+        };
+        let numbered = |last: u64| {
+            let mut name = OsString::from(path.as_os_str());
+            name.push(format!(".~{}~", last + 1));
+            PathBuf::from(name)
+        };
+        match self.mode {
+            Mode::None => None,
+            Mode::Simple => Some(simple()),
+            Mode::Numbered => Some(numbered(last_number(path).unwrap_or(0))),
+            Mode::Existing => Some(last_number(path).map_or_else(simple, numbered)),
+        }
+    }
+
+    /// Rename the existing file `path` to its backup, before it is
+    /// overwritten. Returns the name of the backup, `None` if there is no
+    /// file to back up or no backups are made.
+    pub fn make(&self, path: &Path) -> UResult<Option<PathBuf>> {
+        if fs::symlink_metadata(path).is_err() {
+            return Ok(None);
+        }
+        let Some(backup) = self.path(path) else {
+            return Ok(None);
+        };
+        fs::rename(path, &backup).map_err(|e: io::Error| {
+            UError::from(e).with_name(format!(
+                "cannot back up {} to {}",
+                quote(path.as_os_str()),
+                quote(backup.as_os_str())
+            ))
+        })?;
+        Ok(Some(backup))
+    }
+}
+
+/// Highest number of the numbered backups of `path`, `None` if it has none
+fn last_number(path: &Path) -> Option<u64> {
+    let name = path.file_name()?.as_encoded_bytes();
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    fs::read_dir(dir)
+        .ok()?
+        .filter_map(Result::ok)
+        .filter_map(|entry| {
+            let entry_name = entry.file_name();
+            let number = entry_name
+                .as_encoded_bytes()
+                .strip_prefix(name)?
+                .strip_prefix(b".~")?
+                .strip_suffix(b"~")?;
+            if number.is_empty() || !number.iter().all(u8::is_ascii_digit) {
+                return None;
+            }
+            std::str::from_utf8(number).ok()?.parse().ok()
+        })
+        .max()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    #[derive(Parser)]
+    struct Args {
+        #[command(flatten)]
+        backup: BackupArgs,
+    }
+
+    /// A fresh directory with the file `file` in it
+    fn dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("nyaa-backup-{}-{name}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("file"), "new").unwrap();
+        dir
+    }
+
+    fn from_args(args: &[&str]) -> Option<Backup> {
+        let args = Args::try_parse_from([&["test"], args].concat()).unwrap();
+        args.backup.backup().unwrap()
+    }
+
+    #[test]
+    fn simple() {
+        let dir = dir("simple");
+        let file = dir.join("file");
+        let backup = Backup::new(Mode::Simple, None);
+        assert_eq!(backup.path(&file), Some(dir.join("file~")));
+        let backup = Backup::new(Mode::Simple, Some(".bak".to_string()));
+        assert_eq!(backup.make(&file).unwrap(), Some(dir.join("file.bak")));
+        assert_eq!(fs::read_to_string(dir.join("file.bak")).unwrap(), "new");
+        assert!(!file.exists());
+        // Nothing to back up
+        assert_eq!(backup.make(&file).unwrap(), None);
+        // Suffixes naming another directory are ignored
+        let backup = Backup::new(Mode::Simple, Some("/x".to_string()));
+        assert_eq!(backup.path(&file), Some(dir.join("file~")));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn numbered() {
+        let dir = dir("numbered");
+        let file = dir.join("file");
+        let backup = Backup::new(Mode::Numbered, None);
+        assert_eq!(backup.path(&file), Some(dir.join("file.~1~")));
+        for name in ["file.~1~", "file.~9~", "file.~x~", "file.~~", "other.~20~"] {
+            fs::write(dir.join(name), "").unwrap();
+        }
+        assert_eq!(backup.make(&file).unwrap(), Some(dir.join("file.~10~")));
+        assert_eq!(fs::read_to_string(dir.join("file.~10~")).unwrap(), "new");
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn existing() {
+        let dir = dir("existing");
+        let file = dir.join("file");
+        let backup = Backup::new(Mode::Existing, Some(".old".to_string()));
+        assert_eq!(backup.path(&file), Some(dir.join("file.old")));
+        fs::write(dir.join("file.~2~"), "").unwrap();
+        assert_eq!(backup.path(&file), Some(dir.join("file.~3~")));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn none() {
+        let dir = dir("none");
+        let file = dir.join("file");
+        let backup = Backup::new(Mode::None, None);
+        assert_eq!(backup.path(&file), None);
+        assert_eq!(backup.make(&file).unwrap(), None);
+        assert!(file.exists());
+        assert_eq!(from_args(&["--backup=none"]), None);
+        assert_eq!(from_args(&["--backup=off", "-S", ".bak"]), None);
+        assert_eq!(from_args(&[]), None);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn controls() {
+        let mode = |s: &str| s.parse::<Mode>().map_err(|e| e.to_string());
+        assert_eq!(mode("numbered"), Ok(Mode::Numbered));
+        assert_eq!(mode("t"), Ok(Mode::Numbered));
+        assert_eq!(mode("nu"), Ok(Mode::Numbered));
+        assert_eq!(mode("nev"), Ok(Mode::Simple));
+        assert_eq!(mode("s"), Ok(Mode::Simple));
+        assert_eq!(mode("ex"), Ok(Mode::Existing));
+        assert_eq!(mode("ni"), Ok(Mode::Existing));
+        assert_eq!(mode("of"), Ok(Mode::None));
+        assert!(mode("n").unwrap_err().starts_with("ambiguous argument 'n'"));
+        assert!(mode("").unwrap_err().starts_with("invalid argument ''"));
+        assert!(mode("bogus")
+            .unwrap_err()
+            .starts_with("invalid argument 'bogus'"));
+        assert_eq!(
+            from_args(&["--backup=nu"]).map(|b| b.mode()),
+            Some(Mode::Numbered)
+        );
+        assert_eq!(
+            from_args(&["--backup=simple", "-S", ".bak"]),
+            Some(Backup::new(Mode::Simple, Some(".bak".to_string())))
+        );
+        let args = Args::try_parse_from(["test", "--backup=n"]).unwrap();
+        assert!(args.backup.backup().is_err());
+    }
+}
@@ -0,0 +1,105 @@
+//! Colored output and the `--color=WHEN` option. Color is left out when
+//! `NO_COLOR` is set or `CLICOLOR=0`, and forced by `CLICOLOR_FORCE`,
+//! unless `--color=always` or `--color=never` decide explicitly.
+
+use crate::platform;
+use clap::ValueEnum;
+use std::borrow::Cow;
+use std::io::IsTerminal;
+
+/// When to color the output
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum When {
+    /// Only if the output is a terminal
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+/// The `--color[=WHEN]` option, to flatten into the arguments of a
+/// utility. `--color` alone means always, like in GNU `ls` and `grep`.
+#[derive(Clone, Copy, Debug, Default, clap::Args)]
+pub struct ColorArg {
+    /// When to color the output
+    #[arg(
+        long = "color",
+        value_enum,
+        value_name = "WHEN",
+        num_args = 0..=1,
+        require_equals = true,
+        default_value_t = When::Auto,
+        default_missing_value = "always",
+    )]
+    pub when: When,
+}
+
+fn is_set(var: &str) -> bool {
+    std::env::var_os(var).is_some_and(|value| !value.is_empty())
+}
+
+fn is_var(var: &str, value: &str) -> bool {
+    std::env::var_os(var).is_some_and(|v| v == value)
+}
+
+impl When {
+    /// Whether to color output written to `stream`
+    pub fn is_enabled(self, stream: &impl IsTerminal) -> bool {
+        match self {
+            When::Always => true,
+            When::Never => false,
+            When::Auto if is_set("CLICOLOR_FORCE") && !is_var("CLICOLOR_FORCE", "0") => true,
+            When::Auto => {
+                !is_set("NO_COLOR")
+                    && !is_var("CLICOLOR", "0")
+                    && !is_var("TERM", "dumb")
+                    && stream.is_terminal()
+            }
+        }
+    }
+}
+
+/// Select Graphic Rendition parameters of common styles, for [`Painter::paint`]
+pub mod sgr {
+    pub const BOLD: &str = "1";
+    pub const UNDERLINE: &str = "4";
+    pub const RED: &str = "31";
+    pub const GREEN: &str = "32";
+    pub const YELLOW: &str = "33";
+    pub const BLUE: &str = "34";
+    pub const MAGENTA: &str = "35";
+    pub const CYAN: &str = "36";
+    /// Bold red, the matches of `grep`
+    pub const MATCH: &str = "01;31";
+}
+
+/// Wraps text in escape sequences when coloring is enabled
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Painter {
+    is_enabled: bool,
+}
+
+impl Painter {
+    /// Painter of output written to `stream`
+    pub fn new(when: When, stream: &impl IsTerminal) -> Self {
+        // A console that can't show colors gets none, unless they are
+        // asked for
+        let is_enabled = when.is_enabled(stream) && (when != When::Auto || platform::enable_ansi());
+        Painter { is_enabled }
+    }
+
+    pub fn is_enabled(self) -> bool {
+        self.is_enabled
+    }
+
+    /// `text` in the style of the SGR parameters `sgr`, like `01;31`. The
+    /// line is cleared to its end after the style is reset, so the
+    /// background doesn't bleed when the terminal scrolls.
+    pub fn paint<'a>(self, sgr: &str, text: &'a str) -> Cow<'a, str> {
+        if self.is_enabled && !sgr.is_empty() {
+            Cow::Owned(format!("\x1b[{sgr}m\x1b[K{text}\x1b[m\x1b[K"))
+        } else {
+            Cow::Borrowed(text)
+        }
+    }
+}
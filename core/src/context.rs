@@ -0,0 +1,208 @@
+//! Context of selected lines, the lines around them output along with
+//! them, like `grep -C` does. The lines before a selected line are kept in
+//! a ring of the last ones read until it calls for them, so no more lines
+//! are held than the context has, whatever the size of the input. Groups
+//! of lines output that aren't adjacent are told apart by the gap between
+//! them.
+
+use std::collections::VecDeque;
+
+/// A line kept as context, with where it is in its input
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Line {
+    /// Number of the line, the first one being 1
+    pub number: u64,
+    /// Offset of its first byte in the input
+    pub offset: u64,
+    /// The line, without its terminator
+    pub data: Vec<u8>,
+}
+
+/// The last lines read, up to a number of them
+#[derive(Debug)]
+pub struct Ring {
+    lines: VecDeque<Line>,
+    capacity: usize,
+}
+
+impl Ring {
+    /// Ring of the last `capacity` lines
+    pub fn new(capacity: usize) -> Self {
+        Ring {
+            lines: VecDeque::new(),
+            capacity,
+        }
+    }
+
+    /// Keep `data` as the line `number` at `offset`, forgetting the oldest
+    /// line when the ring is full, whose buffer is reused
+    pub fn push(&mut self, number: u64, offset: u64, data: &[u8]) {
+        if self.capacity == 0 {
+            return;
+        }
+        let mut line = match self.lines.len() == self.capacity {
+            true => self.lines.pop_front().unwrap_or_default(),
+            false => Line::default(),
+        };
+        line.number = number;
+        line.offset = offset;
+        line.data.clear();
+        line.data.extend_from_slice(data);
+        self.lines.push_back(line);
+    }
+
+    /// The oldest line kept
+    pub fn front(&self) -> Option<&Line> {
+        self.lines.front()
+    }
+
+    /// Take the lines kept, oldest first
+    pub fn drain(&mut self) -> impl Iterator<Item = Line> + '_ {
+        self.lines.drain(..)
+    }
+}
+
+/// Which lines around the selected ones of an input are output, and where
+/// gaps separate the groups of lines written
+#[derive(Debug)]
+pub struct Context {
+    before: Ring,
+    after: usize,
+    /// Lines after the last selected one still output as context
+    after_left: usize,
+    /// Number of the last line written, 0 before any
+    last: u64,
+    is_started: bool,
+}
+
+impl Context {
+    /// Context of the `before` lines before every selected line and the
+    /// `after` lines after it
+    pub fn new(before: usize, after: usize) -> Self {
+        Context {
+            before: Ring::new(before),
+            after,
+            after_left: 0,
+            last: 0,
+            is_started: false,
+        }
+    }
+
+    /// Note the line `number` at `offset` isn't selected. Returns whether
+    /// it is output as context after a selected line, else it is kept as
+    /// context of the next one.
+    pub fn unselected(&mut self, number: u64, offset: u64, data: &[u8]) -> bool {
+        if self.after_left > 0 {
+            self.after_left -= 1;
+            return true;
+        }
+        self.before.push(number, offset, data);
+        false
+    }
+
+    /// Note the line `number` is selected. Returns whether a gap separates
+    /// it and the context before it from the lines written before, then
+    /// the lines to output before it as context.
+    pub fn selected(&mut self, number: u64) -> (bool, impl Iterator<Item = Line> + '_) {
+        let first = self.before.front().map_or(number, |line| line.number);
+        let is_gap = self.is_started && first > self.last + 1;
+        self.is_started = true;
+        self.after_left = self.after;
+        (is_gap, self.before.drain())
+    }
+
+    /// Note the line `number` output was written
+    pub fn written(&mut self, number: u64) {
+        self.last = number;
+    }
+
+    /// Note the line `number` at `offset` output couldn't be written, which
+    /// ends the context after the last selected line. It is kept as context
+    /// of the next one still.
+    pub fn unwritten(&mut self, number: u64, offset: u64, data: &[u8]) {
+        self.after_left = 0;
+        self.before.push(number, offset, data);
+    }
+
+    /// Whether the next line unselected is output as context after a
+    /// selected line
+    pub fn is_after(&self) -> bool {
+        self.after_left > 0
+    }
+
+    /// Whether any line was selected
+    pub fn is_started(&self) -> bool {
+        self.is_started
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_lines_around_selected_ones() {
+        let mut context = Context::new(1, 1);
+        let lines = ["a", "b", "c", "X", "d", "e", "f", "X", "X", "g", "X"];
+        let mut out = Vec::new();
+        for (number, data) in (1..).zip(lines) {
+            if data != "X" {
+                if context.unselected(number, 0, data.as_bytes()) {
+                    out.push(format!("{number}-"));
+                    context.written(number);
+                }
+                continue;
+            }
+            let (is_gap, before) = context.selected(number);
+            if is_gap {
+                out.push("--".into());
+            }
+            let before: Vec<_> = before.map(|line| line.number).collect();
+            for number in before {
+                out.push(format!("{number}-"));
+                context.written(number);
+            }
+            out.push(format!("{number}:"));
+            context.written(number);
+        }
+        assert_eq!(
+            out,
+            ["3-", "4:", "5-", "--", "7-", "8:", "9:", "10-", "11:"]
+        );
+        assert!(context.is_started());
+    }
+
+    #[test]
+    fn ends_after_context_at_lines_unwritten() {
+        let mut context = Context::new(1, 2);
+        let _ = context.selected(1);
+        context.written(1);
+        assert!(context.is_after());
+        assert!(context.unselected(2, 0, b"b"));
+        context.unwritten(2, 0, b"b");
+        assert!(!context.is_after());
+        assert!(!context.unselected(3, 0, b"c"));
+        // Only the last line is kept
+        assert!(!context.unselected(4, 0, b"d"));
+        let (is_gap, before) = context.selected(5);
+        assert!(is_gap);
+        assert_eq!(before.map(|line| line.number).collect::<Vec<_>>(), [4]);
+    }
+
+    #[test]
+    fn reuses_the_oldest_line() {
+        let mut ring = Ring::new(2);
+        for (number, data) in (1..).zip(["a", "bb", "ccc"]) {
+            ring.push(number, number * 10, data.as_bytes());
+        }
+        let lines: Vec<_> = ring
+            .drain()
+            .map(|line| (line.number, line.offset, line.data))
+            .collect();
+        assert_eq!(lines, [(2, 20, b"bb".to_vec()), (3, 30, b"ccc".to_vec())]);
+        assert!(ring.front().is_none());
+        let mut ring = Ring::new(0);
+        ring.push(1, 0, b"a");
+        assert!(ring.front().is_none());
+    }
+}
@@ -0,0 +1,452 @@
+//! Dates and times as the utilities take and print them: `-d` strings
+//! like `@1700000000`, `2024-03-01 12:00` or `2 days ago`, the stamps of
+//! `date MMDDhhmm[[CC]YY][.ss]` and `touch -t`, and strftime(3) formats.
+//! Times are in the time zone of `TZ`, or of the system when it's unset.
+
+use crate::error::{UError, UResult};
+use jiff::civil::{Date, DateTime, Time};
+use jiff::tz::Offset;
+use jiff::{Span, Timestamp};
+
+pub use jiff::Zoned;
+
+/// The current time in the local time zone
+pub fn now() -> Zoned {
+    Zoned::now()
+}
+
+fn invalid(s: &str) -> UError {
+    UError::failure(format!("invalid date '{s}'"))
+}
+
+/// `time` formatted with the conversions of strftime(3), like `%F %T`,
+/// with the flags and widths of GNU `date` like `%-d` and `%:z`
+pub fn format(time: &Zoned, format: &str) -> UResult<String> {
+    jiff::fmt::strtime::format(format, time)
+        .map_err(|e| UError::failure(format!("invalid format '{format}': {e}")))
+}
+
+/// Year of a two digit year, 1969 to 2068 like POSIX specifies it
+fn expand_year(yy: i16) -> i16 {
+    if yy >= 69 {
+        1900 + yy
+    } else {
+        2000 + yy
+    }
+}
+
+/// Number of the digits `s`, `None` if it isn't only ASCII digits
+fn number(s: &str) -> Option<i64> {
+    (!s.is_empty() && s.bytes().all(|b| b.is_ascii_digit()))
+        .then(|| s.parse().ok())
+        .flatten()
+}
+
+/// Pairs of digits of `s` as numbers
+fn pairs(s: &str) -> Option<Vec<i8>> {
+    s.as_bytes()
+        .chunks(2)
+        .map(|pair| number(std::str::from_utf8(pair).ok()?).map(|n| n as i8))
+        .collect()
+}
+
+/// Split the `.ss` seconds off a stamp
+fn split_seconds(s: &str) -> Option<(&str, i8)> {
+    match s.split_once('.') {
+        Some((stamp, seconds)) if seconds.len() == 2 => Some((stamp, number(seconds)? as i8)),
+        Some(_) => None,
+        None => Some((s, 0)),
+    }
+}
+
+/// `datetime` in the time zone of `now`, the earlier time when it's
+/// ambiguous and the one after the gap when a DST change skips over it
+fn local(datetime: DateTime, now: &Zoned, s: &str) -> UResult<Zoned> {
+    datetime
+        .to_zoned(now.time_zone().clone())
+        .map_err(|_| invalid(s))
+}
+
+/// A stamp of `date` to set the time: `MMDDhhmm[[CC]YY][.ss]`, in the
+/// current year if it has none
+pub fn parse_posix_stamp(s: &str, now: &Zoned) -> UResult<Zoned> {
+    let (stamp, second) = split_seconds(s).ok_or_else(|| invalid(s))?;
+    if !stamp.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(invalid(s));
+    }
+    let (year, fields) = match stamp.len() {
+        8 => (now.year(), stamp),
+        10 => (
+            expand_year(number(&stamp[8..]).ok_or_else(|| invalid(s))? as i16),
+            &stamp[..8],
+        ),
+        12 => (
+            number(&stamp[8..]).ok_or_else(|| invalid(s))? as i16,
+            &stamp[..8],
+        ),
+        _ => return Err(invalid(s)),
+    };
+    let [month, day, hour, minute] = pairs(fields).ok_or_else(|| invalid(s))?[..] else {
+        return Err(invalid(s));
+    };
+    let datetime =
+        DateTime::new(year, month, day, hour, minute, second, 0).map_err(|_| invalid(s))?;
+    local(datetime, now, s)
+}
+
+/// A stamp of `touch -t`: `[[CC]YY]MMDDhhmm[.ss]`, in the current year if
+/// it has none
+pub fn parse_touch_stamp(s: &str, now: &Zoned) -> UResult<Zoned> {
+    let (stamp, seconds) = match s.split_once('.') {
+        Some((stamp, seconds)) => (stamp, format!(".{seconds}")),
+        None => (s, String::new()),
+    };
+    if !matches!(stamp.len(), 8 | 10 | 12) || !stamp.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(invalid(s));
+    }
+    // The same fields as a `date` stamp, with the year moved to the end
+    let (year, rest) = stamp.split_at(stamp.len() - 8);
+    parse_posix_stamp(&format!("{rest}{year}{seconds}"), now).map_err(|_| invalid(s))
+}
+
+/// A calendar date, `YYYY-MM-DD`
+fn parse_date(s: &str) -> Option<Date> {
+    let mut fields = s.splitn(3, '-');
+    let year = number(fields.next()?)?;
+    let month = number(fields.next()?)?;
+    let day = number(fields.next()?)?;
+    Date::new(year as i16, month as i8, day as i8).ok()
+}
+
+/// A time of day, `hh:mm[:ss[.fraction]]`, with an optional UTC offset
+/// like `Z`, `+02:00` or `-0500`
+fn parse_time(s: &str) -> Option<(Time, Option<Offset>)> {
+    let (time, offset) = match s.find(['Z', 'z', '+', '-']) {
+        Some(i) => (&s[..i], Some(parse_offset(&s[i..])?)),
+        None => (s, None),
+    };
+    let mut fields = time.splitn(3, ':');
+    let hour = number(fields.next()?)?;
+    let minute = number(fields.next()?)?;
+    let (second, nanos) = match fields.next() {
+        Some(seconds) => match seconds.split_once(['.', ',']) {
+            Some((second, fraction)) if !fraction.is_empty() && fraction.len() <= 9 => {
+                let nanos = number(fraction)? * 10_i64.pow(9 - fraction.len() as u32);
+                (number(second)?, nanos)
+            }
+            Some(_) => return None,
+            None => (number(seconds)?, 0),
+        },
+        None => (0, 0),
+    };
+    let time = Time::new(hour as i8, minute as i8, second as i8, nanos as i32).ok()?;
+    Some((time, offset))
+}
+
+/// A UTC offset, `Z`, `UTC` or `±hh[:mm]`
+fn parse_offset(s: &str) -> Option<Offset> {
+    if matches!(s, "Z" | "z" | "UTC" | "utc" | "GMT" | "gmt") {
+        return Some(Offset::UTC);
+    }
+    let (sign, rest) = match s.as_bytes().first()? {
+        b'+' => (1, &s[1..]),
+        b'-' => (-1, &s[1..]),
+        _ => return None,
+    };
+    let digits = rest.replace(':', "");
+    if !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let (hours, minutes) = match digits.len() {
+        2 => (number(&digits)?, 0),
+        4 => (number(&digits[..2])?, number(&digits[2..])?),
+        _ => return None,
+    };
+    Offset::from_seconds(sign * (hours * 3600 + minutes * 60) as i32).ok()
+}
+
+/// Span of a number of the unit of a relative item
+fn parse_unit(s: &str) -> Option<fn(i64) -> Result<Span, jiff::Error>> {
+    let unit = s
+        .strip_suffix('s')
+        .filter(|unit| unit.len() > 1)
+        .unwrap_or(s);
+    Some(match unit.to_ascii_lowercase().as_str() {
+        "year" => |n| Span::new().try_years(n),
+        "month" => |n| Span::new().try_months(n),
+        "fortnight" => |n| Span::new().try_weeks(n.saturating_mul(2)),
+        "week" => |n| Span::new().try_weeks(n),
+        "day" => |n| Span::new().try_days(n),
+        "hour" => |n| Span::new().try_hours(n),
+        "minute" | "min" => |n| Span::new().try_minutes(n),
+        "second" | "sec" => |n| Span::new().try_seconds(n),
+        _ => return None,
+    })
+}
+
+/// Apply the relative items of `words`, like `+3 days`, `2 weeks ago`,
+/// `next month`, `yesterday`, to `time`. A day past the end of a shorter
+/// month is clamped to its last day.
+fn apply_relative(mut time: Zoned, words: &[&str], s: &str) -> UResult<Zoned> {
+    let mut i = 0;
+    while i < words.len() {
+        let word = words[i].to_ascii_lowercase();
+        let (count, unit) = match word.as_str() {
+            "now" | "today" => {
+                i += 1;
+                continue;
+            }
+            "yesterday" => (-1, "day"),
+            "tomorrow" => (1, "day"),
+            "next" | "last" => {
+                i += 1;
+                let count = if word == "next" { 1 } else { -1 };
+                (count, *words.get(i).ok_or_else(|| invalid(s))?)
+            }
+            _ => {
+                // The count, which may be attached to the unit like `3days`
+                let digits_end = word
+                    .char_indices()
+                    .find(|&(pos, c)| !(c.is_ascii_digit() || (pos == 0 && matches!(c, '+' | '-'))))
+                    .map_or(word.len(), |(pos, _)| pos);
+                let (count, unit) = words[i].split_at(digits_end);
+                let count = match count {
+                    "" => 1,
+                    "+" | "-" => return Err(invalid(s)),
+                    _ => count.parse::<i64>().map_err(|_| invalid(s))?,
+                };
+                if unit.is_empty() {
+                    i += 1;
+                    (count, *words.get(i).ok_or_else(|| invalid(s))?)
+                } else {
+                    (count, unit)
+                }
+            }
+        };
+        let span = parse_unit(unit).ok_or_else(|| invalid(s))?;
+        i += 1;
+        let count = if words
+            .get(i)
+            .is_some_and(|word| word.eq_ignore_ascii_case("ago"))
+        {
+            i += 1;
+            -count
+        } else {
+            count
+        };
+        time = span(count)
+            .and_then(|span| time.checked_add(span))
+            .map_err(|_| invalid(s))?;
+    }
+    Ok(time)
+}
+
+/// A date string of `date -d` and `touch -d`: `@SECONDS` since the epoch,
+/// or an optional ISO 8601 date and time like `2024-03-01`,
+/// `2024-03-01T12:00:00Z` or `2024-03-01 12:00 +02:00` followed by
+/// relative items like `+2 hours`, `3 days ago`, `next week` or
+/// `tomorrow`. Missing parts are taken from `now`.
+pub fn parse(s: &str, now: &Zoned) -> UResult<Zoned> {
+    let trimmed = s.trim();
+    if let Some(seconds) = trimmed.strip_prefix('@') {
+        let (whole, nanos) = match seconds.split_once(['.', ',']) {
+            Some((whole, fraction)) if !fraction.is_empty() && fraction.len() <= 9 => {
+                let nanos = number(fraction).ok_or_else(|| invalid(s))?
+                    * 10_i64.pow(9 - fraction.len() as u32);
+                (whole, nanos)
+            }
+            Some(_) => return Err(invalid(s)),
+            None => (seconds, 0),
+        };
+        let whole: i64 = whole.parse().map_err(|_| invalid(s))?;
+        let nanos = if whole < 0 { -nanos } else { nanos };
+        return Timestamp::new(whole, nanos as i32)
+            .map(|time| time.to_zoned(now.time_zone().clone()))
+            .map_err(|_| invalid(s));
+    }
+    let words: Vec<&str> = trimmed.split_whitespace().collect();
+    let mut rest = &words[..];
+    let (mut date, mut time, mut offset) = (None, None, None);
+    if let Some((first, tail)) = rest.split_first() {
+        let (date_part, time_part) = match first.split_once(['T', 't']) {
+            Some((date_part, time_part)) if parse_date(date_part).is_some() => {
+                (date_part, Some(time_part))
+            }
+            _ => (*first, None),
+        };
+        if let Some(parsed) = parse_date(date_part) {
+            date = Some(parsed);
+            rest = tail;
+            match time_part {
+                Some(time_part) => {
+                    let (parsed, parsed_offset) =
+                        parse_time(time_part).ok_or_else(|| invalid(s))?;
+                    (time, offset) = (Some(parsed), parsed_offset);
+                }
+                None => {
+                    if let Some((parsed, parsed_offset)) = rest.first().and_then(|w| parse_time(w))
+                    {
+                        (time, offset) = (Some(parsed), parsed_offset);
+                        rest = &rest[1..];
+                    }
+                }
+            }
+        } else if let Some((parsed, parsed_offset)) = parse_time(first) {
+            (time, offset) = (Some(parsed), parsed_offset);
+            rest = tail;
+        }
+    }
+    if time.is_some() && offset.is_none() {
+        if let Some(parsed) = rest.first().and_then(|w| parse_offset(w)) {
+            offset = Some(parsed);
+            rest = &rest[1..];
+        }
+    }
+    let base = match (date, time) {
+        (None, None) => now.clone(),
+        (date, time) => {
+            let date = date.unwrap_or_else(|| now.date());
+            // A date alone is the start of its day
+            let datetime = date.to_datetime(time.unwrap_or(Time::midnight()));
+            match offset {
+                Some(offset) => offset
+                    .to_timestamp(datetime)
+                    .map(|time| time.to_zoned(now.time_zone().clone()))
+                    .map_err(|_| invalid(s))?,
+                None => local(datetime, now, s)?,
+            }
+        }
+    };
+    apply_relative(base, rest, s)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use jiff::tz::TimeZone;
+
+    /// 2023-11-14 17:13:20 in a US Eastern time zone
+    fn now() -> Zoned {
+        let tz = TimeZone::posix("EST5EDT,M3.2.0,M11.1.0").unwrap();
+        Timestamp::from_second(1_700_000_000).unwrap().to_zoned(tz)
+    }
+
+    /// The local date and time and the offset of `time`
+    fn local_time(time: &Zoned) -> String {
+        format!("{} {}", time.datetime(), time.offset())
+    }
+
+    fn date(s: &str) -> String {
+        local_time(&parse(s, &now()).unwrap())
+    }
+
+    fn posix_stamp(s: &str) -> String {
+        local_time(&parse_posix_stamp(s, &now()).unwrap())
+    }
+
+    fn touch_stamp(s: &str) -> String {
+        local_time(&parse_touch_stamp(s, &now()).unwrap())
+    }
+
+    #[test]
+    fn iso() {
+        assert_eq!(date("2024-03-01"), "2024-03-01T00:00:00 -05");
+        assert_eq!(date(" 2024-03-01 12:30 "), "2024-03-01T12:30:00 -05");
+        assert_eq!(date("2024-03-01T12:30:15.5"), "2024-03-01T12:30:15.5 -05");
+        assert_eq!(date("2024-07-01 12:00"), "2024-07-01T12:00:00 -04");
+        assert_eq!(date("12:30"), "2023-11-14T12:30:00 -05");
+        assert_eq!(date(""), "2023-11-14T17:13:20 -05");
+        for s in ["2024-02-30", "2024-03-01 25:00", "2024-03-01T", "noon"] {
+            assert!(parse(s, &now()).is_err(), "{s}");
+        }
+    }
+
+    #[test]
+    fn offsets() {
+        assert_eq!(date("2024-03-01T12:00:00Z"), "2024-03-01T07:00:00 -05");
+        assert_eq!(date("2024-03-01 12:00 +02:00"), "2024-03-01T05:00:00 -05");
+        assert_eq!(date("2024-03-01 12:00-0500"), "2024-03-01T12:00:00 -05");
+        assert_eq!(date("2024-03-01 12:00 UTC"), "2024-03-01T07:00:00 -05");
+        assert!(parse("2024-03-01 12:00 +1é2", &now()).is_err());
+        assert!(parse("2024-03-01 12:00 +123", &now()).is_err());
+    }
+
+    #[test]
+    fn relative() {
+        assert_eq!(date("2 days ago"), "2023-11-12T17:13:20 -05");
+        assert_eq!(date("+3 hours"), "2023-11-14T20:13:20 -05");
+        assert_eq!(date("3days"), "2023-11-17T17:13:20 -05");
+        assert_eq!(date("next week"), "2023-11-21T17:13:20 -05");
+        assert_eq!(date("last fortnight"), "2023-10-31T17:13:20 -04");
+        assert_eq!(date("yesterday"), "2023-11-13T17:13:20 -05");
+        assert_eq!(date("tomorrow 1 min ago"), "2023-11-15T17:12:20 -05");
+        assert_eq!(date("2024-01-31 +1 month"), "2024-02-29T00:00:00 -05");
+        for s in ["3 parsecs", "next", "+ 2 days", "2 days ago ago"] {
+            assert!(parse(s, &now()).is_err(), "{s}");
+        }
+    }
+
+    #[test]
+    fn epoch() {
+        let seconds = |s| parse(s, &now()).unwrap().timestamp();
+        assert_eq!(seconds("@0"), Timestamp::UNIX_EPOCH);
+        assert_eq!(seconds("@1700000000").as_second(), 1_700_000_000);
+        assert_eq!(seconds("@1.5").as_millisecond(), 1500);
+        assert_eq!(seconds("@-1.5").as_millisecond(), -1500);
+        for s in ["@", "@1.", "@x", "@1.1234567890"] {
+            assert!(parse(s, &now()).is_err(), "{s}");
+        }
+    }
+
+    #[test]
+    fn posix_stamps() {
+        assert_eq!(posix_stamp("03011230"), "2023-03-01T12:30:00 -05");
+        assert_eq!(posix_stamp("0301123024"), "2024-03-01T12:30:00 -05");
+        assert_eq!(posix_stamp("030112302024.45"), "2024-03-01T12:30:45 -05");
+        assert_eq!(posix_stamp("03011230.07"), "2023-03-01T12:30:07 -05");
+        for s in ["0301123", "030112301", "13011230", "03011230.5", "0301-230"] {
+            assert!(parse_posix_stamp(s, &now()).is_err(), "{s}");
+        }
+    }
+
+    #[test]
+    fn touch_stamps() {
+        assert_eq!(touch_stamp("03011230"), "2023-03-01T12:30:00 -05");
+        assert_eq!(touch_stamp("2403011230"), "2024-03-01T12:30:00 -05");
+        assert_eq!(touch_stamp("202403011230.45"), "2024-03-01T12:30:45 -05");
+        for s in ["2403011230.", "240301123", "24030112300"] {
+            assert!(parse_touch_stamp(s, &now()).is_err(), "{s}");
+        }
+    }
+
+    #[test]
+    fn two_digit_years() {
+        assert_eq!(posix_stamp("0301123068"), "2068-03-01T12:30:00 -05");
+        assert_eq!(posix_stamp("0301123069"), "1969-03-01T12:30:00 -05");
+        assert_eq!(touch_stamp("0003011230"), "2000-03-01T12:30:00 -05");
+        assert_eq!(touch_stamp("9903011230"), "1999-03-01T12:30:00 -05");
+    }
+
+    #[test]
+    fn dst_changes() {
+        // 02:30 is skipped in the spring and 01:30 happens twice in the fall
+        assert_eq!(date("2024-03-10 02:30"), "2024-03-10T03:30:00 -04");
+        assert_eq!(posix_stamp("0310023024"), "2024-03-10T03:30:00 -04");
+        assert_eq!(date("2024-11-03 01:30"), "2024-11-03T01:30:00 -04");
+        assert_eq!(touch_stamp("2411030130"), "2024-11-03T01:30:00 -04");
+    }
+
+    #[test]
+    fn non_ascii_stamps() {
+        for s in [
+            "0101000é0",
+            "é1010000",
+            "01010000é",
+            "0101é000.00",
+            "01010000.é",
+        ] {
+            assert!(parse_posix_stamp(s, &now()).is_err(), "{s}");
+            assert!(parse_touch_stamp(s, &now()).is_err(), "{s}");
+        }
+    }
+}
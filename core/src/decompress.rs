@@ -0,0 +1,165 @@
+//! Transparent decoding of compressed input, for `wc --decompress`,
+//! `zcat` and the like. Formats are detected by the magic bytes their
+//! streams start with, or by the extension of the file name. Each format
+//! is decoded when the feature of its name is enabled, gzip, xz and zstd
+//! by default.
+
+use std::fmt;
+use std::io::{self, Read};
+use std::path::Path;
+
+/// A compressed format
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    Gzip,
+    Xz,
+    Zstd,
+    Bzip2,
+}
+
+/// Compressed formats and the magic bytes their streams start with
+const MAGIC: [(Format, &[u8]); 4] = [
+    (Format::Gzip, &[0x1f, 0x8b]),
+    (Format::Xz, &[0xfd, b'7', b'z', b'X', b'Z', 0x00]),
+    (Format::Zstd, &[0x28, 0xb5, 0x2f, 0xfd]),
+    (Format::Bzip2, b"BZh"),
+];
+
+/// Length of the longest magic byte sequence
+const MAGIC_LEN: usize = 6;
+
+/// File name extensions of the formats, including the ones of tarballs
+const EXTENSIONS: [(Format, &[&str]); 4] = [
+    (Format::Gzip, &["gz", "tgz", "z"]),
+    (Format::Xz, &["xz", "txz"]),
+    (Format::Zstd, &["zst", "tzst"]),
+    (Format::Bzip2, &["bz2", "tbz", "tbz2"]),
+];
+
+impl Format {
+    pub fn name(self) -> &'static str {
+        match self {
+            Format::Gzip => "gzip",
+            Format::Xz => "xz",
+            Format::Zstd => "zstd",
+            Format::Bzip2 => "bzip2",
+        }
+    }
+
+    /// Format of a stream starting with `magic`
+    pub fn from_magic(magic: &[u8]) -> Option<Format> {
+        MAGIC
+            .iter()
+            .find(|(_, prefix)| magic.starts_with(prefix))
+            .map(|&(format, _)| format)
+    }
+
+    /// Format of the file `path` by the extension of its name
+    pub fn from_extension(path: &Path) -> Option<Format> {
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+        EXTENSIONS
+            .iter()
+            .find(|(_, extensions)| extensions.contains(&extension.as_str()))
+            .map(|&(format, _)| format)
+    }
+
+    /// Whether this build decodes the format
+    pub fn is_supported(self) -> bool {
+        match self {
+            Format::Gzip => cfg!(feature = "gzip"),
+            Format::Xz => cfg!(feature = "xz"),
+            Format::Zstd => cfg!(feature = "zstd"),
+            Format::Bzip2 => cfg!(feature = "bzip2"),
+        }
+    }
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Reader of the decompressed contents of `reader` when it starts with
+/// the magic bytes of a known format, of `reader` itself otherwise
+pub fn decoder<'a, R: Read + 'a>(mut reader: R) -> io::Result<Box<dyn Read + 'a>> {
+    let mut magic = Vec::with_capacity(MAGIC_LEN);
+    (&mut reader)
+        .take(MAGIC_LEN as u64)
+        .read_to_end(&mut magic)?;
+    let format = Format::from_magic(&magic);
+    let stream = io::Cursor::new(magic).chain(reader);
+    match format {
+        Some(format) => {
+            crate::debug!("decompressing {format}");
+            decoder_of(format, stream)
+        }
+        None => {
+            crate::debug!("not compressed");
+            Ok(Box::new(stream))
+        }
+    }
+}
+
+/// Reader of the decompressed contents of `reader`, compressed in
+/// `format`. Concatenated streams, as written by `cat a.gz b.gz`, are
+/// decoded as one. Decoding errors name the format.
+pub fn decoder_of<'a, R: Read + 'a>(format: Format, reader: R) -> io::Result<Box<dyn Read + 'a>> {
+    match format {
+        #[cfg(feature = "gzip")]
+        Format::Gzip => Ok(named(format, flate2::read::MultiGzDecoder::new(reader))),
+        #[cfg(feature = "xz")]
+        Format::Xz => Ok(named(
+            format,
+            xz2::read::XzDecoder::new_multi_decoder(reader),
+        )),
+        #[cfg(feature = "zstd")]
+        Format::Zstd => Ok(named(format, zstd::stream::read::Decoder::new(reader)?)),
+        #[cfg(feature = "bzip2")]
+        Format::Bzip2 => Ok(named(format, bzip2::read::MultiBzDecoder::new(reader))),
+        #[allow(unreachable_patterns)]
+        _ => {
+            drop(reader);
+            Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                format!("{format} compressed input is not supported by this build"),
+            ))
+        }
+    }
+}
+
+/// `decoder` with errors naming `format`
+// Unused in builds without any format
+#[allow(dead_code)]
+fn named<'a>(format: Format, decoder: impl Read + 'a) -> Box<dyn Read + 'a> {
+    Box::new(Decoder {
+        format,
+        decoder: Box::new(decoder),
+    })
+}
+
+/// A decoder whose errors name the format
+struct Decoder<'a> {
+    format: Format,
+    decoder: Box<dyn Read + 'a>,
+}
+
+impl Read for Decoder<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.decoder.read(buf).map_err(|e| match e.kind() {
+            // Errors of the underlying reader are not about the format
+            io::ErrorKind::InvalidData
+            | io::ErrorKind::InvalidInput
+            | io::ErrorKind::UnexpectedEof
+            | io::ErrorKind::Other
+                if e.raw_os_error().is_none() =>
+            {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("invalid {} data: {e}", self.format),
+                )
+            }
+            _ => e,
+        })
+    }
+}
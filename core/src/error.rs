@@ -0,0 +1,127 @@
+//! Errors of the utilities with the exit codes of coreutils: 1 for
+//! operational failures and 2 for usage errors. Diagnostics take the
+//! `util: name: message` form, with the message translated into the
+//! language of the environment.
+
+use crate::i18n::tr;
+use std::fmt;
+use std::io;
+use std::process::ExitCode;
+
+/// Exit code of an operational failure, like an unreadable operand
+pub const EXIT_FAILURE: u8 = 1;
+
+/// Exit code of invalid usage
+pub const EXIT_USAGE: u8 = 2;
+
+/// A failure with the exit code it ends the utility with
+#[derive(Debug)]
+pub struct UError {
+    code: u8,
+    /// The file or operation that failed
+    name: Option<String>,
+    message: String,
+}
+
+pub type UResult<T> = Result<T, UError>;
+
+impl UError {
+    pub fn new(code: u8, message: impl fmt::Display) -> Self {
+        UError {
+            code,
+            name: None,
+            message: message.to_string(),
+        }
+    }
+
+    /// Operational failure
+    pub fn failure(message: impl fmt::Display) -> Self {
+        UError::new(EXIT_FAILURE, message)
+    }
+
+    /// Invalid usage, like conflicting options
+    pub fn usage(message: impl fmt::Display) -> Self {
+        UError::new(EXIT_USAGE, message)
+    }
+
+    /// Attribute the error to `name`, a file or what failed
+    pub fn with_name(mut self, name: impl fmt::Display) -> Self {
+        self.name = Some(name.to_string());
+        self
+    }
+
+    /// The same error ending the utility with `code`, for utilities with
+    /// exit codes of their own
+    pub fn with_code(mut self, code: u8) -> Self {
+        self.code = code;
+        self
+    }
+
+    pub fn code(&self) -> u8 {
+        self.code
+    }
+}
+
+impl fmt::Display for UError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.name {
+            Some(name) => write!(f, "{name}: {}", tr(&self.message)),
+            None => f.write_str(tr(&self.message)),
+        }
+    }
+}
+
+impl std::error::Error for UError {}
+
+impl From<io::Error> for UError {
+    fn from(err: io::Error) -> Self {
+        UError::failure(describe(&err))
+    }
+}
+
+/// Message for an I/O error without the ` (os error N)` suffix of its
+/// `Display`, like the C library formats it
+pub fn describe(err: &io::Error) -> String {
+    let message = err.to_string();
+    match err.raw_os_error() {
+        Some(code) => match message.strip_suffix(&format!(" (os error {code})")) {
+            Some(message) => message.to_string(),
+            None => message,
+        },
+        None => message,
+    }
+}
+
+/// Print `message` as a diagnostic of `util` that doesn't fail it, like a
+/// warning or a notice of a followed file changing
+pub fn notice(util: &str, message: impl fmt::Display) {
+    eprintln!("{util}: {message}");
+}
+
+/// Outcome of a utility over all of its operands, the highest exit code
+/// of the errors reported so far
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Status(u8);
+
+impl Status {
+    /// Print `err` as a diagnostic of `util` and account for its exit code
+    pub fn report(&mut self, util: &str, err: &UError) {
+        eprintln!("{util}: {err}");
+        self.0 = self.0.max(err.code);
+    }
+
+    /// Report the error of `result`, if any, and return its value
+    pub fn check<T>(&mut self, util: &str, result: UResult<T>) -> Option<T> {
+        result.map_err(|err| self.report(util, &err)).ok()
+    }
+
+    pub fn is_ok(self) -> bool {
+        self.0 == 0
+    }
+}
+
+impl From<Status> for ExitCode {
+    fn from(status: Status) -> Self {
+        ExitCode::from(status.0)
+    }
+}
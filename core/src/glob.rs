@@ -0,0 +1,530 @@
+//! Shell-style wildcard matching like fnmatch(3), for name patterns,
+//! excludes and file operands on Windows, where the shell doesn't expand
+//! them. Patterns and names are raw bytes, so names that aren't UTF-8
+//! match too: their invalid bytes are matched one at a time.
+
+use crate::locale::Locale;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// How a pattern matches
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Options {
+    /// Match whole paths: wildcards don't match `/`, only `**` does
+    pub is_path: bool,
+    /// A leading `.` of a name, or of a component of a path, is only
+    /// matched by a `.` in the pattern
+    pub is_period_explicit: bool,
+    pub ignore_case: bool,
+}
+
+/// Character of a name, or a byte that isn't part of valid UTF-8
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Unit {
+    Char(char),
+    Byte(u8),
+}
+
+fn units(s: &[u8]) -> Vec<Unit> {
+    let mut units = Vec::with_capacity(s.len());
+    for chunk in s.utf8_chunks() {
+        units.extend(chunk.valid().chars().map(Unit::Char));
+        units.extend(chunk.invalid().iter().map(|&b| Unit::Byte(b)));
+    }
+    units
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Class {
+    Alnum,
+    Alpha,
+    Blank,
+    Cntrl,
+    Digit,
+    Graph,
+    Lower,
+    Print,
+    Punct,
+    Space,
+    Upper,
+    Xdigit,
+}
+
+impl Class {
+    fn from_name(name: &[Unit]) -> Option<Self> {
+        let name: String = name
+            .iter()
+            .map(|unit| match unit {
+                Unit::Char(c) => Some(*c),
+                Unit::Byte(_) => None,
+            })
+            .collect::<Option<_>>()?;
+        Some(match name.as_str() {
+            "alnum" => Class::Alnum,
+            "alpha" => Class::Alpha,
+            "blank" => Class::Blank,
+            "cntrl" => Class::Cntrl,
+            "digit" => Class::Digit,
+            "graph" => Class::Graph,
+            "lower" => Class::Lower,
+            "print" => Class::Print,
+            "punct" => Class::Punct,
+            "space" => Class::Space,
+            "upper" => Class::Upper,
+            "xdigit" => Class::Xdigit,
+            _ => return None,
+        })
+    }
+
+    fn contains(self, locale: Locale, c: char) -> bool {
+        match self {
+            Class::Alnum => locale.is_alnum(c),
+            Class::Alpha => locale.is_alpha(c),
+            Class::Blank => locale.is_blank(c),
+            Class::Cntrl => locale.is_cntrl(c),
+            Class::Digit => locale.is_digit(c),
+            Class::Graph => locale.is_graph(c),
+            Class::Lower => locale.is_lower(c),
+            Class::Print => locale.is_print(c),
+            Class::Punct => locale.is_punct(c),
+            Class::Space => locale.is_space(c),
+            Class::Upper => locale.is_upper(c),
+            Class::Xdigit => c.is_ascii_hexdigit(),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+enum Item {
+    Unit(Unit),
+    Range(char, char),
+    Class(Class),
+}
+
+#[derive(Clone, Debug)]
+enum Token {
+    Unit(Unit),
+    /// `?`
+    Any,
+    /// `*`
+    Star,
+    /// `**` in path patterns
+    GlobStar,
+    /// `[...]`
+    Set {
+        is_negated: bool,
+        items: Vec<Item>,
+    },
+}
+
+/// A compiled pattern, where `*` matches any sequence of characters, `?` a
+/// single character, `[...]` a set of characters with ranges like `a-z`
+/// and classes like `[:alpha:]`, negated by a leading `!` or `^`, and `\`
+/// escapes the character that follows it
+#[derive(Clone, Debug)]
+pub struct Pattern {
+    tokens: Vec<Token>,
+    options: Options,
+    locale: Locale,
+}
+
+/// The set that starts after an opening `[` and the number of units it
+/// takes up including the closing `]`, `None` if it isn't terminated
+fn parse_set(pattern: &[Unit]) -> Option<(Token, usize)> {
+    let is_negated = matches!(pattern.first(), Some(Unit::Char('!' | '^')));
+    let mut i = usize::from(is_negated);
+    let mut items = Vec::new();
+    let mut is_first = true;
+    while i < pattern.len() {
+        let item = match pattern[i] {
+            Unit::Char(']') if !is_first => {
+                return Some((Token::Set { is_negated, items }, i + 1));
+            }
+            Unit::Char('[') if pattern.get(i + 1) == Some(&Unit::Char(':')) => {
+                let rest = &pattern[i + 2..];
+                let end = rest
+                    .windows(2)
+                    .position(|w| w == [Unit::Char(':'), Unit::Char(']')]);
+                match end.and_then(|end| Some((end, Class::from_name(&rest[..end])?))) {
+                    Some((end, class)) => {
+                        i += end + 4;
+                        Item::Class(class)
+                    }
+                    None => {
+                        i += 1;
+                        Item::Unit(Unit::Char('['))
+                    }
+                }
+            }
+            Unit::Char(lo) => match (pattern.get(i + 1), pattern.get(i + 2)) {
+                (Some(Unit::Char('-')), Some(&Unit::Char(hi))) if hi != ']' => {
+                    i += 3;
+                    Item::Range(lo, hi)
+                }
+                _ => {
+                    i += 1;
+                    Item::Unit(Unit::Char(lo))
+                }
+            },
+            unit => {
+                i += 1;
+                Item::Unit(unit)
+            }
+        };
+        items.push(item);
+        is_first = false;
+    }
+    None
+}
+
+impl Pattern {
+    pub fn new(pattern: &[u8], options: Options) -> Self {
+        let pattern = units(pattern);
+        let mut tokens = Vec::new();
+        let mut i = 0;
+        while i < pattern.len() {
+            let (token, len) = match pattern[i] {
+                Unit::Char('*')
+                    if options.is_path && pattern.get(i + 1) == Some(&Unit::Char('*')) =>
+                {
+                    (Token::GlobStar, 2)
+                }
+                Unit::Char('*') => (Token::Star, 1),
+                Unit::Char('?') => (Token::Any, 1),
+                Unit::Char('[') => match parse_set(&pattern[i + 1..]) {
+                    Some((set, len)) => (set, len + 1),
+                    // An unterminated set is matched as a literal `[`
+                    None => (Token::Unit(Unit::Char('[')), 1),
+                },
+                Unit::Char('\\') if i + 1 < pattern.len() => (Token::Unit(pattern[i + 1]), 2),
+                unit => (Token::Unit(unit), 1),
+            };
+            tokens.push(token);
+            i += len;
+        }
+        Pattern {
+            tokens,
+            options,
+            locale: Locale::ctype(),
+        }
+    }
+
+    /// Whether the whole of `name` matches
+    pub fn matches(&self, name: &[u8]) -> bool {
+        let name = units(name);
+        // States already known not to match, so backtracking over several
+        // stars doesn't take exponential time
+        let mut failed = vec![false; (self.tokens.len() + 1) * (name.len() + 1)];
+        self.match_at(0, &name, 0, &mut failed)
+    }
+
+    fn fold(&self, c: char) -> char {
+        if self.options.ignore_case {
+            self.locale.to_lower(c)
+        } else {
+            c
+        }
+    }
+
+    fn unit_eq(&self, a: Unit, b: Unit) -> bool {
+        match (a, b) {
+            (Unit::Char(a), Unit::Char(b)) => self.fold(a) == self.fold(b),
+            _ => a == b,
+        }
+    }
+
+    fn set_contains(&self, items: &[Item], unit: Unit) -> bool {
+        items.iter().any(|item| match (item, unit) {
+            (Item::Unit(u), unit) => self.unit_eq(*u, unit),
+            (Item::Range(lo, hi), Unit::Char(c)) => {
+                (lo..=hi).contains(&&c)
+                    || (self.options.ignore_case
+                        && [self.locale.to_lower(c), self.locale.to_upper(c)]
+                            .iter()
+                            .any(|c| (lo..=hi).contains(&c)))
+            }
+            (Item::Class(class), Unit::Char(c)) => class.contains(self.locale, c),
+            (_, Unit::Byte(_)) => false,
+        })
+    }
+
+    /// Whether the unit at `n` of `name` is a `.` that only a literal `.`
+    /// matches
+    fn is_hidden_period(&self, name: &[Unit], n: usize) -> bool {
+        self.options.is_period_explicit
+            && name[n] == Unit::Char('.')
+            && (n == 0 || (self.options.is_path && name[n - 1] == Unit::Char('/')))
+    }
+
+    fn is_separator(&self, unit: Unit) -> bool {
+        self.options.is_path && unit == Unit::Char('/')
+    }
+
+    fn match_at(&self, p: usize, name: &[Unit], n: usize, failed: &mut [bool]) -> bool {
+        let state = p * (name.len() + 1) + n;
+        if failed[state] {
+            return false;
+        }
+        let is_match = match self.tokens.get(p) {
+            None => n == name.len(),
+            Some(Token::Star) if n < name.len() && self.is_hidden_period(name, n) => false,
+            Some(Token::Star) => (0..=name.len() - n)
+                .take_while(|&len| len == 0 || !self.is_separator(name[n + len - 1]))
+                .any(|len| self.match_at(p + 1, name, n + len, failed)),
+            Some(Token::GlobStar) => {
+                // `**/` also matches no directory at all
+                let skips_slash =
+                    matches!(self.tokens.get(p + 1), Some(Token::Unit(Unit::Char('/'))))
+                        && self.match_at(p + 2, name, n, failed);
+                skips_slash
+                    || (0..=name.len() - n)
+                        .take_while(|&len| len == 0 || !self.is_hidden_period(name, n + len - 1))
+                        .any(|len| self.match_at(p + 1, name, n + len, failed))
+            }
+            Some(token) => {
+                let Some(&unit) = name.get(n) else {
+                    failed[state] = true;
+                    return false;
+                };
+                let is_unit_match = match token {
+                    Token::Unit(u) => self.unit_eq(*u, unit),
+                    Token::Any => !self.is_separator(unit) && !self.is_hidden_period(name, n),
+                    Token::Set { is_negated, items } => {
+                        !self.is_separator(unit)
+                            && !self.is_hidden_period(name, n)
+                            && self.set_contains(items, unit) != *is_negated
+                    }
+                    Token::Star | Token::GlobStar => unreachable!(),
+                };
+                is_unit_match && self.match_at(p + 1, name, n + 1, failed)
+            }
+        };
+        if !is_match {
+            failed[state] = true;
+        }
+        is_match
+    }
+}
+
+/// Match the name `name` against `pattern`
+pub fn matches(pattern: &[u8], name: &[u8]) -> bool {
+    Pattern::new(pattern, Options::default()).matches(name)
+}
+
+/// Whether `pattern` contains any wildcard
+pub fn has_wildcards(pattern: &[u8]) -> bool {
+    pattern.iter().any(|b| matches!(b, b'*' | b'?' | b'['))
+}
+
+/// Paths matching `pattern` in name order, where every component of the
+/// pattern may contain wildcards. Like in the shell, wildcards don't match
+/// a leading `.` of a name.
+pub fn expand(pattern: &Path) -> Vec<PathBuf> {
+    let mut paths = vec![PathBuf::new()];
+    for component in pattern.components() {
+        let name = component.as_os_str();
+        if has_wildcards(name.as_encoded_bytes()) {
+            let options = Options {
+                is_period_explicit: true,
+                ..Options::default()
+            };
+            let name_pattern = Pattern::new(name.as_encoded_bytes(), options);
+            paths = paths
+                .iter()
+                .flat_map(|dir| matching_entries(dir, &name_pattern))
+                .collect();
+        } else {
+            paths.iter_mut().for_each(|path| path.push(name));
+        }
+    }
+    paths
+}
+
+/// Entries of `dir`, the current directory when empty, whose name matches
+/// `pattern`
+fn matching_entries(dir: &Path, pattern: &Pattern) -> Vec<PathBuf> {
+    let read_dir = if dir.as_os_str().is_empty() {
+        fs::read_dir(".")
+    } else {
+        fs::read_dir(dir)
+    };
+    let Ok(entries) = read_dir else {
+        return Vec::new();
+    };
+    let mut names: Vec<_> = entries
+        .filter_map(Result::ok)
+        .map(|entry| entry.file_name())
+        .filter(|name| pattern.matches(name.as_encoded_bytes()))
+        .collect();
+    names.sort();
+    names.into_iter().map(|name| dir.join(name)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn path(pattern: &str, name: &str) -> bool {
+        let options = Options {
+            is_path: true,
+            ..Options::default()
+        };
+        Pattern::new(pattern.as_bytes(), options).matches(name.as_bytes())
+    }
+
+    fn period(pattern: &str, name: &str, is_path: bool) -> bool {
+        let options = Options {
+            is_path,
+            is_period_explicit: true,
+            ..Options::default()
+        };
+        Pattern::new(pattern.as_bytes(), options).matches(name.as_bytes())
+    }
+
+    fn ignore_case(pattern: &str, name: &str) -> bool {
+        let options = Options {
+            ignore_case: true,
+            ..Options::default()
+        };
+        Pattern::new(pattern.as_bytes(), options).matches(name.as_bytes())
+    }
+
+    #[test]
+    fn wildcards() {
+        assert!(matches(b"*.rs", b"lib.rs"));
+        assert!(matches(b"*.rs", b".rs"));
+        assert!(!matches(b"*.rs", b"lib.rsx"));
+        assert!(matches(b"a?c", b"abc"));
+        assert!(!matches(b"a?c", b"ac"));
+        assert!(matches(b"a*", b"a/b"));
+        assert!(matches(b"", b""));
+        assert!(!matches(b"", b"a"));
+    }
+
+    #[test]
+    fn sets() {
+        assert!(matches(b"[abc]", b"b"));
+        assert!(!matches(b"[abc]", b"d"));
+        assert!(matches(b"[a-c]x", b"bx"));
+        assert!(matches(b"[!a-c]", b"d"));
+        assert!(!matches(b"[!a-c]", b"b"));
+        assert!(matches(b"[^a-c]", b"d"));
+        assert!(!matches(b"[^a-c]", b"a"));
+        // A `-` at the end is a literal
+        assert!(matches(b"[a-]", b"-"));
+    }
+
+    #[test]
+    fn bracket_first_in_set() {
+        assert!(matches(b"[]]", b"]"));
+        assert!(matches(b"[]a]", b"a"));
+        assert!(matches(b"[!]]", b"a"));
+        assert!(!matches(b"[!]]", b"]"));
+        assert!(matches(b"[]-a]", b"^"));
+    }
+
+    #[test]
+    fn classes() {
+        assert!(matches(b"[[:digit:]]*", b"1st"));
+        assert!(!matches(b"[[:digit:]]*", b"first"));
+        assert!(matches(b"[[:upper:][:digit:]]", b"Q"));
+        assert!(matches(b"[![:space:]]", b"x"));
+        assert!(matches(b"[[:xdigit:]]", b"F"));
+        // An unknown class is a set of the characters of its name
+        assert!(matches(b"[[:bogus:]]", b"b]"));
+        assert!(matches(b"[[:bogus:]]", b"[]"));
+    }
+
+    #[test]
+    fn unterminated_set() {
+        assert!(matches(b"[ab", b"[ab"));
+        assert!(!matches(b"[ab", b"a"));
+        assert!(matches(b"a[", b"a["));
+        assert!(matches(b"[!", b"[!"));
+        assert!(matches(b"*[", b"x["));
+    }
+
+    #[test]
+    fn escapes() {
+        assert!(matches(br"\*", b"*"));
+        assert!(!matches(br"\*", b"a"));
+        assert!(matches(br"a\?", b"a?"));
+        assert!(matches(br"\[a]", b"[a]"));
+        assert!(matches(br"\\", br"\"));
+        // A trailing backslash is a literal
+        assert!(matches(br"a\", br"a\"));
+    }
+
+    #[test]
+    fn paths() {
+        assert!(path("src/*.rs", "src/lib.rs"));
+        assert!(!path("*.rs", "src/lib.rs"));
+        assert!(!path("src?lib", "src/lib"));
+        assert!(!path("src[/]lib", "src/lib"));
+        assert!(path("**/*.rs", "src/a/b/lib.rs"));
+        assert!(path("src/**", "src/a/b"));
+    }
+
+    #[test]
+    fn glob_star_matches_no_directory() {
+        assert!(path("**/*.rs", "lib.rs"));
+        assert!(path("src/**/lib.rs", "src/lib.rs"));
+        assert!(path("src/**/lib.rs", "src/a/b/lib.rs"));
+        assert!(!path("src/**/lib.rs", "srclib.rs"));
+        // `**` is two stars in names
+        assert!(matches(b"**.rs", b"a/b.rs"));
+    }
+
+    #[test]
+    fn explicit_periods() {
+        assert!(!period("*", ".hidden", false));
+        assert!(!period("?hidden", ".hidden", false));
+        assert!(!period("[.]hidden", ".hidden", false));
+        assert!(period(".*", ".hidden", false));
+        assert!(period("*.rs", "lib.rs", false));
+        assert!(period("*", "a/.b", false));
+        assert!(!period("a/*", "a/.b", true));
+        assert!(period("a/.*", "a/.b", true));
+        assert!(!period("**/b", "a/.c/b", true));
+    }
+
+    #[test]
+    fn case_folding() {
+        assert!(ignore_case("*.RS", "lib.rs"));
+        assert!(ignore_case("[A-C]x", "bX"));
+        assert!(ignore_case("[a-c]", "B"));
+        assert!(!ignore_case("[a-c]", "d"));
+        assert!(ignore_case("[!A-C]", "d"));
+        assert!(!ignore_case("[!A-C]", "b"));
+        assert!(!matches(b"[A-C]", b"b"));
+    }
+
+    #[test]
+    fn invalid_utf8() {
+        assert!(matches(b"*", b"\xff\xfe"));
+        assert!(matches(b"?", b"\xff"));
+        assert!(!matches(b"?", b"\xff\xfe"));
+        assert!(matches(b"a\xffb", b"a\xffb"));
+        assert!(matches(b"a*\xff", b"abc\xff"));
+        assert!(!matches(b"[a-z]", b"\xff"));
+        assert!(matches(b"[!a-z]", b"\xff"));
+        assert!(matches(b"[\xff]", b"\xff"));
+    }
+
+    #[test]
+    fn many_stars_are_not_exponential() {
+        let pattern = "a*".repeat(30) + "b";
+        let name = "a".repeat(100);
+        assert!(!matches(pattern.as_bytes(), name.as_bytes()));
+        assert!(matches(pattern.as_bytes(), (name + "b").as_bytes()));
+        assert!(!path(&("**/".repeat(20) + "x"), &"a/".repeat(50)));
+    }
+
+    #[test]
+    fn wildcard_detection() {
+        assert!(has_wildcards(b"*.rs"));
+        assert!(has_wildcards(b"a[bc]"));
+        assert!(has_wildcards(b"a?"));
+        assert!(!has_wildcards(b"lib.rs"));
+    }
+}
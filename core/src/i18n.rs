@@ -0,0 +1,149 @@
+//! Translation of the messages of the utilities: help texts and
+//! diagnostics. The catalogs are embedded at compile time, one per
+//! language, and the language is selected like gettext(3) does, by
+//! `LANGUAGE` and the locale of `LC_MESSAGES`. Messages without a
+//! translation, and all of them in the C locale, stay in English.
+
+use crate::locale::{self, Category};
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+/// Catalogs in the PO format of gettext, by language
+const CATALOGS: &[(&str, &str)] = &[("ru", include_str!("../locales/ru.po"))];
+
+/// The string of a quoted PO line like `"text"`, unescaped
+fn po_string(line: &str) -> Option<String> {
+    let inner = line.strip_prefix('"')?.strip_suffix('"')?;
+    let mut s = String::with_capacity(inner.len());
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        s.push(match c {
+            '\\' => match chars.next()? {
+                'n' => '\n',
+                't' => '\t',
+                c @ ('"' | '\\') => c,
+                _ => return None,
+            },
+            c => c,
+        });
+    }
+    Some(s)
+}
+
+/// Translations of a PO catalog, the messages that have one. Strings may
+/// continue on the following lines, plural forms and contexts aren't
+/// supported.
+fn parse(catalog: &str) -> HashMap<String, String> {
+    let mut translations = HashMap::new();
+    let mut msgid = None;
+    let mut current: Option<(bool, String)> = None;
+    let mut finish = |msgid: &mut Option<String>, current: Option<(bool, String)>| match current {
+        Some((false, id)) => *msgid = Some(id),
+        Some((true, msgstr)) => {
+            if let Some(id) = msgid
+                .take()
+                .filter(|id| !id.is_empty() && !msgstr.is_empty())
+            {
+                translations.insert(id, msgstr);
+            }
+        }
+        None => (),
+    };
+    for line in catalog.lines().map(str::trim) {
+        if let Some(rest) = line.strip_prefix("msgid ") {
+            finish(&mut msgid, current.take());
+            current = po_string(rest).map(|id| (false, id));
+        } else if let Some(rest) = line.strip_prefix("msgstr ") {
+            finish(&mut msgid, current.take());
+            current = po_string(rest).map(|msgstr| (true, msgstr));
+        } else if let (Some(s), Some((_, text))) = (po_string(line), &mut current) {
+            text.push_str(&s);
+        }
+    }
+    finish(&mut msgid, current);
+    translations
+}
+
+/// Languages asked for, best first: the entries of `LANGUAGE`, then the
+/// locale of messages, each also without its territory. Nothing is
+/// translated in the C locale, whatever `LANGUAGE` says.
+fn languages() -> Vec<String> {
+    let locale = match locale::name(Category::Messages) {
+        None => return Vec::new(),
+        Some(name) if name == "C" || name == "POSIX" || name.starts_with("C.") => {
+            return Vec::new()
+        }
+        Some(name) => name,
+    };
+    let language = std::env::var("LANGUAGE").unwrap_or_default();
+    let mut languages = Vec::new();
+    for name in language.split(':').chain([locale.as_str()]) {
+        // `ll_CC.codeset@modifier`
+        let name = name.split(['.', '@']).next().unwrap_or_default();
+        if name.is_empty() {
+            continue;
+        }
+        languages.push(name.to_string());
+        if let Some((language, _)) = name.split_once('_') {
+            languages.push(language.to_string());
+        }
+    }
+    languages
+}
+
+/// Translations into the language of the environment
+fn translations() -> &'static HashMap<String, String> {
+    static TRANSLATIONS: OnceLock<HashMap<String, String>> = OnceLock::new();
+    TRANSLATIONS.get_or_init(|| {
+        languages()
+            .iter()
+            .find_map(|language| CATALOGS.iter().find(|(name, _)| name == language))
+            .map(|(_, catalog)| parse(catalog))
+            .unwrap_or_default()
+    })
+}
+
+/// The translation of `msgid` into the language of the environment
+fn translation(msgid: &str) -> Option<&'static str> {
+    translations().get(msgid).map(String::as_str)
+}
+
+/// `msgid` translated into the language of the environment, as is if it
+/// has no translation
+pub fn tr(msgid: &str) -> &str {
+    translation(msgid).unwrap_or(msgid)
+}
+
+/// `command` with its description, the help of its arguments and the
+/// headings of its help translated. The help lists the arguments, then
+/// the options, without the headings of their own they may have.
+pub fn command(mut command: clap::Command) -> clap::Command {
+    if translations().is_empty() {
+        return command;
+    }
+    // Build the command for the generated --help and --version arguments
+    command.build();
+    if let Some(about) = command
+        .get_about()
+        .and_then(|about| translation(&about.to_string()))
+    {
+        command = command.about(about);
+    }
+    let mut template = format!(
+        "{{before-help}}{{about-with-newline}}\n{} {{usage}}\n",
+        tr("Usage:")
+    );
+    if command.get_positionals().next().is_some() {
+        template += &format!("\n{}\n{{positionals}}\n", tr("Arguments:"));
+    }
+    template += &format!("\n{}\n{{options}}{{after-help}}", tr("Options:"));
+    command.help_template(template).mut_args(|arg| {
+        match arg
+            .get_help()
+            .and_then(|help| translation(&help.to_string()))
+        {
+            Some(help) => arg.help(help),
+            None => arg,
+        }
+    })
+}
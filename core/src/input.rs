@@ -0,0 +1,146 @@
+//! File operands, with `-` standing for the standard input.
+//!
+//! `/dev/stdin` and `/dev/fd/0` are the standard input as well, read
+//! through the same buffered handle as `-`, so operands naming it twice
+//! read it once and the second one finds it consumed. `/dev/fd/N` reads
+//! the open descriptor N from its current offset, like the file systems
+//! providing these names do, also where there are none.
+
+use crate::quote::quote;
+use std::borrow::Cow;
+use std::ffi::OsStr;
+use std::fs;
+use std::io::{self, Read};
+use std::path::Path;
+
+/// An input operand of a utility
+pub enum Input<'a> {
+    /// The standard input, with the name it is reported by
+    Stdin(Option<&'a str>),
+    /// An open file descriptor, named `/dev/fd/N`
+    Descriptor(i32, &'a Path),
+    Path(&'a Path),
+}
+
+impl<'a> Input<'a> {
+    /// Input named by a file operand, where `-` is the standard input
+    pub fn from_operand(operand: &'a Path) -> Self {
+        let Some(name) = operand.to_str() else {
+            return Input::Path(operand);
+        };
+        match name.strip_prefix("/dev/fd/").map(str::parse) {
+            _ if name == "-" || name == "/dev/stdin" => Input::Stdin(Some(name)),
+            Some(Ok(0)) => Input::Stdin(Some(name)),
+            Some(Ok(fd)) => Input::Descriptor(fd, operand),
+            _ => Input::Path(operand),
+        }
+    }
+
+    pub fn is_stdin(&self) -> bool {
+        matches!(self, Input::Stdin(_))
+    }
+
+    /// Descriptor the input is read from without opening a file, whose
+    /// offset operands reading it again share
+    pub fn descriptor(&self) -> Option<i32> {
+        match self {
+            Input::Stdin(_) => Some(0),
+            Input::Descriptor(fd, _) => Some(*fd),
+            Input::Path(_) => None,
+        }
+    }
+
+    /// Name of the input, for formats that escape it themselves
+    pub fn name(&self) -> Option<Cow<'_, str>> {
+        match self {
+            Input::Stdin(name) => name.map(Cow::from),
+            Input::Descriptor(_, path) | Input::Path(path) => Some(path.to_string_lossy()),
+        }
+    }
+
+    /// Name of the input in plain output and diagnostics, quoted when it
+    /// contains control characters or invalid UTF-8
+    pub fn quoted_name(&self) -> Option<Cow<'_, str>> {
+        match self {
+            Input::Stdin(name) => name.map(|name| quote(OsStr::new(name))),
+            Input::Descriptor(_, path) | Input::Path(path) => Some(quote(path.as_os_str())),
+        }
+    }
+
+    /// Metadata of the file, or of what the standard input is connected to
+    pub fn metadata(&self) -> Result<fs::Metadata, io::Error> {
+        match self {
+            #[cfg(any(unix, target_os = "wasi"))]
+            Input::Stdin(_) => {
+                use std::os::fd::{AsRawFd, FromRawFd};
+                // Borrowed rather than duplicated, which WASI can't do
+                // SAFETY: the descriptor of the stdin stays open, and the
+                // file doesn't close it
+                let file = unsafe { fs::File::from_raw_fd(io::stdin().as_raw_fd()) };
+                std::mem::ManuallyDrop::new(file).metadata()
+            }
+            #[cfg(windows)]
+            Input::Stdin(_) => {
+                use std::os::windows::io::AsHandle;
+                let handle = io::stdin().as_handle().try_clone_to_owned()?;
+                fs::File::from(handle).metadata()
+            }
+            #[cfg(not(any(unix, windows, target_os = "wasi")))]
+            Input::Stdin(_) => Err(io::ErrorKind::Unsupported.into()),
+            Input::Descriptor(fd, path) => open_descriptor(*fd, path)?.metadata(),
+            Input::Path(path) => fs::metadata(path),
+        }
+    }
+
+    pub fn open(&self) -> Result<Reader, io::Error> {
+        crate::debug!("opening");
+        match self {
+            Input::Stdin(_) => Ok(Reader::Stdin(io::stdin().lock())),
+            Input::Descriptor(fd, path) => open_descriptor(*fd, path).map(Reader::File),
+            Input::Path(path) => fs::File::open(path).map(Reader::File),
+        }
+    }
+
+    /// Open the input for reading line by line or in small pieces
+    pub fn open_buffered(&self) -> Result<io::BufReader<Reader>, io::Error> {
+        self.open().map(io::BufReader::new)
+    }
+}
+
+/// A duplicate of the open descriptor `fd`, sharing its offset, or the
+/// file `path` names where descriptors can't be duplicated
+pub fn open_descriptor(fd: i32, path: &Path) -> Result<fs::File, io::Error> {
+    #[cfg(unix)]
+    {
+        use std::os::fd::FromRawFd;
+        let _ = path;
+        // SAFETY: duplicating a descriptor that isn't open fails without
+        // harm, and the duplicate is owned by nothing else
+        unsafe {
+            match libc::fcntl(fd, libc::F_DUPFD_CLOEXEC, 0) {
+                -1 => Err(io::Error::last_os_error()),
+                dup => Ok(fs::File::from_raw_fd(dup)),
+            }
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = fd;
+        fs::File::open(path)
+    }
+}
+
+/// Unbuffered reader of an opened input
+pub enum Reader {
+    Stdin(io::StdinLock<'static>),
+    File(fs::File),
+}
+
+impl Read for Reader {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, io::Error> {
+        match self {
+            Reader::Stdin(stdin) => stdin.read(buf),
+            Reader::File(file) => file.read(buf),
+        }
+    }
+}
@@ -0,0 +1,96 @@
+//! Processing of independent operands in parallel, like the files of `wc`
+//! or of the checksum utilities, with the results emitted in the order of
+//! the operands, as if they were processed one after the other. The
+//! number of threads is taken from `--jobs`, `NYAA_JOBS` or the number of
+//! CPUs.
+
+use std::num::NonZeroUsize;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{mpsc, Condvar, Mutex};
+use std::thread;
+
+/// The `--jobs` option
+#[derive(clap::Args, Clone, Debug, Default)]
+pub struct JobsArg {
+    /// Process up to N files in parallel, by default NYAA_JOBS or the
+    /// number of CPUs
+    #[arg(long = "jobs", value_name = "N")]
+    pub jobs: Option<NonZeroUsize>,
+}
+
+impl JobsArg {
+    /// Number of threads to use
+    pub fn jobs(&self) -> usize {
+        self.jobs
+            .or_else(|| std::env::var("NYAA_JOBS").ok()?.parse().ok())
+            .or_else(|| thread::available_parallelism().ok())
+            .map_or(1, NonZeroUsize::get)
+    }
+}
+
+/// Apply `work` to every item on a pool of `jobs` threads and pass the
+/// results to `emit` in the original order as soon as they are available.
+/// Threads don't run further ahead of `emit` than a few items each, so
+/// the results waiting for an earlier one stay few.
+pub fn for_each_ordered<T, R, W, E>(items: &[T], jobs: usize, work: W, mut emit: E)
+where
+    T: Sync,
+    R: Send,
+    W: Fn(&T) -> R + Sync,
+    E: FnMut(&T, R),
+{
+    let jobs = jobs.min(items.len());
+    if jobs <= 1 {
+        items.iter().for_each(|item| emit(item, work(item)));
+        return;
+    }
+    let window = jobs * 4;
+    let next = AtomicUsize::new(0);
+    // Number of the results emitted so far
+    let emitted = (Mutex::new(0), Condvar::new());
+    let (tx, rx) = mpsc::channel();
+    thread::scope(|s| {
+        let mut spawned = 0;
+        for _ in 0..jobs {
+            let (tx, next, emitted, work) = (tx.clone(), &next, &emitted, &work);
+            let worker = thread::Builder::new().spawn_scoped(s, move || loop {
+                let i = next.fetch_add(1, Ordering::Relaxed);
+                if i >= items.len() {
+                    break;
+                }
+                let (count, advanced) = emitted;
+                let count = count.lock().unwrap_or_else(|e| e.into_inner());
+                drop(
+                    advanced
+                        .wait_while(count, |count| i >= *count + window)
+                        .unwrap_or_else(|e| e.into_inner()),
+                );
+                if tx.send((i, work(&items[i]))).is_err() {
+                    break;
+                }
+            });
+            // Targets without threads, like WASI, fail to spawn them
+            if worker.is_err() {
+                break;
+            }
+            spawned += 1;
+        }
+        drop(tx);
+        if spawned == 0 {
+            items.iter().for_each(|item| emit(item, work(item)));
+            return;
+        }
+        let mut done: Vec<Option<R>> = items.iter().map(|_| None).collect();
+        let mut next_emit = 0;
+        for (i, result) in rx {
+            done[i] = Some(result);
+            while let Some(result) = done.get_mut(next_emit).and_then(Option::take) {
+                emit(&items[next_emit], result);
+                next_emit += 1;
+            }
+            let (count, advanced) = &emitted;
+            *count.lock().unwrap_or_else(|e| e.into_inner()) = next_emit;
+            advanced.notify_all();
+        }
+    });
+}
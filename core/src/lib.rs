@@ -0,0 +1,49 @@
+//! Input handling and diagnostics shared by the utilities, so all of them
+//! treat operands and report errors the same way
+
+pub mod backup;
+pub mod color;
+pub mod context;
+pub mod date;
+pub mod decompress;
+pub mod error;
+pub mod glob;
+pub mod i18n;
+pub mod input;
+pub mod jobs;
+pub mod locale;
+pub mod mmap;
+pub mod mode;
+pub mod output;
+pub mod platform;
+pub mod posix;
+pub mod progress;
+pub mod quote;
+pub mod random;
+pub mod record;
+pub mod response;
+pub mod reverse;
+pub mod sandbox;
+pub mod size;
+pub mod sparse;
+pub mod sysinfo;
+pub mod trace;
+pub mod uring;
+pub mod users;
+pub mod walk;
+pub mod watch;
+pub mod xattr;
+
+pub use input::{Input, Reader};
+
+/// Set up the process of a utility. Writing to a closed pipe terminates it
+/// by SIGPIPE like the C utilities, so `wc big.txt | head -1` ends quietly
+/// instead of reporting a broken pipe. Rust ignores the signal by default.
+pub fn init() {
+    #[cfg(unix)]
+    // SAFETY: restoring the default disposition of a signal has no
+    // preconditions, and is done before any other thread is started
+    unsafe {
+        libc::signal(libc::SIGPIPE, libc::SIG_DFL);
+    }
+}
@@ -0,0 +1,263 @@
+//! Locale of the environment as far as the utilities depend on it: the
+//! classification of characters for `LC_CTYPE`, the order of strings for
+//! `LC_COLLATE` and the language of `LC_MESSAGES`. The C and POSIX
+//! locales classify only ASCII and compare bytes, every other locale is
+//! taken for a Unicode one.
+
+use std::cmp::Ordering;
+
+/// Locale category, selected by its `LC_*` variable
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Category {
+    Ctype,
+    Collate,
+    Messages,
+}
+
+impl Category {
+    fn var(self) -> &'static str {
+        match self {
+            Category::Ctype => "LC_CTYPE",
+            Category::Collate => "LC_COLLATE",
+            Category::Messages => "LC_MESSAGES",
+        }
+    }
+}
+
+/// Name of the locale of `category`, from the first non-empty of
+/// `LC_ALL`, the variable of the category and `LANG`, like setlocale(3)
+pub fn name(category: Category) -> Option<String> {
+    ["LC_ALL", category.var(), "LANG"]
+        .into_iter()
+        .filter_map(|var| std::env::var(var).ok())
+        .find(|value| !value.is_empty())
+}
+
+/// How characters are classified and strings ordered
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Locale {
+    /// The C or POSIX locale
+    #[default]
+    C,
+    Unicode,
+}
+
+impl Locale {
+    /// Locale of `category` in the environment, C if none is set
+    pub fn from_env(category: Category) -> Self {
+        match name(category).as_deref() {
+            None | Some("C" | "POSIX") => Locale::C,
+            Some(_) => Locale::Unicode,
+        }
+    }
+
+    /// Locale classifying characters
+    pub fn ctype() -> Self {
+        Locale::from_env(Category::Ctype)
+    }
+
+    /// Locale ordering strings. `C.UTF-8` orders by code point, which is
+    /// the order of the bytes of UTF-8 like in the C locale.
+    pub fn collate() -> Self {
+        match name(Category::Collate) {
+            Some(name) if name.starts_with("C.") => Locale::C,
+            _ => Locale::from_env(Category::Collate),
+        }
+    }
+
+    /// The `space` class. Like in glibc, no-break spaces are not in it.
+    pub fn is_space(self, c: char) -> bool {
+        match self {
+            Locale::C => matches!(c, ' ' | '\t' | '\n' | '\x0b' | '\x0c' | '\r'),
+            Locale::Unicode => c.is_whitespace() && !is_no_break_space(c),
+        }
+    }
+
+    /// The `blank` class, spaces within a line
+    pub fn is_blank(self, c: char) -> bool {
+        match self {
+            Locale::C => matches!(c, ' ' | '\t'),
+            Locale::Unicode => {
+                self.is_space(c) && !matches!(c, '\n'..='\r' | '\u{85}' | '\u{2028}' | '\u{2029}')
+            }
+        }
+    }
+
+    pub fn is_alpha(self, c: char) -> bool {
+        match self {
+            Locale::C => c.is_ascii_alphabetic(),
+            Locale::Unicode => c.is_alphabetic(),
+        }
+    }
+
+    /// The `digit` class, only `0` to `9` in every locale
+    pub fn is_digit(self, c: char) -> bool {
+        c.is_ascii_digit()
+    }
+
+    pub fn is_alnum(self, c: char) -> bool {
+        self.is_alpha(c) || self.is_digit(c)
+    }
+
+    pub fn is_upper(self, c: char) -> bool {
+        match self {
+            Locale::C => c.is_ascii_uppercase(),
+            Locale::Unicode => c.is_uppercase(),
+        }
+    }
+
+    pub fn is_lower(self, c: char) -> bool {
+        match self {
+            Locale::C => c.is_ascii_lowercase(),
+            Locale::Unicode => c.is_lowercase(),
+        }
+    }
+
+    pub fn is_cntrl(self, c: char) -> bool {
+        match self {
+            Locale::C => c.is_ascii_control(),
+            Locale::Unicode => c.is_control(),
+        }
+    }
+
+    /// The `print` class, characters other than controls including space
+    pub fn is_print(self, c: char) -> bool {
+        match self {
+            Locale::C => matches!(c, ' '..='~'),
+            Locale::Unicode => !c.is_control(),
+        }
+    }
+
+    /// The `graph` class, printable characters other than spaces
+    pub fn is_graph(self, c: char) -> bool {
+        self.is_print(c) && !self.is_space(c)
+    }
+
+    /// The `punct` class, printable characters that are neither spaces
+    /// nor alphanumeric
+    pub fn is_punct(self, c: char) -> bool {
+        self.is_graph(c) && !self.is_alnum(c)
+    }
+
+    pub fn to_upper(self, c: char) -> char {
+        match self {
+            Locale::C => c.to_ascii_uppercase(),
+            Locale::Unicode => single(c.to_uppercase()).unwrap_or(c),
+        }
+    }
+
+    pub fn to_lower(self, c: char) -> char {
+        match self {
+            Locale::C => c.to_ascii_lowercase(),
+            Locale::Unicode => single(c.to_lowercase()).unwrap_or(c),
+        }
+    }
+
+    /// The `[=c=]` equivalence class of `c`, in ascending order. In Unicode
+    /// locales the Latin letters with the same base letter are equivalent,
+    /// like `e`, `é` and `ê`, in the C locale every character only to
+    /// itself.
+    pub fn equivalents(self, c: char) -> Vec<char> {
+        if self == Locale::C {
+            return vec![c];
+        }
+        let base = ACCENTED
+            .iter()
+            .find(|(_, accented)| accented.contains(c))
+            .map_or(c, |&(base, _)| base);
+        match ACCENTED.iter().find(|&&(letter, _)| letter == base) {
+            Some(&(base, accented)) => std::iter::once(base).chain(accented.chars()).collect(),
+            None => vec![c],
+        }
+    }
+
+    /// Order of `a` and `b` in the locale. The C locale compares bytes.
+    /// Unicode locales approximate the glibc collation of UTF-8 locales:
+    /// alphanumerics are compared ignoring case and punctuation first,
+    /// then lower case sorts before upper case, and the bytes break the
+    /// remaining ties. Input that isn't UTF-8 is compared bytewise.
+    pub fn compare(self, a: &[u8], b: &[u8]) -> Ordering {
+        if self == Locale::C {
+            return a.cmp(b);
+        }
+        let (Ok(a_str), Ok(b_str)) = (std::str::from_utf8(a), std::str::from_utf8(b)) else {
+            return a.cmp(b);
+        };
+        fn alnums(locale: Locale, s: &str) -> impl Iterator<Item = char> + '_ {
+            s.chars().filter(move |&c| locale.is_alnum(c))
+        }
+        let primary = |s| alnums(self, s).map(|c| self.to_lower(c));
+        // Lower case first, so `a` sorts before `A`
+        let case = |s| alnums(self, s).map(|c| self.is_upper(c));
+        primary(a_str)
+            .cmp(primary(b_str))
+            .then_with(|| case(a_str).cmp(case(b_str)))
+            .then_with(|| a.cmp(b))
+    }
+}
+
+/// The precomposed Latin letters with diacritics, by their base letter, in
+/// ascending order
+const ACCENTED: &[(char, &str)] = &[
+    ('A', "ÀÁÂÃÄÅĀĂĄǍǞǠǺȀȂȦḀẠẢẤẦẨẪẬẮẰẲẴẶ"),
+    ('B', "ḂḄḆ"),
+    ('C', "ÇĆĈĊČḈ"),
+    ('D', "ĎḊḌḎḐḒ"),
+    ('E', "ÈÉÊËĒĔĖĘĚȄȆȨḔḖḘḚḜẸẺẼẾỀỂỄỆ"),
+    ('F', "Ḟ"),
+    ('G', "ĜĞĠĢǦǴḠ"),
+    ('H', "ĤȞḢḤḦḨḪ"),
+    ('I', "ÌÍÎÏĨĪĬĮİǏȈȊḬḮỈỊ"),
+    ('J', "Ĵ"),
+    ('K', "ĶǨḰḲḴ"),
+    ('L', "ĹĻĽḶḸḺḼ"),
+    ('M', "ḾṀṂ"),
+    ('N', "ÑŃŅŇǸṄṆṈṊ"),
+    ('O', "ÒÓÔÕÖŌŎŐƠǑǪǬȌȎȪȬȮȰṌṎṐṒỌỎỐỒỔỖỘỚỜỞỠỢ"),
+    ('P', "ṔṖ"),
+    ('R', "ŔŖŘȐȒṘṚṜṞ"),
+    ('S', "ŚŜŞŠȘṠṢṤṦṨ"),
+    ('T', "ŢŤȚṪṬṮṰ"),
+    ('U', "ÙÚÛÜŨŪŬŮŰŲƯǓǕǗǙǛȔȖṲṴṶṸṺỤỦỨỪỬỮỰ"),
+    ('V', "ṼṾ"),
+    ('W', "ŴẀẂẄẆẈ"),
+    ('X', "ẊẌ"),
+    ('Y', "ÝŶŸȲẎỲỴỶỸ"),
+    ('Z', "ŹŻŽẐẒẔ"),
+    ('a', "àáâãäåāăąǎǟǡǻȁȃȧḁạảấầẩẫậắằẳẵặ"),
+    ('b', "ḃḅḇ"),
+    ('c', "çćĉċčḉ"),
+    ('d', "ďḋḍḏḑḓ"),
+    ('e', "èéêëēĕėęěȅȇȩḕḗḙḛḝẹẻẽếềểễệ"),
+    ('f', "ḟ"),
+    ('g', "ĝğġģǧǵḡ"),
+    ('h', "ĥȟḣḥḧḩḫẖ"),
+    ('i', "ìíîïĩīĭįǐȉȋḭḯỉị"),
+    ('j', "ĵǰ"),
+    ('k', "ķǩḱḳḵ"),
+    ('l', "ĺļľḷḹḻḽ"),
+    ('m', "ḿṁṃ"),
+    ('n', "ñńņňǹṅṇṉṋ"),
+    ('o', "òóôõöōŏőơǒǫǭȍȏȫȭȯȱṍṏṑṓọỏốồổỗộớờởỡợ"),
+    ('p', "ṕṗ"),
+    ('r', "ŕŗřȑȓṙṛṝṟ"),
+    ('s', "śŝşšșṡṣṥṧṩ"),
+    ('t', "ţťțṫṭṯṱẗ"),
+    ('u', "ùúûüũūŭůűųưǔǖǘǚǜȕȗṳṵṷṹṻụủứừửữự"),
+    ('v', "ṽṿ"),
+    ('w', "ŵẁẃẅẇẉẘ"),
+    ('x', "ẋẍ"),
+    ('y', "ýÿŷȳẏẙỳỵỷỹ"),
+    ('z', "źżžẑẓẕ"),
+];
+
+/// Whether `c` is a space that doesn't allow a line break
+pub fn is_no_break_space(c: char) -> bool {
+    matches!(c, '\u{a0}' | '\u{2007}' | '\u{202f}' | '\u{2060}')
+}
+
+/// The only character of a case mapping, `None` if it maps to several
+fn single(mut chars: impl Iterator<Item = char>) -> Option<char> {
+    let c = chars.next()?;
+    chars.next().is_none().then_some(c)
+}
@@ -0,0 +1,263 @@
+//! Contents of input files, memory-mapped when they are regular files
+//! large enough for it to pay off, and read otherwise: for pipes,
+//! terminals, small files and the files of procfs and sysfs, which report
+//! no size.
+//!
+//! A mapped file that another process truncates would kill the reader
+//! with SIGBUS on the first access past its new end. On Unix, the pages
+//! of a mapping that fault are replaced by zeros instead, and the
+//! truncation is reported by [`Mapping::check`] once the contents were
+//! used. Windows doesn't let a mapped file be truncated.
+
+use std::fs;
+use std::io::{self, Read};
+use std::ops::Deref;
+use std::path::Path;
+
+/// Regular files at least this large are mapped
+const THRESHOLD: u64 = 1 << 20;
+
+/// Contents of an input file
+pub enum FileData {
+    Mapped(Mapping),
+    /// The file to read, when it isn't mapped
+    Stream(fs::File),
+}
+
+impl FileData {
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let file = fs::File::open(path)?;
+        let metadata = file.metadata()?;
+        Ok(FileData::new(file, &metadata))
+    }
+
+    /// Contents of `file`, whose metadata is `metadata`, mapped if it is
+    /// a large regular file that can be mapped
+    pub fn new(file: fs::File, metadata: &fs::Metadata) -> Self {
+        if !metadata.is_file() {
+            crate::debug!("reading, not a regular file");
+            return FileData::Stream(file);
+        }
+        if metadata.len() < THRESHOLD {
+            crate::debug!("reading, {} bytes are too few to map", metadata.len());
+            return FileData::Stream(file);
+        }
+        match Mapping::new(&file) {
+            Some(mapping) => {
+                crate::debug!("mapped {} bytes", mapping.len());
+                FileData::Mapped(mapping)
+            }
+            None => {
+                crate::debug!("reading, mapping failed");
+                FileData::Stream(file)
+            }
+        }
+    }
+}
+
+/// A read-only mapping of a whole file, the bytes of the file through
+/// [`Deref`]
+pub struct Mapping {
+    // Unregistered before the pages are unmapped
+    guard: sys::Guard,
+    mmap: memmap2::Mmap,
+    /// Position of reading through [`Read`]
+    pos: usize,
+}
+
+impl Mapping {
+    fn new(file: &fs::File) -> Option<Self> {
+        // SAFETY: the mapping is only read, and accesses past the end of
+        // a truncated file are caught by the guard
+        let mmap = unsafe { memmap2::Mmap::map(file) }.ok()?;
+        let guard = sys::Guard::new(&mmap)?;
+        #[cfg(unix)]
+        let _ = mmap.advise(memmap2::Advice::Sequential);
+        Some(Mapping {
+            guard,
+            mmap,
+            pos: 0,
+        })
+    }
+
+    /// Fail if the file was truncated since it was mapped, so part of the
+    /// contents read as zeros
+    pub fn check(&self) -> io::Result<()> {
+        if self.guard.is_truncated() {
+            Err(io::Error::other("file truncated"))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl Deref for Mapping {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.mmap
+    }
+}
+
+impl Read for Mapping {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = (&self.mmap[self.pos..]).read(buf)?;
+        self.pos += n;
+        // The zeros of a truncated file end up in `buf` before the check
+        // fails, like a short read
+        self.check()?;
+        Ok(n)
+    }
+}
+
+impl Read for FileData {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            FileData::Mapped(mapping) => mapping.read(buf),
+            FileData::Stream(file) => file.read(buf),
+        }
+    }
+}
+
+#[cfg(unix)]
+mod sys {
+    use std::ptr;
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+    use std::sync::OnceLock;
+
+    /// Mappings guarded at the same time at most, more are read instead
+    const SLOTS: usize = 64;
+
+    /// Address range of a guarded mapping, free while its start is 0
+    struct Slot {
+        start: AtomicUsize,
+        len: AtomicUsize,
+        is_truncated: AtomicBool,
+    }
+
+    #[allow(clippy::declare_interior_mutable_const)]
+    const FREE: Slot = Slot {
+        start: AtomicUsize::new(0),
+        len: AtomicUsize::new(0),
+        is_truncated: AtomicBool::new(false),
+    };
+
+    static GUARDED: [Slot; SLOTS] = [FREE; SLOTS];
+
+    /// The SIGBUS handler before ours, for faults of other memory
+    static PREVIOUS: OnceLock<Option<libc::sigaction>> = OnceLock::new();
+
+    static PAGE_SIZE: AtomicUsize = AtomicUsize::new(0);
+
+    /// Replace the faulting page of a guarded mapping by a page of zeros,
+    /// which lets the access be retried and succeed
+    extern "C" fn on_sigbus(_: libc::c_int, info: *mut libc::siginfo_t, _: *mut libc::c_void) {
+        // SAFETY: the kernel passes the information of the signal
+        let addr = unsafe { (*info).si_addr() } as usize;
+        for slot in &GUARDED {
+            let start = slot.start.load(Ordering::SeqCst);
+            if start == 0 || addr < start || addr - start >= slot.len.load(Ordering::SeqCst) {
+                continue;
+            }
+            let page = addr & !(PAGE_SIZE.load(Ordering::Relaxed) - 1);
+            // SAFETY: the page belongs to a mapping of ours, which is
+            // only read. mmap(2) isn't async-signal-safe by the letter of
+            // POSIX, but is a plain system call on the systems we run on.
+            let zeros = unsafe {
+                libc::mmap(
+                    page as *mut libc::c_void,
+                    PAGE_SIZE.load(Ordering::Relaxed),
+                    libc::PROT_READ,
+                    libc::MAP_PRIVATE | libc::MAP_ANONYMOUS | libc::MAP_FIXED,
+                    -1,
+                    0,
+                )
+            };
+            if zeros != libc::MAP_FAILED {
+                slot.is_truncated.store(true, Ordering::SeqCst);
+                return;
+            }
+        }
+        // Not a fault of ours: put the previous handler back, so the
+        // access faults again and gets the usual treatment
+        let previous = PREVIOUS.get().copied().flatten();
+        // SAFETY: sigaction(2) is async-signal-safe, and the previous
+        // action was returned by it
+        unsafe {
+            match previous {
+                Some(previous) => libc::sigaction(libc::SIGBUS, &previous, ptr::null_mut()),
+                None => libc::signal(libc::SIGBUS, libc::SIG_DFL) as libc::c_int,
+            };
+        }
+    }
+
+    /// Install the SIGBUS handler once, `false` if it couldn't be
+    fn install() -> bool {
+        PREVIOUS
+            .get_or_init(|| {
+                // SAFETY: sysconf(3) has no preconditions, and the actions
+                // are initialized before they are passed
+                unsafe {
+                    let page_size = libc::sysconf(libc::_SC_PAGESIZE);
+                    PAGE_SIZE.store(page_size.max(4096) as usize, Ordering::Relaxed);
+                    let mut action: libc::sigaction = std::mem::zeroed();
+                    action.sa_sigaction = on_sigbus as *const () as usize;
+                    action.sa_flags = libc::SA_SIGINFO | libc::SA_NODEFER;
+                    libc::sigemptyset(&mut action.sa_mask);
+                    let mut previous: libc::sigaction = std::mem::zeroed();
+                    (libc::sigaction(libc::SIGBUS, &action, &mut previous) == 0).then_some(previous)
+                }
+            })
+            .is_some()
+    }
+
+    /// Registration of a mapping with the SIGBUS handler, for as long as
+    /// it lives
+    pub struct Guard(&'static Slot);
+
+    impl Guard {
+        /// Guard `mapping`, `None` if it can't be
+        pub fn new(mapping: &[u8]) -> Option<Self> {
+            if !install() {
+                return None;
+            }
+            // The length claims a slot, the start makes it visible
+            let slot = GUARDED.iter().find(|slot| {
+                slot.len
+                    .compare_exchange(0, mapping.len(), Ordering::SeqCst, Ordering::SeqCst)
+                    .is_ok()
+            })?;
+            slot.is_truncated.store(false, Ordering::SeqCst);
+            slot.start
+                .store(mapping.as_ptr() as usize, Ordering::SeqCst);
+            Some(Guard(slot))
+        }
+
+        pub fn is_truncated(&self) -> bool {
+            self.0.is_truncated.load(Ordering::SeqCst)
+        }
+    }
+
+    impl Drop for Guard {
+        fn drop(&mut self) {
+            self.0.start.store(0, Ordering::SeqCst);
+            self.0.len.store(0, Ordering::SeqCst);
+        }
+    }
+}
+
+/// Mapped files can't be truncated elsewhere
+#[cfg(not(unix))]
+mod sys {
+    pub struct Guard;
+
+    impl Guard {
+        pub fn new(_mapping: &[u8]) -> Option<Self> {
+            Some(Guard)
+        }
+
+        pub fn is_truncated(&self) -> bool {
+            false
+        }
+    }
+}
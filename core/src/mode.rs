@@ -0,0 +1,349 @@
+//! File modes like `chmod` takes them: octal like `755`, or symbolic like
+//! `u+rwx,g-s,o=rX`, for `chmod`, `mkdir -m`, `install -m` and `mkfifo -m`
+
+use crate::error::{UError, UResult};
+
+const SUID: u32 = 0o4000;
+const SGID: u32 = 0o2000;
+const STICKY: u32 = 0o1000;
+
+/// Permission bits of the user, group and others classes
+const USER: u32 = 0o4700;
+const GROUP: u32 = 0o2070;
+const OTHERS: u32 = 0o1007;
+const ALL: u32 = USER | GROUP | OTHERS;
+
+/// The permission bits that are read, written and executed
+const READ: u32 = 0o444;
+const WRITE: u32 = 0o222;
+const EXECUTE: u32 = 0o111;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Op {
+    Add,
+    Remove,
+    Set,
+}
+
+/// What an operation applies
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Perms {
+    /// Bits of `rwxst`, and whether `X` was given
+    Bits { bits: u32, is_conditional_x: bool },
+    /// The current permissions of a class, `u`, `g` or `o`
+    Copy(u32),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Action {
+    /// Classes affected, `None` when no class was given, to affect all of
+    /// them without setting the bits of the umask
+    who: Option<u32>,
+    op: Op,
+    perms: Perms,
+}
+
+/// A parsed mode, to apply to the mode of a file
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Mode(Kind);
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Kind {
+    /// Octal mode, and whether it has more than four digits, so it also
+    /// clears the set-user-ID and set-group-ID bits of directories
+    Octal {
+        mode: u32,
+        is_explicit: bool,
+    },
+    Symbolic {
+        actions: Vec<Action>,
+        umask: u32,
+    },
+}
+
+fn invalid(s: &str) -> UError {
+    UError::failure(format!("invalid mode: '{s}'"))
+}
+
+/// The umask of the process
+#[cfg(unix)]
+pub fn umask() -> u32 {
+    // SAFETY: umask(2) can't fail. The mask is read by setting it, so it
+    // is set back right away.
+    let mask = unsafe {
+        let mask = libc::umask(0);
+        libc::umask(mask);
+        mask
+    };
+    mask as u32 & 0o777
+}
+
+/// There is no umask elsewhere, the usual one is assumed
+#[cfg(not(unix))]
+pub fn umask() -> u32 {
+    0o022
+}
+
+/// Parse `s`, where symbolic clauses without a class don't set the bits
+/// of `umask`
+pub fn parse(s: &str, umask: u32) -> UResult<Mode> {
+    if !s.is_empty() && s.bytes().all(|b| (b'0'..=b'7').contains(&b)) {
+        let mode = u32::from_str_radix(s, 8)
+            .ok()
+            .filter(|&mode| mode <= 0o7777)
+            .ok_or_else(|| invalid(s))?;
+        return Ok(Mode(Kind::Octal {
+            mode,
+            is_explicit: s.len() > 4,
+        }));
+    }
+    let mut actions = Vec::new();
+    for clause in s.split(',') {
+        let mut chars = clause.chars().peekable();
+        let mut who = None;
+        while let Some(&c) = chars.peek() {
+            let class = match c {
+                'u' => USER,
+                'g' => GROUP,
+                'o' => OTHERS,
+                'a' => ALL,
+                _ => break,
+            };
+            who = Some(who.unwrap_or(0) | class);
+            chars.next();
+        }
+        // A clause has at least one operation
+        if chars.peek().is_none() {
+            return Err(invalid(s));
+        }
+        while let Some(c) = chars.next() {
+            let op = match c {
+                '+' => Op::Add,
+                '-' => Op::Remove,
+                '=' => Op::Set,
+                _ => return Err(invalid(s)),
+            };
+            let perms = match chars.peek() {
+                Some(&class @ ('u' | 'g' | 'o')) => {
+                    chars.next();
+                    Perms::Copy(match class {
+                        'u' => USER,
+                        'g' => GROUP,
+                        _ => OTHERS,
+                    })
+                }
+                _ => {
+                    let mut bits = 0;
+                    let mut is_conditional_x = false;
+                    while let Some(&c) = chars.peek() {
+                        match c {
+                            'r' => bits |= READ,
+                            'w' => bits |= WRITE,
+                            'x' => bits |= EXECUTE,
+                            'X' => is_conditional_x = true,
+                            's' => bits |= SUID | SGID,
+                            't' => bits |= STICKY,
+                            _ => break,
+                        }
+                        chars.next();
+                    }
+                    Perms::Bits {
+                        bits,
+                        is_conditional_x,
+                    }
+                }
+            };
+            actions.push(Action { who, op, perms });
+        }
+    }
+    Ok(Mode(Kind::Symbolic { actions, umask }))
+}
+
+/// Permissions of the class `from` moved to the positions of every class
+fn spread(mode: u32, from: u32) -> u32 {
+    let rwx = match from {
+        USER => (mode >> 6) & 0o7,
+        GROUP => (mode >> 3) & 0o7,
+        _ => mode & 0o7,
+    };
+    rwx * 0o111
+}
+
+impl Mode {
+    /// The mode a file with the mode `mode` gets, a directory if `is_dir`.
+    /// Like in GNU `chmod`, the set-user-ID and set-group-ID bits of
+    /// directories are kept unless they are named explicitly.
+    pub fn apply(&self, mode: u32, is_dir: bool) -> u32 {
+        let mode = mode & 0o7777;
+        match &self.0 {
+            Kind::Octal {
+                mode: new,
+                is_explicit,
+            } => {
+                if is_dir && !is_explicit {
+                    new | (mode & (SUID | SGID))
+                } else {
+                    *new
+                }
+            }
+            Kind::Symbolic { actions, umask } => actions
+                .iter()
+                .fold(mode, |mode, action| action.apply(mode, is_dir, *umask)),
+        }
+    }
+}
+
+impl Action {
+    fn apply(&self, mode: u32, is_dir: bool, umask: u32) -> u32 {
+        let who = self.who.unwrap_or(ALL);
+        let bits = match self.perms {
+            Perms::Copy(from) => spread(mode, from),
+            Perms::Bits {
+                bits,
+                is_conditional_x,
+            } => {
+                // `X` is execute for directories and for files some class
+                // may execute already
+                let x = if is_conditional_x && (is_dir || mode & EXECUTE != 0) {
+                    EXECUTE
+                } else {
+                    0
+                };
+                bits | x
+            }
+        };
+        let mut affected = bits & who;
+        // Without a class the bits of the umask aren't set, `=` clears them
+        // still
+        if self.who.is_none() {
+            affected &= !(umask & 0o777);
+        }
+        match self.op {
+            Op::Add => mode | affected,
+            Op::Remove => mode & !affected,
+            Op::Set => {
+                let mut cleared = who & !(SUID | SGID | STICKY) | (who & STICKY);
+                if !is_dir {
+                    cleared |= who & (SUID | SGID);
+                }
+                (mode & !cleared) | affected
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn apply(s: &str, mode: u32) -> u32 {
+        parse(s, 0o022).unwrap().apply(mode, false)
+    }
+
+    fn apply_dir(s: &str, mode: u32) -> u32 {
+        parse(s, 0o022).unwrap().apply(mode, true)
+    }
+
+    #[test]
+    fn octal() {
+        assert_eq!(apply("755", 0o600), 0o755);
+        assert_eq!(apply("0", 0o777), 0);
+        assert_eq!(apply("4755", 0o644), 0o4755);
+        assert_eq!(apply("7777", 0), 0o7777);
+    }
+
+    #[test]
+    fn octal_keeps_directory_special_bits() {
+        assert_eq!(apply_dir("755", 0o2700), 0o2755);
+        assert_eq!(apply_dir("00755", 0o6700), 0o755);
+        assert_eq!(apply("755", 0o2700), 0o755);
+    }
+
+    #[test]
+    fn add_remove_set() {
+        assert_eq!(apply("u+x", 0o644), 0o744);
+        assert_eq!(apply("go-r", 0o644), 0o600);
+        assert_eq!(apply("o=rw", 0o640), 0o646);
+        assert_eq!(apply("a+w", 0o444), 0o666);
+        assert_eq!(apply("ug=rwx,o=", 0o644), 0o770);
+        assert_eq!(apply("u=", 0o755), 0o055);
+    }
+
+    #[test]
+    fn several_operations_in_a_clause() {
+        assert_eq!(apply("u+x-w", 0o644), 0o544);
+        assert_eq!(apply("g=r+w", 0o600), 0o660);
+    }
+
+    #[test]
+    fn no_class_respects_umask() {
+        assert_eq!(apply("+w", 0o444), 0o644);
+        assert_eq!(apply("=rwx", 0o000), 0o755);
+        assert_eq!(apply("-w", 0o666), 0o466);
+        assert_eq!(parse("+w", 0).unwrap().apply(0o444, false), 0o666);
+        // `=` clears the bits of the umask still
+        assert_eq!(apply("=r", 0o622), 0o444);
+    }
+
+    #[test]
+    fn conditional_execute() {
+        assert_eq!(apply("a+X", 0o644), 0o644);
+        assert_eq!(apply("a+X", 0o744), 0o755);
+        assert_eq!(apply_dir("a+X", 0o644), 0o755);
+        assert_eq!(apply("o=rX", 0o750), 0o755);
+        assert_eq!(apply("o=rX", 0o640), 0o644);
+    }
+
+    #[test]
+    fn special_bits() {
+        assert_eq!(apply("u+s", 0o755), 0o4755);
+        assert_eq!(apply("g+s", 0o755), 0o2755);
+        assert_eq!(apply("g-s", 0o2755), 0o755);
+        assert_eq!(apply("+t", 0o755), 0o1755);
+        assert_eq!(apply("o+t", 0o755), 0o1755);
+        // `s` means nothing to others, `t` nothing to the user
+        assert_eq!(apply("o+s", 0o755), 0o755);
+        assert_eq!(apply("u+t", 0o755), 0o755);
+        assert_eq!(apply("a+st", 0o755), 0o7755);
+    }
+
+    #[test]
+    fn set_clears_special_bits_but_of_directories() {
+        assert_eq!(apply("g=rx", 0o2775), 0o755);
+        assert_eq!(apply_dir("g=rx", 0o2775), 0o2755);
+        assert_eq!(apply_dir("g=rxs", 0o775), 0o2755);
+        assert_eq!(apply("o=r", 0o1757), 0o754);
+    }
+
+    #[test]
+    fn copy_permissions() {
+        assert_eq!(apply("g=u", 0o740), 0o770);
+        assert_eq!(apply("o+g", 0o750), 0o755);
+        assert_eq!(apply("go=u", 0o700), 0o777);
+        assert_eq!(apply("u-o", 0o701), 0o601);
+    }
+
+    #[test]
+    fn clauses_apply_in_order() {
+        assert_eq!(apply("u=rwx,g=u,g-w", 0o000), 0o750);
+        assert_eq!(apply("a=,u+r", 0o777), 0o400);
+    }
+
+    #[test]
+    fn invalid_modes() {
+        for s in [
+            "", "8", "77777", "u", "u+z", "x+r", "u+r,", ",u+r", "u+r g-w", "+rw-q",
+        ] {
+            assert!(parse(s, 0o022).is_err(), "{s:?} should be invalid");
+        }
+        assert_eq!(
+            parse("a+q", 0).unwrap_err().to_string(),
+            "invalid mode: 'a+q'"
+        );
+    }
+
+    #[test]
+    fn file_type_bits_are_ignored() {
+        assert_eq!(apply("u+x", 0o100644), 0o744);
+    }
+}
@@ -0,0 +1,76 @@
+//! Buffered output of the utilities. The stdout is written in blocks, or
+//! by lines when it is a terminal, so output shows up as it is produced
+//! there. Write errors, like a full disk the output is redirected to, are
+//! reported as `write error` diagnostics.
+
+use crate::error::{UError, UResult};
+use crate::i18n::tr;
+use std::io::{self, BufWriter, IsTerminal, LineWriter, Write};
+
+enum Buffer {
+    Block(BufWriter<Box<dyn Write>>),
+    Line(LineWriter<Box<dyn Write>>),
+}
+
+/// A locked and buffered standard stream. Call [`OutputSink::finish`]
+/// at the end, so an error writing the buffered rest isn't lost.
+pub struct OutputSink {
+    buffer: Buffer,
+}
+
+/// Diagnostic of an error writing the output
+pub fn write_error(err: io::Error) -> UError {
+    UError::from(err).with_name(tr("write error"))
+}
+
+impl OutputSink {
+    /// Sink writing to `out`, by lines if `is_line_buffered`
+    pub fn new(out: Box<dyn Write>, is_line_buffered: bool) -> Self {
+        let buffer = if is_line_buffered {
+            Buffer::Line(LineWriter::new(out))
+        } else {
+            Buffer::Block(BufWriter::new(out))
+        };
+        OutputSink { buffer }
+    }
+
+    pub fn stdout() -> Self {
+        let out = io::stdout().lock();
+        let is_terminal = out.is_terminal();
+        OutputSink::new(Box::new(out), is_terminal)
+    }
+
+    pub fn stderr() -> Self {
+        let out = io::stderr().lock();
+        let is_terminal = out.is_terminal();
+        OutputSink::new(Box::new(out), is_terminal)
+    }
+
+    /// Write out what is buffered, failing with a write error
+    pub fn finish(mut self) -> UResult<()> {
+        self.flush().map_err(write_error)
+    }
+}
+
+impl Write for OutputSink {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match &mut self.buffer {
+            Buffer::Block(out) => out.write(buf),
+            Buffer::Line(out) => out.write(buf),
+        }
+    }
+
+    fn write_all(&mut self, buf: &[u8]) -> io::Result<()> {
+        match &mut self.buffer {
+            Buffer::Block(out) => out.write_all(buf),
+            Buffer::Line(out) => out.write_all(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match &mut self.buffer {
+            Buffer::Block(out) => out.flush(),
+            Buffer::Line(out) => out.flush(),
+        }
+    }
+}
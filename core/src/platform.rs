@@ -0,0 +1,294 @@
+//! What differs on Windows, behind one interface: ANSI escapes in the
+//! console, the verbatim paths `canonicalize` returns, the line endings
+//! of text files, the file metadata that Unix has and Windows lacks and
+//! looking up processes. On Unix the functions are plain or do nothing.
+
+use crate::record::Terminator;
+use std::borrow::Cow;
+use std::ffi::OsString;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Let the console interpret the ANSI escapes written to the stdout and
+/// the stderr, `false` if it can't. Consoles of Windows 10 and later can,
+/// terminals elsewhere always do.
+pub fn enable_ansi() -> bool {
+    sys::enable_ansi()
+}
+
+/// Whether the process `pid` is running, also when it belongs to another
+/// user. Where processes can't be looked up, like on WASI, every one is
+/// taken to be running.
+pub fn is_process_running(pid: u32) -> bool {
+    sys::is_process_running(pid)
+}
+
+/// Terminator of the lines of text files: CRLF on Windows, where lines
+/// read with it lose a trailing carriage return, and newline elsewhere
+pub fn text_terminator() -> Terminator {
+    if cfg!(windows) {
+        Terminator::CrLf
+    } else {
+        Terminator::Newline
+    }
+}
+
+/// Name or argument of the bytes `bytes`, read from a file list or a
+/// response file. Names are bytes on Unix and WASI, and UTF-16 on
+/// Windows, where invalid UTF-8 is replaced.
+#[cfg(any(unix, target_os = "wasi"))]
+pub fn os_string(bytes: Vec<u8>) -> OsString {
+    #[cfg(unix)]
+    use std::os::unix::ffi::OsStringExt;
+    #[cfg(target_os = "wasi")]
+    use std::os::wasi::ffi::OsStringExt;
+    OsString::from_vec(bytes)
+}
+
+#[cfg(not(any(unix, target_os = "wasi")))]
+pub fn os_string(bytes: Vec<u8>) -> OsString {
+    OsString::from(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+/// `path` without the `\\?\` prefix of verbatim paths, which
+/// `fs::canonicalize` returns on Windows and no one wants to see, when
+/// the path means the same without it
+pub fn normalize_path(path: &Path) -> Cow<'_, Path> {
+    if !cfg!(windows) {
+        return Cow::Borrowed(path);
+    }
+    let Some(s) = path.to_str() else {
+        return Cow::Borrowed(path);
+    };
+    if let Some(unc) = s.strip_prefix(r"\\?\UNC\") {
+        return Cow::Owned(format!(r"\\{unc}").into());
+    }
+    match s.strip_prefix(r"\\?\") {
+        // A drive path like `C:\dir`, shorter than the limit of paths
+        // without the prefix and without components the prefix makes
+        // literal
+        Some(rest)
+            if rest.len() < 260
+                && rest.as_bytes().get(1) == Some(&b':')
+                && !rest.split('\\').any(|c| c == "." || c == "..") =>
+        {
+            Cow::Owned(rest.into())
+        }
+        _ => Cow::Borrowed(path),
+    }
+}
+
+/// Metadata of a file with the fields of Unix `stat`, for `ls`, `stat`,
+/// `du` and the like. On Windows, the mode is made up from the file type
+/// and the read-only attribute, and users and groups are 0.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FileInfo {
+    /// Device of the file system, the volume serial number on Windows
+    pub dev: u64,
+    /// Number of the file on its device, the file index on Windows
+    pub ino: u64,
+    pub nlink: u64,
+    /// File type and permission bits
+    pub mode: u32,
+    pub uid: u32,
+    pub gid: u32,
+    pub size: u64,
+    /// Number of 512-byte blocks allocated
+    pub blocks: u64,
+    /// Preferred size of the blocks of I/O
+    pub blksize: u64,
+}
+
+/// File type bits of the mode
+pub const S_IFMT: u32 = 0o170000;
+pub const S_IFDIR: u32 = 0o040000;
+pub const S_IFREG: u32 = 0o100000;
+pub const S_IFLNK: u32 = 0o120000;
+
+impl FileInfo {
+    /// Information in `metadata`. Windows metadata doesn't tell the
+    /// device, number and links of a file, see [`FileInfo::from_path`].
+    pub fn from_metadata(metadata: &fs::Metadata) -> Self {
+        sys::from_metadata(metadata)
+    }
+
+    /// Information of the file at `path`, following symbolic links
+    pub fn from_path(path: &Path) -> io::Result<Self> {
+        sys::from_path(path)
+    }
+
+    pub fn is_dir(&self) -> bool {
+        self.mode & S_IFMT == S_IFDIR
+    }
+}
+
+#[cfg(unix)]
+mod sys {
+    use super::FileInfo;
+    use std::fs;
+    use std::io;
+    use std::os::unix::fs::MetadataExt;
+    use std::path::Path;
+
+    pub fn enable_ansi() -> bool {
+        true
+    }
+
+    pub fn from_metadata(metadata: &fs::Metadata) -> FileInfo {
+        FileInfo {
+            dev: metadata.dev(),
+            ino: metadata.ino(),
+            nlink: metadata.nlink(),
+            mode: metadata.mode(),
+            uid: metadata.uid(),
+            gid: metadata.gid(),
+            size: metadata.size(),
+            blocks: metadata.blocks(),
+            blksize: metadata.blksize(),
+        }
+    }
+
+    pub fn from_path(path: &Path) -> io::Result<FileInfo> {
+        fs::metadata(path).map(|metadata| from_metadata(&metadata))
+    }
+
+    pub fn is_process_running(pid: u32) -> bool {
+        let Ok(pid) = libc::pid_t::try_from(pid) else {
+            return false;
+        };
+        // SAFETY: signal 0 only checks that the process can be signaled
+        let is_signaled = unsafe { libc::kill(pid, 0) } == 0;
+        is_signaled || io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+    }
+}
+
+/// Metadata made up from what every platform has
+#[cfg(not(unix))]
+fn portable_info(metadata: &fs::Metadata) -> FileInfo {
+    let file_type = if metadata.is_dir() {
+        S_IFDIR
+    } else if metadata.is_symlink() {
+        S_IFLNK
+    } else {
+        S_IFREG
+    };
+    let mut mode = if metadata.is_dir() { 0o755 } else { 0o644 };
+    if metadata.permissions().readonly() {
+        mode &= !0o222;
+    }
+    FileInfo {
+        dev: 0,
+        ino: 0,
+        nlink: 1,
+        mode: file_type | mode,
+        uid: 0,
+        gid: 0,
+        size: metadata.len(),
+        blocks: metadata.len().div_ceil(512),
+        blksize: 4096,
+    }
+}
+
+#[cfg(windows)]
+mod sys {
+    use super::FileInfo;
+    use std::fs;
+    use std::io;
+    use std::os::windows::fs::OpenOptionsExt;
+    use std::os::windows::io::AsRawHandle;
+    use std::path::Path;
+    use windows_sys::Win32::Foundation::{CloseHandle, ERROR_ACCESS_DENIED, STILL_ACTIVE};
+    use windows_sys::Win32::Storage::FileSystem::{
+        GetFileInformationByHandle, BY_HANDLE_FILE_INFORMATION, FILE_FLAG_BACKUP_SEMANTICS,
+    };
+    use windows_sys::Win32::System::Console::{
+        GetConsoleMode, GetStdHandle, SetConsoleMode, ENABLE_VIRTUAL_TERMINAL_PROCESSING,
+        STD_ERROR_HANDLE, STD_OUTPUT_HANDLE,
+    };
+    use windows_sys::Win32::System::Threading::{
+        GetExitCodeProcess, OpenProcess, PROCESS_QUERY_LIMITED_INFORMATION,
+    };
+
+    pub fn enable_ansi() -> bool {
+        [STD_OUTPUT_HANDLE, STD_ERROR_HANDLE].into_iter().all(|id| {
+            // SAFETY: the handle is checked by the console functions, which
+            // fail for handles that aren't consoles
+            unsafe {
+                let handle = GetStdHandle(id);
+                let mut mode = 0;
+                GetConsoleMode(handle, &mut mode) != 0
+                    && (mode & ENABLE_VIRTUAL_TERMINAL_PROCESSING != 0
+                        || SetConsoleMode(handle, mode | ENABLE_VIRTUAL_TERMINAL_PROCESSING) != 0)
+            }
+        })
+    }
+
+    pub fn from_metadata(metadata: &fs::Metadata) -> FileInfo {
+        super::portable_info(metadata)
+    }
+
+    /// The identity and links of a file are only known by a handle of it
+    pub fn from_path(path: &Path) -> io::Result<FileInfo> {
+        // Directories can only be opened with backup semantics
+        let file = fs::OpenOptions::new()
+            .read(true)
+            .custom_flags(FILE_FLAG_BACKUP_SEMANTICS)
+            .open(path)?;
+        let mut info = super::portable_info(&file.metadata()?);
+        // SAFETY: the handle is open for the lifetime of `file` and the
+        // information is written into a zeroed structure of its type
+        let by_handle = unsafe {
+            let mut by_handle: BY_HANDLE_FILE_INFORMATION = std::mem::zeroed();
+            if GetFileInformationByHandle(file.as_raw_handle(), &mut by_handle) == 0 {
+                return Err(io::Error::last_os_error());
+            }
+            by_handle
+        };
+        info.dev = by_handle.dwVolumeSerialNumber as u64;
+        info.ino = (by_handle.nFileIndexHigh as u64) << 32 | by_handle.nFileIndexLow as u64;
+        info.nlink = by_handle.nNumberOfLinks as u64;
+        Ok(info)
+    }
+
+    pub fn is_process_running(pid: u32) -> bool {
+        // SAFETY: the handle is checked before it is used, and closed
+        unsafe {
+            let process = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, 0, pid);
+            if process.is_null() {
+                // A process of another user can't be opened
+                return io::Error::last_os_error().raw_os_error()
+                    == Some(ERROR_ACCESS_DENIED as i32);
+            }
+            let mut code = 0;
+            let is_running =
+                GetExitCodeProcess(process, &mut code) != 0 && code == STILL_ACTIVE as u32;
+            CloseHandle(process);
+            is_running
+        }
+    }
+}
+
+#[cfg(not(any(unix, windows)))]
+mod sys {
+    use super::FileInfo;
+    use std::fs;
+    use std::io;
+    use std::path::Path;
+
+    pub fn enable_ansi() -> bool {
+        true
+    }
+
+    pub fn from_metadata(metadata: &fs::Metadata) -> FileInfo {
+        super::portable_info(metadata)
+    }
+
+    pub fn from_path(path: &Path) -> io::Result<FileInfo> {
+        fs::metadata(path).map(|metadata| from_metadata(&metadata))
+    }
+
+    pub fn is_process_running(_pid: u32) -> bool {
+        true
+    }
+}
@@ -0,0 +1,98 @@
+//! Strict POSIX behavior, enabled by setting `POSIXLY_CORRECT` like for
+//! the GNU utilities: options end at the first operand instead of being
+//! accepted anywhere, and extensions that change the results of POSIX
+//! usage are disabled
+
+use std::ffi::OsString;
+use std::sync::OnceLock;
+
+/// Whether `POSIXLY_CORRECT` is set, to any value
+pub fn is_enabled() -> bool {
+    static IS_ENABLED: OnceLock<bool> = OnceLock::new();
+    *IS_ENABLED.get_or_init(|| std::env::var_os("POSIXLY_CORRECT").is_some())
+}
+
+/// Whether the option `name` of `command`, a long name or a short one,
+/// takes its value from the following argument when not attached to it
+fn takes_value(command: &clap::Command, name: Name) -> bool {
+    command
+        .get_arguments()
+        .find(|arg| match name {
+            Name::Long(long) => {
+                arg.get_long() == Some(long)
+                    || arg
+                        .get_all_aliases()
+                        .is_some_and(|aliases| aliases.contains(&long))
+            }
+            Name::Short(short) => arg.get_short() == Some(short),
+        })
+        .is_some_and(|arg| {
+            arg.get_action().takes_values()
+                && !arg.is_require_equals_set()
+                && arg
+                    .get_num_args()
+                    .is_none_or(|range| range.min_values() > 0)
+        })
+}
+
+#[derive(Clone, Copy)]
+enum Name<'a> {
+    Long(&'a str),
+    Short(char),
+}
+
+/// The arguments of `command`, with options after the first operand taken
+/// as operands when POSIX behavior is enabled, by inserting a `--` before
+/// that operand
+pub fn operands_last(command: &clap::Command, args: Vec<OsString>) -> Vec<OsString> {
+    if !is_enabled() {
+        return args;
+    }
+    options_first(command, args, |_| false)
+}
+
+/// The arguments of `command`, with options after the first operand taken
+/// as operands whatever the behavior, like getopt(3) takes them for option
+/// strings starting with `+`. Arguments that `is_operand` accepts are the
+/// first operand even if they start with `-`, like negative numbers.
+pub fn options_first(
+    command: &clap::Command,
+    mut args: Vec<OsString>,
+    is_operand: impl Fn(&str) -> bool,
+) -> Vec<OsString> {
+    let mut i = 1;
+    while let Some(arg) = args.get(i) {
+        let Some(arg) = arg.to_str() else {
+            break;
+        };
+        if arg == "--" {
+            return args;
+        }
+        if is_operand(arg) {
+            break;
+        }
+        if let Some(long) = arg.strip_prefix("--") {
+            if !long.contains('=') && takes_value(command, Name::Long(long)) {
+                i += 1;
+            }
+        } else if let Some(shorts) = arg.strip_prefix('-').filter(|shorts| !shorts.is_empty()) {
+            // The value of an option in a cluster is the rest of it, or
+            // the next argument at its end
+            for (pos, short) in shorts.char_indices() {
+                if takes_value(command, Name::Short(short)) {
+                    if pos + short.len_utf8() == shorts.len() {
+                        i += 1;
+                    }
+                    break;
+                }
+            }
+        } else {
+            break;
+        }
+        i += 1;
+    }
+    if i < args.len() {
+        args.insert(i, OsString::from("--"));
+    }
+    args
+}
@@ -0,0 +1,225 @@
+//! Progress of long transfers on the stderr, shared by `dd status=progress`,
+//! `cp` and `mv` of large files and `wc --progress`, so all of them look
+//! and throttle the same way.
+//!
+//! While the stderr is a terminal, a line with the bytes done and the
+//! rate, and a bar with the time left when the total is known, is redrawn
+//! at most ten times a second. On Unix, SIGUSR1 prints the same numbers
+//! once as a plain line wherever the stderr goes, like `dd` does.
+
+use crate::size::{self, Base};
+use std::io::{self, IsTerminal};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Bytes transferred so far, updated from any number of threads
+pub struct Progress {
+    done: AtomicU64,
+    /// Size of the whole transfer, if it is known
+    total: Option<u64>,
+    start: Instant,
+    /// Whether the line is drawn, which it is only on a terminal
+    is_drawn: bool,
+    /// Time of the last redraw and whether the line is on the screen
+    state: Mutex<(Instant, bool)>,
+}
+
+impl Progress {
+    const INTERVAL: Duration = Duration::from_millis(100);
+    const BAR_WIDTH: usize = 30;
+
+    /// Start reporting a transfer of `total` bytes, an unknown number if
+    /// `None`. Installs the SIGUSR1 handler, so the signal no longer
+    /// terminates the process.
+    pub fn new(total: Option<u64>) -> Self {
+        signal::install();
+        Progress {
+            done: AtomicU64::new(0),
+            total,
+            start: Instant::now(),
+            is_drawn: io::stderr().is_terminal(),
+            state: Mutex::new((Instant::now(), false)),
+        }
+    }
+
+    /// Bytes transferred so far
+    pub fn done(&self) -> u64 {
+        self.done.load(Ordering::Relaxed)
+    }
+
+    /// Count `bytes` more as transferred, redrawing the line if it is
+    /// time to, and printing the status if SIGUSR1 asked for it
+    pub fn add(&self, bytes: u64) {
+        self.done.fetch_add(bytes, Ordering::Relaxed);
+        if !self.is_drawn && !signal::is_pending() {
+            return;
+        }
+        // Threads that find another one drawing skip the redraw
+        let Ok(mut state) = self.state.try_lock() else {
+            return;
+        };
+        if signal::take() {
+            let clear = if state.1 { "\r\x1b[2K" } else { "" };
+            eprintln!("{clear}{}", self.status());
+            state.1 = false;
+        }
+        if self.is_drawn && state.0.elapsed() >= Self::INTERVAL {
+            *state = (Instant::now(), true);
+            eprint!(
+                "\r\x1b[2K{}",
+                line(self.done(), self.total, self.start.elapsed())
+            );
+        }
+    }
+
+    /// Plain line with the bytes done, the time taken and the rate, like
+    /// `dd` prints at the end
+    pub fn status(&self) -> String {
+        let done = self.done();
+        let elapsed = self.start.elapsed();
+        format!(
+            "{done} bytes ({}) done, {:.3} s, {}/s",
+            size::human(done, Base::Iec),
+            elapsed.as_secs_f64(),
+            size::human(rate(done, elapsed), Base::Iec),
+        )
+    }
+
+    /// Remove the line from the screen, so other output can be printed.
+    /// It is drawn again by the next [`add`](Self::add) due to redraw.
+    pub fn clear(&self) {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        if state.1 {
+            eprint!("\r\x1b[2K");
+            state.1 = false;
+        }
+    }
+}
+
+impl Drop for Progress {
+    fn drop(&mut self) {
+        self.clear();
+    }
+}
+
+/// Bytes per second of `done` bytes in `elapsed`
+fn rate(done: u64, elapsed: Duration) -> u64 {
+    let secs = elapsed.as_secs_f64();
+    if secs > 0.0 {
+        (done as f64 / secs) as u64
+    } else {
+        0
+    }
+}
+
+/// Duration as `M:SS`, or `H:MM:SS` from an hour on
+fn format_duration(secs: u64) -> String {
+    let (hours, mins, secs) = (secs / 3600, secs / 60 % 60, secs % 60);
+    if hours > 0 {
+        format!("{hours}:{mins:02}:{secs:02}")
+    } else {
+        format!("{mins}:{secs:02}")
+    }
+}
+
+/// Progress line of `done` bytes of `total` after `elapsed`
+fn line(done: u64, total: Option<u64>, elapsed: Duration) -> String {
+    let rate = rate(done, elapsed);
+    let Some(total) = total.filter(|&total| total > 0) else {
+        return format!(
+            "{} {}/s",
+            size::human(done, Base::Iec),
+            size::human(rate, Base::Iec)
+        );
+    };
+    let ratio = (done as f64 / total as f64).min(1.0);
+    let filled = (ratio * Progress::BAR_WIDTH as f64) as usize;
+    let eta = match rate {
+        0 => "-:--".to_string(),
+        rate => format_duration(total.saturating_sub(done).div_ceil(rate)),
+    };
+    format!(
+        "[{}{}] {:3.0}% {}/{} {}/s ETA {eta}",
+        "#".repeat(filled),
+        " ".repeat(Progress::BAR_WIDTH - filled),
+        ratio * 100.0,
+        size::human(done, Base::Iec),
+        size::human(total, Base::Iec),
+        size::human(rate, Base::Iec),
+    )
+}
+
+#[cfg(unix)]
+mod signal {
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Once;
+
+    static IS_PENDING: AtomicBool = AtomicBool::new(false);
+    static INSTALL: Once = Once::new();
+
+    extern "C" fn on_sigusr1(_: libc::c_int) {
+        IS_PENDING.store(true, Ordering::SeqCst);
+    }
+
+    /// Install the SIGUSR1 handler once. Interrupted reads and writes are
+    /// restarted, so the signal doesn't fail them.
+    pub fn install() {
+        INSTALL.call_once(|| {
+            // SAFETY: the action is initialized before it is passed, and
+            // the handler only stores to an atomic
+            unsafe {
+                let mut action: libc::sigaction = std::mem::zeroed();
+                action.sa_sigaction = on_sigusr1 as *const () as usize;
+                action.sa_flags = libc::SA_RESTART;
+                libc::sigemptyset(&mut action.sa_mask);
+                libc::sigaction(libc::SIGUSR1, &action, std::ptr::null_mut());
+            }
+        });
+    }
+
+    pub fn is_pending() -> bool {
+        IS_PENDING.load(Ordering::Relaxed)
+    }
+
+    /// Whether the status was asked for since the last call
+    pub fn take() -> bool {
+        IS_PENDING.swap(false, Ordering::SeqCst)
+    }
+}
+
+#[cfg(not(unix))]
+mod signal {
+    pub fn install() {}
+
+    pub fn is_pending() -> bool {
+        false
+    }
+
+    pub fn take() -> bool {
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn durations() {
+        assert_eq!(format_duration(0), "0:00");
+        assert_eq!(format_duration(75), "1:15");
+        assert_eq!(format_duration(3 * 3600 + 5), "3:00:05");
+    }
+
+    #[test]
+    fn lines() {
+        let secs = Duration::from_secs;
+        assert_eq!(line(2048, None, secs(2)), "2.0K 1.0K/s");
+        assert_eq!(
+            line(1024, Some(4096), secs(1)),
+            format!("[{:30}]  25% 1.0K/4.0K 1.0K/s ETA 0:03", "#".repeat(7))
+        );
+        assert!(line(0, Some(10), Duration::ZERO).ends_with("ETA -:--"));
+    }
+}
@@ -0,0 +1,115 @@
+//! Rendering of file names in output and diagnostics, so names with
+//! newlines, control characters or invalid UTF-8 can't corrupt the
+//! terminal or the line structure of the output
+
+use std::borrow::Cow;
+use std::ffi::OsStr;
+
+/// `name` as is, unless it contains control characters or invalid UTF-8,
+/// in which case it is shell-escaped like `'a'$'\n''b'`
+pub fn quote(name: &OsStr) -> Cow<'_, str> {
+    match name.to_str() {
+        Some(s) if !s.chars().any(char::is_control) => Cow::Borrowed(s),
+        _ => Cow::Owned(shell_escape(name)),
+    }
+}
+
+/// `name` in single quotes that a POSIX shell reads back as the same
+/// name, with control characters and invalid UTF-8 in `$'...'` escapes
+pub fn shell_escape(name: &OsStr) -> String {
+    #[derive(PartialEq)]
+    enum State {
+        Unquoted,
+        /// Inside `'...'`
+        Quoted,
+        /// Inside `$'...'`
+        Escaped,
+    }
+    let mut out = String::with_capacity(name.len() + 2);
+    let mut state = State::Unquoted;
+    let mut enter = |out: &mut String, next: State| {
+        if state != next {
+            if state != State::Unquoted {
+                out.push('\'');
+            }
+            match next {
+                State::Quoted => out.push('\''),
+                State::Escaped => out.push_str("$'"),
+                State::Unquoted => (),
+            }
+            state = next;
+        }
+    };
+    for chunk in name.as_encoded_bytes().utf8_chunks() {
+        for c in chunk.valid().chars() {
+            match c {
+                '\'' => {
+                    enter(&mut out, State::Unquoted);
+                    out.push_str("\\'");
+                }
+                c if c.is_control() => {
+                    enter(&mut out, State::Escaped);
+                    push_escape(&mut out, c);
+                }
+                c => {
+                    enter(&mut out, State::Quoted);
+                    out.push(c);
+                }
+            }
+        }
+        for &b in chunk.invalid() {
+            enter(&mut out, State::Escaped);
+            out.push_str(&format!("\\{b:03o}"));
+        }
+    }
+    if name.is_empty() {
+        out.push_str("''");
+    }
+    enter(&mut out, State::Unquoted);
+    out
+}
+
+/// `name` with C-style backslash escapes for backslashes, control
+/// characters and invalid UTF-8, like `a\nb`
+pub fn escape(name: &OsStr) -> Cow<'_, str> {
+    if let Some(s) = name.to_str() {
+        if !s.chars().any(|c| c == '\\' || c.is_control()) {
+            return Cow::Borrowed(s);
+        }
+    }
+    let mut out = String::with_capacity(name.len() + 2);
+    for chunk in name.as_encoded_bytes().utf8_chunks() {
+        for c in chunk.valid().chars() {
+            match c {
+                '\\' => out.push_str("\\\\"),
+                c if c.is_control() => push_escape(&mut out, c),
+                c => out.push(c),
+            }
+        }
+        for &b in chunk.invalid() {
+            out.push_str(&format!("\\{b:03o}"));
+        }
+    }
+    Cow::Owned(out)
+}
+
+/// Append the backslash escape of the control character `c`
+fn push_escape(out: &mut String, c: char) {
+    match c {
+        '\x07' => out.push_str("\\a"),
+        '\x08' => out.push_str("\\b"),
+        '\t' => out.push_str("\\t"),
+        '\n' => out.push_str("\\n"),
+        '\x0b' => out.push_str("\\v"),
+        '\x0c' => out.push_str("\\f"),
+        '\r' => out.push_str("\\r"),
+        c if c.is_ascii() => out.push_str(&format!("\\{:03o}", c as u32)),
+        // C1 controls, escaped as their UTF-8 bytes
+        c => {
+            let mut buf = [0; 4];
+            for b in c.encode_utf8(&mut buf).bytes() {
+                out.push_str(&format!("\\{b:03o}"));
+            }
+        }
+    }
+}
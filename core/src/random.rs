@@ -0,0 +1,148 @@
+//! Random bytes of the utilities that shuffle, like `sort -R`: from the
+//! entropy of the system, from a generator seeded by `--seed` so that a
+//! shuffle can be reproduced, or read from a `--random-source` file like
+//! coreutils reads them
+
+use std::collections::hash_map::RandomState;
+use std::hash::BuildHasher;
+use std::io::{self, Read};
+
+/// A source of random bytes
+pub enum Random {
+    /// The state of a xoshiro256** generator
+    Generator([u64; 4]),
+    /// Bytes read in order, running out at the end of the source
+    Source(Box<dyn Read>),
+}
+
+/// The next output of the SplitMix64 generator of the state `state`,
+/// which seeds xoshiro256**
+fn split_mix(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+impl Random {
+    /// A generator seeded from the entropy the system gives hashers
+    pub fn new() -> Self {
+        let hasher = RandomState::new();
+        Random::with_seed(&hasher.hash_one(0u8).to_le_bytes())
+    }
+
+    /// A generator seeded with `seed`, giving the same bytes for the same
+    /// seed on every platform
+    pub fn with_seed(seed: &[u8]) -> Self {
+        let mut mix = seed.len() as u64;
+        for chunk in seed.chunks(8) {
+            let mut word = [0; 8];
+            word[..chunk.len()].copy_from_slice(chunk);
+            mix ^= u64::from_le_bytes(word);
+            mix = split_mix(&mut mix);
+        }
+        Random::Generator([(); 4].map(|()| split_mix(&mut mix)))
+    }
+
+    /// The bytes of `source`, like those of a `--random-source` file
+    pub fn from_reader(source: impl Read + 'static) -> Self {
+        Random::Source(Box::new(source))
+    }
+
+    /// The next 64 bits of a generator
+    fn next_u64(state: &mut [u64; 4]) -> u64 {
+        let result = state[1].wrapping_mul(5).rotate_left(7).wrapping_mul(9);
+        let t = state[1] << 17;
+        state[2] ^= state[0];
+        state[3] ^= state[1];
+        state[1] ^= state[2];
+        state[0] ^= state[3];
+        state[2] ^= t;
+        state[3] = state[3].rotate_left(45);
+        result
+    }
+
+    /// Fill `buf` with random bytes. Only a source fails, with
+    /// `UnexpectedEof` once it runs out.
+    pub fn fill(&mut self, buf: &mut [u8]) -> io::Result<()> {
+        match self {
+            Random::Generator(state) => {
+                for chunk in buf.chunks_mut(8) {
+                    let bytes = Random::next_u64(state).to_le_bytes();
+                    chunk.copy_from_slice(&bytes[..chunk.len()]);
+                }
+                Ok(())
+            }
+            Random::Source(source) => source.read_exact(buf),
+        }
+    }
+
+    /// A number below `n`, which isn't 0, every one as likely, for
+    /// picking what comes next in a shuffle
+    pub fn below(&mut self, n: u64) -> io::Result<u64> {
+        // Draws in the last partial run of `n` values would favor the
+        // lowest ones
+        let limit = u64::MAX - (u64::MAX % n + 1) % n;
+        loop {
+            let mut bytes = [0; 8];
+            self.fill(&mut bytes)?;
+            let draw = u64::from_le_bytes(bytes);
+            if draw <= limit {
+                return Ok(draw % n);
+            }
+        }
+    }
+}
+
+impl Default for Random {
+    fn default() -> Self {
+        Random::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bytes(random: &mut Random) -> [u8; 20] {
+        let mut buf = [0; 20];
+        random.fill(&mut buf).unwrap();
+        buf
+    }
+
+    #[test]
+    fn seeds_reproduce() {
+        let first = bytes(&mut Random::with_seed(b"seed"));
+        assert_eq!(first, bytes(&mut Random::with_seed(b"seed")));
+        assert_ne!(first, bytes(&mut Random::with_seed(b"seed2")));
+        assert_ne!(first, bytes(&mut Random::with_seed(b"")));
+        assert_ne!(bytes(&mut Random::new()), bytes(&mut Random::new()));
+    }
+
+    #[test]
+    fn sources_run_out() {
+        let mut random = Random::from_reader(&b"0123456789abcdefXY"[..]);
+        let mut salt = [0; 16];
+        random.fill(&mut salt).unwrap();
+        assert_eq!(&salt, b"0123456789abcdef");
+        let err = random.fill(&mut salt).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn below_is_in_range() {
+        let mut random = Random::with_seed(b"below");
+        let mut seen = [false; 6];
+        for _ in 0..200 {
+            seen[random.below(6).unwrap() as usize] = true;
+        }
+        assert!(seen.iter().all(|&seen| seen));
+        assert_eq!(random.below(1).unwrap(), 0);
+        let mut random = Random::from_reader(&[0xff; 8][..]);
+        assert_eq!(
+            random.below(u64::MAX).unwrap_err().kind(),
+            io::ErrorKind::UnexpectedEof
+        );
+    }
+}
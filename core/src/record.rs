@@ -0,0 +1,96 @@
+//! Reading of input as raw byte records, lines or NUL-terminated, without
+//! any conversion to UTF-8
+
+use std::io::{self, BufRead};
+
+/// What terminates a record
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Terminator {
+    #[default]
+    Newline,
+    /// `\r\n`, a lone `\n` doesn't end a record
+    CrLf,
+    Nul,
+}
+
+impl Terminator {
+    /// Terminator of lines, or of NUL-terminated records for `-z`
+    pub fn line(zero_terminated: bool) -> Self {
+        if zero_terminated {
+            Terminator::Nul
+        } else {
+            Terminator::Newline
+        }
+    }
+
+    /// The terminator as written at the end of a record
+    pub fn as_bytes(self) -> &'static [u8] {
+        match self {
+            Terminator::Newline => b"\n",
+            Terminator::CrLf => b"\r\n",
+            Terminator::Nul => b"\0",
+        }
+    }
+
+    /// `record` without its terminator, if it has one
+    pub fn strip(self, record: &[u8]) -> &[u8] {
+        record.strip_suffix(self.as_bytes()).unwrap_or(record)
+    }
+}
+
+/// Reads records including their terminator. The last record lacks it when
+/// the input doesn't end with one.
+///
+/// ```
+/// use nyaa_core::record::{Records, Terminator};
+///
+/// let mut records = Records::new(&b"a\r\nb\nc"[..], Terminator::CrLf);
+/// assert_eq!(records.next_record()?, Some(&b"a\r\n"[..]));
+/// assert_eq!(records.next_record()?, Some(&b"b\nc"[..]));
+/// assert_eq!(records.next_record()?, None);
+/// # Ok::<(), std::io::Error>(())
+/// ```
+pub struct Records<R> {
+    reader: R,
+    terminator: Terminator,
+    buf: Vec<u8>,
+}
+
+impl<R: BufRead> Records<R> {
+    pub fn new(reader: R, terminator: Terminator) -> Self {
+        Records {
+            reader,
+            terminator,
+            buf: Vec::new(),
+        }
+    }
+
+    /// The next record, valid until the following call, or `None` at the
+    /// end of the input
+    pub fn next_record(&mut self) -> Result<Option<&[u8]>, io::Error> {
+        self.buf.clear();
+        let last = *self.terminator.as_bytes().last().unwrap_or(&b'\n');
+        loop {
+            if self.reader.read_until(last, &mut self.buf)? == 0
+                || self.buf.ends_with(self.terminator.as_bytes())
+            {
+                break;
+            }
+        }
+        Ok((!self.buf.is_empty()).then_some(self.buf.as_slice()))
+    }
+
+    pub fn terminator(&self) -> Terminator {
+        self.terminator
+    }
+
+    /// The reader, at the start of the next record, for skipping records
+    /// without reading them one at a time
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.reader
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
@@ -0,0 +1,101 @@
+//! Response files: an `@FILE` argument stands for the arguments listed in
+//! FILE, for argument lists longer than the system allows or generated by
+//! other programs. Utilities opt in by passing their arguments through
+//! [`expand`] before parsing them.
+//!
+//! Every line of a response file is one argument, taken as is, and empty
+//! lines are skipped. A line in double quotes may contain the escapes
+//! `\"`, `\\`, `\n`, `\t` and `\r`, and one in single quotes none, so
+//! arguments can have leading or trailing blanks or be empty. Response
+//! files don't nest, `@@` stands for a literal `@`, and arguments after
+//! `--` are left alone.
+
+use crate::error::{UError, UResult};
+use crate::platform::os_string;
+use crate::quote::quote;
+use std::ffi::{OsStr, OsString};
+use std::fs;
+use std::path::Path;
+
+/// The argument of `line`, unquoted
+fn parse_line(line: &[u8]) -> Option<Vec<u8>> {
+    match line.first() {
+        Some(b'\'') => {
+            let rest = &line[1..];
+            let end = rest.iter().position(|&b| b == b'\'')?;
+            (end + 1 == rest.len()).then(|| rest[..end].to_vec())
+        }
+        Some(b'"') => {
+            let mut arg = Vec::with_capacity(line.len());
+            let mut bytes = line[1..].iter();
+            while let Some(&b) = bytes.next() {
+                match b {
+                    b'"' => return bytes.as_slice().is_empty().then_some(arg),
+                    b'\\' => arg.push(match bytes.next()? {
+                        b'n' => b'\n',
+                        b't' => b'\t',
+                        b'r' => b'\r',
+                        &b @ (b'"' | b'\\') => b,
+                        _ => return None,
+                    }),
+                    b => arg.push(b),
+                }
+            }
+            None
+        }
+        _ => Some(line.to_vec()),
+    }
+}
+
+/// The arguments listed in the response file `path`
+fn read(path: &Path) -> UResult<Vec<OsString>> {
+    let data = fs::read(path).map_err(|e| UError::from(e).with_name(quote(path.as_os_str())))?;
+    let mut args = Vec::new();
+    for (number, line) in data.split(|&b| b == b'\n').enumerate() {
+        let line = line.strip_suffix(b"\r").unwrap_or(line);
+        if line.is_empty() {
+            continue;
+        }
+        let arg = parse_line(line).ok_or_else(|| {
+            UError::usage("unterminated or invalid quoting").with_name(format!(
+                "{}:{}",
+                quote(path.as_os_str()),
+                number + 1
+            ))
+        })?;
+        args.push(os_string(arg));
+    }
+    Ok(args)
+}
+
+/// `args`, the first being the program name, with every `@FILE` argument
+/// replaced by the arguments in FILE
+pub fn expand(args: Vec<OsString>) -> UResult<Vec<OsString>> {
+    let mut expanded = Vec::with_capacity(args.len());
+    let mut args = args.into_iter();
+    expanded.extend(args.next());
+    while let Some(arg) = args.next() {
+        if arg == "--" {
+            expanded.push(arg);
+            expanded.extend(args);
+            break;
+        }
+        let Some(rest) = arg
+            .as_encoded_bytes()
+            .strip_prefix(b"@")
+            .filter(|rest| !rest.is_empty())
+        else {
+            expanded.push(arg);
+            continue;
+        };
+        // SAFETY: the bytes come from an OsStr and are split right after
+        // an ASCII `@`
+        let rest = unsafe { OsStr::from_encoded_bytes_unchecked(rest) };
+        if rest.as_encoded_bytes().starts_with(b"@") {
+            expanded.push(rest.to_os_string());
+        } else {
+            expanded.extend(read(Path::new(rest))?);
+        }
+    }
+    Ok(expanded)
+}
@@ -0,0 +1,84 @@
+//! Reading of seekable files from their end toward their start, for `tac`
+//! and `tail`, which would otherwise read a whole file to get to its last
+//! lines. The data read so far is kept in one buffer, which every read
+//! extends at its front.
+
+use std::io::{self, Read, Seek, SeekFrom};
+
+/// Smallest number of bytes read at a time
+pub const BLOCK_SIZE: usize = 64 * 1024;
+
+/// Reader of the data between the offset of a file when the reader was
+/// made and the end of the file, in blocks from the end
+pub struct ReverseReader<R> {
+    inner: R,
+    /// Offset reading backward stops at
+    start: u64,
+    /// Offset of the first byte read so far
+    pos: u64,
+}
+
+impl<R: Read + Seek> ReverseReader<R> {
+    /// Reader of `inner` from its current offset to its end
+    pub fn new(mut inner: R) -> io::Result<Self> {
+        let start = inner.stream_position()?;
+        let end = inner.seek(SeekFrom::End(0))?;
+        Ok(ReverseReader {
+            inner,
+            start,
+            pos: end.max(start),
+        })
+    }
+
+    /// Offset in the file of the first byte read so far
+    pub fn position(&self) -> u64 {
+        self.pos
+    }
+
+    /// Whether everything was read
+    pub fn is_done(&self) -> bool {
+        self.pos == self.start
+    }
+
+    /// Read the data before what was read so far into the front of `buf`,
+    /// which holds the data read so far or a part at its start. Reads a
+    /// block, or as much as `buf` holds if that is more, so prepending to
+    /// a growing buffer takes linear time. Returns the number of bytes
+    /// read, 0 once everything was.
+    pub fn read_before(&mut self, buf: &mut Vec<u8>) -> io::Result<usize> {
+        let len = (BLOCK_SIZE.max(buf.len()) as u64).min(self.pos - self.start) as usize;
+        if len == 0 {
+            return Ok(0);
+        }
+        let pos = self.pos - len as u64;
+        self.inner.seek(SeekFrom::Start(pos))?;
+        let mut data = vec![0; len + buf.len()];
+        self.inner.read_exact(&mut data[..len])?;
+        data[len..].copy_from_slice(buf);
+        *buf = data;
+        self.pos = pos;
+        Ok(len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn reads_backward_from_the_offset() {
+        let data: Vec<u8> = (0..3 * BLOCK_SIZE + 10).map(|i| i as u8).collect();
+        let mut cursor = Cursor::new(&data);
+        cursor.set_position(7);
+        let mut reader = ReverseReader::new(cursor).unwrap();
+        let mut buf = Vec::new();
+        assert_eq!(reader.read_before(&mut buf).unwrap(), BLOCK_SIZE);
+        assert_eq!(reader.read_before(&mut buf).unwrap(), BLOCK_SIZE);
+        // As much as the buffer holds
+        assert_eq!(reader.read_before(&mut buf).unwrap(), BLOCK_SIZE + 3);
+        assert!(reader.is_done());
+        assert_eq!(reader.read_before(&mut buf).unwrap(), 0);
+        assert_eq!(buf, data[7..]);
+    }
+}
@@ -0,0 +1,327 @@
+//! The `--sandbox` option of the utilities that only read files: before
+//! touching any input, the process gives up writing files and the system
+//! calls it doesn't need, so a hostile input exploiting a bug in the
+//! utility can't do much. This is Linux only, with seccomp restricting
+//! the system calls to a small set and files to opening them for reading,
+//! and Landlock, where the kernel has it, restricting the file system to
+//! reading as well.
+
+use crate::error::{UError, UResult};
+
+/// The `--sandbox` option
+#[derive(clap::Args, Clone, Debug, Default)]
+pub struct SandboxArg {
+    /// Restrict the process to reading files and a minimal set of system
+    /// calls before reading any input (Linux only)
+    #[arg(long = "sandbox")]
+    pub is_sandboxed: bool,
+}
+
+impl SandboxArg {
+    /// Enter the sandbox if it was asked for
+    pub fn apply(&self) -> UResult<()> {
+        if self.is_sandboxed {
+            enter()
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// Restrict the process to reading files for the rest of its life. Open
+/// descriptors, like the stdout, stay writable. Threads started before
+/// aren't restricted.
+pub fn enter() -> UResult<()> {
+    sys::enter().map_err(|e| UError::from(e).with_name("sandbox"))
+}
+
+#[cfg(all(
+    target_os = "linux",
+    any(target_arch = "x86_64", target_arch = "aarch64")
+))]
+mod sys {
+    use std::io;
+
+    fn check(result: libc::c_long) -> io::Result<libc::c_long> {
+        if result < 0 {
+            Err(io::Error::last_os_error())
+        } else {
+            Ok(result)
+        }
+    }
+
+    pub fn enter() -> io::Result<()> {
+        // SAFETY: the calls only change the restrictions of the process
+        unsafe {
+            check(libc::prctl(libc::PR_SET_NO_NEW_PRIVS, 1, 0, 0, 0) as libc::c_long)?;
+        }
+        landlock::restrict()?;
+        seccomp::restrict()
+    }
+
+    mod landlock {
+        use super::check;
+        use std::io;
+        use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+
+        const CREATE_RULESET_VERSION: libc::c_uint = 1;
+        const RULE_PATH_BENEATH: libc::c_int = 1;
+
+        const ACCESS_FS_READ_FILE: u64 = 1 << 2;
+        const ACCESS_FS_READ_DIR: u64 = 1 << 3;
+
+        /// The file system rights a version of the ABI knows, from
+        /// executing files to making symbolic links in the first one
+        fn handled_access_fs(abi: libc::c_long) -> u64 {
+            match abi {
+                1 => (1 << 13) - 1,
+                // Linking and renaming across directories
+                2 => (1 << 14) - 1,
+                // Truncating
+                3 | 4 => (1 << 15) - 1,
+                // ioctl(2) on devices
+                _ => (1 << 16) - 1,
+            }
+        }
+
+        #[repr(C)]
+        struct RulesetAttr {
+            handled_access_fs: u64,
+            /// Binding and connecting TCP sockets, from the fourth ABI
+            handled_access_net: u64,
+        }
+
+        #[repr(C, packed)]
+        struct PathBeneathAttr {
+            allowed_access: u64,
+            parent_fd: libc::c_int,
+        }
+
+        /// Allow only reading files and directories, anywhere. Kernels
+        /// without Landlock are left to seccomp.
+        pub fn restrict() -> io::Result<()> {
+            // SAFETY: the calls get attributes of the sizes they are told
+            // and descriptors they own
+            unsafe {
+                let abi = match check(libc::syscall(
+                    libc::SYS_landlock_create_ruleset,
+                    std::ptr::null::<RulesetAttr>(),
+                    0,
+                    CREATE_RULESET_VERSION,
+                )) {
+                    Ok(abi) => abi,
+                    Err(e) if matches!(e.raw_os_error(), Some(libc::ENOSYS | libc::EOPNOTSUPP)) => {
+                        return Ok(())
+                    }
+                    Err(e) => return Err(e),
+                };
+                let attr = RulesetAttr {
+                    handled_access_fs: handled_access_fs(abi),
+                    handled_access_net: if abi >= 4 { 0b11 } else { 0 },
+                };
+                let size = if abi >= 4 {
+                    size_of::<RulesetAttr>()
+                } else {
+                    size_of::<u64>()
+                };
+                let ruleset = check(libc::syscall(
+                    libc::SYS_landlock_create_ruleset,
+                    &attr,
+                    size,
+                    0,
+                ))?;
+                let ruleset = OwnedFd::from_raw_fd(ruleset as libc::c_int);
+                let root = libc::open(c"/".as_ptr(), libc::O_PATH | libc::O_CLOEXEC);
+                check(root as libc::c_long)?;
+                let root = OwnedFd::from_raw_fd(root);
+                let rule = PathBeneathAttr {
+                    allowed_access: ACCESS_FS_READ_FILE | ACCESS_FS_READ_DIR,
+                    parent_fd: root.as_raw_fd(),
+                };
+                check(libc::syscall(
+                    libc::SYS_landlock_add_rule,
+                    ruleset.as_raw_fd(),
+                    RULE_PATH_BENEATH,
+                    &rule,
+                    0,
+                ))?;
+                check(libc::syscall(
+                    libc::SYS_landlock_restrict_self,
+                    ruleset.as_raw_fd(),
+                    0,
+                ))?;
+            }
+            Ok(())
+        }
+    }
+
+    mod seccomp {
+        use super::check;
+        use std::io;
+
+        #[cfg(target_arch = "x86_64")]
+        const AUDIT_ARCH: u32 = 0xc000_003e;
+        #[cfg(target_arch = "aarch64")]
+        const AUDIT_ARCH: u32 = 0xc000_00b7;
+
+        /// System calls of the x32 ABI on x86_64, which the numbers of the
+        /// allowed calls don't cover
+        const X32_SYSCALL_BIT: u32 = 0x4000_0000;
+
+        /// Offsets in `struct seccomp_data`
+        const NR: u32 = 0;
+        const ARCH: u32 = 4;
+        /// Lower half of an argument, on little-endian targets
+        const fn arg(n: u32) -> u32 {
+            16 + 8 * n
+        }
+
+        /// Flags of open(2) that write or make files
+        const WRITE_FLAGS: u32 = (libc::O_WRONLY
+            | libc::O_RDWR
+            | libc::O_CREAT
+            | libc::O_TRUNC
+            | libc::O_APPEND
+            // O_TMPFILE includes O_DIRECTORY
+            | libc::O_TMPFILE & !libc::O_DIRECTORY) as u32;
+
+        /// System calls of reading files, writing to open descriptors,
+        /// memory, threads and signals
+        const ALLOWED: &[libc::c_long] = &[
+            libc::SYS_read,
+            libc::SYS_readv,
+            libc::SYS_pread64,
+            libc::SYS_preadv,
+            libc::SYS_write,
+            libc::SYS_writev,
+            // Copying between open descriptors, like reading and writing
+            libc::SYS_copy_file_range,
+            libc::SYS_splice,
+            libc::SYS_close,
+            libc::SYS_lseek,
+            libc::SYS_fstat,
+            libc::SYS_newfstatat,
+            libc::SYS_statx,
+            libc::SYS_getdents64,
+            libc::SYS_readlinkat,
+            libc::SYS_getcwd,
+            libc::SYS_fcntl,
+            libc::SYS_fadvise64,
+            libc::SYS_ppoll,
+            libc::SYS_mmap,
+            libc::SYS_munmap,
+            libc::SYS_mremap,
+            libc::SYS_mprotect,
+            libc::SYS_madvise,
+            libc::SYS_brk,
+            libc::SYS_futex,
+            libc::SYS_clone,
+            libc::SYS_clone3,
+            libc::SYS_set_robust_list,
+            libc::SYS_rseq,
+            libc::SYS_sched_yield,
+            libc::SYS_sched_getaffinity,
+            libc::SYS_getrandom,
+            libc::SYS_getpid,
+            libc::SYS_gettid,
+            libc::SYS_tgkill,
+            libc::SYS_rt_sigaction,
+            libc::SYS_rt_sigprocmask,
+            libc::SYS_rt_sigreturn,
+            libc::SYS_sigaltstack,
+            libc::SYS_exit,
+            libc::SYS_exit_group,
+            #[cfg(target_arch = "x86_64")]
+            libc::SYS_poll,
+        ];
+
+        fn load(offset: u32) -> libc::sock_filter {
+            libc::sock_filter {
+                code: (libc::BPF_LD | libc::BPF_W | libc::BPF_ABS) as u16,
+                jt: 0,
+                jf: 0,
+                k: offset,
+            }
+        }
+
+        fn jump(op: u32, k: u32, jt: u8, jf: u8) -> libc::sock_filter {
+            libc::sock_filter {
+                code: (libc::BPF_JMP | op | libc::BPF_K) as u16,
+                jt,
+                jf,
+                k,
+            }
+        }
+
+        fn ret(k: u32) -> libc::sock_filter {
+            libc::sock_filter {
+                code: (libc::BPF_RET | libc::BPF_K) as u16,
+                jt: 0,
+                jf: 0,
+                k,
+            }
+        }
+
+        /// Install the filter: system calls outside the set fail with
+        /// EPERM, and so do opening files for writing and ioctl(2) other
+        /// than asking whether a descriptor is a terminal
+        pub fn restrict() -> io::Result<()> {
+            let allow = ret(libc::SECCOMP_RET_ALLOW);
+            let deny = ret(libc::SECCOMP_RET_ERRNO | libc::EPERM as u32);
+            let mut filter = vec![
+                load(ARCH),
+                jump(libc::BPF_JEQ, AUDIT_ARCH, 1, 0),
+                ret(libc::SECCOMP_RET_KILL_PROCESS),
+                load(NR),
+                jump(libc::BPF_JGE, X32_SYSCALL_BIT, 0, 1),
+                deny,
+            ];
+            for &nr in ALLOWED {
+                filter.extend([jump(libc::BPF_JEQ, nr as u32, 0, 1), allow]);
+            }
+            // The arguments are checked last, loading one loses the number
+            filter.extend([
+                jump(libc::BPF_JEQ, libc::SYS_openat as u32, 0, 4),
+                load(arg(2)),
+                jump(libc::BPF_JSET, WRITE_FLAGS, 1, 0),
+                allow,
+                deny,
+                jump(libc::BPF_JEQ, libc::SYS_ioctl as u32, 0, 3),
+                load(arg(1)),
+                jump(libc::BPF_JEQ, libc::TCGETS as u32, 0, 1),
+                allow,
+                deny,
+            ]);
+            let program = libc::sock_fprog {
+                len: filter.len() as libc::c_ushort,
+                filter: filter.as_mut_ptr(),
+            };
+            // SAFETY: the program points to the filter, which the kernel
+            // copies
+            unsafe {
+                check(libc::syscall(
+                    libc::SYS_seccomp,
+                    libc::SECCOMP_SET_MODE_FILTER,
+                    0,
+                    &program,
+                ))?;
+            }
+            Ok(())
+        }
+    }
+}
+
+#[cfg(not(all(
+    target_os = "linux",
+    any(target_arch = "x86_64", target_arch = "aarch64")
+)))]
+mod sys {
+    use std::io;
+
+    pub fn enter() -> io::Result<()> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "not supported on this system",
+        ))
+    }
+}
@@ -0,0 +1,146 @@
+//! Sizes in human readable form and block sizes, with the rounding and
+//! suffixes of coreutils: `-h` scales by powers of 1024 with `K`, `M`,
+//! `G`... suffixes, `--si` by powers of 1000 with `k`, `M`, `G`..., and
+//! values are rounded up, so a size is never shown smaller than it is
+
+use std::str::FromStr;
+
+/// Factor between the units of human readable sizes
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Base {
+    /// Powers of 1024, for `-h`
+    #[default]
+    Iec,
+    /// Powers of 1000, for `--si`
+    Si,
+}
+
+impl Base {
+    fn factor(self) -> u128 {
+        match self {
+            Base::Iec => 1024,
+            Base::Si => 1000,
+        }
+    }
+
+    fn suffixes(self) -> [&'static str; 6] {
+        match self {
+            Base::Iec => ["K", "M", "G", "T", "P", "E"],
+            Base::Si => ["k", "M", "G", "T", "P", "E"],
+        }
+    }
+}
+
+/// `n` with the largest suffix that keeps it at least 1, like `1.5K` or
+/// `213M`: one decimal below 10 and none above, rounded up
+pub fn human(n: u64, base: Base) -> String {
+    let factor = base.factor();
+    let n = u128::from(n);
+    if n < factor {
+        return n.to_string();
+    }
+    let suffixes = base.suffixes();
+    let mut exp = 0;
+    let mut unit = factor;
+    while exp + 1 < suffixes.len() && n >= unit * factor {
+        unit *= factor;
+        exp += 1;
+    }
+    let tenths = (n * 10).div_ceil(unit);
+    if tenths < 100 {
+        return format!("{}.{}{}", tenths / 10, tenths % 10, suffixes[exp]);
+    }
+    let whole = n.div_ceil(unit);
+    // Rounding up reached the next unit, like 1023.5K
+    if whole >= factor && exp + 1 < suffixes.len() {
+        return format!("1.0{}", suffixes[exp + 1]);
+    }
+    format!("{whole}{}", suffixes[exp])
+}
+
+/// Size with an optional multiplier suffix, like `--block-size` takes it:
+/// `K`, `M`, `G`, `T`, `P`, `E` for powers of 1024, also as `KiB`, `MiB`...,
+/// `KB`, `MB`... for powers of 1000 and `b` for 512. A suffix alone stands
+/// for one of its unit, so `M` is 1048576.
+pub fn parse_size(s: &str) -> Option<u64> {
+    let (number, suffix) = s.split_at(s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len()));
+    let number: u64 = match number {
+        "" if suffix.is_empty() => return None,
+        "" => 1,
+        _ => number.parse().ok()?,
+    };
+    let mut chars = suffix.chars();
+    let exp = match chars.next() {
+        None => 0,
+        Some('b') if chars.as_str().is_empty() => return number.checked_mul(512),
+        Some('K' | 'k') => 1,
+        Some('M') => 2,
+        Some('G') => 3,
+        Some('T') => 4,
+        Some('P') => 5,
+        Some('E') => 6,
+        Some(_) => return None,
+    };
+    let factor: u64 = match chars.as_str() {
+        "" | "iB" => 1024,
+        "B" if exp > 0 => 1000,
+        _ => return None,
+    };
+    number.checked_mul(factor.checked_pow(exp)?)
+}
+
+/// Unit sizes are printed in, from `--block-size` or `-h`/`--si`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BlockSize {
+    /// Human readable sizes
+    Human(Base),
+    /// Number of blocks of this many bytes, rounded up
+    Fixed(u64),
+}
+
+impl Default for BlockSize {
+    fn default() -> Self {
+        BlockSize::Fixed(1024)
+    }
+}
+
+impl BlockSize {
+    /// Block size from the environment of `util`: `UTIL_BLOCK_SIZE` (like
+    /// `DU_BLOCK_SIZE`), `BLOCK_SIZE` or `BLOCKSIZE`, 1024 when none is set
+    /// to a valid size
+    pub fn from_env(util: &str) -> Self {
+        [
+            format!("{}_BLOCK_SIZE", util.to_ascii_uppercase()),
+            "BLOCK_SIZE".to_string(),
+            "BLOCKSIZE".to_string(),
+        ]
+        .iter()
+        .filter_map(|var| std::env::var(var).ok())
+        .find_map(|value| value.parse().ok())
+        .unwrap_or_default()
+    }
+
+    /// `bytes` in this unit
+    pub fn format(self, bytes: u64) -> String {
+        match self {
+            BlockSize::Human(base) => human(bytes, base),
+            BlockSize::Fixed(size) => bytes.div_ceil(size).to_string(),
+        }
+    }
+}
+
+impl FromStr for BlockSize {
+    type Err = String;
+
+    /// A size for [`parse_size`], or `human-readable` or `si`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "human-readable" => Ok(BlockSize::Human(Base::Iec)),
+            "si" => Ok(BlockSize::Human(Base::Si)),
+            _ => parse_size(s)
+                .filter(|&size| size > 0)
+                .map(BlockSize::Fixed)
+                .ok_or_else(|| format!("invalid block size '{s}'")),
+        }
+    }
+}
@@ -0,0 +1,358 @@
+//! Sparse files: finding the holes of a file, so copies skip them instead
+//! of writing zeros, and writing zeros as holes, for `cp --sparse`,
+//! `dd conv=sparse` and `truncate`. Disk images full of unallocated space
+//! then stay as small on disk as the originals.
+//!
+//! Holes are found with `SEEK_DATA` and `SEEK_HOLE` where the system has
+//! them. Elsewhere, and on file systems without holes, a file is a single
+//! data region, and [`SparseWriter`] still seeks over zeros, which most
+//! file systems store as holes anyway.
+
+use clap::ValueEnum;
+use std::fs;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::ops::Range;
+
+/// Blocks of zeros of this size, aligned to it, are written as holes
+pub const BLOCK_SIZE: usize = 4096;
+
+/// `--sparse=WHEN` of `cp`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum Sparse {
+    /// Keep the holes of sparse files
+    #[default]
+    Auto,
+    /// Make holes of all blocks of zeros
+    Always,
+    /// Write every byte
+    Never,
+}
+
+/// The regions of `file` holding data, in order. The offset of `file` is
+/// kept.
+pub fn data_regions(file: &mut fs::File) -> io::Result<Vec<Range<u64>>> {
+    let len = file.metadata()?.len();
+    let pos = file.stream_position()?;
+    let regions = sys::data_regions(file, len);
+    file.seek(SeekFrom::Start(pos))?;
+    regions
+}
+
+/// Whether `metadata` is of a file with fewer blocks allocated than its
+/// size takes, which is likely sparse
+pub fn is_sparse(metadata: &fs::Metadata) -> bool {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        metadata.is_file() && metadata.blocks().saturating_mul(512) < metadata.len()
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = metadata;
+        false
+    }
+}
+
+/// Deallocate `range` of `file`, so it reads as zeros without taking
+/// space, failing with [`io::ErrorKind::Unsupported`] where that can't be
+/// done
+pub fn punch_hole(file: &fs::File, range: Range<u64>) -> io::Result<()> {
+    sys::punch_hole(file, range)
+}
+
+/// Writer of a file that seeks over the blocks of zeros rather than
+/// writing them, leaving holes. Call [`SparseWriter::finish`] at the end,
+/// so a hole at the end makes it into the size of the file.
+pub struct SparseWriter {
+    file: fs::File,
+    /// Offset of the next byte written
+    pos: u64,
+    /// Whether the file offset lags behind `pos` after a hole
+    is_seek_pending: bool,
+    is_punching: bool,
+    /// Hole skipped since the last data, to punch if `is_punching`
+    hole: Option<Range<u64>>,
+}
+
+impl SparseWriter {
+    /// Writer of `file` from its current offset, which has nothing at the
+    /// holes to write yet, like a new or truncated file
+    pub fn new(mut file: fs::File) -> io::Result<Self> {
+        let pos = file.stream_position()?;
+        Ok(SparseWriter {
+            file,
+            pos,
+            is_seek_pending: false,
+            is_punching: false,
+            hole: None,
+        })
+    }
+
+    /// Writer of `file` that punches the holes it writes, for existing
+    /// files, where seeking would leave their data in place
+    pub fn punching(file: fs::File) -> io::Result<Self> {
+        Ok(SparseWriter {
+            is_punching: true,
+            ..SparseWriter::new(file)?
+        })
+    }
+
+    fn skip(&mut self, len: u64) {
+        if len == 0 {
+            return;
+        }
+        let end = self.pos + len;
+        match &mut self.hole {
+            Some(hole) => hole.end = end,
+            None => self.hole = Some(self.pos..end),
+        }
+        self.pos = end;
+        self.is_seek_pending = true;
+    }
+
+    /// Punch the hole skipped so far, or write it as zeros where holes
+    /// can't be punched
+    fn end_hole(&mut self) -> io::Result<()> {
+        let Some(hole) = self.hole.take() else {
+            return Ok(());
+        };
+        if !self.is_punching {
+            return Ok(());
+        }
+        match punch_hole(&self.file, hole.clone()) {
+            Err(e) if e.kind() == io::ErrorKind::Unsupported => {
+                self.file.seek(SeekFrom::Start(hole.start))?;
+                io::copy(
+                    &mut io::repeat(0).take(hole.end - hole.start),
+                    &mut self.file,
+                )?;
+                self.is_seek_pending = false;
+                Ok(())
+            }
+            result => result,
+        }
+    }
+
+    /// Give the file the size of what was written and return it
+    pub fn finish(mut self) -> io::Result<fs::File> {
+        self.end_hole()?;
+        if self.file.metadata()?.len() < self.pos {
+            self.file.set_len(self.pos)?;
+        }
+        self.file.seek(SeekFrom::Start(self.pos))?;
+        Ok(self.file)
+    }
+}
+
+impl Write for SparseWriter {
+    /// Write up to the end of the block at the current offset
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let in_block = BLOCK_SIZE - (self.pos % BLOCK_SIZE as u64) as usize;
+        let piece = &buf[..buf.len().min(in_block)];
+        // Only whole blocks, partial ones are allocated anyway
+        if piece.len() == BLOCK_SIZE && piece.iter().all(|&b| b == 0) {
+            self.skip(BLOCK_SIZE as u64);
+            return Ok(BLOCK_SIZE);
+        }
+        self.end_hole()?;
+        if self.is_seek_pending {
+            self.file.seek(SeekFrom::Start(self.pos))?;
+            self.is_seek_pending = false;
+        }
+        let n = self.file.write(piece)?;
+        self.pos += n as u64;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+/// Copy the rest of `from` to `to` from their offsets, the holes as
+/// `sparse` says, and return the number of bytes copied with `to`. `to`
+/// is expected to be empty from its offset on, like a new file.
+pub fn copy(from: &mut fs::File, to: fs::File, sparse: Sparse) -> io::Result<(u64, fs::File)> {
+    let start = from.stream_position()?;
+    let mut to = match sparse {
+        Sparse::Never => {
+            let mut to = to;
+            let n = io::copy(from, &mut to)?;
+            return Ok((n, to));
+        }
+        Sparse::Auto if !is_sparse(&from.metadata()?) => {
+            let mut to = to;
+            let n = io::copy(from, &mut to)?;
+            return Ok((n, to));
+        }
+        Sparse::Auto | Sparse::Always => SparseWriter::new(to)?,
+    };
+    let end = from.metadata()?.len().max(start);
+    let regions = match sparse {
+        Sparse::Always => std::iter::once(start..end).collect(),
+        _ => data_regions(from)?,
+    };
+    // Offset in `to` of the start of `from`
+    let base = to.pos;
+    for region in regions {
+        let region = region.start.max(start)..region.end;
+        if region.is_empty() {
+            continue;
+        }
+        to.skip(base + (region.start - start) - to.pos);
+        from.seek(SeekFrom::Start(region.start))?;
+        io::copy(&mut from.take(region.end - region.start), &mut to)?;
+    }
+    to.skip(base + (end - start) - to.pos);
+    from.seek(SeekFrom::Start(end))?;
+    Ok((end - start, to.finish()?))
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+mod sys {
+    use std::fs;
+    use std::io;
+    use std::ops::Range;
+    use std::os::fd::AsRawFd;
+
+    fn seek(file: &fs::File, offset: u64, whence: libc::c_int) -> io::Result<Option<u64>> {
+        // SAFETY: only the offset of the open descriptor changes
+        match unsafe { libc::lseek(file.as_raw_fd(), offset as libc::off_t, whence) } {
+            -1 => match io::Error::last_os_error() {
+                // Nothing of the kind after the offset
+                e if e.raw_os_error() == Some(libc::ENXIO) => Ok(None),
+                e => Err(e),
+            },
+            offset => Ok(Some(offset as u64)),
+        }
+    }
+
+    pub fn data_regions(file: &fs::File, len: u64) -> io::Result<Vec<Range<u64>>> {
+        let mut regions = Vec::new();
+        let mut pos = 0;
+        while pos < len {
+            let start = match seek(file, pos, libc::SEEK_DATA) {
+                Ok(Some(start)) => start,
+                Ok(None) => break,
+                // A file system without holes
+                Err(e) if e.raw_os_error() == Some(libc::EINVAL) => {
+                    return Ok(std::iter::once(0..len).collect())
+                }
+                Err(e) => return Err(e),
+            };
+            let end = seek(file, start, libc::SEEK_HOLE)?.unwrap_or(len).min(len);
+            regions.push(start..end);
+            pos = end;
+        }
+        Ok(regions)
+    }
+
+    pub fn punch_hole(file: &fs::File, range: Range<u64>) -> io::Result<()> {
+        let mode = libc::FALLOC_FL_PUNCH_HOLE | libc::FALLOC_FL_KEEP_SIZE;
+        // SAFETY: only the blocks of the open descriptor change
+        let result = unsafe {
+            libc::fallocate(
+                file.as_raw_fd(),
+                mode,
+                range.start as libc::off_t,
+                (range.end - range.start) as libc::off_t,
+            )
+        };
+        match result {
+            0 => Ok(()),
+            _ => match io::Error::last_os_error() {
+                e if e.raw_os_error() == Some(libc::EOPNOTSUPP) => {
+                    Err(io::Error::new(io::ErrorKind::Unsupported, e))
+                }
+                e => Err(e),
+            },
+        }
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+mod sys {
+    use std::fs;
+    use std::io;
+    use std::ops::Range;
+
+    pub fn data_regions(_file: &fs::File, len: u64) -> io::Result<Vec<Range<u64>>> {
+        Ok(std::iter::once(0..len)
+            .filter(|range| !range.is_empty())
+            .collect())
+    }
+
+    pub fn punch_hole(_file: &fs::File, _range: Range<u64>) -> io::Result<()> {
+        Err(io::ErrorKind::Unsupported.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_file(name: &str) -> (std::path::PathBuf, fs::File) {
+        let path = std::env::temp_dir().join(format!("nyaa-sparse-{}-{name}", std::process::id()));
+        let file = fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&path)
+            .unwrap();
+        (path, file)
+    }
+
+    /// Data, a hole of three blocks, data, and a hole at the end
+    fn contents() -> Vec<u8> {
+        let mut data = vec![1; 100];
+        data.resize(BLOCK_SIZE * 4 + 10, 0);
+        data.extend_from_slice(&[2; 10]);
+        data.resize(BLOCK_SIZE * 8, 0);
+        data
+    }
+
+    #[test]
+    fn writer_and_copy() {
+        let (path, file) = temp_file("from");
+        let mut writer = SparseWriter::new(file).unwrap();
+        writer.write_all(&contents()).unwrap();
+        let mut from = writer.finish().unwrap();
+        assert_eq!(fs::read(&path).unwrap(), contents());
+        // On file systems with holes, the data is in the blocks written
+        let regions = data_regions(&mut from).unwrap();
+        assert!(regions
+            .iter()
+            .all(|region| region.end <= BLOCK_SIZE as u64 * 8));
+        assert!(regions.iter().any(|region| region.contains(&0)));
+        assert!(regions
+            .iter()
+            .any(|region| region.contains(&(BLOCK_SIZE as u64 * 4 + 10))));
+
+        for sparse in [Sparse::Auto, Sparse::Always, Sparse::Never] {
+            let (copy_path, to) = temp_file("to");
+            from.seek(SeekFrom::Start(0)).unwrap();
+            let (n, _) = copy(&mut from, to, sparse).unwrap();
+            assert_eq!(n, contents().len() as u64);
+            assert_eq!(fs::read(&copy_path).unwrap(), contents(), "{sparse:?}");
+            fs::remove_file(copy_path).unwrap();
+        }
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn punching_writer() {
+        let (path, mut file) = temp_file("punch");
+        file.write_all(&vec![7; BLOCK_SIZE * 3]).unwrap();
+        file.seek(SeekFrom::Start(0)).unwrap();
+        let mut writer = SparseWriter::punching(file).unwrap();
+        writer.write_all(&[1; 10]).unwrap();
+        writer.write_all(&vec![0; BLOCK_SIZE * 2 - 10]).unwrap();
+        writer.finish().unwrap();
+        let mut expected = vec![1; 10];
+        expected.resize(BLOCK_SIZE * 2, 0);
+        expected.resize(BLOCK_SIZE * 3, 7);
+        assert_eq!(fs::read(&path).unwrap(), expected);
+        fs::remove_file(path).unwrap();
+    }
+}
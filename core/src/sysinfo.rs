@@ -0,0 +1,365 @@
+//! System information for `uptime`, `who`, `users`, `nproc` and `free`:
+//! the uptime, load averages and memory of `/proc`, the CPUs the process
+//! may run on and the login sessions in the utmp file.
+//!
+//! The files are read through a [`Source`], the real ones by [`Live`], so
+//! the parsers can be fed any contents. Missing fields and malformed
+//! lines fail with [`io::ErrorKind::InvalidData`] naming the file.
+
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::time::Duration;
+
+pub const UPTIME: &str = "/proc/uptime";
+pub const LOADAVG: &str = "/proc/loadavg";
+pub const MEMINFO: &str = "/proc/meminfo";
+pub const STATUS: &str = "/proc/self/status";
+pub const UTMP: &str = "/var/run/utmp";
+
+/// Where the system information files are read from
+pub trait Source {
+    fn read(&self, path: &str) -> io::Result<Vec<u8>>;
+}
+
+/// The files of the running system
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Live;
+
+impl Source for Live {
+    fn read(&self, path: &str) -> io::Result<Vec<u8>> {
+        fs::read(path)
+    }
+}
+
+/// Files given by their contents, the ones missing fail with
+/// [`io::ErrorKind::NotFound`]
+impl Source for HashMap<&str, Vec<u8>> {
+    fn read(&self, path: &str) -> io::Result<Vec<u8>> {
+        self.get(path)
+            .cloned()
+            .ok_or_else(|| io::ErrorKind::NotFound.into())
+    }
+}
+
+fn invalid(path: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("invalid {path}"))
+}
+
+fn read_text(source: &impl Source, path: &str) -> io::Result<String> {
+    String::from_utf8(source.read(path)?).map_err(|_| invalid(path))
+}
+
+/// `/proc/uptime`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Uptime {
+    /// Time since the boot
+    pub up: Duration,
+    /// Time the CPUs were idle, summed over them
+    pub idle: Duration,
+}
+
+impl Uptime {
+    pub fn parse(text: &str) -> Option<Self> {
+        let mut fields = text.split_ascii_whitespace();
+        let mut seconds = || Duration::try_from_secs_f64(fields.next()?.parse().ok()?).ok();
+        Some(Uptime {
+            up: seconds()?,
+            idle: seconds()?,
+        })
+    }
+
+    pub fn read(source: &impl Source) -> io::Result<Self> {
+        Uptime::parse(&read_text(source, UPTIME)?).ok_or_else(|| invalid(UPTIME))
+    }
+}
+
+/// `/proc/loadavg`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LoadAverage {
+    /// Average numbers of runnable tasks over 1, 5 and 15 minutes
+    pub averages: [f64; 3],
+    pub running: u32,
+    /// Number of tasks, threads included
+    pub tasks: u32,
+    /// ID of the most recently started process
+    pub last_pid: u32,
+}
+
+impl LoadAverage {
+    pub fn parse(text: &str) -> Option<Self> {
+        let fields: Vec<&str> = text.split_ascii_whitespace().collect();
+        let [one, five, fifteen, tasks, last_pid] = fields[..] else {
+            return None;
+        };
+        let (running, tasks) = tasks.split_once('/')?;
+        Some(LoadAverage {
+            averages: [one.parse().ok()?, five.parse().ok()?, fifteen.parse().ok()?],
+            running: running.parse().ok()?,
+            tasks: tasks.parse().ok()?,
+            last_pid: last_pid.parse().ok()?,
+        })
+    }
+
+    pub fn read(source: &impl Source) -> io::Result<Self> {
+        LoadAverage::parse(&read_text(source, LOADAVG)?).ok_or_else(|| invalid(LOADAVG))
+    }
+}
+
+/// `/proc/meminfo`, the sizes in bytes by their names there
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MemInfo {
+    fields: HashMap<String, u64>,
+}
+
+impl MemInfo {
+    /// The fields of lines like `MemTotal: 8048576 kB`, the sizes in kB
+    /// and the counts, like `HugePages_Total`, without a unit
+    pub fn parse(text: &str) -> Option<Self> {
+        let mut fields = HashMap::new();
+        for line in text.lines().filter(|line| !line.trim().is_empty()) {
+            let (name, value) = line.split_once(':')?;
+            let value = match value.split_ascii_whitespace().collect::<Vec<_>>()[..] {
+                [number] => number.parse().ok()?,
+                [number, "kB"] => number.parse::<u64>().ok()?.checked_mul(1024)?,
+                _ => return None,
+            };
+            fields.insert(name.to_string(), value);
+        }
+        Some(MemInfo { fields })
+    }
+
+    pub fn read(source: &impl Source) -> io::Result<Self> {
+        MemInfo::parse(&read_text(source, MEMINFO)?).ok_or_else(|| invalid(MEMINFO))
+    }
+
+    /// The field `name`, like `MemFree`
+    pub fn get(&self, name: &str) -> Option<u64> {
+        self.fields.get(name).copied()
+    }
+
+    pub fn total(&self) -> u64 {
+        self.get("MemTotal").unwrap_or(0)
+    }
+
+    pub fn free(&self) -> u64 {
+        self.get("MemFree").unwrap_or(0)
+    }
+
+    /// Memory available for new programs without swapping, estimated by
+    /// the kernel since Linux 3.14 and by the free memory and caches
+    /// before
+    pub fn available(&self) -> u64 {
+        self.get("MemAvailable").unwrap_or_else(|| {
+            ["MemFree", "Buffers", "Cached"]
+                .iter()
+                .filter_map(|name| self.get(name))
+                .sum()
+        })
+    }
+}
+
+/// Numbers of the CPUs in a list like `0-3,8,10-11`
+pub fn parse_cpu_list(text: &str) -> Option<Vec<usize>> {
+    let mut cpus = Vec::new();
+    for range in text.trim().split(',').filter(|range| !range.is_empty()) {
+        let (first, last): (usize, usize) = match range.split_once('-') {
+            Some((first, last)) => (first.parse().ok()?, last.parse().ok()?),
+            None => (range.parse().ok()?, range.parse().ok()?),
+        };
+        if first > last {
+            return None;
+        }
+        cpus.extend(first..=last);
+    }
+    Some(cpus)
+}
+
+/// Numbers of the CPUs the process may run on, by `Cpus_allowed_list` of
+/// `/proc/self/status`
+pub fn cpu_affinity(source: &impl Source) -> io::Result<Vec<usize>> {
+    read_text(source, STATUS)?
+        .lines()
+        .find_map(|line| line.strip_prefix("Cpus_allowed_list:"))
+        .and_then(parse_cpu_list)
+        .ok_or_else(|| invalid(STATUS))
+}
+
+/// What a utmp record is about, by its `ut_type`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UtmpKind {
+    Empty,
+    RunLevel,
+    BootTime,
+    NewTime,
+    OldTime,
+    InitProcess,
+    LoginProcess,
+    UserProcess,
+    DeadProcess,
+    Accounting,
+}
+
+impl UtmpKind {
+    fn from_raw(kind: i16) -> Option<Self> {
+        Some(match kind {
+            0 => UtmpKind::Empty,
+            1 => UtmpKind::RunLevel,
+            2 => UtmpKind::BootTime,
+            3 => UtmpKind::NewTime,
+            4 => UtmpKind::OldTime,
+            5 => UtmpKind::InitProcess,
+            6 => UtmpKind::LoginProcess,
+            7 => UtmpKind::UserProcess,
+            8 => UtmpKind::DeadProcess,
+            9 => UtmpKind::Accounting,
+            _ => return None,
+        })
+    }
+}
+
+/// A record of the utmp file, with the strings up to their first NUL
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UtmpEntry {
+    pub kind: UtmpKind,
+    pub pid: i32,
+    /// Terminal, relative to `/dev`
+    pub line: String,
+    pub id: String,
+    pub user: String,
+    /// Remote host of a login, or the kernel version of a boot record
+    pub host: String,
+    /// Seconds since the epoch
+    pub time: i64,
+}
+
+impl UtmpEntry {
+    /// Size of a record of the Linux layout, the same on 32-bit and
+    /// 64-bit targets
+    pub const SIZE: usize = 384;
+
+    /// The record at the start of `record`, in the byte order of the
+    /// target
+    pub fn parse(record: &[u8]) -> Option<Self> {
+        let record = record.get(..Self::SIZE)?;
+        let string = |range: std::ops::Range<usize>| {
+            let field = &record[range];
+            let end = field.iter().position(|&b| b == 0).unwrap_or(field.len());
+            String::from_utf8_lossy(&field[..end]).into_owned()
+        };
+        let int = |at: usize| i32::from_ne_bytes(record[at..at + 4].try_into().unwrap());
+        Some(UtmpEntry {
+            kind: UtmpKind::from_raw(i16::from_ne_bytes([record[0], record[1]]))?,
+            pid: int(4),
+            line: string(8..40),
+            id: string(40..44),
+            user: string(44..76),
+            host: string(76..332),
+            // After the exit status and the session
+            time: i64::from(int(340)),
+        })
+    }
+
+    /// Sessions of logged in users
+    pub fn is_user_process(&self) -> bool {
+        self.kind == UtmpKind::UserProcess && !self.user.is_empty()
+    }
+}
+
+/// The records of the utmp file `path`, [`UTMP`] for the current logins
+pub fn utmp_entries(source: &impl Source, path: &str) -> io::Result<Vec<UtmpEntry>> {
+    let data = source.read(path)?;
+    if data.len() % UtmpEntry::SIZE != 0 {
+        return Err(invalid(path));
+    }
+    data.chunks_exact(UtmpEntry::SIZE)
+        .map(|record| UtmpEntry::parse(record).ok_or_else(|| invalid(path)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn source(path: &'static str, contents: &[u8]) -> HashMap<&'static str, Vec<u8>> {
+        HashMap::from([(path, contents.to_vec())])
+    }
+
+    #[test]
+    fn uptime() {
+        let uptime = Uptime::read(&source(UPTIME, b"350735.47 234388.90\n")).unwrap();
+        assert_eq!(uptime.up, Duration::from_millis(350_735_470));
+        assert_eq!(uptime.idle, Duration::from_millis(234_388_900));
+        let err = Uptime::read(&source(UPTIME, b"350735.47\n")).unwrap_err();
+        assert_eq!(err.to_string(), "invalid /proc/uptime");
+    }
+
+    #[test]
+    fn load_average() {
+        let load = LoadAverage::read(&source(LOADAVG, b"0.56 0.69 1.05 2/73 14834\n")).unwrap();
+        assert_eq!(load.averages, [0.56, 0.69, 1.05]);
+        assert_eq!((load.running, load.tasks, load.last_pid), (2, 73, 14834));
+        assert!(LoadAverage::parse("0.56 0.69 1.05 2 14834").is_none());
+    }
+
+    #[test]
+    fn meminfo() {
+        let text = "MemTotal:        6147400 kB\nMemFree:          189536 kB\n\
+                    Buffers:           79004 kB\nCached:          5287712 kB\n\
+                    HugePages_Total:       0\n";
+        let info = MemInfo::parse(text).unwrap();
+        assert_eq!(info.total(), 6_147_400 * 1024);
+        assert_eq!(info.get("HugePages_Total"), Some(0));
+        assert_eq!(info.available(), (189_536 + 79_004 + 5_287_712) * 1024);
+        let info = MemInfo::parse(&format!("{text}MemAvailable: 5488560 kB\n")).unwrap();
+        assert_eq!(info.available(), 5_488_560 * 1024);
+        assert!(MemInfo::parse("MemTotal 6147400 kB\n").is_none());
+    }
+
+    #[test]
+    fn cpu_lists() {
+        assert_eq!(
+            parse_cpu_list("0-3,8,10-11\n"),
+            Some(vec![0, 1, 2, 3, 8, 10, 11])
+        );
+        assert_eq!(parse_cpu_list("0"), Some(vec![0]));
+        assert_eq!(parse_cpu_list("3-1"), None);
+        let status = b"Name:\tnproc\nCpus_allowed:\tf\nCpus_allowed_list:\t0-3\n";
+        assert_eq!(cpu_affinity(&source(STATUS, status)).unwrap(), [0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn utmp() {
+        let mut record = [0; UtmpEntry::SIZE];
+        record[..2].copy_from_slice(&7i16.to_ne_bytes());
+        record[4..8].copy_from_slice(&1234i32.to_ne_bytes());
+        record[8..13].copy_from_slice(b"pts/0");
+        record[40..42].copy_from_slice(b"ts");
+        record[44..49].copy_from_slice(b"alice");
+        record[76..85].copy_from_slice(b"10.0.0.1:");
+        record[340..344].copy_from_slice(&1_700_000_000i32.to_ne_bytes());
+        let mut data = record.to_vec();
+        record[..2].copy_from_slice(&2i16.to_ne_bytes());
+        data.extend_from_slice(&record);
+        let entries = utmp_entries(&source(UTMP, &data), UTMP).unwrap();
+        assert_eq!(
+            entries[0],
+            UtmpEntry {
+                kind: UtmpKind::UserProcess,
+                pid: 1234,
+                line: "pts/0".to_string(),
+                id: "ts".to_string(),
+                user: "alice".to_string(),
+                host: "10.0.0.1:".to_string(),
+                time: 1_700_000_000,
+            }
+        );
+        assert!(entries[0].is_user_process());
+        assert_eq!(entries[1].kind, UtmpKind::BootTime);
+        assert!(utmp_entries(&source(UTMP, &data[1..]), UTMP).is_err());
+        assert_eq!(
+            utmp_entries(&HashMap::new(), UTMP).unwrap_err().kind(),
+            io::ErrorKind::NotFound
+        );
+    }
+}
@@ -0,0 +1,88 @@
+//! Verbose diagnostics, for finding out why a utility is slow or skipped
+//! a file without reaching for strace: which files are opened, how they
+//! are read and how long every operand took. They are enabled by `-v`
+//! of the utilities that have it or by setting `NYAA_DEBUG` to anything
+//! but `0`, and written to the stderr as `UTIL: debug: NAME: MESSAGE`.
+//!
+//! Messages are logged with [`debug!`](crate::debug), which doesn't
+//! format them unless diagnostics are enabled. The operand a message is
+//! about is the one [`operand`] is running for on the same thread.
+
+use std::cell::RefCell;
+use std::fmt;
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::OnceLock;
+use std::time::Instant;
+
+static IS_ENABLED: AtomicBool = AtomicBool::new(false);
+static UTIL: OnceLock<&'static str> = OnceLock::new();
+
+thread_local! {
+    static OPERAND: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+/// The `-v` option
+#[derive(clap::Args, Clone, Debug, Default)]
+pub struct VerboseArg {
+    /// Write to the stderr which files are opened, how they are read and
+    /// how long each took, like NYAA_DEBUG=1
+    #[arg(short = 'v', long = "verbose")]
+    pub is_verbose: bool,
+}
+
+impl VerboseArg {
+    /// Enable diagnostics of `util` if `-v` was given or `NYAA_DEBUG` is
+    /// set
+    pub fn init(&self, util: &'static str) {
+        init(util, self.is_verbose);
+    }
+}
+
+/// Enable diagnostics of `util` if `is_verbose` or `NYAA_DEBUG` is set
+pub fn init(util: &'static str, is_verbose: bool) {
+    let is_debug = std::env::var_os("NYAA_DEBUG").is_some_and(|var| !var.is_empty() && var != "0");
+    let _ = UTIL.set(util);
+    IS_ENABLED.store(is_verbose || is_debug, Ordering::Relaxed);
+}
+
+pub fn is_enabled() -> bool {
+    IS_ENABLED.load(Ordering::Relaxed)
+}
+
+/// Write a diagnostic, use [`debug!`](crate::debug) instead
+pub fn log(message: fmt::Arguments) {
+    let util = UTIL.get().copied().unwrap_or("nyaa");
+    OPERAND.with_borrow(|operand| {
+        // A single write, so lines of parallel operands don't mix
+        let line = match operand {
+            Some(name) => format!("{util}: debug: {name}: {message}\n"),
+            None => format!("{util}: debug: {message}\n"),
+        };
+        let _ = io::stderr().write_all(line.as_bytes());
+    });
+}
+
+/// Run `f` for the operand `name`, which the messages logged meanwhile
+/// are about, and log how long it took
+pub fn operand<R>(name: impl fmt::Display, f: impl FnOnce() -> R) -> R {
+    if !is_enabled() {
+        return f();
+    }
+    let outer = OPERAND.replace(Some(name.to_string()));
+    let start = Instant::now();
+    let result = f();
+    log(format_args!("done in {:.3?}", start.elapsed()));
+    OPERAND.set(outer);
+    result
+}
+
+/// Log a diagnostic formatted like [`format!`] if they are enabled
+#[macro_export]
+macro_rules! debug {
+    ($($arg:tt)*) => {
+        if $crate::trace::is_enabled() {
+            $crate::trace::log(format_args!($($arg)*));
+        }
+    };
+}
@@ -0,0 +1,376 @@
+//! Reading and writing files through io_uring, on Linux with the `uring`
+//! feature: a reader with the next chunk read ahead while the current one
+//! is processed, a writer with the last chunk written behind, and the
+//! heads of many small files read with a single system call.
+//!
+//! Where io_uring is not built in, or the kernel or a sandbox denies it,
+//! the functions fall back to plain reads and writes, so callers use them
+//! unconditionally. Files are read and written from their offsets, which
+//! are left where plain reads and writes would leave them.
+
+use std::fs;
+use std::io::{self, Read, Write};
+
+/// Reader of `file` from its offset, reading ahead through io_uring where
+/// it is available
+pub fn reader(file: &fs::File) -> Box<dyn Read + '_> {
+    #[cfg(all(feature = "uring", target_os = "linux"))]
+    match ring::Reader::new(file) {
+        Ok(reader) => {
+            crate::debug!("reading through io_uring");
+            return Box::new(reader);
+        }
+        Err(e) => crate::debug!("io_uring not available: {e}"),
+    }
+    Box::new(file)
+}
+
+/// Writer of `file` from its offset, writing behind through io_uring
+/// where it is available. Call [`Write::flush`] at the end, errors of
+/// writing behind are only reported by it and the next writes.
+pub fn writer(file: &fs::File) -> Box<dyn Write + '_> {
+    #[cfg(all(feature = "uring", target_os = "linux"))]
+    match ring::Writer::new(file) {
+        Ok(writer) => {
+            crate::debug!("writing through io_uring");
+            return Box::new(writer);
+        }
+        Err(e) => crate::debug!("io_uring not available: {e}"),
+    }
+    Box::new(file)
+}
+
+/// Read up to `len` bytes of each of `files` from their offsets. Files
+/// whose result is `len` bytes long may continue.
+pub fn read_heads(files: &[&fs::File], len: usize) -> Vec<io::Result<Vec<u8>>> {
+    #[cfg(all(feature = "uring", target_os = "linux"))]
+    match ring::read_heads(files, len) {
+        Ok(heads) => return heads,
+        Err(e) => crate::debug!("io_uring not available: {e}"),
+    }
+    files.iter().map(|file| read_head(file, len)).collect()
+}
+
+/// Read up to `len` bytes of `file`
+fn read_head(file: &fs::File, len: usize) -> io::Result<Vec<u8>> {
+    let mut buf = Vec::with_capacity(len);
+    file.take(len as u64).read_to_end(&mut buf)?;
+    Ok(buf)
+}
+
+#[cfg(all(feature = "uring", target_os = "linux"))]
+mod ring {
+    use io_uring::{cqueue, opcode, squeue, types, IoUring};
+    use std::fs;
+    use std::io::{self, Read, Seek, SeekFrom, Write};
+    use std::os::fd::AsRawFd;
+    use std::os::unix::fs::FileExt;
+
+    const CHUNK_SIZE: usize = 256 * 1024;
+
+    /// Files read together by [`read_heads`]
+    const BATCH_SIZE: usize = 64;
+
+    /// Offset standing for the current offset of the file, which is
+    /// advanced by the operation
+    const CURRENT: u64 = u64::MAX;
+
+    /// Queue `entry` and submit the queue, the buffers it points to have
+    /// to outlive the operation
+    unsafe fn submit(ring: &mut IoUring, entry: &squeue::Entry) -> io::Result<()> {
+        // SAFETY: the caller keeps the buffers alive
+        unsafe { ring.submission().push(entry) }
+            .map_err(|_| io::Error::other("io_uring submission queue is full"))?;
+        ring.submit()?;
+        Ok(())
+    }
+
+    /// Wait for the next completion
+    fn complete(ring: &mut IoUring) -> io::Result<cqueue::Entry> {
+        loop {
+            if let Some(cqe) = ring.completion().next() {
+                return Ok(cqe);
+            }
+            match ring.submit_and_wait(1) {
+                Err(e) if e.kind() != io::ErrorKind::Interrupted => return Err(e),
+                _ => (),
+            }
+        }
+    }
+
+    fn length(cqe: &cqueue::Entry) -> io::Result<usize> {
+        match cqe.result() {
+            len @ 0.. => Ok(len as usize),
+            errno => Err(io::Error::from_raw_os_error(-errno)),
+        }
+    }
+
+    pub struct Reader<'a> {
+        ring: IoUring,
+        file: &'a fs::File,
+        /// Chunk being consumed and chunk being read ahead
+        bufs: [Vec<u8>; 2],
+        /// File offset of the start of the chunk being consumed
+        start: u64,
+        pos: usize,
+        len: usize,
+        in_flight: bool,
+    }
+
+    impl<'a> Reader<'a> {
+        /// Start reading `file` ahead, failing when io_uring is not
+        /// available
+        pub fn new(mut file: &'a fs::File) -> io::Result<Self> {
+            let mut reader = Reader {
+                ring: IoUring::new(2)?,
+                start: file.stream_position()?,
+                file,
+                bufs: [vec![0; CHUNK_SIZE], vec![0; CHUNK_SIZE]],
+                pos: 0,
+                len: 0,
+                in_flight: false,
+            };
+            reader.read_ahead()?;
+            Ok(reader)
+        }
+
+        fn read_ahead(&mut self) -> io::Result<()> {
+            let buf = &mut self.bufs[1];
+            let entry = opcode::Read::new(
+                types::Fd(self.file.as_raw_fd()),
+                buf.as_mut_ptr(),
+                buf.len() as u32,
+            )
+            .offset(self.start + self.len as u64)
+            .build();
+            // SAFETY: the buffer is neither touched nor dropped before the
+            // read has completed, `wait` is called first and also on drop
+            unsafe { submit(&mut self.ring, &entry)? };
+            self.in_flight = true;
+            Ok(())
+        }
+
+        /// Wait for the read ahead to complete and return its length
+        fn wait(&mut self) -> io::Result<usize> {
+            let cqe = complete(&mut self.ring);
+            self.in_flight = false;
+            length(&cqe?)
+        }
+    }
+
+    impl Read for Reader<'_> {
+        fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+            if self.pos == self.len {
+                if !self.in_flight {
+                    return Ok(0);
+                }
+                let len = self.wait()?;
+                self.bufs.swap(0, 1);
+                self.start += self.len as u64;
+                (self.pos, self.len) = (0, len);
+                if len > 0 {
+                    self.read_ahead()?;
+                }
+            }
+            let n = out.len().min(self.len - self.pos);
+            out[..n].copy_from_slice(&self.bufs[0][self.pos..self.pos + n]);
+            self.pos += n;
+            Ok(n)
+        }
+    }
+
+    impl Drop for Reader<'_> {
+        /// Leave the offset of the file after the bytes consumed
+        fn drop(&mut self) {
+            if self.in_flight {
+                let _ = self.wait();
+            }
+            let _ = self
+                .file
+                .seek(SeekFrom::Start(self.start + self.pos as u64));
+        }
+    }
+
+    pub struct Writer<'a> {
+        ring: IoUring,
+        file: &'a fs::File,
+        /// Chunk being filled and chunk being written behind
+        bufs: [Vec<u8>; 2],
+        /// File offset of the chunk being filled
+        offset: u64,
+        in_flight: bool,
+    }
+
+    impl<'a> Writer<'a> {
+        /// Start writing `file` behind, failing when io_uring is not
+        /// available
+        pub fn new(mut file: &'a fs::File) -> io::Result<Self> {
+            Ok(Writer {
+                ring: IoUring::new(2)?,
+                offset: file.stream_position()?,
+                file,
+                bufs: [
+                    Vec::with_capacity(CHUNK_SIZE),
+                    Vec::with_capacity(CHUNK_SIZE),
+                ],
+                in_flight: false,
+            })
+        }
+
+        /// Wait for the write behind, and write what it left out
+        fn wait(&mut self) -> io::Result<()> {
+            if !self.in_flight {
+                return Ok(());
+            }
+            let cqe = complete(&mut self.ring);
+            self.in_flight = false;
+            let written = length(&cqe?)?;
+            let buf = &self.bufs[1];
+            let offset = self.offset - buf.len() as u64;
+            self.file
+                .write_all_at(&buf[written..], offset + written as u64)
+        }
+
+        /// Start writing the chunk filled so far
+        fn write_behind(&mut self) -> io::Result<()> {
+            self.wait()?;
+            self.bufs.swap(0, 1);
+            self.bufs[0].clear();
+            let buf = &self.bufs[1];
+            let entry = opcode::Write::new(
+                types::Fd(self.file.as_raw_fd()),
+                buf.as_ptr(),
+                buf.len() as u32,
+            )
+            .offset(self.offset)
+            .build();
+            self.offset += buf.len() as u64;
+            // SAFETY: the buffer is neither touched nor dropped before the
+            // write has completed, `wait` is called first and also on drop
+            unsafe { submit(&mut self.ring, &entry)? };
+            self.in_flight = true;
+            Ok(())
+        }
+    }
+
+    impl Write for Writer<'_> {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            let chunk = &mut self.bufs[0];
+            let n = buf.len().min(CHUNK_SIZE - chunk.len());
+            chunk.extend_from_slice(&buf[..n]);
+            if chunk.len() == CHUNK_SIZE {
+                self.write_behind()?;
+            }
+            Ok(n)
+        }
+
+        /// Write everything and leave the offset of the file after it
+        fn flush(&mut self) -> io::Result<()> {
+            if !self.bufs[0].is_empty() {
+                self.write_behind()?;
+            }
+            self.wait()?;
+            self.file.seek(SeekFrom::Start(self.offset))?;
+            Ok(())
+        }
+    }
+
+    impl Drop for Writer<'_> {
+        fn drop(&mut self) {
+            let _ = self.flush();
+        }
+    }
+
+    pub fn read_heads(files: &[&fs::File], len: usize) -> io::Result<Vec<io::Result<Vec<u8>>>> {
+        let mut ring = IoUring::new(BATCH_SIZE.min(files.len()).max(1) as u32)?;
+        let mut heads = Vec::with_capacity(files.len());
+        for batch in files.chunks(BATCH_SIZE) {
+            let mut bufs: Vec<Vec<u8>> = batch.iter().map(|_| vec![0; len]).collect();
+            for (i, (file, buf)) in batch.iter().zip(&mut bufs).enumerate() {
+                let entry = opcode::Read::new(
+                    types::Fd(file.as_raw_fd()),
+                    buf.as_mut_ptr(),
+                    buf.len() as u32,
+                )
+                .offset(CURRENT)
+                .build()
+                .user_data(i as u64);
+                // SAFETY: the buffers aren't touched before all reads have
+                // completed, `submit` submits only the entries pushed
+                unsafe { ring.submission().push(&entry) }
+                    .map_err(|_| io::Error::other("io_uring submission queue is full"))?;
+            }
+            ring.submit()?;
+            let mut lengths: Vec<Option<io::Result<usize>>> = batch.iter().map(|_| None).collect();
+            for _ in 0..batch.len() {
+                match complete(&mut ring) {
+                    Ok(cqe) => lengths[cqe.user_data() as usize] = Some(length(&cqe)),
+                    Err(e) => {
+                        // Reads may still be writing to the buffers
+                        std::mem::forget(bufs);
+                        return Err(e);
+                    }
+                }
+            }
+            for ((file, mut buf), read) in batch.iter().zip(bufs).zip(lengths) {
+                let head = read.unwrap_or_else(|| Err(io::ErrorKind::Interrupted.into()));
+                heads.push(head.and_then(|n| {
+                    buf.truncate(n);
+                    // A short read isn't necessarily the end
+                    if n > 0 && n < len {
+                        buf.extend(super::read_head(file, len - n)?);
+                    }
+                    Ok(buf)
+                }));
+            }
+        }
+        Ok(heads)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Seek, SeekFrom};
+
+    #[test]
+    fn write_read_and_heads() {
+        let path = std::env::temp_dir().join(format!("nyaa-uring-{}", std::process::id()));
+        let mut file = fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&path)
+            .unwrap();
+        let data: Vec<u8> = (0..1_000_003u32).map(|i| (i % 251) as u8).collect();
+        file.write_all(b"head").unwrap();
+        let mut out = writer(&file);
+        for piece in data.chunks(100_000) {
+            out.write_all(piece).unwrap();
+        }
+        out.flush().unwrap();
+        drop(out);
+        assert_eq!(file.stream_position().unwrap(), 4 + data.len() as u64);
+
+        file.seek(SeekFrom::Start(4)).unwrap();
+        let mut read = Vec::new();
+        let mut input = reader(&file);
+        input.by_ref().take(10).read_to_end(&mut read).unwrap();
+        drop(input);
+        // The offset is after the bytes consumed, not the ones read ahead
+        assert_eq!(file.stream_position().unwrap(), 14);
+        reader(&file).read_to_end(&mut read).unwrap();
+        assert_eq!(read, data);
+
+        let (first, second) = (
+            fs::File::open(&path).unwrap(),
+            fs::File::open(&path).unwrap(),
+        );
+        (&second).seek(SeekFrom::Start(4)).unwrap();
+        let heads = read_heads(&[&first, &second], 6);
+        assert_eq!(heads[0].as_ref().unwrap(), b"head\0\x01");
+        assert_eq!(heads[1].as_ref().unwrap(), &data[..6]);
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,176 @@
+//! Names of users and groups, looked up once per ID, so listing many files
+//! of the same owner doesn't query the user database for each of them.
+//! IDs without a name are shown as their number, and numbers are accepted
+//! where names are expected, like `chown` does.
+
+use std::collections::HashMap;
+
+/// Cache of the user and group databases
+#[derive(Debug, Default)]
+pub struct Users {
+    user_names: HashMap<u32, String>,
+    group_names: HashMap<u32, String>,
+    uids: HashMap<String, Option<u32>>,
+    gids: HashMap<String, Option<u32>>,
+}
+
+/// ID of `name`, which is taken for a number when it has no entry or
+/// starts with `+`
+fn id_of(name: &str, lookup: impl FnOnce(&str) -> Option<u32>) -> Option<u32> {
+    match name.strip_prefix('+') {
+        Some(number) => number.parse().ok(),
+        None => lookup(name).or_else(|| name.parse().ok()),
+    }
+}
+
+impl Users {
+    pub fn new() -> Self {
+        Users::default()
+    }
+
+    /// Name of the user `uid`, its number if it has none
+    pub fn user_name(&mut self, uid: u32) -> &str {
+        self.user_names
+            .entry(uid)
+            .or_insert_with(|| sys::user_name(uid).unwrap_or_else(|| uid.to_string()))
+    }
+
+    /// Name of the group `gid`, its number if it has none
+    pub fn group_name(&mut self, gid: u32) -> &str {
+        self.group_names
+            .entry(gid)
+            .or_insert_with(|| sys::group_name(gid).unwrap_or_else(|| gid.to_string()))
+    }
+
+    /// ID of the user `name`, or of the number `name`
+    pub fn uid(&mut self, name: &str) -> Option<u32> {
+        if let Some(&uid) = self.uids.get(name) {
+            return uid;
+        }
+        let uid = id_of(name, sys::uid);
+        self.uids.insert(name.to_string(), uid);
+        uid
+    }
+
+    /// ID of the group `name`, or of the number `name`
+    pub fn gid(&mut self, name: &str) -> Option<u32> {
+        if let Some(&gid) = self.gids.get(name) {
+            return gid;
+        }
+        let gid = id_of(name, sys::gid);
+        self.gids.insert(name.to_string(), gid);
+        gid
+    }
+}
+
+#[cfg(unix)]
+mod sys {
+    use std::ffi::{CStr, CString};
+    use std::mem::MaybeUninit;
+    use std::os::raw::{c_char, c_int};
+
+    /// Run a reentrant lookup like getpwuid_r(3) with a buffer for the
+    /// strings of the entry, growing it while it's too small. `lookup`
+    /// returns the error code and whether an entry was found.
+    fn with_buffer<T>(mut lookup: impl FnMut(&mut [c_char]) -> (c_int, Option<T>)) -> Option<T> {
+        let mut buf = vec![0 as c_char; 1024];
+        loop {
+            match lookup(&mut buf) {
+                (libc::ERANGE, _) if buf.len() < 1 << 20 => buf.resize(buf.len() * 2, 0),
+                (0, entry) => return entry,
+                _ => return None,
+            }
+        }
+    }
+
+    fn passwd(
+        lookup: impl Fn(*mut libc::passwd, &mut [c_char], *mut *mut libc::passwd) -> c_int,
+    ) -> Option<(String, u32)> {
+        with_buffer(|buf| {
+            let mut entry = MaybeUninit::<libc::passwd>::uninit();
+            let mut result = std::ptr::null_mut();
+            let code = lookup(entry.as_mut_ptr(), buf, &mut result);
+            if code != 0 || result.is_null() {
+                return (code, None);
+            }
+            // SAFETY: a non-null result points to the initialized entry,
+            // whose name points into the buffer
+            let entry = unsafe { entry.assume_init() };
+            let name = unsafe { CStr::from_ptr(entry.pw_name) };
+            (0, Some((name.to_string_lossy().into_owned(), entry.pw_uid)))
+        })
+    }
+
+    fn group(
+        lookup: impl Fn(*mut libc::group, &mut [c_char], *mut *mut libc::group) -> c_int,
+    ) -> Option<(String, u32)> {
+        with_buffer(|buf| {
+            let mut entry = MaybeUninit::<libc::group>::uninit();
+            let mut result = std::ptr::null_mut();
+            let code = lookup(entry.as_mut_ptr(), buf, &mut result);
+            if code != 0 || result.is_null() {
+                return (code, None);
+            }
+            // SAFETY: a non-null result points to the initialized entry,
+            // whose name points into the buffer
+            let entry = unsafe { entry.assume_init() };
+            let name = unsafe { CStr::from_ptr(entry.gr_name) };
+            (0, Some((name.to_string_lossy().into_owned(), entry.gr_gid)))
+        })
+    }
+
+    pub fn user_name(uid: u32) -> Option<String> {
+        // SAFETY: the pointers are valid for the duration of the call and
+        // the buffer length is its own
+        passwd(|entry, buf, result| unsafe {
+            libc::getpwuid_r(uid, entry, buf.as_mut_ptr(), buf.len(), result)
+        })
+        .map(|(name, _)| name)
+    }
+
+    pub fn group_name(gid: u32) -> Option<String> {
+        // SAFETY: as for user_name
+        group(|entry, buf, result| unsafe {
+            libc::getgrgid_r(gid, entry, buf.as_mut_ptr(), buf.len(), result)
+        })
+        .map(|(name, _)| name)
+    }
+
+    pub fn uid(name: &str) -> Option<u32> {
+        let name = CString::new(name).ok()?;
+        // SAFETY: as for user_name, and the name is NUL-terminated
+        passwd(|entry, buf, result| unsafe {
+            libc::getpwnam_r(name.as_ptr(), entry, buf.as_mut_ptr(), buf.len(), result)
+        })
+        .map(|(_, uid)| uid)
+    }
+
+    pub fn gid(name: &str) -> Option<u32> {
+        let name = CString::new(name).ok()?;
+        // SAFETY: as for uid
+        group(|entry, buf, result| unsafe {
+            libc::getgrnam_r(name.as_ptr(), entry, buf.as_mut_ptr(), buf.len(), result)
+        })
+        .map(|(_, gid)| gid)
+    }
+}
+
+/// There are no user databases with numeric IDs elsewhere
+#[cfg(not(unix))]
+mod sys {
+    pub fn user_name(_uid: u32) -> Option<String> {
+        None
+    }
+
+    pub fn group_name(_gid: u32) -> Option<String> {
+        None
+    }
+
+    pub fn uid(_name: &str) -> Option<u32> {
+        None
+    }
+
+    pub fn gid(_name: &str) -> Option<u32> {
+        None
+    }
+}
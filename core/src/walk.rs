@@ -0,0 +1,357 @@
+//! Recursive traversal of directory trees, for the utilities operating on
+//! whole trees. Directories are visited before and after their contents,
+//! loops through followed symbolic links are detected, and errors are
+//! passed on without stopping the traversal.
+
+use crate::error::UError;
+#[cfg(any(unix, windows))]
+use crate::platform::FileInfo;
+use crate::quote::quote;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Which symbolic links lead into the directories they point to
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Follow {
+    /// None, like `-P`
+    #[default]
+    Never,
+    /// Only the operands, like `-H`
+    Operands,
+    /// All of them, like `-L`
+    Always,
+}
+
+/// How a tree is traversed
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Options {
+    pub follow: Follow,
+    /// Don't descend into directories on other file systems than their
+    /// operand, like `--one-file-system`
+    pub one_file_system: bool,
+    /// Visit the entries of directories in name order instead of the order
+    /// the file system lists them in
+    pub is_sorted: bool,
+}
+
+/// Why an entry is visited
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Event {
+    /// A directory, before its contents
+    Enter,
+    /// A directory, after its contents
+    Leave,
+    /// Anything but a directory
+    Leaf,
+}
+
+/// What to do after visiting an entry
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Control {
+    Continue,
+    /// Don't descend into the directory entered, nor leave it
+    Skip,
+}
+
+pub struct Entry {
+    pub path: PathBuf,
+    /// 0 for the operand the traversal started at
+    pub depth: usize,
+    /// Metadata of the entry, of the target of followed symbolic links
+    pub metadata: fs::Metadata,
+}
+
+impl Entry {
+    pub fn is_dir(&self) -> bool {
+        self.metadata.is_dir()
+    }
+}
+
+/// Identity of a directory, to detect loops
+#[cfg(any(unix, windows))]
+type FileId = (u64, u64);
+
+#[cfg(not(any(unix, windows)))]
+type FileId = PathBuf;
+
+#[cfg(unix)]
+fn file_id(entry: &Entry) -> Option<FileId> {
+    let info = FileInfo::from_metadata(&entry.metadata);
+    Some((info.dev, info.ino))
+}
+
+/// Metadata doesn't identify files on Windows, a handle does
+#[cfg(windows)]
+fn file_id(entry: &Entry) -> Option<FileId> {
+    let info = FileInfo::from_path(&entry.path).ok()?;
+    Some((info.dev, info.ino))
+}
+
+#[cfg(not(any(unix, windows)))]
+fn file_id(entry: &Entry) -> Option<FileId> {
+    fs::canonicalize(&entry.path).ok()
+}
+
+#[cfg(unix)]
+fn device(_path: &Path, metadata: &fs::Metadata) -> Option<u64> {
+    Some(FileInfo::from_metadata(metadata).dev)
+}
+
+#[cfg(windows)]
+fn device(path: &Path, _metadata: &fs::Metadata) -> Option<u64> {
+    FileInfo::from_path(path).ok().map(|info| info.dev)
+}
+
+/// Devices are not told apart elsewhere
+#[cfg(not(any(unix, windows)))]
+fn device(_path: &Path, _metadata: &fs::Metadata) -> Option<u64> {
+    None
+}
+
+struct Walker<'a, V, E> {
+    options: &'a Options,
+    visit: V,
+    on_error: E,
+    /// Directories being traversed, from the operand down
+    ancestors: Vec<FileId>,
+    root_device: Option<u64>,
+}
+
+impl<V, E> Walker<'_, V, E>
+where
+    V: FnMut(&Entry, Event) -> Control,
+    E: FnMut(UError),
+{
+    fn error(&mut self, err: impl Into<UError>, path: &Path) {
+        (self.on_error)(err.into().with_name(quote(path.as_os_str())));
+    }
+
+    fn walk(&mut self, path: PathBuf, depth: usize) {
+        let follow = match self.options.follow {
+            Follow::Never => false,
+            Follow::Operands => depth == 0,
+            Follow::Always => true,
+        };
+        let metadata = if follow {
+            // A dangling link is visited as the link
+            fs::metadata(&path).or_else(|_| fs::symlink_metadata(&path))
+        } else {
+            fs::symlink_metadata(&path)
+        };
+        let metadata = match metadata {
+            Ok(metadata) => metadata,
+            Err(e) => return self.error(e, &path),
+        };
+        if depth == 0 {
+            self.root_device = device(&path, &metadata);
+        }
+        let entry = Entry {
+            path,
+            depth,
+            metadata,
+        };
+        if !entry.is_dir() {
+            (self.visit)(&entry, Event::Leaf);
+            return;
+        }
+        let id = file_id(&entry);
+        if let Some(id) = &id {
+            if self.ancestors.contains(id) {
+                let err = UError::failure("file system loop detected");
+                return self.error(err, &entry.path);
+            }
+        }
+        if (self.visit)(&entry, Event::Enter) == Control::Skip {
+            return;
+        }
+        let is_crossing = self.options.one_file_system
+            && depth > 0
+            && device(&entry.path, &entry.metadata) != self.root_device;
+        if !is_crossing {
+            let entries = fs::read_dir(&entry.path)
+                .and_then(|entries| entries.collect::<Result<Vec<_>, _>>());
+            match entries {
+                Ok(mut entries) => {
+                    if self.options.is_sorted {
+                        entries.sort_by_key(fs::DirEntry::file_name);
+                    }
+                    let is_tracked = id.is_some();
+                    self.ancestors.extend(id);
+                    for child in entries {
+                        self.walk(child.path(), depth + 1);
+                    }
+                    if is_tracked {
+                        self.ancestors.pop();
+                    }
+                }
+                Err(e) => self.error(e, &entry.path),
+            }
+        }
+        (self.visit)(&entry, Event::Leave);
+    }
+}
+
+/// Visit `root` and, if it is a directory, everything under it. `visit` is
+/// called for every entry, and `on_error` with the errors of the entries
+/// that couldn't be visited, named after them.
+pub fn walk(
+    root: &Path,
+    options: &Options,
+    visit: impl FnMut(&Entry, Event) -> Control,
+    on_error: impl FnMut(UError),
+) {
+    Walker {
+        options,
+        visit,
+        on_error,
+        ancestors: Vec::new(),
+        root_device: None,
+    }
+    .walk(root.to_path_buf(), 0);
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::symlink;
+
+    /// A fresh tree: the file `a`, the directory `b` with the file `c` and
+    /// `up` linking back to the top, and `link` linking to `b`
+    fn tree(name: &str) -> PathBuf {
+        let root = std::env::temp_dir().join(format!("nyaa-walk-{}-{name}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("b")).unwrap();
+        fs::write(root.join("a"), "").unwrap();
+        fs::write(root.join("b/c"), "").unwrap();
+        symlink("..", root.join("b/up")).unwrap();
+        symlink("b", root.join("link")).unwrap();
+        root
+    }
+
+    /// The events of walking `path` under `root`, with the paths relative
+    /// to `root`, and the messages of the errors
+    fn walk_events(
+        root: &Path,
+        path: &Path,
+        options: Options,
+        skip: Option<&str>,
+    ) -> (Vec<(String, Event)>, Vec<String>) {
+        let mut events = Vec::new();
+        let mut errors = Vec::new();
+        walk(
+            path,
+            &options,
+            |entry, event| {
+                let name = entry.path.strip_prefix(root).unwrap().display().to_string();
+                let control = if skip == Some(&name) {
+                    Control::Skip
+                } else {
+                    Control::Continue
+                };
+                events.push((name, event));
+                control
+            },
+            |err| errors.push(err.to_string()),
+        );
+        (events, errors)
+    }
+
+    fn sorted(follow: Follow) -> Options {
+        Options {
+            follow,
+            is_sorted: true,
+            ..Options::default()
+        }
+    }
+
+    #[test]
+    fn sorted_without_following() {
+        let root = tree("never");
+        let (events, errors) = walk_events(&root, &root, sorted(Follow::Never), None);
+        let expected = [
+            ("", Event::Enter),
+            ("a", Event::Leaf),
+            ("b", Event::Enter),
+            ("b/c", Event::Leaf),
+            ("b/up", Event::Leaf),
+            ("b", Event::Leave),
+            ("link", Event::Leaf),
+            ("", Event::Leave),
+        ];
+        let expected: Vec<_> = expected
+            .map(|(name, event)| (name.to_string(), event))
+            .into();
+        assert_eq!(events, expected);
+        assert!(errors.is_empty(), "{errors:?}");
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn skip() {
+        let root = tree("skip");
+        let (events, errors) = walk_events(&root, &root, sorted(Follow::Never), Some("b"));
+        let names: Vec<_> = events.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, ["", "a", "b", "link", ""]);
+        assert!(errors.is_empty(), "{errors:?}");
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn follow_operands() {
+        let root = tree("operands");
+        let link = root.join("link");
+        let (events, errors) = walk_events(&root, &link, sorted(Follow::Operands), None);
+        let expected = [
+            ("link", Event::Enter),
+            ("link/c", Event::Leaf),
+            ("link/up", Event::Leaf),
+            ("link", Event::Leave),
+        ];
+        let expected: Vec<_> = expected
+            .map(|(name, event)| (name.to_string(), event))
+            .into();
+        assert_eq!(events, expected);
+        assert!(errors.is_empty(), "{errors:?}");
+        // Without following, the operand is only the link
+        let (events, _) = walk_events(&root, &link, sorted(Follow::Never), None);
+        assert_eq!(events, [("link".to_string(), Event::Leaf)]);
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn loops_are_reported() {
+        let root = tree("loop");
+        let (events, errors) = walk_events(&root, &root, sorted(Follow::Always), None);
+        let entered: Vec<_> = events
+            .iter()
+            .filter(|(_, event)| *event == Event::Enter)
+            .map(|(name, _)| name.as_str())
+            .collect();
+        assert_eq!(entered, ["", "b", "link"]);
+        assert_eq!(events.last(), Some(&(String::new(), Event::Leave)));
+        // `b/up` and `link/up` lead back to the top
+        assert_eq!(errors.len(), 2, "{errors:?}");
+        for (err, name) in errors.iter().zip(["b/up", "link/up"]) {
+            assert!(err.ends_with("file system loop detected"), "{err}");
+            assert!(err.contains(name), "{err}");
+        }
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn errors_are_passed_on() {
+        let root = tree("missing");
+        let missing = root.join("missing");
+        let (events, errors) = walk_events(&root, &missing, Options::default(), None);
+        assert!(events.is_empty());
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].contains("missing"), "{}", errors[0]);
+        // A dangling link followed is visited as the link
+        symlink("nowhere", root.join("dangling")).unwrap();
+        let dangling = root.join("dangling");
+        let (events, errors) = walk_events(&root, &dangling, sorted(Follow::Always), None);
+        assert_eq!(events, [("dangling".to_string(), Event::Leaf)]);
+        assert!(errors.is_empty(), "{errors:?}");
+        fs::remove_dir_all(root).unwrap();
+    }
+}
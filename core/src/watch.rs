@@ -0,0 +1,185 @@
+//! Waiting for files to change, for `tail -f`. On Linux, inotify watches
+//! the files and the directories they are in, so the wait ends as soon as
+//! one is written, truncated, renamed, removed or created again. Where
+//! there is no inotify, or it runs out of watches, waiting sleeps for the
+//! interval the files are polled at instead.
+
+use std::path::Path;
+use std::time::Duration;
+
+/// Waits for changes of the files added to it
+pub struct Watcher {
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    inotify: Option<std::os::fd::OwnedFd>,
+}
+
+impl Watcher {
+    /// Watcher through inotify where it is available, polling otherwise
+    pub fn new() -> Self {
+        Watcher {
+            #[cfg(any(target_os = "linux", target_os = "android"))]
+            inotify: match inotify::init() {
+                Ok(fd) => Some(fd),
+                Err(e) => {
+                    crate::debug!("inotify not available: {e}");
+                    None
+                }
+            },
+        }
+    }
+
+    /// Whether waiting sleeps rather than being woken by changes
+    pub fn is_polling(&self) -> bool {
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        let is_polling = self.inotify.is_none();
+        #[cfg(not(any(target_os = "linux", target_os = "android")))]
+        let is_polling = true;
+        is_polling
+    }
+
+    /// Watch `path` and its directory, where it may be created again after
+    /// being removed or renamed. Watching the file follows it through
+    /// renames. Call it again after the file is opened anew. Falls back on
+    /// polling when the watches can't be added.
+    pub fn add(&mut self, path: &Path) {
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        if let Some(fd) = &self.inotify {
+            use std::os::fd::AsFd;
+            let dir = match path.parent() {
+                Some(dir) if !dir.as_os_str().is_empty() => dir,
+                _ => Path::new("."),
+            };
+            // A missing file is noticed through its directory
+            let _ = inotify::add(fd.as_fd(), path, inotify::FILE_EVENTS);
+            if let Err(e) = inotify::add(fd.as_fd(), dir, inotify::DIR_EVENTS) {
+                crate::debug!("can't watch {}: {e}, polling instead", dir.display());
+                self.inotify = None;
+            }
+        }
+        #[cfg(not(any(target_os = "linux", target_os = "android")))]
+        let _ = path;
+    }
+
+    /// Wait until a watched file changes or `timeout` passes, forever
+    /// without one. Polling waits for the timeout, which it needs.
+    pub fn wait(&mut self, timeout: Option<Duration>) {
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        if let Some(fd) = &self.inotify {
+            use std::os::fd::AsFd;
+            if let Err(e) = inotify::wait(fd.as_fd(), timeout) {
+                crate::debug!("inotify failed: {e}, polling instead");
+                self.inotify = None;
+            }
+            return;
+        }
+        std::thread::sleep(timeout.unwrap_or(Duration::from_secs(1)));
+    }
+}
+
+impl Default for Watcher {
+    fn default() -> Self {
+        Watcher::new()
+    }
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+mod inotify {
+    use std::ffi::CString;
+    use std::io;
+    use std::os::fd::{AsRawFd, BorrowedFd, FromRawFd, OwnedFd};
+    use std::os::unix::ffi::OsStrExt;
+    use std::path::Path;
+    use std::time::Duration;
+
+    /// Changes of the data or metadata of a file, and its moving away
+    pub const FILE_EVENTS: u32 =
+        libc::IN_MODIFY | libc::IN_ATTRIB | libc::IN_DELETE_SELF | libc::IN_MOVE_SELF;
+
+    /// Files of a directory appearing, disappearing or changing
+    pub const DIR_EVENTS: u32 = libc::IN_MODIFY
+        | libc::IN_ATTRIB
+        | libc::IN_CREATE
+        | libc::IN_DELETE
+        | libc::IN_MOVED_FROM
+        | libc::IN_MOVED_TO;
+
+    pub fn init() -> io::Result<OwnedFd> {
+        // SAFETY: inotify_init1 takes no pointers, and the descriptor it
+        // returns is owned by nothing else
+        unsafe {
+            match libc::inotify_init1(libc::IN_NONBLOCK | libc::IN_CLOEXEC) {
+                -1 => Err(io::Error::last_os_error()),
+                fd => Ok(OwnedFd::from_raw_fd(fd)),
+            }
+        }
+    }
+
+    pub fn add(fd: BorrowedFd, path: &Path, mask: u32) -> io::Result<()> {
+        let path = CString::new(path.as_os_str().as_bytes())?;
+        // SAFETY: the path is a NUL-terminated string that outlives the call
+        match unsafe { libc::inotify_add_watch(fd.as_raw_fd(), path.as_ptr(), mask) } {
+            -1 => Err(io::Error::last_os_error()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Wait for events and drain them. Which files they are about doesn't
+    /// matter, every file is checked after any change.
+    pub fn wait(fd: BorrowedFd, timeout: Option<Duration>) -> io::Result<()> {
+        let mut pollfd = libc::pollfd {
+            fd: fd.as_raw_fd(),
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        let timeout = timeout.map_or(-1, |timeout| {
+            libc::c_int::try_from(timeout.as_millis()).unwrap_or(libc::c_int::MAX)
+        });
+        // SAFETY: the pollfd is valid for the call, which gets its count
+        if unsafe { libc::poll(&mut pollfd, 1, timeout) } == -1 {
+            let e = io::Error::last_os_error();
+            if e.kind() != io::ErrorKind::Interrupted {
+                return Err(e);
+            }
+        }
+        let mut buf = [0u8; 4096];
+        loop {
+            // SAFETY: the buffer is valid for writes of its length
+            let n = unsafe { libc::read(fd.as_raw_fd(), buf.as_mut_ptr().cast(), buf.len()) };
+            if n >= 0 {
+                continue;
+            }
+            let e = io::Error::last_os_error();
+            match e.kind() {
+                io::ErrorKind::WouldBlock => return Ok(()),
+                io::ErrorKind::Interrupted => continue,
+                _ => return Err(e),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::time::Instant;
+
+    #[test]
+    fn wakes_on_appends() {
+        let dir = std::env::temp_dir().join(format!("nyaa-watch-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("log");
+        fs::write(&path, "one\n").unwrap();
+        let mut watcher = Watcher::new();
+        watcher.add(&path);
+        let start = Instant::now();
+        fs::write(&path, "two\n").unwrap();
+        watcher.wait(Some(Duration::from_secs(10)));
+        // Polling sleeps for the timeout instead
+        assert_eq!(
+            watcher.is_polling(),
+            start.elapsed() >= Duration::from_secs(10)
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,220 @@
+//! Extended attributes and security contexts of files, for `--preserve`
+//! of `cp`, `mv` and `install` and for `ls -Z`.
+//!
+//! Attributes are supported on Linux. Elsewhere, and on file systems
+//! without them, the functions fail with an error that [`is_unsupported`]
+//! tells apart, which utilities ignore unless the attributes were asked
+//! for explicitly. Symbolic links are followed only if `follow` is set.
+
+use std::ffi::{OsStr, OsString};
+use std::io;
+use std::path::Path;
+
+/// Attribute holding the SELinux security context
+pub const SELINUX: &str = "security.selinux";
+
+/// Attributes to copy
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Preserve {
+    /// All attributes except the security context
+    pub xattr: bool,
+    /// The security context
+    pub context: bool,
+}
+
+/// Whether `err` means the system or file system has no extended
+/// attributes, rather than that accessing them failed
+pub fn is_unsupported(err: &io::Error) -> bool {
+    if err.kind() == io::ErrorKind::Unsupported {
+        return true;
+    }
+    #[cfg(unix)]
+    if let Some(code) = err.raw_os_error() {
+        return code == libc::ENOTSUP || code == libc::EOPNOTSUPP || code == libc::ENOSYS;
+    }
+    false
+}
+
+/// Names of the attributes of `path`
+pub fn list(path: &Path, follow: bool) -> io::Result<Vec<OsString>> {
+    sys::list(path, follow)
+}
+
+/// Value of the attribute `name` of `path`, `None` if it has none
+pub fn get(path: &Path, name: &OsStr, follow: bool) -> io::Result<Option<Vec<u8>>> {
+    sys::get(path, name, follow)
+}
+
+/// Set the attribute `name` of `path`, creating or replacing it
+pub fn set(path: &Path, name: &OsStr, value: &[u8], follow: bool) -> io::Result<()> {
+    sys::set(path, name, value, follow)
+}
+
+/// Security context of `path`, like `system_u:object_r:etc_t:s0`, `None`
+/// if it has none or the system has no security contexts
+pub fn context(path: &Path, follow: bool) -> io::Result<Option<String>> {
+    match get(path, OsStr::new(SELINUX), follow) {
+        Ok(value) => Ok(value.map(|value| {
+            let end = value.iter().position(|&b| b == 0).unwrap_or(value.len());
+            String::from_utf8_lossy(&value[..end]).into_owned()
+        })),
+        Err(e) if is_unsupported(&e) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// Set the security context of `path`
+pub fn set_context(path: &Path, context: &str, follow: bool) -> io::Result<()> {
+    // The kernel stores contexts terminated by a NUL
+    let mut value = context.as_bytes().to_vec();
+    value.push(0);
+    set(path, OsStr::new(SELINUX), &value, follow)
+}
+
+/// Copy the attributes selected by `preserve` from `from` to `to`, not
+/// following symbolic links. An attribute failing to copy doesn't stop
+/// the others, the first error is returned after all were tried.
+pub fn copy(from: &Path, to: &Path, preserve: Preserve) -> io::Result<()> {
+    if preserve == Preserve::default() {
+        return Ok(());
+    }
+    let mut result = Ok(());
+    for name in list(from, false)? {
+        let is_selected = if name == SELINUX {
+            preserve.context
+        } else {
+            preserve.xattr
+        };
+        if !is_selected {
+            continue;
+        }
+        let copied = get(from, &name, false).and_then(|value| match value {
+            Some(value) => set(to, &name, &value, false),
+            // Removed since it was listed
+            None => Ok(()),
+        });
+        if result.is_ok() {
+            result = copied;
+        }
+    }
+    result
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+mod sys {
+    use std::ffi::{CString, OsStr, OsString};
+    use std::io;
+    use std::os::unix::ffi::{OsStrExt, OsStringExt};
+    use std::path::Path;
+
+    fn c_string(s: &OsStr) -> io::Result<CString> {
+        CString::new(s.as_bytes()).map_err(|_| io::ErrorKind::InvalidInput.into())
+    }
+
+    /// Call `query` with a buffer growing until the value fits, sizing it
+    /// first by a call without one
+    fn with_buffer(mut query: impl FnMut(&mut [u8]) -> libc::ssize_t) -> io::Result<Vec<u8>> {
+        loop {
+            let size = query(&mut []);
+            if size < 0 {
+                return Err(io::Error::last_os_error());
+            }
+            let mut buf = vec![0; size as usize];
+            match query(&mut buf) {
+                len if len >= 0 => {
+                    buf.truncate(len as usize);
+                    return Ok(buf);
+                }
+                // Grown since it was sized
+                _ if io::Error::last_os_error().raw_os_error() == Some(libc::ERANGE) => continue,
+                _ => return Err(io::Error::last_os_error()),
+            }
+        }
+    }
+
+    pub fn list(path: &Path, follow: bool) -> io::Result<Vec<OsString>> {
+        let path = c_string(path.as_os_str())?;
+        let names = with_buffer(|buf| {
+            let (ptr, len) = (buf.as_mut_ptr().cast(), buf.len());
+            // SAFETY: the buffer is valid for `len` bytes, and the path is
+            // a C string
+            unsafe {
+                if follow {
+                    libc::listxattr(path.as_ptr(), ptr, len)
+                } else {
+                    libc::llistxattr(path.as_ptr(), ptr, len)
+                }
+            }
+        })?;
+        Ok(names
+            .split(|&b| b == 0)
+            .filter(|name| !name.is_empty())
+            .map(|name| OsString::from_vec(name.to_vec()))
+            .collect())
+    }
+
+    pub fn get(path: &Path, name: &OsStr, follow: bool) -> io::Result<Option<Vec<u8>>> {
+        let (path, name) = (c_string(path.as_os_str())?, c_string(name)?);
+        let value = with_buffer(|buf| {
+            let (ptr, len) = (buf.as_mut_ptr().cast(), buf.len());
+            // SAFETY: as in `list`
+            unsafe {
+                if follow {
+                    libc::getxattr(path.as_ptr(), name.as_ptr(), ptr, len)
+                } else {
+                    libc::lgetxattr(path.as_ptr(), name.as_ptr(), ptr, len)
+                }
+            }
+        });
+        match value {
+            Ok(value) => Ok(Some(value)),
+            Err(e) if e.raw_os_error() == Some(libc::ENODATA) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub fn set(path: &Path, name: &OsStr, value: &[u8], follow: bool) -> io::Result<()> {
+        let (path, name) = (c_string(path.as_os_str())?, c_string(name)?);
+        let (ptr, len) = (value.as_ptr().cast(), value.len());
+        // SAFETY: the value is valid for `len` bytes, and the path and the
+        // name are C strings
+        let result = unsafe {
+            if follow {
+                libc::setxattr(path.as_ptr(), name.as_ptr(), ptr, len, 0)
+            } else {
+                libc::lsetxattr(path.as_ptr(), name.as_ptr(), ptr, len, 0)
+            }
+        };
+        if result < 0 {
+            Err(io::Error::last_os_error())
+        } else {
+            Ok(())
+        }
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+mod sys {
+    use std::ffi::{OsStr, OsString};
+    use std::io;
+    use std::path::Path;
+
+    fn unsupported<T>() -> io::Result<T> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "extended attributes are not supported on this system",
+        ))
+    }
+
+    pub fn list(_path: &Path, _follow: bool) -> io::Result<Vec<OsString>> {
+        unsupported()
+    }
+
+    pub fn get(_path: &Path, _name: &OsStr, _follow: bool) -> io::Result<Option<Vec<u8>>> {
+        unsupported()
+    }
+
+    pub fn set(_path: &Path, _name: &OsStr, _value: &[u8], _follow: bool) -> io::Result<()> {
+        unsupported()
+    }
+}
@@ -0,0 +1,45 @@
+//! Tests of reading records on generated input, split across the buffer
+//! of the reader at every possible place
+
+use nyaa_core::record::{Records, Terminator};
+use proptest::prelude::*;
+use std::io::BufReader;
+
+fn input() -> impl Strategy<Value = Vec<u8>> {
+    prop::collection::vec(prop::sample::select(&b"ab\r\n\0\xff"[..]), 0..256)
+}
+
+fn terminator() -> impl Strategy<Value = Terminator> {
+    prop::sample::select(&[Terminator::Newline, Terminator::CrLf, Terminator::Nul][..])
+}
+
+fn as_bytes(terminator: Terminator) -> &'static [u8] {
+    match terminator {
+        Terminator::Newline => b"\n",
+        Terminator::CrLf => b"\r\n",
+        Terminator::Nul => b"\0",
+    }
+}
+
+proptest! {
+    #[test]
+    fn records_split_input(data in input(), terminator in terminator(), capacity in 1..16usize) {
+        let end = as_bytes(terminator);
+        let mut records = Records::new(BufReader::with_capacity(capacity, &data[..]), terminator);
+        let mut joined = Vec::new();
+        let mut count = 0;
+        while let Some(record) = records.next_record().unwrap() {
+            let body = terminator.strip(record);
+            // Only the last record lacks the terminator, and none has it
+            // inside
+            prop_assert!(body.len() < record.len() || joined.len() + record.len() == data.len());
+            prop_assert!(!body.windows(end.len()).any(|window| window == end));
+            joined.extend_from_slice(record);
+            count += 1;
+        }
+        prop_assert_eq!(&joined, &data);
+        let terminated = data.windows(end.len()).filter(|window| *window == end).count();
+        let is_unterminated = !data.is_empty() && !data.ends_with(end);
+        prop_assert_eq!(count, terminated + usize::from(is_unterminated));
+    }
+}
@@ -0,0 +1,76 @@
+//! Command line interface of `echo`
+
+use crate::Options;
+use clap::{ArgAction, CommandFactory, Parser};
+use nyaa_core::error::Status;
+use nyaa_core::i18n;
+use nyaa_core::output::{self, OutputSink};
+use nyaa_core::trace;
+use std::env;
+use std::ffi::OsString;
+use std::process::ExitCode;
+
+const UTIL: &str = "echo";
+
+/// The options as help, the man page and completions show them. The
+/// arguments are parsed like coreutils does instead, see [`Options`].
+#[derive(Parser)]
+#[command(name = "echo", version)]
+#[command(about = "display a line of text", long_about = None)]
+#[command(disable_help_flag = true, disable_version_flag = true)]
+#[allow(dead_code)]
+struct Args {
+    /// Do not output the trailing newline
+    #[arg(short = 'n')]
+    is_no_newline: bool,
+
+    /// Interpret the backslash escapes \a, \b, \e, \f, \n, \r, \t, \v, \\,
+    /// \0NNN and \xHH, and \c ending the output
+    #[arg(short = 'e')]
+    is_escapes: bool,
+
+    /// Do not interpret backslash escapes, the default
+    #[arg(short = 'E')]
+    is_no_escapes: bool,
+
+    /// Strings to print, separated by spaces. Options are only taken from
+    /// the leading arguments, anything else, -- included, is printed
+    #[arg(value_name = "STRING")]
+    strings: Vec<OsString>,
+
+    /// Print help, when it is the only argument
+    #[arg(long, action=ArgAction::Help)]
+    help: (),
+
+    /// Print version, when it is the only argument
+    #[arg(long, action=ArgAction::Version)]
+    version: (),
+}
+
+pub fn command() -> clap::Command {
+    i18n::command(Args::command())
+}
+
+/// Run `echo` with the command line `args`, starting with the program name
+pub fn main(args: Vec<OsString>) -> ExitCode {
+    let mut status = Status::default();
+    let is_posix = env::var_os("POSIXLY_CORRECT").is_some();
+    let operands: Vec<&[u8]> = args
+        .iter()
+        .skip(1)
+        .map(|arg| arg.as_encoded_bytes())
+        .collect();
+    if !is_posix && matches!(operands[..], [b"--help" | b"--version"]) {
+        command().get_matches_from(&args);
+        return status.into();
+    }
+    trace::init(UTIL, false);
+    let (options, operands) = Options::parse(&operands, is_posix);
+    let mut out = OutputSink::stdout();
+    if let Err(e) = crate::echo(operands, &options, &mut out) {
+        status.report(UTIL, &output::write_error(e));
+        return status.into();
+    }
+    status.check(UTIL, out.finish());
+    status.into()
+}
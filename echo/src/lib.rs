@@ -0,0 +1,197 @@
+//! Printing of arguments with the semantics of `echo` of coreutils, which
+//! shells have built-ins of that differ in details.
+//!
+//! Options are only the leading arguments made of the letters `n`, `e`
+//! and `E` after a `-`, anything else, `--` included, is printed. With
+//! `POSIXLY_CORRECT` set, escapes are always interpreted and options are
+//! only taken when the first argument is `-n`.
+//!
+//! ```
+//! let args: [&[u8]; 3] = [b"-ne", b"a\\tb", b"c\\cd"];
+//! let (options, operands) = nyaa_echo::Options::parse(&args, false);
+//! let mut out = Vec::new();
+//! nyaa_echo::echo(operands, &options, &mut out)?;
+//! assert_eq!(out, b"a\tb c");
+//! # Ok::<(), std::io::Error>(())
+//! ```
+
+pub mod cli;
+
+use std::io::{self, Write};
+
+/// How the arguments are printed
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Options {
+    /// Whether a newline ends the output, unless `-n`
+    pub is_newline: bool,
+    /// Whether backslash escapes are interpreted, by `-e`
+    pub is_escapes: bool,
+}
+
+impl Options {
+    /// Options of the leading arguments of `args`, without the program
+    /// name, and the arguments left to print. `is_posix` is whether
+    /// `POSIXLY_CORRECT` is set.
+    pub fn parse<A: AsRef<[u8]>>(args: &[A], is_posix: bool) -> (Options, &[A]) {
+        let mut options = Options {
+            is_newline: true,
+            is_escapes: is_posix,
+        };
+        if is_posix && args.first().map(AsRef::as_ref) != Some(b"-n") {
+            return (options, args);
+        }
+        let count = args
+            .iter()
+            .take_while(|arg| match arg.as_ref() {
+                [b'-', letters @ ..] => {
+                    !letters.is_empty() && letters.iter().all(|b| b"neE".contains(b))
+                }
+                _ => false,
+            })
+            .count();
+        for &letter in args[..count].iter().flat_map(|arg| &arg.as_ref()[1..]) {
+            match letter {
+                b'n' => options.is_newline = false,
+                b'e' => options.is_escapes = true,
+                _ => options.is_escapes = is_posix,
+            }
+        }
+        (options, &args[count..])
+    }
+}
+
+/// Value of the hexadecimal or octal digit `b`
+fn digit(b: u8, radix: u32) -> Option<u8> {
+    (b as char).to_digit(radix).map(|d| d as u8)
+}
+
+/// Append `arg` to `out` with its backslash escapes interpreted. Returns
+/// `false` at a `\c`, which ends the output there.
+fn unescape(arg: &[u8], out: &mut Vec<u8>) -> bool {
+    let mut i = 0;
+    while i < arg.len() {
+        let b = arg[i];
+        i += 1;
+        if b != b'\\' || i == arg.len() {
+            out.push(b);
+            continue;
+        }
+        let escape = arg[i];
+        i += 1;
+        let c = match escape {
+            b'a' => 0x07,
+            b'b' => 0x08,
+            b'c' => return false,
+            b'e' => 0x1b,
+            b'f' => 0x0c,
+            b'n' => b'\n',
+            b'r' => b'\r',
+            b't' => b'\t',
+            b'v' => 0x0b,
+            b'\\' => b'\\',
+            b'x' => match arg.get(i).and_then(|&b| digit(b, 16)) {
+                Some(high) => {
+                    i += 1;
+                    match arg.get(i).and_then(|&b| digit(b, 16)) {
+                        Some(low) => {
+                            i += 1;
+                            high << 4 | low
+                        }
+                        None => high,
+                    }
+                }
+                None => {
+                    out.extend_from_slice(b"\\x");
+                    continue;
+                }
+            },
+            // `\0` takes up to three octal digits after it, `\1` to `\7`
+            // up to two more, wrapping around past a byte like in C
+            b'0'..=b'7' => {
+                let mut value = digit(escape, 8).unwrap_or(0);
+                let max = if escape == b'0' { 3 } else { 2 };
+                for _ in 0..max {
+                    match arg.get(i).and_then(|&b| digit(b, 8)) {
+                        Some(d) => {
+                            value = value.wrapping_mul(8).wrapping_add(d);
+                            i += 1;
+                        }
+                        None => break,
+                    }
+                }
+                value
+            }
+            _ => {
+                out.extend_from_slice(&[b'\\', escape]);
+                continue;
+            }
+        };
+        out.push(c);
+    }
+    true
+}
+
+/// Write `args` to `out` separated by spaces, as `options` say
+pub fn echo<A: AsRef<[u8]>>(args: &[A], options: &Options, out: &mut impl Write) -> io::Result<()> {
+    let mut line = Vec::new();
+    for (i, arg) in args.iter().enumerate() {
+        if i > 0 {
+            line.push(b' ');
+        }
+        if !options.is_escapes {
+            line.extend_from_slice(arg.as_ref());
+        } else if !unescape(arg.as_ref(), &mut line) {
+            return out.write_all(&line);
+        }
+    }
+    if options.is_newline {
+        line.push(b'\n');
+    }
+    out.write_all(&line)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn echo_str(args: &[&str], is_posix: bool) -> String {
+        let (options, operands) = Options::parse(args, is_posix);
+        let mut out = Vec::new();
+        echo(operands, &options, &mut out).unwrap();
+        String::from_utf8_lossy(&out).into_owned()
+    }
+
+    #[test]
+    fn options() {
+        assert_eq!(echo_str(&["-n", "a", "-n"], false), "a -n");
+        assert_eq!(echo_str(&["-nE", "-e", "a\\tb"], false), "a\tb");
+        assert_eq!(echo_str(&["-eE", "a\\tb"], false), "a\\tb\n");
+        assert_eq!(echo_str(&["-nx", "a"], false), "-nx a\n");
+        assert_eq!(echo_str(&["-", "--", "a"], false), "- -- a\n");
+        assert_eq!(echo_str(&[], false), "\n");
+    }
+
+    #[test]
+    fn posixly_correct() {
+        assert_eq!(echo_str(&["-e", "a\\tb"], true), "-e a\tb\n");
+        assert_eq!(echo_str(&["-n", "-E", "a\\tb"], true), "a\tb");
+        assert_eq!(echo_str(&["a", "-n"], true), "a -n\n");
+    }
+
+    #[test]
+    fn escapes() {
+        let echo_e = |arg: &str| echo_str(&["-e", arg], false);
+        assert_eq!(
+            echo_e(r"\a\b\e\f\n\r\t\v\\"),
+            "\x07\x08\x1b\x0c\n\r\t\x0b\\\n"
+        );
+        assert_eq!(echo_e(r"\0101\01010\101\1010\0\08"), "AA0AA0\0\08\n");
+        assert_eq!(echo_e(r"\x41\x4a4\xg\x"), "AJ4\\xg\\x\n");
+        assert_eq!(echo_e(r"\q\"), "\\q\\\n");
+        assert_eq!(echo_str(&["-e", "a", r"b\cc", "d"], false), "a b");
+        let mut out = Vec::new();
+        let (options, operands) = Options::parse(&["-e", r"\0777\400"], false);
+        echo(operands, &options, &mut out).unwrap();
+        assert_eq!(out, b"\xff\x00\n");
+    }
+}
@@ -0,0 +1,5 @@
+//! `false` of coreutils: exits with a failure whatever the arguments,
+//! which are ignored unless `--help` or `--version` is the only one, for
+//! shells without the built-in. Printing help or the version fails too.
+
+pub mod cli;
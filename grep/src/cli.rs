@@ -0,0 +1,711 @@
+//! Command line interface of `grep`
+
+use crate::{matcher, BinaryFiles, Error, Found, Matcher, Mode, Options, Syntax};
+use clap::{ArgAction, ArgMatches, CommandFactory, FromArgMatches, Parser};
+use nyaa_core::error::{self, Status, UError, UResult};
+use nyaa_core::glob::{self, Pattern};
+use nyaa_core::i18n;
+use nyaa_core::jobs::{self, JobsArg};
+use nyaa_core::locale::Locale;
+use nyaa_core::mmap::FileData;
+use nyaa_core::output::{self, OutputSink};
+use nyaa_core::posix;
+use nyaa_core::quote::quote;
+use nyaa_core::response;
+use nyaa_core::sandbox::SandboxArg;
+use nyaa_core::trace;
+use nyaa_core::walk::{self, Control, Event, Follow};
+use nyaa_core::{Input, Reader};
+use std::ffi::{OsStr, OsString};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+const UTIL: &str = "grep";
+
+/// Exit code of errors, as lines not being selected exit with 1
+const EXIT_TROUBLE: u8 = 2;
+
+#[derive(Parser)]
+#[command(name = "grep", version)]
+#[command(about = "print lines that match patterns", long_about = None)]
+#[command(disable_help_flag = true, disable_version_flag = true)]
+#[command(args_override_self = true)]
+struct Args {
+    /// Take PATTERNS for extended regular expressions
+    #[arg(short = 'E', long = "extended-regexp")]
+    is_extended: bool,
+
+    /// Take PATTERNS for strings, matching themselves
+    #[arg(short = 'F', long = "fixed-strings")]
+    is_fixed: bool,
+
+    /// Take PATTERNS for basic regular expressions, the default
+    #[arg(short = 'G', long = "basic-regexp")]
+    is_basic: bool,
+
+    /// Take PATTERNS for a Perl-compatible regular expression
+    #[arg(short = 'P', long = "perl-regexp")]
+    is_perl: bool,
+
+    /// Use PATTERNS, one on each of their lines, instead of the first
+    /// operand
+    #[arg(
+        short = 'e',
+        long = "regexp",
+        value_name = "PATTERNS",
+        allow_hyphen_values = true
+    )]
+    patterns: Vec<OsString>,
+
+    /// Use the patterns on the lines of FILE, the stdin for -
+    #[arg(short = 'f', long = "file", value_name = "FILE")]
+    pattern_files: Vec<PathBuf>,
+
+    /// Ignore the case of letters in patterns and input
+    #[arg(short = 'i', long = "ignore-case")]
+    is_ignore_case: bool,
+
+    /// Select the lines not matching
+    #[arg(short = 'v', long = "invert-match")]
+    is_invert: bool,
+
+    /// Prefix lines by their number in the file
+    #[arg(short = 'n', long = "line-number")]
+    is_line_number: bool,
+
+    /// Prefix lines by the offset in the file of their first byte, or of
+    /// the match with -o
+    #[arg(short = 'b', long = "byte-offset")]
+    is_byte_offset: bool,
+
+    /// Output only the matches in the selected lines, each on a line of its
+    /// own
+    #[arg(short = 'o', long = "only-matching")]
+    is_only_matching: bool,
+
+    /// Output NUM lines of context after the selected ones, with -- between
+    /// groups of lines that aren't adjacent
+    #[arg(
+        short = 'A',
+        long = "after-context",
+        value_name = "NUM",
+        allow_hyphen_values = true
+    )]
+    after_context: Option<String>,
+
+    /// Output NUM lines of context before the selected ones
+    #[arg(
+        short = 'B',
+        long = "before-context",
+        value_name = "NUM",
+        allow_hyphen_values = true
+    )]
+    before_context: Option<String>,
+
+    /// Output NUM lines of context around the selected ones, unless -A or
+    /// -B say otherwise
+    #[arg(
+        short = 'C',
+        long = "context",
+        value_name = "NUM",
+        allow_hyphen_values = true
+    )]
+    context: Option<String>,
+
+    /// Output only the number of lines selected in every file
+    #[arg(short = 'c', long = "count")]
+    is_count: bool,
+
+    /// Output only the names of the files with lines selected
+    #[arg(
+        short = 'l',
+        long = "files-with-matches",
+        overrides_with = "is_files_without_match"
+    )]
+    is_files_with_matches: bool,
+
+    /// Output only the names of the files without lines selected
+    #[arg(
+        short = 'L',
+        long = "files-without-match",
+        overrides_with = "is_files_with_matches"
+    )]
+    is_files_without_match: bool,
+
+    /// Output nothing, and exit with 0 at the first line selected, even
+    /// after errors
+    #[arg(short = 'q', long = "quiet", visible_alias = "silent")]
+    is_quiet: bool,
+
+    /// Prefix output lines by the name of their file, the default with
+    /// several files
+    #[arg(short = 'H', long = "with-filename", overrides_with = "is_no_filename")]
+    is_with_filename: bool,
+
+    /// Don't prefix output lines by the name of their file
+    #[arg(short = 'h', long = "no-filename", overrides_with = "is_with_filename")]
+    is_no_filename: bool,
+
+    /// Search the files under directories, following only the symbolic
+    /// links on the command line
+    #[arg(short = 'r', long = "recursive")]
+    is_recursive: bool,
+
+    /// Search the files under directories, following all symbolic links
+    #[arg(short = 'R', long = "dereference-recursive")]
+    is_dereference_recursive: bool,
+
+    /// Search only the files whose name matches GLOB, unless a later
+    /// --exclude matches it too
+    #[arg(long = "include", value_name = "GLOB")]
+    includes: Vec<OsString>,
+
+    /// Skip the files whose name matches GLOB, unless a later --include
+    /// matches it too. Names on the command line match by any part after a
+    /// slash as well.
+    #[arg(long = "exclude", value_name = "GLOB")]
+    excludes: Vec<OsString>,
+
+    /// Skip the directories whose name matches GLOB
+    #[arg(long = "exclude-dir", value_name = "GLOB")]
+    exclude_dirs: Vec<OsString>,
+
+    /// Search input with binary data as TYPE says
+    #[arg(
+        long = "binary-files",
+        value_name = "TYPE",
+        value_enum,
+        overrides_with_all = ["is_text", "is_without_match"]
+    )]
+    binary_files: Option<BinaryFiles>,
+
+    /// Same as --binary-files=text
+    #[arg(
+        short = 'a',
+        long = "text",
+        overrides_with_all = ["binary_files", "is_without_match"]
+    )]
+    is_text: bool,
+
+    /// Same as --binary-files=without-match
+    #[arg(short = 'I', overrides_with_all = ["binary_files", "is_text"])]
+    is_without_match: bool,
+
+    #[command(flatten)]
+    jobs: JobsArg,
+
+    #[command(flatten)]
+    sandbox: SandboxArg,
+
+    /// PATTERNS, one on each of their lines, without -e or -f, then the
+    /// FILEs to search, the stdin without them or for -, the working
+    /// directory with -r. An argument @FILE stands for the arguments on the
+    /// lines of FILE, @@NAME for the file @NAME
+    #[arg(value_name = "PATTERNS [FILE]")]
+    operands: Vec<OsString>,
+
+    /// Print help
+    #[arg(long, action=ArgAction::Help)]
+    help: (),
+
+    /// Print version
+    #[arg(long, action=ArgAction::Version)]
+    version: (),
+}
+
+pub fn command() -> clap::Command {
+    i18n::command(Args::command())
+}
+
+/// The patterns of `-e` and `-f`, or of the first operand without them,
+/// with the files to search
+fn patterns(args: &Args) -> UResult<(Vec<Vec<u8>>, &[OsString])> {
+    let mut operands = &args.operands[..];
+    let specs = if args.patterns.is_empty() && args.pattern_files.is_empty() {
+        let (spec, files) = operands
+            .split_first()
+            .ok_or_else(|| UError::usage("no pattern given"))?;
+        operands = files;
+        std::slice::from_ref(spec)
+    } else {
+        &args.patterns[..]
+    };
+    let mut patterns: Vec<Vec<u8>> = specs
+        .iter()
+        .flat_map(|spec| spec.as_encoded_bytes().split(|&b| b == b'\n'))
+        .map(<[u8]>::to_vec)
+        .collect();
+    for path in &args.pattern_files {
+        let input = Input::from_operand(path);
+        let name = input
+            .quoted_name()
+            .map_or_else(|| "-".to_string(), |name| name.into_owned());
+        let mut content = Vec::new();
+        input
+            .open()
+            .and_then(|mut reader| reader.read_to_end(&mut content))
+            .map_err(|e| UError::from(e).with_name(name))?;
+        // The newline ending the last pattern doesn't start another one
+        if let Some(rest) = content.strip_suffix(b"\n") {
+            content.truncate(rest.len());
+        } else if content.is_empty() {
+            continue;
+        }
+        patterns.extend(content.split(|&b| b == b'\n').map(<[u8]>::to_vec));
+    }
+    Ok((patterns, operands))
+}
+
+/// The number of lines of context `spec`, at most the largest one
+fn context_length(spec: &str) -> UResult<usize> {
+    let digits = spec.trim_start();
+    let digits = digits.strip_prefix('+').unwrap_or(digits);
+    if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(UError::new(EXIT_TROUBLE, "invalid context length argument").with_name(spec));
+    }
+    Ok(digits.bytes().fold(0usize, |n, digit| {
+        n.saturating_mul(10)
+            .saturating_add(usize::from(digit - b'0'))
+    }))
+}
+
+/// The lines of context before and after the selected ones, `None` without
+/// any option for them
+fn context(args: &Args) -> UResult<Option<(usize, usize)>> {
+    let length = |spec: &Option<String>| spec.as_deref().map(context_length).transpose();
+    let (before, after, both) = (
+        length(&args.before_context)?,
+        length(&args.after_context)?,
+        length(&args.context)?,
+    );
+    if before.is_none() && after.is_none() && both.is_none() {
+        return Ok(None);
+    }
+    let default = both.unwrap_or(0);
+    Ok(Some((before.unwrap_or(default), after.unwrap_or(default))))
+}
+
+/// The syntax of the patterns, of which only one may be chosen
+fn syntax(args: &Args) -> UResult<Syntax> {
+    let chosen = [
+        (args.is_basic, Syntax::Basic),
+        (args.is_extended, Syntax::Extended),
+        (args.is_fixed, Syntax::Fixed),
+        (args.is_perl, Syntax::Perl),
+    ];
+    let mut chosen = chosen.into_iter().filter(|&(is_chosen, _)| is_chosen);
+    match (chosen.next(), chosen.next()) {
+        (Some(_), Some(_)) => Err(UError::new(EXIT_TROUBLE, "conflicting matchers specified")),
+        (Some((_, syntax)), None) => Ok(syntax),
+        (None, _) => Ok(Syntax::default()),
+    }
+}
+
+/// The globs of `--include`, `--exclude` and `--exclude-dir`
+struct Filters {
+    /// Globs of the names of files in the order given, each with whether
+    /// it includes the files it matches
+    files: Vec<(Pattern, bool)>,
+    dirs: Vec<Pattern>,
+}
+
+impl Filters {
+    fn new(matches: &ArgMatches) -> Self {
+        let globs = |id| {
+            let indices = matches.indices_of(id).into_iter().flatten();
+            let values = matches.get_many::<OsString>(id).into_iter().flatten();
+            indices.zip(
+                values.map(|glob| Pattern::new(glob.as_encoded_bytes(), glob::Options::default())),
+            )
+        };
+        let mut files: Vec<_> = globs("includes")
+            .map(|(index, glob)| (index, glob, true))
+            .chain(globs("excludes").map(|(index, glob)| (index, glob, false)))
+            .collect();
+        files.sort_by_key(|&(index, _, _)| index);
+        Filters {
+            files: files
+                .into_iter()
+                .map(|(_, glob, is_include)| (glob, is_include))
+                .collect(),
+            dirs: globs("exclude_dirs").map(|(_, glob)| glob).collect(),
+        }
+    }
+
+    /// Whether the file `name` is searched: the last glob matching it
+    /// decides, and if none does the first one excludes it if it includes
+    fn is_file_included(&self, name: &OsStr, is_operand: bool) -> bool {
+        let matches = |glob: &Pattern| names(name, is_operand).any(|name| glob.matches(name));
+        match self.files.iter().rev().find(|(glob, _)| matches(glob)) {
+            Some(&(_, is_include)) => is_include,
+            None => self
+                .files
+                .first()
+                .is_none_or(|&(_, is_include)| !is_include),
+        }
+    }
+
+    fn is_dir_excluded(&self, name: &OsStr, is_operand: bool) -> bool {
+        self.dirs
+            .iter()
+            .any(|glob| names(name, is_operand).any(|name| glob.matches(name)))
+    }
+}
+
+/// The names that globs match `name` by: the name, and for an operand
+/// every part of it that starts after a slash
+fn names(name: &OsStr, is_operand: bool) -> impl Iterator<Item = &[u8]> {
+    let name = name.as_encoded_bytes();
+    let starts = (1..name.len()).filter(move |&start| {
+        is_operand
+            && std::path::is_separator(char::from(name[start - 1]))
+            && !std::path::is_separator(char::from(name[start]))
+    });
+    std::iter::once(0)
+        .chain(starts)
+        .map(move |start| &name[start..])
+}
+
+/// The regular files under the directory `dir` that `filters` let
+/// through, in name order, with the errors met walking it
+fn walk(dir: &Path, follow: Follow, filters: &Filters, errors: &mut Vec<UError>) -> Vec<PathBuf> {
+    let options = walk::Options {
+        follow,
+        is_sorted: true,
+        ..walk::Options::default()
+    };
+    let name = |path: &Path| path.file_name().unwrap_or_default().to_os_string();
+    let mut files = Vec::new();
+    walk::walk(
+        dir,
+        &options,
+        |entry, event| match event {
+            Event::Enter
+                if entry.depth > 0 && filters.is_dir_excluded(&name(&entry.path), false) =>
+            {
+                nyaa_core::debug!("skipping {}, excluded", quote(entry.path.as_os_str()));
+                Control::Skip
+            }
+            // Symbolic links not followed and special files are skipped
+            Event::Leaf
+                if entry.metadata.is_file()
+                    && filters.is_file_included(&name(&entry.path), false) =>
+            {
+                files.push(entry.path.clone());
+                Control::Continue
+            }
+            _ => Control::Continue,
+        },
+        |e| errors.push(e.with_code(EXIT_TROUBLE)),
+    );
+    files
+}
+
+/// A file to search, or an error reported in its turn among them
+enum Task {
+    Search(Search),
+    Report(UError),
+}
+
+struct Search {
+    file: PathBuf,
+    /// Whether the file is an operand, which may name the stdin
+    is_operand: bool,
+    with_filename: bool,
+}
+
+impl Search {
+    fn input(&self) -> Input<'_> {
+        match self.is_operand {
+            true => Input::from_operand(&self.file),
+            false => Input::Path(&self.file),
+        }
+    }
+}
+
+/// Name of `input` in the output
+fn name(input: &Input) -> String {
+    if input.is_stdin() {
+        i18n::tr("(standard input)").to_string()
+    } else {
+        input
+            .quoted_name()
+            .map_or_else(|| "-".to_string(), |name| name.into_owned())
+    }
+}
+
+/// Search the file `input`, mapped if it is large enough
+fn run(
+    input: &Input,
+    matcher: &dyn Matcher,
+    options: &Options,
+    out: &mut impl Write,
+) -> UResult<Found> {
+    let name = name(input);
+    let read_error = |e| UError::from(e).with_name(&name);
+    let found = match input.open().map_err(read_error)? {
+        Reader::File(file) => {
+            let metadata = file.metadata().map_err(read_error)?;
+            match FileData::new(file, &metadata) {
+                FileData::Mapped(mapping) => {
+                    let found = crate::search_slice(&mapping, &name, matcher, options, out);
+                    mapping.check().map_err(read_error)?;
+                    found
+                }
+                FileData::Stream(file) => crate::search(file, &name, matcher, options, out),
+            }
+        }
+        reader => crate::search(reader, &name, matcher, options, out),
+    };
+    found.map_err(|e| match e {
+        Error::Read(e) => read_error(e),
+        Error::Write(e) => output::write_error(e),
+    })
+}
+
+/// Run `grep` with the command line `args`, starting with the program name
+pub fn main(args: Vec<OsString>) -> ExitCode {
+    let mut status = Status::default();
+    let Some(args) = status.check(UTIL, response::expand(args)) else {
+        return status.into();
+    };
+    let args = posix::operands_last(&command(), args);
+    let matches = command().get_matches_from(args);
+    let args = Args::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+    trace::init(UTIL, false);
+    let trouble = |e: UError| e.with_code(EXIT_TROUBLE);
+    // Pattern files are input as much as the operands are
+    if status
+        .check(UTIL, args.sandbox.apply().map_err(trouble))
+        .is_none()
+    {
+        return status.into();
+    }
+    let mode = if args.is_quiet {
+        Mode::Quiet
+    } else if args.is_files_with_matches {
+        Mode::FilesWithMatches
+    } else if args.is_files_without_match {
+        Mode::FilesWithoutMatch
+    } else if args.is_count {
+        Mode::Count
+    } else {
+        Mode::Lines
+    };
+    let Some(syntax) = status.check(UTIL, syntax(&args)) else {
+        return status.into();
+    };
+    let Some(context) = status.check(UTIL, context(&args)) else {
+        return status.into();
+    };
+    let Some((patterns, files)) = status.check(UTIL, patterns(&args).map_err(trouble)) else {
+        return status.into();
+    };
+    let locale = Locale::ctype();
+    let matcher = match matcher::new(&patterns, syntax, args.is_ignore_case, locale) {
+        Ok((matcher, warnings)) => {
+            for warning in warnings {
+                error::notice(UTIL, warning);
+            }
+            matcher
+        }
+        Err(message) => {
+            status.report(UTIL, &UError::new(EXIT_TROUBLE, message));
+            return status.into();
+        }
+    };
+    // Where no line can be selected the files aren't even opened, unless
+    // they are listed for that
+    let is_hopeless = if args.is_invert {
+        !patterns.is_empty() && patterns.iter().all(Vec::is_empty)
+    } else {
+        patterns.is_empty()
+    };
+    if is_hopeless && mode != Mode::FilesWithoutMatch {
+        return ExitCode::FAILURE;
+    }
+    let is_recursive = args.is_recursive || args.is_dereference_recursive;
+    let follow = if args.is_dereference_recursive {
+        Follow::Always
+    } else {
+        Follow::Operands
+    };
+    // Without operands a recursive search is of the working directory,
+    // whose files are named without a leading `./`
+    let is_implicit = files.is_empty();
+    let files = match files {
+        [] if is_recursive => vec![OsString::from(".")],
+        [] => vec![OsString::from("-")],
+        files => files.to_vec(),
+    };
+    let with_filename = if args.is_with_filename || args.is_no_filename {
+        Some(args.is_with_filename)
+    } else {
+        None
+    };
+    let binary_files = if args.is_text {
+        BinaryFiles::Text
+    } else if args.is_without_match {
+        BinaryFiles::WithoutMatch
+    } else {
+        args.binary_files.unwrap_or_default()
+    };
+    let options = Options {
+        is_invert: args.is_invert,
+        is_line_number: args.is_line_number,
+        is_byte_offset: args.is_byte_offset,
+        with_filename: false,
+        is_only_matching: args.is_only_matching,
+        context,
+        is_separated: false,
+        mode,
+        binary_files,
+        locale,
+    };
+    let filters = Filters::new(&matches);
+    let mut tasks = Vec::new();
+    for file in &files {
+        let operand = Path::new(file);
+        let input = Input::from_operand(operand);
+        let is_dir = match input.metadata() {
+            _ if input.is_stdin() => false,
+            Ok(metadata) => metadata.is_dir(),
+            Err(e) => {
+                let name = input.quoted_name().unwrap_or_default();
+                tasks.push(Task::Report(trouble(UError::from(e).with_name(name))));
+                continue;
+            }
+        };
+        let is_searched = if is_dir {
+            is_implicit || !filters.is_dir_excluded(file, true)
+        } else {
+            input.is_stdin() || filters.is_file_included(file, true)
+        };
+        if !is_searched {
+            continue;
+        }
+        if !(is_dir && is_recursive) {
+            tasks.push(Task::Search(Search {
+                file: operand.to_path_buf(),
+                is_operand: true,
+                with_filename: with_filename.unwrap_or(files.len() > 1),
+            }));
+            continue;
+        }
+        let mut errors = Vec::new();
+        let walked = walk(operand, follow, &filters, &mut errors);
+        tasks.extend(errors.into_iter().map(Task::Report));
+        tasks.extend(walked.into_iter().map(|path| {
+            Task::Search(Search {
+                file: match is_implicit {
+                    true => path.strip_prefix(".").unwrap_or(&path).to_path_buf(),
+                    false => path,
+                },
+                is_operand: false,
+                with_filename: with_filename.unwrap_or(true),
+            })
+        }));
+    }
+    // Operands reading the same descriptor, like several stdin ones, have
+    // to be read in order, the first one consumes the whole stream
+    let mut descriptors: Vec<i32> = tasks
+        .iter()
+        .filter_map(|task| match task {
+            Task::Search(search) => search.input().descriptor(),
+            Task::Report(_) => None,
+        })
+        .collect();
+    descriptors.sort_unstable();
+    let jobs = if descriptors.windows(2).any(|pair| pair[0] == pair[1]) {
+        1
+    } else {
+        args.jobs.jobs()
+    };
+    // Files searched ahead of their turn have their output kept until it,
+    // the others are output as they are searched
+    let is_parallel = jobs.min(tasks.len()) > 1;
+    // Whether groups of lines are separated
+    let is_grouped = mode == Mode::Lines && options.context.is_some();
+    // Whether nothing can change the exit status any more
+    let is_done = AtomicBool::new(false);
+    let mut out = OutputSink::stdout();
+    let mut is_selected = false;
+    let mut is_separated = false;
+    jobs::for_each_ordered(
+        &tasks,
+        jobs,
+        |task| {
+            let Task::Search(search) = task else {
+                return None;
+            };
+            if !is_parallel || is_done.load(Ordering::Relaxed) {
+                return None;
+            }
+            // The separator before the first group of lines is taken off
+            // in its turn unless lines were output with context before
+            let options = Options {
+                with_filename: search.with_filename,
+                is_separated: is_grouped,
+                ..options.clone()
+            };
+            let mut buf = Vec::new();
+            let found = run(&search.input(), &*matcher, &options, &mut buf);
+            Some((buf, found))
+        },
+        |task, searched| {
+            if is_done.load(Ordering::Relaxed) {
+                return;
+            }
+            let search = match task {
+                Task::Search(search) => search,
+                Task::Report(e) => return status.report(UTIL, e),
+            };
+            let input = search.input();
+            let options = Options {
+                with_filename: search.with_filename,
+                is_separated,
+                ..options.clone()
+            };
+            let found = match searched {
+                Some((buf, found)) => {
+                    let buf = match is_grouped && !is_separated {
+                        true => buf.strip_prefix(b"--\n").unwrap_or(&buf),
+                        false => &buf[..],
+                    };
+                    out.write_all(buf).map_err(output::write_error).and(found)
+                }
+                None => run(&input, &*matcher, &options, &mut out),
+            };
+            let found = found.and_then(|found| {
+                if found.is_binary_match {
+                    // After the lines output before it, like GNU grep
+                    out.flush().map_err(output::write_error)?;
+                    let name = name(&input);
+                    error::notice(UTIL, format!("{name}: {}", i18n::tr("binary file matches")));
+                }
+                Ok(found)
+            });
+            match found {
+                Ok(found) => {
+                    is_selected |= found.selected > 0;
+                    is_separated |= found.has_context_output;
+                }
+                Err(e) => status.report(UTIL, &trouble(e)),
+            }
+            if mode == Mode::Quiet && is_selected {
+                is_done.store(true, Ordering::Relaxed);
+            }
+        },
+    );
+    if is_done.into_inner() {
+        return ExitCode::SUCCESS;
+    }
+    status.check(UTIL, out.finish().map_err(trouble));
+    match (status.is_ok(), is_selected) {
+        (false, _) => status.into(),
+        (true, true) => ExitCode::SUCCESS,
+        (true, false) => ExitCode::FAILURE,
+    }
+}
@@ -0,0 +1,725 @@
+//! Searching of lines matching patterns with the semantics of `grep`.
+//!
+//! Patterns are POSIX basic or extended regular expressions, translated by
+//! [`pattern`], fixed strings or Perl-compatible regular expressions, each
+//! syntax matched by a [`Matcher`] of its own. Input is read a buffer at a
+//! time as raw bytes, so input of any size and encoding is searched. The
+//! lines of a buffer that can't match are skipped together, the others are
+//! matched one at a time without their terminator. The selected lines are
+//! output, or only their matches, with the lines around them as context
+//! kept by [`nyaa_core::context`]. Or they are counted, or only the files
+//! with or without them are output.
+//!
+//! Like GNU `grep`, input is binary from the read buffer with a NUL on,
+//! its NULs ending lines, and only the first line selected there is
+//! reported instead of output. Lines that aren't text in the locale are
+//! reported instead of output as well.
+//!
+//! ```
+//! use nyaa_core::locale::Locale;
+//! use nyaa_grep::Syntax;
+//!
+//! let patterns = [b"b\\+".to_vec()];
+//! let (matcher, _) = nyaa_grep::matcher::new(&patterns, Syntax::Basic, false, Locale::C).unwrap();
+//! let options = nyaa_grep::Options::default();
+//! let mut out = Vec::new();
+//! nyaa_grep::search(&b"abc\nxyz\nbb\n"[..], "-", &*matcher, &options, &mut out).unwrap();
+//! assert_eq!(out, b"abc\nbb\n");
+//! ```
+
+pub mod cli;
+pub mod matcher;
+pub mod pattern;
+
+pub use matcher::{Matcher, Syntax};
+
+use nyaa_core::context::Context;
+use nyaa_core::locale::Locale;
+use nyaa_core::record::{Records, Terminator};
+use std::cell::Cell;
+use std::io::{self, BufRead, BufReader, Read, Write};
+
+/// Size of the buffers input is read and checked for NULs in, the one of
+/// GNU `grep`
+const BUFFER_SIZE: usize = 96 * 1024;
+
+/// What is output of the selected lines
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Mode {
+    /// The lines
+    #[default]
+    Lines,
+    /// Their number for every file
+    Count,
+    /// The names of the files with any, the first one ending the search
+    FilesWithMatches,
+    /// The names of the files without any
+    FilesWithoutMatch,
+    /// Nothing, the first one ending the search
+    Quiet,
+}
+
+/// How input with binary data is searched
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum BinaryFiles {
+    /// The first line selected in binary data is reported instead of
+    /// output, ending the search
+    #[default]
+    Binary,
+    /// Input with NULs has no lines selected, lines that aren't text are
+    /// left out of the output
+    WithoutMatch,
+    /// Binary data is searched and output like text
+    Text,
+}
+
+/// Which lines are selected and how they are output
+#[derive(Clone, Debug, Default)]
+pub struct Options {
+    /// Whether the lines not matching are selected instead
+    pub is_invert: bool,
+    /// Whether output lines start with their number in the file
+    pub is_line_number: bool,
+    /// Whether output lines start with the offset in the file of their
+    /// first byte, or of the match with `is_only_matching`
+    pub is_byte_offset: bool,
+    /// Whether output lines and counts start with the name of the file
+    pub with_filename: bool,
+    /// Whether only the matches in the selected lines are output, each on
+    /// a line of its own
+    pub is_only_matching: bool,
+    /// Lines output before and after the selected ones, `None` for no
+    /// context and no `--` between the groups of lines that aren't adjacent
+    pub context: Option<(usize, usize)>,
+    /// Whether lines were selected for output with context before, in
+    /// other files, so `--` goes before the first group of lines
+    pub is_separated: bool,
+    pub mode: Mode,
+    pub binary_files: BinaryFiles,
+    /// Locale whose text the lines are, any bytes in the C locale
+    pub locale: Locale,
+}
+
+/// Outcome of a [`search`]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Found {
+    /// Lines selected, none for input with NULs whose binary data doesn't
+    /// match
+    pub selected: u64,
+    /// Whether selected lines were binary data, reported instead of output
+    pub is_binary_match: bool,
+    /// Whether lines were selected for output with context, which later
+    /// groups of lines are separated from
+    pub has_context_output: bool,
+}
+
+/// A buffered reader noting whether any of its buffers had a NUL, checked
+/// a buffer at a time as it is filled
+struct NulDetector<'a, R> {
+    reader: R,
+    /// Bytes at the start of the buffer already checked
+    checked: usize,
+    has_nul: &'a Cell<bool>,
+}
+
+impl<R: BufRead> Read for NulDetector<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let len = {
+            let available = self.fill_buf()?;
+            let len = available.len().min(buf.len());
+            buf[..len].copy_from_slice(&available[..len]);
+            len
+        };
+        self.consume(len);
+        Ok(len)
+    }
+}
+
+impl<R: BufRead> BufRead for NulDetector<'_, R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        let buf = self.reader.fill_buf()?;
+        if self.checked < buf.len() {
+            if buf[self.checked..].contains(&0) {
+                self.has_nul.set(true);
+            }
+            self.checked = buf.len();
+        }
+        Ok(buf)
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.reader.consume(amt);
+        self.checked = self.checked.saturating_sub(amt);
+    }
+}
+
+/// Data in memory read a buffer at a time, like a file is, so binary data
+/// is found at the same lines
+struct Chunks<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl Read for Chunks<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let len = {
+            let available = self.fill_buf()?;
+            let len = available.len().min(buf.len());
+            buf[..len].copy_from_slice(&available[..len]);
+            len
+        };
+        self.consume(len);
+        Ok(len)
+    }
+}
+
+impl BufRead for Chunks<'_> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        let end = (self.pos / BUFFER_SIZE + 1) * BUFFER_SIZE;
+        Ok(&self.data[self.pos..end.min(self.data.len())])
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.pos += amt;
+    }
+}
+
+/// Failure of [`search`], reading its input or writing the output
+#[derive(Debug)]
+pub enum Error {
+    Read(io::Error),
+    Write(io::Error),
+}
+
+/// Output of `input`, named `name`, what `options` ask of the lines that
+/// `matcher` selects. Only the first selected line is read if it ends the
+/// search.
+pub fn search(
+    input: impl Read,
+    name: &str,
+    matcher: &dyn Matcher,
+    options: &Options,
+    out: &mut impl Write,
+) -> Result<Found, Error> {
+    let reader = BufReader::with_capacity(BUFFER_SIZE, input);
+    search_buffered(reader, name, matcher, options, out)
+}
+
+/// Like [`search`], of input in memory, like a mapped file
+pub fn search_slice(
+    data: &[u8],
+    name: &str,
+    matcher: &dyn Matcher,
+    options: &Options,
+    out: &mut impl Write,
+) -> Result<Found, Error> {
+    let reader = Chunks { data, pos: 0 };
+    search_buffered(reader, name, matcher, options, out)
+}
+
+fn search_buffered(
+    reader: impl BufRead,
+    name: &str,
+    matcher: &dyn Matcher,
+    options: &Options,
+    out: &mut impl Write,
+) -> Result<Found, Error> {
+    let terminator = Terminator::Newline;
+    let has_nul = Cell::new(false);
+    let reader = NulDetector {
+        reader,
+        checked: 0,
+        has_nul: &has_nul,
+    };
+    let mut records = Records::new(reader, terminator);
+    let mut context = match options.mode {
+        Mode::Lines => options
+            .context
+            .map(|(before, after)| Context::new(before, after)),
+        _ => None,
+    };
+    let mut selected = 0;
+    let mut number = 0;
+    let mut offset = 0;
+    // Lines selected before the input turned out to be binary
+    let mut selected_as_text = None;
+    let mut has_encoding_errors = false;
+    'records: loop {
+        // The lines that can't be selected are skipped a buffer at a time,
+        // but for lines still output as context
+        let is_skipping = !options.is_invert
+            && selected_as_text.is_none()
+            && !context.as_ref().is_some_and(Context::is_after);
+        if is_skipping {
+            let reader = records.get_mut();
+            let buf = reader.fill_buf().map_err(Error::Read)?;
+            // Binary data is split at its NULs, into lines matched one at
+            // a time
+            if options.binary_files == BinaryFiles::Text || !has_nul.get() {
+                let skipped = unmatched(buf, matcher);
+                let count = memchr::memchr_iter(b'\n', skipped).count() as u64;
+                if let Some(context) = &mut context {
+                    let before = options.context.map_or(0, |(before, _)| before);
+                    // The lines kept as context of the next selected one
+                    let start = last_lines(skipped, before);
+                    let kept = &skipped[start..];
+                    let mut number =
+                        number + count - memchr::memchr_iter(b'\n', kept).count() as u64;
+                    let mut offset = offset + start as u64;
+                    for record in kept.split_inclusive(|&b| b == b'\n') {
+                        number += 1;
+                        context.unselected(number, offset, terminator.strip(record));
+                        offset += record.len() as u64;
+                    }
+                }
+                number += count;
+                offset += skipped.len() as u64;
+                let len = skipped.len();
+                reader.consume(len);
+            }
+        }
+        let Some(record) = records.next_record().map_err(Error::Read)? else {
+            break;
+        };
+        if options.binary_files != BinaryFiles::Text && selected_as_text.is_none() && has_nul.get()
+        {
+            if options.binary_files == BinaryFiles::WithoutMatch {
+                selected = 0;
+                break;
+            }
+            selected_as_text = Some(selected);
+        }
+        let line = terminator.strip(record);
+        if selected_as_text.is_some() {
+            // Binary data is only searched for whether it has a line
+            // selected, or counted
+            for line in line.split(|&b| b == 0) {
+                if matcher.is_match(line) == options.is_invert {
+                    continue;
+                }
+                selected += 1;
+                if options.mode != Mode::Count {
+                    break 'records;
+                }
+            }
+            continue;
+        }
+        number += 1;
+        let line_offset = offset;
+        offset += record.len() as u64;
+        let mut prefix = Prefix {
+            name,
+            number,
+            offset: line_offset,
+            separator: b'-',
+        };
+        if matcher.is_match(line) == options.is_invert {
+            if let Some(context) = &mut context {
+                if context.unselected(number, line_offset, line) {
+                    if prefix
+                        .output(line, matcher, options, out)
+                        .map_err(Error::Write)?
+                    {
+                        context.written(number);
+                    } else {
+                        has_encoding_errors |= options.binary_files == BinaryFiles::Binary;
+                        context.unwritten(number, line_offset, line);
+                    }
+                }
+            }
+            continue;
+        }
+        selected += 1;
+        match options.mode {
+            Mode::Lines => {
+                if let Some(context) = &mut context {
+                    let is_first = !context.is_started();
+                    let (is_gap, before) = context.selected(number);
+                    if is_gap || (is_first && options.is_separated) {
+                        out.write_all(b"--\n").map_err(Error::Write)?;
+                    }
+                    let mut last = None;
+                    for line in before {
+                        let prefix = Prefix {
+                            number: line.number,
+                            offset: line.offset,
+                            ..prefix
+                        };
+                        if prefix
+                            .output(&line.data, matcher, options, out)
+                            .map_err(Error::Write)?
+                        {
+                            last = Some(line.number);
+                        } else {
+                            has_encoding_errors |= options.binary_files == BinaryFiles::Binary;
+                        }
+                    }
+                    if let Some(last) = last {
+                        context.written(last);
+                    }
+                }
+                prefix.separator = b':';
+                let is_written = prefix
+                    .output(line, matcher, options, out)
+                    .map_err(Error::Write)?;
+                match &mut context {
+                    Some(context) if is_written => context.written(number),
+                    Some(context) => context.unwritten(number, line_offset, line),
+                    None => (),
+                }
+                if !is_written {
+                    has_encoding_errors |= options.binary_files == BinaryFiles::Binary;
+                }
+            }
+            Mode::Count => (),
+            Mode::FilesWithMatches | Mode::FilesWithoutMatch | Mode::Quiet => break,
+        }
+    }
+    let is_listed = match options.mode {
+        Mode::Count => {
+            if options.with_filename {
+                write!(out, "{name}:").map_err(Error::Write)?;
+            }
+            writeln!(out, "{selected}").map_err(Error::Write)?;
+            false
+        }
+        Mode::FilesWithMatches => selected > 0,
+        Mode::FilesWithoutMatch => selected == 0,
+        Mode::Lines | Mode::Quiet => false,
+    };
+    if is_listed {
+        writeln!(out, "{name}").map_err(Error::Write)?;
+    }
+    let is_binary_match = options.mode == Mode::Lines
+        && (has_encoding_errors || selected_as_text.is_some_and(|text| text < selected));
+    Ok(Found {
+        selected,
+        is_binary_match,
+        has_context_output: context.is_some_and(|context| context.is_started()),
+    })
+}
+
+/// The lines at the start of `buf`, whole in it, before the first one that
+/// `matcher` may select
+fn unmatched<'a>(buf: &'a [u8], matcher: &dyn Matcher) -> &'a [u8] {
+    let Some(end) = memchr::memrchr(b'\n', buf) else {
+        return &[];
+    };
+    let lines = &buf[..=end];
+    match matcher.candidate(lines) {
+        Some(start) => &lines[..memchr::memrchr(b'\n', &lines[..start]).map_or(0, |end| end + 1)],
+        None => lines,
+    }
+}
+
+/// Offset of the last `count` of the terminated `lines`, or of all of them
+/// if there are fewer
+fn last_lines(lines: &[u8], count: usize) -> usize {
+    let mut start = lines.len();
+    for _ in 0..count {
+        if start == 0 {
+            break;
+        }
+        start = memchr::memrchr(b'\n', &lines[..start - 1]).map_or(0, |end| end + 1);
+    }
+    start
+}
+
+/// Whether `line` is output as text, rather than reported as binary data
+fn is_text(line: &[u8], options: &Options) -> bool {
+    options.binary_files == BinaryFiles::Text
+        || options.locale == Locale::C
+        || std::str::from_utf8(line).is_ok()
+}
+
+/// What output lines start with, as far as `options` ask for it
+#[derive(Clone, Copy)]
+struct Prefix<'a> {
+    /// Name of the file
+    name: &'a str,
+    number: u64,
+    offset: u64,
+    /// What follows every part, `:` for selected lines and `-` for context
+    separator: u8,
+}
+
+impl Prefix<'_> {
+    /// Output `line`, selected or context as the separator says, or only
+    /// its matches with `is_only_matching`. Returns whether it was written,
+    /// which it isn't if it, or a match, isn't text.
+    fn output(
+        &self,
+        line: &[u8],
+        matcher: &dyn Matcher,
+        options: &Options,
+        out: &mut impl Write,
+    ) -> io::Result<bool> {
+        if !options.is_only_matching {
+            if !is_text(line, options) {
+                return Ok(false);
+            }
+            self.write_line(line, options, out)?;
+            return Ok(true);
+        }
+        // Only the lines matching have anything output, context with -v
+        if (self.separator == b':') == options.is_invert {
+            return Ok(true);
+        }
+        for range in matcher::find_iter(matcher, line) {
+            if !is_text(&line[range.clone()], options) {
+                return Ok(false);
+            }
+            let prefix = Prefix {
+                offset: self.offset + range.start as u64,
+                ..*self
+            };
+            prefix.write_line(&line[range], options, out)?;
+        }
+        Ok(true)
+    }
+
+    /// Output `line` after the prefix. The line is terminated even if it
+    /// isn't in the input.
+    fn write_line(&self, line: &[u8], options: &Options, out: &mut impl Write) -> io::Result<()> {
+        let separator = char::from(self.separator);
+        if options.with_filename {
+            write!(out, "{}{separator}", self.name)?;
+        }
+        if options.is_line_number {
+            write!(out, "{}{separator}", self.number)?;
+        }
+        if options.is_byte_offset {
+            write!(out, "{}{separator}", self.offset)?;
+        }
+        out.write_all(line)?;
+        out.write_all(b"\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn new(patterns: &[&str], is_ignore_case: bool, locale: Locale) -> Box<dyn Matcher> {
+        let patterns: Vec<_> = patterns.iter().map(|p| p.as_bytes().to_vec()).collect();
+        matcher::new(&patterns, Syntax::Extended, is_ignore_case, locale)
+            .unwrap()
+            .0
+    }
+
+    fn matcher(patterns: &[&str]) -> Box<dyn Matcher> {
+        new(patterns, false, Locale::C)
+    }
+
+    fn search_str(input: &str, matcher: &dyn Matcher, options: &Options) -> (String, u64) {
+        let mut out = Vec::new();
+        let found = search(input.as_bytes(), "f", matcher, options, &mut out).unwrap();
+        (String::from_utf8(out).unwrap(), found.selected)
+    }
+
+    #[test]
+    fn matches_any_pattern() {
+        let matcher = matcher(&["^a", "c$", "x{2}"]);
+        assert!(matcher.is_match(b"ab"));
+        assert!(matcher.is_match(b"bc"));
+        assert!(matcher.is_match(b"axxb"));
+        assert!(!matcher.is_match(b"ba"));
+        assert!(!matcher.is_match(b"cb"));
+        assert!(!self::matcher(&[]).is_match(b""));
+        assert!(self::matcher(&[""]).is_match(b"x"));
+    }
+
+    #[test]
+    fn ignores_case() {
+        assert!(new(&["[a-c]X"], true, Locale::C).is_match(b"Bx"));
+        let matcher = new(&["é"], true, Locale::Unicode);
+        assert!(matcher.is_match("É".as_bytes()));
+    }
+
+    #[test]
+    fn matches_bytes() {
+        assert!(matcher(&["a.b"]).is_match(b"a\xffb"));
+        let matcher = new(&["a.b"], false, Locale::Unicode);
+        assert!(!matcher.is_match(b"a\xffb"));
+        assert!(matcher.is_match("aéb".as_bytes()));
+    }
+
+    #[test]
+    fn outputs_lines() {
+        let matcher = matcher(&["a"]);
+        let mut options = Options::default();
+        assert_eq!(
+            search_str("a\nb\nca", &*matcher, &options),
+            ("a\nca\n".into(), 2)
+        );
+        options.is_invert = true;
+        options.is_line_number = true;
+        options.with_filename = true;
+        assert_eq!(
+            search_str("a\nb\nca", &*matcher, &options),
+            ("f:2:b\n".into(), 1)
+        );
+    }
+
+    #[test]
+    fn outputs_context() {
+        let matcher = matcher(&["x"]);
+        let input = "a\nx\nb\nc\nd\nx\ne\n";
+        let mut options = Options {
+            context: Some((1, 1)),
+            is_line_number: true,
+            ..Options::default()
+        };
+        assert_eq!(
+            search_str(input, &*matcher, &options).0,
+            "1-a\n2:x\n3-b\n--\n5-d\n6:x\n7-e\n"
+        );
+        options.context = Some((0, 0));
+        options.is_separated = true;
+        assert_eq!(
+            search_str(input, &*matcher, &options).0,
+            "--\n2:x\n--\n6:x\n"
+        );
+        options.context = Some((2, 0));
+        options.is_separated = false;
+        options.is_byte_offset = true;
+        assert_eq!(
+            search_str(input, &*matcher, &options).0,
+            "1-0-a\n2:2:x\n--\n4-6-c\n5-8-d\n6:10:x\n"
+        );
+    }
+
+    #[test]
+    fn outputs_only_matches() {
+        let matcher = matcher(&["a|ab", "x*"]);
+        let mut options = Options {
+            is_only_matching: true,
+            is_byte_offset: true,
+            ..Options::default()
+        };
+        assert_eq!(
+            search_str("zab xxa\nc\n", &*matcher, &options),
+            ("1:ab\n4:xx\n6:a\n".into(), 2)
+        );
+        options.is_invert = true;
+        assert_eq!(
+            search_str("a\nb\n", &*self::matcher(&["a"]), &options),
+            ("".into(), 1)
+        );
+    }
+
+    #[test]
+    fn skips_lines_not_matching() {
+        let input: String = (1..=30000)
+            .map(|number| match number % 10000 {
+                0 => "needle\n",
+                _ => "hay\n",
+            })
+            .collect();
+        let options = Options {
+            context: Some((1, 1)),
+            is_line_number: true,
+            ..Options::default()
+        };
+        let expected = "9999-hay\n10000:needle\n10001-hay\n--\n19999-hay\n20000:needle\n\
+            20001-hay\n--\n29999-hay\n30000:needle\n";
+        let fixed = matcher::new(&[b"needle".to_vec()], Syntax::Fixed, false, Locale::C)
+            .unwrap()
+            .0;
+        for matcher in [matcher(&["^ne+dle$"]), fixed] {
+            assert_eq!(
+                search_str(&input, &*matcher, &options),
+                (expected.into(), 3)
+            );
+            let mut out = Vec::new();
+            search_slice(input.as_bytes(), "f", &*matcher, &options, &mut out).unwrap();
+            assert_eq!(out, expected.as_bytes());
+        }
+    }
+
+    #[test]
+    fn counts_and_lists_files() {
+        let matcher = matcher(&["a"]);
+        let mut options = Options {
+            mode: Mode::Count,
+            ..Options::default()
+        };
+        assert_eq!(
+            search_str("a\nb\na\n", &*matcher, &options),
+            ("2\n".into(), 2)
+        );
+        options.mode = Mode::FilesWithMatches;
+        assert_eq!(
+            search_str("a\nb\na\n", &*matcher, &options),
+            ("f\n".into(), 1)
+        );
+        assert_eq!(search_str("b\n", &*matcher, &options), ("".into(), 0));
+        options.mode = Mode::FilesWithoutMatch;
+        assert_eq!(search_str("a\n", &*matcher, &options), ("".into(), 1));
+        assert_eq!(search_str("b\n", &*matcher, &options), ("f\n".into(), 0));
+        options.mode = Mode::Quiet;
+        assert_eq!(search_str("a\na\n", &*matcher, &options), ("".into(), 1));
+    }
+
+    #[test]
+    fn reports_binary_data() {
+        let matcher = matcher(&["^a"]);
+        let search_bytes = |input: &[u8], options: &Options| {
+            let mut out = Vec::new();
+            let found = search(input, "f", &*matcher, options, &mut out).unwrap();
+            (out, found)
+        };
+        let mut options = Options::default();
+        let found = |selected, is_binary_match| Found {
+            selected,
+            is_binary_match,
+            has_context_output: false,
+        };
+        let input = b"ab\nb\0ac\nad\n";
+        assert_eq!(search_bytes(input, &options), (vec![], found(1, true)));
+        options.mode = Mode::Count;
+        assert_eq!(
+            search_bytes(input, &options),
+            (b"3\n".to_vec(), found(3, false))
+        );
+        options.binary_files = BinaryFiles::WithoutMatch;
+        assert_eq!(
+            search_bytes(input, &options),
+            (b"0\n".to_vec(), found(0, false))
+        );
+        options.mode = Mode::Lines;
+        options.binary_files = BinaryFiles::Text;
+        let (out, found_text) = search_bytes(input, &options);
+        assert_eq!((out, found_text), (b"ab\nad\n".to_vec(), found(2, false)));
+        // Data in memory is binary from the same buffer on
+        options.binary_files = BinaryFiles::Binary;
+        let mut input = b"ab\n".repeat(BUFFER_SIZE);
+        input[BUFFER_SIZE * 2] = 0;
+        let (out, found) = search_bytes(&input, &options);
+        let mut out_slice = Vec::new();
+        let found_slice = search_slice(&input, "f", &*matcher, &options, &mut out_slice).unwrap();
+        assert_eq!((out_slice, found_slice), (out, found));
+    }
+
+    #[test]
+    fn reports_lines_not_text() {
+        let matcher = matcher(&["a"]);
+        let mut options = Options {
+            locale: Locale::Unicode,
+            ..Options::default()
+        };
+        let mut out = Vec::new();
+        let found = search(&b"a\xff\nba\n"[..], "f", &*matcher, &options, &mut out).unwrap();
+        assert_eq!(
+            (out, found.selected, found.is_binary_match),
+            (b"ba\n".to_vec(), 2, true)
+        );
+        options.locale = Locale::C;
+        let mut out = Vec::new();
+        let found = search(&b"a\xff\nba\n"[..], "f", &*matcher, &options, &mut out).unwrap();
+        assert_eq!(
+            (out, found.is_binary_match),
+            (b"a\xff\nba\n".to_vec(), false)
+        );
+    }
+}
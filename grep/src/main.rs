@@ -0,0 +1,6 @@
+use std::process::ExitCode;
+
+fn main() -> ExitCode {
+    nyaa_core::init();
+    nyaa_grep::cli::main(std::env::args_os().collect())
+}
@@ -0,0 +1,632 @@
+//! Matching of lines by patterns, with a [`Matcher`] for every syntax of
+//! them, so searching doesn't depend on how the patterns are matched.
+//! Regular expressions are translated for `regex-automata`, but for those
+//! with back-references which are matched by backtracking, fixed strings
+//! are looked for by `memchr`, or by Aho-Corasick when there are several.
+//!
+//! Before lines are matched one at a time, a matcher looks for the first
+//! line that may match in a whole buffer of them, which skips the lines
+//! before at the speed of a substring search: the literals of regexes are
+//! found by the prefilters of `regex-automata`, with `memchr` too.
+
+use crate::pattern::{self, Node};
+use aho_corasick::AhoCorasick;
+use memchr::memmem;
+use nyaa_core::locale::Locale;
+use regex_automata::meta::Regex;
+use regex_automata::util::syntax;
+use regex_automata::{Anchored, Input, MatchKind};
+use std::ops::Range;
+
+/// How patterns match, as `-G`, `-E`, `-F` and `-P` select
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Syntax {
+    /// POSIX basic regular expressions
+    #[default]
+    Basic,
+    /// POSIX extended regular expressions
+    Extended,
+    /// Strings matching themselves
+    Fixed,
+    /// Perl-compatible regular expressions, with the `pcre` feature
+    Perl,
+}
+
+/// Finds the lines that patterns match, and the matches in them
+pub trait Matcher: Sync {
+    /// Whether `line`, without its terminator, matches
+    fn is_match(&self, line: &[u8]) -> bool;
+
+    /// Offset in `lines`, each with its terminator, of a byte of the first
+    /// line that may match, `None` if none does. No line before it does.
+    fn candidate(&self, lines: &[u8]) -> Option<usize> {
+        let _ = lines;
+        Some(0)
+    }
+
+    /// The first match in `line`, without its terminator, starting at
+    /// `start` or after it. Of the matches starting there the longest is
+    /// found, as POSIX asks, but for Perl-compatible patterns.
+    fn find_at(&self, line: &[u8], start: usize) -> Option<Range<usize>>;
+}
+
+/// The non-empty matches in `line`, without its terminator, from its start
+pub fn find_iter<'a>(
+    matcher: &'a dyn Matcher,
+    line: &'a [u8],
+) -> impl Iterator<Item = Range<usize>> + 'a {
+    let mut start = 0;
+    std::iter::from_fn(move || {
+        while start <= line.len() {
+            let found = matcher.find_at(line, start)?;
+            if found.is_empty() {
+                start = found.start + 1;
+                continue;
+            }
+            start = found.end;
+            return Some(found);
+        }
+        None
+    })
+}
+
+/// Matcher of `patterns` in `syntax`, of the characters of `locale`, with
+/// the warnings about them. Fails with the message of the first invalid
+/// pattern, untranslated.
+pub fn new(
+    patterns: &[Vec<u8>],
+    syntax: Syntax,
+    is_ignore_case: bool,
+    locale: Locale,
+) -> Result<(Box<dyn Matcher>, Vec<String>), String> {
+    if patterns.is_empty() {
+        return Ok((Box::new(Nothing), Vec::new()));
+    }
+    let mut warnings = Vec::new();
+    let matcher: Box<dyn Matcher> = match syntax {
+        Syntax::Basic | Syntax::Extended => {
+            let mut regexes = Vec::new();
+            let mut nodes = Vec::new();
+            for pattern in patterns {
+                let translation = pattern::translate(pattern, syntax == Syntax::Extended, locale)?;
+                warnings.extend(translation.warnings);
+                regexes.push(translation.regex);
+                nodes.push(translation.node);
+            }
+            match regexes.into_iter().collect::<Option<Vec<_>>>() {
+                Some(regexes) => Box::new(RegexMatcher::new(&regexes, is_ignore_case, locale)?),
+                None => Box::new(BacktrackMatcher::new(&nodes, is_ignore_case, locale)?),
+            }
+        }
+        // Only ASCII letters are folded by Aho-Corasick
+        Syntax::Fixed
+            if is_ignore_case
+                && locale == Locale::Unicode
+                && !patterns.iter().all(|pattern| pattern.is_ascii()) =>
+        {
+            let regexes: Vec<_> = patterns
+                .iter()
+                .map(|pattern| pattern::fixed(pattern, locale))
+                .collect();
+            Box::new(RegexMatcher::new(&regexes, is_ignore_case, locale)?)
+        }
+        Syntax::Fixed => Box::new(FixedMatcher::new(patterns, is_ignore_case)?),
+        Syntax::Perl => perl(patterns, is_ignore_case, locale)?,
+    };
+    Ok((matcher, warnings))
+}
+
+/// Matcher of no patterns, which match no line
+struct Nothing;
+
+impl Matcher for Nothing {
+    fn is_match(&self, _: &[u8]) -> bool {
+        false
+    }
+
+    fn candidate(&self, _: &[u8]) -> Option<usize> {
+        None
+    }
+
+    fn find_at(&self, _: &[u8], _: usize) -> Option<Range<usize>> {
+        None
+    }
+}
+
+/// The syntax of the regexes of `locale`, which match bytes
+fn syntax_config(is_ignore_case: bool, locale: Locale) -> syntax::Config {
+    syntax::Config::new()
+        .unicode(locale == Locale::Unicode)
+        .utf8(false)
+        .case_insensitive(is_ignore_case)
+}
+
+/// Matcher of POSIX regular expressions translated for `regex-automata`,
+/// whose translations match no newline
+pub struct RegexMatcher {
+    /// The regexes joined
+    regex: Regex,
+    /// The same regex finding all matches, the longest of which is the one
+    /// POSIX asks for at the leftmost position the other finds
+    longest: Regex,
+}
+
+impl RegexMatcher {
+    /// Matcher of any of `regexes`, in the syntax of `regex-automata`
+    pub fn new(regexes: &[String], is_ignore_case: bool, locale: Locale) -> Result<Self, String> {
+        let alternatives: Vec<_> = regexes.iter().map(|regex| format!("(?:{regex})")).collect();
+        let pattern = alternatives.join("|");
+        let build = |match_kind| {
+            Regex::builder()
+                .configure(Regex::config().match_kind(match_kind))
+                // Anchors match at the ends of the lines of a buffer
+                .syntax(syntax_config(is_ignore_case, locale).multi_line(true))
+                .build(&pattern)
+                .map_err(|e| match e.size_limit() {
+                    Some(_) => pattern::TOO_BIG.to_string(),
+                    None => e.to_string(),
+                })
+        };
+        Ok(RegexMatcher {
+            regex: build(MatchKind::LeftmostFirst)?,
+            longest: build(MatchKind::All)?,
+        })
+    }
+}
+
+impl Matcher for RegexMatcher {
+    fn is_match(&self, line: &[u8]) -> bool {
+        self.regex.is_match(line)
+    }
+
+    fn candidate(&self, lines: &[u8]) -> Option<usize> {
+        self.regex.find(lines).map(|found| found.start())
+    }
+
+    fn find_at(&self, line: &[u8], start: usize) -> Option<Range<usize>> {
+        let found = self.regex.find(Input::new(line).range(start..))?;
+        let input = Input::new(line)
+            .range(found.start()..)
+            .anchored(Anchored::Yes);
+        Some(self.longest.find(input).unwrap_or(found).range())
+    }
+}
+
+/// Matcher of POSIX regular expressions with back-references, by
+/// backtracking over their nodes, whose atoms are regexes matched where
+/// they are. Every way of matching at a position is tried for the longest.
+pub struct BacktrackMatcher {
+    patterns: Vec<Step>,
+    /// Regexes of the atoms, which steps refer to by their index
+    atoms: Vec<Regex>,
+    /// Number of groups of the pattern with the most
+    groups: usize,
+    is_ignore_case: bool,
+    locale: Locale,
+}
+
+/// A node of a pattern, with its atoms compiled
+enum Step {
+    Atom(usize),
+    Concat(Vec<Step>),
+    Alternation(Vec<Step>),
+    /// A group, numbered from 1
+    Group(usize, Box<Step>),
+    /// A step repeated between the bounds
+    Repeat(Box<Step>, u32, Option<u32>),
+    BackReference(usize),
+}
+
+/// Groups of a pattern, the range of the text each matched last, `None`
+/// if it hasn't matched
+type Groups = Vec<Option<Range<usize>>>;
+
+impl BacktrackMatcher {
+    /// Matcher of any of the patterns `nodes`
+    pub fn new(nodes: &[Node], is_ignore_case: bool, locale: Locale) -> Result<Self, String> {
+        let mut atoms = Vec::new();
+        let mut groups = 0;
+        let patterns = nodes
+            .iter()
+            .map(|node| step(node, &mut atoms, &mut groups))
+            .collect();
+        let atoms = atoms
+            .iter()
+            .map(|atom| {
+                Regex::builder()
+                    .syntax(syntax_config(is_ignore_case, locale))
+                    .build(atom)
+                    .map_err(|e| e.to_string())
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(BacktrackMatcher {
+            patterns,
+            atoms,
+            groups,
+            is_ignore_case,
+            locale,
+        })
+    }
+
+    /// The first match in `line` starting at `start` or after it, the
+    /// longest of those starting there, or any of them if `is_any`
+    fn find(&self, line: &[u8], start: usize, is_any: bool) -> Option<Range<usize>> {
+        let search = Search {
+            matcher: self,
+            line,
+        };
+        let mut groups = vec![None; self.groups];
+        (start..=line.len()).find_map(|pos| {
+            let mut longest = None;
+            for pattern in &self.patterns {
+                search.step(pattern, pos, &mut groups, &mut |end, _| {
+                    longest = longest.max(Some(end));
+                    is_any
+                });
+                if is_any && longest.is_some() {
+                    break;
+                }
+            }
+            longest.map(|end| pos..end)
+        })
+    }
+
+    /// Whether the texts of a back-reference and of its group are the same
+    fn is_same(&self, a: &[u8], b: &[u8]) -> bool {
+        match self.locale {
+            _ if !self.is_ignore_case => a == b,
+            Locale::C => a.eq_ignore_ascii_case(b),
+            Locale::Unicode => {
+                String::from_utf8_lossy(a).to_lowercase()
+                    == String::from_utf8_lossy(b).to_lowercase()
+            }
+        }
+    }
+}
+
+/// The step of `node`, with its atoms added to `atoms` where they aren't
+/// yet and `groups` raised to the number of its last group
+fn step(node: &Node, atoms: &mut Vec<String>, groups: &mut usize) -> Step {
+    let mut steps = |nodes: &[Node]| nodes.iter().map(|node| step(node, atoms, groups)).collect();
+    match node {
+        Node::Leaf(regex) => Step::Atom(match atoms.iter().position(|atom| atom == regex) {
+            Some(index) => index,
+            None => {
+                atoms.push(regex.clone());
+                atoms.len() - 1
+            }
+        }),
+        Node::Concat(nodes) => Step::Concat(steps(nodes)),
+        Node::Alternation(nodes) => Step::Alternation(steps(nodes)),
+        Node::Group(number, node) => {
+            *groups = (*groups).max(*number);
+            Step::Group(*number, Box::new(step(node, atoms, groups)))
+        }
+        Node::Repeat(node, quantifier) => Step::Repeat(
+            Box::new(step(node, atoms, groups)),
+            quantifier.min,
+            quantifier.max,
+        ),
+        Node::BackReference(number) => Step::BackReference(*number),
+    }
+}
+
+/// A search of a line by backtracking
+struct Search<'a> {
+    matcher: &'a BacktrackMatcher,
+    line: &'a [u8],
+}
+
+/// What is matched after a step, from the end of its match with the
+/// groups then, which tells whether the search is done
+type Next<'n> = dyn FnMut(usize, &mut Groups) -> bool + 'n;
+
+impl Search<'_> {
+    /// Match `step` at `pos` in every way until `next` is done with one
+    fn step(&self, step: &Step, pos: usize, groups: &mut Groups, next: &mut Next) -> bool {
+        match step {
+            Step::Atom(index) => match self.atom(*index, pos) {
+                Some(end) => next(end, groups),
+                None => false,
+            },
+            Step::Concat(steps) => self.concat(steps, pos, groups, next),
+            Step::Alternation(steps) => steps.iter().any(|step| self.step(step, pos, groups, next)),
+            Step::Group(number, step) => self.step(step, pos, groups, &mut |end, groups| {
+                let outer = groups[number - 1].replace(pos..end);
+                let is_done = next(end, groups);
+                groups[number - 1] = outer;
+                is_done
+            }),
+            Step::Repeat(step, min, max) => self.repeat(step, (*min, *max), 0, pos, groups, next),
+            Step::BackReference(number) => {
+                let Some(group) = groups[number - 1].clone() else {
+                    return false;
+                };
+                let end = pos + group.len();
+                let is_same = self
+                    .line
+                    .get(pos..end)
+                    .is_some_and(|text| self.matcher.is_same(&self.line[group], text));
+                is_same && next(end, groups)
+            }
+        }
+    }
+
+    /// The end of the match of the atom of `index` at `pos`
+    fn atom(&self, index: usize, pos: usize) -> Option<usize> {
+        let input = Input::new(self.line).range(pos..).anchored(Anchored::Yes);
+        self.matcher.atoms[index]
+            .find(input)
+            .map(|found| found.end())
+    }
+
+    fn concat(&self, steps: &[Step], pos: usize, groups: &mut Groups, next: &mut Next) -> bool {
+        let Some((first, rest)) = steps.split_first() else {
+            return next(pos, groups);
+        };
+        self.step(first, pos, groups, &mut |end, groups| {
+            self.concat(rest, end, groups, next)
+        })
+    }
+
+    /// Match `step` repeated between `bounds` after `count` times, more
+    /// times first
+    fn repeat(
+        &self,
+        step: &Step,
+        (min, max): (u32, Option<u32>),
+        count: u32,
+        pos: usize,
+        groups: &mut Groups,
+        next: &mut Next,
+    ) -> bool {
+        if let Step::Atom(index) = *step {
+            // The ends of the repetitions of atoms are found in a loop, as
+            // they are repeated along whole lines
+            let mut ends = vec![pos];
+            while max.is_none_or(|max| ends.len() <= max as usize) {
+                let end = ends[ends.len() - 1];
+                match self.atom(index, end) {
+                    // An assertion holds however often it's repeated
+                    Some(next) if next == end => {
+                        ends.resize(ends.len().max(min as usize + 1), end);
+                        break;
+                    }
+                    Some(next) => ends.push(next),
+                    None => break,
+                }
+            }
+            return ends
+                .into_iter()
+                .enumerate()
+                .rev()
+                .take_while(|&(count, _)| count >= min as usize)
+                .any(|(_, end)| next(end, groups));
+        }
+        let can_repeat = max.is_none_or(|max| count < max);
+        let is_done = can_repeat
+            && self.step(step, pos, groups, &mut |end, groups| {
+                // Repeating what matched nothing matches nothing more
+                (end > pos || count < min)
+                    && self.repeat(step, (min, max), count + 1, end, groups, next)
+            });
+        is_done || (count >= min && next(pos, groups))
+    }
+}
+
+/// Matcher of fixed strings
+pub enum FixedMatcher {
+    /// A single string matched with its case
+    One(Box<memmem::Finder<'static>>),
+    /// Several strings, or strings matched in either case
+    Many(AhoCorasick),
+}
+
+impl FixedMatcher {
+    /// Matcher of any of `patterns`, with ASCII letters in either case if
+    /// `is_ignore_case`
+    pub fn new(patterns: &[Vec<u8>], is_ignore_case: bool) -> Result<Self, String> {
+        if let [pattern] = patterns {
+            if !is_ignore_case {
+                return Ok(FixedMatcher::One(Box::new(
+                    memmem::Finder::new(pattern).into_owned(),
+                )));
+            }
+        }
+        AhoCorasick::builder()
+            .match_kind(aho_corasick::MatchKind::LeftmostLongest)
+            .ascii_case_insensitive(is_ignore_case)
+            .build(patterns)
+            .map(FixedMatcher::Many)
+            .map_err(|e| e.to_string())
+    }
+}
+
+impl Matcher for BacktrackMatcher {
+    fn is_match(&self, line: &[u8]) -> bool {
+        self.find(line, 0, true).is_some()
+    }
+
+    fn find_at(&self, line: &[u8], start: usize) -> Option<Range<usize>> {
+        self.find(line, start, false)
+    }
+}
+
+impl Matcher for FixedMatcher {
+    fn is_match(&self, line: &[u8]) -> bool {
+        match self {
+            FixedMatcher::One(finder) => finder.find(line).is_some(),
+            FixedMatcher::Many(automaton) => automaton.is_match(line),
+        }
+    }
+
+    fn candidate(&self, lines: &[u8]) -> Option<usize> {
+        self.find_at(lines, 0).map(|found| found.start)
+    }
+
+    fn find_at(&self, line: &[u8], start: usize) -> Option<Range<usize>> {
+        match self {
+            FixedMatcher::One(finder) => {
+                let found = finder.find(&line[start..])?;
+                Some(start + found..start + found + finder.needle().len())
+            }
+            FixedMatcher::Many(automaton) => {
+                let input = aho_corasick::Input::new(line).range(start..);
+                automaton.find(input).map(|found| found.range())
+            }
+        }
+    }
+}
+
+/// Matcher of Perl-compatible regular expressions, as far as
+/// `regex-automata` has them, the first match at a position being the one
+/// found. Lines are matched one at a time, as the expressions may match
+/// newlines.
+#[cfg(feature = "pcre")]
+pub struct PerlMatcher {
+    regex: Regex,
+}
+
+#[cfg(feature = "pcre")]
+impl Matcher for PerlMatcher {
+    fn is_match(&self, line: &[u8]) -> bool {
+        self.regex.is_match(line)
+    }
+
+    fn find_at(&self, line: &[u8], start: usize) -> Option<Range<usize>> {
+        let found = self.regex.find(Input::new(line).range(start..))?;
+        Some(found.range())
+    }
+}
+
+/// Matcher of the Perl-compatible regular expression `patterns`, of which
+/// there may be one only
+fn perl(
+    patterns: &[Vec<u8>],
+    is_ignore_case: bool,
+    locale: Locale,
+) -> Result<Box<dyn Matcher>, String> {
+    #[cfg(feature = "pcre")]
+    {
+        let [pattern] = patterns else {
+            return Err("the -P option only supports a single pattern".to_string());
+        };
+        let pattern = std::str::from_utf8(pattern).map_err(|e| e.to_string())?;
+        let regex = Regex::builder()
+            .syntax(syntax_config(is_ignore_case, locale))
+            .build(pattern)
+            .map_err(|e| match e.syntax_error() {
+                // The last line says what is wrong, after the pattern
+                Some(e) => e
+                    .to_string()
+                    .lines()
+                    .last()
+                    .map_or_else(String::new, |line| {
+                        line.trim_start_matches("error: ").to_string()
+                    }),
+                None => e.to_string(),
+            })?;
+        Ok(Box::new(PerlMatcher { regex }))
+    }
+    #[cfg(not(feature = "pcre"))]
+    {
+        let _ = (patterns, is_ignore_case, locale);
+        Err("Perl matching not supported in a --disable-perl-regexp build".to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matches(matcher: &dyn Matcher, line: &str) -> Vec<String> {
+        let line = line.as_bytes();
+        find_iter(matcher, line)
+            .map(|range| String::from_utf8_lossy(&line[range]).into_owned())
+            .collect()
+    }
+
+    fn matcher(patterns: &[&str], syntax: Syntax, is_ignore_case: bool) -> Box<dyn Matcher> {
+        let patterns: Vec<_> = patterns.iter().map(|p| p.as_bytes().to_vec()).collect();
+        new(&patterns, syntax, is_ignore_case, Locale::Unicode)
+            .unwrap()
+            .0
+    }
+
+    #[test]
+    fn finds_the_longest_matches() {
+        let extended = matcher(&["a|ab", "x*"], Syntax::Extended, false);
+        assert_eq!(matches(&*extended, "zab xxa"), ["ab", "xx", "a"]);
+        let basic = matcher(&[r"a\|ab", "x*"], Syntax::Basic, false);
+        assert_eq!(matches(&*basic, "zab xxa"), ["ab", "xx", "a"]);
+        let fixed = matcher(&["a", "ab", "x"], Syntax::Fixed, false);
+        assert_eq!(matches(&*fixed, "zab xxa"), ["ab", "x", "x", "a"]);
+    }
+
+    #[test]
+    fn matches_fixed_strings() {
+        let one = matcher(&["a.c"], Syntax::Fixed, false);
+        assert!(one.is_match(b"xa.c"));
+        assert!(!one.is_match(b"abc"));
+        let folded = matcher(&["AB"], Syntax::Fixed, true);
+        assert_eq!(matches(&*folded, "xaBy"), ["aB"]);
+        let folded = matcher(&["É"], Syntax::Fixed, true);
+        assert_eq!(matches(&*folded, "café"), ["é"]);
+        let empty = matcher(&["x", ""], Syntax::Fixed, false);
+        assert!(empty.is_match(b""));
+        let nothing = matcher(&[], Syntax::Fixed, false);
+        assert!(!nothing.is_match(b""));
+    }
+
+    #[test]
+    fn finds_candidate_lines() {
+        let lines = b"ab\nb\nab\n";
+        let regex = matcher(&["^b", "x$"], Syntax::Basic, false);
+        assert_eq!(regex.candidate(lines), Some(3));
+        assert_eq!(regex.candidate(b"bx\nx\n"), Some(0));
+        let fixed = matcher(&["ab", "z"], Syntax::Fixed, false);
+        assert_eq!(fixed.candidate(&lines[1..]), Some(4));
+        assert_eq!(matcher(&["c"], Syntax::Fixed, false).candidate(lines), None);
+        assert_eq!(matcher(&[], Syntax::Fixed, false).candidate(lines), None);
+        // Matches don't span lines
+        let regex = matcher(&["a.*c", "b[^a]*a"], Syntax::Basic, false);
+        assert_eq!(regex.candidate(b"ab\nc\nba\n"), Some(5));
+    }
+
+    #[test]
+    fn matches_back_references() {
+        let basic = matcher(&[r"\(fo\)\1"], Syntax::Basic, false);
+        assert!(basic.is_match(b"xfofo"));
+        assert!(!basic.is_match(b"foba"));
+        let extended = matcher(&[r"(fo|ba)\1", "z"], Syntax::Extended, false);
+        assert_eq!(
+            matches(&*extended, "fobababa z fofo"),
+            ["baba", "z", "fofo"]
+        );
+        // The longest match, with the groups matching less than they could
+        let longest = matcher(&[r"\(a*\)\1b"], Syntax::Basic, false);
+        assert_eq!(matches(&*longest, "aaaab ab"), ["aaaab", "b"]);
+        let repeated = matcher(&[r"^\(.\)*\1$"], Syntax::Basic, false);
+        assert!(repeated.is_match(b"abcc"));
+        assert!(!repeated.is_match(b"abca"));
+        let anchored = matcher(&[r"\<\(x\)\1\>"], Syntax::Basic, false);
+        assert_eq!(matches(&*anchored, "xxx xx"), ["xx"]);
+        // Groups that didn't match can't be referred to
+        let unset = matcher(&[r"(a)|b\1"], Syntax::Extended, false);
+        assert_eq!(matches(&*unset, "bb a"), ["a"]);
+        let folded = matcher(&[r"\(é\)\1"], Syntax::Basic, true);
+        assert_eq!(matches(&*folded, "xÉé"), ["Éé"]);
+    }
+
+    #[test]
+    fn matches_perl_patterns() {
+        let patterns = [b"a+?".to_vec()];
+        let result = new(&patterns, Syntax::Perl, false, Locale::C);
+        #[cfg(feature = "pcre")]
+        assert_eq!(matches(&*result.unwrap().0, "baa"), ["a", "a"]);
+        #[cfg(not(feature = "pcre"))]
+        assert!(result.is_err());
+        let patterns = [b"a".to_vec(), b"b".to_vec()];
+        assert!(new(&patterns, Syntax::Perl, false, Locale::C).is_err());
+    }
+}
@@ -0,0 +1,862 @@
+//! Translation of POSIX basic and extended regular expressions, with the
+//! extensions of GNU `grep`, into the syntax of `regex-automata`.
+//!
+//! Patterns are bytes. In Unicode locales their valid UTF-8 characters are
+//! atoms that `.`, brackets and quantifiers take whole, in the C locale
+//! every byte is. Bracket classes like `[:alpha:]` are the ones of the
+//! locale, the same as in `tr` and `sort`. Where GNU is lenient the
+//! translation is too: `)` without `(` and `{` not starting an interval
+//! are literal, and a quantifier at the start of an expression is dropped
+//! with a warning. Basic expressions are the same but for `\(`, `\)`,
+//! `\{`, `\}`, `\|`, `\+` and `\?` in place of the unescaped characters,
+//! which are literal, like quantifiers at the start, `^` not at the start
+//! and `$` not at the end.
+//!
+//! Lines have no newlines, so the translations match none: a buffer of
+//! lines can be searched with `^` and `$` at the ends of lines without
+//! finding matches that span lines.
+//!
+//! Back-references have no translation, `regex-automata` has no syntax for
+//! them. Patterns with them are matched by backtracking over their
+//! [`Node`]s instead, whose atoms are translated all the same.
+
+use nyaa_core::i18n::tr;
+use nyaa_core::locale::Locale;
+use std::fmt::Write;
+
+/// Most repetitions an interval may ask for, `RE_DUP_MAX` of POSIX
+const DUP_MAX: u32 = 0x7fff;
+
+const UNMATCHED_PAREN: &str = "Unmatched ( or \\(";
+const UNMATCHED_CLOSE_PAREN: &str = "Unmatched ) or \\)";
+const INVALID_INTERVAL: &str = "Invalid content of \\{\\}";
+const UNMATCHED_BRACKET: &str = "Unmatched [, [^, [:, [., or [=";
+pub const TOO_BIG: &str = "Regular expression too big";
+
+/// Any character but a newline
+const ANY: &str = r"[^\n]";
+
+/// Whether a character of a locale is in a class
+type Class = fn(Locale, char) -> bool;
+
+/// The classes of brackets, named like in `[:alpha:]`
+const CLASSES: &[(&str, Class)] = &[
+    ("alpha", Locale::is_alpha),
+    ("upper", Locale::is_upper),
+    ("lower", Locale::is_lower),
+    ("digit", Locale::is_digit),
+    ("xdigit", |_, c| c.is_ascii_hexdigit()),
+    ("space", Locale::is_space),
+    ("print", Locale::is_print),
+    ("punct", Locale::is_punct),
+    ("graph", Locale::is_graph),
+    ("cntrl", Locale::is_cntrl),
+    ("blank", Locale::is_blank),
+    ("alnum", Locale::is_alnum),
+];
+
+/// A pattern in the syntax of `regex-automata`, with the warnings about
+/// it
+#[derive(Debug, PartialEq, Eq)]
+pub struct Translation {
+    /// `None` if the pattern has back-references
+    pub regex: Option<String>,
+    pub node: Node,
+    pub warnings: Vec<String>,
+}
+
+/// A pattern parsed
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Node {
+    /// An atom or an assertion, in the syntax of `regex-automata`
+    Leaf(String),
+    Concat(Vec<Node>),
+    Alternation(Vec<Node>),
+    /// A group, numbered from 1 in the order the groups open
+    Group(usize, Box<Node>),
+    Repeat(Box<Node>, Quantifier),
+    /// The text the group of the number matched
+    BackReference(usize),
+}
+
+impl Node {
+    /// The node in the syntax of `regex-automata`, `None` if it has
+    /// back-references
+    pub fn regex(&self) -> Option<String> {
+        Some(match self {
+            Node::Leaf(regex) => regex.clone(),
+            Node::Concat(nodes) => nodes.iter().map(Node::regex).collect::<Option<_>>()?,
+            Node::Alternation(nodes) => nodes
+                .iter()
+                .map(Node::regex)
+                .collect::<Option<Vec<_>>>()?
+                .join("|"),
+            Node::Group(_, node) => format!("(?:{})", node.regex()?),
+            Node::Repeat(node, quantifier) => format!("(?:{}){}", node.regex()?, quantifier.regex),
+            Node::BackReference(_) => return None,
+        })
+    }
+}
+
+/// How many times a node repeats
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Quantifier {
+    /// The quantifier in the syntax of `regex-automata`
+    pub regex: String,
+    pub min: u32,
+    /// `None` for no upper bound
+    pub max: Option<u32>,
+}
+
+/// Translate the regular expression `pattern`, extended or basic, of the
+/// characters of `locale`. Fails with the message of GNU `grep` for it,
+/// untranslated.
+pub fn translate(
+    pattern: &[u8],
+    is_extended: bool,
+    locale: Locale,
+) -> Result<Translation, &'static str> {
+    let mut parser = Parser {
+        pattern,
+        pos: 0,
+        is_extended,
+        locale,
+        depth: 0,
+        groups: Vec::new(),
+        warnings: Vec::new(),
+    };
+    let node = parser.alternation()?;
+    Ok(Translation {
+        regex: node.regex(),
+        node,
+        warnings: parser.warnings,
+    })
+}
+
+/// The regex matching the fixed string `pattern` of the characters of
+/// `locale`
+pub fn fixed(pattern: &[u8], locale: Locale) -> String {
+    let mut parser = Parser {
+        pattern,
+        pos: 0,
+        is_extended: false,
+        locale,
+        depth: 0,
+        groups: Vec::new(),
+        warnings: Vec::new(),
+    };
+    let mut regex = String::new();
+    while parser.pos < pattern.len() {
+        regex += &literal(parser.atom());
+    }
+    regex
+}
+
+/// An element of a pattern, a character or, in the C locale or where the
+/// pattern isn't UTF-8, a byte
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum Atom {
+    Char(char),
+    Byte(u8),
+}
+
+/// An item of a bracket expression
+enum Item {
+    Atom(Atom),
+    /// The characters of an `[=c=]` equivalence class
+    Equivalents(Atom),
+    Range(Atom, Atom),
+    Class(Class),
+}
+
+struct Parser<'a> {
+    pattern: &'a [u8],
+    pos: usize,
+    is_extended: bool,
+    locale: Locale,
+    /// Groups open around the position
+    depth: usize,
+    /// Whether the groups opened before the position are closed, which
+    /// back-references may only refer to then
+    groups: Vec<bool>,
+    warnings: Vec<String>,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<u8> {
+        self.pattern.get(self.pos).copied()
+    }
+
+    fn eat(&mut self, b: u8) -> bool {
+        let is_next = self.peek() == Some(b);
+        self.pos += usize::from(is_next);
+        is_next
+    }
+
+    /// Whether the operator `b` is at the position, escaped in basic
+    /// expressions
+    fn is_operator(&self, b: u8) -> bool {
+        match self.is_extended {
+            true => self.peek() == Some(b),
+            false => self.pattern[self.pos..].starts_with(&[b'\\', b]),
+        }
+    }
+
+    /// Take the operator `b` if it is at the position
+    fn eat_operator(&mut self, b: u8) -> bool {
+        let is_next = self.is_operator(b);
+        if is_next {
+            self.pos += if self.is_extended { 1 } else { 2 };
+        }
+        is_next
+    }
+
+    /// Whether the end of the pattern, or of its branch, is at the position
+    fn is_branch_end(&self) -> bool {
+        self.peek().is_none()
+            || self.is_operator(b'|')
+            || (self.is_operator(b')') && (self.depth > 0 || !self.is_extended))
+    }
+
+    /// The atom at the position, taken
+    fn atom(&mut self) -> Atom {
+        let rest = &self.pattern[self.pos..];
+        if self.locale == Locale::Unicode {
+            let len = utf8_len(rest);
+            if let Some(c) = std::str::from_utf8(&rest[..len])
+                .ok()
+                .and_then(|s| s.chars().next())
+            {
+                self.pos += len;
+                return Atom::Char(c);
+            }
+        }
+        self.pos += 1;
+        match rest[0] {
+            b if b.is_ascii() => Atom::Char(char::from(b)),
+            b => Atom::Byte(b),
+        }
+    }
+
+    /// Branches separated by `|`, up to the end of the pattern or of the
+    /// group
+    fn alternation(&mut self) -> Result<Node, &'static str> {
+        let mut branches = vec![self.branch()?];
+        while self.eat_operator(b'|') {
+            branches.push(self.branch()?);
+        }
+        Ok(match branches.len() {
+            1 => branches.pop().expect("a branch"),
+            _ => Node::Alternation(branches),
+        })
+    }
+
+    fn branch(&mut self) -> Result<Node, &'static str> {
+        let mut pieces = Vec::new();
+        // Whether nothing but `^` was in the branch so far
+        let mut is_start = true;
+        loop {
+            if self.is_branch_end() {
+                if self.is_operator(b')') && self.depth == 0 {
+                    return Err(UNMATCHED_CLOSE_PAREN);
+                }
+                return Ok(Node::Concat(pieces));
+            }
+            if is_start && !self.is_extended {
+                // Quantifiers at the start of basic expressions are literal
+                let quantifier = match self.peek() {
+                    Some(b'*') => Some((b'*', 1)),
+                    _ => [b'+', b'?', b'{']
+                        .into_iter()
+                        .find(|&b| self.is_operator(b))
+                        .map(|b| (b, 2)),
+                };
+                if let Some((b, len)) = quantifier {
+                    self.pos += len;
+                    pieces.push(Node::Leaf(literal(Atom::Char(char::from(b)))));
+                    is_start = false;
+                    continue;
+                }
+            } else if is_start {
+                if let Some(quantifier) = self.quantifier(true)? {
+                    self.warn_start(&quantifier.regex);
+                    continue;
+                }
+            }
+            let mut piece = self.piece_atom(pieces.is_empty())?;
+            let is_start_anchor = is_start && is_leaf(&piece, &["^", "$"]);
+            is_start &= is_leaf(&piece, &["^"]);
+            if is_start_anchor && !self.is_extended {
+                // A quantifier after it is literal, taken as the next piece
+                pieces.push(piece);
+                continue;
+            }
+            // Anchors at the start of extended expressions take
+            // quantifiers, with the warning
+            while let Some(quantifier) = self.quantifier(is_start_anchor)? {
+                if is_start_anchor {
+                    self.warn_start(&quantifier.regex);
+                }
+                piece = Node::Repeat(Box::new(piece), quantifier);
+            }
+            pieces.push(piece);
+        }
+    }
+
+    /// Warn about `quantifier` at the start of an expression
+    fn warn_start(&mut self, quantifier: &str) {
+        let name = match quantifier {
+            "*" | "+" | "?" => quantifier,
+            _ => "{...}",
+        };
+        self.warnings
+            .push(tr("warning: {} at start of expression").replace("{}", name));
+    }
+
+    /// The regex of the atom at the position, the first of its branch if
+    /// `is_first`
+    fn piece_atom(&mut self, is_first: bool) -> Result<Node, &'static str> {
+        if self.eat_operator(b'(') {
+            self.depth += 1;
+            self.groups.push(false);
+            let number = self.groups.len();
+            let inner = self.alternation()?;
+            if !self.eat_operator(b')') {
+                return Err(UNMATCHED_PAREN);
+            }
+            self.depth -= 1;
+            self.groups[number - 1] = true;
+            return Ok(Node::Group(number, Box::new(inner)));
+        }
+        let b = self.peek().expect("atoms are parsed before the end");
+        self.pos += 1;
+        Ok(Node::Leaf(match b {
+            b'[' => self.bracket()?,
+            b'.' => ANY.to_string(),
+            // Anchors of basic expressions are only at their ends
+            b'^' if !self.is_extended && !is_first => literal(Atom::Char('^')),
+            b'$' if !self.is_extended && !self.is_branch_end() => literal(Atom::Char('$')),
+            b'^' | b'$' => char::from(b).to_string(),
+            b'\\' => {
+                let Some(escaped) = self.peek() else {
+                    return Err("Trailing backslash");
+                };
+                let regex = match escaped {
+                    b'w' => self.class(&[Item::Class(is_word)], false),
+                    b'W' => self.class(&[Item::Class(is_word)], true),
+                    b's' => self.class(&[Item::Class(Locale::is_space)], false),
+                    b'S' => self.class(&[Item::Class(Locale::is_space)], true),
+                    b'b' => r"\b".to_string(),
+                    b'B' => r"\B".to_string(),
+                    b'<' => r"\b{start}".to_string(),
+                    b'>' => r"\b{end}".to_string(),
+                    // Only lines are matched
+                    b'`' => "^".to_string(),
+                    b'\'' => "$".to_string(),
+                    b'1'..=b'9' => {
+                        let number = usize::from(escaped - b'0');
+                        if self.groups.get(number - 1) != Some(&true) {
+                            return Err("Invalid back reference");
+                        }
+                        self.pos += 1;
+                        return Ok(Node::BackReference(number));
+                    }
+                    _ => {
+                        let atom = self.atom();
+                        return Ok(Node::Leaf(literal(atom)));
+                    }
+                };
+                self.pos += 1;
+                regex
+            }
+            _ => {
+                self.pos -= 1;
+                let atom = self.atom();
+                literal(atom)
+            }
+        }))
+    }
+
+    /// The quantifier at the position in the syntax of `regex-automata`,
+    /// taken. A `{` not starting an interval isn't one, nor is an empty or
+    /// reversed interval at the start of an expression, which is invalid
+    /// elsewhere.
+    fn quantifier(&mut self, is_start: bool) -> Result<Option<Quantifier>, &'static str> {
+        if !self.is_extended {
+            return self.basic_quantifier();
+        }
+        match self.peek() {
+            Some(b @ (b'*' | b'+' | b'?')) => {
+                self.pos += 1;
+                Ok(Some(operator(b)))
+            }
+            Some(b'{') => {
+                let rest = &self.pattern[self.pos + 1..];
+                let Some(end) = rest.iter().position(|&b| b == b'}') else {
+                    return Ok(None);
+                };
+                let spec = &rest[..end];
+                let interval = if spec.is_empty() {
+                    None
+                } else {
+                    let Some(interval) = interval(spec) else {
+                        return Ok(None);
+                    };
+                    Some(interval).filter(|&(min, max)| max.is_none_or(|max| min <= max))
+                };
+                let Some(interval) = interval else {
+                    return match is_start {
+                        true => Ok(None),
+                        false => Err(INVALID_INTERVAL),
+                    };
+                };
+                self.pos += end + 2;
+                repetition(interval).map(Some)
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// The quantifier of a basic expression at the position, taken, where
+    /// `\{` always starts an interval
+    fn basic_quantifier(&mut self) -> Result<Option<Quantifier>, &'static str> {
+        if self.eat(b'*') {
+            return Ok(Some(operator(b'*')));
+        }
+        for b in [b'+', b'?'] {
+            if self.eat_operator(b) {
+                return Ok(Some(operator(b)));
+            }
+        }
+        if !self.eat_operator(b'{') {
+            return Ok(None);
+        }
+        let rest = &self.pattern[self.pos..];
+        let end = rest
+            .windows(2)
+            .position(|w| w == b"\\}")
+            .ok_or("Unmatched \\{")?;
+        let interval = interval(&rest[..end])
+            .filter(|&(min, max)| max.is_none_or(|max| min <= max))
+            .ok_or(INVALID_INTERVAL)?;
+        self.pos += end + 2;
+        repetition(interval).map(Some)
+    }
+
+    /// The bracket expression after its `[`, taken
+    fn bracket(&mut self) -> Result<String, &'static str> {
+        let is_negated = self.eat(b'^');
+        let start = self.pos;
+        let mut items = Vec::new();
+        loop {
+            match self.peek() {
+                None => return Err(UNMATCHED_BRACKET),
+                Some(b']') if self.pos > start => break,
+                _ => (),
+            }
+            let Some(low) = self.bracket_atom()? else {
+                let class = self.bracket_class()?;
+                if self.peek() == Some(b'-') && self.pattern.get(self.pos + 1) != Some(&b']') {
+                    return Err("Invalid range end");
+                }
+                items.push(Item::Class(class));
+                continue;
+            };
+            let is_range = self.peek() == Some(b'-')
+                && !matches!(self.pattern.get(self.pos + 1), None | Some(b']'));
+            if !is_range {
+                items.push(low);
+                continue;
+            }
+            self.pos += 1;
+            let (Item::Atom(low) | Item::Equivalents(low)) = low else {
+                unreachable!("bracket atoms are atoms or equivalents");
+            };
+            let high = match self.bracket_atom()? {
+                Some(Item::Atom(high) | Item::Equivalents(high)) => high,
+                _ => return Err("Invalid range end"),
+            };
+            let is_mixed = matches!((low, high), (Atom::Char(_), Atom::Byte(_)))
+                && self.locale == Locale::Unicode;
+            if high < low || is_mixed {
+                return Err("Invalid range end");
+            }
+            items.push(Item::Range(low, high));
+        }
+        let content = &self.pattern[start..self.pos];
+        self.pos += 1;
+        // `[:space:]` is a bracket of its letters, but likely meant to be
+        // the class
+        if content.len() > 1
+            && content.starts_with(b":")
+            && content.ends_with(b":")
+            && !content.contains(&b'[')
+        {
+            return Err("character class syntax is [[:space:]], not [:space:]");
+        }
+        Ok(self.class(&items, is_negated))
+    }
+
+    /// The atom of a bracket at the position, with the ones equivalent to
+    /// it for `[=c=]`, or `None` if a class is there, all taken but the
+    /// class
+    fn bracket_atom(&mut self) -> Result<Option<Item>, &'static str> {
+        if self.peek() != Some(b'[') {
+            return Ok(Some(Item::Atom(self.atom())));
+        }
+        let delimiter = match self.pattern.get(self.pos + 1) {
+            Some(&b @ (b'.' | b'=')) => b,
+            Some(b':') => return Ok(None),
+            _ => return Ok(Some(Item::Atom(self.atom()))),
+        };
+        self.pos += 2;
+        let start = self.pos;
+        let end = self.pattern[start..]
+            .windows(2)
+            .position(|w| w == [delimiter, b']'])
+            .ok_or(UNMATCHED_BRACKET)?;
+        if end == 0 {
+            return Err("Invalid collation character");
+        }
+        let atom = self.atom();
+        if self.pos != start + end {
+            return Err("Invalid collation character");
+        }
+        self.pos += 2;
+        Ok(Some(match delimiter {
+            b'=' => Item::Equivalents(atom),
+            _ => Item::Atom(atom),
+        }))
+    }
+
+    /// The predicate of the `[:name:]` class at the position, taken
+    fn bracket_class(&mut self) -> Result<Class, &'static str> {
+        self.pos += 2;
+        let start = self.pos;
+        let end = self.pattern[start..]
+            .windows(2)
+            .position(|w| w == b":]")
+            .ok_or(UNMATCHED_BRACKET)?;
+        let name = &self.pattern[start..start + end];
+        self.pos += end + 2;
+        CLASSES
+            .iter()
+            .find(|(class, _)| class.as_bytes() == name)
+            .map(|&(_, class)| class)
+            .ok_or("Invalid character class name")
+    }
+
+    /// A class of the atoms of `items`, or of the others. In Unicode locales
+    /// the bytes of invalid UTF-8 are in classes only as listed, negated
+    /// classes match characters. Newlines are in no negated class.
+    fn class(&self, items: &[Item], is_negated: bool) -> String {
+        let mut chars = String::new();
+        let mut bytes = String::new();
+        let limit = match self.locale {
+            Locale::C => '\x7f',
+            Locale::Unicode => char::MAX,
+        };
+        for item in items {
+            match *item {
+                Item::Atom(atom) => push_class_atom(&mut chars, &mut bytes, atom, None),
+                Item::Equivalents(Atom::Char(c)) => {
+                    for c in self.locale.equivalents(c) {
+                        push_class_atom(&mut chars, &mut bytes, Atom::Char(c), None);
+                    }
+                }
+                Item::Equivalents(atom) => push_class_atom(&mut chars, &mut bytes, atom, None),
+                Item::Range(low, high) => {
+                    push_class_atom(&mut chars, &mut bytes, low, Some(high));
+                }
+                Item::Class(class) => {
+                    for (low, high) in ranges(|c| class(self.locale, c), limit) {
+                        let high = (high != low).then_some(Atom::Char(high));
+                        push_class_atom(&mut chars, &mut bytes, Atom::Char(low), high);
+                    }
+                }
+            }
+        }
+        match (chars.is_empty(), bytes.is_empty()) {
+            // Patterns of the C locale are matched as bytes whole
+            _ if self.locale == Locale::C && is_negated => format!("[^{chars}{bytes}\\n]"),
+            _ if self.locale == Locale::C => format!("[{chars}{bytes}]"),
+            (true, _) if is_negated => ANY.to_string(),
+            _ if is_negated => format!("[^{chars}\\n]"),
+            (false, true) => format!("[{chars}]"),
+            (true, false) => format!("(?-u:[{bytes}])"),
+            (false, false) => format!("(?:[{chars}]|(?-u:[{bytes}]))"),
+            (true, true) => r"[^\x{0}-\x{10ffff}]".to_string(),
+        }
+    }
+}
+
+/// The quantifier repeating between the bounds of `interval`
+fn repetition((min, max): (u32, Option<u32>)) -> Result<Quantifier, &'static str> {
+    if min.max(max.unwrap_or(0)) > DUP_MAX {
+        return Err(TOO_BIG);
+    }
+    let regex = match max {
+        Some(max) if max == min => format!("{{{min}}}"),
+        Some(max) => format!("{{{min},{max}}}"),
+        None => format!("{{{min},}}"),
+    };
+    Ok(Quantifier { regex, min, max })
+}
+
+/// The quantifier of the operator `*`, `+` or `?`
+fn operator(b: u8) -> Quantifier {
+    let (min, max) = match b {
+        b'*' => (0, None),
+        b'+' => (1, None),
+        _ => (0, Some(1)),
+    };
+    Quantifier {
+        regex: char::from(b).to_string(),
+        min,
+        max,
+    }
+}
+
+/// Whether `node` is an atom or assertion that is one of `regexes`
+fn is_leaf(node: &Node, regexes: &[&str]) -> bool {
+    matches!(node, Node::Leaf(regex) if regexes.contains(&regex.as_str()))
+}
+
+/// A literal atom
+fn literal(atom: Atom) -> String {
+    match atom {
+        Atom::Char(c) if r"\.+*?()|[]{}^$#&-~".contains(c) => format!(r"\{c}"),
+        Atom::Char(c) if c.is_control() => format!(r"\x{{{:x}}}", u32::from(c)),
+        Atom::Char(c) => c.to_string(),
+        Atom::Byte(b) => format!(r"(?-u:\x{b:02x})"),
+    }
+}
+
+/// Append `low`, or the range from it to `high`, to the characters of a
+/// class or to its bytes where the range has one
+fn push_class_atom(chars: &mut String, bytes: &mut String, low: Atom, high: Option<Atom>) {
+    let is_bytes = matches!(low, Atom::Byte(_)) || matches!(high, Some(Atom::Byte(_)));
+    let class = if is_bytes { bytes } else { chars };
+    for (i, atom) in std::iter::once(low).chain(high).enumerate() {
+        if i > 0 {
+            class.push('-');
+        }
+        match atom {
+            Atom::Char(c) if r"\[]^-&~".contains(c) => {
+                class.push('\\');
+                class.push(c);
+            }
+            Atom::Char(c) if c.is_control() || c.is_whitespace() => {
+                write!(class, r"\x{{{:x}}}", u32::from(c)).unwrap();
+            }
+            Atom::Char(c) => class.push(c),
+            Atom::Byte(b) => write!(class, r"\x{b:02x}").unwrap(),
+        }
+    }
+}
+
+/// Whether `c` is in words, alphanumeric or `_`
+fn is_word(locale: Locale, c: char) -> bool {
+    c == '_' || locale.is_alnum(c)
+}
+
+/// Length of the UTF-8 sequence `bytes` start with, judged by the first
+/// byte
+fn utf8_len(bytes: &[u8]) -> usize {
+    let len = match bytes[0] {
+        0xc0..=0xdf => 2,
+        0xe0..=0xef => 3,
+        0xf0..=0xf7 => 4,
+        _ => 1,
+    };
+    len.min(bytes.len())
+}
+
+/// The lower and upper bounds of an interval, `{m}`, `{m,}`, `{,n}` or
+/// `{m,n}` without its braces, with no upper one for `{m,}`
+fn interval(spec: &[u8]) -> Option<(u32, Option<u32>)> {
+    let number = |digits: &[u8]| -> Option<u32> {
+        if digits.is_empty() || !digits.iter().all(u8::is_ascii_digit) {
+            return None;
+        }
+        Some(digits.iter().fold(0u32, |n, &digit| {
+            n.saturating_mul(10).saturating_add(u32::from(digit - b'0'))
+        }))
+    };
+    match spec.iter().position(|&b| b == b',') {
+        None => number(spec).map(|n| (n, Some(n))),
+        Some(comma) => {
+            let (min, max) = (&spec[..comma], &spec[comma + 1..]);
+            let min = if min.is_empty() { Some(0) } else { number(min) }?;
+            let max = if max.is_empty() {
+                None
+            } else {
+                Some(number(max)?)
+            };
+            Some((min, max))
+        }
+    }
+}
+
+/// The ranges of the characters up to `limit` that are in a class
+fn ranges(is_in: impl Fn(char) -> bool, limit: char) -> Vec<(char, char)> {
+    let mut ranges: Vec<(char, char)> = Vec::new();
+    for c in ('\0'..=limit).filter(|&c| is_in(c)) {
+        match ranges.last_mut() {
+            Some((_, high)) if char::from_u32(u32::from(*high) + 1) == Some(c) => *high = c,
+            _ => ranges.push((c, c)),
+        }
+    }
+    ranges
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn regex(pattern: &str) -> String {
+        translate(pattern.as_bytes(), true, Locale::C)
+            .unwrap()
+            .regex
+            .unwrap()
+    }
+
+    fn error(pattern: &str) -> &'static str {
+        translate(pattern.as_bytes(), true, Locale::C).unwrap_err()
+    }
+
+    #[test]
+    fn translates_atoms_and_quantifiers() {
+        assert_eq!(regex("a.b"), r"a[^\n]b");
+        assert_eq!(regex("(a|b)+"), "(?:(?:a|b))+");
+        assert_eq!(regex("a**"), "(?:(?:a)*)*");
+        assert_eq!(regex("a{2}{,3}"), "(?:(?:a){2}){0,3}");
+        assert_eq!(regex("^a$"), "^a$");
+        assert_eq!(regex(r"\.\a\<"), r"\.a\b{start}");
+        assert_eq!(regex(r"\`a\'"), "^a$");
+    }
+
+    #[test]
+    fn takes_stray_characters_literally() {
+        assert_eq!(regex("a)"), r"a\)");
+        assert_eq!(regex("a{1"), r"a\{1");
+        assert_eq!(regex("a{x}"), r"a\{x\}");
+        assert_eq!(regex("&~#"), r"\&\~\#");
+        assert_eq!(regex("{}|^{2,1}"), r"\{\}|^\{2,1\}");
+    }
+
+    #[test]
+    fn drops_quantifiers_at_the_start() {
+        let translation = translate(b"*a|^+b|({1}c)", true, Locale::C).unwrap();
+        assert_eq!(translation.regex.unwrap(), "a|(?:^)+b|(?:c)");
+        assert_eq!(
+            translation.warnings,
+            [
+                "warning: * at start of expression",
+                "warning: + at start of expression",
+                "warning: {...} at start of expression",
+            ]
+        );
+    }
+
+    #[test]
+    fn keeps_quantifiers_at_the_start_of_basic_expressions() {
+        let translation = translate(br"*a\|^*b\|\(^\{1\}c\)", false, Locale::C).unwrap();
+        assert_eq!(translation.regex.unwrap(), r"\*a|^\*b|(?:^\{1\}c)");
+        assert!(translation.warnings.is_empty());
+    }
+
+    #[test]
+    fn translates_brackets() {
+        assert_eq!(regex("[]a-c]"), r"[\]a-c]");
+        assert_eq!(regex("[^]-]"), r"[^\]\-\n]");
+        assert_eq!(regex(r"[\]"), r"[\\]");
+        assert_eq!(regex("[[:digit:][.-.]]"), r"[0-9\-]");
+        let translation = translate(b"[[=a=]\xe9-\xff]", true, Locale::C).unwrap();
+        assert_eq!(translation.regex.unwrap(), r"[a\xe9-\xff]");
+        assert_eq!(regex("[:]"), "[:]");
+    }
+
+    #[test]
+    fn translates_unicode() {
+        let pattern = ["é+[à-á][^".as_bytes(), b"\xff]"].concat();
+        let translation = translate(&pattern, true, Locale::Unicode).unwrap();
+        assert_eq!(translation.regex.unwrap(), r"(?:é)+[à-á][^\n]");
+        let translation = translate("[[=e=]]".as_bytes(), true, Locale::Unicode).unwrap();
+        assert!(translation.regex.unwrap().starts_with("[eèéêë"));
+        let translation = translate(b"\xff.", true, Locale::Unicode).unwrap();
+        assert_eq!(translation.regex.unwrap(), r"(?-u:\xff)[^\n]");
+        let translation = translate(b"\xe9", true, Locale::C).unwrap();
+        assert_eq!(translation.regex.unwrap(), r"(?-u:\xe9)");
+    }
+
+    #[test]
+    fn reports_errors() {
+        assert_eq!(error("(a"), "Unmatched ( or \\(");
+        assert_eq!(error("[a"), "Unmatched [, [^, [:, [., or [=");
+        assert_eq!(error("[]"), "Unmatched [, [^, [:, [., or [=");
+        assert_eq!(error("a\\"), "Trailing backslash");
+        assert_eq!(error("a{2,1}"), "Invalid content of \\{\\}");
+        assert_eq!(error("a{}"), "Invalid content of \\{\\}");
+        assert_eq!(error("a{32768}"), "Regular expression too big");
+        assert_eq!(error("[[:foo:]]"), "Invalid character class name");
+        assert_eq!(
+            error("[:space:]"),
+            "character class syntax is [[:space:]], not [:space:]"
+        );
+        assert_eq!(error("[b-a]"), "Invalid range end");
+        assert_eq!(error("[[:alpha:]-z]"), "Invalid range end");
+        assert_eq!(error("[[.ab.]]"), "Invalid collation character");
+        assert_eq!(error("(a)\\2"), "Invalid back reference");
+        assert_eq!(error("(a(b)\\1)"), "Invalid back reference");
+    }
+
+    #[test]
+    fn translates_basic_expressions() {
+        let basic = |pattern: &str| translate(pattern.as_bytes(), false, Locale::C);
+        let regex = |pattern| basic(pattern).unwrap().regex.unwrap();
+        assert_eq!(regex(r"\(a\|b\)\{2\}c\+d\?"), "(?:(?:a|b)){2}(?:c)+(?:d)?");
+        assert_eq!(regex("(a|b){2}+?"), r"\(a\|b\)\{2\}\+\?");
+        assert_eq!(regex(r"*a\|\{1\}"), r"\*a|\{1\}");
+        assert_eq!(regex("^^*$$"), r"^(?:\^)*\$$");
+        assert_eq!(regex("^*a"), r"^\*a");
+        assert_eq!(regex(r"\(^a$\)b^"), r"(?:^a$)b\^");
+        assert_eq!(regex("a**"), "(?:(?:a)*)*");
+        let error = |pattern| basic(pattern).unwrap_err();
+        assert_eq!(error(r"a\)"), "Unmatched ) or \\)");
+        assert_eq!(error(r"\(a"), "Unmatched ( or \\(");
+        assert_eq!(error(r"a\{1"), "Unmatched \\{");
+        assert_eq!(error(r"a\{1,x\}"), "Invalid content of \\{\\}");
+        assert_eq!(error(r"\(a\1\)"), "Invalid back reference");
+    }
+
+    #[test]
+    fn parses_back_references() {
+        let translation = translate(br"\(a\(b\)\2\)*\1", false, Locale::C).unwrap();
+        assert_eq!(translation.regex, None);
+        let group = |number, node| Node::Group(number, Box::new(node));
+        let concat = |nodes: &[Node]| Node::Concat(nodes.to_vec());
+        let leaf = |regex: &str| Node::Leaf(regex.to_string());
+        let inner = concat(&[
+            leaf("a"),
+            group(2, concat(&[leaf("b")])),
+            Node::BackReference(2),
+        ]);
+        let star = Quantifier {
+            regex: "*".to_string(),
+            min: 0,
+            max: None,
+        };
+        let repeat = Node::Repeat(Box::new(group(1, inner)), star);
+        assert_eq!(translation.node, concat(&[repeat, Node::BackReference(1)]));
+        let translation = translate(br"(a)|b\1", true, Locale::C).unwrap();
+        assert_eq!(translation.regex, None);
+    }
+
+    #[test]
+    fn translates_fixed_strings() {
+        assert_eq!(fixed(b"a.*[", Locale::C), r"a\.\*\[");
+        assert_eq!(fixed("é+".as_bytes(), Locale::Unicode), r"é\+");
+        assert_eq!(fixed(b"\xe9\xff", Locale::Unicode), r"(?-u:\xe9)(?-u:\xff)");
+    }
+}
@@ -0,0 +1,122 @@
+//! Cyclic redundancy checks, whose digest is the 32-bit value in big-endian
+//! byte order
+
+use crate::Digest;
+
+/// Table of the CRC of `cksum`, of the polynomial 0x04c11db7 with the most
+/// significant bit first
+const CKSUM_TABLE: [u32; 256] = {
+    let mut table = [0; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = (i as u32) << 24;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 0x8000_0000 != 0 {
+                (crc << 1) ^ 0x04c1_1db7
+            } else {
+                crc << 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+/// The CRC of POSIX `cksum`, which covers the length of the data too
+#[derive(Clone, Debug, Default)]
+pub struct Cksum {
+    crc: u32,
+    len: u64,
+}
+
+impl Cksum {
+    pub fn new() -> Self {
+        Cksum::default()
+    }
+
+    fn add(crc: u32, byte: u8) -> u32 {
+        (crc << 8) ^ CKSUM_TABLE[((crc >> 24) as u8 ^ byte) as usize]
+    }
+
+    /// The checksum of the data fed so far, as `cksum` prints it
+    pub fn value(&self) -> u32 {
+        let mut crc = self.crc;
+        // The length follows the data, least significant byte first and
+        // without the zero bytes above it
+        let mut len = self.len;
+        while len != 0 {
+            crc = Cksum::add(crc, len as u8);
+            len >>= 8;
+        }
+        !crc
+    }
+}
+
+impl Digest for Cksum {
+    fn update(&mut self, data: &[u8]) {
+        self.crc = data.iter().fold(self.crc, |crc, &b| Cksum::add(crc, b));
+        self.len += data.len() as u64;
+    }
+
+    fn finalize_reset(&mut self) -> Vec<u8> {
+        let value = self.value();
+        *self = Cksum::new();
+        value.to_be_bytes().to_vec()
+    }
+
+    fn output_size(&self) -> usize {
+        4
+    }
+}
+
+/// The CRC-32 of zlib, gzip and PNG
+#[derive(Clone, Debug, Default)]
+pub struct Crc32b(crc32fast::Hasher);
+
+impl Crc32b {
+    pub fn new() -> Self {
+        Crc32b::default()
+    }
+}
+
+impl Digest for Crc32b {
+    fn update(&mut self, data: &[u8]) {
+        self.0.update(data);
+    }
+
+    fn finalize_reset(&mut self) -> Vec<u8> {
+        let value = std::mem::take(&mut self.0).finalize();
+        value.to_be_bytes().to_vec()
+    }
+
+    fn output_size(&self) -> usize {
+        4
+    }
+}
+
+/// The CRC-32C of Castagnoli
+#[derive(Clone, Debug, Default)]
+pub struct Crc32c(u32);
+
+impl Crc32c {
+    pub fn new() -> Self {
+        Crc32c::default()
+    }
+}
+
+impl Digest for Crc32c {
+    fn update(&mut self, data: &[u8]) {
+        self.0 = crc32c::crc32c_append(self.0, data);
+    }
+
+    fn finalize_reset(&mut self) -> Vec<u8> {
+        std::mem::take(&mut self.0).to_be_bytes().to_vec()
+    }
+
+    fn output_size(&self) -> usize {
+        4
+    }
+}
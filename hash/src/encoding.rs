@@ -0,0 +1,79 @@
+//! Text forms of digests: lowercase hexadecimal like the `*sum` utilities
+//! print by default, and the padded base64 of `--base64`
+
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Text form of a digest
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Encoding {
+    #[default]
+    Hex,
+    Base64,
+}
+
+impl Encoding {
+    pub fn encode(self, digest: &[u8]) -> String {
+        match self {
+            Encoding::Hex => digest.iter().map(|b| format!("{b:02x}")).collect(),
+            Encoding::Base64 => {
+                let mut s = String::with_capacity(digest.len().div_ceil(3) * 4);
+                for chunk in digest.chunks(3) {
+                    let bits = chunk
+                        .iter()
+                        .enumerate()
+                        .fold(0u32, |bits, (i, &b)| bits | (b as u32) << (16 - 8 * i));
+                    for i in 0..4 {
+                        if i <= chunk.len() {
+                            s.push(BASE64[(bits >> (18 - 6 * i) & 0x3f) as usize] as char);
+                        } else {
+                            s.push('=');
+                        }
+                    }
+                }
+                s
+            }
+        }
+    }
+
+    /// The digest of the text `s`, `None` if it isn't in the encoding.
+    /// Hexadecimal digits may be of either case.
+    pub fn decode(self, s: &str) -> Option<Vec<u8>> {
+        match self {
+            Encoding::Hex => {
+                if !s.len().is_multiple_of(2) {
+                    return None;
+                }
+                s.as_bytes()
+                    .chunks(2)
+                    .map(|pair| {
+                        let digit = |b: u8| (b as char).to_digit(16);
+                        Some((digit(pair[0])? << 4 | digit(pair[1])?) as u8)
+                    })
+                    .collect()
+            }
+            Encoding::Base64 => {
+                if !s.len().is_multiple_of(4) {
+                    return None;
+                }
+                let mut digest = Vec::with_capacity(s.len() / 4 * 3);
+                let chunks = s.as_bytes().chunks(4);
+                let count = chunks.len();
+                for (n, chunk) in chunks.enumerate() {
+                    // Padding only ends the last group
+                    let padding = chunk.iter().rev().take_while(|&&b| b == b'=').count();
+                    if padding > 2 || (padding > 0 && n + 1 != count) {
+                        return None;
+                    }
+                    let mut bits = 0u32;
+                    for &b in &chunk[..4 - padding] {
+                        let value = BASE64.iter().position(|&c| c == b)?;
+                        bits = bits << 6 | value as u32;
+                    }
+                    bits <<= 6 * padding;
+                    digest.extend(&bits.to_be_bytes()[1..4 - padding]);
+                }
+                Some(digest)
+            }
+        }
+    }
+}
@@ -0,0 +1,358 @@
+//! Checksums and message digests of the checksum utilities, `cksum`,
+//! `md5sum`, the `sha*sum` ones and `b2sum`. Every algorithm is fed data
+//! in pieces through the [`Digest`] trait, and the digests are printed
+//! and read back in one of the [`Encoding`]s.
+//!
+//! The cryptographic digests are the RustCrypto and BLAKE3 crates, which
+//! use the SIMD instructions of the CPU where it has them.
+
+pub mod crc;
+pub mod encoding;
+
+pub use encoding::Encoding;
+
+use blake2::digest::{Update, VariableOutput};
+use std::fmt;
+use std::io::{self, Read};
+use std::str::FromStr;
+
+/// A checksum or digest computed over data fed in pieces
+pub trait Digest {
+    /// Feed the next piece of the data
+    fn update(&mut self, data: &[u8]);
+
+    /// The digest of the data fed so far, starting over for new data
+    fn finalize_reset(&mut self) -> Vec<u8>;
+
+    /// Length of the digest in bytes
+    fn output_size(&self) -> usize;
+}
+
+/// Algorithm of a checksum or digest
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Algorithm {
+    /// The CRC of POSIX `cksum`
+    Crc,
+    /// The CRC-32 of zlib and gzip
+    Crc32b,
+    /// The CRC-32 of Castagnoli, of iSCSI and ext4
+    Crc32c,
+    Md5,
+    Sha1,
+    Sha224,
+    Sha256,
+    Sha384,
+    Sha512,
+    Blake2b,
+    Blake2s,
+    Blake3,
+}
+
+impl Algorithm {
+    pub const ALL: &'static [Algorithm] = &[
+        Algorithm::Crc,
+        Algorithm::Crc32b,
+        Algorithm::Crc32c,
+        Algorithm::Md5,
+        Algorithm::Sha1,
+        Algorithm::Sha224,
+        Algorithm::Sha256,
+        Algorithm::Sha384,
+        Algorithm::Sha512,
+        Algorithm::Blake2b,
+        Algorithm::Blake2s,
+        Algorithm::Blake3,
+    ];
+
+    /// Name of the algorithm, as `cksum -a` takes it
+    pub fn name(self) -> &'static str {
+        match self {
+            Algorithm::Crc => "crc",
+            Algorithm::Crc32b => "crc32b",
+            Algorithm::Crc32c => "crc32c",
+            Algorithm::Md5 => "md5",
+            Algorithm::Sha1 => "sha1",
+            Algorithm::Sha224 => "sha224",
+            Algorithm::Sha256 => "sha256",
+            Algorithm::Sha384 => "sha384",
+            Algorithm::Sha512 => "sha512",
+            Algorithm::Blake2b => "blake2b",
+            Algorithm::Blake2s => "blake2s",
+            Algorithm::Blake3 => "blake3",
+        }
+    }
+
+    /// Name of the algorithm in the tagged lines of `--tag`, like
+    /// `SHA256 (file) = ...`
+    pub fn tag(self) -> &'static str {
+        match self {
+            Algorithm::Crc => "CRC",
+            Algorithm::Crc32b => "CRC32B",
+            Algorithm::Crc32c => "CRC32C",
+            Algorithm::Md5 => "MD5",
+            Algorithm::Sha1 => "SHA1",
+            Algorithm::Sha224 => "SHA224",
+            Algorithm::Sha256 => "SHA256",
+            Algorithm::Sha384 => "SHA384",
+            Algorithm::Sha512 => "SHA512",
+            Algorithm::Blake2b => "BLAKE2b",
+            Algorithm::Blake2s => "BLAKE2s",
+            Algorithm::Blake3 => "BLAKE3",
+        }
+    }
+
+    /// Whether the algorithm is a CRC, which is no protection against
+    /// deliberate changes of the data
+    pub fn is_crc(self) -> bool {
+        matches!(self, Algorithm::Crc | Algorithm::Crc32b | Algorithm::Crc32c)
+    }
+
+    /// Length of the digest in bytes, by default
+    pub fn output_size(self) -> usize {
+        match self {
+            Algorithm::Crc | Algorithm::Crc32b | Algorithm::Crc32c => 4,
+            Algorithm::Md5 => 16,
+            Algorithm::Sha1 => 20,
+            Algorithm::Sha224 => 28,
+            Algorithm::Sha256 | Algorithm::Blake2s | Algorithm::Blake3 => 32,
+            Algorithm::Sha384 => 48,
+            Algorithm::Sha512 | Algorithm::Blake2b => 64,
+        }
+    }
+
+    /// A digest of the algorithm
+    pub fn hasher(self) -> Box<dyn Digest + Send> {
+        match self {
+            Algorithm::Crc => Box::new(crc::Cksum::new()),
+            Algorithm::Crc32b => Box::new(crc::Crc32b::new()),
+            Algorithm::Crc32c => Box::new(crc::Crc32c::new()),
+            Algorithm::Md5 => Box::new(Fixed(md5::Md5::default())),
+            Algorithm::Sha1 => Box::new(Fixed(sha1::Sha1::default())),
+            Algorithm::Sha224 => Box::new(Fixed(sha2::Sha224::default())),
+            Algorithm::Sha256 => Box::new(Fixed(sha2::Sha256::default())),
+            Algorithm::Sha384 => Box::new(Fixed(sha2::Sha384::default())),
+            Algorithm::Sha512 => Box::new(Fixed(sha2::Sha512::default())),
+            Algorithm::Blake2b => Box::new(Fixed(blake2::Blake2b512::default())),
+            Algorithm::Blake2s => Box::new(Fixed(blake2::Blake2s256::default())),
+            Algorithm::Blake3 => Box::new(Blake3::new(32)),
+        }
+    }
+
+    /// A digest of the algorithm `len` bytes long, like `b2sum -l` asks
+    /// for, `None` if the algorithm has no digest of that length
+    pub fn hasher_with_size(self, len: usize) -> Option<Box<dyn Digest + Send>> {
+        match self {
+            _ if len == self.output_size() => Some(self.hasher()),
+            Algorithm::Blake2b => Some(Box::new(Blake2bVar::new(len)?)),
+            Algorithm::Blake3 if len > 0 => Some(Box::new(Blake3::new(len))),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Algorithm {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Algorithm {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Algorithm::ALL
+            .iter()
+            .copied()
+            .find(|algorithm| algorithm.name() == s)
+            .ok_or_else(|| format!("unknown algorithm '{s}'"))
+    }
+}
+
+/// A digest of fixed length of the RustCrypto crates
+struct Fixed<D>(D);
+
+impl<D> Digest for Fixed<D>
+where
+    D: sha2::Digest + sha2::digest::FixedOutputReset,
+{
+    fn update(&mut self, data: &[u8]) {
+        sha2::Digest::update(&mut self.0, data);
+    }
+
+    fn finalize_reset(&mut self) -> Vec<u8> {
+        sha2::Digest::finalize_reset(&mut self.0).to_vec()
+    }
+
+    fn output_size(&self) -> usize {
+        <D as sha2::Digest>::output_size()
+    }
+}
+
+/// BLAKE2b with a digest of 1 to 64 bytes
+struct Blake2bVar {
+    state: blake2::Blake2bVar,
+    len: usize,
+}
+
+impl Blake2bVar {
+    fn new(len: usize) -> Option<Self> {
+        let state = blake2::Blake2bVar::new(len).ok()?;
+        Some(Blake2bVar { state, len })
+    }
+}
+
+impl Digest for Blake2bVar {
+    fn update(&mut self, data: &[u8]) {
+        self.state.update(data);
+    }
+
+    fn finalize_reset(&mut self) -> Vec<u8> {
+        let fresh = blake2::Blake2bVar::new(self.len).expect("the length was valid before");
+        let mut out = vec![0; self.len];
+        std::mem::replace(&mut self.state, fresh)
+            .finalize_variable(&mut out)
+            .expect("the buffer has the length of the digest");
+        out
+    }
+
+    fn output_size(&self) -> usize {
+        self.len
+    }
+}
+
+/// BLAKE3, whose digest may have any length
+struct Blake3 {
+    state: blake3::Hasher,
+    len: usize,
+}
+
+impl Blake3 {
+    fn new(len: usize) -> Self {
+        Blake3 {
+            state: blake3::Hasher::new(),
+            len,
+        }
+    }
+}
+
+impl Digest for Blake3 {
+    fn update(&mut self, data: &[u8]) {
+        self.state.update(data);
+    }
+
+    fn finalize_reset(&mut self) -> Vec<u8> {
+        let mut out = vec![0; self.len];
+        self.state.finalize_xof().fill(&mut out);
+        self.state.reset();
+        out
+    }
+
+    fn output_size(&self) -> usize {
+        self.len
+    }
+}
+
+/// Feed all of `reader` to `digest` and return the digest
+pub fn digest_reader(digest: &mut dyn Digest, mut reader: impl Read) -> io::Result<Vec<u8>> {
+    let mut buf = vec![0; 64 * 1024];
+    loop {
+        match reader.read(&mut buf) {
+            Ok(0) => return Ok(digest.finalize_reset()),
+            Ok(n) => digest.update(&buf[..n]),
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => (),
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex_digest(algorithm: Algorithm, data: &[u8]) -> String {
+        let mut digest = algorithm.hasher();
+        digest.update(data);
+        Encoding::Hex.encode(&digest.finalize_reset())
+    }
+
+    #[test]
+    fn known_digests() {
+        for (algorithm, expected) in [
+            (Algorithm::Crc32b, "352441c2"),
+            (Algorithm::Crc32c, "364b3fb7"),
+            (Algorithm::Md5, "900150983cd24fb0d6963f7d28e17f72"),
+            (Algorithm::Sha1, "a9993e364706816aba3e25717850c26c9cd0d89d"),
+            (
+                Algorithm::Sha256,
+                "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad",
+            ),
+            (
+                Algorithm::Blake2s,
+                "508c5e8c327c14e2e1a72ba34eeb452f37458b209ed63a294d999b4c86675982",
+            ),
+            (
+                Algorithm::Blake3,
+                "6437b3ac38465133ffb63b75273a8db548c558465d79db03fd359c6cd5bd9d85",
+            ),
+        ] {
+            assert_eq!(hex_digest(algorithm, b"abc"), expected, "{algorithm}");
+        }
+    }
+
+    #[test]
+    fn cksum() {
+        let mut digest = crc::Cksum::new();
+        assert_eq!(digest.value(), 4294967295);
+        digest.update(b"abc");
+        assert_eq!(digest.value(), 1219131554);
+    }
+
+    #[test]
+    fn base64() {
+        let mut digest = Algorithm::Sha256.hasher();
+        digest.update(b"abc");
+        let encoded = Encoding::Base64.encode(&digest.finalize_reset());
+        assert_eq!(encoded, "ungWv48Bz+pBQUDeXa4iI7ADYaOWF3qctBD/YfIAFa0=");
+        for data in [&b""[..], b"a", b"ab", b"abc", b"abcd"] {
+            let encoded = Encoding::Base64.encode(data);
+            assert_eq!(Encoding::Base64.decode(&encoded).as_deref(), Some(data));
+            let encoded = Encoding::Hex.encode(data);
+            assert_eq!(Encoding::Hex.decode(&encoded).as_deref(), Some(data));
+        }
+        assert_eq!(Encoding::Base64.encode(b"ab"), "YWI=");
+        assert!(Encoding::Base64.decode("YW=I").is_none());
+        assert!(Encoding::Hex.decode("abc").is_none());
+    }
+
+    #[test]
+    fn pieces_and_reset() {
+        for &algorithm in Algorithm::ALL {
+            let mut digest = algorithm.hasher();
+            digest.update(b"a");
+            digest.update(b"bc");
+            let pieces = digest.finalize_reset();
+            digest.update(b"abc");
+            assert_eq!(pieces, digest.finalize_reset(), "{algorithm}");
+            assert_eq!(pieces.len(), algorithm.output_size(), "{algorithm}");
+        }
+    }
+
+    #[test]
+    fn sizes() {
+        let mut digest = Algorithm::Blake2b.hasher_with_size(32).unwrap();
+        digest.update(b"abc");
+        assert_eq!(
+            Encoding::Hex.encode(&digest.finalize_reset()),
+            "bddd813c634239723171ef3fee98579b94964e3bb1cb3e427262c8c068d52319"
+        );
+        assert!(Algorithm::Blake2b.hasher_with_size(65).is_none());
+        assert!(Algorithm::Sha256.hasher_with_size(16).is_none());
+        assert_eq!(
+            Algorithm::Blake3
+                .hasher_with_size(64)
+                .unwrap()
+                .output_size(),
+            64
+        );
+    }
+}
@@ -0,0 +1,130 @@
+//! Command line interface of `paste`
+
+use crate::{Delimiters, Options};
+use clap::{ArgAction, CommandFactory, FromArgMatches, Parser};
+use nyaa_core::error::{Status, UError};
+use nyaa_core::i18n;
+use nyaa_core::output::{self, OutputSink};
+use nyaa_core::posix;
+use nyaa_core::record::{Records, Terminator};
+use nyaa_core::response;
+use nyaa_core::trace;
+use nyaa_core::Input;
+use std::ffi::OsString;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+const UTIL: &str = "paste";
+
+#[derive(Parser)]
+#[command(name = "paste", version)]
+#[command(about = "merge lines of files", long_about = None)]
+#[command(disable_help_flag = true, disable_version_flag = true)]
+struct Args {
+    /// Put the characters of LIST between the fields in turn instead of
+    /// tabs, with the escapes \n, \t, \\ and \0 for none
+    #[arg(
+        short = 'd',
+        long = "delimiters",
+        value_name = "LIST",
+        allow_hyphen_values = true,
+        value_parser = Delimiters::parse
+    )]
+    delimiters: Option<Delimiters>,
+
+    /// Paste the lines of one file at a time instead of in parallel
+    #[arg(short = 's', long = "serial")]
+    is_serial: bool,
+
+    /// Line delimiter is NUL, not newline
+    #[arg(short = 'z', long = "zero-terminated")]
+    is_zero_terminated: bool,
+
+    /// Input files. An argument @FILE stands for the arguments on the
+    /// lines of FILE, @@NAME for the file @NAME
+    files: Vec<PathBuf>,
+
+    /// Print help
+    #[arg(long, action=ArgAction::Help)]
+    help: (),
+
+    /// Print version
+    #[arg(long, action=ArgAction::Version)]
+    version: (),
+}
+
+pub fn command() -> clap::Command {
+    i18n::command(Args::command())
+}
+
+/// Run `paste` with the command line `args`, starting with the program name
+pub fn main(args: Vec<OsString>) -> ExitCode {
+    let mut status = Status::default();
+    let Some(args) = status.check(UTIL, response::expand(args)) else {
+        return status.into();
+    };
+    let args = posix::operands_last(&command(), args);
+    let matches = command().get_matches_from(args);
+    let args = Args::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+    trace::init(UTIL, false);
+    let options = Options {
+        delimiters: args.delimiters.unwrap_or_default(),
+        is_serial: args.is_serial,
+        terminator: Terminator::line(args.is_zero_terminated),
+    };
+    let operands = if args.files.is_empty() {
+        vec![PathBuf::from("-")]
+    } else {
+        args.files
+    };
+    // Every `-` operand reads from the one reader of the standard input
+    let mut names = Vec::new();
+    let mut readers = Vec::new();
+    let mut indices = Vec::new();
+    let mut stdin_index = None;
+    for operand in &operands {
+        let input = Input::from_operand(operand);
+        if input.is_stdin() {
+            if let Some(index) = stdin_index {
+                indices.push(index);
+                continue;
+            }
+        }
+        let name = input
+            .quoted_name()
+            .map_or_else(|| "-".to_string(), |name| name.into_owned());
+        match input.open_buffered() {
+            Ok(reader) => {
+                if input.is_stdin() {
+                    stdin_index = Some(readers.len());
+                }
+                indices.push(readers.len());
+                readers.push(Some(Records::new(reader, options.terminator)));
+                names.push(name);
+            }
+            Err(e) => {
+                status.report(UTIL, &UError::from(e).with_name(name));
+                // Lines are only pasted in parallel when all files are there
+                if !options.is_serial {
+                    return status.into();
+                }
+            }
+        }
+    }
+    let mut out = OutputSink::stdout();
+    let result = crate::paste(
+        &mut readers,
+        &indices,
+        &options,
+        &mut out,
+        &mut |index, e| {
+            status.report(UTIL, &UError::from(e).with_name(names[index].clone()));
+        },
+    );
+    if let Err(e) = result {
+        status.report(UTIL, &output::write_error(e));
+        return status.into();
+    }
+    status.check(UTIL, out.finish());
+    status.into()
+}
@@ -0,0 +1,225 @@
+//! Merging of lines of files with the semantics of `paste`.
+//!
+//! In parallel, the nth lines of all operands are joined into the nth
+//! output line, with empty fields for the operands that already ended.
+//! In serial, all lines of an operand are joined into one output line.
+//! Every `-` operand reads the next lines of the one standard input, so
+//! `paste - -` joins its lines in pairs.
+//!
+//! ```
+//! use nyaa_core::record::{Records, Terminator};
+//! use nyaa_paste::{Delimiters, Options};
+//!
+//! let mut readers = [
+//!     Some(Records::new(&b"1\n2\n"[..], Terminator::Newline)),
+//!     Some(Records::new(&b"a\nb\nc\n"[..], Terminator::Newline)),
+//! ];
+//! let options = Options {
+//!     delimiters: Delimiters::parse(",").unwrap(),
+//!     is_serial: false,
+//!     terminator: Terminator::Newline,
+//! };
+//! let mut out = Vec::new();
+//! nyaa_paste::paste(&mut readers, &[0, 1], &options, &mut out, &mut |_, _| ())?;
+//! assert_eq!(out, b"1,a\n2,b\n,c\n");
+//! # Ok::<(), std::io::Error>(())
+//! ```
+
+pub mod cli;
+
+use nyaa_core::record::{Records, Terminator};
+use std::io::{self, BufRead, Write};
+
+/// Delimiters put between the fields in turn, starting over with every
+/// output line. An empty one puts nothing between two fields.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Delimiters(Vec<Vec<u8>>);
+
+impl Delimiters {
+    /// Delimiters of the characters of `list`, with the escapes `\n`, `\t`,
+    /// `\\`, `\b`, `\f`, `\r`, `\v` and `\0` for an empty delimiter. A
+    /// backslash before any other character stands for that character. An
+    /// empty list is one empty delimiter.
+    pub fn parse(list: &str) -> Result<Self, String> {
+        let mut delimiters = Vec::new();
+        let mut chars = list.chars();
+        while let Some(c) = chars.next() {
+            let c = match c {
+                '\\' => match chars.next() {
+                    Some('0') => {
+                        delimiters.push(Vec::new());
+                        continue;
+                    }
+                    Some('b') => '\x08',
+                    Some('f') => '\x0c',
+                    Some('n') => '\n',
+                    Some('r') => '\r',
+                    Some('t') => '\t',
+                    Some('v') => '\x0b',
+                    Some(c) => c,
+                    None => return Err("the list ends with an unescaped backslash".to_string()),
+                },
+                c => c,
+            };
+            delimiters.push(c.to_string().into_bytes());
+        }
+        if delimiters.is_empty() {
+            delimiters.push(Vec::new());
+        }
+        Ok(Delimiters(delimiters))
+    }
+
+    /// Delimiter after the field `i` of a line
+    fn get(&self, i: usize) -> &[u8] {
+        &self.0[i % self.0.len()]
+    }
+}
+
+impl Default for Delimiters {
+    /// A tab
+    fn default() -> Self {
+        Delimiters(vec![b"\t".to_vec()])
+    }
+}
+
+/// How the lines are pasted
+pub struct Options {
+    pub delimiters: Delimiters,
+    /// Whether the lines of each operand make one output line
+    pub is_serial: bool,
+    /// What ends the lines of the input and the output
+    pub terminator: Terminator,
+}
+
+/// Append the next line of the reader `index`, without its terminator, to
+/// `line`. Returns false at its end. A reader that fails is reported
+/// through `on_error` with its index and ends there.
+fn append_line<R: BufRead>(
+    readers: &mut [Option<Records<R>>],
+    index: usize,
+    line: &mut Vec<u8>,
+    on_error: &mut impl FnMut(usize, io::Error),
+) -> bool {
+    let Some(records) = &mut readers[index] else {
+        return false;
+    };
+    let terminator = records.terminator();
+    match records.next_record() {
+        Ok(Some(record)) => {
+            line.extend_from_slice(terminator.strip(record));
+            return true;
+        }
+        Ok(None) => (),
+        Err(e) => on_error(index, e),
+    }
+    readers[index] = None;
+    false
+}
+
+/// Paste the lines of the `operands`, each the index of its reader in
+/// `readers`. Read errors are reported through `on_error`, which gets the
+/// index of the reader that failed, and end that reader. Fails on write
+/// errors.
+pub fn paste<R: BufRead>(
+    readers: &mut [Option<Records<R>>],
+    operands: &[usize],
+    options: &Options,
+    out: &mut impl Write,
+    on_error: &mut impl FnMut(usize, io::Error),
+) -> io::Result<()> {
+    let delimiters = &options.delimiters;
+    let terminator = options.terminator.as_bytes();
+    let mut line = Vec::new();
+    if options.is_serial {
+        for &index in operands {
+            line.clear();
+            let mut i = 0;
+            // Each line is written after the delimiter before it
+            while append_line(readers, index, &mut line, on_error) {
+                out.write_all(&line)?;
+                line.clear();
+                line.extend_from_slice(delimiters.get(i));
+                i += 1;
+            }
+            out.write_all(terminator)?;
+        }
+        return Ok(());
+    }
+    loop {
+        line.clear();
+        let mut has_fields = false;
+        for (i, &index) in operands.iter().enumerate() {
+            if i > 0 {
+                line.extend_from_slice(delimiters.get(i - 1));
+            }
+            has_fields |= append_line(readers, index, &mut line, on_error);
+        }
+        if !has_fields {
+            return Ok(());
+        }
+        line.extend_from_slice(terminator);
+        out.write_all(&line)?;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn paste_str(inputs: &[&str], operands: &[usize], delimiters: &str, is_serial: bool) -> String {
+        let mut readers: Vec<_> = inputs
+            .iter()
+            .map(|input| Some(Records::new(input.as_bytes(), Terminator::Newline)))
+            .collect();
+        let options = Options {
+            delimiters: Delimiters::parse(delimiters).unwrap(),
+            is_serial,
+            terminator: Terminator::Newline,
+        };
+        let mut out = Vec::new();
+        paste(&mut readers, operands, &options, &mut out, &mut |_, _| ()).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn delimiters() {
+        let parsed = Delimiters::parse(r"a\0\t\\\q").unwrap();
+        assert_eq!(parsed.0, [&b"a"[..], b"", b"\t", b"\\", b"q"]);
+        assert_eq!(Delimiters::parse("").unwrap().0, [b""]);
+        assert_eq!(Delimiters::parse("é").unwrap().0, ["é".as_bytes()]);
+        assert!(Delimiters::parse(r"a\").is_err());
+    }
+
+    #[test]
+    fn parallel() {
+        assert_eq!(
+            paste_str(&["1\n2\n3\n", "x\ny"], &[0, 1], "\t", false),
+            "1\tx\n2\ty\n3\t\n"
+        );
+        assert_eq!(
+            paste_str(&["1\n", "", "2\n"], &[0, 1, 2], ":,", false),
+            "1:,2\n"
+        );
+        assert_eq!(paste_str(&["", ""], &[0, 1], "\t", false), "");
+    }
+
+    #[test]
+    fn shared_reader() {
+        assert_eq!(
+            paste_str(&["p\nq\nr\n", "1\n"], &[0, 1, 0], "\t", false),
+            "p\t1\tq\nr\t\t\n"
+        );
+        assert_eq!(
+            paste_str(&["p\nq\n", "1\n"], &[0, 1, 0], "\t", true),
+            "p\tq\n1\n\n"
+        );
+    }
+
+    #[test]
+    fn serial() {
+        assert_eq!(
+            paste_str(&["1\n2\n3\n", "", "x\ny"], &[0, 1, 2], ",;", true),
+            "1,2;3\n\nx,y\n"
+        );
+    }
+}
@@ -0,0 +1,188 @@
+//! Conversion of the arguments of numeric directives, like GNU `printf`
+//! does: an argument starting with a quote stands for the code of the
+//! character after it, anything else is read like strtoimax(3),
+//! strtoumax(3) or strtold(3) read it. A bad argument is converted as far
+//! as it goes, with a diagnostic that doesn't stop printing.
+
+use crate::float::{is_space, LongDouble, Parsed};
+use nyaa_core::locale::Locale;
+
+/// What is wrong with an argument of a numeric directive
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Invalid {
+    /// Not a number at all
+    NotNumeric,
+    /// A number followed by something else
+    NotComplete,
+    /// A number out of the range of the conversion
+    Range,
+    /// Characters after a character constant like `'ab`, ignored with a
+    /// warning that doesn't fail
+    Ignored(Vec<u8>),
+}
+
+/// The code of the character after the quote starting `arg`, for the
+/// arguments that are character constants, and a warning about any
+/// characters after it unless `is_posix`
+fn character(arg: &[u8], locale: Locale, is_posix: bool) -> Option<(u32, Option<Invalid>)> {
+    let rest = match arg {
+        [b'\'' | b'"', rest @ ..] if !rest.is_empty() => rest,
+        _ => return None,
+    };
+    let (code, len) = match rest.utf8_chunks().next() {
+        Some(chunk) if locale == Locale::Unicode && !chunk.valid().is_empty() => {
+            let c = chunk.valid().chars().next().unwrap_or_default();
+            (c as u32, c.len_utf8())
+        }
+        _ => (rest[0] as u32, 1),
+    };
+    let ignored = &rest[len..];
+    let invalid = (!ignored.is_empty() && !is_posix).then(|| Invalid::Ignored(ignored.to_vec()));
+    Some((code, invalid))
+}
+
+/// The integer at the start of `arg` like strtoumax(3) reads it with the
+/// base 0: after white space and a sign, hexadecimal after `0x`, octal
+/// after `0` and decimal else. Returns whether it is negative, its
+/// magnitude, or `None` if that overflows, and the bytes read, 0 if none.
+fn integer(arg: &[u8]) -> (bool, Option<u64>, usize) {
+    let mut i = arg.iter().take_while(|&&b| is_space(b)).count();
+    let is_negative = arg.get(i) == Some(&b'-');
+    if matches!(arg.get(i), Some(b'-' | b'+')) {
+        i += 1;
+    }
+    let radix = match &arg[i..] {
+        [b'0', b'x' | b'X', b, ..] if b.is_ascii_hexdigit() => {
+            i += 2;
+            16
+        }
+        [b'0', ..] => 8,
+        _ => 10,
+    };
+    let start = i;
+    let mut magnitude = Some(0u64);
+    while let Some(d) = arg.get(i).and_then(|&b| (b as char).to_digit(radix)) {
+        magnitude = magnitude
+            .and_then(|m| m.checked_mul(radix as u64))
+            .and_then(|m| m.checked_add(d as u64));
+        i += 1;
+    }
+    (is_negative, magnitude, if i > start { i } else { 0 })
+}
+
+/// What is wrong with `arg` read up to `len` bytes, if anything
+fn check(arg: &[u8], len: usize, is_range_error: bool) -> Option<Invalid> {
+    if is_range_error {
+        Some(Invalid::Range)
+    } else if len == arg.len() {
+        None
+    } else if len == 0 {
+        Some(Invalid::NotNumeric)
+    } else {
+        Some(Invalid::NotComplete)
+    }
+}
+
+/// `arg` as the argument of `%d` and `%i`, and of `*` widths and
+/// precisions. Out of range values are clamped.
+pub fn signed(arg: &[u8], locale: Locale, is_posix: bool) -> (i64, Option<Invalid>) {
+    if let Some((code, invalid)) = character(arg, locale, is_posix) {
+        return (code as i64, invalid);
+    }
+    let (is_negative, magnitude, len) = integer(arg);
+    let (value, is_range_error) = match (is_negative, magnitude) {
+        (false, Some(m)) if m <= i64::MAX as u64 => (m as i64, false),
+        (true, Some(m)) if m <= i64::MIN.unsigned_abs() => ((m as i64).wrapping_neg(), false),
+        (false, _) => (i64::MAX, true),
+        (true, _) => (i64::MIN, true),
+    };
+    (value, check(arg, len, is_range_error))
+}
+
+/// `arg` as the argument of `%o`, `%u`, `%x` and `%X`. Negative values
+/// wrap around, out of range ones are clamped.
+pub fn unsigned(arg: &[u8], locale: Locale, is_posix: bool) -> (u64, Option<Invalid>) {
+    if let Some((code, invalid)) = character(arg, locale, is_posix) {
+        return (code as u64, invalid);
+    }
+    let (is_negative, magnitude, len) = integer(arg);
+    let (value, is_range_error) = match magnitude {
+        Some(m) if is_negative => (m.wrapping_neg(), false),
+        Some(m) => (m, false),
+        None => (u64::MAX, true),
+    };
+    (value, check(arg, len, is_range_error))
+}
+
+/// `arg` as the argument of the floating-point conversions
+pub fn float(arg: &[u8], locale: Locale, is_posix: bool) -> (LongDouble, Option<Invalid>) {
+    if let Some((code, invalid)) = character(arg, locale, is_posix) {
+        return (LongDouble::from_u64(code as u64, false), invalid);
+    }
+    let Parsed {
+        value,
+        len,
+        is_range_error,
+    } = LongDouble::parse(arg);
+    (value, check(arg, len, is_range_error))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn integers() {
+        let signed = |arg: &str| signed(arg.as_bytes(), Locale::C, false);
+        assert_eq!(signed("42"), (42, None));
+        assert_eq!(signed(" \t-0x1F"), (-31, None));
+        assert_eq!(signed("+010"), (8, None));
+        assert_eq!(signed("09"), (0, Some(Invalid::NotComplete)));
+        assert_eq!(signed("0x"), (0, Some(Invalid::NotComplete)));
+        assert_eq!(signed("12 "), (12, Some(Invalid::NotComplete)));
+        assert_eq!(signed("- 1"), (0, Some(Invalid::NotNumeric)));
+        assert_eq!(signed(""), (0, None));
+        assert_eq!(signed("-9223372036854775808"), (i64::MIN, None));
+        assert_eq!(
+            signed("9223372036854775808"),
+            (i64::MAX, Some(Invalid::Range))
+        );
+        assert_eq!(
+            signed("-99999999999999999999x"),
+            (i64::MIN, Some(Invalid::Range))
+        );
+        let unsigned = |arg: &str| unsigned(arg.as_bytes(), Locale::C, false);
+        assert_eq!(unsigned("-1"), (u64::MAX, None));
+        assert_eq!(
+            unsigned("18446744073709551616"),
+            (u64::MAX, Some(Invalid::Range))
+        );
+    }
+
+    #[test]
+    fn character_constants() {
+        assert_eq!(signed(b"'A", Locale::C, false), (65, None));
+        assert_eq!(
+            signed(b"\"", Locale::C, false),
+            (0, Some(Invalid::NotNumeric))
+        );
+        assert_eq!(signed("'é".as_bytes(), Locale::Unicode, false), (233, None));
+        assert_eq!(
+            signed("'é".as_bytes(), Locale::C, false),
+            (195, Some(Invalid::Ignored(vec![0xa9])))
+        );
+        assert_eq!(signed(b"'ab", Locale::C, true), (97, None));
+        assert_eq!(unsigned(b"'\xff", Locale::Unicode, false), (255, None));
+        let (value, invalid) = float(b"'a", Locale::C, false);
+        assert_eq!((value, invalid), (LongDouble::from_u64(97, false), None));
+    }
+
+    #[test]
+    fn floats() {
+        let float = |arg: &str| float(arg.as_bytes(), Locale::C, false).1;
+        assert_eq!(float("1e5000"), Some(Invalid::Range));
+        assert_eq!(float("1e"), Some(Invalid::NotComplete));
+        assert_eq!(float(".e1"), Some(Invalid::NotNumeric));
+        assert_eq!(float(" -0x1.8p3"), None);
+    }
+}
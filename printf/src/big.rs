@@ -0,0 +1,217 @@
+//! Unsigned integers of any size, as far as converting long doubles
+//! exactly from and to decimal takes them: multiplying by powers,
+//! shifting, and dividing into quotients of a few words
+
+use std::cmp::Ordering;
+
+/// An unsigned integer, in 32-bit words from the least significant one,
+/// without leading zero words
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Big(Vec<u32>);
+
+impl Big {
+    pub fn from_u64(n: u64) -> Self {
+        let mut big = Big(vec![n as u32, (n >> 32) as u32]);
+        big.trim();
+        big
+    }
+
+    fn trim(&mut self) {
+        while self.0.last() == Some(&0) {
+            self.0.pop();
+        }
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Number of bits up to the highest one set, 0 for zero
+    pub fn bits(&self) -> u64 {
+        match self.0.last() {
+            Some(&top) => self.0.len() as u64 * 32 - top.leading_zeros() as u64,
+            None => 0,
+        }
+    }
+
+    /// Multiply by `m` and add `a`
+    pub fn mul_add(&mut self, m: u32, a: u32) {
+        let mut carry = a as u64;
+        for word in &mut self.0 {
+            let product = *word as u64 * m as u64 + carry;
+            *word = product as u32;
+            carry = product >> 32;
+        }
+        if carry > 0 {
+            self.0.push(carry as u32);
+        }
+        self.trim();
+    }
+
+    /// Multiply by `base` to the power `exp`, for a `base` of at most 10
+    pub fn mul_pow(&mut self, base: u32, exp: u64) {
+        // Nine factors at a time, as many of 10 as a word holds
+        let (step, per_step) = (9, base.pow(9));
+        let mut exp = exp;
+        while exp >= step {
+            self.mul_add(per_step, 0);
+            exp -= step;
+        }
+        self.mul_add(base.pow(exp as u32), 0);
+    }
+
+    /// Shift left by `bits`
+    pub fn shl(&mut self, bits: u64) {
+        if self.is_zero() {
+            return;
+        }
+        let (words, bits) = ((bits / 32) as usize, (bits % 32) as u32);
+        if bits > 0 {
+            let mut carry = 0;
+            for word in &mut self.0 {
+                let shifted = (*word as u64) << bits | carry;
+                *word = shifted as u32;
+                carry = shifted >> 32;
+            }
+            if carry > 0 {
+                self.0.push(carry as u32);
+            }
+        }
+        self.0.splice(0..0, std::iter::repeat_n(0, words));
+    }
+
+    /// Shift right by one bit
+    fn shr1(&mut self) {
+        let mut carry = 0;
+        for word in self.0.iter_mut().rev() {
+            let shifted = *word >> 1 | carry << 31;
+            carry = *word & 1;
+            *word = shifted;
+        }
+        self.trim();
+    }
+
+    /// Subtract `other`, which isn't greater
+    fn sub(&mut self, other: &Big) {
+        let mut borrow = 0;
+        for (i, word) in self.0.iter_mut().enumerate() {
+            let rhs = other.0.get(i).copied().unwrap_or(0) as i64 + borrow;
+            let diff = *word as i64 - rhs;
+            borrow = (diff < 0) as i64;
+            *word = diff.rem_euclid(1 << 32) as u32;
+        }
+        self.trim();
+    }
+
+    /// Divide by `den`, leaving the remainder, and return the quotient,
+    /// which must be less than 2^128
+    pub fn div_rem(&mut self, den: &Big) -> u128 {
+        let mut quotient = 0;
+        let Some(shift) = self.bits().checked_sub(den.bits()) else {
+            return 0;
+        };
+        let mut shifted = den.clone();
+        shifted.shl(shift);
+        for i in (0..=shift).rev() {
+            if *self >= shifted {
+                self.sub(&shifted);
+                quotient |= 1 << i;
+            }
+            shifted.shr1();
+        }
+        quotient
+    }
+
+    /// Divide by `d`, returning the remainder
+    fn div_rem_small(&mut self, d: u32) -> u32 {
+        let mut rem = 0u64;
+        for word in self.0.iter_mut().rev() {
+            let cur = rem << 32 | *word as u64;
+            *word = (cur / d as u64) as u32;
+            rem = cur % d as u64;
+        }
+        self.trim();
+        rem as u32
+    }
+
+    /// Decimal digits, from the most significant one, as values 0 to 9.
+    /// Zero has none.
+    pub fn digits(mut self) -> Vec<u8> {
+        const BASE: u32 = 1_000_000_000;
+        let mut chunks = Vec::new();
+        while !self.is_zero() {
+            chunks.push(self.div_rem_small(BASE));
+        }
+        let mut digits = Vec::with_capacity(chunks.len() * 9);
+        for (i, chunk) in chunks.iter().rev().enumerate() {
+            let chunk = chunk.to_string();
+            if i > 0 {
+                digits.resize(digits.len() + 9 - chunk.len(), 0);
+            }
+            digits.extend(chunk.bytes().map(|b| b - b'0'));
+        }
+        digits
+    }
+}
+
+impl Ord for Big {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0
+            .len()
+            .cmp(&other.0.len())
+            .then_with(|| self.0.iter().rev().cmp(other.0.iter().rev()))
+    }
+}
+
+impl PartialOrd for Big {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn big(digits: &str) -> Big {
+        let mut big = Big::default();
+        for b in digits.bytes() {
+            big.mul_add(10, (b - b'0') as u32);
+        }
+        big
+    }
+
+    fn string(big: Big) -> String {
+        big.digits().iter().map(|d| (d + b'0') as char).collect()
+    }
+
+    #[test]
+    fn arithmetic() {
+        let mut n = big("123456789012345678901234567890");
+        assert_eq!(n.bits(), 97);
+        n.mul_pow(10, 20);
+        assert_eq!(
+            string(n.clone()),
+            "12345678901234567890123456789000000000000000000000"
+        );
+        let mut m = Big::from_u64(5);
+        m.mul_pow(5, 30);
+        assert_eq!(string(m), "4656612873077392578125");
+        let mut shifted = Big::from_u64(3);
+        shifted.shl(100);
+        assert_eq!(string(shifted), "3802951800684688204490109616128");
+        assert_eq!(string(Big::default()), "");
+        assert_eq!(string(big("1000000000")), "1000000000");
+    }
+
+    #[test]
+    fn division() {
+        let mut n = big("98765432109876543210987654321");
+        let q = n.div_rem(&big("1234567890123"));
+        assert_eq!(q, 80000000729029606);
+        assert_eq!(string(n), "694365672783");
+        let mut small = Big::from_u64(7);
+        assert_eq!(small.div_rem(&big("8")), 0);
+        assert_eq!(small, Big::from_u64(7));
+    }
+}
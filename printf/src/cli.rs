@@ -0,0 +1,146 @@
+//! Command line interface of `printf`
+
+use crate::{End, Error, Invalid, Options};
+use clap::{ArgAction, CommandFactory, Parser};
+use nyaa_core::error::{self, Status, UError};
+use nyaa_core::i18n;
+use nyaa_core::locale::Locale;
+use nyaa_core::output::{self, OutputSink};
+use nyaa_core::quote::shell_escape;
+use nyaa_core::trace;
+use std::env;
+use std::ffi::{OsStr, OsString};
+use std::process::ExitCode;
+
+const UTIL: &str = "printf";
+
+/// The options as help, the man page and completions show them. The
+/// arguments are parsed like coreutils does instead, with `--help` and
+/// `--version` only taken as the only argument.
+#[derive(Parser)]
+#[command(name = "printf", version)]
+#[command(about = "format and print data", long_about = None)]
+#[command(disable_help_flag = true, disable_version_flag = true)]
+#[allow(dead_code)]
+struct Args {
+    /// Format of the output: characters printed as they are, backslash
+    /// escapes, and directives like %d printing the arguments. The format
+    /// is reused while arguments are left.
+    #[arg(value_name = "FORMAT")]
+    format: OsString,
+
+    /// Arguments of the directives. Those of numeric directives are read
+    /// like C constants, or as the code of the character after a leading
+    /// quote.
+    #[arg(value_name = "ARGUMENT")]
+    arguments: Vec<OsString>,
+
+    /// Print help, when it is the only argument
+    #[arg(long, action=ArgAction::Help)]
+    help: (),
+
+    /// Print version, when it is the only argument
+    #[arg(long, action=ArgAction::Version)]
+    version: (),
+}
+
+pub fn command() -> clap::Command {
+    i18n::command(Args::command())
+}
+
+/// `message` with `{}` replaced by `arg` quoted for the shell
+fn quoted(message: &str, arg: &[u8]) -> String {
+    // SAFETY: the bytes are those of a whole argument, an OsStr
+    let arg = unsafe { OsStr::from_encoded_bytes_unchecked(arg) };
+    i18n::tr(message).replace("{}", &shell_escape(arg))
+}
+
+/// Report the invalid argument `arg` of a numeric directive
+fn report_invalid(status: &mut Status, arg: &[u8], invalid: Invalid) {
+    let message = match invalid {
+        Invalid::NotNumeric => quoted("{}: expected a numeric value", arg),
+        Invalid::NotComplete => quoted("{}: value not completely converted", arg),
+        Invalid::Range => quoted("{}: Numerical result out of range", arg),
+        Invalid::Ignored(rest) => {
+            let message = i18n::tr(
+                "warning: {}: character(s) following character constant have been ignored",
+            );
+            let message = message.replace("{}", &String::from_utf8_lossy(&rest));
+            error::notice(UTIL, message);
+            return;
+        }
+    };
+    status.report(UTIL, &UError::failure(message));
+}
+
+/// The error ending the output
+fn describe(err: Error) -> UError {
+    match err {
+        Error::Conversion(spec) => UError::failure(
+            i18n::tr("{}: invalid conversion specification")
+                .replace("{}", &String::from_utf8_lossy(&spec)),
+        ),
+        Error::MissingHex => UError::failure("missing hexadecimal number in escape"),
+        Error::UniversalName(c, code) => {
+            let digits = if c == b'u' { 4 } else { 8 };
+            let name = format!("\\{}{code:0digits$x}", c as char);
+            UError::failure(i18n::tr("invalid universal character name {}").replace("{}", &name))
+        }
+        Error::Width(arg) => UError::failure(quoted("invalid field width: {}", &arg)),
+        Error::Precision(arg) => UError::failure(quoted("invalid precision: {}", &arg)),
+        Error::Write(e) => output::write_error(e),
+    }
+}
+
+/// Run `printf` with the command line `args`, starting with the program
+/// name
+pub fn main(args: Vec<OsString>) -> ExitCode {
+    let mut status = Status::default();
+    let mut operands: Vec<&[u8]> = args
+        .iter()
+        .skip(1)
+        .map(|arg| arg.as_encoded_bytes())
+        .collect();
+    if matches!(operands[..], [b"--help" | b"--version"]) {
+        command().get_matches_from(&args);
+        return status.into();
+    }
+    trace::init(UTIL, false);
+    if operands.first() == Some(&&b"--"[..]) {
+        operands.remove(0);
+    }
+    let Some((format, arguments)) = operands.split_first() else {
+        status.report(UTIL, &UError::usage("missing operand"));
+        return status.into();
+    };
+    let options = Options {
+        locale: Locale::ctype(),
+        is_posix: env::var_os("POSIXLY_CORRECT").is_some(),
+    };
+    let mut out = OutputSink::stdout();
+    let mut warn = |arg: &[u8], invalid| report_invalid(&mut status, arg, invalid);
+    let end = crate::printf(format, arguments, &options, &mut out, &mut warn);
+    match end {
+        Ok(End::Done(used)) => {
+            if let Some(excess) = arguments.get(used) {
+                let message = quoted(
+                    "warning: ignoring excess arguments, starting with {}",
+                    excess,
+                );
+                error::notice(UTIL, message);
+            }
+        }
+        // The output ends there successfully, whatever failed before
+        Ok(End::Stopped) => status = Status::default(),
+        Err(err) => {
+            // What was formatted before is still printed
+            if !matches!(err, Error::Write(_)) {
+                status.check(UTIL, out.finish());
+            }
+            status.report(UTIL, &describe(err));
+            return status.into();
+        }
+    }
+    status.check(UTIL, out.finish());
+    status.into()
+}
@@ -0,0 +1,165 @@
+//! Backslash escapes of the format and of the arguments of `%b`
+
+use crate::Error;
+use nyaa_core::locale::Locale;
+use std::io::Write;
+
+/// Value of the hexadecimal digit at `s[i]`, if it is one
+fn hex_digit(s: &[u8], i: usize) -> Option<u32> {
+    s.get(i).and_then(|&b| (b as char).to_digit(16))
+}
+
+/// Write the character `code` of `\u` or `\U`, encoded in UTF-8 in a
+/// Unicode locale. Where it can't be encoded, the escape is written back
+/// in upper case.
+fn write_unicode(code: u32, locale: Locale, out: &mut impl Write) -> Result<(), Error> {
+    match char::from_u32(code) {
+        Some(c) if c.is_ascii() || locale == Locale::Unicode => {
+            out.write_all(c.encode_utf8(&mut [0; 4]).as_bytes())?
+        }
+        _ if code < 0x10000 => write!(out, "\\u{code:04X}")?,
+        _ => write!(out, "\\U{code:08X}")?,
+    }
+    Ok(())
+}
+
+/// Write the escape at the start of `s`, after its backslash. Octal
+/// escapes take up to three digits, after a `0` that doesn't count if
+/// `is_octal_0`, like in `%b`. Returns the length of the escape, or
+/// `None` at a `\c`, which ends the output.
+pub fn write_escape(
+    s: &[u8],
+    is_octal_0: bool,
+    locale: Locale,
+    out: &mut impl Write,
+) -> Result<Option<usize>, Error> {
+    let Some(&c) = s.first() else {
+        out.write_all(b"\\")?;
+        return Ok(Some(0));
+    };
+    let len = match c {
+        b'x' => {
+            let digits = (1..3).take_while(|&i| hex_digit(s, i).is_some()).count();
+            if digits == 0 {
+                return Err(Error::MissingHex);
+            }
+            let value = (1..=digits).fold(0, |value, i| value * 16 + hex_digit(s, i).unwrap_or(0));
+            out.write_all(&[value as u8])?;
+            digits + 1
+        }
+        b'0'..=b'7' => {
+            let start = (is_octal_0 && c == b'0') as usize;
+            let digits = s[start..]
+                .iter()
+                .take(3)
+                .take_while(|b| matches!(b, b'0'..=b'7'))
+                .count();
+            let value = s[start..start + digits]
+                .iter()
+                .fold(0u32, |value, b| value * 8 + (b - b'0') as u32);
+            out.write_all(&[value as u8])?;
+            start + digits
+        }
+        b'c' => return Ok(None),
+        b'u' | b'U' => {
+            let digits = if c == b'u' { 4 } else { 8 };
+            let mut code = 0u32;
+            for i in 1..=digits {
+                code = code * 16 + hex_digit(s, i).ok_or(Error::MissingHex)?;
+            }
+            // Controls, surrogates and the ASCII characters other than
+            // `$`, `@` and `` ` `` aren't names of characters
+            if (code <= 0x9f && !matches!(code, 0x24 | 0x40 | 0x60))
+                || (0xd800..=0xdfff).contains(&code)
+            {
+                return Err(Error::UniversalName(c, code));
+            }
+            write_unicode(code, locale, out)?;
+            digits + 1
+        }
+        _ => {
+            let byte = match c {
+                b'a' => b'\x07',
+                b'b' => b'\x08',
+                b'e' => b'\x1b',
+                b'f' => b'\x0c',
+                b'n' => b'\n',
+                b'r' => b'\r',
+                b't' => b'\t',
+                b'v' => b'\x0b',
+                b'"' | b'\\' => c,
+                _ => {
+                    out.write_all(&[b'\\', c])?;
+                    return Ok(Some(1));
+                }
+            };
+            out.write_all(&[byte])?;
+            1
+        }
+    };
+    Ok(Some(len))
+}
+
+/// Write the argument `arg` of `%b` with its escapes interpreted. Returns
+/// `false` at a `\c`.
+pub fn write_escaped(arg: &[u8], locale: Locale, out: &mut impl Write) -> Result<bool, Error> {
+    let mut rest = arg;
+    while let Some(i) = memchr::memchr(b'\\', rest) {
+        out.write_all(&rest[..i])?;
+        match write_escape(&rest[i + 1..], true, locale, out)? {
+            Some(len) => rest = &rest[i + 1 + len..],
+            None => return Ok(false),
+        }
+    }
+    out.write_all(rest)?;
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn escaped(arg: &str, locale: Locale) -> Result<(Vec<u8>, bool), Error> {
+        let mut out = Vec::new();
+        let is_done = write_escaped(arg.as_bytes(), locale, &mut out)?;
+        Ok((out, is_done))
+    }
+
+    #[test]
+    fn escapes() {
+        let (out, is_done) = escaped(r#"\a\b\e\f\n\r\t\v\\\"\q\"#, Locale::C).unwrap();
+        assert_eq!(out, b"\x07\x08\x1b\x0c\n\r\t\x0b\\\"\\q\\");
+        assert!(is_done);
+        let (out, _) = escaped(r"\0101\101\1010\08\x41\x4a4\400", Locale::C).unwrap();
+        assert_eq!(out, b"AAA0\x008AJ4\x00");
+        let (out, is_done) = escaped(r"a\cb", Locale::C).unwrap();
+        assert_eq!((out, is_done), (b"a".to_vec(), false));
+        let mut out = Vec::new();
+        assert_eq!(
+            write_escape(b"0101", false, Locale::C, &mut out).unwrap(),
+            Some(3)
+        );
+        assert_eq!(out, b"\x08");
+    }
+
+    #[test]
+    fn unicode() {
+        let (out, _) = escaped(r"\u00e9\U0001F600$", Locale::Unicode).unwrap();
+        assert_eq!(out, "é😀$".as_bytes());
+        let (out, _) = escaped(r"\u00e9\U0001f600$\U00110000", Locale::C).unwrap();
+        assert_eq!(out, br"\u00E9\U0001F600$\U00110000");
+        assert!(matches!(
+            escaped(r"\u0041", Locale::C),
+            Err(Error::UniversalName(b'u', 0x41))
+        ));
+        assert!(matches!(
+            escaped(r"\ud800", Locale::C),
+            Err(Error::UniversalName(..))
+        ));
+        assert!(matches!(
+            escaped(r"\u12", Locale::C),
+            Err(Error::MissingHex)
+        ));
+        assert!(matches!(escaped(r"\xg", Locale::C), Err(Error::MissingHex)));
+    }
+}
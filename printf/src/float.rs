@@ -0,0 +1,608 @@
+//! The `long double` of C on x86, the 80-bit extended precision that GNU
+//! `printf` converts floating-point arguments to: a 64-bit significand
+//! and a 15-bit exponent. Arguments are rounded to it exactly like
+//! strtold(3) does, and printed with the exact decimal value of what they
+//! were rounded to, so `%.20f` of 0.1 shows the same digits as GNU.
+
+use crate::big::Big;
+
+/// Exponent of the significand of the least subnormal value, 2^-16445
+const MIN_EXPONENT: i32 = -16445;
+
+/// Exponent of the significand of the largest finite values, below 2^16384
+const MAX_EXPONENT: i32 = 16320;
+
+/// Bits of the significant digits of an argument taken into account,
+/// about 12000 decimal ones. The exact midpoint between two long doubles
+/// has less, the digits past them only tell it isn't one.
+const MAX_BITS: u64 = 40000;
+
+/// What a long double is, apart from its sign
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Kind {
+    /// `significand × 2^exponent`, normalized with the top bit of the
+    /// significand set unless it is subnormal or zero
+    Finite {
+        significand: u64,
+        exponent: i32,
+    },
+    Infinite,
+    Nan,
+}
+
+/// A long double
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LongDouble {
+    pub is_negative: bool,
+    pub kind: Kind,
+}
+
+/// A long double read from the start of an argument
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Parsed {
+    pub value: LongDouble,
+    /// Bytes of the argument read, 0 if it doesn't start with a number
+    pub len: usize,
+    /// Whether the number overflowed to infinity, or was too small to be
+    /// held without losing precision
+    pub is_range_error: bool,
+}
+
+/// Whether `b` is white space in the C locale
+pub fn is_space(b: u8) -> bool {
+    matches!(b, b' ' | b'\t' | b'\n' | b'\x0b' | b'\x0c' | b'\r')
+}
+
+/// Whether `s` starts with `prefix` in any case
+fn starts_with_ignore_case(s: &[u8], prefix: &[u8]) -> bool {
+    s.len() >= prefix.len() && s[..prefix.len()].eq_ignore_ascii_case(prefix)
+}
+
+/// The digits of `s` from `i` on in `radix`, added to `n` while it has
+/// less than [`MAX_BITS`]. Returns the index after them, how many there
+/// are, how many of them were left out, and whether any of those isn't
+/// zero.
+fn digits(s: &[u8], mut i: usize, radix: u32, n: &mut Big) -> (usize, usize, usize, bool) {
+    let (mut count, mut dropped, mut is_sticky) = (0, 0, false);
+    while let Some(d) = s.get(i).and_then(|&b| (b as char).to_digit(radix)) {
+        if n.bits() < MAX_BITS {
+            n.mul_add(radix, d);
+        } else {
+            dropped += 1;
+            is_sticky |= d != 0;
+        }
+        count += 1;
+        i += 1;
+    }
+    (i, count, dropped, is_sticky)
+}
+
+/// A decimal or binary exponent after `s[i]`, `e` or `p`, for the
+/// index after it, if there is one
+fn exponent(s: &[u8], i: usize, marker: u8) -> Option<(usize, i64)> {
+    if !s.get(i)?.eq_ignore_ascii_case(&marker) {
+        return None;
+    }
+    let mut j = i + 1;
+    let is_negative = match s.get(j) {
+        Some(b'-') => {
+            j += 1;
+            true
+        }
+        Some(b'+') => {
+            j += 1;
+            false
+        }
+        _ => false,
+    };
+    let start = j;
+    let mut value: i64 = 0;
+    while let Some(b) = s.get(j).filter(|b| b.is_ascii_digit()) {
+        value = (value * 10 + (b - b'0') as i64).min(1 << 40);
+        j += 1;
+    }
+    (j > start).then_some((j, if is_negative { -value } else { value }))
+}
+
+impl LongDouble {
+    pub const ZERO: LongDouble = LongDouble {
+        is_negative: false,
+        kind: Kind::Finite {
+            significand: 0,
+            exponent: 0,
+        },
+    };
+
+    /// `n`, negated if `is_negative`, which every u64 is exactly
+    pub fn from_u64(n: u64, is_negative: bool) -> Self {
+        let shift = n.leading_zeros().min(63);
+        LongDouble {
+            is_negative,
+            kind: Kind::Finite {
+                significand: n << shift,
+                exponent: if n == 0 { 0 } else { -(shift as i32) },
+            },
+        }
+    }
+
+    pub fn is_zero(&self) -> bool {
+        matches!(self.kind, Kind::Finite { significand: 0, .. })
+    }
+
+    /// The number at the start of `s` like strtold(3) reads it in the C
+    /// locale: after white space and a sign, a decimal number with an
+    /// optional exponent, a hexadecimal one after `0x` with an optional
+    /// binary exponent, `inf`, `infinity` or `nan`, in any case
+    pub fn parse(s: &[u8]) -> Parsed {
+        let mut i = s.iter().take_while(|&&b| is_space(b)).count();
+        let is_negative = s.get(i) == Some(&b'-');
+        if matches!(s.get(i), Some(b'-' | b'+')) {
+            i += 1;
+        }
+        let special = |kind, len| Parsed {
+            value: LongDouble { is_negative, kind },
+            len,
+            is_range_error: false,
+        };
+        let rest = &s[i..];
+        if starts_with_ignore_case(rest, b"infinity") {
+            return special(Kind::Infinite, i + 8);
+        }
+        if starts_with_ignore_case(rest, b"inf") {
+            return special(Kind::Infinite, i + 3);
+        }
+        if starts_with_ignore_case(rest, b"nan") {
+            let mut len = i + 3;
+            if s.get(len) == Some(&b'(') {
+                let chars = s[len + 1..]
+                    .iter()
+                    .take_while(|b| b.is_ascii_alphanumeric() || **b == b'_')
+                    .count();
+                if s.get(len + 1 + chars) == Some(&b')') {
+                    len += chars + 2;
+                }
+            }
+            return special(Kind::Nan, len);
+        }
+        let is_hex_digit = |i: usize| s.get(i).is_some_and(u8::is_ascii_hexdigit);
+        let is_hex = starts_with_ignore_case(rest, b"0x")
+            && (is_hex_digit(i + 2) || (s.get(i + 2) == Some(&b'.') && is_hex_digit(i + 3)));
+        let (radix, marker, digit_bits) = if is_hex {
+            i += 2;
+            (16, b'p', 4)
+        } else {
+            (10, b'e', 1)
+        };
+        // The number is n × radix^scale
+        let mut n = Big::default();
+        let (mut end, integer, dropped, mut is_sticky) = digits(s, i, radix, &mut n);
+        let mut scale = dropped as i64;
+        let mut fraction = 0;
+        if s.get(end) == Some(&b'.') {
+            let (after, count, dropped, sticky) = digits(s, end + 1, radix, &mut n);
+            (end, fraction) = (after, count);
+            scale -= (count - dropped) as i64;
+            is_sticky |= sticky;
+        }
+        if integer + fraction == 0 {
+            return Parsed {
+                value: LongDouble::ZERO,
+                len: 0,
+                is_range_error: false,
+            };
+        }
+        if is_sticky {
+            n.mul_add(radix, 1);
+            scale -= 1;
+        }
+        let (end, exp) = exponent(s, end, marker).unwrap_or((end, 0));
+        let (pow2, pow10) = match is_hex {
+            true => (scale * digit_bits + exp, 0),
+            false => (0, scale + exp),
+        };
+        let (value, is_range_error) = LongDouble::from_parts(is_negative, n, pow2, pow10);
+        Parsed {
+            value,
+            len: end,
+            is_range_error,
+        }
+    }
+
+    /// The long double nearest to `n × 2^pow2 × 10^pow10`, ties to even,
+    /// and whether that is out of range
+    pub fn from_parts(is_negative: bool, mut n: Big, pow2: i64, pow10: i64) -> (Self, bool) {
+        let finite = |significand, exponent| LongDouble {
+            is_negative,
+            kind: Kind::Finite {
+                significand,
+                exponent,
+            },
+        };
+        let infinite = LongDouble {
+            is_negative,
+            kind: Kind::Infinite,
+        };
+        if n.is_zero() {
+            return (finite(0, 0), false);
+        }
+        // Far out of range, where the powers would only take long to
+        // compute, by the binary logarithm give or take a few
+        let log2 = n.bits() as i64 + pow2 + pow10 * 3322 / 1000;
+        if log2 > MAX_EXPONENT as i64 + 64 + 8 {
+            return (infinite, true);
+        }
+        if log2 < MIN_EXPONENT as i64 - 8 {
+            return (finite(0, 0), true);
+        }
+        // The value is n / den
+        let mut den = Big::from_u64(1);
+        match pow10 >= 0 {
+            true => n.mul_pow(10, pow10 as u64),
+            false => den.mul_pow(10, pow10.unsigned_abs()),
+        }
+        match pow2 >= 0 {
+            true => n.shl(pow2 as u64),
+            false => den.shl(pow2.unsigned_abs()),
+        }
+        // The greatest power of 2 not above the value is 2^top
+        let mut top = n.bits() as i64 - den.bits() as i64;
+        let is_below = match top >= 0 {
+            true => {
+                let mut shifted = den.clone();
+                shifted.shl(top as u64);
+                n < shifted
+            }
+            false => {
+                let mut shifted = n.clone();
+                shifted.shl(top.unsigned_abs());
+                shifted < den
+            }
+        };
+        top -= is_below as i64;
+        let mut exponent = (top - 63).max(MIN_EXPONENT as i64);
+        match exponent >= 0 {
+            true => den.shl(exponent as u64),
+            false => n.shl(exponent.unsigned_abs()),
+        }
+        let mut significand = n.div_rem(&den);
+        let is_exact = n.is_zero();
+        n.shl(1);
+        if n > den || (n == den && significand & 1 == 1) {
+            significand += 1;
+        }
+        if significand >> 64 != 0 {
+            significand >>= 1;
+            exponent += 1;
+        }
+        if exponent > MAX_EXPONENT as i64 {
+            return (infinite, true);
+        }
+        let is_tiny = significand >> 63 == 0;
+        let exponent = if significand == 0 { 0 } else { exponent as i32 };
+        (finite(significand as u64, exponent), is_tiny && !is_exact)
+    }
+
+    /// The value printed by the conversion `conversion`, one of `aAeEfFgG`,
+    /// with `precision` digits after the point, or significant ones for
+    /// `g`, like printf(3) does but without the sign. `is_alternate` is
+    /// the `#` flag.
+    pub fn format(&self, conversion: u8, precision: Option<usize>, is_alternate: bool) -> String {
+        let formatted = match self.kind {
+            Kind::Infinite => "inf".to_string(),
+            Kind::Nan => "nan".to_string(),
+            Kind::Finite {
+                significand,
+                exponent,
+            } => match conversion.to_ascii_lowercase() {
+                b'a' => hex(significand, exponent, precision, is_alternate),
+                b'e' => {
+                    let precision = precision.unwrap_or(6);
+                    Decimal::new(significand, exponent).scientific(precision, is_alternate)
+                }
+                b'f' => {
+                    let precision = precision.unwrap_or(6);
+                    let mut decimal = Decimal::new(significand, exponent);
+                    decimal.round(-(precision as i64));
+                    decimal.fixed(precision, is_alternate)
+                }
+                _ => Decimal::new(significand, exponent).general(precision, is_alternate),
+            },
+        };
+        match conversion.is_ascii_uppercase() {
+            true => formatted.to_ascii_uppercase(),
+            false => formatted,
+        }
+    }
+}
+
+/// `%a` of `significand × 2^exponent`: the top 4 bits of the significand
+/// as the digit before the point and the other 60 after it, like glibc
+/// prints long doubles
+fn hex(significand: u64, exponent: i32, precision: Option<usize>, is_alternate: bool) -> String {
+    const FRACTION_BITS: u32 = 60;
+    let (mut lead, mut fraction) = (
+        significand >> FRACTION_BITS,
+        significand & ((1 << FRACTION_BITS) - 1),
+    );
+    let mut exponent = if significand == 0 {
+        0
+    } else {
+        exponent + FRACTION_BITS as i32
+    };
+    let mut digits = format!("{fraction:015x}");
+    match precision {
+        None => digits.truncate(digits.trim_end_matches('0').len()),
+        Some(precision) if precision < 15 => {
+            let dropped = FRACTION_BITS - 4 * precision as u32;
+            let rest = fraction & ((1 << dropped) - 1);
+            let half = 1 << (dropped - 1);
+            fraction >>= dropped;
+            let last = if precision == 0 { lead } else { fraction };
+            if rest > half || (rest == half && last & 1 == 1) {
+                fraction += 1;
+                if fraction >> (4 * precision) != 0 {
+                    fraction = 0;
+                    lead += 1;
+                    // Past f, the digit before the point is 1 again
+                    if lead == 16 {
+                        lead = 1;
+                        exponent += 4;
+                    }
+                }
+            }
+            digits = match precision {
+                0 => String::new(),
+                _ => format!("{fraction:0precision$x}"),
+            };
+        }
+        Some(precision) => digits.extend(std::iter::repeat_n('0', precision - 15)),
+    }
+    let point = if digits.is_empty() && !is_alternate {
+        ""
+    } else {
+        "."
+    };
+    format!("0x{lead:x}{point}{digits}p{exponent:+}")
+}
+
+/// A finite decimal number `digits × 10^exp`, its digits as values from 0
+/// to 9 without leading zeros, none for zero
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Decimal {
+    digits: Vec<u8>,
+    exp: i64,
+}
+
+impl Decimal {
+    /// The exact value of `significand × 2^exponent`, which 2^-n is
+    /// 5^n × 10^-n of
+    fn new(significand: u64, exponent: i32) -> Self {
+        let mut n = Big::from_u64(significand);
+        let exp = match exponent >= 0 {
+            true => {
+                n.shl(exponent as u64);
+                0
+            }
+            false => {
+                n.mul_pow(5, exponent.unsigned_abs() as u64);
+                exponent as i64
+            }
+        };
+        Decimal {
+            digits: n.digits(),
+            exp,
+        }
+    }
+
+    /// Exponent of the place of the leading digit
+    fn magnitude(&self) -> i64 {
+        self.exp + self.digits.len() as i64 - 1
+    }
+
+    /// Round to a multiple of `10^place`, ties to even
+    fn round(&mut self, place: i64) {
+        if self.exp >= place {
+            return;
+        }
+        let dropped = (place - self.exp) as usize;
+        self.exp = place;
+        if dropped > self.digits.len() {
+            self.digits.clear();
+            return;
+        }
+        let kept = self.digits.len() - dropped;
+        let first = self.digits[kept];
+        let is_tie = first == 5 && self.digits[kept + 1..].iter().all(|&d| d == 0);
+        let is_odd = kept > 0 && self.digits[kept - 1] % 2 == 1;
+        self.digits.truncate(kept);
+        if first > 5 || (first == 5 && (!is_tie || is_odd)) {
+            match self.digits.iter().rposition(|&d| d != 9) {
+                Some(i) => {
+                    self.digits[i] += 1;
+                    self.digits[i + 1..].fill(0);
+                }
+                None => {
+                    self.digits.fill(0);
+                    self.digits.insert(0, 1);
+                }
+            }
+        }
+        let zeros = self.digits.iter().take_while(|&&d| d == 0).count();
+        self.digits.drain(..zeros);
+    }
+
+    /// `%f` with `precision` digits after the point, once rounded to them
+    fn fixed(&self, precision: usize, is_alternate: bool) -> String {
+        let mut digits: String = self.digits.iter().map(|d| (d + b'0') as char).collect();
+        if !digits.is_empty() {
+            let zeros = self.exp + precision as i64;
+            digits.extend(std::iter::repeat_n('0', zeros.max(0) as usize));
+        }
+        if digits.len() <= precision {
+            digits.insert_str(0, &"0".repeat(precision + 1 - digits.len()));
+        }
+        let (integer, fraction) = digits.split_at(digits.len() - precision);
+        let point = if precision > 0 || is_alternate {
+            "."
+        } else {
+            ""
+        };
+        format!("{integer}{point}{fraction}")
+    }
+
+    /// `%e` with `precision` digits after the point
+    fn scientific(mut self, precision: usize, is_alternate: bool) -> String {
+        let magnitude = match self.digits.is_empty() {
+            true => 0,
+            false => {
+                self.round(self.magnitude() - precision as i64);
+                self.magnitude()
+            }
+        };
+        let mut digits: String = self.digits.iter().map(|d| (d + b'0') as char).collect();
+        digits.truncate(precision + 1);
+        let len = digits.len().max(1);
+        digits.extend(std::iter::repeat_n('0', precision + 1 - len));
+        if self.digits.is_empty() {
+            digits.insert(0, '0');
+        }
+        let (lead, fraction) = digits.split_at(1);
+        let point = if precision > 0 || is_alternate {
+            "."
+        } else {
+            ""
+        };
+        let sign = if magnitude < 0 { '-' } else { '+' };
+        format!("{lead}{point}{fraction}e{sign}{:02}", magnitude.abs())
+    }
+
+    /// `%g` with `precision` significant digits, in the style of `%f` for
+    /// exponents from -4 to below the precision and of `%e` else, without
+    /// trailing zeros unless `is_alternate`
+    fn general(mut self, precision: Option<usize>, is_alternate: bool) -> String {
+        let precision = precision.unwrap_or(6).max(1);
+        let (unrounded, magnitude) = match self.digits.is_empty() {
+            true => (0, 0),
+            false => {
+                let unrounded = self.magnitude();
+                self.round(unrounded + 1 - precision as i64);
+                (unrounded, self.magnitude())
+            }
+        };
+        let mut formatted = if (-4..precision as i64).contains(&magnitude) {
+            // Rounding up to a power of 10 leaves a zero digit too many
+            let places = (precision as i64 - 1 - magnitude) as usize;
+            self.round(-(places as i64));
+            self.fixed(places, is_alternate)
+        } else if unrounded == precision as i64 - 1 {
+            // Like glibc, with the digits after the point of the `%f` style
+            // it rounded up from
+            self.scientific(0, is_alternate)
+        } else {
+            self.scientific(precision - 1, is_alternate)
+        };
+        if !is_alternate {
+            let end = formatted.find('e').unwrap_or(formatted.len());
+            if formatted[..end].contains('.') {
+                let kept = formatted[..end]
+                    .trim_end_matches('0')
+                    .trim_end_matches('.')
+                    .len();
+                formatted.replace_range(kept..end, "");
+            }
+        }
+        formatted
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn formatted(
+        conversion: u8,
+        precision: Option<usize>,
+        is_alternate: bool,
+        arg: &str,
+    ) -> String {
+        let parsed = LongDouble::parse(arg.as_bytes());
+        assert_eq!(parsed.len, arg.len());
+        let sign = if parsed.value.is_negative { "-" } else { "" };
+        sign.to_string() + &parsed.value.format(conversion, precision, is_alternate)
+    }
+
+    #[test]
+    fn parse() {
+        let parsed = LongDouble::parse(b" -0x1.8p3x");
+        assert_eq!(parsed.value, LongDouble::from_u64(12, true));
+        assert_eq!((parsed.len, parsed.is_range_error), (9, false));
+        assert_eq!(LongDouble::parse(b"infinit").len, 3);
+        assert_eq!(LongDouble::parse(b"nan(a_1)").len, 8);
+        assert_eq!(LongDouble::parse(b"nan(").len, 3);
+        assert_eq!(LongDouble::parse(b"0x").len, 1);
+        assert_eq!(LongDouble::parse(b"1e+").len, 1);
+        assert_eq!(LongDouble::parse(b".").len, 0);
+        assert!(LongDouble::parse(b"1e5000").is_range_error);
+        assert!(LongDouble::parse(b"1e-4950").is_range_error);
+        assert!(!LongDouble::parse(b"0x1p-16440").is_range_error);
+    }
+
+    #[test]
+    fn decimal() {
+        assert_eq!(
+            formatted(b'f', Some(20), false, "0.1"),
+            "0.10000000000000000000"
+        );
+        assert_eq!(
+            formatted(b'e', Some(30), false, "0.1"),
+            "1.000000000000000000013552527156e-01"
+        );
+        assert_eq!(formatted(b'f', Some(0), false, "0.5"), "0");
+        assert_eq!(formatted(b'f', Some(0), false, "1.5"), "2");
+        assert_eq!(formatted(b'f', Some(0), false, "2.5"), "2");
+        assert_eq!(formatted(b'f', Some(1), false, "0.05"), "0.1");
+        assert_eq!(formatted(b'f', Some(3), false, "2.0005"), "2.001");
+        assert_eq!(formatted(b'f', Some(0), true, "1"), "1.");
+        assert_eq!(formatted(b'e', Some(0), false, "12345"), "1e+04");
+        assert_eq!(formatted(b'f', None, false, "-0"), "-0.000000");
+        assert_eq!(formatted(b'e', None, false, "-0"), "-0.000000e+00");
+        assert_eq!(formatted(b'F', None, false, "-inf"), "-INF");
+    }
+
+    #[test]
+    fn general() {
+        assert_eq!(formatted(b'g', None, false, "100000"), "100000");
+        assert_eq!(formatted(b'g', None, false, "1000000"), "1e+06");
+        assert_eq!(formatted(b'g', None, false, "0.0001"), "0.0001");
+        assert_eq!(formatted(b'g', None, false, "9.9999995"), "10");
+        assert_eq!(formatted(b'g', None, true, "1"), "1.00000");
+        assert_eq!(formatted(b'g', None, true, "999999.5"), "1.e+06");
+        assert_eq!(formatted(b'G', None, false, "1e-10"), "1E-10");
+        assert_eq!(formatted(b'g', None, false, "-0"), "-0");
+    }
+
+    #[test]
+    fn hexadecimal() {
+        assert_eq!(formatted(b'a', None, false, "1"), "0x8p-3");
+        assert_eq!(
+            formatted(b'a', None, false, "0.1"),
+            "0xc.ccccccccccccccdp-7"
+        );
+        assert_eq!(formatted(b'a', None, false, "3"), "0xcp-2");
+        assert_eq!(formatted(b'A', None, false, "-255.5"), "-0XF.F8P+4");
+        assert_eq!(formatted(b'a', Some(2), false, "1"), "0x8.00p-3");
+        assert_eq!(formatted(b'a', None, false, "0"), "0x0p+0");
+        assert_eq!(formatted(b'a', None, false, "-0"), "-0x0p+0");
+        assert_eq!(
+            formatted(b'a', None, false, "1e-4950"),
+            "0x0.000000000000003p-16385"
+        );
+        assert_eq!(
+            formatted(b'a', None, false, "0x1p-16440"),
+            "0x0.00000000000002p-16385"
+        );
+        assert_eq!(formatted(b'a', Some(0), false, "15.9"), "0x1p+4");
+        assert_eq!(formatted(b'a', Some(0), false, "1.5"), "0xcp-3");
+        assert_eq!(formatted(b'a', Some(1), false, "1.96875"), "0xf.cp-3");
+    }
+}
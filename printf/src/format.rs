@@ -0,0 +1,306 @@
+//! Directives of formats: the flags, field width, precision and
+//! conversion of a `%` directive as written, and how a directive formats
+//! its value like printf(3) does
+
+use crate::float::{Kind, LongDouble};
+
+/// Conversions a directive may end with, besides `%b` and `%q` directly
+/// after the `%`
+const CONVERSIONS: &[u8] = b"diouxXfFeEgGaAcs";
+
+/// The flags of a directive
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Flags {
+    /// `-`: pad on the right
+    pub is_left: bool,
+    /// `+`: a plus sign before signed numbers that aren't negative
+    pub is_plus: bool,
+    /// ` `: a space before signed numbers that aren't negative, unless `+`
+    pub is_space: bool,
+    /// `#`: the alternate form
+    pub is_alternate: bool,
+    /// `0`: pad numbers with zeros after their sign
+    pub is_zero: bool,
+    /// `'` or `I`: grouping of thousands and locale digits, which the
+    /// locales supported have none of
+    pub is_grouping: bool,
+}
+
+/// A field width or precision as written
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Field {
+    Digits(usize),
+    /// `*`, taken from the next argument
+    Star,
+}
+
+/// A directive as written, after its `%`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Directive {
+    pub flags: Flags,
+    pub width: Option<Field>,
+    pub precision: Option<Field>,
+    /// The conversion character, `None` at the end of the format
+    pub conversion: Option<u8>,
+    /// Bytes of the directive up to and including the conversion
+    pub len: usize,
+}
+
+impl Directive {
+    /// Parse the directive at the start of `s`, after its `%`. Length
+    /// modifiers are skipped, all arguments being of the widest types.
+    pub fn parse(s: &[u8]) -> Self {
+        let mut flags = Flags::default();
+        let mut i = 0;
+        while let Some(&b) = s.get(i) {
+            match b {
+                b'-' => flags.is_left = true,
+                b'+' => flags.is_plus = true,
+                b' ' => flags.is_space = true,
+                b'#' => flags.is_alternate = true,
+                b'0' => flags.is_zero = true,
+                b'\'' | b'I' => flags.is_grouping = true,
+                _ => break,
+            }
+            i += 1;
+        }
+        let field = |i: &mut usize| match s.get(*i) {
+            Some(b'*') => {
+                *i += 1;
+                Some(Field::Star)
+            }
+            Some(b) if b.is_ascii_digit() => {
+                let mut value = 0usize;
+                while let Some(b) = s.get(*i).filter(|b| b.is_ascii_digit()) {
+                    value = value.saturating_mul(10).saturating_add((b - b'0') as usize);
+                    *i += 1;
+                }
+                Some(Field::Digits(value))
+            }
+            _ => None,
+        };
+        let width = field(&mut i);
+        let precision = match s.get(i) {
+            Some(b'.') => {
+                i += 1;
+                Some(field(&mut i).unwrap_or(Field::Digits(0)))
+            }
+            _ => None,
+        };
+        i += s[i..].iter().take_while(|b| b"hlLjzt".contains(b)).count();
+        let conversion = s.get(i).copied();
+        Directive {
+            flags,
+            width,
+            precision,
+            conversion,
+            len: (i + 1).min(s.len()),
+        }
+    }
+
+    /// Whether the conversion is one and goes with the flags and the
+    /// precision, like GNU `printf` checks
+    pub fn is_valid(&self) -> bool {
+        let Some(c) = self.conversion.filter(|c| CONVERSIONS.contains(c)) else {
+            return false;
+        };
+        let flags = self.flags;
+        !(flags.is_grouping && b"aAceEosxX".contains(&c)
+            || flags.is_alternate && b"cdisu".contains(&c)
+            || flags.is_zero && b"cs".contains(&c)
+            || self.precision.is_some() && c == b'c')
+    }
+}
+
+/// A directive with its fields known, ready to format a value
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Spec {
+    pub flags: Flags,
+    pub width: usize,
+    pub precision: Option<usize>,
+    pub conversion: u8,
+}
+
+impl Spec {
+    /// The sign of a number, or what stands for it
+    fn sign(&self, is_negative: bool) -> &'static [u8] {
+        match (is_negative, self.flags) {
+            (true, _) => b"-",
+            (false, Flags { is_plus: true, .. }) => b"+",
+            (false, Flags { is_space: true, .. }) => b" ",
+            _ => b"",
+        }
+    }
+
+    /// `prefix` and `body` padded to the field width, with zeros between
+    /// them if the `0` flag says so and `is_zero_padded`
+    fn pad(&self, prefix: &[u8], body: &[u8], is_zero_padded: bool) -> Vec<u8> {
+        let fill = self.width.saturating_sub(prefix.len() + body.len());
+        let mut padded = Vec::with_capacity(prefix.len() + body.len() + fill);
+        if self.flags.is_left {
+            padded.extend_from_slice(prefix);
+            padded.extend_from_slice(body);
+            padded.resize(padded.len() + fill, b' ');
+        } else if self.flags.is_zero && is_zero_padded {
+            padded.extend_from_slice(prefix);
+            padded.resize(padded.len() + fill, b'0');
+            padded.extend_from_slice(body);
+        } else {
+            padded.resize(fill, b' ');
+            padded.extend_from_slice(prefix);
+            padded.extend_from_slice(body);
+        }
+        padded
+    }
+
+    /// An integer of the magnitude `magnitude`, in the radix of the
+    /// conversion, with at least as many digits as the precision
+    fn integer(&self, is_negative: bool, magnitude: u64) -> Vec<u8> {
+        let mut digits = match self.conversion {
+            b'o' => format!("{magnitude:o}"),
+            b'x' => format!("{magnitude:x}"),
+            b'X' => format!("{magnitude:X}"),
+            _ => magnitude.to_string(),
+        };
+        if let Some(precision) = self.precision {
+            if magnitude == 0 && precision == 0 {
+                digits.clear();
+            }
+            if digits.len() < precision {
+                digits.insert_str(0, &"0".repeat(precision - digits.len()));
+            }
+        }
+        let mut prefix = Vec::new();
+        match self.conversion {
+            b'd' | b'i' => prefix.extend_from_slice(self.sign(is_negative)),
+            b'o' if self.flags.is_alternate && !digits.starts_with('0') => digits.insert(0, '0'),
+            b'x' | b'X' if self.flags.is_alternate && magnitude != 0 => {
+                prefix.extend_from_slice(&[b'0', self.conversion])
+            }
+            _ => (),
+        }
+        // A precision takes the place of zero padding
+        self.pad(&prefix, digits.as_bytes(), self.precision.is_none())
+    }
+
+    /// `n` by `%d` or `%i`
+    pub fn signed(&self, n: i64) -> Vec<u8> {
+        self.integer(n < 0, n.unsigned_abs())
+    }
+
+    /// `n` by `%o`, `%u`, `%x` or `%X`
+    pub fn unsigned(&self, n: u64) -> Vec<u8> {
+        self.integer(false, n)
+    }
+
+    /// `x` by one of the floating-point conversions. Infinities and NaNs
+    /// are padded with spaces.
+    pub fn float(&self, x: &LongDouble) -> Vec<u8> {
+        let body = x.format(self.conversion, self.precision, self.flags.is_alternate);
+        let mut prefix = self.sign(x.is_negative).to_vec();
+        let body = match body.strip_prefix("0x").or_else(|| body.strip_prefix("0X")) {
+            Some(rest) => {
+                prefix.extend_from_slice(&body.as_bytes()[..2]);
+                rest
+            }
+            None => &body,
+        };
+        let is_finite = matches!(x.kind, Kind::Finite { .. });
+        self.pad(&prefix, body.as_bytes(), is_finite)
+    }
+
+    /// `s` by `%s`, cut to the precision, or by `%c`
+    pub fn string(&self, s: &[u8]) -> Vec<u8> {
+        let len = self
+            .precision
+            .map_or(s.len(), |precision| precision.min(s.len()));
+        self.pad(b"", &s[..len], false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spec(directive: &str) -> Spec {
+        let directive = Directive::parse(directive.as_bytes());
+        assert!(directive.is_valid());
+        let digits = |field| match field {
+            Some(Field::Digits(n)) => Some(n),
+            _ => None,
+        };
+        Spec {
+            flags: directive.flags,
+            width: digits(directive.width).unwrap_or(0),
+            precision: digits(directive.precision),
+            conversion: directive.conversion.unwrap(),
+        }
+    }
+
+    fn string(s: Vec<u8>) -> String {
+        String::from_utf8(s).unwrap()
+    }
+
+    #[test]
+    fn parse() {
+        let directive = Directive::parse(b"-0*.5ldx");
+        assert!(directive.flags.is_left && directive.flags.is_zero);
+        assert_eq!(directive.width, Some(Field::Star));
+        assert_eq!(directive.precision, Some(Field::Digits(5)));
+        assert_eq!((directive.conversion, directive.len), (Some(b'd'), 7));
+        assert_eq!(Directive::parse(b".f").precision, Some(Field::Digits(0)));
+        assert_eq!(Directive::parse(b"5").conversion, None);
+        assert_eq!(Directive::parse(b"5").len, 1);
+        assert!(!Directive::parse(b"z").is_valid());
+        assert!(!Directive::parse(b"#d").is_valid());
+        assert!(!Directive::parse(b"05s").is_valid());
+        assert!(!Directive::parse(b".1c").is_valid());
+        assert!(!Directive::parse(b"'x").is_valid());
+        assert!(Directive::parse(b"'d").is_valid());
+    }
+
+    #[test]
+    fn integers() {
+        assert_eq!(string(spec("5d").signed(-42)), "  -42");
+        assert_eq!(string(spec("-5d").signed(42)), "42   ");
+        assert_eq!(string(spec("05d").signed(-42)), "-0042");
+        assert_eq!(string(spec("+.3d").signed(7)), "+007");
+        assert_eq!(string(spec(" d").signed(7)), " 7");
+        assert_eq!(string(spec("08.3d").signed(7)), "     007");
+        assert_eq!(string(spec(".0d").signed(0)), "");
+        assert_eq!(string(spec("#o").unsigned(8)), "010");
+        assert_eq!(string(spec("#.3o").unsigned(8)), "010");
+        assert_eq!(string(spec("#x").unsigned(255)), "0xff");
+        assert_eq!(string(spec("#08X").unsigned(255)), "0X0000FF");
+        assert_eq!(string(spec("#x").unsigned(0)), "0");
+        assert_eq!(
+            string(spec("+u").unsigned(u64::MAX)),
+            "18446744073709551615"
+        );
+    }
+
+    #[test]
+    fn floats() {
+        let x = LongDouble::from_u64(3, true);
+        assert_eq!(string(spec("010.2f").float(&x)), "-000003.00");
+        assert_eq!(string(spec("+e").float(&LongDouble::ZERO)), "+0.000000e+00");
+        assert_eq!(string(spec("010a").float(&x)), "-0x000cp-2");
+        let nan = LongDouble {
+            is_negative: true,
+            kind: Kind::Nan,
+        };
+        assert_eq!(string(spec("06f").float(&nan)), "  -nan");
+        let inf = LongDouble {
+            is_negative: false,
+            kind: Kind::Infinite,
+        };
+        assert_eq!(string(spec("-+5F").float(&inf)), "+INF ");
+    }
+
+    #[test]
+    fn strings() {
+        assert_eq!(string(spec("5s").string(b"ab")), "   ab");
+        assert_eq!(string(spec("-5.1s").string(b"ab")), "a    ");
+        assert_eq!(string(spec("3c").string(b"a")), "  a");
+    }
+}
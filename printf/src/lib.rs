@@ -0,0 +1,312 @@
+//! Formatted printing with the semantics of `printf` of coreutils: the
+//! format is applied to the arguments again and again while it takes
+//! some and any are left, arguments of numeric directives are read like C
+//! reads numbers, and floating-point ones are printed as the long doubles
+//! they round to.
+//!
+//! ```
+//! use nyaa_core::locale::Locale;
+//! use nyaa_printf::{End, Options};
+//!
+//! let options = Options { locale: Locale::C, is_posix: false };
+//! let args: [&[u8]; 6] = [b"a", b"0x10", b"1.5", b"b", b"-7", b"25"];
+//! let mut out = Vec::new();
+//! let end = nyaa_printf::printf(b"%s=%03d %.2e\\n", &args, &options, &mut out, &mut |_, _| ())?;
+//! assert_eq!(out, b"a=016 1.50e+00\nb=-07 2.50e+01\n");
+//! assert_eq!(end, End::Done(6));
+//! # Ok::<(), nyaa_printf::Error>(())
+//! ```
+
+mod arg;
+pub mod big;
+pub mod cli;
+mod escape;
+pub mod float;
+pub mod format;
+mod quote;
+
+pub use arg::Invalid;
+
+use format::{Directive, Field, Spec};
+use nyaa_core::locale::Locale;
+use std::io::{self, Write};
+
+/// How the arguments are read and printed
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Options {
+    /// The locale of characters, for character constants, `\u` escapes
+    /// and `%q`
+    pub locale: Locale,
+    /// Whether `POSIXLY_CORRECT` is set, which silences the warning about
+    /// characters after character constants
+    pub is_posix: bool,
+}
+
+/// An error ending the output
+#[derive(Debug)]
+pub enum Error {
+    /// A directive, from its `%`, that isn't a valid conversion
+    /// specification
+    Conversion(Vec<u8>),
+    /// `\x` without a hexadecimal digit, or `\u` or `\U` without all of
+    /// theirs
+    MissingHex,
+    /// `\u` or `\U` of a code that isn't a universal character name
+    UniversalName(u8, u32),
+    /// The argument of a `*` field width out of the range of an int
+    Width(Vec<u8>),
+    /// The argument of a `*` precision out of the range of an int
+    Precision(Vec<u8>),
+    Write(io::Error),
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Error::Write(err)
+    }
+}
+
+/// How printing ended
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum End {
+    /// At the end of the format once no argument was left, or when it
+    /// took none, with the number of arguments used
+    Done(usize),
+    /// At a `\c`, which stops the output right away
+    Stopped,
+}
+
+/// The arguments of the format, taken one after the other
+struct Args<'a, A> {
+    args: &'a [A],
+    used: usize,
+}
+
+impl<A: AsRef<[u8]>> Args<'_, A> {
+    /// The next argument, if any is left
+    fn next(&mut self) -> Option<&[u8]> {
+        let arg = self.args.get(self.used)?;
+        self.used += 1;
+        Some(arg.as_ref())
+    }
+
+    /// The next argument, empty if none is left like missing arguments
+    /// of directives are
+    fn next_or_empty(&mut self) -> &[u8] {
+        self.next().unwrap_or_default()
+    }
+}
+
+/// A `*` field of a directive from the next argument, 0 if none is left,
+/// or that argument as the error if it is out of the range of an int
+fn star(
+    args: &mut Args<impl AsRef<[u8]>>,
+    options: &Options,
+    warn: &mut dyn FnMut(&[u8], Invalid),
+) -> Result<i64, Vec<u8>> {
+    let Some(arg) = args.next() else {
+        return Ok(0);
+    };
+    let (value, invalid) = arg::signed(arg, options.locale, options.is_posix);
+    if let Some(invalid) = invalid {
+        warn(arg, invalid);
+    }
+    match i32::try_from(value) {
+        Ok(_) => Ok(value),
+        Err(_) => Err(arg.to_vec()),
+    }
+}
+
+/// Print the directive at the start of `s`, after its `%`, with the
+/// arguments it takes. Returns its length, or `None` at a `\c`.
+fn directive(
+    s: &[u8],
+    args: &mut Args<impl AsRef<[u8]>>,
+    options: &Options,
+    out: &mut impl Write,
+    warn: &mut dyn FnMut(&[u8], Invalid),
+) -> Result<Option<usize>, Error> {
+    match s.first() {
+        Some(b'%') => {
+            out.write_all(b"%")?;
+            return Ok(Some(1));
+        }
+        Some(b'b') => {
+            let is_done = escape::write_escaped(args.next_or_empty(), options.locale, out)?;
+            return Ok(is_done.then_some(1));
+        }
+        Some(b'q') => {
+            out.write_all(&quote::quote(args.next_or_empty(), options.locale))?;
+            return Ok(Some(1));
+        }
+        _ => (),
+    }
+    let directive = Directive::parse(s);
+    let mut flags = directive.flags;
+    let width = match directive.width {
+        Some(Field::Star) => {
+            let width = star(args, options, warn).map_err(Error::Width)?;
+            flags.is_left |= width < 0;
+            width.unsigned_abs() as usize
+        }
+        Some(Field::Digits(width)) => width,
+        None => 0,
+    };
+    let precision = match directive.precision {
+        Some(Field::Star) => {
+            let precision = star(args, options, warn).map_err(Error::Precision)?;
+            // A negative precision is taken as omitted
+            usize::try_from(precision).ok()
+        }
+        Some(Field::Digits(precision)) => Some(precision),
+        None => None,
+    };
+    let conversion = match directive.conversion {
+        Some(conversion) if directive.is_valid() => conversion,
+        _ => {
+            let mut spec = vec![b'%'];
+            spec.extend_from_slice(&s[..directive.len]);
+            return Err(Error::Conversion(spec));
+        }
+    };
+    let spec = Spec {
+        flags,
+        width,
+        precision,
+        conversion,
+    };
+    let arg = args.next_or_empty();
+    let (formatted, invalid) = match conversion {
+        b'd' | b'i' => {
+            let (n, invalid) = arg::signed(arg, options.locale, options.is_posix);
+            (spec.signed(n), invalid)
+        }
+        b'o' | b'u' | b'x' | b'X' => {
+            let (n, invalid) = arg::unsigned(arg, options.locale, options.is_posix);
+            (spec.unsigned(n), invalid)
+        }
+        b'c' => (spec.string(&[arg.first().copied().unwrap_or(0)]), None),
+        b's' => (spec.string(arg), None),
+        _ => {
+            let (x, invalid) = arg::float(arg, options.locale, options.is_posix);
+            (spec.float(&x), invalid)
+        }
+    };
+    if let Some(invalid) = invalid {
+        warn(arg, invalid);
+    }
+    out.write_all(&formatted)?;
+    Ok(Some(directive.len))
+}
+
+/// Print `format` once, taking arguments from `args` as its directives
+/// need them. Returns `false` at a `\c`.
+fn print_once(
+    format: &[u8],
+    args: &mut Args<impl AsRef<[u8]>>,
+    options: &Options,
+    out: &mut impl Write,
+    warn: &mut dyn FnMut(&[u8], Invalid),
+) -> Result<bool, Error> {
+    let mut rest = format;
+    while let Some(i) = memchr::memchr2(b'%', b'\\', rest) {
+        out.write_all(&rest[..i])?;
+        let len = match rest[i] {
+            b'%' => directive(&rest[i + 1..], args, options, out, warn)?,
+            _ => escape::write_escape(&rest[i + 1..], false, options.locale, out)?,
+        };
+        match len {
+            Some(len) => rest = &rest[i + 1 + len..],
+            None => return Ok(false),
+        }
+    }
+    out.write_all(rest)?;
+    Ok(true)
+}
+
+/// Print `args` by `format`, calling `warn` with each argument of a
+/// numeric directive that is invalid, which is still printed converted as
+/// far as it goes
+pub fn printf<A: AsRef<[u8]>>(
+    format: &[u8],
+    args: &[A],
+    options: &Options,
+    out: &mut impl Write,
+    warn: &mut dyn FnMut(&[u8], Invalid),
+) -> Result<End, Error> {
+    let mut args = Args { args, used: 0 };
+    loop {
+        let used = args.used;
+        if !print_once(format, &mut args, options, out, warn)? {
+            return Ok(End::Stopped);
+        }
+        if args.used == used || args.used >= args.args.len() {
+            return Ok(End::Done(args.used));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn printed(format: &str, args: &[&str]) -> (String, Result<End, Error>, Vec<Invalid>) {
+        let options = Options {
+            locale: Locale::Unicode,
+            is_posix: false,
+        };
+        let mut out = Vec::new();
+        let mut invalid = Vec::new();
+        let args: Vec<&[u8]> = args.iter().map(|arg| arg.as_bytes()).collect();
+        let end = printf(format.as_bytes(), &args, &options, &mut out, &mut |_, i| {
+            invalid.push(i)
+        });
+        (String::from_utf8(out).unwrap(), end, invalid)
+    }
+
+    #[test]
+    fn reuse() {
+        let (out, end, _) = printed("%s-%s\\n", &["a", "b", "c"]);
+        assert_eq!(out, "a-b\nc-\n");
+        assert!(matches!(end, Ok(End::Done(3))));
+        let (out, end, _) = printed("x\\n", &["a"]);
+        assert_eq!(out, "x\n");
+        assert!(matches!(end, Ok(End::Done(0))));
+        let (out, end, _) = printed("%s%%%b%q|", &["a", "\\t", "b c"]);
+        assert_eq!(out, "a%\t'b c'|");
+        assert!(matches!(end, Ok(End::Done(3))));
+    }
+
+    #[test]
+    fn stars() {
+        let (out, _, _) = printed("[%*d|%-*.*s]", &["-4", "1", "3", "1", "abc"]);
+        assert_eq!(out, "[1   |a  ]");
+        let (out, _, _) = printed("[%.*d]", &["-1", "5"]);
+        assert_eq!(out, "[5]");
+        let (_, end, _) = printed("%*d", &["2147483648", "1"]);
+        assert!(matches!(end, Err(Error::Width(arg)) if arg == b"2147483648"));
+        let (_, end, _) = printed("%.*d", &["-2147483649"]);
+        assert!(matches!(end, Err(Error::Precision(_))));
+    }
+
+    #[test]
+    fn errors() {
+        let (out, end, _) = printed("a%5yb", &[]);
+        assert_eq!(out, "a");
+        assert!(matches!(end, Err(Error::Conversion(spec)) if spec == b"%5y"));
+        let (_, end, _) = printed("%", &[]);
+        assert!(matches!(end, Err(Error::Conversion(spec)) if spec == b"%"));
+        let (out, end, _) = printed("a\\cb%s", &["x"]);
+        assert_eq!(out, "a");
+        assert!(matches!(end, Ok(End::Stopped)));
+        let (out, end, _) = printed("%b%s", &["1\\c2", "x"]);
+        assert_eq!(out, "1");
+        assert!(matches!(end, Ok(End::Stopped)));
+        let (out, _, invalid) = printed("%d %x %f %c %s\\n", &["1a", "z", "1e9999", "", ""]);
+        assert_eq!(out, "1 0 inf \0 \n");
+        assert_eq!(
+            invalid,
+            [Invalid::NotComplete, Invalid::NotNumeric, Invalid::Range]
+        );
+    }
+}
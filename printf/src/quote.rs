@@ -0,0 +1,174 @@
+//! Quoting of the arguments of `%q`, the way GNU `printf` does it: as is
+//! when the shell reads them back unchanged, else in double quotes for
+//! single quotes among characters safe in both, and in single quotes with
+//! control characters in `$'...'` else.
+
+use nyaa_core::locale::Locale;
+
+/// A character of an argument, or a byte of one not printable as one
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Unit {
+    Char(char),
+    Byte(u8),
+}
+
+/// The units of `arg`, characters if they are printable in `locale`
+fn units(arg: &[u8], locale: Locale) -> Vec<Unit> {
+    let mut units = Vec::with_capacity(arg.len());
+    for chunk in arg.utf8_chunks() {
+        for c in chunk.valid().chars() {
+            if c.is_ascii() || (locale == Locale::Unicode && locale.is_print(c)) {
+                units.push(Unit::Char(c));
+            } else {
+                let mut buf = [0; 4];
+                units.extend(c.encode_utf8(&mut buf).bytes().map(Unit::Byte));
+            }
+        }
+        units.extend(chunk.invalid().iter().copied().map(Unit::Byte));
+    }
+    units
+}
+
+/// Whether `c` needs no quoting anywhere
+fn is_plain(c: char) -> bool {
+    c.is_ascii_alphanumeric() || "%+,-./:@]_".contains(c) || !c.is_ascii()
+}
+
+/// Whether the unit `i` of `units` needs quoting
+fn needs_quoting(units: &[Unit], i: usize) -> bool {
+    match units[i] {
+        Unit::Char(c) if is_plain(c) => false,
+        Unit::Char('#' | '~') => i == 0,
+        Unit::Char('{' | '}') => units.len() == 1,
+        _ => true,
+    }
+}
+
+/// Append the escape of `b` in `$'...'`
+fn push_escape(out: &mut String, b: u8) {
+    match b {
+        b'\x07' => out.push_str("\\a"),
+        b'\x08' => out.push_str("\\b"),
+        b'\x0c' => out.push_str("\\f"),
+        b'\n' => out.push_str("\\n"),
+        b'\r' => out.push_str("\\r"),
+        b'\t' => out.push_str("\\t"),
+        b'\x0b' => out.push_str("\\v"),
+        b => out.push_str(&format!("\\{b:03o}")),
+    }
+}
+
+/// `units` in single quotes, starting after the escape of a control
+/// character if `is_escaping`. Returns whether they end with one.
+fn single_quoted(units: &[Unit], mut is_escaping: bool, out: &mut String) -> bool {
+    out.push('\'');
+    for &unit in units {
+        match unit {
+            Unit::Char('\'') => {
+                out.push_str("'\\''");
+                is_escaping = false;
+            }
+            Unit::Char(c) if !c.is_ascii_control() => {
+                if is_escaping {
+                    out.push_str("''");
+                    is_escaping = false;
+                }
+                out.push(c);
+            }
+            Unit::Char(_) | Unit::Byte(_) => {
+                if !is_escaping {
+                    out.push_str("'$'");
+                    is_escaping = true;
+                }
+                let b = match unit {
+                    Unit::Char(c) => c as u8,
+                    Unit::Byte(b) => b,
+                };
+                push_escape(out, b);
+            }
+        }
+    }
+    out.push('\'');
+    is_escaping
+}
+
+/// `arg` quoted for the shell like `%q` does in `locale`
+pub fn quote(arg: &[u8], locale: Locale) -> Vec<u8> {
+    if arg.is_empty() {
+        return b"''".to_vec();
+    }
+    let units = units(arg, locale);
+    if !(0..units.len()).any(|i| needs_quoting(&units, i)) {
+        return arg.to_vec();
+    }
+    let mut out = String::new();
+    let is_escaping = single_quoted(&units, false, &mut out);
+    if !units.contains(&Unit::Char('\'')) {
+        return out.into_bytes();
+    }
+    // Single quotes among characters safe in double quotes are quoted
+    // with those
+    let is_double_safe = |unit: &Unit| match *unit {
+        Unit::Char(c) => is_plain(c) || c == ' ' || c == '\'',
+        Unit::Byte(_) => false,
+    };
+    if units.iter().all(is_double_safe) {
+        let mut quoted = vec![b'"'];
+        quoted.extend_from_slice(arg);
+        quoted.push(b'"');
+        return quoted;
+    }
+    // GNU quotes those again, still in the state the first time ended
+    // in, which shows as an empty `''` before the first character when
+    // that was an escape
+    out.clear();
+    single_quoted(&units, is_escaping, &mut out);
+    out.into_bytes()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn quoted(arg: &str) -> String {
+        String::from_utf8(quote(arg.as_bytes(), Locale::Unicode)).unwrap()
+    }
+
+    #[test]
+    fn plain() {
+        assert_eq!(quoted("abc"), "abc");
+        assert_eq!(quoted("%+,-./:@]_a#a~{a}"), "%+,-./:@]_a#a~{a}");
+        assert_eq!(quoted("{}"), "{}");
+        assert_eq!(quoted("é"), "é");
+        assert_eq!(quoted(""), "''");
+    }
+
+    #[test]
+    fn single_quotes() {
+        assert_eq!(quoted("a b"), "'a b'");
+        assert_eq!(quoted("#a"), "'#a'");
+        assert_eq!(quoted("{"), "'{'");
+        assert_eq!(quoted("a\\b"), "'a\\b'");
+        assert_eq!(quoted("a'b$"), "'a'\\''b$'");
+    }
+
+    #[test]
+    fn double_quotes() {
+        assert_eq!(quoted("a'b c"), "\"a'b c\"");
+        assert_eq!(quoted("'"), "\"'\"");
+        assert_eq!(quoted("é'"), "\"é'\"");
+    }
+
+    #[test]
+    fn escapes() {
+        assert_eq!(quoted("a\tb"), "'a'$'\\t''b'");
+        assert_eq!(quoted("\n"), "''$'\\n'");
+        assert_eq!(quoted("a\x01\x02b"), "'a'$'\\001\\002''b'");
+        assert_eq!(quoted("a\n'b"), "'a'$'\\n'\\''b'");
+        assert_eq!(quoted("a'\n"), "'''a'\\'''$'\\n'");
+        assert_eq!(quoted("\n'\n"), "'\\n'\\'''$'\\n'");
+        assert_eq!(quoted("\u{85}"), "''$'\\302\\205'");
+        assert_eq!(quote("é".as_bytes(), Locale::C), b"''$'\\303\\251'");
+        assert_eq!(quote(b"\xff", Locale::Unicode), b"''$'\\377'");
+    }
+}
@@ -0,0 +1,144 @@
+//! Command line interface of `seq`
+
+use crate::{Format, FormatError, Invalid, Operand};
+use clap::{ArgAction, CommandFactory, FromArgMatches, Parser};
+use nyaa_core::error::{Status, UError, UResult};
+use nyaa_core::i18n;
+use nyaa_core::output::{self, OutputSink};
+use nyaa_core::posix;
+use nyaa_core::quote::shell_escape;
+use nyaa_core::trace;
+use std::ffi::{OsStr, OsString};
+use std::process::ExitCode;
+
+const UTIL: &str = "seq";
+
+#[derive(Parser)]
+#[command(name = "seq", version)]
+#[command(about = "print a sequence of numbers", long_about = None)]
+#[command(disable_help_flag = true, disable_version_flag = true)]
+struct Args {
+    /// Use the printf style floating-point FORMAT
+    #[arg(short = 'f', long, value_name = "FORMAT", allow_hyphen_values = true)]
+    format: Option<OsString>,
+
+    /// Use STRING to separate numbers, a newline by default
+    #[arg(short = 's', long, value_name = "STRING", allow_hyphen_values = true)]
+    separator: Option<OsString>,
+
+    /// Equalize width by padding with leading zeroes
+    #[arg(short = 'w', long = "equal-width")]
+    is_equal_width: bool,
+
+    /// LAST, FIRST LAST, or FIRST INCREMENT LAST, FIRST and INCREMENT
+    /// being 1 by default. Options are only taken before them.
+    #[arg(value_name = "NUMBER")]
+    operands: Vec<OsString>,
+
+    /// Print help
+    #[arg(long, action=ArgAction::Help)]
+    help: (),
+
+    /// Print version
+    #[arg(long, action=ArgAction::Version)]
+    version: (),
+}
+
+pub fn command() -> clap::Command {
+    i18n::command(Args::command())
+}
+
+/// Whether `arg` is a negative number rather than options
+fn is_negative_number(arg: &str) -> bool {
+    let mut chars = arg.chars();
+    chars.next() == Some('-') && chars.next().is_some_and(|c| c == '.' || c.is_ascii_digit())
+}
+
+/// `message` with `{}` replaced by `arg` quoted for the shell
+fn quoted(message: &str, arg: &OsStr) -> String {
+    i18n::tr(message).replace("{}", &shell_escape(arg))
+}
+
+/// The operand `arg`
+fn operand(arg: &OsStr) -> Result<Operand, UError> {
+    Operand::parse(arg.as_encoded_bytes()).map_err(|invalid| {
+        let message = match invalid {
+            Invalid::NotNumber => "invalid floating point argument: {}",
+            Invalid::Nan => "invalid 'not-a-number' argument: {}",
+        };
+        UError::usage(quoted(message, arg))
+    })
+}
+
+/// The format of `-f`
+fn format(format: &OsStr) -> Result<Format, UError> {
+    Format::parse(format.as_encoded_bytes()).map_err(|err| {
+        let message = match err {
+            FormatError::NoDirective => quoted("format {} has no % directive", format),
+            FormatError::EndsInPercent => quoted("format {} ends in %", format),
+            FormatError::Unknown(c) => {
+                let directive = String::from_utf8_lossy(&[b'%', c]).into_owned();
+                i18n::tr("format {} has unknown {} directive")
+                    .replacen("{}", &shell_escape(format), 1)
+                    .replacen("{}", &directive, 1)
+            }
+            FormatError::TooManyDirectives => quoted("format {} has too many % directives", format),
+        };
+        UError::failure(message)
+    })
+}
+
+/// FIRST, INCREMENT and LAST of one to three operands, read in that order
+fn operands(operands: &[OsString]) -> UResult<[Operand; 3]> {
+    match operands {
+        [last] => Ok([Operand::one(), Operand::one(), operand(last)?]),
+        [first, last] => Ok([operand(first)?, Operand::one(), operand(last)?]),
+        [first, step, last] => {
+            let first = operand(first)?;
+            let increment = operand(step)?;
+            if increment.number.is_zero() {
+                return Err(UError::usage(quoted(
+                    "invalid Zero increment value: {}",
+                    step,
+                )));
+            }
+            Ok([first, increment, operand(last)?])
+        }
+        _ => unreachable!("one to three operands"),
+    }
+}
+
+fn run(args: &Args) -> UResult<()> {
+    match &args.operands[..] {
+        [] => return Err(UError::usage("missing operand")),
+        [_, _, _, extra, ..] => return Err(UError::usage(quoted("extra operand {}", extra))),
+        _ => (),
+    }
+    let format = args.format.as_deref().map(format).transpose()?;
+    if format.is_some() && args.is_equal_width {
+        return Err(UError::usage(
+            "format string may not be specified when printing equal width strings",
+        ));
+    }
+    let [first, step, last] = operands(&args.operands)?;
+    let format =
+        format.unwrap_or_else(|| Format::default(&first, &step, &last, args.is_equal_width));
+    let separator = args
+        .separator
+        .as_deref()
+        .map_or(&b"\n"[..], OsStr::as_encoded_bytes);
+    let mut out = OutputSink::stdout();
+    crate::seq(&first, &step, &last, &format, separator, &mut out).map_err(output::write_error)?;
+    out.finish()
+}
+
+/// Run `seq` with the command line `args`, starting with the program name
+pub fn main(args: Vec<OsString>) -> ExitCode {
+    let mut status = Status::default();
+    let args = posix::options_first(&command(), args, is_negative_number);
+    let matches = command().get_matches_from(args);
+    let args = Args::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+    trace::init(UTIL, false);
+    status.check(UTIL, run(&args));
+    status.into()
+}
@@ -0,0 +1,367 @@
+//! Printing of sequences of numbers with the semantics of `seq` of
+//! coreutils: the numbers are printed with as many digits after the point
+//! as the first number and the increment are written with, or by a
+//! printf(3) format of one floating-point directive, as the long doubles
+//! they round to.
+//!
+//! The numbers are added up exactly as the decimals the operands write,
+//! where GNU `seq` multiplies the increment as a long double, so steps of
+//! tenths never drift and integers past 2^64 keep all their digits.
+//!
+//! ```
+//! use nyaa_seq::{Format, Operand};
+//!
+//! let [first, step, last] = ["-1", "0.5", "1"].map(|arg| Operand::parse(arg.as_bytes()).unwrap());
+//! let format = Format::default(&first, &step, &last, true);
+//! let mut out = Vec::new();
+//! nyaa_seq::seq(&first, &step, &last, &format, b" ", &mut out)?;
+//! assert_eq!(out, b"-1.0 -0.5 00.0 00.5 01.0\n");
+//! # Ok::<(), std::io::Error>(())
+//! ```
+
+pub mod cli;
+mod number;
+
+pub use number::{Invalid, Number, Operand};
+
+use nyaa_printf::format::{Directive, Field, Flags, Spec};
+use std::io::{self, Write};
+
+/// Bytes of numbers written at once
+const BATCH_SIZE: usize = 32 * 1024;
+
+/// Why a format of `-f` is invalid
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FormatError {
+    NoDirective,
+    /// A `%` ending the format
+    EndsInPercent,
+    /// A directive of another conversion than a floating-point one
+    Unknown(u8),
+    TooManyDirectives,
+}
+
+/// How the numbers are printed
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Format {
+    /// With `precision` digits after the point, and zeros after the sign
+    /// up to `width`
+    Fixed { precision: usize, width: usize },
+    /// By a floating-point directive, between the text before and after
+    /// it with `%%` printing `%`
+    Printf {
+        prefix: Vec<u8>,
+        spec: Spec,
+        suffix: Vec<u8>,
+    },
+}
+
+impl Format {
+    /// The format of `-f`, checked like GNU `seq` does: a single directive
+    /// with the flags `-+#0 '`, a width, a precision, an optional `L` and
+    /// one of the conversions `aAeEfFgG`
+    pub fn parse(format: &[u8]) -> Result<Self, FormatError> {
+        let at = |i: usize| format.get(i).copied().unwrap_or(0);
+        let is_directive = |i: usize| at(i) == b'%' && at(i + 1) != b'%';
+        let mut prefix = Vec::new();
+        let mut i = 0;
+        while !is_directive(i) {
+            if i >= format.len() {
+                return Err(FormatError::NoDirective);
+            }
+            prefix.push(format[i]);
+            i += (format[i] == b'%') as usize + 1;
+        }
+        let start = i + 1;
+        i = start
+            + format[start..]
+                .iter()
+                .take_while(|b| b"-+#0 '".contains(b))
+                .count();
+        i += format[i..]
+            .iter()
+            .take_while(|b| b.is_ascii_digit())
+            .count();
+        if at(i) == b'.' {
+            i += 1;
+            i += format[i..]
+                .iter()
+                .take_while(|b| b.is_ascii_digit())
+                .count();
+        }
+        i += (at(i) == b'L') as usize;
+        match format.get(i) {
+            None => return Err(FormatError::EndsInPercent),
+            Some(&c) if !b"aAeEfFgG".contains(&c) => return Err(FormatError::Unknown(c)),
+            Some(_) => (),
+        }
+        let directive = Directive::parse(&format[start..=i]);
+        let digits = |field| match field {
+            Some(Field::Digits(n)) => Some(n),
+            _ => None,
+        };
+        let spec = Spec {
+            flags: directive.flags,
+            width: digits(directive.width).unwrap_or(0),
+            precision: digits(directive.precision),
+            conversion: format[i],
+        };
+        let mut suffix = Vec::new();
+        i += 1;
+        while i < format.len() {
+            if is_directive(i) {
+                return Err(FormatError::TooManyDirectives);
+            }
+            suffix.push(format[i]);
+            i += (format[i] == b'%') as usize + 1;
+        }
+        Ok(Format::Printf {
+            prefix,
+            spec,
+            suffix,
+        })
+    }
+
+    /// The format without `-f`, from the precisions of the operands and,
+    /// if `is_equal_width`, their widths, like GNU `seq` makes it: `%Lg`
+    /// when some precision isn't known
+    pub fn default(first: &Operand, step: &Operand, last: &Operand, is_equal_width: bool) -> Self {
+        let general = Format::Printf {
+            prefix: Vec::new(),
+            spec: Spec {
+                flags: Flags::default(),
+                width: 0,
+                precision: None,
+                conversion: b'g',
+            },
+            suffix: Vec::new(),
+        };
+        let (Some(first_precision), Some(step_precision), Some(last_precision)) =
+            (first.precision, step.precision, last.precision)
+        else {
+            return general;
+        };
+        let precision = first_precision.max(step_precision);
+        if !is_equal_width {
+            return Format::Fixed {
+                precision,
+                width: 0,
+            };
+        }
+        let mut first_width = first.width + (precision - first_precision) as i64;
+        let mut last_width = last.width + precision as i64 - last_precision as i64;
+        if precision > 0 {
+            first_width += (first_precision == 0) as i64;
+            last_width += (last_precision == 0) as i64;
+        } else if last_precision > 0 {
+            last_width -= 1;
+        }
+        // Negative widths are huge ones to GNU `seq`
+        match usize::try_from(first_width.max(last_width)) {
+            Ok(width) if first_width >= 0 && last_width >= 0 && width <= i32::MAX as usize => {
+                Format::Fixed { precision, width }
+            }
+            _ => general,
+        }
+    }
+
+    /// The text before the numbers and after them
+    fn affixes(&self) -> (&[u8], &[u8]) {
+        match self {
+            Format::Fixed { .. } => (b"", b""),
+            Format::Printf { prefix, suffix, .. } => (prefix, suffix),
+        }
+    }
+
+    /// Append the number `x` formatted, without the prefix and the suffix
+    fn write_number(&self, x: &Number, out: &mut Vec<u8>) {
+        match (self, x) {
+            (&Format::Fixed { precision, width }, Number::Finite(x)) => {
+                x.write_fixed(precision, width, out)
+            }
+            (&Format::Fixed { precision, width }, _) => {
+                let spec = Spec {
+                    flags: Flags {
+                        is_zero: true,
+                        ..Flags::default()
+                    },
+                    width,
+                    precision: Some(precision),
+                    conversion: b'f',
+                };
+                out.extend_from_slice(&spec.float(&x.to_long_double()))
+            }
+            (Format::Printf { spec, .. }, _) => {
+                out.extend_from_slice(&spec.float(&x.to_long_double()))
+            }
+        }
+    }
+}
+
+/// Print the numbers from `first` to `last` by `step`, which isn't zero,
+/// in `format` and separated by `separator`, and a newline after them if
+/// there are any
+pub fn seq(
+    first: &Operand,
+    step: &Operand,
+    last: &Operand,
+    format: &Format,
+    separator: &[u8],
+    out: &mut impl Write,
+) -> io::Result<()> {
+    let scale = [first, step, last]
+        .iter()
+        .map(|operand| operand.number.scale())
+        .max()
+        .unwrap_or(0);
+    let [mut x, mut step_number, mut last_number] =
+        [first, step, last].map(|operand| operand.number.clone());
+    for number in [&mut x, &mut step_number, &mut last_number] {
+        number.rescale(scale);
+    }
+    let is_descending = step_number.is_negative();
+    let is_past = |x: &Number| match is_descending {
+        true => *x < last_number,
+        false => *x > last_number,
+    };
+    if is_past(&x) {
+        return Ok(());
+    }
+    let (prefix, suffix) = format.affixes();
+    // The numbers are written in batches of many, the one last appended
+    // starting at `start`
+    let mut batch = Vec::with_capacity(BATCH_SIZE * 2);
+    batch.extend_from_slice(prefix);
+    let mut start = batch.len();
+    format.write_number(&x, &mut batch);
+    batch.extend_from_slice(suffix);
+    loop {
+        x.add(&step_number);
+        if is_past(&x) {
+            // The number just past the last one is still printed if it
+            // prints as the last one, and not as the number before, for
+            // formats rounding both to it
+            let mut next = Vec::new();
+            format.write_number(&x, &mut next);
+            let text = &batch[start..batch.len() - suffix.len()];
+            let is_last = Operand::parse(&next).is_ok_and(|next| next.number == last_number);
+            if is_last && next != text {
+                for part in [separator, prefix, &next, suffix] {
+                    batch.extend_from_slice(part);
+                }
+            }
+            break;
+        }
+        if batch.len() >= BATCH_SIZE {
+            out.write_all(&batch)?;
+            batch.clear();
+        }
+        batch.extend_from_slice(separator);
+        batch.extend_from_slice(prefix);
+        start = batch.len();
+        format.write_number(&x, &mut batch);
+        batch.extend_from_slice(suffix);
+    }
+    batch.push(b'\n');
+    out.write_all(&batch)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn seq_of(args: &[&str], format: Option<&str>, is_equal_width: bool) -> String {
+        let mut operands: Vec<Operand> = args
+            .iter()
+            .map(|arg| Operand::parse(arg.as_bytes()).unwrap())
+            .collect();
+        let last = operands.pop().unwrap();
+        let step = match operands.len() {
+            2 => operands.pop().unwrap(),
+            _ => Operand::one(),
+        };
+        let first = operands.pop().unwrap_or_else(Operand::one);
+        let format = match format {
+            Some(format) => Format::parse(format.as_bytes()).unwrap(),
+            None => Format::default(&first, &step, &last, is_equal_width),
+        };
+        let mut out = Vec::new();
+        seq(&first, &step, &last, &format, b" ", &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn steps() {
+        assert_eq!(seq_of(&["3"], None, false), "1 2 3\n");
+        assert_eq!(seq_of(&["5", "-2", "1"], None, false), "5 3 1\n");
+        assert_eq!(seq_of(&["2", "1"], None, false), "");
+        assert_eq!(
+            seq_of(&["1.2", "-0.3", "0.3"], None, false),
+            "1.2 0.9 0.6 0.3\n"
+        );
+        assert_eq!(seq_of(&["-1", "1", "1"], None, false), "-1 0 1\n");
+        assert_eq!(seq_of(&["-0", "1"], None, false), "-0 1\n");
+        assert_eq!(
+            seq_of(&["0", "1e-6", "3e-6"], None, false),
+            "0.000000 0.000001 0.000002 0.000003\n"
+        );
+        assert_eq!(
+            seq_of(
+                &["99999999999999999999", "100000000000000000001"],
+                None,
+                false
+            ),
+            "99999999999999999999 100000000000000000000 100000000000000000001\n"
+        );
+        assert_eq!(seq_of(&["1", "0x1p-1", "2"], None, false), "1 1.5 2\n");
+        assert_eq!(seq_of(&["0", "inf", "1"], None, false), "0\n");
+    }
+
+    #[test]
+    fn equal_width() {
+        assert_eq!(seq_of(&["9", "11"], None, true), "09 10 11\n");
+        assert_eq!(
+            seq_of(&["-.5", ".5", "1"], None, true),
+            "-0.5 00.0 00.5 01.0\n"
+        );
+        assert_eq!(seq_of(&["10e-1", "2"], None, true), "01.0 02.0\n");
+        assert_eq!(seq_of(&["1", "1", "2.50"], None, true), "1 2\n");
+        assert_eq!(seq_of(&["0x9", "11"], None, true), "09 10 11\n");
+    }
+
+    #[test]
+    fn formats() {
+        assert_eq!(
+            seq_of(&["1", "3"], Some("%05.1f"), false),
+            "001.0 002.0 003.0\n"
+        );
+        assert_eq!(
+            seq_of(&["1", "2"], Some("x%%%gy%%"), false),
+            "x%1y% x%2y%\n"
+        );
+        assert_eq!(
+            seq_of(&["0", "0.1000001", "0.3"], Some("%.1f"), false),
+            "0.0 0.1 0.2 0.3\n"
+        );
+        assert_eq!(
+            seq_of(&["999999", "1000000"], Some("%Lg"), false),
+            "999999 1e+06\n"
+        );
+        assert_eq!(Format::parse(b"%d"), Err(FormatError::Unknown(b'd')));
+        assert_eq!(Format::parse(b"%%"), Err(FormatError::NoDirective));
+        assert_eq!(Format::parse(b"a%-5"), Err(FormatError::EndsInPercent));
+        assert_eq!(Format::parse(b"%g%"), Err(FormatError::TooManyDirectives));
+    }
+
+    #[test]
+    fn operands() {
+        assert_eq!(Operand::parse(b"1e5000").err(), Some(Invalid::NotNumber));
+        assert_eq!(Operand::parse(b"1 ").err(), Some(Invalid::NotNumber));
+        assert_eq!(Operand::parse(b"").err(), Some(Invalid::NotNumber));
+        assert_eq!(Operand::parse(b"-nan").err(), Some(Invalid::Nan));
+        let operand = Operand::parse(b" +1.50e1").unwrap();
+        assert_eq!((operand.width, operand.precision), (3, Some(1)));
+        assert_eq!(Operand::parse(b"0x1.8").unwrap().precision, None);
+        assert_eq!(Operand::parse(b"inf").unwrap().precision, Some(0));
+    }
+}
@@ -0,0 +1,491 @@
+//! Numbers of sequences: the operands read like strtold(3) reads them but
+//! kept as the exact decimals they are written as, so that adding the
+//! increment again and again never drifts, with the widths and precisions
+//! GNU `seq` takes from how they are written
+
+use nyaa_printf::big::Big;
+use nyaa_printf::float::{self, Kind, LongDouble};
+use std::cmp::Ordering;
+use std::iter;
+
+/// A finite number, `digits × 10^-scale`
+#[derive(Clone, Debug)]
+pub struct Decimal {
+    pub is_negative: bool,
+    /// ASCII digits from the most significant one, without leading zeros,
+    /// so none for zero
+    digits: Vec<u8>,
+    /// Digits after the point
+    scale: usize,
+}
+
+/// Add the magnitude `b` to the magnitude `a`, both ASCII digits
+fn add_magnitude(a: &mut Vec<u8>, b: &[u8]) {
+    if a.len() < b.len() {
+        a.splice(0..0, iter::repeat_n(b'0', b.len() - a.len()));
+    }
+    let offset = a.len() - b.len();
+    let mut carry = 0;
+    for i in (0..a.len()).rev() {
+        let digit = match i.checked_sub(offset) {
+            Some(j) => b[j] - b'0',
+            None if carry == 0 => break,
+            None => 0,
+        };
+        let sum = a[i] - b'0' + digit + carry;
+        a[i] = b'0' + sum % 10;
+        carry = sum / 10;
+    }
+    if carry > 0 {
+        a.insert(0, b'1');
+    }
+}
+
+/// Subtract the magnitude `b` from the magnitude `a`, which isn't less
+fn sub_magnitude(a: &mut Vec<u8>, b: &[u8]) {
+    let offset = a.len() - b.len();
+    let mut borrow = 0;
+    for i in (0..a.len()).rev() {
+        let digit = match i.checked_sub(offset) {
+            Some(j) => b[j] - b'0',
+            None if borrow == 0 => break,
+            None => 0,
+        };
+        let (diff, is_borrowing) = match (a[i] - b'0').checked_sub(digit + borrow) {
+            Some(diff) => (diff, false),
+            None => (a[i] - b'0' + 10 - digit - borrow, true),
+        };
+        a[i] = b'0' + diff;
+        borrow = is_borrowing as u8;
+    }
+    let zeros = a.iter().take_while(|&&b| b == b'0').count();
+    a.drain(..zeros);
+}
+
+/// Compare the magnitudes `a` and `b`, without leading zeros
+fn cmp_magnitude(a: &[u8], b: &[u8]) -> Ordering {
+    a.len().cmp(&b.len()).then_with(|| a.cmp(b))
+}
+
+impl Decimal {
+    /// The number of the ASCII digits `digits` with `scale` of them after
+    /// the point
+    fn new(is_negative: bool, mut digits: Vec<u8>, scale: usize) -> Self {
+        let zeros = digits.iter().take_while(|&&b| b == b'0').count();
+        digits.drain(..zeros);
+        Decimal {
+            is_negative,
+            digits,
+            scale,
+        }
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.digits.is_empty()
+    }
+
+    /// Have at least `scale` digits after the point
+    pub fn rescale(&mut self, scale: usize) {
+        if scale > self.scale {
+            if !self.is_zero() {
+                self.digits
+                    .resize(self.digits.len() + scale - self.scale, b'0');
+            }
+            self.scale = scale;
+        }
+    }
+
+    /// Add `other`, which has the same scale. Zero is negative only if
+    /// both are.
+    fn add(&mut self, other: &Decimal) {
+        debug_assert_eq!(self.scale, other.scale);
+        if other.is_zero() {
+            self.is_negative &= other.is_negative;
+        } else if self.is_zero() {
+            self.clone_from(other);
+        } else if self.is_negative == other.is_negative {
+            add_magnitude(&mut self.digits, &other.digits);
+        } else if cmp_magnitude(&self.digits, &other.digits) != Ordering::Less {
+            sub_magnitude(&mut self.digits, &other.digits);
+            self.is_negative &= !self.is_zero();
+        } else {
+            let mut digits = other.digits.clone();
+            sub_magnitude(&mut digits, &self.digits);
+            self.digits = digits;
+            self.is_negative = other.is_negative;
+        }
+    }
+
+    /// Append the number with `precision` digits after the point, cut or
+    /// padded with zeros, and zeros after the sign up to `width`, like
+    /// `%0*.*Lf` prints it
+    pub fn write_fixed(&self, precision: usize, width: usize, out: &mut Vec<u8>) {
+        let (len, scale) = (self.digits.len(), self.scale);
+        let integer = &self.digits[..len.saturating_sub(scale)];
+        if self.is_negative {
+            out.push(b'-');
+        }
+        let printed = self.is_negative as usize
+            + integer.len().max(1)
+            + if precision > 0 { precision + 1 } else { 0 };
+        if width > printed {
+            out.resize(out.len() + width - printed, b'0');
+        }
+        match integer {
+            [] => out.push(b'0'),
+            _ => out.extend_from_slice(integer),
+        }
+        if precision > 0 {
+            out.push(b'.');
+            let zeros = scale.saturating_sub(len).min(precision);
+            let fraction = &self.digits[len - (scale - scale.saturating_sub(len))..];
+            let fraction = &fraction[..fraction.len().min(precision - zeros)];
+            let missing = precision - zeros - fraction.len();
+            out.resize(out.len() + zeros, b'0');
+            out.extend_from_slice(fraction);
+            out.resize(out.len() + missing, b'0');
+        }
+    }
+
+    /// The nearest long double
+    pub fn to_long_double(&self) -> LongDouble {
+        let n = match self.digits.len() {
+            0..=19 => {
+                let n = self
+                    .digits
+                    .iter()
+                    .fold(0, |n, b| n * 10 + (b - b'0') as u64);
+                if self.scale == 0 {
+                    return LongDouble::from_u64(n, self.is_negative);
+                }
+                Big::from_u64(n)
+            }
+            _ => {
+                let mut n = Big::default();
+                for chunk in self.digits.chunks(9) {
+                    let value = chunk.iter().fold(0, |n, b| n * 10 + (b - b'0') as u32);
+                    n.mul_add(10u32.pow(chunk.len() as u32), value);
+                }
+                n
+            }
+        };
+        LongDouble::from_parts(self.is_negative, n, 0, -(self.scale as i64)).0
+    }
+
+    /// The exact value of the finite long double `x`
+    fn from_long_double(x: &LongDouble) -> Self {
+        let Kind::Finite {
+            significand,
+            exponent,
+        } = x.kind
+        else {
+            unreachable!("not a finite long double");
+        };
+        let mut n = Big::from_u64(significand);
+        let scale = match u64::try_from(exponent) {
+            Ok(exponent) => {
+                n.shl(exponent);
+                0
+            }
+            // `significand / 2^e` is `significand × 5^e / 10^e`
+            Err(_) => {
+                n.mul_pow(5, exponent.unsigned_abs() as u64);
+                exponent.unsigned_abs() as usize
+            }
+        };
+        let digits = n.digits().into_iter().map(|d| b'0' + d).collect();
+        Decimal::new(x.is_negative, digits, scale)
+    }
+
+    /// The number of the decimal constant `s`, without a sign, that
+    /// strtold(3) reads whole to a finite nonzero long double
+    fn parse(s: &[u8], is_negative: bool) -> Self {
+        let mut digits = Vec::with_capacity(s.len());
+        let mut fraction = None;
+        let mut i = 0;
+        while let Some(&b) = s.get(i) {
+            match b {
+                b'0'..=b'9' => {
+                    digits.push(b);
+                    fraction = fraction.map(|len| len + 1);
+                }
+                b'.' => fraction = Some(0),
+                _ => break,
+            }
+            i += 1;
+        }
+        let exponent = s.get(i + 1..).map_or(0, strtol);
+        // The long double is in range, which bounds the exponent
+        let scale = fraction.unwrap_or(0) as i64 - exponent;
+        if scale < 0 {
+            digits.resize(digits.len() + scale.unsigned_abs() as usize, b'0');
+        }
+        Decimal::new(is_negative, digits, scale.max(0) as usize)
+    }
+}
+
+impl PartialEq for Decimal {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Decimal {}
+
+impl Ord for Decimal {
+    fn cmp(&self, other: &Self) -> Ordering {
+        if self.scale != other.scale {
+            let (mut a, mut b) = (self.clone(), other.clone());
+            a.rescale(b.scale);
+            b.rescale(a.scale);
+            return a.cmp(&b);
+        }
+        let sign = |x: &Decimal| match (x.is_zero(), x.is_negative) {
+            (true, _) => 0,
+            (false, true) => -1,
+            (false, false) => 1,
+        };
+        match sign(self).cmp(&sign(other)) {
+            Ordering::Equal if sign(self) < 0 => cmp_magnitude(&other.digits, &self.digits),
+            Ordering::Equal => cmp_magnitude(&self.digits, &other.digits),
+            ordering => ordering,
+        }
+    }
+}
+
+impl PartialOrd for Decimal {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// A number of a sequence
+#[derive(Clone, Debug)]
+pub enum Number {
+    Finite(Decimal),
+    Infinite {
+        is_negative: bool,
+    },
+    /// The sum of infinities of opposite signs, which no number compares
+    /// to
+    Nan,
+}
+
+impl Number {
+    pub fn is_negative(&self) -> bool {
+        match self {
+            Number::Finite(x) => x.is_negative && !x.is_zero(),
+            Number::Infinite { is_negative } => *is_negative,
+            Number::Nan => false,
+        }
+    }
+
+    pub fn is_zero(&self) -> bool {
+        matches!(self, Number::Finite(x) if x.is_zero())
+    }
+
+    /// Digits after the point, 0 if not finite
+    pub fn scale(&self) -> usize {
+        match self {
+            Number::Finite(x) => x.scale,
+            _ => 0,
+        }
+    }
+
+    /// Have at least `scale` digits after the point, if finite
+    pub fn rescale(&mut self, scale: usize) {
+        if let Number::Finite(x) = self {
+            x.rescale(scale);
+        }
+    }
+
+    /// Add `other`, which has the same scale if both are finite
+    pub fn add(&mut self, other: &Number) {
+        match (&mut *self, other) {
+            (Number::Finite(x), Number::Finite(y)) => x.add(y),
+            (Number::Nan, _) => (),
+            (Number::Infinite { is_negative: a }, Number::Infinite { is_negative: b })
+                if a != b =>
+            {
+                *self = Number::Nan
+            }
+            (Number::Infinite { .. }, _) => (),
+            (_, other) => self.clone_from(other),
+        }
+    }
+
+    /// The nearest long double, a negative NaN for NaN like x86 gives for
+    /// the sum of opposite infinities
+    pub fn to_long_double(&self) -> LongDouble {
+        match self {
+            Number::Finite(x) => x.to_long_double(),
+            Number::Infinite { is_negative } => LongDouble {
+                is_negative: *is_negative,
+                kind: Kind::Infinite,
+            },
+            Number::Nan => LongDouble {
+                is_negative: true,
+                kind: Kind::Nan,
+            },
+        }
+    }
+}
+
+impl PartialEq for Number {
+    fn eq(&self, other: &Self) -> bool {
+        self.partial_cmp(other) == Some(Ordering::Equal)
+    }
+}
+
+impl PartialOrd for Number {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        let rank = |x: &Number| match x {
+            Number::Infinite { is_negative: true } => Some(-1),
+            Number::Finite(_) => Some(0),
+            Number::Infinite { is_negative: false } => Some(1),
+            Number::Nan => None,
+        };
+        match (self, other) {
+            (Number::Finite(x), Number::Finite(y)) => Some(x.cmp(y)),
+            _ => Some(rank(self)?.cmp(&rank(other)?)),
+        }
+    }
+}
+
+/// The value of the decimal integer at the start of `s` like strtol(3)
+/// reads it, saturated, 0 if there is none
+fn strtol(s: &[u8]) -> i64 {
+    let is_negative = s.first() == Some(&b'-');
+    let digits = s
+        .iter()
+        .skip(matches!(s.first(), Some(b'-' | b'+')) as usize)
+        .take_while(|b| b.is_ascii_digit());
+    let n = digits.fold(0i64, |n, b| {
+        n.saturating_mul(10).saturating_add((b - b'0') as i64)
+    });
+    if is_negative {
+        -n
+    } else {
+        n
+    }
+}
+
+/// Why an operand is invalid
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Invalid {
+    /// Not a number as a whole, or out of the range of long doubles
+    NotNumber,
+    Nan,
+}
+
+/// An operand, with its width and precision
+#[derive(Clone, Debug)]
+pub struct Operand {
+    pub number: Number,
+    /// Width of the number printed with `precision` digits after the
+    /// point, which may be off for what GNU `seq` miscounts
+    pub width: i64,
+    /// Digits after the point the operand is written with, `None` if it
+    /// isn't written as a decimal, like hexadecimal fractions
+    pub precision: Option<usize>,
+}
+
+impl Operand {
+    /// 1, the first number and the increment when they are omitted
+    pub fn one() -> Self {
+        Operand {
+            number: Number::Finite(Decimal::new(false, b"1".to_vec(), 0)),
+            width: 1,
+            precision: Some(0),
+        }
+    }
+
+    /// Read `arg` whole like strtold(3) does
+    pub fn parse(arg: &[u8]) -> Result<Self, Invalid> {
+        let parsed = LongDouble::parse(arg);
+        if parsed.len == 0 || parsed.len != arg.len() {
+            return Err(Invalid::NotNumber);
+        }
+        let value = parsed.value;
+        let is_negative = value.is_negative;
+        let number = match value.kind {
+            Kind::Nan => return Err(Invalid::Nan),
+            Kind::Infinite if parsed.is_range_error => return Err(Invalid::NotNumber),
+            Kind::Infinite => Number::Infinite { is_negative },
+            // Underflows are taken as zero
+            _ if value.is_zero() => Number::Finite(Decimal::new(is_negative, Vec::new(), 0)),
+            Kind::Finite { .. } => {
+                let start = arg.iter().take_while(|&&b| float::is_space(b)).count();
+                let s = &arg[start..];
+                let s = s.strip_prefix(b"-").or(s.strip_prefix(b"+")).unwrap_or(s);
+                Number::Finite(match s.get(1) {
+                    Some(b'x' | b'X') => Decimal::from_long_double(&value),
+                    _ => Decimal::parse(s, is_negative),
+                })
+            }
+        };
+        let start = arg
+            .iter()
+            .take_while(|&&b| float::is_space(b) || b == b'+')
+            .count();
+        let (width, precision) = layout(&arg[start..], &number);
+        Ok(Operand {
+            number,
+            width,
+            precision,
+        })
+    }
+}
+
+/// The width and precision of the operand `arg` of `number`, after its
+/// leading white space and plus signs, as GNU `seq` counts them
+fn layout(arg: &[u8], number: &Number) -> (i64, Option<usize>) {
+    let point = arg.iter().position(|&b| b == b'.');
+    // Past the range of an int, there is no precision
+    let mut precision = i32::MAX as i64;
+    let mut width = 0;
+    if point.is_none() && !arg.contains(&b'p') {
+        precision = 0;
+    }
+    let is_hex = arg.iter().any(|b| matches!(b, b'x' | b'X'));
+    if !is_hex && matches!(number, Number::Finite(_)) {
+        width = arg.len() as i64;
+        let mut fraction = 0;
+        if let Some(point) = point {
+            fraction = arg[point + 1..]
+                .iter()
+                .take_while(|b| !matches!(b, b'e' | b'E'))
+                .count() as i64;
+            precision = fraction;
+            width += match fraction {
+                0 => -1,
+                _ => (point == 0 || !arg[point - 1].is_ascii_digit()) as i64,
+            };
+        }
+        let e = arg.iter().position(|&b| b == b'e');
+        if let Some(e) = e.or_else(|| arg.iter().position(|&b| b == b'E')) {
+            let mut exponent = strtol(&arg[e + 1..]).max(-i64::MAX);
+            precision = match exponent < 0 {
+                true => precision.saturating_sub(exponent),
+                false => precision - precision.min(exponent),
+            };
+            width -= (arg.len() - e) as i64;
+            if exponent < 0 {
+                if point.is_none_or(|point| e == point + 1) {
+                    width += 1;
+                }
+                exponent = -exponent;
+            } else {
+                if point.is_some() && precision == 0 && fraction > 0 {
+                    width -= 1;
+                }
+                exponent = -precision.min(exponent);
+            }
+            width = width.saturating_add(exponent);
+        }
+    }
+    let precision = usize::try_from(precision)
+        .ok()
+        .filter(|&p| p < i32::MAX as usize);
+    (width, precision)
+}
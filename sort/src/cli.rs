@@ -0,0 +1,602 @@
+//! Command line interface of `sort`
+
+use crate::debug::{self, Annotator};
+use crate::external::{self, Error, Options, Sorter};
+use crate::key::{Flags, Key};
+use crate::Order;
+use clap::{ArgAction, CommandFactory, FromArgMatches, Parser, ValueEnum};
+use nyaa_core::error::{self, Status, UError, UResult};
+use nyaa_core::i18n;
+use nyaa_core::jobs::JobsArg;
+use nyaa_core::locale::Locale;
+use nyaa_core::output::{self, OutputSink};
+use nyaa_core::platform;
+use nyaa_core::posix;
+use nyaa_core::quote::{quote, shell_escape};
+use nyaa_core::random::Random;
+use nyaa_core::response;
+use nyaa_core::trace;
+use nyaa_core::Input;
+use std::ffi::OsString;
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+const UTIL: &str = "sort";
+
+/// Exit code of every failure, 2 like in coreutils
+const EXIT_TROUBLE: u8 = 2;
+
+/// Exit code of `-c` for input that isn't sorted
+const EXIT_DISORDER: u8 = 1;
+
+/// How `-c` reports input that isn't sorted
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+enum Check {
+    /// Report the first line out of order
+    DiagnoseFirst,
+    /// Only exit with 1
+    #[value(alias = "silent")]
+    Quiet,
+}
+
+#[derive(Parser)]
+#[command(name = "sort", version)]
+#[command(about = "sort lines of text files", long_about = None)]
+#[command(disable_help_flag = true, disable_version_flag = true)]
+// Options may be repeated, like in scripts building up a command line
+#[command(args_override_self = true)]
+struct Args {
+    /// Ignore leading blanks
+    #[arg(short = 'b', long = "ignore-leading-blanks")]
+    is_ignore_blanks: bool,
+
+    /// Fold lower case to upper case characters
+    #[arg(short = 'f', long = "ignore-case")]
+    is_ignore_case: bool,
+
+    /// Compare according to the floating point numbers the lines start
+    /// with, like 1e3 or inf
+    #[arg(short = 'g', long = "general-numeric-sort")]
+    is_general_numeric: bool,
+
+    /// Compare the numbers with SI suffixes the lines start with, like 2K
+    /// or 1G
+    #[arg(short = 'h', long = "human-numeric-sort")]
+    is_human_numeric: bool,
+
+    /// Compare the abbreviated month names the lines start with, unknown
+    /// < JAN < ... < DEC
+    #[arg(short = 'M', long = "month-sort")]
+    is_month: bool,
+
+    /// Compare according to the numbers the lines start with
+    #[arg(short = 'n', long = "numeric-sort")]
+    is_numeric: bool,
+
+    /// Shuffle the lines by comparing their hashes, with equal keys
+    /// together
+    #[arg(short = 'R', long = "random-sort")]
+    is_random: bool,
+
+    /// Take the salt of the hashes of -R from FILE
+    #[arg(long = "random-source", value_name = "FILE")]
+    random_source: Option<PathBuf>,
+
+    /// Make the salt of the hashes of -R from SEED, so the same seed
+    /// shuffles the same way every time
+    #[arg(long, value_name = "SEED", conflicts_with = "random_source")]
+    seed: Option<OsString>,
+
+    /// Reverse the result of comparisons
+    #[arg(short = 'r', long = "reverse")]
+    is_reverse: bool,
+
+    /// Compare the lines as version numbers, like in file names
+    #[arg(short = 'V', long = "version-sort")]
+    is_version: bool,
+
+    /// Sort by the key KEYDEF, F[.C][OPTS][,F[.C][OPTS]]: from the field F,
+    /// at its character C, to the end of the line or of the second field,
+    /// up to its character C. OPTS are the letters of b, f, g, h, M, n, R,
+    /// r and V for the key instead of the global options
+    #[arg(short = 'k', long = "key", value_name = "KEYDEF")]
+    keys: Vec<String>,
+
+    /// Separate the fields with SEP instead of the transition from
+    /// non-blank to blank characters, \0 for NUL
+    #[arg(short = 't', long = "field-separator", value_name = "SEP")]
+    separators: Vec<OsString>,
+
+    /// Output only the first of the lines with equal keys
+    #[arg(short = 'u', long = "unique")]
+    is_unique: bool,
+
+    /// Keep the lines with equal keys in the order of the input instead of
+    /// comparing the whole lines
+    #[arg(short = 's', long = "stable")]
+    is_stable: bool,
+
+    /// Check whether the input is sorted instead of sorting it, reporting
+    /// the first line out of order unless WHEN is quiet
+    #[arg(
+        short = 'c',
+        long = "check",
+        value_name = "WHEN",
+        value_enum,
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "diagnose-first"
+    )]
+    check: Option<Check>,
+
+    /// Same as --check=quiet
+    #[arg(short = 'C')]
+    is_check_quiet: bool,
+
+    /// Write the output to FILE instead of the stdout. FILE may be one of
+    /// the inputs, it is written after they are read.
+    #[arg(short = 'o', long = "output", value_name = "FILE")]
+    outputs: Vec<PathBuf>,
+
+    /// Underline the part of each output line that every key compares and
+    /// warn about questionable keys and options on the stderr
+    #[arg(long = "debug")]
+    is_debug: bool,
+
+    /// Sort SIZE of lines in memory at once and spill them to temporary
+    /// files beyond it: KiB, or with a suffix of b for bytes, K, M, G,
+    /// T... or % of the physical memory
+    #[arg(
+        short = 'S',
+        long = "buffer-size",
+        value_name = "SIZE",
+        allow_hyphen_values = true
+    )]
+    buffer_size: Option<String>,
+
+    /// Create the temporary files in DIR instead of $TMPDIR or /tmp, in
+    /// turn in all DIRs given
+    #[arg(short = 'T', long = "temporary-directory", value_name = "DIR")]
+    temporary_dirs: Vec<PathBuf>,
+
+    /// Sort on up to N threads, by default NYAA_JOBS or the number of
+    /// CPUs but at most 8
+    #[arg(long = "parallel", value_name = "N")]
+    parallel: Option<String>,
+
+    /// Line delimiter is NUL, not newline
+    #[arg(short = 'z', long = "zero-terminated")]
+    is_zero_terminated: bool,
+
+    /// Read the input file names from the NUL-terminated names in file F,
+    /// - for the stdin
+    #[arg(long, value_name = "F", conflicts_with = "files")]
+    files0_from: Option<PathBuf>,
+
+    /// Input files. An argument @FILE stands for the arguments on the
+    /// lines of FILE, @@NAME for the file @NAME
+    files: Vec<PathBuf>,
+
+    /// Print help
+    #[arg(long, action=ArgAction::Help)]
+    help: (),
+
+    /// Print version
+    #[arg(long, action=ArgAction::Version)]
+    version: (),
+}
+
+pub fn command() -> clap::Command {
+    i18n::command(Args::command())
+}
+
+/// The byte separating fields, which every `-t` has to agree on
+fn separator(separators: &[OsString]) -> UResult<Option<u8>> {
+    let mut separator = None;
+    for spec in separators {
+        let byte = match spec.as_encoded_bytes() {
+            [] => return Err(UError::usage("empty tab")),
+            b"\\0" => b'\0',
+            &[byte] => byte,
+            _ => {
+                return Err(UError::usage(
+                    i18n::tr("multi-character tab {}").replace("{}", &shell_escape(spec)),
+                ))
+            }
+        };
+        if separator.is_some_and(|separator| separator != byte) {
+            return Err(UError::usage("incompatible tabs"));
+        }
+        separator = Some(byte);
+    }
+    Ok(separator)
+}
+
+/// The order the command line asks for
+/// Flags of the global ordering options
+fn flags(args: &Args) -> Flags {
+    Flags {
+        skips_start_blanks: args.is_ignore_blanks,
+        skips_end_blanks: args.is_ignore_blanks,
+        is_fold: args.is_ignore_case,
+        is_numeric: args.is_numeric,
+        is_general_numeric: args.is_general_numeric,
+        is_human_numeric: args.is_human_numeric,
+        is_month: args.is_month,
+        is_random: args.is_random,
+        is_version: args.is_version,
+        is_reverse: args.is_reverse,
+    }
+}
+
+fn order(args: &Args) -> UResult<Order> {
+    let separator = separator(&args.separators)?;
+    let flags = flags(args);
+    let mut keys = Vec::new();
+    for spec in &args.keys {
+        let mut key = Key::parse(spec).map_err(UError::usage)?;
+        if !key.has_flags() {
+            key.flags = flags;
+        }
+        keys.push(key);
+    }
+    // Options other than -r order by the whole line as a key
+    let is_ordered = flags
+        != Flags {
+            is_reverse: flags.is_reverse,
+            ..Flags::default()
+        };
+    if keys.is_empty() && is_ordered {
+        keys.push(Key::whole_line(flags));
+    }
+    if let Some(letters) = keys.iter().find_map(|key| key.flags.incompatible()) {
+        return Err(UError::usage(
+            i18n::tr("options '-{}' are incompatible").replace("{}", &letters),
+        ));
+    }
+    let salt = if keys.iter().any(|key| key.flags.is_random) {
+        salt(args)?
+    } else {
+        [0; 16]
+    };
+    Ok(Order {
+        keys,
+        separator,
+        locale: Locale::collate(),
+        is_reverse: args.is_reverse,
+        is_stable: args.is_stable,
+        salt,
+    })
+}
+
+/// Salt of the hashes of `-R`, the first bytes of the random source,
+/// bytes generated from the seed, or random ones
+fn salt(args: &Args) -> UResult<[u8; 16]> {
+    let mut salt = [0; 16];
+    let Some(source) = &args.random_source else {
+        let mut random = match &args.seed {
+            Some(seed) => Random::with_seed(seed.as_encoded_bytes()),
+            None => Random::new(),
+        };
+        // Generators never fail
+        random.fill(&mut salt).map_err(UError::from)?;
+        return Ok(salt);
+    };
+    let file = File::open(source).map_err(|e| {
+        let name = format!("{}: {}", i18n::tr("open failed"), quote(source.as_os_str()));
+        UError::from(e).with_name(name).with_code(EXIT_TROUBLE)
+    })?;
+    let name = shell_escape(source.as_os_str());
+    Random::from_reader(file).fill(&mut salt).map_err(|e| {
+        let err = if e.kind() == io::ErrorKind::UnexpectedEof {
+            UError::failure(format!("{name}: {}", i18n::tr("end of file")))
+        } else {
+            UError::from(e).with_name(name)
+        };
+        err.with_code(EXIT_TROUBLE)
+    })?;
+    Ok(salt)
+}
+
+/// Bytes of the `-S` argument `spec`
+fn buffer_size(spec: &str) -> UResult<usize> {
+    let error = |message: &str| {
+        UError::usage(i18n::tr(message).replace("{}", &shell_escape(spec.as_ref())))
+    };
+    let (number, suffix) = spec.split_at(spec.bytes().take_while(u8::is_ascii_digit).count());
+    if number.is_empty() {
+        return Err(error("invalid -S argument {}"));
+    }
+    let Ok(number) = number.parse::<u128>() else {
+        return Err(error("-S argument {} too large"));
+    };
+    let size = match suffix {
+        "%" => number
+            .checked_mul(u128::from(external::physical_memory()))
+            .map(|size| size / 100),
+        "b" => Some(number),
+        _ => {
+            let exp = match suffix {
+                "" | "K" | "k" => 1,
+                "M" => 2,
+                "G" => 3,
+                "T" => 4,
+                "P" => 5,
+                "E" => 6,
+                "Z" => 7,
+                "Y" => 8,
+                "R" => 9,
+                "Q" => 10,
+                _ => return Err(error("invalid suffix in -S argument {}")),
+            };
+            number.checked_mul(1024u128.pow(exp))
+        }
+    };
+    size.and_then(|size| usize::try_from(size).ok())
+        .ok_or_else(|| error("-S argument {} too large"))
+}
+
+/// Number of threads of the `--parallel` argument `spec`
+fn threads(spec: &str) -> UResult<usize> {
+    match spec.parse::<usize>() {
+        Ok(0) => Err(UError::usage("number in parallel must be nonzero")),
+        Ok(threads) => Ok(threads),
+        Err(_) => Err(UError::usage(
+            i18n::tr("invalid --parallel argument {}").replace("{}", &shell_escape(spec.as_ref())),
+        )),
+    }
+}
+
+/// How the lines are sorted besides their order
+fn options(args: &Args) -> UResult<Options> {
+    let buffer_size = match &args.buffer_size {
+        Some(spec) => buffer_size(spec)?.max(external::MIN_BUFFER_SIZE),
+        None => external::default_buffer_size(),
+    };
+    let threads = match &args.parallel {
+        Some(spec) => threads(spec)?,
+        None => JobsArg::default().jobs().min(8),
+    };
+    let temporary_dirs = if args.temporary_dirs.is_empty() {
+        vec![std::env::temp_dir()]
+    } else {
+        args.temporary_dirs.clone()
+    };
+    Ok(Options {
+        terminator: if args.is_zero_terminated {
+            b'\0'
+        } else {
+            b'\n'
+        },
+        is_unique: args.is_unique,
+        buffer_size,
+        temporary_dirs,
+        threads,
+    })
+}
+
+/// Diagnostic of `err`, reading the input `name` if it is a read error
+fn sort_error(err: Error, name: &str) -> UError {
+    let err = match err {
+        Error::Read(e) => UError::from(e).with_name(format!("{}: {name}", i18n::tr("read failed"))),
+        Error::Write(e) => output::write_error(e),
+        Error::Create(dir, e) => {
+            let name = i18n::tr("cannot create temporary file in {}")
+                .replace("{}", &shell_escape(dir.as_os_str()));
+            UError::from(e).with_name(name)
+        }
+        Error::Temporary(e) => UError::from(e).with_name(i18n::tr("temporary file")),
+    };
+    err.with_code(EXIT_TROUBLE)
+}
+
+/// The input files named in `list`, NUL-terminated
+fn read_file_list(list: &Path) -> UResult<Vec<PathBuf>> {
+    let name = quote(list.as_os_str());
+    let is_stdin = list.as_os_str() == "-";
+    let data = if is_stdin {
+        let mut data = Vec::new();
+        io::stdin().lock().read_to_end(&mut data).map(|_| data)
+    } else {
+        fs::read(list)
+    };
+    let data = data.map_err(|e| {
+        let name = format!("{}: {name}", i18n::tr("open failed"));
+        UError::from(e).with_name(name).with_code(EXIT_TROUBLE)
+    })?;
+    let data = data.strip_suffix(b"\0").unwrap_or(&data);
+    if data.is_empty() {
+        return Err(UError::failure(
+            i18n::tr("no input from {}").replace("{}", &shell_escape(list.as_os_str())),
+        )
+        .with_code(EXIT_TROUBLE));
+    }
+    let mut files = Vec::new();
+    for (i, file) in data.split(|&b| b == b'\0').enumerate() {
+        if file.is_empty() {
+            let message = format!(
+                "{name}:{}: {}",
+                i + 1,
+                i18n::tr("invalid zero-length file name")
+            );
+            return Err(UError::failure(message).with_code(EXIT_TROUBLE));
+        }
+        if is_stdin && file == b"-" {
+            return Err(UError::usage(
+                "when reading file names from stdin, no file name of '-' allowed",
+            ));
+        }
+        files.push(PathBuf::from(platform::os_string(file.to_vec())));
+    }
+    Ok(files)
+}
+
+/// The input files, the stdin if there are none
+fn files(args: &Args) -> UResult<Vec<PathBuf>> {
+    if let Some(list) = &args.files0_from {
+        read_file_list(list)
+    } else if args.files.is_empty() {
+        Ok(vec![PathBuf::from("-")])
+    } else {
+        Ok(args.files.clone())
+    }
+}
+
+/// The file of `-o`, which may be given again but not as another file
+fn output(args: &Args) -> UResult<Option<&Path>> {
+    let mut outputs = args.outputs.iter();
+    let output = outputs.next();
+    if outputs.any(|other| Some(other) != output) {
+        return Err(UError::usage("multiple output files specified"));
+    }
+    if output.is_some() && args.is_debug {
+        return Err(UError::usage(
+            i18n::tr("options '-{}' are incompatible").replace("{}", "o --debug"),
+        ));
+    }
+    Ok(output.map(PathBuf::as_path))
+}
+
+/// The option the input is checked by, `c` or quietly `C`
+fn check_mode(args: &Args) -> UResult<Option<char>> {
+    let letter = match (args.check, args.is_check_quiet) {
+        (Some(_), true) => return Err(UError::usage("options '-cC' are incompatible")),
+        (Some(Check::DiagnoseFirst), false) => 'c',
+        (Some(Check::Quiet), false) | (None, true) => 'C',
+        (None, false) => return Ok(None),
+    };
+    if args.is_debug {
+        return Err(UError::usage(
+            i18n::tr("options '-{}' are incompatible").replace("{}", &format!("{letter} --debug")),
+        ));
+    }
+    if !args.outputs.is_empty() {
+        return Err(UError::usage(
+            i18n::tr("options '-{}' are incompatible").replace("{}", &format!("{letter}o")),
+        ));
+    }
+    Ok(Some(letter))
+}
+
+/// Check that the lines of `file` are in `order`, reporting the first one
+/// that isn't unless `is_quiet`. Returns whether they are.
+fn check(file: &Path, order: &Order, options: &Options, is_quiet: bool) -> UResult<bool> {
+    let input = Input::from_operand(file);
+    let name = input
+        .quoted_name()
+        .map_or_else(|| "-".to_string(), |name| name.into_owned());
+    let reader = input.open_buffered().map_err(|e| {
+        let name = format!("{}: {name}", i18n::tr("open failed"));
+        UError::from(e).with_name(name).with_code(EXIT_TROUBLE)
+    })?;
+    let disorder = crate::disorder(reader, order, options.terminator, options.is_unique)
+        .map_err(|e| sort_error(Error::Read(e), &name))?;
+    let Some((number, line)) = disorder else {
+        return Ok(true);
+    };
+    if !is_quiet {
+        let mut message =
+            format!("{UTIL}: {name}:{number}: {}", i18n::tr("disorder: ")).into_bytes();
+        message.extend_from_slice(&line);
+        message.push(options.terminator);
+        // Like a diagnostic, a failure to print it is not reported
+        let _ = io::stderr().lock().write_all(&message);
+    }
+    Ok(false)
+}
+
+/// Sort the lines of the `files` into `output`, or the stdout if there is
+/// none. The output is opened only after all the input is read.
+fn run(
+    files: &[PathBuf],
+    order: &Order,
+    options: &Options,
+    output: Option<&Path>,
+    is_debug: bool,
+) -> UResult<()> {
+    let mut sorter = Sorter::new(order, options);
+    for file in files {
+        let input = Input::from_operand(file);
+        let name = input
+            .quoted_name()
+            .map_or_else(|| "-".to_string(), |name| name.into_owned());
+        let reader = input.open().map_err(|e| {
+            let name = format!("{}: {name}", i18n::tr("cannot read"));
+            UError::from(e).with_name(name).with_code(EXIT_TROUBLE)
+        })?;
+        sorter.read(reader).map_err(|e| sort_error(e, &name))?;
+    }
+    let mut out = match output {
+        Some(path) => {
+            let file = File::create(path).map_err(|e| {
+                let name = format!("{}: {}", i18n::tr("open failed"), quote(path.as_os_str()));
+                UError::from(e).with_name(name).with_code(EXIT_TROUBLE)
+            })?;
+            OutputSink::new(Box::new(file), false)
+        }
+        None => OutputSink::stdout(),
+    };
+    let sorted = if is_debug {
+        let mut out = Annotator::new(&mut out, order, options.terminator, options.is_unique);
+        sorter.finish(&mut out)
+    } else {
+        sorter.finish(&mut out)
+    };
+    sorted.map_err(|e| sort_error(e, ""))?;
+    out.finish().map_err(|e| e.with_code(EXIT_TROUBLE))
+}
+
+/// Run `sort` with the command line `args`, starting with the program name
+pub fn main(args: Vec<OsString>) -> ExitCode {
+    let mut status = Status::default();
+    let Some(args) = status.check(UTIL, response::expand(args)) else {
+        return status.into();
+    };
+    let args = posix::operands_last(&command(), args);
+    let matches = command().get_matches_from(args);
+    let args = Args::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+    trace::init(UTIL, false);
+    let Some(check_mode) = status.check(UTIL, check_mode(&args)) else {
+        return status.into();
+    };
+    let Some(output) = status.check(UTIL, output(&args)) else {
+        return status.into();
+    };
+    let Some(order) = status.check(UTIL, order(&args)) else {
+        return status.into();
+    };
+    let Some(options) = status.check(UTIL, options(&args)) else {
+        return status.into();
+    };
+    if args.is_debug {
+        let warnings = debug::warnings(&order, flags(&args), !args.keys.is_empty(), args.is_unique);
+        for warning in warnings {
+            error::notice(UTIL, &warning);
+        }
+    }
+    let Some(files) = status.check(UTIL, files(&args)) else {
+        return status.into();
+    };
+    match check_mode {
+        Some(letter) => {
+            let result = match files.get(1) {
+                Some(extra) => Err(UError::usage(
+                    i18n::tr("extra operand {} not allowed with -{}")
+                        .replacen("{}", &shell_escape(extra.as_os_str()), 1)
+                        .replacen("{}", &letter.to_string(), 1),
+                )),
+                None => check(&files[0], &order, &options, letter == 'C'),
+            };
+            match status.check(UTIL, result) {
+                Some(false) => ExitCode::from(EXIT_DISORDER),
+                _ => status.into(),
+            }
+        }
+        None => {
+            status.check(UTIL, run(&files, &order, &options, output, args.is_debug));
+            status.into()
+        }
+    }
+}
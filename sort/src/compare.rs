@@ -0,0 +1,549 @@
+//! Comparisons of keys other than by the collation of the locale
+
+use crate::key::is_blank;
+use md5::{Digest, Md5};
+use nyaa_core::locale::Locale;
+use std::cmp::Ordering;
+
+/// Abbreviated month names in the order of the months
+const MONTHS: [&[u8]; 12] = [
+    b"JAN", b"FEB", b"MAR", b"APR", b"MAY", b"JUN", b"JUL", b"AUG", b"SEP", b"OCT", b"NOV", b"DEC",
+];
+
+/// A decimal number, as compared by `-n`
+struct Decimal<'a> {
+    is_negative: bool,
+    /// Digits before the decimal point, without leading zeros
+    integer: &'a [u8],
+    /// Digits after the decimal point, without trailing zeros
+    fraction: &'a [u8],
+    /// The rest of the key after the number
+    rest: &'a [u8],
+}
+
+impl<'a> Decimal<'a> {
+    /// The number at the start of `key` after blanks, `-` and digits with
+    /// an optional decimal point. Keys without one are 0.
+    fn parse(key: &'a [u8]) -> Self {
+        let start = key.iter().take_while(|&&b| is_blank(b)).count();
+        let key = &key[start..];
+        let (is_negative, key) = match key.strip_prefix(b"-") {
+            Some(key) => (true, key),
+            None => (false, key),
+        };
+        let digits = |s: &'a [u8]| &s[..s.iter().take_while(|b| b.is_ascii_digit()).count()];
+        let integer = digits(key);
+        let (fraction, rest) = match key[integer.len()..].strip_prefix(b".") {
+            Some(rest) => {
+                let fraction = digits(rest);
+                (fraction, &rest[fraction.len()..])
+            }
+            None => (&[][..], &key[integer.len()..]),
+        };
+        let integer = &integer[integer.iter().take_while(|&&b| b == b'0').count()..];
+        let fraction =
+            &fraction[..fraction.len() - fraction.iter().rev().take_while(|&&b| b == b'0').count()];
+        Decimal {
+            // Zero is never negative
+            is_negative: is_negative && !(integer.is_empty() && fraction.is_empty()),
+            integer,
+            fraction,
+            rest,
+        }
+    }
+
+    fn is_zero(&self) -> bool {
+        self.integer.is_empty() && self.fraction.is_empty()
+    }
+
+    /// Power of 1024 of the SI suffix after the number, negative for
+    /// negative numbers and 0 for zero
+    fn unit_order(&self) -> i8 {
+        if self.is_zero() {
+            return 0;
+        }
+        let order = si_power(self.rest.first());
+        if self.is_negative {
+            -order
+        } else {
+            order
+        }
+    }
+
+    /// Order of the absolute values
+    fn compare_magnitude(&self, other: &Self) -> Ordering {
+        self.integer
+            .len()
+            .cmp(&other.integer.len())
+            .then_with(|| self.integer.cmp(other.integer))
+            .then_with(|| self.fraction.cmp(other.fraction))
+    }
+}
+
+/// Power of 1024 of the SI suffix `suffix`, 0 if it isn't one
+fn si_power(suffix: Option<&u8>) -> i8 {
+    match suffix {
+        Some(b'K' | b'k') => 1,
+        Some(b'M') => 2,
+        Some(b'G') => 3,
+        Some(b'T') => 4,
+        Some(b'P') => 5,
+        Some(b'E') => 6,
+        Some(b'Z') => 7,
+        Some(b'Y') => 8,
+        Some(b'R') => 9,
+        Some(b'Q') => 10,
+        _ => 0,
+    }
+}
+
+/// Length of the number at the start of `key` like `-n` reads it, with
+/// its SI suffix for `-h` in `is_human`, 0 if it starts with none
+pub fn number_len(key: &[u8], is_human: bool) -> usize {
+    let digits = |from: usize| {
+        key[from..]
+            .iter()
+            .take_while(|b| b.is_ascii_digit())
+            .count()
+    };
+    let sign = usize::from(key.first() == Some(&b'-'));
+    let integer = digits(sign);
+    let mut len = sign + integer;
+    let mut fraction = 0;
+    if key.get(len) == Some(&b'.') {
+        fraction = digits(len + 1);
+        len += 1 + fraction;
+    }
+    if integer + fraction == 0 {
+        return 0;
+    }
+    if is_human && si_power(key.get(len)) != 0 {
+        len += 1;
+    }
+    len
+}
+
+/// Order of the decimal numbers `a` and `b` start with
+pub fn numeric(a: &[u8], b: &[u8]) -> Ordering {
+    let (a, b) = (Decimal::parse(a), Decimal::parse(b));
+    match (a.is_negative, b.is_negative) {
+        (false, false) => a.compare_magnitude(&b),
+        (true, true) => b.compare_magnitude(&a),
+        (true, false) => Ordering::Less,
+        (false, true) => Ordering::Greater,
+    }
+}
+
+/// Order of the numbers `a` and `b` start with by their SI suffixes, so
+/// `2K` is less than `1M`, and then like [`numeric`]
+pub fn human_numeric(a: &[u8], b: &[u8]) -> Ordering {
+    let (x, y) = (Decimal::parse(a), Decimal::parse(b));
+    x.unit_order()
+        .cmp(&y.unit_order())
+        .then_with(|| numeric(a, b))
+}
+
+/// The floating point number at the start of `key` after white space, as
+/// `strtod` reads it, with infinities, NaNs and hexadecimal ones, and the
+/// length of `key` up to its end. Keys without one are `None`.
+fn float(key: &[u8]) -> Option<(f64, usize)> {
+    let start = key
+        .iter()
+        .take_while(|&&b| b.is_ascii_whitespace() || b == b'\x0b')
+        .count();
+    let (sign, start) = match key.get(start) {
+        Some(b'-') => (-1.0, start + 1),
+        Some(b'+') => (1.0, start + 1),
+        _ => (1.0, start),
+    };
+    let key = &key[start..];
+    let starts_with = |prefix: &[u8]| {
+        key.get(..prefix.len())
+            .is_some_and(|s| s.eq_ignore_ascii_case(prefix))
+    };
+    let digits = |s: &[u8], is_digit: fn(&u8) -> bool| s.iter().take_while(|b| is_digit(b)).count();
+    if starts_with(b"inf") {
+        let len = if starts_with(b"infinity") { 8 } else { 3 };
+        return Some((sign * f64::INFINITY, start + len));
+    }
+    if starts_with(b"nan") {
+        // Like `nan(0x7ff)`, with the characters of a payload
+        let payload = key[3..]
+            .strip_prefix(b"(")
+            .map(|rest| digits(rest, |&b| b.is_ascii_alphanumeric() || b == b'_'))
+            .filter(|&len| key.get(4 + len) == Some(&b')'));
+        let len = payload.map_or(3, |len| 5 + len);
+        return Some((f64::NAN.copysign(sign), start + len));
+    }
+    // The exponent after `key[len]`, if it has digits, and the length
+    // up to its end
+    let exponent = |len: usize| -> Option<(i32, usize)> {
+        let power = len + 1 + usize::from(matches!(key.get(len + 1), Some(b'-' | b'+')));
+        let power_len = digits(key.get(power..).unwrap_or_default(), u8::is_ascii_digit);
+        if power_len == 0 {
+            return None;
+        }
+        let value = key[power..power + power_len].iter().fold(0i32, |n, digit| {
+            n.saturating_mul(10).saturating_add(i32::from(digit - b'0'))
+        });
+        let value = if key[len + 1] == b'-' { -value } else { value };
+        Some((value, power + power_len))
+    };
+    if starts_with(b"0x") {
+        let hex = &key[2..];
+        let integer = digits(hex, u8::is_ascii_hexdigit);
+        let is_point = hex.get(integer) == Some(&b'.');
+        let fraction = if is_point {
+            digits(&hex[integer + 1..], u8::is_ascii_hexdigit)
+        } else {
+            0
+        };
+        if integer + fraction > 0 {
+            let fraction_start = integer + usize::from(is_point);
+            let mantissa = hex[..integer]
+                .iter()
+                .chain(&hex[fraction_start..fraction_start + fraction])
+                .filter_map(|&digit| char::from(digit).to_digit(16))
+                .fold(0.0, |n, digit| n * 16.0 + f64::from(digit));
+            let mut len = 2 + fraction_start + fraction;
+            let mut power = i32::try_from(fraction)
+                .unwrap_or(i32::MAX)
+                .saturating_mul(-4);
+            if let Some((value, end)) = exponent(len).filter(|_| matches!(key[len], b'p' | b'P')) {
+                power = power.saturating_add(value);
+                len = end;
+            }
+            return Some((sign * mantissa * 2f64.powi(power), start + len));
+        }
+    }
+    let integer = digits(key, u8::is_ascii_digit);
+    let mut len = integer;
+    if key.get(len) == Some(&b'.') {
+        let fraction = digits(&key[len + 1..], u8::is_ascii_digit);
+        if integer + fraction == 0 {
+            return None;
+        }
+        len += 1 + fraction;
+    } else if integer == 0 {
+        return None;
+    }
+    if let Some(b'e' | b'E') = key.get(len) {
+        if let Some((_, end)) = exponent(len) {
+            len = end;
+        }
+    }
+    let number: f64 = std::str::from_utf8(&key[..len]).ok()?.parse().ok()?;
+    Some((sign * number, start + len))
+}
+
+/// Length of the floating point number at the start of `key` with the
+/// white space before it, like `-g` reads it, 0 if it starts with none
+pub fn float_len(key: &[u8]) -> usize {
+    float(key).map_or(0, |(_, len)| len)
+}
+
+/// Order of the floating point numbers `a` and `b` start with. Keys
+/// without one go first, then NaNs, then the numbers.
+pub fn general_numeric(a: &[u8], b: &[u8]) -> Ordering {
+    match (float(a).map(|(a, _)| a), float(b).map(|(b, _)| b)) {
+        (None, None) => Ordering::Equal,
+        (None, Some(_)) => Ordering::Less,
+        (Some(_), None) => Ordering::Greater,
+        (Some(a), Some(b)) => match (a.is_nan(), b.is_nan()) {
+            (true, true) => a.to_bits().cmp(&b.to_bits()),
+            (true, false) => Ordering::Less,
+            (false, true) => Ordering::Greater,
+            (false, false) => a.partial_cmp(&b).unwrap_or(Ordering::Equal),
+        },
+    }
+}
+
+/// Number of the month whose abbreviated name `key` starts with after
+/// blanks, from 1, or 0 if it starts with none
+fn month_number(key: &[u8]) -> usize {
+    let key = &key[key.iter().take_while(|&&b| is_blank(b)).count()..];
+    let Some(name) = key.get(..3) else {
+        return 0;
+    };
+    MONTHS
+        .iter()
+        .position(|month| month.eq_ignore_ascii_case(name))
+        .map_or(0, |i| i + 1)
+}
+
+/// Length of the month name at the start of `key`, 0 if it starts with
+/// none
+pub fn month_len(key: &[u8]) -> usize {
+    if key.first().is_some_and(|&b| is_blank(b)) || month_number(key) == 0 {
+        0
+    } else {
+        3
+    }
+}
+
+/// Order of the months `a` and `b` start with, after keys starting with
+/// none
+pub fn month(a: &[u8], b: &[u8]) -> Ordering {
+    month_number(a).cmp(&month_number(b))
+}
+
+/// Order of `a` and `b` as versions, like `filevercmp` of gnulib: runs of
+/// digits compare as numbers, other characters with letters first and `~`
+/// before everything, even the end. Suffixes like `.tar.gz` are compared
+/// only if the rest is equal.
+pub fn version(a: &[u8], b: &[u8]) -> Ordering {
+    // Empty keys first, then `.`, `..` and other names starting with `.`
+    let rank = |s: &[u8]| match s {
+        [] => 0,
+        b"." => 1,
+        b".." => 2,
+        [b'.', ..] => 3,
+        _ => 4,
+    };
+    match rank(a).cmp(&rank(b)) {
+        Ordering::Equal if rank(a) >= 3 => {}
+        ordering => return ordering,
+    }
+    let (a_prefix, b_prefix) = (suffix_start(a), suffix_start(b));
+    match compare_versions(&a[..a_prefix], &b[..b_prefix]) {
+        Ordering::Equal if a_prefix < a.len() || b_prefix < b.len() => compare_versions(a, b),
+        ordering => ordering,
+    }
+}
+
+/// Index of the suffix of `name`, the longest match of
+/// `(\.[A-Za-z~][A-Za-z0-9~]*)*$`, which is never the whole name
+fn suffix_start(name: &[u8]) -> usize {
+    let mut start = 0;
+    let mut i = 0;
+    while i < name.len() {
+        i += 1;
+        start = i;
+        while i + 1 < name.len()
+            && name[i] == b'.'
+            && (name[i + 1].is_ascii_alphabetic() || name[i + 1] == b'~')
+        {
+            i += 2;
+            while i < name.len() && (name[i].is_ascii_alphanumeric() || name[i] == b'~') {
+                i += 1;
+            }
+        }
+    }
+    start
+}
+
+/// Weight of the character of `s` at `i` outside of digits, where the
+/// end is before all characters but `~`
+fn version_weight(s: &[u8], i: usize) -> i32 {
+    match s.get(i) {
+        None => -1,
+        Some(c) if c.is_ascii_digit() => 0,
+        Some(c) if c.is_ascii_alphabetic() => i32::from(*c),
+        Some(b'~') => -2,
+        Some(&c) => i32::from(c) + 256,
+    }
+}
+
+/// Order of the versions `a` and `b` by the algorithm of Debian
+fn compare_versions(a: &[u8], b: &[u8]) -> Ordering {
+    let is_digit = |s: &[u8], i: usize| s.get(i).is_some_and(u8::is_ascii_digit);
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() || j < b.len() {
+        while (i < a.len() && !is_digit(a, i)) || (j < b.len() && !is_digit(b, j)) {
+            let (x, y) = (version_weight(a, i), version_weight(b, j));
+            if x != y {
+                return x.cmp(&y);
+            }
+            i += 1;
+            j += 1;
+        }
+        i += a[i..].iter().take_while(|&&c| c == b'0').count();
+        j += b[j..].iter().take_while(|&&c| c == b'0').count();
+        let mut first_diff = Ordering::Equal;
+        while is_digit(a, i) && is_digit(b, j) {
+            if first_diff == Ordering::Equal {
+                first_diff = a[i].cmp(&b[j]);
+            }
+            i += 1;
+            j += 1;
+        }
+        if is_digit(a, i) {
+            return Ordering::Greater;
+        }
+        if is_digit(b, j) {
+            return Ordering::Less;
+        }
+        if first_diff != Ordering::Equal {
+            return first_diff;
+        }
+    }
+    Ordering::Equal
+}
+
+/// Order of the MD5 hashes of `salt` followed by `a` and by `b`, then of
+/// `a` and `b` if they are equal, which is the order of `sort -R` in the
+/// C locale
+pub fn random(salt: &[u8], a: &[u8], b: &[u8]) -> Ordering {
+    let hash = |key: &[u8]| Md5::new().chain_update(salt).chain_update(key).finalize();
+    hash(a)
+        .as_slice()
+        .cmp(hash(b).as_slice())
+        .then_with(|| a.cmp(b))
+}
+
+/// Order of `a` and `b` in `locale` with lower case letters taken for
+/// upper case ones
+pub fn folded(locale: Locale, a: &[u8], b: &[u8]) -> Ordering {
+    if locale == Locale::C {
+        return a
+            .iter()
+            .map(u8::to_ascii_uppercase)
+            .cmp(b.iter().map(u8::to_ascii_uppercase));
+    }
+    locale.compare(&fold(locale, a), &fold(locale, b))
+}
+
+/// `key` with its letters in upper case, only the ASCII ones if it isn't
+/// UTF-8
+pub fn fold(locale: Locale, key: &[u8]) -> Vec<u8> {
+    match std::str::from_utf8(key) {
+        Ok(text) => text
+            .chars()
+            .map(|c| locale.to_upper(c))
+            .collect::<String>()
+            .into_bytes(),
+        Err(_) => key.to_ascii_uppercase(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn numbers() {
+        let sorted = [
+            &b"-10"[..],
+            b"-9.5",
+            b"-0.01",
+            b"-0",
+            b"",
+            b"0.00",
+            b"abc",
+            b".5",
+            b"  0.50x",
+            b"1",
+            b"0001.000",
+            b"2",
+            b"10",
+            b"10.01",
+        ];
+        for pair in sorted.windows(2) {
+            let ordering = numeric(pair[0], pair[1]);
+            assert_ne!(ordering, Ordering::Greater, "{pair:?}");
+        }
+        assert_eq!(numeric(b"-0", b"0"), Ordering::Equal);
+        assert_eq!(numeric(b"1", b"0001.000"), Ordering::Equal);
+        assert_eq!(numeric(b"-9.5", b"-10"), Ordering::Greater);
+        assert_eq!(numeric(b"+1", b"0"), Ordering::Equal);
+    }
+
+    fn assert_sorted(compare: fn(&[u8], &[u8]) -> Ordering, sorted: &[&str]) {
+        for pair in sorted.windows(2) {
+            let ordering = compare(pair[0].as_bytes(), pair[1].as_bytes());
+            assert_eq!(ordering, Ordering::Less, "{pair:?}");
+        }
+    }
+
+    #[test]
+    fn human_numbers() {
+        assert_sorted(
+            human_numeric,
+            &[
+                "-1G", "-3M", "-5K", "-1", "0K", "5", "1.5K", " 2K", "10K", "1M", "1G",
+            ],
+        );
+        assert_eq!(human_numeric(b"0M", b"0"), Ordering::Equal);
+        assert_eq!(human_numeric(b"1024", b"1k"), Ordering::Less);
+    }
+
+    #[test]
+    fn general_numbers() {
+        assert_sorted(
+            general_numeric,
+            &[
+                "abc", "nan", "-nan", "-inf", "-1e3", "0x", "1e", " .5e1", "0x1.8p2", "0X10",
+                "1e3", "INF",
+            ],
+        );
+        assert_eq!(general_numeric(b"-0", b"0.0"), Ordering::Equal);
+        assert_eq!(general_numeric(b"", b"x"), Ordering::Equal);
+        assert_eq!(general_numeric(b"1.e2", b"100"), Ordering::Equal);
+    }
+
+    #[test]
+    fn lengths_of_numbers_and_months() {
+        assert_eq!(number_len(b"-12.50x", false), 6);
+        assert_eq!(number_len(b"1.K", true), 3);
+        assert_eq!(number_len(b"0Kb", true), 2);
+        assert_eq!(number_len(b"-.", false), 0);
+        assert_eq!(float_len(b" -1.5e+3x"), 8);
+        assert_eq!(float_len(b"1e+"), 1);
+        assert_eq!(float_len(b"0x1.8p-1p"), 8);
+        assert_eq!(float_len(b"0x"), 1);
+        assert_eq!(float_len(b"infinity"), 8);
+        assert_eq!(float_len(b"nan(12)"), 7);
+        assert_eq!(float_len(b"nan(12"), 3);
+        assert_eq!(float_len(b"."), 0);
+        assert_eq!(month_len(b"Janvier"), 3);
+        assert_eq!(month_len(b"Ja"), 0);
+    }
+
+    #[test]
+    fn months() {
+        assert_sorted(month, &["ja", " jan", "Feb", "MARCH", "Sept", "december"]);
+        assert_eq!(month(b"xyz", b""), Ordering::Equal);
+    }
+
+    #[test]
+    fn versions() {
+        assert_sorted(
+            version,
+            &[
+                "",
+                ".",
+                "..",
+                ".a",
+                "1.2~rc1",
+                "1.2",
+                "1.2a",
+                "1.10",
+                "a~",
+                "a",
+                "a1",
+                "foo-1.2.tar.gz",
+                "foo-1.10.tar.bz2",
+                "foo-1.10.tar.gz",
+            ],
+        );
+        assert_eq!(version(b"01", b"1"), Ordering::Equal);
+    }
+
+    #[test]
+    fn random_order_is_the_hashes() {
+        // MD5 of 16 zero bytes and the letter: a 0531..., b 2864..., c fb8d...
+        assert_sorted(|a, b| random(&[0; 16], a, b), &["a", "b", "c"]);
+        assert_eq!(random(&[0; 16], b"a", b"a"), Ordering::Equal);
+    }
+
+    #[test]
+    fn folds_case() {
+        assert_eq!(folded(Locale::C, b"abc", b"ABC"), Ordering::Equal);
+        // Folded to upper case, `_` sorts after letters
+        assert_eq!(folded(Locale::C, b"a", b"_"), Ordering::Less);
+        assert_eq!(
+            folded(Locale::Unicode, "жук".as_bytes(), "ЖУК".as_bytes()),
+            Ordering::Equal
+        );
+    }
+}
@@ -0,0 +1,329 @@
+//! Diagnostics of `--debug`: warnings about questionable keys and options,
+//! and the output lines followed by underlines of the parts of them each
+//! key compares, like in coreutils
+
+use crate::compare;
+use crate::key::{is_blank, Flags, Key};
+use crate::Order;
+use nyaa_core::i18n::tr;
+use nyaa_core::locale::{self, Category, Locale};
+use nyaa_core::quote::shell_escape;
+use std::io::{self, Write};
+use unicode_width::UnicodeWidthChar;
+
+/// Warnings about `order`, the first of them the collation it uses.
+/// `global` are the flags of the options outside of keys, `has_keys`
+/// whether keys were given with `-k`.
+pub fn warnings(order: &Order, global: Flags, has_keys: bool, is_unique: bool) -> Vec<String> {
+    let mut warnings = vec![match locale::name(Category::Collate).as_deref() {
+        None | Some("C" | "POSIX") => {
+            tr("text ordering performed using simple byte comparison").to_string()
+        }
+        Some(name) => tr("text ordering performed using {} sorting rules")
+            .replace("{}", &shell_escape(name.as_ref())),
+    }];
+    // The global flags that no key takes up
+    let mut unused = global;
+    let (mut is_numeric, mut is_general_numeric) = (false, false);
+    let (mut is_numeric_span, mut is_general_numeric_span) = (false, false);
+    for (key, number) in order.keys.iter().zip(1..) {
+        let flags = key.flags;
+        let is_any_numeric = flags.is_numeric || flags.is_general_numeric || flags.is_human_numeric;
+        if flags.is_general_numeric {
+            is_general_numeric = true;
+        } else if is_any_numeric {
+            is_numeric = true;
+        }
+        let is_zero_width = key.end.is_some_and(|(field, _)| field < key.start.0);
+        if is_zero_width {
+            warnings.push(
+                tr("key {} has zero width and will be ignored").replace("{}", &number.to_string()),
+            );
+        }
+        // Keys within the first field, like `1.2,1.3`, start after blanks
+        let is_line_offset = matches!(key.end, Some((0, char)) if char != 0);
+        let end_char = key.end.map_or(0, |(_, char)| char);
+        let are_blanks_significant = !flags.skips_start_blanks
+            && (!(is_any_numeric || flags.is_month) || key.start.1 != 0)
+            || !flags.skips_end_blanks && end_char != 0;
+        if !is_zero_width
+            && has_keys
+            && order.separator.is_none()
+            && !is_line_offset
+            && are_blanks_significant
+        {
+            warnings.push(
+                tr("leading blanks are significant in key {}; consider also specifying 'b'")
+                    .replace("{}", &number.to_string()),
+            );
+        }
+        let is_span = key.end.is_none_or(|(field, _)| key.start.0 < field);
+        if has_keys && is_any_numeric && is_span {
+            warnings.push(
+                tr("key {} is numeric and spans multiple fields")
+                    .replace("{}", &number.to_string()),
+            );
+            if flags.is_general_numeric {
+                is_general_numeric_span = true;
+            } else {
+                is_numeric_span = true;
+            }
+        }
+        unused.skips_start_blanks &= !flags.skips_start_blanks;
+        unused.skips_end_blanks &= !flags.skips_end_blanks;
+        unused.is_fold &= !flags.is_fold;
+        unused.is_numeric &= !flags.is_numeric;
+        unused.is_general_numeric &= !flags.is_general_numeric;
+        unused.is_human_numeric &= !flags.is_human_numeric;
+        unused.is_month &= !flags.is_month;
+        unused.is_random &= !flags.is_random;
+        unused.is_version &= !flags.is_version;
+        unused.is_reverse &= !flags.is_reverse;
+    }
+    // Separators that numbers may take for part of them
+    let mut is_number_warned = false;
+    if is_numeric_span || is_general_numeric_span {
+        let message = match order.separator {
+            Some(b'.') => {
+                is_number_warned = true;
+                Some("field separator {} is treated as a decimal point in numbers")
+            }
+            Some(b'-') => Some("field separator {} is treated as a minus sign in numbers"),
+            Some(b'+') if is_general_numeric_span => {
+                Some("field separator {} is treated as a plus sign in numbers")
+            }
+            _ => None,
+        };
+        if let (Some(message), Some(separator)) = (message, order.separator) {
+            let separator = shell_escape(char::from(separator).to_string().as_ref());
+            warnings.push(tr(message).replace("{}", &separator));
+        }
+    }
+    if (is_numeric || is_general_numeric) && !is_number_warned {
+        let message = if order.separator == Some(b'.') {
+            "numbers use {} as a decimal point in this locale"
+        } else {
+            "note numbers use {} as a decimal point in this locale"
+        };
+        warnings.push(tr(message).replace("{}", &shell_escape(".".as_ref())));
+    }
+    let is_stable = order.is_stable || is_unique;
+    let has_unused = unused
+        != Flags {
+            is_reverse: unused.is_reverse,
+            ..Flags::default()
+        };
+    if has_unused || unused.is_reverse && is_stable && !order.keys.is_empty() {
+        let letters = Flags {
+            is_reverse: unused.is_reverse && is_stable,
+            ..unused
+        }
+        .letters();
+        let message = if letters.len() == 1 {
+            "option '-{}' is ignored"
+        } else {
+            "options '-{}' are ignored"
+        };
+        warnings.push(tr(message).replace("{}", &letters));
+    }
+    if unused.is_reverse && !is_stable && !order.keys.is_empty() {
+        warnings.push(tr("option '-r' only applies to last-resort comparison").to_string());
+    }
+    warnings
+}
+
+/// Width of `text` on a terminal in `locale`, where tabs, shown as `>`,
+/// are one column wide and other control characters none
+fn width(text: &[u8], locale: Locale) -> usize {
+    let tabs = text.iter().filter(|&&b| b == b'\t').count();
+    let width: usize = match locale {
+        Locale::C => text.iter().filter(|b| !b.is_ascii_control()).count(),
+        Locale::Unicode => text
+            .utf8_chunks()
+            .map(|chunk| {
+                let valid: usize = chunk
+                    .valid()
+                    .chars()
+                    .filter(|c| !c.is_control())
+                    .map(|c| c.width().unwrap_or(0))
+                    .sum();
+                valid + chunk.invalid().len()
+            })
+            .sum(),
+    };
+    width + tabs
+}
+
+/// Output of `--debug`: every line written to it with its tabs shown as
+/// `>`, each followed by underlines of the parts its keys compare, then of
+/// the whole line if it is compared as a last resort
+pub struct Annotator<'a, W: Write> {
+    out: W,
+    order: &'a Order,
+    /// Byte ending the lines written
+    terminator: u8,
+    /// Whether lines with equal keys are compared whole
+    is_last_resort: bool,
+    /// Locale of the widths of characters
+    locale: Locale,
+    /// The line written so far
+    line: Vec<u8>,
+}
+
+impl<'a, W: Write> Annotator<'a, W> {
+    pub fn new(out: W, order: &'a Order, terminator: u8, is_unique: bool) -> Self {
+        Annotator {
+            out,
+            order,
+            terminator,
+            is_last_resort: !(is_unique || order.is_stable),
+            locale: Locale::ctype(),
+            line: Vec::new(),
+        }
+    }
+
+    /// Where the part of `line` that `key` compares starts and ends
+    fn bounds(&self, key: &Key, line: &[u8]) -> (usize, usize) {
+        let (mut start, end) = key.bounds(line, self.order.separator);
+        let flags = key.flags;
+        let is_numeric = flags.is_numeric || flags.is_general_numeric || flags.is_human_numeric;
+        if !(is_numeric || flags.is_month) {
+            return (start, end);
+        }
+        // Blanks before the key are skipped, past its end if it ends
+        // before it starts
+        let limit = if end < start { line.len() } else { end };
+        start += line[start..limit]
+            .iter()
+            .take_while(|&&b| is_blank(b))
+            .count();
+        if end < start {
+            return (start, end);
+        }
+        let key = &line[start..end];
+        let len = if flags.is_month {
+            compare::month_len(key)
+        } else if flags.is_general_numeric {
+            compare::float_len(key)
+        } else {
+            compare::number_len(key, flags.is_human_numeric)
+        };
+        (start, start + len)
+    }
+
+    /// Write the underline of `line[start..end]`
+    fn underline(&mut self, line: &[u8], start: usize, end: usize) -> io::Result<()> {
+        let offset = width(&line[..start.min(line.len())], self.locale);
+        let width = width(&line[start..end.max(start)], self.locale);
+        let mut underline = " ".repeat(offset);
+        if width == 0 {
+            underline.push_str(tr("^ no match for key"));
+        } else {
+            underline.push_str(&"_".repeat(width));
+        }
+        underline.push('\n');
+        self.out.write_all(underline.as_bytes())
+    }
+
+    fn annotate(&mut self) -> io::Result<()> {
+        let line = std::mem::take(&mut self.line);
+        let mut shown: Vec<u8> = line
+            .iter()
+            .map(|&b| if b == b'\t' { b'>' } else { b })
+            .collect();
+        shown.push(b'\n');
+        self.out.write_all(&shown)?;
+        for key in &self.order.keys {
+            let (start, end) = self.bounds(key, &line);
+            self.underline(&line, start, end)?;
+        }
+        if self.order.keys.is_empty() || self.is_last_resort {
+            self.underline(&line, 0, line.len())?;
+        }
+        self.line = line;
+        self.line.clear();
+        Ok(())
+    }
+}
+
+impl<W: Write> Write for Annotator<'_, W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut rest = buf;
+        while let Some(end) = memchr::memchr(self.terminator, rest) {
+            self.line.extend_from_slice(&rest[..end]);
+            self.annotate()?;
+            rest = &rest[end + 1..];
+        }
+        self.line.extend_from_slice(rest);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn order(keys: &[&str], separator: Option<u8>) -> Order {
+        Order {
+            keys: keys.iter().map(|spec| Key::parse(spec).unwrap()).collect(),
+            separator,
+            locale: Locale::C,
+            is_reverse: false,
+            is_stable: false,
+            salt: [0; 16],
+        }
+    }
+
+    fn annotated(input: &str, order: &Order) -> String {
+        let mut out = Vec::new();
+        let mut annotator = Annotator::new(&mut out, order, b'\n', false);
+        annotator.locale = Locale::C;
+        annotator.write_all(input.as_bytes()).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn underlines_keys_and_whole_lines() {
+        let order = order(&["2n", "1,1"], None);
+        assert_eq!(
+            annotated("x\t-0.5kb\n\n", &order),
+            "x>-0.5kb\n  ____\n_\n________\n\n^ no match for key\n^ no match for key\n^ no match for key\n"
+        );
+    }
+
+    #[test]
+    fn warns_about_keys() {
+        let mut order = order(&["2,1", "1.2", "3n"], Some(b'-'));
+        let global = Flags {
+            is_numeric: true,
+            is_reverse: true,
+            ..Flags::default()
+        };
+        assert_eq!(
+            warnings(&order, global, true, false)[1..],
+            [
+                "key 1 has zero width and will be ignored",
+                "key 3 is numeric and spans multiple fields",
+                "field separator '-' is treated as a minus sign in numbers",
+                "note numbers use '.' as a decimal point in this locale",
+                "option '-r' only applies to last-resort comparison",
+            ]
+        );
+        order.separator = None;
+        order.is_stable = true;
+        assert_eq!(
+            warnings(&order, global, true, false)[1..],
+            [
+                "key 1 has zero width and will be ignored",
+                "leading blanks are significant in key 2; consider also specifying 'b'",
+                "key 3 is numeric and spans multiple fields",
+                "note numbers use '.' as a decimal point in this locale",
+                "option '-r' is ignored",
+            ]
+        );
+    }
+}
@@ -0,0 +1,432 @@
+//! Sorting of inputs larger than memory. Lines are read into a buffer of
+//! a limited size, and whenever it fills up its lines are sorted and
+//! spilled to a temporary file as a sorted run. The runs are merged at
+//! most [`MERGE_WIDTH`] at a time, so few files are open at once, and the
+//! last merge writes the output. Input that fits into the buffer is
+//! sorted in memory without temporary files.
+//!
+//! Runs are merged with the lines of earlier runs first among equal ones,
+//! so the order stays stable and `-u` keeps the first of equal lines like
+//! in memory.
+//!
+//! On Unix the temporary files are removed as soon as they are created
+//! and used through their open descriptors, so they don't outlive `sort`
+//! even when it is killed.
+
+use crate::{lines, sort, Order};
+use nyaa_core::sysinfo::{Live, MemInfo};
+use std::cmp::Ordering;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, BufWriter, Read, Seek, Write};
+use std::path::PathBuf;
+
+/// Number of runs merged at once
+pub const MERGE_WIDTH: usize = 16;
+
+/// Size of the reads of the input
+const BLOCK_SIZE: usize = 128 * 1024;
+
+/// Smallest buffer size that makes sense, a read of the input, so small
+/// inputs are sorted in memory however small a buffer is asked for
+pub const MIN_BUFFER_SIZE: usize = BLOCK_SIZE;
+
+/// Failure of a [`Sorter`]
+#[derive(Debug)]
+pub enum Error {
+    Read(io::Error),
+    Write(io::Error),
+    /// No temporary file could be created in the directory
+    Create(PathBuf, io::Error),
+    /// Writing or reading back a temporary file failed
+    Temporary(io::Error),
+}
+
+/// How lines are sorted besides their order
+#[derive(Clone, Debug)]
+pub struct Options {
+    /// Byte ending the lines
+    pub terminator: u8,
+    /// Whether only the first of the lines with equal keys is output
+    pub is_unique: bool,
+    /// Bytes of memory for the lines sorted at once, with their index
+    pub buffer_size: usize,
+    /// Directories the temporary files are created in, in turn
+    pub temporary_dirs: Vec<PathBuf>,
+    /// Threads sorting the lines in memory
+    pub threads: usize,
+}
+
+/// The size of the buffer without `-S`: three quarters of the memory
+/// available, or of an eighth of all memory if that is more, like in
+/// coreutils. Where the memory can't be read, 1/8 of 1 GiB is taken.
+pub fn default_buffer_size() -> usize {
+    let memory = match MemInfo::read(&Live) {
+        Ok(info) => info.available().max(info.total() / 8),
+        Err(_) => physical_memory() / 8,
+    };
+    usize::try_from(memory / 4 * 3).unwrap_or(usize::MAX)
+}
+
+/// Bytes of physical memory, 1 GiB where it can't be read
+pub fn physical_memory() -> u64 {
+    MemInfo::read(&Live).map_or(1 << 30, |info| info.total())
+}
+
+/// A sorted run spilled to a temporary file
+struct Run {
+    file: File,
+    /// How many merges the run went through, so runs are merged with runs
+    /// of the same size
+    level: usize,
+    /// The file, removed when the run is dropped where it can't be
+    /// removed while it is open
+    #[cfg(not(unix))]
+    path: PathBuf,
+}
+
+impl Run {
+    fn new(file: File, path: PathBuf) -> Self {
+        // Removed right away, the open file lives on until it is closed
+        #[cfg(unix)]
+        let _ = fs::remove_file(&path);
+        Run {
+            file,
+            level: 0,
+            #[cfg(not(unix))]
+            path,
+        }
+    }
+}
+
+#[cfg(not(unix))]
+impl Drop for Run {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+/// The lines of a run, read back one at a time
+struct Source {
+    reader: BufReader<File>,
+    /// The current line, without its terminator
+    line: Vec<u8>,
+}
+
+impl Source {
+    /// Read the next line, returning false at the end of the run
+    fn advance(&mut self, terminator: u8) -> io::Result<bool> {
+        self.line.clear();
+        if self.reader.read_until(terminator, &mut self.line)? == 0 {
+            return Ok(false);
+        }
+        if self.line.last() == Some(&terminator) {
+            self.line.pop();
+        }
+        Ok(true)
+    }
+}
+
+/// Sorts all lines read into it, spilling them to temporary files when
+/// they don't fit into its buffer
+pub struct Sorter<'a> {
+    order: &'a Order,
+    options: &'a Options,
+    /// Lines read and not yet sorted, the last of them possibly without
+    /// its terminator
+    buffer: Vec<u8>,
+    /// Number of terminated lines in the buffer
+    count: usize,
+    /// The runs spilled so far, in the order of the input, with levels
+    /// that never increase towards the end
+    runs: Vec<Run>,
+    /// Number of temporary files created, which names the next one
+    created: usize,
+}
+
+impl<'a> Sorter<'a> {
+    pub fn new(order: &'a Order, options: &'a Options) -> Self {
+        Sorter {
+            order,
+            options,
+            buffer: Vec::new(),
+            count: 0,
+            runs: Vec::new(),
+            created: 0,
+        }
+    }
+
+    /// Memory the buffered lines take up, with two slices for each in the
+    /// index sorting them
+    fn used(&self) -> usize {
+        self.buffer.len() + self.count * 2 * size_of::<&[u8]>()
+    }
+
+    /// Read all lines of `input`, the last one ended if it isn't
+    pub fn read(&mut self, mut input: impl Read) -> Result<(), Error> {
+        let terminator = self.options.terminator;
+        loop {
+            if self.used() >= self.options.buffer_size {
+                self.spill()?;
+            }
+            let start = self.buffer.len();
+            self.buffer.resize(start + BLOCK_SIZE, 0);
+            let n = match input.read(&mut self.buffer[start..]) {
+                Ok(n) => n,
+                Err(e) => {
+                    self.buffer.truncate(start);
+                    if e.kind() == io::ErrorKind::Interrupted {
+                        continue;
+                    }
+                    return Err(Error::Read(e));
+                }
+            };
+            self.buffer.truncate(start + n);
+            if n == 0 {
+                break;
+            }
+            self.count += memchr::memchr_iter(terminator, &self.buffer[start..]).count();
+        }
+        if self.buffer.last().is_some_and(|&last| last != terminator) {
+            self.buffer.push(terminator);
+            self.count += 1;
+        }
+        Ok(())
+    }
+
+    /// Sort the terminated lines of the buffer into a new run
+    fn spill(&mut self) -> Result<(), Error> {
+        let terminator = self.options.terminator;
+        // A line longer than the buffer is read on until its end
+        let Some(end) = memchr::memrchr(terminator, &self.buffer).map(|i| i + 1) else {
+            return Ok(());
+        };
+        let mut run = self.create()?;
+        let mut lines = lines(&self.buffer[..end], terminator);
+        sort(
+            &mut lines,
+            self.order,
+            self.options.is_unique,
+            self.options.threads,
+        );
+        let mut out = BufWriter::new(&mut run.file);
+        for line in lines {
+            out.write_all(line)
+                .and_then(|()| out.write_all(&[terminator]))
+                .map_err(Error::Temporary)?;
+        }
+        out.flush().map_err(Error::Temporary)?;
+        drop(out);
+        self.buffer.drain(..end);
+        self.count = 0;
+        self.runs.push(run);
+        // Merge the last runs of the same level, like carries of a counter
+        while self.runs.len() >= MERGE_WIDTH {
+            let first = self.runs.len() - MERGE_WIDTH;
+            if self.runs[first].level != self.runs[self.runs.len() - 1].level {
+                break;
+            }
+            self.merge_runs(first)?;
+        }
+        Ok(())
+    }
+
+    /// Merge the runs from `first` on into one
+    fn merge_runs(&mut self, first: usize) -> Result<(), Error> {
+        let runs: Vec<Run> = self.runs.drain(first..).collect();
+        let level = runs.iter().map(|run| run.level).max().unwrap_or(0) + 1;
+        let mut run = self.create()?;
+        run.level = level;
+        let mut out = BufWriter::new(&mut run.file);
+        self.merge(runs, &mut out, Error::Temporary)?;
+        out.flush().map_err(Error::Temporary)?;
+        drop(out);
+        self.runs.insert(first, run);
+        Ok(())
+    }
+
+    /// A new empty run in the next temporary directory
+    fn create(&mut self) -> Result<Run, Error> {
+        let dirs = &self.options.temporary_dirs;
+        let dir = &dirs[self.created % dirs.len()];
+        loop {
+            let name = format!("nyaa-sort-{}-{}", std::process::id(), self.created);
+            self.created += 1;
+            let path = dir.join(name);
+            let file = File::options()
+                .read(true)
+                .write(true)
+                .create_new(true)
+                .open(&path);
+            match file {
+                Ok(file) => return Ok(Run::new(file, path)),
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+                Err(e) => return Err(Error::Create(dir.clone(), e)),
+            }
+        }
+    }
+
+    /// Merge `runs` into `out`, whose write errors are mapped to an error
+    /// by `write_error`
+    fn merge(
+        &self,
+        runs: Vec<Run>,
+        out: &mut impl Write,
+        write_error: fn(io::Error) -> Error,
+    ) -> Result<(), Error> {
+        let terminator = self.options.terminator;
+        let is_unique = self.options.is_unique;
+        let compare = |a: &[u8], b: &[u8]| match is_unique {
+            true => self.order.compare_keys(a, b),
+            false => self.order.compare(a, b),
+        };
+        // The sources are dropped before the runs, whose files may only
+        // be removed when they are closed
+        let mut sources = Vec::with_capacity(runs.len());
+        for run in &runs {
+            let mut file = run.file.try_clone().map_err(Error::Temporary)?;
+            file.rewind().map_err(Error::Temporary)?;
+            let mut source = Source {
+                reader: BufReader::new(file),
+                line: Vec::new(),
+            };
+            if source.advance(terminator).map_err(Error::Temporary)? {
+                sources.push(source);
+            }
+        }
+        // The last line written, which equal ones are left out after
+        let mut last: Option<Vec<u8>> = None;
+        // The first source of the smallest lines
+        while let Some(min) = (0..sources.len()).reduce(|min, i| {
+            match compare(&sources[i].line, &sources[min].line) {
+                Ordering::Less => i,
+                _ => min,
+            }
+        }) {
+            let line = &sources[min].line;
+            let is_duplicate = is_unique
+                && last
+                    .as_deref()
+                    .is_some_and(|last| compare(last, line) == Ordering::Equal);
+            if !is_duplicate {
+                out.write_all(line)
+                    .and_then(|()| out.write_all(&[terminator]))
+                    .map_err(write_error)?;
+                if is_unique {
+                    let last = last.get_or_insert_with(Vec::new);
+                    last.clear();
+                    last.extend_from_slice(line);
+                }
+            }
+            if !sources[min].advance(terminator).map_err(Error::Temporary)? {
+                sources.remove(min);
+            }
+        }
+        Ok(())
+    }
+
+    /// Write all lines read, sorted, to `out`
+    pub fn finish(mut self, out: &mut impl Write) -> Result<(), Error> {
+        let terminator = self.options.terminator;
+        if self.runs.is_empty() {
+            let mut lines = lines(&self.buffer, terminator);
+            sort(
+                &mut lines,
+                self.order,
+                self.options.is_unique,
+                self.options.threads,
+            );
+            for line in lines {
+                out.write_all(line)
+                    .and_then(|()| out.write_all(&[terminator]))
+                    .map_err(Error::Write)?;
+            }
+            return Ok(());
+        }
+        self.spill()?;
+        while self.runs.len() > MERGE_WIDTH {
+            let rest = self.runs.split_off(MERGE_WIDTH);
+            self.merge_runs(0)?;
+            self.runs.extend(rest);
+        }
+        let runs = std::mem::take(&mut self.runs);
+        self.merge(runs, out, Error::Write)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::key::Key;
+    use nyaa_core::locale::Locale;
+
+    fn sort_external(input: &str, keys: &[&str], is_unique: bool, buffer_size: usize) -> String {
+        let order = Order {
+            keys: keys.iter().map(|spec| Key::parse(spec).unwrap()).collect(),
+            separator: None,
+            locale: Locale::C,
+            is_reverse: false,
+            is_stable: false,
+            salt: [0; 16],
+        };
+        let options = Options {
+            terminator: b'\n',
+            is_unique,
+            buffer_size,
+            temporary_dirs: vec![std::env::temp_dir()],
+            threads: 1,
+        };
+        let mut sorter = Sorter::new(&order, &options);
+        sorter.read(Trickle(input.as_bytes())).unwrap();
+        let mut out = Vec::new();
+        sorter.finish(&mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    /// Input read 7 bytes at a time, so lines are split across reads
+    struct Trickle<'a>(&'a [u8]);
+
+    impl Read for Trickle<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let len = buf.len().min(self.0.len()).min(7);
+            buf[..len].copy_from_slice(&self.0[..len]);
+            self.0 = &self.0[len..];
+            Ok(len)
+        }
+    }
+
+    /// Lines `i j` for the numbers i from 0 to `n` in a shuffled order,
+    /// with the count j of lines with the same i so far
+    fn shuffled(n: usize) -> String {
+        (0..n * 3)
+            .map(|i| (i * 7919) % n)
+            .scan(vec![0; n], |seen, i| {
+                seen[i] += 1;
+                Some(format!("{i} {}\n", seen[i]))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn spills_and_merges_runs() {
+        let input = shuffled(500);
+        let in_memory = sort_external(&input, &["1,1n"], false, usize::MAX);
+        // About 10 lines a run, so runs are merged in several levels
+        let external = sort_external(&input, &["1,1n"], false, 10 * 40);
+        assert_eq!(external, in_memory);
+        assert!(in_memory.starts_with("0 1\n0 2\n0 3\n1 1\n"));
+    }
+
+    #[test]
+    fn keeps_the_first_of_equal_lines_across_runs() {
+        let input = shuffled(500);
+        let unique = sort_external(&input, &["1,1n"], true, 10 * 40);
+        let expected: String = (0..500).map(|i| format!("{i} 1\n")).collect();
+        assert_eq!(unique, expected);
+    }
+
+    #[test]
+    fn ends_the_last_line() {
+        assert_eq!(sort_external("b\na", &[], false, 1), "a\nb\n");
+        assert_eq!(sort_external("", &[], false, 1), "");
+    }
+}
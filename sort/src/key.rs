@@ -0,0 +1,394 @@
+//! Sort keys, the parts of lines `-k` compares, and how they are found in
+//! a line. Fields are separated by a separator byte, or start with the
+//! blanks before them when there is none, so ` b` is the second field of
+//! `a b`.
+
+use nyaa_core::i18n::tr;
+use nyaa_core::quote::shell_escape;
+
+/// How a key is compared
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Flags {
+    /// Skip the blanks before the start of the key
+    pub skips_start_blanks: bool,
+    /// Skip the blanks before the end character of the key
+    pub skips_end_blanks: bool,
+    /// Compare lower case letters like upper case ones
+    pub is_fold: bool,
+    /// Compare the numbers the keys start with
+    pub is_numeric: bool,
+    /// Compare the floating point numbers the keys start with
+    pub is_general_numeric: bool,
+    /// Compare the numbers the keys start with and their SI suffixes
+    pub is_human_numeric: bool,
+    /// Compare the abbreviated month names the keys start with
+    pub is_month: bool,
+    /// Compare hashes of the keys, which shuffles them
+    pub is_random: bool,
+    /// Compare the keys as version numbers
+    pub is_version: bool,
+    pub is_reverse: bool,
+}
+
+impl Flags {
+    /// Set the flags of the option letters at the start of `spec`, where
+    /// `b` is for the start of the key in `is_start`, and return the rest
+    fn parse<'a>(&mut self, spec: &'a str, is_start: bool) -> &'a str {
+        spec.trim_start_matches(|c| match c {
+            'b' if is_start => {
+                self.skips_start_blanks = true;
+                true
+            }
+            'b' => {
+                self.skips_end_blanks = true;
+                true
+            }
+            'f' => {
+                self.is_fold = true;
+                true
+            }
+            'g' => {
+                self.is_general_numeric = true;
+                true
+            }
+            'h' => {
+                self.is_human_numeric = true;
+                true
+            }
+            'M' => {
+                self.is_month = true;
+                true
+            }
+            'n' => {
+                self.is_numeric = true;
+                true
+            }
+            'R' => {
+                self.is_random = true;
+                true
+            }
+            'V' => {
+                self.is_version = true;
+                true
+            }
+            'r' => {
+                self.is_reverse = true;
+                true
+            }
+            _ => false,
+        })
+    }
+
+    /// The option letters of the flags, like `bnr`
+    pub fn letters(&self) -> String {
+        let letters = [
+            (self.skips_start_blanks || self.skips_end_blanks, 'b'),
+            (self.is_fold, 'f'),
+            (self.is_general_numeric, 'g'),
+            (self.is_human_numeric, 'h'),
+            (self.is_month, 'M'),
+            (self.is_numeric, 'n'),
+            (self.is_random, 'R'),
+            (self.is_reverse, 'r'),
+            (self.is_version, 'V'),
+        ];
+        letters
+            .iter()
+            .filter(|(is_set, _)| *is_set)
+            .map(|&(_, letter)| letter)
+            .collect()
+    }
+
+    /// Whether the flags compare by the collation of the locale, rather
+    /// than as numbers, months, hashes or versions
+    pub fn is_collated(&self) -> bool {
+        !(self.is_general_numeric
+            || self.is_human_numeric
+            || self.is_month
+            || self.is_numeric
+            || self.is_random
+            || self.is_version)
+    }
+
+    /// The option letters of the flags if they ask for more than one way
+    /// to compare, like `fgn`, without the ones for blanks and reversing
+    pub fn incompatible(&self) -> Option<String> {
+        let ways = [
+            self.is_general_numeric,
+            self.is_human_numeric,
+            self.is_month,
+            self.is_numeric,
+            self.is_random,
+            self.is_version,
+        ];
+        if ways.iter().filter(|&&is_set| is_set).count() < 2 {
+            return None;
+        }
+        let flags = Flags {
+            skips_start_blanks: false,
+            skips_end_blanks: false,
+            is_reverse: false,
+            ..*self
+        };
+        Some(flags.letters())
+    }
+}
+
+/// The part of a line from a start position to an end position
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Key {
+    /// Field the key starts in and its character, both from 0
+    pub start: (usize, usize),
+    /// Field the key ends in, from 0, and its last character, from 1 with
+    /// 0 for the end of the field. A key without end goes to the end of
+    /// the line.
+    pub end: Option<(usize, usize)>,
+    pub flags: Flags,
+}
+
+impl Key {
+    /// Key of the whole line
+    pub fn whole_line(flags: Flags) -> Self {
+        Key {
+            start: (0, 0),
+            end: None,
+            flags,
+        }
+    }
+
+    /// Key of a `-k` operand, `F[.C][OPTS][,F[.C][OPTS]]` with fields and
+    /// characters from 1
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let invalid = |message: &str| {
+            tr("{}: invalid field specification {}")
+                .replacen("{}", tr(message), 1)
+                .replacen("{}", &shell_escape(spec.as_ref()), 1)
+        };
+        let mut flags = Flags::default();
+        let (field, rest) = count(spec, "invalid number at field start")?;
+        let field = field
+            .checked_sub(1)
+            .ok_or_else(|| invalid("field number is zero"))?;
+        let (char, rest) = match rest.strip_prefix('.') {
+            Some(rest) => {
+                let (char, rest) = count(rest, "invalid number after '.'")?;
+                let char = char
+                    .checked_sub(1)
+                    .ok_or_else(|| invalid("character offset is zero"))?;
+                (char, rest)
+            }
+            None => (0, rest),
+        };
+        let rest = flags.parse(rest, true);
+        let (end, rest) = match rest.strip_prefix(',') {
+            Some(rest) => {
+                let (end_field, rest) = count(rest, "invalid number after ','")?;
+                let end_field = end_field
+                    .checked_sub(1)
+                    .ok_or_else(|| invalid("field number is zero"))?;
+                let (end_char, rest) = match rest.strip_prefix('.') {
+                    Some(rest) => count(rest, "invalid number after '.'")?,
+                    None => (0, rest),
+                };
+                (Some((end_field, end_char)), flags.parse(rest, false))
+            }
+            None => (None, rest),
+        };
+        if !rest.is_empty() {
+            return Err(invalid("stray character in field spec"));
+        }
+        Ok(Key {
+            start: (field, char),
+            end,
+            flags,
+        })
+    }
+
+    /// Whether the key has ordering options of its own, instead of taking
+    /// the global ones
+    pub fn has_flags(&self) -> bool {
+        self.flags != Flags::default()
+    }
+
+    /// The key in `line`, whose fields are separated by `separator`
+    pub fn find<'a>(&self, line: &'a [u8], separator: Option<u8>) -> &'a [u8] {
+        let (start, end) = self.bounds(line, separator);
+        &line[start..end.max(start)]
+    }
+
+    /// Where the key starts and ends in `line`, whose fields are separated
+    /// by `separator`. The end is before the start if the key ends in a
+    /// field before the one it starts in.
+    pub fn bounds(&self, line: &[u8], separator: Option<u8>) -> (usize, usize) {
+        let (field, char) = self.start;
+        let mut start = skip_fields(line, field, separator, true);
+        if self.flags.skips_start_blanks {
+            start = skip_blanks(line, start);
+        }
+        start = line.len().min(start.saturating_add(char));
+        let end = match self.end {
+            // The last field is taken whole, without the separator after it
+            Some((field, 0)) => skip_fields(line, field.saturating_add(1), separator, false),
+            Some((field, char)) => {
+                let mut end = skip_fields(line, field, separator, true);
+                if self.flags.skips_end_blanks {
+                    end = skip_blanks(line, end);
+                }
+                line.len().min(end.saturating_add(char))
+            }
+            None => line.len(),
+        };
+        (start, end)
+    }
+}
+
+/// The count at the start of `spec` and the rest after it. Counts too
+/// large for `usize` are the largest one.
+fn count<'a>(spec: &'a str, what: &str) -> Result<(usize, &'a str), String> {
+    let len = spec.bytes().take_while(u8::is_ascii_digit).count();
+    if len == 0 {
+        return Err(tr("{}: invalid count at start of {}")
+            .replacen("{}", tr(what), 1)
+            .replacen("{}", &shell_escape(spec.as_ref()), 1));
+    }
+    let count = spec[..len].bytes().fold(0usize, |n, digit| {
+        n.saturating_mul(10)
+            .saturating_add(usize::from(digit - b'0'))
+    });
+    Ok((count, &spec[len..]))
+}
+
+/// Whether `b` is a blank, which fields start with when there is no
+/// separator
+pub fn is_blank(b: u8) -> bool {
+    matches!(b, b' ' | b'\t' | b'\n')
+}
+
+fn skip_blanks(line: &[u8], start: usize) -> usize {
+    start + line[start..].iter().take_while(|&&b| is_blank(b)).count()
+}
+
+/// Index in `line` after its first `count` fields, and after the separator
+/// following the last of them in `is_past_separator`
+fn skip_fields(line: &[u8], count: usize, separator: Option<u8>, is_past_separator: bool) -> usize {
+    let mut i = 0;
+    for left in (0..count).rev() {
+        if i >= line.len() {
+            break;
+        }
+        match separator {
+            Some(separator) => {
+                i += line[i..].iter().take_while(|&&b| b != separator).count();
+                if i < line.len() && (left > 0 || is_past_separator) {
+                    i += 1;
+                }
+            }
+            None => {
+                i = skip_blanks(line, i);
+                i += line[i..].iter().take_while(|&&b| !is_blank(b)).count();
+            }
+        }
+    }
+    i
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn find(spec: &str, line: &str, separator: Option<u8>) -> String {
+        let key = Key::parse(spec).unwrap();
+        String::from_utf8(key.find(line.as_bytes(), separator).to_vec()).unwrap()
+    }
+
+    #[test]
+    fn parses_positions_and_flags() {
+        let key = Key::parse("2.3bn,4.5rb").unwrap();
+        assert_eq!(key.start, (1, 2));
+        assert_eq!(key.end, Some((3, 5)));
+        let flags = Flags {
+            skips_start_blanks: true,
+            skips_end_blanks: true,
+            is_numeric: true,
+            is_reverse: true,
+            ..Flags::default()
+        };
+        assert_eq!(key.flags, flags);
+        let key = Key::parse("1gh,1MRV").unwrap();
+        assert!(key.flags.is_general_numeric && key.flags.is_human_numeric);
+        assert!(key.flags.is_month && key.flags.is_random && key.flags.is_version);
+        let key = Key::parse("1,2").unwrap();
+        assert_eq!((key.start, key.end), ((0, 0), Some((1, 0))));
+        assert!(!key.has_flags());
+        assert_eq!(
+            Key::parse("99999999999999999999999").unwrap().start.0,
+            usize::MAX - 1
+        );
+    }
+
+    #[test]
+    fn parse_errors() {
+        let error = |spec| Key::parse(spec).unwrap_err();
+        assert_eq!(
+            error("0"),
+            "field number is zero: invalid field specification '0'"
+        );
+        assert_eq!(
+            error("1.0"),
+            "character offset is zero: invalid field specification '1.0'"
+        );
+        assert_eq!(
+            error("1,0"),
+            "field number is zero: invalid field specification '1,0'"
+        );
+        assert_eq!(
+            error("1.x"),
+            "invalid number after '.': invalid count at start of 'x'"
+        );
+        assert_eq!(
+            error(""),
+            "invalid number at field start: invalid count at start of ''"
+        );
+        assert_eq!(
+            error("1z"),
+            "stray character in field spec: invalid field specification '1z'"
+        );
+    }
+
+    #[test]
+    fn incompatible_flags() {
+        let flags = |spec| Key::parse(spec).unwrap().flags.incompatible();
+        assert_eq!(flags("1bfn"), None);
+        assert_eq!(flags("1fR"), None);
+        assert_eq!(flags("1nM").as_deref(), Some("Mn"));
+        assert_eq!(flags("1bfgr,1Vh").as_deref(), Some("fghV"));
+        assert_eq!(Key::parse("1br").unwrap().flags.letters(), "br");
+    }
+
+    #[test]
+    fn fields_start_with_blanks() {
+        assert_eq!(find("2", "a  b c", None), "  b c");
+        assert_eq!(find("2,2", "a  b c", None), "  b");
+        assert_eq!(find("2b,2", "a  b c", None), "b");
+        assert_eq!(find("2.2,2.3", "a  b c", None), " b");
+        assert_eq!(find("2.1b,2.1b", "a  b c", None), "b");
+        assert_eq!(find("1,1", "  a b", None), "  a");
+        assert_eq!(find("4", "a b", None), "");
+    }
+
+    #[test]
+    fn fields_between_separators() {
+        assert_eq!(find("2", "a:b:c", Some(b':')), "b:c");
+        assert_eq!(find("2,2", "a:b:c", Some(b':')), "b");
+        assert_eq!(find("1,2", "a::c", Some(b':')), "a:");
+        assert_eq!(find("3,3", "a::c", Some(b':')), "c");
+        assert_eq!(find("2.2,3.1", "a:bc:de", Some(b':')), "c:d");
+        assert_eq!(find("2,2", "a", Some(b':')), "");
+    }
+
+    #[test]
+    fn ends_before_starts_are_empty() {
+        assert_eq!(find("2,1", "a b", None), "");
+        assert_eq!(find("1.3,1.1", "abc", None), "");
+    }
+}
@@ -0,0 +1,341 @@
+//! Sorting of lines with the semantics of `sort`.
+//!
+//! Lines are compared by their keys in turn, each by the collation of the
+//! locale, as numbers, months or versions, or by their hashes, and the lines whose keys are all equal by the
+//! whole line as a last resort, unless the order is stable. Sorting is
+//! stable, so with `-u` the first of the lines with equal keys is the one
+//! kept.
+//!
+//! ```
+//! use nyaa_core::locale::Locale;
+//! use nyaa_sort::key::Key;
+//! use nyaa_sort::Order;
+//!
+//! let order = Order {
+//!     keys: vec![Key::parse("2n").unwrap()],
+//!     separator: Some(b':'),
+//!     locale: Locale::C,
+//!     is_reverse: false,
+//!     is_stable: false,
+//!     salt: [0; 16],
+//! };
+//! let mut lines = nyaa_sort::lines(b"b:10\na:9\nc:9\n", b'\n');
+//! nyaa_sort::sort(&mut lines, &order, false, 1);
+//! assert_eq!(lines, [&b"a:9"[..], b"c:9", b"b:10"]);
+//! ```
+
+pub mod cli;
+pub mod compare;
+pub mod debug;
+pub mod external;
+pub mod key;
+
+use key::Key;
+use nyaa_core::locale::Locale;
+use std::cmp::Ordering;
+use std::io::{self, BufRead};
+use std::thread;
+
+/// Fewest lines sorted on a thread of their own
+const PARALLEL_MIN_LINES: usize = 16 * 1024;
+
+/// How lines are ordered
+#[derive(Clone, Debug)]
+pub struct Order {
+    /// Keys compared in turn, the whole line by the collation of the
+    /// locale if there are none
+    pub keys: Vec<Key>,
+    /// Byte separating the fields, which start with blanks if there is none
+    pub separator: Option<u8>,
+    /// Locale collating keys and lines
+    pub locale: Locale,
+    /// Whether the whole lines are compared in reverse as a last resort
+    pub is_reverse: bool,
+    /// Whether lines with equal keys stay in the order of the input,
+    /// instead of being compared whole
+    pub is_stable: bool,
+    /// Bytes hashed before the keys compared by their hashes
+    pub salt: [u8; 16],
+}
+
+impl Order {
+    /// Order of `a` and `b` by their keys only
+    pub fn compare_keys(&self, a: &[u8], b: &[u8]) -> Ordering {
+        if self.keys.is_empty() {
+            return self.compare_lines(a, b);
+        }
+        self.keys
+            .iter()
+            .map(|key| self.compare_key(key, a, b))
+            .find(|&ordering| ordering != Ordering::Equal)
+            .unwrap_or(Ordering::Equal)
+    }
+
+    /// Order of `a` and `b` by their keys, then by the whole lines unless
+    /// the order is stable
+    pub fn compare(&self, a: &[u8], b: &[u8]) -> Ordering {
+        match self.compare_keys(a, b) {
+            Ordering::Equal if !self.keys.is_empty() && !self.is_stable => self.compare_lines(a, b),
+            ordering => ordering,
+        }
+    }
+
+    fn compare_key(&self, key: &Key, a: &[u8], b: &[u8]) -> Ordering {
+        let (a, b) = (key.find(a, self.separator), key.find(b, self.separator));
+        let flags = key.flags;
+        // Keys are folded for the other comparisons too, before them
+        let folded;
+        let (a, b) = if flags.is_fold && !flags.is_collated() {
+            folded = (compare::fold(self.locale, a), compare::fold(self.locale, b));
+            (&folded.0[..], &folded.1[..])
+        } else {
+            (a, b)
+        };
+        let ordering = if flags.is_numeric {
+            compare::numeric(a, b)
+        } else if flags.is_general_numeric {
+            compare::general_numeric(a, b)
+        } else if flags.is_human_numeric {
+            compare::human_numeric(a, b)
+        } else if flags.is_month {
+            compare::month(a, b)
+        } else if flags.is_random {
+            compare::random(&self.salt, a, b)
+        } else if flags.is_version {
+            compare::version(a, b)
+        } else if flags.is_fold {
+            compare::folded(self.locale, a, b)
+        } else {
+            self.locale.compare(a, b)
+        };
+        if flags.is_reverse {
+            ordering.reverse()
+        } else {
+            ordering
+        }
+    }
+
+    fn compare_lines(&self, a: &[u8], b: &[u8]) -> Ordering {
+        let ordering = self.locale.compare(a, b);
+        if self.is_reverse {
+            ordering.reverse()
+        } else {
+            ordering
+        }
+    }
+}
+
+/// The lines of `data`, each ended by `terminator`, without it
+pub fn lines(data: &[u8], terminator: u8) -> Vec<&[u8]> {
+    let mut start = 0;
+    memchr::memchr_iter(terminator, data)
+        .map(|end| {
+            let line = &data[start..end];
+            start = end + 1;
+            line
+        })
+        .collect()
+}
+
+/// Sort `lines` in `order` on up to `threads` threads. In `is_unique`,
+/// only the first of the lines with equal keys is kept.
+pub fn sort(lines: &mut Vec<&[u8]>, order: &Order, is_unique: bool, threads: usize) {
+    if is_unique {
+        let compare = |a: &[u8], b: &[u8]| order.compare_keys(a, b);
+        parallel_sort(lines, threads, &compare);
+        lines.dedup_by(|a, b| compare(a, b) == Ordering::Equal);
+    } else {
+        parallel_sort(lines, threads, &|a, b| order.compare(a, b));
+    }
+}
+
+/// The number, from 1, and the contents of the first line of `input` out
+/// of `order`, if there is one. In `is_unique`, a line with the same keys
+/// as the one before it is out of order too.
+pub fn disorder(
+    mut input: impl BufRead,
+    order: &Order,
+    terminator: u8,
+    is_unique: bool,
+) -> io::Result<Option<(u64, Vec<u8>)>> {
+    let (mut previous, mut line) = (Vec::new(), Vec::new());
+    let mut number = 0;
+    loop {
+        line.clear();
+        if input.read_until(terminator, &mut line)? == 0 {
+            return Ok(None);
+        }
+        if line.last() == Some(&terminator) {
+            line.pop();
+        }
+        number += 1;
+        if number > 1 {
+            let ordering = if is_unique {
+                order.compare_keys(&previous, &line)
+            } else {
+                order.compare(&previous, &line)
+            };
+            if ordering == Ordering::Greater || (is_unique && ordering == Ordering::Equal) {
+                return Ok(Some((number, line)));
+            }
+        }
+        std::mem::swap(&mut previous, &mut line);
+    }
+}
+
+/// Sort `lines` stably by `compare`, the halves on different threads and
+/// then merged, down to halves of [`PARALLEL_MIN_LINES`]
+fn parallel_sort<F>(lines: &mut [&[u8]], threads: usize, compare: &F)
+where
+    F: Fn(&[u8], &[u8]) -> Ordering + Sync,
+{
+    if threads <= 1 || lines.len() < 2 * PARALLEL_MIN_LINES {
+        lines.sort_by(|a, b| compare(a, b));
+        return;
+    }
+    let mid = lines.len() / 2;
+    let (left, right) = lines.split_at_mut(mid);
+    let is_spawned = thread::scope(|s| {
+        let worker =
+            thread::Builder::new().spawn_scoped(s, || parallel_sort(left, threads / 2, compare));
+        parallel_sort(right, threads - threads / 2, compare);
+        worker.is_ok()
+    });
+    // Targets without threads, like WASI, fail to spawn them
+    if !is_spawned {
+        left.sort_by(|a, b| compare(a, b));
+    }
+    let mut merged = Vec::with_capacity(lines.len());
+    let (mut i, mut j) = (0, mid);
+    while i < mid && j < lines.len() {
+        // The left line first among equal ones, for a stable sort
+        if compare(lines[j], lines[i]) == Ordering::Less {
+            merged.push(lines[j]);
+            j += 1;
+        } else {
+            merged.push(lines[i]);
+            i += 1;
+        }
+    }
+    merged.extend_from_slice(&lines[i..mid]);
+    merged.extend_from_slice(&lines[j..]);
+    lines.copy_from_slice(&merged);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use key::Flags;
+
+    fn sorted(input: &str, order: &Order, is_unique: bool) -> Vec<String> {
+        let mut lines = lines(input.as_bytes(), b'\n');
+        sort(&mut lines, order, is_unique, 1);
+        lines
+            .iter()
+            .map(|line| String::from_utf8(line.to_vec()).unwrap())
+            .collect()
+    }
+
+    fn order(keys: &[&str], separator: Option<u8>) -> Order {
+        Order {
+            keys: keys.iter().map(|spec| Key::parse(spec).unwrap()).collect(),
+            separator,
+            locale: Locale::C,
+            is_reverse: false,
+            is_stable: false,
+            salt: [0; 16],
+        }
+    }
+
+    #[test]
+    fn sorts_on_threads() {
+        let input: String = (0..100_000)
+            .map(|i| format!("{} {i}\n", (i * 7919) % 1000))
+            .collect();
+        let order = order(&["1,1n"], None);
+        let mut lines = lines(input.as_bytes(), b'\n');
+        let mut expected = lines.clone();
+        sort(&mut expected, &order, false, 1);
+        sort(&mut lines, &order, false, 8);
+        assert_eq!(lines, expected);
+    }
+
+    #[test]
+    fn sorts_by_bytes() {
+        assert_eq!(
+            sorted("b\nB\na\n\n", &order(&[], None), false),
+            ["", "B", "a", "b"]
+        );
+    }
+
+    #[test]
+    fn sorts_by_the_locale() {
+        let order = Order {
+            locale: Locale::Unicode,
+            ..order(&[], None)
+        };
+        assert_eq!(sorted("b\nB\na\n", &order, false), ["a", "b", "B"]);
+    }
+
+    #[test]
+    fn breaks_ties_by_the_whole_line() {
+        let mut order = order(&["2,2"], None);
+        assert_eq!(
+            sorted("b x\na x\nc w\n", &order, false),
+            ["c w", "a x", "b x"]
+        );
+        order.is_reverse = true;
+        assert_eq!(
+            sorted("b x\na x\nc w\n", &order, false),
+            ["c w", "b x", "a x"]
+        );
+    }
+
+    #[test]
+    fn keys_in_turn() {
+        let order = order(&["2,2n", "1,1r"], Some(b','));
+        assert_eq!(
+            sorted("a,10\nb,9\nc,10\nd,x\n", &order, false),
+            ["d,x", "b,9", "c,10", "a,10"]
+        );
+    }
+
+    #[test]
+    fn folds_keys_before_other_comparisons() {
+        assert_eq!(
+            sorted("B\nAE\na\n", &order(&["1fV"], None), false),
+            ["a", "AE", "B"]
+        );
+        assert_eq!(
+            sorted("3m\n2M\n1\n", &order(&["1fh"], None), false),
+            ["1", "2M", "3m"]
+        );
+    }
+
+    #[test]
+    fn keeps_the_first_of_equal_keys() {
+        let mut order = order(&[], None);
+        order.keys.push(Key::whole_line(Flags {
+            is_fold: true,
+            ..Flags::default()
+        }));
+        assert_eq!(sorted("b\nA\nB\na\n", &order, true), ["A", "b"]);
+        assert_eq!(sorted("b\nA\nB\na\n", &order, false), ["A", "a", "B", "b"]);
+        order.is_stable = true;
+        assert_eq!(sorted("b\nA\nB\na\n", &order, false), ["A", "a", "b", "B"]);
+    }
+
+    #[test]
+    fn finds_the_first_disorder() {
+        let order = order(&["1,1"], None);
+        let disorder = |input: &str, is_unique| {
+            disorder(input.as_bytes(), &order, b'\n', is_unique)
+                .unwrap()
+                .map(|(number, line)| (number, String::from_utf8(line).unwrap()))
+        };
+        assert_eq!(disorder("a\nb x\nb\nc", false), Some((3, "b".to_string())));
+        assert_eq!(disorder("a\nb\nb x\nc", false), None);
+        assert_eq!(disorder("a\nb\nb x\nc", true), Some((3, "b x".to_string())));
+        assert_eq!(disorder("", true), None);
+    }
+}
@@ -0,0 +1,140 @@
+//! Tests of sorting generated lines against sorting them by the obvious
+//! comparison, in memory and through temporary files, and of checking
+//! the order of the lines sorted
+
+use nyaa_core::locale::Locale;
+use nyaa_sort::external::{Options, Sorter};
+use nyaa_sort::key::Key;
+use nyaa_sort::Order;
+use proptest::prelude::*;
+
+/// Lines of a few fields, numbers with signs and points among them, and
+/// bytes that aren't UTF-8
+fn input() -> impl Strategy<Value = Vec<Vec<u8>>> {
+    let line = prop::collection::vec(prop::sample::select(&b"ab 1-9.0\t\xff"[..]), 0..12);
+    prop::collection::vec(line, 0..64)
+}
+
+/// Keys of every kind of comparison of whole lines and fields
+const KEYS: &[&[&str]] = &[&[], &["1n"], &["2,2"], &["1f", "2n"]];
+
+fn keys() -> impl Strategy<Value = &'static [&'static str]> {
+    prop::sample::select(KEYS)
+}
+
+fn order(keys: &[&str]) -> Order {
+    Order {
+        keys: keys.iter().map(|spec| Key::parse(spec).unwrap()).collect(),
+        separator: None,
+        locale: Locale::C,
+        is_reverse: false,
+        is_stable: false,
+        salt: [0; 16],
+    }
+}
+
+/// The leading number of `line` after its blanks, 0 if there is none,
+/// scaled to compare as an integer
+fn number(line: &[u8]) -> i128 {
+    let line = line.trim_ascii_start();
+    let (sign, digits) = match line.strip_prefix(b"-") {
+        Some(digits) => (-1, digits),
+        None => (1, line),
+    };
+    let integer = digits.iter().take_while(|b| b.is_ascii_digit()).count();
+    let fraction = match digits.get(integer) {
+        Some(b'.') => &digits[integer + 1..],
+        _ => &[],
+    };
+    let fraction = fraction.iter().take_while(|b| b.is_ascii_digit());
+    let scaled: Vec<u8> = digits[..integer]
+        .iter()
+        .chain(fraction.chain(std::iter::repeat(&b'0')).take(16))
+        .copied()
+        .collect();
+    sign * std::str::from_utf8(&scaled)
+        .unwrap()
+        .parse::<i128>()
+        .unwrap()
+}
+
+fn sorted(lines: &[Vec<u8>], order: &Order, is_unique: bool, threads: usize) -> Vec<Vec<u8>> {
+    let mut sorted: Vec<&[u8]> = lines.iter().map(Vec::as_slice).collect();
+    nyaa_sort::sort(&mut sorted, order, is_unique, threads);
+    sorted.into_iter().map(<[u8]>::to_vec).collect()
+}
+
+fn joined(lines: &[Vec<u8>]) -> Vec<u8> {
+    lines
+        .iter()
+        .flat_map(|line| [&line[..], b"\n"].concat())
+        .collect()
+}
+
+proptest! {
+    #[test]
+    fn whole_lines(lines in input(), is_unique in any::<bool>()) {
+        let mut expected = lines.clone();
+        expected.sort();
+        if is_unique {
+            expected.dedup();
+        }
+        prop_assert_eq!(sorted(&lines, &order(&[]), is_unique, 1), expected);
+    }
+
+    #[test]
+    fn numeric_keys(lines in input()) {
+        let mut expected = lines.clone();
+        expected.sort_by(|a, b| number(a).cmp(&number(b)).then_with(|| a.cmp(b)));
+        prop_assert_eq!(sorted(&lines, &order(&["1n"]), false, 1), expected);
+    }
+
+    #[test]
+    fn disorder(lines in input(), keys in keys()) {
+        let order = order(keys);
+        let sorted_lines = sorted(&lines, &order, false, 1);
+        let disorder = nyaa_sort::disorder(&joined(&lines)[..], &order, b'\n', false).unwrap();
+        prop_assert_eq!(disorder.is_none(), sorted_lines == lines);
+        let disorder = nyaa_sort::disorder(&joined(&sorted_lines)[..], &order, b'\n', false);
+        prop_assert!(disorder.unwrap().is_none());
+    }
+
+    #[test]
+    fn spilled_runs(
+        lines in input(),
+        keys in keys(),
+        is_unique in any::<bool>(),
+        buffer_size in 1..256usize,
+    ) {
+        let order = order(keys);
+        let options = Options {
+            terminator: b'\n',
+            is_unique,
+            buffer_size,
+            temporary_dirs: vec![std::env::temp_dir()],
+            threads: 1,
+        };
+        let mut sorter = Sorter::new(&order, &options);
+        sorter.read(&joined(&lines)[..]).unwrap();
+        let mut out = Vec::new();
+        sorter.finish(&mut out).unwrap();
+        prop_assert_eq!(out, joined(&sorted(&lines, &order, is_unique, 1)));
+    }
+}
+
+proptest! {
+    // Halves are only sorted on threads of their own past 32768 lines
+    #![proptest_config(ProptestConfig::with_cases(4))]
+
+    #[test]
+    fn threads_dont_matter(seed in any::<u64>(), is_unique in any::<bool>()) {
+        let lines: Vec<Vec<u8>> = (0..40_000u64)
+            .map(|i| format!("{} {i}", i.wrapping_mul(seed | 1) % 5_000).into_bytes())
+            .collect();
+        let order = order(&["1n"]);
+        prop_assert_eq!(
+            sorted(&lines, &order, is_unique, 4),
+            sorted(&lines, &order, is_unique, 1)
+        );
+    }
+}
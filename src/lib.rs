@@ -1 +1,20 @@
+//! Applets of the multicall `nyaa` binary
 
+use std::ffi::OsString;
+use std::process::ExitCode;
+
+/// A utility that can be run from the multicall binary
+pub struct Applet {
+    pub name: &'static str,
+    /// Run the utility with a command line starting with the program name
+    pub main: fn(Vec<OsString>) -> ExitCode,
+    /// Description of the command line, for completions
+    pub command: fn() -> clap::Command,
+}
+
+/// All applets enabled by features, in name order
+pub const APPLETS: &[Applet] = include!(concat!(env!("OUT_DIR"), "/applets.rs"));
+
+pub fn find(name: &str) -> Option<&'static Applet> {
+    APPLETS.iter().find(|applet| applet.name == name)
+}
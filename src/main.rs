@@ -1,3 +1,157 @@
-fn main() {
-    println!("Hello, world!");
+use clap_complete::Shell;
+use nyaa_core::error::{Status, UError, UResult, EXIT_USAGE};
+use nyaa_utils::{find, Applet, APPLETS};
+use std::ffi::OsString;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+const UTIL: &str = "nyaa";
+
+fn usage() -> String {
+    let names: Vec<&str> = APPLETS.iter().map(|applet| applet.name).collect();
+    format!(
+        "Usage: nyaa APPLET [ARGUMENT]...\n   \
+         or: APPLET [ARGUMENT]...  (through a link named APPLET)\n   \
+         or: nyaa --install [DIRECTORY]\n   \
+         or: nyaa --list\n   \
+         or: nyaa completions SHELL [APPLET]...\n   \
+         or: nyaa man APPLET | --dir DIRECTORY\n\n\
+         Applets: {}",
+        names.join(", ")
+    )
+}
+
+/// Link every applet name in `dir` to the running binary
+fn install(dir: &Path, status: &mut Status) {
+    if !fs::metadata(dir).is_ok_and(|metadata| metadata.is_dir()) {
+        let err = UError::failure("Not a directory").with_name(dir.display());
+        return status.report(UTIL, &err);
+    }
+    let Some(exe) = status.check(UTIL, std::env::current_exe().map_err(UError::from)) else {
+        return;
+    };
+    for applet in APPLETS {
+        let link = dir
+            .join(applet.name)
+            .with_extension(std::env::consts::EXE_EXTENSION);
+        if let Err(e) = link_to(&exe, &link) {
+            status.report(UTIL, &UError::from(e).with_name(link.display()));
+        }
+    }
+}
+
+#[cfg(unix)]
+fn link_to(exe: &Path, link: &Path) -> Result<(), io::Error> {
+    std::os::unix::fs::symlink(exe, link)
+}
+
+/// Symbolic links need extra privileges on Windows, hard links don't
+#[cfg(not(unix))]
+fn link_to(exe: &Path, link: &Path) -> Result<(), io::Error> {
+    fs::hard_link(exe, link)
+}
+
+fn find_operand(name: &OsString) -> UResult<&'static Applet> {
+    let name = name.to_string_lossy();
+    find(&name).ok_or_else(|| UError::usage("applet not found").with_name(name))
+}
+
+/// Print the completion script of `shell` for the named applets, or for
+/// all of them
+fn completions(args: &[OsString]) -> UResult<()> {
+    let shell = args
+        .first()
+        .ok_or_else(|| UError::usage("missing shell operand").with_name("completions"))?
+        .to_string_lossy();
+    let shell = shell.parse::<Shell>().map_err(|_| {
+        UError::usage("unknown shell, expected one of bash, elvish, fish, powershell, zsh")
+            .with_name(&shell)
+    })?;
+    let mut applets = args[1..]
+        .iter()
+        .map(find_operand)
+        .collect::<UResult<Vec<_>>>()?;
+    if applets.is_empty() {
+        applets.extend(APPLETS);
+    }
+    let mut out = io::stdout().lock();
+    for applet in applets {
+        clap_complete::generate(shell, &mut (applet.command)(), applet.name, &mut out);
+    }
+    Ok(())
+}
+
+/// Print the man page of an applet, or write the pages of all of them to
+/// `APPLET.1` files in a directory
+fn man(args: &[OsString]) -> UResult<()> {
+    let result = match args {
+        [flag, dir] if flag == "--dir" => APPLETS.iter().try_for_each(|applet| {
+            let path = Path::new(dir).join(format!("{}.1", applet.name));
+            let mut out = io::BufWriter::new(fs::File::create(&path)?);
+            clap_mangen::Man::new((applet.command)()).render(&mut out)?;
+            out.flush()
+        }),
+        [name] => {
+            let applet = find_operand(name)?;
+            clap_mangen::Man::new((applet.command)()).render(&mut io::stdout())
+        }
+        _ => {
+            return Err(UError::usage("expected an applet or --dir DIRECTORY").with_name("man"));
+        }
+    };
+    result.map_err(|e| UError::from(e).with_name("man"))
+}
+
+/// Name of the program `arg` with its directory and executable extension
+/// stripped
+fn applet_name(arg: &OsString) -> Option<String> {
+    let path = Path::new(arg);
+    let name = match path.extension() {
+        Some(ext) if ext == std::env::consts::EXE_EXTENSION => path.file_stem(),
+        _ => path.file_name(),
+    };
+    name?.to_str().map(str::to_string)
+}
+
+fn main() -> ExitCode {
+    nyaa_core::init();
+    let mut args: Vec<OsString> = std::env::args_os().collect();
+    // Called through a link named after the applet
+    if let Some(applet) = args
+        .first()
+        .and_then(applet_name)
+        .and_then(|name| find(&name))
+    {
+        return (applet.main)(args);
+    }
+    let mut status = Status::default();
+    let Some(first) = args.get(1).map(|arg| arg.to_string_lossy().into_owned()) else {
+        eprintln!("{}", usage());
+        return ExitCode::from(EXIT_USAGE);
+    };
+    match first.as_str() {
+        "--help" => println!("{}", usage()),
+        "--list" => APPLETS
+            .iter()
+            .for_each(|applet| println!("{}", applet.name)),
+        "completions" => {
+            status.check(UTIL, completions(&args[2..]));
+        }
+        "man" => {
+            status.check(UTIL, man(&args[2..]));
+        }
+        "--install" => {
+            let dir = args
+                .get(2)
+                .map_or_else(|| PathBuf::from("."), PathBuf::from);
+            install(&dir, &mut status);
+        }
+        _ => match status.check(UTIL, find_operand(&args[1])) {
+            Some(applet) => return (applet.main)(args.split_off(1)),
+            None => eprintln!("{}", usage()),
+        },
+    }
+    status.into()
 }
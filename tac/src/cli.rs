@@ -0,0 +1,165 @@
+//! Command line interface of `tac`
+
+use crate::{Error, Separator};
+use clap::{ArgAction, CommandFactory, FromArgMatches, Parser};
+use nyaa_core::error::{Status, UError};
+use nyaa_core::i18n;
+use nyaa_core::input;
+use nyaa_core::output::{self, OutputSink};
+use nyaa_core::posix;
+use nyaa_core::quote::shell_escape;
+use nyaa_core::response;
+use nyaa_core::trace;
+use nyaa_core::Input;
+use std::borrow::Cow;
+use std::ffi::OsString;
+use std::fs;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+const UTIL: &str = "tac";
+
+#[derive(Parser)]
+#[command(name = "tac", version)]
+#[command(about = "concatenate and print files in reverse", long_about = None)]
+#[command(disable_help_flag = true, disable_version_flag = true)]
+struct Args {
+    /// Attach the separator before instead of after
+    #[arg(short = 'b', long = "before")]
+    is_before: bool,
+
+    /// Interpret the separator as a regular expression
+    #[arg(short = 'r', long = "regex")]
+    is_regex: bool,
+
+    /// Use STRING as the separator instead of newline, an empty one for NUL
+    #[arg(
+        short = 's',
+        long,
+        value_name = "STRING",
+        allow_hyphen_values = true,
+        default_value = "\n",
+        hide_default_value = true
+    )]
+    separator: String,
+
+    /// Input files. An argument @FILE stands for the arguments on the
+    /// lines of FILE, @@NAME for the file @NAME
+    files: Vec<PathBuf>,
+
+    /// Print help
+    #[arg(long, action=ArgAction::Help)]
+    help: (),
+
+    /// Print version
+    #[arg(long, action=ArgAction::Version)]
+    version: (),
+}
+
+/// Failure printing an input. The following inputs are still printed
+/// after a failure to read one, but not after one to write the output.
+enum Failure {
+    Open(io::Error),
+    Read(io::Error),
+    Write(io::Error),
+}
+
+impl From<Error> for Failure {
+    fn from(err: Error) -> Self {
+        match err {
+            Error::Read(e) => Failure::Read(e),
+            Error::Write(e) => Failure::Write(e),
+        }
+    }
+}
+
+/// Print the records of `input` in reverse order, reading regular files
+/// backward and anything else whole
+fn tac_input(
+    input: &Input,
+    args: &Args,
+    separator: &Separator,
+    out: &mut OutputSink,
+) -> Result<(), Failure> {
+    let file = match input {
+        Input::Stdin(_) => input::open_descriptor(0, Path::new("/dev/stdin")).ok(),
+        Input::Descriptor(fd, path) => {
+            Some(input::open_descriptor(*fd, path).map_err(Failure::Open)?)
+        }
+        Input::Path(path) => Some(fs::File::open(path).map_err(Failure::Open)?),
+    };
+    if let Some(file) = file.filter(|file| file.metadata().is_ok_and(|metadata| metadata.is_file()))
+    {
+        nyaa_core::debug!("reading backward");
+        return Ok(crate::tac(file, separator, args.is_before, out)?);
+    }
+    let mut data = Vec::new();
+    input
+        .open()
+        .map_err(Failure::Open)?
+        .read_to_end(&mut data)
+        .map_err(Failure::Read)?;
+    crate::tac_slice(&data, separator, args.is_before, out).map_err(Failure::Write)
+}
+
+pub fn command() -> clap::Command {
+    i18n::command(Args::command())
+}
+
+/// Run `tac` with the command line `args`, starting with the program name
+pub fn main(args: Vec<OsString>) -> ExitCode {
+    let mut status = Status::default();
+    let Some(args) = status.check(UTIL, response::expand(args)) else {
+        return status.into();
+    };
+    let args = posix::operands_last(&command(), args);
+    let matches = command().get_matches_from(args);
+    let args = Args::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+    trace::init(UTIL, false);
+    // The terminating NUL of the C string is the separator of GNU tac
+    let separator = if args.separator.is_empty() {
+        "\0"
+    } else {
+        &args.separator
+    };
+    let separator = if args.is_regex {
+        match Separator::regex(separator) {
+            Ok(separator) => separator,
+            Err(_) => {
+                status.report(UTIL, &UError::failure("Invalid regular expression"));
+                return status.into();
+            }
+        }
+    } else {
+        Separator::Bytes(separator.as_bytes().to_vec())
+    };
+    let operands = if args.files.is_empty() {
+        vec![PathBuf::from("-")]
+    } else {
+        args.files.clone()
+    };
+    let mut out = OutputSink::stdout();
+    for operand in &operands {
+        let input = Input::from_operand(operand);
+        let name = input.quoted_name().unwrap_or(Cow::Borrowed("-"));
+        match trace::operand(&name, || tac_input(&input, &args, &separator, &mut out)) {
+            Ok(()) => (),
+            Err(Failure::Open(e)) => {
+                let name = i18n::tr("failed to open {} for reading")
+                    .replace("{}", &shell_escape(operand.as_os_str()));
+                status.report(UTIL, &UError::from(e).with_name(name));
+            }
+            Err(Failure::Read(e)) => {
+                let name = format!("{name}: {}", i18n::tr("read error"));
+                status.report(UTIL, &UError::from(e).with_name(name));
+            }
+            Err(Failure::Write(e)) => {
+                status.report(UTIL, &output::write_error(e));
+                return status.into();
+            }
+        }
+    }
+    status.check(UTIL, out.finish());
+    status.into()
+}
@@ -0,0 +1,168 @@
+//! Printing of records in reverse order with the semantics of `tac`.
+//!
+//! A record is the text up to and including a [`Separator`], which ends
+//! it, or starts it when separators are attached before. Seekable files
+//! are read from the end by [`tac`] in blocks, so only the record being
+//! printed is held in memory however large the file is. Other input is
+//! read whole before printing its last record.
+//!
+//! ```
+//! let separator = nyaa_tac::Separator::Bytes(b"\n".to_vec());
+//! let mut out = Vec::new();
+//! nyaa_tac::tac_slice(b"one\ntwo\nthree", &separator, false, &mut out)?;
+//! assert_eq!(out, b"threetwo\none\n");
+//! # Ok::<(), std::io::Error>(())
+//! ```
+
+pub mod cli;
+
+use nyaa_core::reverse::ReverseReader;
+use regex_automata::meta::{BuildError, Regex};
+use regex_automata::{util::syntax, Anchored, Input};
+use std::io::{self, Read, Seek, Write};
+
+/// What separates the records
+pub enum Separator {
+    Bytes(Vec<u8>),
+    Regex(Box<Regex>),
+}
+
+impl Separator {
+    /// Separator matching the regular expression `pattern` on bytes, with
+    /// `^` and `$` matching at the start and end of lines
+    pub fn regex(pattern: &str) -> Result<Self, Box<BuildError>> {
+        let config = syntax::Config::new()
+            .unicode(false)
+            .utf8(false)
+            .multi_line(true);
+        let regex = Regex::builder()
+            .syntax(config)
+            .build(pattern)
+            .map_err(Box::new)?;
+        Ok(Separator::Regex(Box::new(regex)))
+    }
+
+    /// Starts and lengths of the separators in `hay[..limit]` from the
+    /// last one backward, each ending before the next one starts, so
+    /// overlapping matches are taken from the end. Empty matches don't
+    /// separate anything and are skipped.
+    fn rfind_all(&self, hay: &[u8], limit: usize) -> Vec<(usize, usize)> {
+        let mut found = Vec::new();
+        match self {
+            Separator::Bytes(sep) => {
+                let finder = memchr::memmem::FinderRev::new(sep);
+                let mut limit = limit;
+                while let Some(start) = finder.rfind(&hay[..limit]) {
+                    found.push((start, sep.len()));
+                    limit = start;
+                }
+            }
+            Separator::Regex(regex) => {
+                // Every position some match starts at is inside one of the
+                // leftmost matches, which are found in one pass. The last
+                // position of each with a match is then looked for.
+                let spans: Vec<_> = regex
+                    .find_iter(Input::new(hay).range(..limit))
+                    .filter(|m| !m.is_empty())
+                    .map(|m| m.range())
+                    .collect();
+                let mut limit = limit;
+                for span in spans.into_iter().rev() {
+                    for start in (span.start..span.end.min(limit)).rev() {
+                        let input = Input::new(hay).range(start..limit).anchored(Anchored::Yes);
+                        if let Some(m) = regex.search(&input).filter(|m| !m.is_empty()) {
+                            found.push((start, m.len()));
+                            limit = start;
+                        }
+                    }
+                }
+            }
+        }
+        found
+    }
+}
+
+/// Failure of [`tac`], reading its input or writing the output
+#[derive(Debug)]
+pub enum Error {
+    Read(io::Error),
+    Write(io::Error),
+}
+
+/// Records of a buffer printed so far, from its end
+struct Printed {
+    /// End of the records left to print
+    end: usize,
+    /// End of the part where separators are looked for, before the
+    /// separator of the last record left when it ends the record
+    limit: usize,
+}
+
+/// Print the records of `buf` ending at `printed.end` in reverse order,
+/// but for the one at the start of `buf`, which may continue before it
+/// unless `is_start`
+fn print_records(
+    buf: &[u8],
+    printed: &mut Printed,
+    separator: &Separator,
+    is_before: bool,
+    is_start: bool,
+    out: &mut impl Write,
+) -> io::Result<()> {
+    for (start, len) in separator.rfind_all(buf, printed.limit) {
+        // A separator at the start may be part of a longer match
+        if start == 0 && !is_start {
+            break;
+        }
+        let from = if is_before { start } else { start + len };
+        out.write_all(&buf[from..printed.end])?;
+        printed.end = from;
+        printed.limit = start;
+    }
+    if is_start {
+        out.write_all(&buf[..printed.end])?;
+        printed.end = 0;
+        printed.limit = 0;
+    }
+    Ok(())
+}
+
+/// Print the records of `data` in reverse order, with separators attached
+/// before the records if `is_before` and after them otherwise
+pub fn tac_slice(
+    data: &[u8],
+    separator: &Separator,
+    is_before: bool,
+    out: &mut impl Write,
+) -> io::Result<()> {
+    let mut printed = Printed {
+        end: data.len(),
+        limit: data.len(),
+    };
+    print_records(data, &mut printed, separator, is_before, true, out)
+}
+
+/// Print the records of `file` from its current offset to its end in
+/// reverse order, reading it backward
+pub fn tac<R: Read + Seek>(
+    file: R,
+    separator: &Separator,
+    is_before: bool,
+    out: &mut impl Write,
+) -> Result<(), Error> {
+    let mut reader = ReverseReader::new(file).map_err(Error::Read)?;
+    let mut buf = Vec::new();
+    let mut printed = Printed { end: 0, limit: 0 };
+    loop {
+        let n = reader.read_before(&mut buf).map_err(Error::Read)?;
+        printed.end += n;
+        printed.limit += n;
+        let is_start = reader.is_done();
+        print_records(&buf, &mut printed, separator, is_before, is_start, out)
+            .map_err(Error::Write)?;
+        if is_start {
+            return Ok(());
+        }
+        buf.truncate(printed.end);
+    }
+}
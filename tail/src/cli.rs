@@ -0,0 +1,394 @@
+//! Command line interface of `tail`
+
+use crate::follow::{self, Follow, Followed, Headers};
+use crate::{Error, Options, Start, Unit};
+use clap::{ArgAction, CommandFactory, FromArgMatches, Parser};
+use nyaa_core::error::{self, Status, UError};
+use nyaa_core::i18n;
+use nyaa_core::input::{self, Reader};
+use nyaa_core::output::{self, OutputSink};
+use nyaa_core::posix;
+use nyaa_core::quote::shell_escape;
+use nyaa_core::response;
+use nyaa_core::size;
+use nyaa_core::trace;
+use nyaa_core::Input;
+use std::borrow::Cow;
+use std::ffi::OsString;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::time::Duration;
+
+pub(crate) const UTIL: &str = "tail";
+
+/// Lines printed when no number is given
+const DEFAULT_LINES: u64 = 10;
+
+/// Seconds between looking at followed files that are polled
+const DEFAULT_SLEEP_INTERVAL: f64 = 1.0;
+
+#[derive(Parser)]
+#[command(name = "tail", version)]
+#[command(about = "output the last part of files", long_about = None)]
+#[command(disable_help_flag = true, disable_version_flag = true)]
+struct Args {
+    /// Output the last NUM bytes, or from byte NUM on with +NUM. NUM may
+    /// have a multiplier suffix: b 512, kB 1000, K 1024, MB 1000*1000,
+    /// M 1024*1024, and so on for G, T, P, E
+    #[arg(
+        short = 'c',
+        long = "bytes",
+        value_name = "[+]NUM",
+        value_parser = parse_start,
+        allow_hyphen_values = true,
+        overrides_with = "lines"
+    )]
+    bytes: Option<Start>,
+
+    /// Output the last NUM lines instead of the last 10, or from line NUM
+    /// on with +NUM
+    #[arg(
+        short = 'n',
+        long = "lines",
+        value_name = "[+]NUM",
+        value_parser = parse_start,
+        allow_hyphen_values = true,
+        overrides_with = "bytes"
+    )]
+    lines: Option<Start>,
+
+    /// Output appended data as the file grows, following HOW, the open
+    /// file by default
+    #[arg(
+        short = 'f',
+        long = "follow",
+        value_name = "HOW",
+        value_enum,
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "descriptor"
+    )]
+    follow: Option<Follow>,
+
+    /// Same as --follow=name --retry
+    #[arg(short = 'F')]
+    is_follow_name_retry: bool,
+
+    /// Keep trying to open a file that is inaccessible
+    #[arg(long = "retry")]
+    is_retry: bool,
+
+    /// With -f, sleep about N seconds between looking at files that
+    /// can't be watched for changes
+    #[arg(
+        short = 's',
+        long = "sleep-interval",
+        value_name = "N",
+        value_parser = parse_seconds,
+        default_value_t = DEFAULT_SLEEP_INTERVAL,
+        hide_default_value = true
+    )]
+    sleep_interval: f64,
+
+    /// With -f, end after the process PID ends
+    #[arg(
+        long,
+        value_name = "PID",
+        value_parser = clap::value_parser!(u32).range(..=i64::from(i32::MAX))
+    )]
+    pid: Option<u32>,
+
+    /// Never output headers giving file names
+    #[arg(
+        short = 'q',
+        long = "quiet",
+        visible_alias = "silent",
+        overrides_with = "is_verbose"
+    )]
+    is_quiet: bool,
+
+    /// Always output headers giving file names
+    #[arg(short = 'v', long = "verbose", overrides_with = "is_quiet")]
+    is_verbose: bool,
+
+    /// Line delimiter is NUL, not newline
+    #[arg(short = 'z', long = "zero-terminated")]
+    is_zero_terminated: bool,
+
+    /// Input files. An argument @FILE stands for the arguments on the
+    /// lines of FILE, @@NAME for the file @NAME
+    files: Vec<PathBuf>,
+
+    /// Print help
+    #[arg(long, action=ArgAction::Help)]
+    help: (),
+
+    /// Print version
+    #[arg(long, action=ArgAction::Version)]
+    version: (),
+}
+
+/// `[+-]NUM` with a multiplier suffix, counting from the end unless it
+/// starts with `+`
+fn parse_start(s: &str) -> Result<Start, String> {
+    let (number, is_from) = match s.as_bytes().first() {
+        Some(b'+') => (&s[1..], true),
+        Some(b'-') => (&s[1..], false),
+        _ => (s, false),
+    };
+    let digits = number
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(number.len());
+    let n = match size::parse_size(number) {
+        Some(n) => n,
+        None if digits > 0 && size::parse_size(&format!("1{}", &number[digits..])).is_some() => {
+            return Err("number too large".to_string())
+        }
+        None => return Err("not a number with an optional multiplier suffix".to_string()),
+    };
+    Ok(if is_from {
+        Start::From(n)
+    } else {
+        Start::Last(n)
+    })
+}
+
+/// Non-negative number of seconds
+fn parse_seconds(s: &str) -> Result<f64, String> {
+    match s.parse::<f64>() {
+        Ok(secs) if secs >= 0.0 && Duration::try_from_secs_f64(secs).is_ok() => Ok(secs),
+        _ => Err("not a non-negative number of seconds".to_string()),
+    }
+}
+
+/// The arguments with the obsolete forms `-NUM[bcl][f]` and
+/// `+NUM[bcl][f]` of the first one, taken when there is at most one file
+/// operand, turned into the options they stand for
+fn expand_obsolete(mut args: Vec<OsString>) -> Vec<OsString> {
+    let operands = match args.get(2) {
+        Some(arg) if arg == "--" => args.len() - 3,
+        _ => args.len().saturating_sub(2),
+    };
+    let Some(arg) = args.get(1).and_then(|arg| arg.to_str()) else {
+        return args;
+    };
+    let Some(sign @ ('-' | '+')) = arg.chars().next() else {
+        return args;
+    };
+    let rest = &arg[1..];
+    let digits = rest
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(rest.len());
+    let (number, unit) = rest.split_at(digits);
+    let (unit, is_follow) = match unit.strip_suffix('f') {
+        Some(unit) => (unit, true),
+        None => (unit, false),
+    };
+    // `-c` alone is the option, not 10 bytes
+    let is_obsolete = operands <= 1
+        && matches!(unit, "" | "b" | "c" | "l")
+        && (!number.is_empty() || matches!((sign, unit), ('+', _) | (_, "b" | "l")))
+        && (sign == '+' || !number.is_empty() || !unit.is_empty() || !is_follow);
+    if !is_obsolete {
+        return args;
+    }
+    let number = if number.is_empty() { "10" } else { number };
+    let (option, number) = match unit {
+        "b" => ("-c", format!("{number}b")),
+        "c" => ("-c", number.to_string()),
+        _ => ("-n", number.to_string()),
+    };
+    let sign = if sign == '+' { "+" } else { "" };
+    let mut options: Vec<OsString> = vec![option.into(), format!("{sign}{number}").into()];
+    if is_follow {
+        options.push("-f".into());
+    }
+    args.splice(1..2, options);
+    args
+}
+
+/// Failure printing an input. The following inputs are still printed
+/// after a failure to read one, but not after one to write the output.
+enum Failure {
+    Open(io::Error),
+    Read(io::Error),
+    Write(io::Error),
+}
+
+impl From<Error> for Failure {
+    fn from(err: Error) -> Self {
+        match err {
+            Error::Read(e) => Failure::Read(e),
+            Error::Write(e) => Failure::Write(e),
+        }
+    }
+}
+
+/// Open `input`, as a file that can seek if it is a regular one, the
+/// standard input included
+fn open(input: &Input) -> io::Result<Reader> {
+    match input.open()? {
+        Reader::Stdin(stdin) => match input::open_descriptor(0, Path::new("/dev/stdin")) {
+            Ok(file) if file.metadata().is_ok_and(|metadata| metadata.is_file()) => {
+                Ok(Reader::File(file))
+            }
+            _ => Ok(Reader::Stdin(stdin)),
+        },
+        reader => Ok(reader),
+    }
+}
+
+/// Print the end of `reader`, seeking in regular files and reading
+/// anything else to its end
+fn tail_reader(reader: &mut Reader, options: &Options, out: &mut OutputSink) -> Result<(), Error> {
+    match reader {
+        Reader::File(file) if file.metadata().is_ok_and(|metadata| metadata.is_file()) => {
+            nyaa_core::debug!("seeking in the file");
+            crate::tail_file(&*file, options, out)
+        }
+        reader => crate::tail_stream(reader, options, out),
+    }
+}
+
+pub fn command() -> clap::Command {
+    i18n::command(Args::command())
+}
+
+/// Run `tail` with the command line `args`, starting with the program name
+pub fn main(args: Vec<OsString>) -> ExitCode {
+    let mut status = Status::default();
+    let Some(args) = status.check(UTIL, response::expand(args)) else {
+        return status.into();
+    };
+    let args = posix::operands_last(&command(), expand_obsolete(args));
+    let matches = command().get_matches_from(args);
+    let args = Args::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+    trace::init(UTIL, false);
+    let options = Options {
+        unit: if args.bytes.is_some() {
+            Unit::Bytes
+        } else {
+            Unit::Lines
+        },
+        start: args
+            .bytes
+            .or(args.lines)
+            .unwrap_or(Start::Last(DEFAULT_LINES)),
+        delimiter: if args.is_zero_terminated {
+            b'\0'
+        } else {
+            b'\n'
+        },
+    };
+    let operands = if args.files.is_empty() {
+        vec![PathBuf::from("-")]
+    } else {
+        args.files.clone()
+    };
+    let follow_options = follow::Options {
+        follow: if args.is_follow_name_retry {
+            Follow::Name
+        } else {
+            args.follow.unwrap_or(Follow::Descriptor)
+        },
+        is_retry: args.is_retry || args.is_follow_name_retry,
+        sleep_interval: Duration::from_secs_f64(args.sleep_interval),
+        // No process has the number 0, which GNU tail takes as none
+        pid: args.pid.filter(|&pid| pid != 0),
+    };
+    let is_following = args.follow.is_some() || args.is_follow_name_retry;
+    if is_following && follow_options.follow == Follow::Name {
+        if let Some(operand) = operands
+            .iter()
+            .find(|operand| Input::from_operand(operand).is_stdin())
+        {
+            let message = i18n::tr("cannot follow {} by name")
+                .replace("{}", &shell_escape(operand.as_os_str()));
+            status.report(UTIL, &UError::failure(message));
+            return status.into();
+        }
+    }
+    if args.is_retry && !is_following {
+        error::notice(
+            UTIL,
+            i18n::tr("warning: --retry ignored; --retry is useful only when following"),
+        );
+    } else if args.is_retry && follow_options.follow == Follow::Descriptor {
+        error::notice(
+            UTIL,
+            i18n::tr("warning: --retry only effective for the initial open"),
+        );
+    }
+    if args.pid.is_some() && !is_following {
+        error::notice(
+            UTIL,
+            i18n::tr("warning: PID ignored; --pid=PID is useful only when following"),
+        );
+    }
+    let mut headers = Headers {
+        is_enabled: args.is_verbose || (operands.len() > 1 && !args.is_quiet),
+        last: None,
+    };
+    let mut out = OutputSink::stdout();
+    let mut followed = Vec::new();
+    for (number, operand) in operands.iter().enumerate() {
+        let input = Input::from_operand(operand);
+        let name = match input {
+            Input::Stdin(_) => Cow::Borrowed(i18n::tr("standard input")),
+            _ => input.quoted_name().unwrap_or_default(),
+        };
+        let result = trace::operand(&name, || {
+            let reader = open(&input).map_err(Failure::Open)?;
+            headers
+                .before(number, &name, &mut out)
+                .map_err(Failure::Write)?;
+            let mut reader = reader;
+            tail_reader(&mut reader, &options, &mut out)?;
+            Ok(reader)
+        });
+        let file = match result {
+            Ok(Reader::File(file)) => Some(file),
+            // A pipe or terminal on the standard input isn't followed
+            Ok(Reader::Stdin(_)) => continue,
+            Err(Failure::Open(e)) => {
+                let name = i18n::tr("cannot open {} for reading")
+                    .replace("{}", &shell_escape(operand.as_os_str()));
+                status.report(UTIL, &UError::from(e).with_name(name));
+                None
+            }
+            Err(Failure::Read(e)) => {
+                let name =
+                    i18n::tr("error reading {}").replace("{}", &shell_escape(operand.as_os_str()));
+                status.report(UTIL, &UError::from(e).with_name(name));
+                None
+            }
+            Err(Failure::Write(e)) => {
+                status.report(UTIL, &output::write_error(e));
+                return status.into();
+            }
+        };
+        if is_following {
+            followed.push(Followed::new(
+                number,
+                name.into_owned(),
+                operand.clone(),
+                file,
+            ));
+        }
+    }
+    if !followed.is_empty() {
+        // Like GNU tail, the data followed gets a header unless it comes
+        // from the last operand, even when that one couldn't be printed
+        headers.last = headers.last.and(Some(operands.len() - 1));
+        follow::follow(
+            followed,
+            &follow_options,
+            &mut headers,
+            &mut out,
+            &mut status,
+        );
+    }
+    status.check(UTIL, out.finish());
+    status.into()
+}
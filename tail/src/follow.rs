@@ -0,0 +1,324 @@
+//! Following of files as they grow, for `-f` and `-F`. Every followed
+//! file is read to its end whenever the [`Watcher`] tells that something
+//! changed, or each time it polls. A file truncated below what was
+//! printed of it is printed again from its start. Following by name opens
+//! the file again when the name comes to stand for another one, like when
+//! logs are rotated.
+
+use crate::cli::UTIL;
+use nyaa_core::error::{self, Status, UError};
+use nyaa_core::i18n::tr;
+use nyaa_core::output::{self, OutputSink};
+use nyaa_core::platform::{self, FileInfo};
+use nyaa_core::quote::{quote, shell_escape};
+use nyaa_core::watch::Watcher;
+use std::fs;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// What is followed
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum Follow {
+    /// The open file, wherever it is renamed to
+    Descriptor,
+    /// The file the name stands for, opened again when it is replaced
+    Name,
+}
+
+pub struct Options {
+    pub follow: Follow,
+    /// Whether files that can't be opened are tried again
+    pub is_retry: bool,
+    /// Time between looking at the files when they are polled, or at the
+    /// process following ends with
+    pub sleep_interval: Duration,
+    /// Process whose end ends following, after the data written until
+    /// then is printed
+    pub pid: Option<u32>,
+}
+
+/// Headers telling which file the data printed next comes from
+pub struct Headers {
+    pub is_enabled: bool,
+    /// Number of the file the data printed last came from, with a header
+    /// printed before it unless it was the first
+    pub last: Option<usize>,
+}
+
+impl Headers {
+    /// Print the header of the file `number` named `name` before its data,
+    /// unless its data was printed last
+    pub fn before(&mut self, number: usize, name: &str, out: &mut OutputSink) -> io::Result<()> {
+        if !self.is_enabled || self.last == Some(number) {
+            return Ok(());
+        }
+        let separator = if self.last.is_some() { "\n" } else { "" };
+        self.last = Some(number);
+        writeln!(out, "{separator}==> {name} <==")
+    }
+}
+
+/// Failure writing the output, which ends following
+struct WriteError(io::Error);
+
+/// A file being followed
+pub struct Followed {
+    /// Number of the file among the operands, telling the headers apart
+    number: usize,
+    /// Name of the file in headers
+    header: String,
+    /// Operand naming the file
+    operand: PathBuf,
+    file: Option<fs::File>,
+    /// Offset up to which the file was printed
+    offset: u64,
+    /// Device and number of the file the name stood for when last looked
+    /// at, telling when it is replaced
+    id: Option<(u64, u64)>,
+}
+
+impl Followed {
+    /// Follow the operand `number`, `operand`, with headers naming it
+    /// `header`, from the offset of `file`, where it was printed up to, or
+    /// once it can be opened when it couldn't be
+    pub fn new(number: usize, header: String, operand: PathBuf, file: Option<fs::File>) -> Self {
+        let mut followed = Followed {
+            number,
+            header,
+            operand,
+            file: None,
+            offset: 0,
+            id: None,
+        };
+        if let Some(mut file) = file {
+            followed.offset = file.stream_position().unwrap_or(0);
+            followed.id = id(&followed.operand);
+            followed.file = Some(file);
+            followed.set_nonblocking();
+        }
+        followed
+    }
+
+    /// Name of the file in diagnostics, quoted when it has to be
+    fn name(&self) -> String {
+        quote(self.operand.as_os_str()).into_owned()
+    }
+
+    /// Open the file the name stands for now, to print it from its start.
+    /// Returns false when it is a directory, which can't be followed.
+    fn reopen(&mut self) -> io::Result<bool> {
+        self.file = None;
+        self.id = id(&self.operand);
+        let file = fs::File::open(&self.operand)?;
+        if file.metadata()?.is_dir() {
+            return Ok(false);
+        }
+        self.file = Some(file);
+        self.offset = 0;
+        self.set_nonblocking();
+        Ok(true)
+    }
+
+    /// Make reading pipes and devices return rather than wait for data, so
+    /// the other files are still followed
+    fn set_nonblocking(&self) {
+        #[cfg(unix)]
+        if let Some(file) = &self.file {
+            use std::os::fd::AsRawFd;
+            if file.metadata().is_ok_and(|metadata| !metadata.is_file()) {
+                // SAFETY: the descriptor is open for the calls, which take
+                // no pointers
+                unsafe {
+                    let flags = libc::fcntl(file.as_raw_fd(), libc::F_GETFL);
+                    libc::fcntl(file.as_raw_fd(), libc::F_SETFL, flags | libc::O_NONBLOCK);
+                }
+            }
+        }
+    }
+
+    /// Print what was written to the open file since it was last read.
+    /// Returns false when the file is given up on after a read error.
+    fn print_new(
+        &mut self,
+        headers: &mut Headers,
+        out: &mut OutputSink,
+        status: &mut Status,
+    ) -> Result<bool, WriteError> {
+        let Some(file) = &mut self.file else {
+            return Ok(true);
+        };
+        let metadata = file.metadata();
+        if metadata.is_ok_and(|metadata| metadata.is_file() && metadata.len() < self.offset) {
+            out.flush().map_err(WriteError)?;
+            let name = quote(self.operand.as_os_str());
+            error::notice(UTIL, format!("{name}: {}", tr("file truncated")));
+            self.offset = file.seek(SeekFrom::Start(0)).unwrap_or(0);
+        }
+        let mut buf = vec![0; 64 * 1024];
+        loop {
+            let n = match file.read(&mut buf) {
+                Ok(0) => return Ok(true),
+                Ok(n) => n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => return Ok(true),
+                Err(e) => {
+                    out.flush().map_err(WriteError)?;
+                    let name = tr("error reading {}")
+                        .replace("{}", &shell_escape(self.operand.as_os_str()));
+                    status.report(UTIL, &UError::from(e).with_name(name));
+                    self.file = None;
+                    return Ok(false);
+                }
+            };
+            headers
+                .before(self.number, &self.header, out)
+                .map_err(WriteError)?;
+            out.write_all(&buf[..n]).map_err(WriteError)?;
+            self.offset += n as u64;
+        }
+    }
+
+    /// Look at what the name stands for now, opening the file again when
+    /// it was replaced or appeared. Returns false when the file is given
+    /// up on.
+    fn check_name(
+        &mut self,
+        options: &Options,
+        watcher: &mut Watcher,
+        headers: &mut Headers,
+        out: &mut OutputSink,
+        status: &mut Status,
+    ) -> Result<bool, WriteError> {
+        let info = FileInfo::from_path(&self.operand);
+        let new_id = info.as_ref().ok().map(|info| (info.dev, info.ino));
+        // Unless it is still missing, or still what couldn't be followed
+        if new_id == self.id {
+            return Ok(true);
+        }
+        // The rest of the file the name stood for comes first
+        if !self.print_new(headers, out, status)? {
+            return Ok(false);
+        }
+        out.flush().map_err(WriteError)?;
+        let was_open = self.file.is_some();
+        if let Err(e) = info {
+            self.file = None;
+            self.id = None;
+            if !options.is_retry {
+                status.report(UTIL, &UError::from(e).with_name(self.name()));
+                return Ok(false);
+            }
+            let message = tr("{} has become inaccessible")
+                .replace("{}", &shell_escape(self.operand.as_os_str()));
+            error::notice(UTIL, UError::from(e).with_name(message));
+            return Ok(true);
+        }
+        match self.reopen() {
+            Ok(true) => {
+                let message = if was_open {
+                    tr("{} has been replaced;  following new file")
+                } else {
+                    tr("{} has appeared;  following new file")
+                };
+                let message = message.replace("{}", &shell_escape(self.operand.as_os_str()));
+                error::notice(UTIL, message);
+                watcher.add(&self.operand);
+                Ok(true)
+            }
+            Ok(false) => Ok(self.give_up(options, status)),
+            Err(e) => {
+                let name = tr("cannot open {} for reading")
+                    .replace("{}", &shell_escape(self.operand.as_os_str()));
+                status.report(UTIL, &UError::from(e).with_name(name));
+                Ok(options.is_retry)
+            }
+        }
+    }
+
+    /// Report that the file isn't of a type that can be followed. Returns
+    /// whether the name is still followed, for when it is replaced.
+    fn give_up(&self, options: &Options, status: &mut Status) -> bool {
+        let message = if options.is_retry {
+            "cannot follow end of this type of file"
+        } else {
+            "cannot follow end of this type of file; giving up on this name"
+        };
+        status.report(UTIL, &UError::failure(message).with_name(self.name()));
+        options.is_retry
+    }
+
+    /// Bring the output up to date with the file. Returns false when the
+    /// file is given up on.
+    fn update(
+        &mut self,
+        options: &Options,
+        watcher: &mut Watcher,
+        headers: &mut Headers,
+        out: &mut OutputSink,
+        status: &mut Status,
+    ) -> Result<bool, WriteError> {
+        if self.file.is_none() && !options.is_retry {
+            if self.operand.is_dir() {
+                return Ok(self.give_up(options, status));
+            }
+            return Ok(false);
+        }
+        let is_by_name = options.follow == Follow::Name || self.file.is_none();
+        if is_by_name && !self.check_name(options, watcher, headers, out, status)? {
+            return Ok(false);
+        }
+        self.print_new(headers, out, status)
+    }
+}
+
+/// Device and number of the file `path` stands for
+fn id(path: &std::path::Path) -> Option<(u64, u64)> {
+    FileInfo::from_path(path)
+        .ok()
+        .map(|info| (info.dev, info.ino))
+}
+
+/// Print what is written to the `files` from now on, with a header when
+/// the data comes from another file than the data printed before it, until
+/// none of them can be followed anymore, the process of `options.pid` ends
+/// or writing the output fails
+pub fn follow(
+    mut files: Vec<Followed>,
+    options: &Options,
+    headers: &mut Headers,
+    out: &mut OutputSink,
+    status: &mut Status,
+) {
+    let mut watcher = Watcher::new();
+    for followed in &files {
+        watcher.add(&followed.operand);
+    }
+    loop {
+        // Looked at first, so what was written before the end is printed
+        let is_ended = options
+            .pid
+            .is_some_and(|pid| !platform::is_process_running(pid));
+        let mut i = 0;
+        while i < files.len() {
+            match files[i].update(options, &mut watcher, headers, out, status) {
+                Ok(true) => i += 1,
+                Ok(false) => drop(files.remove(i)),
+                Err(WriteError(e)) => return status.report(UTIL, &output::write_error(e)),
+            }
+        }
+        if let Err(e) = out.flush() {
+            return status.report(UTIL, &output::write_error(e));
+        }
+        if is_ended {
+            return;
+        }
+        if files.is_empty() {
+            status.report(UTIL, &UError::failure("no files remaining"));
+            return;
+        }
+        let timeout =
+            (watcher.is_polling() || options.pid.is_some()).then_some(options.sleep_interval);
+        watcher.wait(timeout);
+    }
+}
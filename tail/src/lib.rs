@@ -0,0 +1,227 @@
+//! Printing of the end of files with the semantics of `tail`.
+//!
+//! The part to print is chosen by a [`Start`] in lines or bytes. Regular
+//! files are printed by [`tail_file`], which seeks to the end and reads
+//! backward only as far as the last lines reach. Pipes and other input
+//! that can't seek are printed by [`tail_stream`], which keeps only the
+//! blocks read last that the end can be in.
+//!
+//! ```
+//! use nyaa_tail::{Options, Start, Unit};
+//!
+//! let options = Options {
+//!     unit: Unit::Lines,
+//!     start: Start::Last(2),
+//!     delimiter: b'\n',
+//! };
+//! let mut out = Vec::new();
+//! nyaa_tail::tail_stream(&b"one\ntwo\nthree\n"[..], &options, &mut out).unwrap();
+//! assert_eq!(out, b"two\nthree\n");
+//! ```
+
+pub mod cli;
+mod follow;
+
+use nyaa_core::reverse::ReverseReader;
+use std::collections::VecDeque;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+
+const BUF_SIZE: usize = 64 * 1024;
+
+/// What [`Start`] counts
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Unit {
+    Lines,
+    Bytes,
+}
+
+/// Where the printed part starts
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Start {
+    /// The last N lines or bytes, `-n N`
+    Last(u64),
+    /// From the Nth line or byte on, counting from 1, `-n +N`
+    From(u64),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Options {
+    pub unit: Unit,
+    pub start: Start,
+    /// Byte ending the lines, newline or NUL
+    pub delimiter: u8,
+}
+
+/// Failure of printing, reading the input or writing the output
+#[derive(Debug)]
+pub enum Error {
+    Read(io::Error),
+    Write(io::Error),
+}
+
+/// Copy the rest of `reader` to `out`
+fn copy(reader: &mut impl Read, out: &mut impl Write) -> Result<(), Error> {
+    let mut buf = vec![0; BUF_SIZE];
+    loop {
+        let n = match reader.read(&mut buf) {
+            Ok(0) => return Ok(()),
+            Ok(n) => n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(Error::Read(e)),
+        };
+        out.write_all(&buf[..n]).map_err(Error::Write)?;
+    }
+}
+
+/// Skip `n` units of `reader` and copy the rest to `out`
+fn skip_and_copy(
+    reader: &mut impl Read,
+    n: u64,
+    options: &Options,
+    out: &mut impl Write,
+) -> Result<(), Error> {
+    if options.unit == Unit::Bytes {
+        let skipped = io::copy(&mut reader.take(n), &mut io::sink()).map_err(Error::Read)?;
+        if skipped < n {
+            return Ok(());
+        }
+        return copy(reader, out);
+    }
+    let mut buf = vec![0; BUF_SIZE];
+    let mut left = n;
+    while left > 0 {
+        let len = match reader.read(&mut buf) {
+            Ok(0) => return Ok(()),
+            Ok(len) => len,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(Error::Read(e)),
+        };
+        for i in memchr::memchr_iter(options.delimiter, &buf[..len]) {
+            left -= 1;
+            if left == 0 {
+                out.write_all(&buf[i + 1..len]).map_err(Error::Write)?;
+                break;
+            }
+        }
+    }
+    copy(reader, out)
+}
+
+/// Offset in `data` of the last `n` lines, where a delimiter at the end
+/// ends the last line rather than starting an empty one
+fn last_lines(data: &[u8], n: u64, delimiter: u8) -> usize {
+    if n == 0 {
+        return data.len();
+    }
+    let text = match data.last() {
+        Some(&last) if last == delimiter => &data[..data.len() - 1],
+        _ => data,
+    };
+    memchr::memrchr_iter(delimiter, text)
+        .nth(usize::try_from(n - 1).unwrap_or(usize::MAX))
+        .map_or(0, |i| i + 1)
+}
+
+/// Print the end of `file` from its current offset, seeking to where it
+/// starts. Only the last lines are read to find it.
+pub fn tail_file<F: Read + Seek>(
+    mut file: F,
+    options: &Options,
+    out: &mut impl Write,
+) -> Result<(), Error> {
+    let start = match (options.unit, options.start) {
+        (Unit::Lines, Start::From(n)) => {
+            return skip_and_copy(&mut file, n.saturating_sub(1), options, out)
+        }
+        (Unit::Bytes, Start::From(n)) => {
+            let offset = file.stream_position().map_err(Error::Read)?;
+            offset.saturating_add(n.saturating_sub(1))
+        }
+        (Unit::Bytes, Start::Last(n)) => {
+            let offset = file.stream_position().map_err(Error::Read)?;
+            let end = file.seek(SeekFrom::End(0)).map_err(Error::Read)?;
+            end.saturating_sub(n).max(offset)
+        }
+        (Unit::Lines, Start::Last(0)) => file.seek(SeekFrom::End(0)).map_err(Error::Read)?,
+        (Unit::Lines, Start::Last(n)) => {
+            let mut reader = ReverseReader::new(&mut file).map_err(Error::Read)?;
+            let mut block = Vec::new();
+            let mut left = n;
+            let mut is_last = true;
+            'blocks: loop {
+                block.clear();
+                if reader.read_before(&mut block).map_err(Error::Read)? == 0 {
+                    break reader.position();
+                }
+                let mut text = &block[..];
+                // The delimiter at the end ends the last line
+                if std::mem::take(&mut is_last) && text.last() == Some(&options.delimiter) {
+                    text = &text[..text.len() - 1];
+                }
+                for i in memchr::memrchr_iter(options.delimiter, text) {
+                    left -= 1;
+                    if left == 0 {
+                        break 'blocks reader.position() + i as u64 + 1;
+                    }
+                }
+            }
+        }
+    };
+    file.seek(SeekFrom::Start(start)).map_err(Error::Read)?;
+    copy(&mut file, out)
+}
+
+/// Print the end of `reader`, which is read to its end. The blocks the
+/// end can't be in are dropped as soon as later ones are read.
+pub fn tail_stream(
+    mut reader: impl Read,
+    options: &Options,
+    out: &mut impl Write,
+) -> Result<(), Error> {
+    let n = match options.start {
+        Start::From(n) => return skip_and_copy(&mut reader, n.saturating_sub(1), options, out),
+        Start::Last(n) => n,
+    };
+    // Blocks read last with the number of units in each
+    let mut blocks: VecDeque<(Vec<u8>, u64)> = VecDeque::new();
+    let mut total = 0;
+    let mut buf = vec![0; BUF_SIZE];
+    loop {
+        let len = match reader.read(&mut buf) {
+            Ok(0) => break,
+            Ok(len) => len,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(Error::Read(e)),
+        };
+        // Copied out, so short reads of pipes don't hold whole buffers
+        let block = buf[..len].to_vec();
+        let units = match options.unit {
+            Unit::Lines => memchr::memchr_iter(options.delimiter, &block).count() as u64,
+            Unit::Bytes => len as u64,
+        };
+        blocks.push_back((block, units));
+        total += units;
+        // The first block is dropped when the others hold more than
+        // enough, lines needing one more delimiter in case the last one
+        // ends the input
+        while let Some(&(_, first)) = blocks.front() {
+            let is_enough = match options.unit {
+                Unit::Lines => total - first > n,
+                Unit::Bytes => total - first >= n,
+            };
+            if !is_enough {
+                break;
+            }
+            blocks.pop_front();
+            total -= first;
+        }
+    }
+    let data: Vec<u8> = blocks.into_iter().flat_map(|(block, _)| block).collect();
+    let start = match options.unit {
+        Unit::Lines => last_lines(&data, n, options.delimiter),
+        Unit::Bytes => data
+            .len()
+            .saturating_sub(n.try_into().unwrap_or(usize::MAX)),
+    };
+    out.write_all(&data[start..]).map_err(Error::Write)
+}
@@ -0,0 +1,1062 @@
+//! Golden tests: every case runs an applet and its GNU coreutils
+//! counterpart with the same arguments and input in `tests/fixtures`, and
+//! compares their stdout, stderr and exit code. Cases are skipped when
+//! the GNU utility isn't installed. Intentional differences are opted out
+//! of per case, with the reason. The cases of an applet run when its
+//! feature is enabled.
+
+// The harness is unused in builds without any applet
+#![allow(dead_code)]
+
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Output, Stdio};
+
+/// Part of the result of a run
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Part {
+    Stdout,
+    Stderr,
+    Code,
+}
+
+struct Case {
+    args: &'static [&'static str],
+    stdin: &'static [u8],
+    /// Parts that differ from GNU on purpose, and why
+    differs: &'static [(Part, &'static str)],
+}
+
+const fn case(args: &'static [&'static str]) -> Case {
+    Case {
+        args,
+        stdin: b"",
+        differs: &[],
+    }
+}
+
+const fn with_stdin(args: &'static [&'static str], stdin: &'static [u8]) -> Case {
+    Case {
+        args,
+        stdin,
+        differs: &[],
+    }
+}
+
+fn fixtures() -> &'static Path {
+    Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures"))
+}
+
+fn run(mut command: Command, stdin: &[u8]) -> Output {
+    command
+        .current_dir(fixtures())
+        .env("LC_ALL", "C.UTF-8")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    let mut child = command.spawn().expect("failed to run the utility");
+    // The utility may exit without reading all of its input
+    let _ = child.stdin.take().unwrap().write_all(stdin);
+    child
+        .wait_with_output()
+        .expect("failed to wait for the utility")
+}
+
+/// Whether the system utility `util` is the one of GNU coreutils, or of
+/// the GNU package of its own like `grep`
+fn is_gnu(util: &str) -> bool {
+    Command::new(util)
+        .arg("--version")
+        .output()
+        .is_ok_and(|output| {
+            let version = String::from_utf8_lossy(&output.stdout);
+            version.contains("GNU coreutils") || version.contains(&format!("(GNU {util})"))
+        })
+}
+
+/// Run the cases of `applet`, failing with a report of every mismatch
+fn check(applet: &str, cases: &[Case]) {
+    if !is_gnu(applet) {
+        eprintln!("skipping the golden tests of {applet}: GNU {applet} is not installed");
+        return;
+    }
+    let mut failures = Vec::new();
+    for case in cases {
+        let mut nyaa = Command::new(env!("CARGO_BIN_EXE_nyaa"));
+        nyaa.arg(applet).args(case.args);
+        let mut gnu = Command::new(applet);
+        gnu.args(case.args);
+        let (ours, theirs) = (run(nyaa, case.stdin), run(gnu, case.stdin));
+        let parts = [
+            (Part::Stdout, ours.stdout, theirs.stdout),
+            (Part::Stderr, ours.stderr, theirs.stderr),
+            (
+                Part::Code,
+                format!("{:?}", ours.status.code()).into_bytes(),
+                format!("{:?}", theirs.status.code()).into_bytes(),
+            ),
+        ];
+        for (part, ours, theirs) in parts {
+            if ours != theirs && !case.differs.iter().any(|(differs, _)| *differs == part) {
+                failures.push(format!(
+                    "{applet} {}: {part:?} differs\n  nyaa: {:?}\n  GNU:  {:?}",
+                    case.args.join(" "),
+                    String::from_utf8_lossy(&ours),
+                    String::from_utf8_lossy(&theirs),
+                ));
+            }
+        }
+    }
+    assert!(failures.is_empty(), "\n{}", failures.join("\n"));
+}
+
+#[test]
+#[cfg(feature = "cat")]
+fn cat() {
+    check(
+        "cat",
+        &[
+            case(&["lines.txt"]),
+            case(&["lines.txt", "no-newline.txt", "empty.txt", "utf8.txt"]),
+            case(&["-n", "lines.txt", "no-newline.txt", "lines.txt"]),
+            case(&["-b", "lines.txt", "tabs.txt"]),
+            case(&["-A", "tabs.txt", "utf8.txt"]),
+            case(&["-T", "tabs.txt"]),
+            case(&["lines.txt", "missing.txt", "tabs.txt"]),
+            case(&["."]),
+            with_stdin(&[], b"one\ntwo\n"),
+            with_stdin(&["-", "lines.txt", "-"], b"one\ntwo\n"),
+            with_stdin(&["-s"], b"\n\n\none\n\n\n\ntwo\n\n"),
+            with_stdin(&["-sn"], b"\n\n\none\n\n\n\ntwo\n\n"),
+            with_stdin(&["-v"], b"\x01\x1b[0m\x7f\x80\x9f\xa0\xff\t\n"),
+            with_stdin(&["-E"], b"dos\r\nline\r\rend\r"),
+            with_stdin(&["-et"], b"a\tb\r\n\x00"),
+            Case {
+                args: &["--bogus"],
+                stdin: b"",
+                differs: &[
+                    (Part::Stderr, "usage errors are reported by clap"),
+                    (Part::Code, "usage errors exit with 2, GNU cat exits with 1"),
+                ],
+            },
+        ],
+    );
+}
+
+#[test]
+#[cfg(feature = "echo")]
+fn echo() {
+    check(
+        "echo",
+        &[
+            case(&[]),
+            case(&["a", "", "b"]),
+            case(&["-n", "a", "-n"]),
+            case(&["-nE", "-e", r"a\tb\c", "c"]),
+            case(&["-E", r"a\tb"]),
+            case(&["-e", r"\a\b\e\f\n\r\t\v\\\q\"]),
+            case(&["-e", r"\0101\01010\101\1010\0\08\0777\400"]),
+            case(&["-e", r"\x41\x4a4\xg\x"]),
+            case(&["-nx", "--", "-", "-n"]),
+            case(&["--help", "a"]),
+            case(&["-n", "--version"]),
+        ],
+    );
+}
+
+#[test]
+#[cfg(feature = "false")]
+fn false_() {
+    check(
+        "false",
+        &[
+            case(&[]),
+            case(&["a", "--bogus"]),
+            case(&["--help", "a"]),
+            case(&["--", "--version"]),
+        ],
+    );
+}
+
+#[test]
+#[cfg(feature = "grep")]
+fn grep() {
+    const LINES: &[u8] = b"apple\nBanana\ncherry pie\n\nbanana split\nno newline";
+    const BINARY: &[u8] = b"x\n\0\nx y\n";
+    check(
+        "grep",
+        &[
+            case(&["-E", "o", "lines.txt"]),
+            case(&["-E", "-n", "^[a-z]+ ", "lines.txt", "tabs.txt"]),
+            case(&["-E", "-c", "a", "lines.txt", "empty.txt", "no-newline.txt"]),
+            case(&["-E", "-l", "e", "lines.txt", "tabs.txt", "utf8.txt"]),
+            case(&["-E", "-L", "e", "lines.txt", "tabs.txt", "utf8.txt"]),
+            case(&["-E", "-v", ".", "lines.txt"]),
+            case(&["-E", "(ca|\\<dog)f?", "lines.txt", "utf8.txt"]),
+            case(&["-E", "^.{5}$", "utf8.txt"]),
+            case(&["-E", "[[:upper:]]|[[:space:]]{2}", "lines.txt", "tabs.txt"]),
+            with_stdin(&["-E", "-i", "banana"], LINES),
+            with_stdin(&["-E", "-e", "^a", "-e", "pie$"], LINES),
+            with_stdin(&["-E", "-e", "apple\ncherry"], LINES),
+            with_stdin(&["-E", "-vn", "-e", "an|pp"], LINES),
+            with_stdin(&["-E", "-c", "-i", "^b"], LINES),
+            with_stdin(&["-E", "newline", "-", "lines.txt"], LINES),
+            with_stdin(&["-E", "-f", "-", "lines.txt"], b"fox\nlazy\n"),
+            with_stdin(&["-E", "-f", "empty.txt"], LINES),
+            with_stdin(&["-E", "-v", "-f", "empty.txt"], LINES),
+            with_stdin(&["-E", "-v", ""], LINES),
+            with_stdin(&["-E", "-q", "x", "missing.txt", "-"], b"x\n"),
+            with_stdin(&["-E", "-q", "x", "-", "missing.txt"], b"x\n"),
+            with_stdin(&["-E", "-l", "-c", "a"], LINES),
+            case(&["-E", "x", "missing.txt", "lines.txt"]),
+            case(&["-E", "-f", "missing.txt", "lines.txt"]),
+            case(&["-E", "a", "."]),
+            case(&["-E", "*quick|a{,2}z", "lines.txt"]),
+            case(&["-E", "a)", "lines.txt"]),
+            case(&["-E", "(a", "lines.txt"]),
+            case(&["-E", "[[:alpha:]", "lines.txt"]),
+            case(&["-E", "[:alpha:]", "lines.txt"]),
+            case(&["-E", "a{2,1}", "lines.txt"]),
+            case(&["-E", "a\\", "lines.txt"]),
+            case(&["-E", "-r", "quick"]),
+            case(&["-E", "-rn", "quick", "."]),
+            case(&["-E", "-r", "-l", "--include=u*", "e", "."]),
+            case(&["-E", "-r", "--exclude=lines*", "quick"]),
+            case(&["-E", "-r", "--exclude-dir=.", "quick", "."]),
+            case(&[
+                "-E",
+                "--exclude=*.txt",
+                "--include=u*",
+                "o",
+                "lines.txt",
+                "utf8.txt",
+            ]),
+            case(&["-E", "-h", "o", "lines.txt", "utf8.txt"]),
+            case(&["-E", "-H", "-c", "o", "lines.txt"]),
+            case(&["-E", "-C1", "-n", "^j", "lines.txt", "tabs.txt"]),
+            case(&["-E", "-A1", "-b", "quick|lazy", "lines.txt"]),
+            case(&["-E", "-B2", "-c", "lazy", "lines.txt"]),
+            case(&[
+                "-E",
+                "-o",
+                "-b",
+                "-n",
+                "[a-z]{4,}|o",
+                "lines.txt",
+                "utf8.txt",
+            ]),
+            case(&["-E", "-o", "-v", "-C1", "^$|[aeiou]{2}", "lines.txt"]),
+            case(&["-E", "-C", "x", "a", "lines.txt"]),
+            with_stdin(&["-E", "-A0", "-o", "an|anana"], LINES),
+            with_stdin(&["-E", "-B1", "-A", "1", "-C", "9", "pie"], LINES),
+            case(&["a\\{2\\}\\|^p\\(i\\)e*", "lines.txt"]),
+            case(&["-o", "*\\|+a\\+", "lines.txt"]),
+            with_stdin(&["^*"], b"*x\nyz\n"),
+            with_stdin(&["-o", "\\(fo\\)\\1"], b"fofo\nfoba\nxfofofo\n"),
+            with_stdin(&["-E", "-n", "^(a|b)*\\1$"], b"abaa\naab\nbb\n"),
+            case(&["\\(a\\1\\)", "lines.txt"]),
+            case(&["-G", "-E", "a", "lines.txt"]),
+            case(&["-E", "-E", "a", "lines.txt"]),
+            case(&["a\\)", "lines.txt"]),
+            case(&["-F", "-o", "-e", "an", "-e", "anana", "lines.txt"]),
+            case(&["-F", "-i", "-n", "A{", "lines.txt"]),
+            with_stdin(&["-F", "-c", "-e", "x", "-e", ""], LINES),
+            with_stdin(&["-E", "x"], BINARY),
+            with_stdin(&["-E", "-c", "x"], BINARY),
+            with_stdin(&["-E", "-a", "x"], BINARY),
+            with_stdin(&["-E", "-I", "x"], BINARY),
+            with_stdin(&["-E", "--binary-files=without-match", "-a", "x"], BINARY),
+            Case {
+                args: &[],
+                stdin: b"",
+                differs: &[(Part::Stderr, "usage errors are reported by clap")],
+            },
+            Case {
+                args: &["--bogus", "x"],
+                stdin: b"",
+                differs: &[(Part::Stderr, "usage errors are reported by clap")],
+            },
+        ],
+    );
+}
+
+#[test]
+#[cfg(feature = "paste")]
+fn paste() {
+    check(
+        "paste",
+        &[
+            case(&["lines.txt", "no-newline.txt"]),
+            case(&["lines.txt", "empty.txt", "utf8.txt"]),
+            case(&["-s", "lines.txt", "no-newline.txt", "tabs.txt"]),
+            case(&["-d", ":,", "lines.txt", "tabs.txt", "utf8.txt"]),
+            case(&["-s", "-d", "\\n\\0", "lines.txt"]),
+            case(&["-d", "", "lines.txt", "utf8.txt"]),
+            case(&["lines.txt", "missing.txt"]),
+            case(&["-s", "lines.txt", "missing.txt", "tabs.txt"]),
+            with_stdin(&[], b"one\ntwo"),
+            with_stdin(&["-", "-", "lines.txt"], b"1\n2\n3\n4\n5\n"),
+            with_stdin(&["-z", "-d", ",", "-", "-"], b"a\0b\0c\0"),
+            Case {
+                args: &["--bogus"],
+                stdin: b"",
+                differs: &[
+                    (Part::Stderr, "usage errors are reported by clap"),
+                    (
+                        Part::Code,
+                        "usage errors exit with 2, GNU paste exits with 1",
+                    ),
+                ],
+            },
+        ],
+    );
+}
+
+#[test]
+#[cfg(feature = "printf")]
+fn printf() {
+    check(
+        "printf",
+        &[
+            case(&["%s-%s\\n", "a", "b", "c"]),
+            case(&[
+                "%d %i %o %u %x %X %c|%5s|%-5.2s|\\n",
+                "42",
+                "-7",
+                "8",
+                "-1",
+                "255",
+                "0x1f",
+                "abc",
+                "ab",
+                "abc",
+            ]),
+            case(&[
+                "%+05d|% d|%.3d|%.0d|%#o|%#x|%#X\\n",
+                "42",
+                "7",
+                "5",
+                "0",
+                "8",
+                "255",
+                "0",
+            ]),
+            case(&["%f %e %g %a %A\\n", "0.1", "0.1", "0.1", "0.1", "-255.5"]),
+            case(&["%.20f %.30e %.40g\\n", "0.1", "0.1", "0.1"]),
+            case(&[
+                "%.0f %.0f %.0f %#g %#.0f %G %g\\n",
+                "0.5",
+                "1.5",
+                "2.5",
+                "1",
+                "1",
+                "1e-10",
+                "1000000",
+            ]),
+            case(&[
+                "%f %e %g %a %5f|%-6e|\\n",
+                "-0",
+                "-0",
+                "1e4932",
+                "0x1p-16440",
+                "nan",
+                "-inf",
+            ]),
+            case(&["%010.3f|%010a|%010f\\n", "-3.14159", "-3", "inf"]),
+            case(&[
+                "%*d|%-*d|%.*d|%*.*s|\\n",
+                "5",
+                "1",
+                "-5",
+                "2",
+                "3",
+                "4",
+                "6",
+                "2",
+                "abc",
+            ]),
+            case(&["%Lf %lld %hhx %zd\\n", "1.5", "3", "255", "1"]),
+            case(&["%b|%b\\n", "a\\tb\\0101\\101\\x41", "x\\cy"]),
+            case(&[
+                "%q %q %q %q %q %q\\n",
+                "a b",
+                "a'b",
+                "a\nb",
+                "",
+                "~x",
+                "a'b c",
+            ]),
+            case(&["\\u00e9\\U0001F600\\x41\\101\\0101\\e\\q\\\"\\n"]),
+            case(&["a\\cb", "x"]),
+            case(&["%d\\n", "'A", "'é", "'ab"]),
+            Case {
+                args: &["x\\n", "a", "b"],
+                stdin: b"",
+                differs: &[(
+                    Part::Stderr,
+                    "quoted with ASCII quotes, GNU uses the locale's",
+                )],
+            },
+            case(&["%s %s\\n", "a", "b", "c"]),
+            case(&["--", "%s\\n", "x"]),
+            Case {
+                args: &["--help", "x"],
+                stdin: b"",
+                differs: &[(
+                    Part::Stderr,
+                    "quoted with ASCII quotes, GNU uses the locale's",
+                )],
+            },
+            Case {
+                args: &["%d\\n", "abc"],
+                stdin: b"",
+                differs: &[(
+                    Part::Stderr,
+                    "quoted with ASCII quotes, GNU uses the locale's",
+                )],
+            },
+            Case {
+                args: &["%d\\n", "12abc"],
+                stdin: b"",
+                differs: &[(
+                    Part::Stderr,
+                    "quoted with ASCII quotes, GNU uses the locale's",
+                )],
+            },
+            Case {
+                args: &["%d\\n", "99999999999999999999"],
+                stdin: b"",
+                differs: &[(
+                    Part::Stderr,
+                    "quoted with ASCII quotes, GNU uses the locale's",
+                )],
+            },
+            Case {
+                args: &["%f\\n", "1e"],
+                stdin: b"",
+                differs: &[(
+                    Part::Stderr,
+                    "quoted with ASCII quotes, GNU uses the locale's",
+                )],
+            },
+            Case {
+                args: &["%*d\\n", "2147483648", "1"],
+                stdin: b"",
+                differs: &[(
+                    Part::Stderr,
+                    "quoted with ASCII quotes, GNU uses the locale's",
+                )],
+            },
+            Case {
+                args: &["%.*d\\n", "99999999999", "1"],
+                stdin: b"",
+                differs: &[(
+                    Part::Stderr,
+                    "quoted with ASCII quotes, GNU uses the locale's",
+                )],
+            },
+            case(&["%z"]),
+            case(&["a%5"]),
+            case(&["%#d", "1"]),
+            case(&["%.1c", "x"]),
+            case(&["\\x"]),
+            case(&["\\u0041"]),
+            Case {
+                args: &[],
+                stdin: b"",
+                differs: &[
+                    (Part::Stderr, "GNU printf adds a hint to try --help"),
+                    (
+                        Part::Code,
+                        "usage errors exit with 2, GNU printf exits with 1",
+                    ),
+                ],
+            },
+        ],
+    );
+}
+
+#[test]
+#[cfg(feature = "seq")]
+fn seq() {
+    check(
+        "seq",
+        &[
+            case(&["5"]),
+            case(&["-3", "2"]),
+            case(&["10", "-3", "1"]),
+            case(&["2", "1"]),
+            case(&["0.1", "0.1", "1"]),
+            case(&["1.2", "-0.3", "0.3"]),
+            case(&["0", ".000001", ".000003"]),
+            case(&["-0", "1"]),
+            case(&["99999999999999999999", "100000000000000000002"]),
+            case(&["1", "0x1p-1", "2"]),
+            case(&["0x1.8", "3"]),
+            case(&["1e1", "12"]),
+            case(&["1", "1e-1", "1.2"]),
+            case(&["0", "inf", "1"]),
+            case(&["-1", "-inf"]),
+            case(&["-w", "9", "11"]),
+            case(&["-w", "-.5", ".5", "1"]),
+            case(&["-w", "10e-1", "2"]),
+            case(&["-w", "1.5e1", "2", "20"]),
+            case(&["-w", "1", "1", "2.50"]),
+            case(&["-w", "0x9", "11"]),
+            case(&["-s", ", ", "1", "3"]),
+            case(&["-s,", "-w", "8", "10"]),
+            case(&["--separator=", "1", "3"]),
+            case(&["-f", "%05.1f", "1", "3"]),
+            case(&["-f", "x%%%gy%%", "1", "2"]),
+            case(&["-f", "%.1f", "0", "0.1000001", "0.3"]),
+            case(&["-f", "%-+8.3e|", "1", "0.5", "2"]),
+            case(&["--format=%#Lg", "999999", "1000001"]),
+            case(&["-f", "%a", "1", "3"]),
+            case(&["-f", "%g", "1e20", "1e19", "1.5e20"]),
+            Case {
+                args: &["-f", "%d", "1"],
+                stdin: b"",
+                differs: &[(
+                    Part::Stderr,
+                    "quoted with ASCII quotes, GNU uses the locale's",
+                )],
+            },
+            Case {
+                args: &["-f", "%%", "1"],
+                stdin: b"",
+                differs: &[(
+                    Part::Stderr,
+                    "quoted with ASCII quotes, GNU uses the locale's",
+                )],
+            },
+            Case {
+                args: &["-f", "%g%", "1"],
+                stdin: b"",
+                differs: &[(
+                    Part::Stderr,
+                    "quoted with ASCII quotes, GNU uses the locale's",
+                )],
+            },
+            Case {
+                args: &["-f", "a%", "1"],
+                stdin: b"",
+                differs: &[(
+                    Part::Stderr,
+                    "quoted with ASCII quotes, GNU uses the locale's",
+                )],
+            },
+            Case {
+                args: &["-w", "-f", "%x", "1"],
+                stdin: b"",
+                differs: &[(
+                    Part::Stderr,
+                    "quoted with ASCII quotes, GNU uses the locale's",
+                )],
+            },
+            Case {
+                args: &["-w", "-f", "%g", "1"],
+                stdin: b"",
+                differs: &[
+                    (Part::Stderr, "GNU seq adds a hint to try --help"),
+                    (Part::Code, "usage errors exit with 2, GNU seq exits with 1"),
+                ],
+            },
+            Case {
+                args: &[],
+                stdin: b"",
+                differs: &[
+                    (Part::Stderr, "GNU seq adds a hint to try --help"),
+                    (Part::Code, "usage errors exit with 2, GNU seq exits with 1"),
+                ],
+            },
+            Case {
+                args: &["1", "2", "3", "4"],
+                stdin: b"",
+                differs: &[
+                    (Part::Stderr, "GNU seq adds a hint to try --help"),
+                    (Part::Code, "usage errors exit with 2, GNU seq exits with 1"),
+                ],
+            },
+            Case {
+                args: &["x"],
+                stdin: b"",
+                differs: &[
+                    (Part::Stderr, "GNU seq adds a hint to try --help"),
+                    (Part::Code, "usage errors exit with 2, GNU seq exits with 1"),
+                ],
+            },
+            Case {
+                args: &["1", "nan"],
+                stdin: b"",
+                differs: &[
+                    (Part::Stderr, "GNU seq adds a hint to try --help"),
+                    (Part::Code, "usage errors exit with 2, GNU seq exits with 1"),
+                ],
+            },
+            Case {
+                args: &["1", "0", "2"],
+                stdin: b"",
+                differs: &[
+                    (Part::Stderr, "GNU seq adds a hint to try --help"),
+                    (Part::Code, "usage errors exit with 2, GNU seq exits with 1"),
+                ],
+            },
+            Case {
+                args: &["1e5000"],
+                stdin: b"",
+                differs: &[
+                    (Part::Stderr, "GNU seq adds a hint to try --help"),
+                    (Part::Code, "usage errors exit with 2, GNU seq exits with 1"),
+                ],
+            },
+            Case {
+                args: &["1 "],
+                stdin: b"",
+                differs: &[
+                    (Part::Stderr, "GNU seq adds a hint to try --help"),
+                    (Part::Code, "usage errors exit with 2, GNU seq exits with 1"),
+                ],
+            },
+            Case {
+                args: &["--bogus"],
+                stdin: b"",
+                differs: &[
+                    (Part::Stderr, "usage errors are reported by clap"),
+                    (Part::Code, "usage errors exit with 2, GNU seq exits with 1"),
+                ],
+            },
+        ],
+    );
+}
+
+#[test]
+#[cfg(feature = "sort")]
+fn sort() {
+    const NUMBERS: &[u8] = b"b 10\na 9\nB 2\n  c -1\nA 10.5\n\nb 10\nx\t-0\n";
+    check(
+        "sort",
+        &[
+            case(&["lines.txt", "no-newline.txt", "utf8.txt", "tabs.txt"]),
+            case(&["-r", "lines.txt", "utf8.txt"]),
+            case(&["-b", "tabs.txt", "lines.txt"]),
+            with_stdin(&[], NUMBERS),
+            with_stdin(&["-n"], NUMBERS),
+            with_stdin(&["-f", "-u"], NUMBERS),
+            with_stdin(&["-k2n", "-r"], NUMBERS),
+            with_stdin(&["-k2,2n", "-k1,1r"], NUMBERS),
+            with_stdin(&["-k1.2b,1.2", "-u"], NUMBERS),
+            with_stdin(
+                &["-t", ":", "-k2,2", "-k3n"],
+                b"a:y:3\nb:x:10\nc:y:2\nd::\n",
+            ),
+            with_stdin(&["-z", "-t", "\\0"], b"b\0a\0c"),
+            with_stdin(&["-h"], b" 2K\n1G\n-3M\n0K\n5\nx\n1.5K\n10\n"),
+            with_stdin(
+                &["-g"],
+                b"inf\n-inf\nnan\n1e3\n0x10\nabc\n 5\n1e\n0x\n.5e1\n",
+            ),
+            with_stdin(&["-M", "-r"], b"Feb\n jan\nxyz\ndecember\nMA\n"),
+            with_stdin(
+                &["-V"],
+                b"foo-1.10.tar.gz\nfoo-1.2.tar.gz\n1.2~rc1\n1.2\n.a\n..\n.\na~\na\n",
+            ),
+            with_stdin(
+                &["-t", "-", "-k2V", "-k1,1M"],
+                b"b-1.10\na-1.9\nmar-1\njan-1\n",
+            ),
+            with_stdin(&["-s", "-k1,1f"], NUMBERS),
+            with_stdin(&["-s", "-r", "-k2,2n", "-u"], NUMBERS),
+            with_stdin(&["-c"], NUMBERS),
+            with_stdin(&["-C"], NUMBERS),
+            with_stdin(&["--check=silent", "-n"], NUMBERS),
+            with_stdin(&["-cu"], b"a\nb\nb\n"),
+            with_stdin(&["-c", "-k1,1", "-s"], b"a 2\na 1\nb\n"),
+            case(&["-c", "-C", "lines.txt"]),
+            case(&["-c", "-o", "out.txt", "lines.txt"]),
+            Case {
+                args: &["-c", "lines.txt", "utf8.txt"],
+                stdin: b"",
+                differs: &[(
+                    Part::Stderr,
+                    "quoted with ASCII quotes, GNU uses the locale's",
+                )],
+            },
+            case(&["-o", "/dev/stdout", "-o", "/dev/stdout", "lines.txt"]),
+            case(&["-o", "a.txt", "-o", "b.txt", "lines.txt"]),
+            with_stdin(&["--files0-from=-", "-r"], b"lines.txt\0utf8.txt\0"),
+            with_stdin(&["--files0-from=-"], b"lines.txt\0\0utf8.txt"),
+            with_stdin(&["--files0-from=-"], b"lines.txt\0-\0"),
+            case(&["-nM", "lines.txt"]),
+            case(&["-k1,1gh", "-k1,1R", "lines.txt"]),
+            Case {
+                args: &["-R", "--random-source=lines.txt", "utf8.txt", "lines.txt"],
+                stdin: b"",
+                differs: &[(
+                    Part::Stdout,
+                    "keys are hashed as they are, GNU hashes their strxfrm outside of C",
+                )],
+            },
+            Case {
+                args: &["-R", "--random-source=empty.txt", "lines.txt"],
+                stdin: b"",
+                differs: &[(
+                    Part::Stderr,
+                    "quoted with ASCII quotes, GNU uses the locale's",
+                )],
+            },
+            Case {
+                args: &["--debug", "-k2n"],
+                stdin: NUMBERS,
+                differs: &[(
+                    Part::Stderr,
+                    "quoted with ASCII quotes, GNU uses the locale's",
+                )],
+            },
+            Case {
+                args: &["--debug", "-k2,2", "-k1,1", "-s", "-u"],
+                stdin: NUMBERS,
+                differs: &[(
+                    Part::Stderr,
+                    "quoted with ASCII quotes, GNU uses the locale's",
+                )],
+            },
+            Case {
+                args: &["--debug", "-M", "-b"],
+                stdin: b"Feb 2\n\tjan\nxyz\n",
+                differs: &[(
+                    Part::Stderr,
+                    "quoted with ASCII quotes, GNU uses the locale's",
+                )],
+            },
+            Case {
+                args: &["--debug", "-t", ".", "-k1g", "-r", "-n"],
+                stdin: b"1.5\n.2\n-3.e1\n",
+                differs: &[(
+                    Part::Stderr,
+                    "quoted with ASCII quotes, GNU uses the locale's",
+                )],
+            },
+            Case {
+                args: &["--debug", "-f", "tabs.txt", "utf8.txt"],
+                stdin: b"",
+                differs: &[(
+                    Part::Stderr,
+                    "quoted with ASCII quotes, GNU uses the locale's",
+                )],
+            },
+            case(&["--debug", "-o", "out.txt", "lines.txt"]),
+            case(&["--debug", "-C", "lines.txt"]),
+            with_stdin(&["-S", "1", "-T", "/nonexistent", "-n"], NUMBERS),
+            with_stdin(&["--parallel=2", "-u"], NUMBERS),
+            case(&["--parallel=0", "lines.txt"]),
+            case(&["lines.txt", "missing.txt"]),
+            case(&["."]),
+            Case {
+                args: &["-k", "2,x", "lines.txt"],
+                stdin: b"",
+                differs: &[(
+                    Part::Stderr,
+                    "quoted with ASCII quotes, GNU uses the locale's",
+                )],
+            },
+            Case {
+                args: &["-k", "1.0", "lines.txt"],
+                stdin: b"",
+                differs: &[(
+                    Part::Stderr,
+                    "quoted with ASCII quotes, GNU uses the locale's",
+                )],
+            },
+            Case {
+                args: &["-t", "ab", "lines.txt"],
+                stdin: b"",
+                differs: &[(
+                    Part::Stderr,
+                    "quoted with ASCII quotes, GNU uses the locale's",
+                )],
+            },
+            Case {
+                args: &["--bogus"],
+                stdin: b"",
+                differs: &[(Part::Stderr, "usage errors are reported by clap")],
+            },
+        ],
+    );
+}
+
+#[test]
+#[cfg(feature = "tac")]
+fn tac() {
+    check(
+        "tac",
+        &[
+            case(&["lines.txt"]),
+            case(&["lines.txt", "no-newline.txt", "empty.txt", "utf8.txt"]),
+            case(&["-b", "lines.txt", "no-newline.txt"]),
+            case(&["-s", " ", "lines.txt"]),
+            case(&["-b", "-s", "e", "utf8.txt"]),
+            case(&["-r", "-s", "[0-9]+", "lines.txt"]),
+            case(&["lines.txt", "missing.txt", "tabs.txt"]),
+            case(&["-r", "-s", "[", "lines.txt"]),
+            with_stdin(&[], b"one\ntwo\nthree"),
+            with_stdin(&["-", "lines.txt"], b"one\ntwo\n"),
+            with_stdin(&["-s", ""], b"one\0two\0"),
+            with_stdin(&["-s", "--", "-"], b"a--b--c"),
+            Case {
+                args: &["--bogus"],
+                stdin: b"",
+                differs: &[
+                    (Part::Stderr, "usage errors are reported by clap"),
+                    (Part::Code, "usage errors exit with 2, GNU tac exits with 1"),
+                ],
+            },
+        ],
+    );
+}
+
+#[test]
+#[cfg(feature = "tail")]
+fn tail() {
+    check(
+        "tail",
+        &[
+            case(&["lines.txt"]),
+            case(&["-n", "2", "lines.txt", "no-newline.txt", "empty.txt"]),
+            case(&["-n", "+2", "lines.txt"]),
+            case(&["-n", "0", "lines.txt"]),
+            case(&["-c", "5", "utf8.txt", "no-newline.txt"]),
+            case(&["-c", "+3", "tabs.txt"]),
+            case(&["-q", "-n", "1", "lines.txt", "tabs.txt"]),
+            case(&["-v", "lines.txt"]),
+            case(&["-n", "1", "lines.txt", "missing.txt", "tabs.txt"]),
+            case(&["-2", "lines.txt"]),
+            case(&["+3c", "lines.txt"]),
+            case(&["--pid=1", "lines.txt"]),
+            case(&["--retry", "-n", "1", "lines.txt"]),
+            with_stdin(&[], b"one\ntwo\nthree"),
+            with_stdin(&["-n", "2", "-", "lines.txt"], b"one\ntwo\nthree\n"),
+            with_stdin(&["-z", "-n", "1"], b"one\0two\0"),
+            Case {
+                args: &["--bogus"],
+                stdin: b"",
+                differs: &[
+                    (Part::Stderr, "usage errors are reported by clap"),
+                    (
+                        Part::Code,
+                        "usage errors exit with 2, GNU tail exits with 1",
+                    ),
+                ],
+            },
+        ],
+    );
+}
+
+#[test]
+#[cfg(feature = "tr")]
+fn tr() {
+    check(
+        "tr",
+        &[
+            with_stdin(&["a-z", "A-Z"], b"Hello, World!\n"),
+            with_stdin(&["[:lower:]", "[:upper:]"], b"mixed Case\n"),
+            with_stdin(&["-d", "[:digit:]"], b"a1b22c333\n"),
+            with_stdin(&["-s", " "], b"a  b    c\n"),
+            with_stdin(&["-cs", "[:alnum:]", "\\n"], b"one, two;  three\n"),
+            with_stdin(&["-ds", "a", "b"], b"abba bbaab\n"),
+            with_stdin(&["-t", "abc", "xy"], b"aabbcc\n"),
+            with_stdin(&["abc", "x[y*]"], b"aabbcc\n"),
+            with_stdin(&["\\101-\\103", "[x*2]z"], b"ABCD\n"),
+            with_stdin(&["[a*2]b", "xyz"], b"aab\n"),
+            with_stdin(&["-c", "a", "-"], b"banana\n"),
+            with_stdin(&["a\\", "xy"], b"a\\b\n"),
+            with_stdin(&["-d", "[=a=][=\\n=]"], b"banana\n"),
+            Case {
+                args: &["a", "[=a=]"],
+                stdin: b"",
+                differs: &[(Part::Code, "invalid sets exit with 2, GNU tr exits with 1")],
+            },
+            Case {
+                args: &["[a*]", "x"],
+                stdin: b"",
+                differs: &[(Part::Code, "invalid sets exit with 2, GNU tr exits with 1")],
+            },
+            Case {
+                args: &["z-a", "x"],
+                stdin: b"",
+                differs: &[(Part::Code, "invalid sets exit with 2, GNU tr exits with 1")],
+            },
+            Case {
+                args: &["a"],
+                stdin: b"",
+                differs: &[
+                    (Part::Stderr, "GNU tr adds a hint to try --help"),
+                    (Part::Code, "usage errors exit with 2, GNU tr exits with 1"),
+                ],
+            },
+            Case {
+                args: &["--bogus"],
+                stdin: b"",
+                differs: &[
+                    (Part::Stderr, "usage errors are reported by clap"),
+                    (Part::Code, "usage errors exit with 2, GNU tr exits with 1"),
+                ],
+            },
+        ],
+    );
+}
+
+#[test]
+#[cfg(feature = "true")]
+fn true_() {
+    check(
+        "true",
+        &[
+            case(&[]),
+            case(&["a", "--bogus"]),
+            case(&["--help", "a"]),
+            case(&["--", "--version"]),
+        ],
+    );
+}
+
+#[test]
+#[cfg(feature = "uniq")]
+fn uniq() {
+    const REPEATS: &[u8] = b"a 1\nA 1\nb  1\nb\t1\nb 1\nc\nc";
+    check(
+        "uniq",
+        &[
+            case(&["lines.txt"]),
+            with_stdin(&[], REPEATS),
+            with_stdin(&["-c"], REPEATS),
+            with_stdin(&["-ci"], REPEATS),
+            with_stdin(&["-d"], REPEATS),
+            with_stdin(&["-u", "-i"], REPEATS),
+            with_stdin(&["-d", "-u"], REPEATS),
+            with_stdin(&["-c", "-f", "1"], REPEATS),
+            with_stdin(&["-c", "-s", "1", "-w", "1"], REPEATS),
+            with_stdin(&["-c", "-f", "99999999999999999999"], REPEATS),
+            with_stdin(&["-z", "-c"], b"a\0a\0b"),
+            with_stdin(&["-D"], REPEATS),
+            with_stdin(&["-D", "-u", "-i"], REPEATS),
+            with_stdin(&["--all-repeated=separate", "-f", "1"], REPEATS),
+            with_stdin(&["--all-repeated=prepend", "-z"], b"a\0a\0b\0c\0c"),
+            with_stdin(&["--group"], REPEATS),
+            with_stdin(&["--group=prepend", "-s", "1"], REPEATS),
+            with_stdin(&["--group=append", "-i"], REPEATS),
+            with_stdin(&["--group=both"], REPEATS),
+            with_stdin(&["--group=both"], b""),
+            Case {
+                args: &["--group", "-c"],
+                stdin: REPEATS,
+                differs: &[
+                    (Part::Stderr, "GNU uniq adds a hint to try --help"),
+                    (
+                        Part::Code,
+                        "usage errors exit with 2, GNU uniq exits with 1",
+                    ),
+                ],
+            },
+            Case {
+                args: &["-D", "-c"],
+                stdin: REPEATS,
+                differs: &[
+                    (Part::Stderr, "GNU uniq adds a hint to try --help"),
+                    (
+                        Part::Code,
+                        "usage errors exit with 2, GNU uniq exits with 1",
+                    ),
+                ],
+            },
+            with_stdin(&["-", "-"], REPEATS),
+            with_stdin(&["-f", "x"], REPEATS),
+            with_stdin(&["-s", "-1"], REPEATS),
+            with_stdin(&["-w", ""], REPEATS),
+            case(&["missing.txt"]),
+            Case {
+                args: &["lines.txt", "-", "extra"],
+                stdin: b"",
+                differs: &[
+                    (Part::Stderr, "GNU uniq adds a hint to try --help"),
+                    (
+                        Part::Code,
+                        "usage errors exit with 2, GNU uniq exits with 1",
+                    ),
+                ],
+            },
+            Case {
+                args: &["--bogus"],
+                stdin: b"",
+                differs: &[
+                    (Part::Stderr, "usage errors are reported by clap"),
+                    (
+                        Part::Code,
+                        "usage errors exit with 2, GNU uniq exits with 1",
+                    ),
+                ],
+            },
+        ],
+    );
+}
+
+#[test]
+#[cfg(feature = "wc")]
+fn wc() {
+    check(
+        "wc",
+        &[
+            case(&["lines.txt"]),
+            case(&["lines.txt", "utf8.txt", "no-newline.txt", "empty.txt"]),
+            case(&["-l", "lines.txt", "no-newline.txt"]),
+            case(&["-w", "utf8.txt"]),
+            case(&["-c", "utf8.txt"]),
+            case(&["-m", "utf8.txt"]),
+            case(&["-L", "utf8.txt", "tabs.txt"]),
+            with_stdin(&["-L"], b"a\xffb\n\xe6\x97x\xc3\n"),
+            case(&["-lwmcL", "lines.txt", "tabs.txt"]),
+            case(&["lines.txt", "missing.txt"]),
+            Case {
+                args: &["."],
+                stdin: b"",
+                differs: &[(Part::Stdout, "no counts are printed for directories")],
+            },
+            with_stdin(&[], b"one two\nthree\n"),
+            with_stdin(&["-", "lines.txt"], b"one two\nthree\n"),
+            with_stdin(&["-", "-"], b"one two\nthree\n"),
+            with_stdin(&["/dev/stdin", "-"], b"one two\nthree\n"),
+            Case {
+                args: &["--files0-from=-"],
+                stdin: b"lines.txt\0utf8.txt\0",
+                differs: &[(
+                    Part::Stdout,
+                    "the list is read ahead, so the column width fits all files",
+                )],
+            },
+            Case {
+                args: &["--bogus"],
+                stdin: b"",
+                differs: &[
+                    (Part::Stderr, "usage errors are reported by clap"),
+                    (Part::Code, "usage errors exit with 2, GNU wc exits with 1"),
+                ],
+            },
+        ],
+    );
+}
+
+#[test]
+#[cfg(feature = "yes")]
+fn yes() {
+    // Only the cases that end, the others print until the output fails
+    check(
+        "yes",
+        &[Case {
+            args: &["--bogus"],
+            stdin: b"",
+            differs: &[
+                (Part::Stderr, "usage errors are reported by clap"),
+                (Part::Code, "usage errors exit with 2, GNU yes exits with 1"),
+            ],
+        }],
+    );
+}
@@ -0,0 +1,180 @@
+//! Command line interface of `tr`
+
+use crate::set::{self, Mode, Set};
+use crate::{Chars, Error, Tr};
+use clap::{ArgAction, CommandFactory, FromArgMatches, Parser};
+use nyaa_core::error::{self, Status, UError};
+use nyaa_core::i18n;
+use nyaa_core::locale::Locale;
+use nyaa_core::output::{self, OutputSink};
+use nyaa_core::posix;
+use nyaa_core::quote::shell_escape;
+use nyaa_core::response;
+use nyaa_core::trace;
+use std::ffi::OsString;
+use std::io;
+use std::process::ExitCode;
+
+const UTIL: &str = "tr";
+
+#[derive(Parser)]
+#[command(name = "tr", version)]
+#[command(about = "translate, squeeze, and/or delete characters", long_about = None)]
+#[command(disable_help_flag = true, disable_version_flag = true)]
+struct Args {
+    /// Use the complement of SET1
+    #[arg(short = 'c', short_alias = 'C', long = "complement")]
+    is_complement: bool,
+
+    /// Delete the characters in SET1 instead of translating them
+    #[arg(short = 'd', long = "delete")]
+    is_delete: bool,
+
+    /// Replace each run of a character in the last given set with one
+    /// occurrence of it
+    #[arg(short = 's', long = "squeeze-repeats")]
+    is_squeeze: bool,
+
+    /// Truncate SET1 to the length of SET2 instead of extending SET2
+    #[arg(short = 't', long = "truncate-set1")]
+    is_truncate: bool,
+
+    /// Take the input and the sets for UTF-8 text, with the character
+    /// classes of the locale, instead of bytes
+    #[arg(long = "utf8")]
+    is_utf8: bool,
+
+    /// SET1 and SET2: characters, escapes like \n and \177, ranges like
+    /// a-z, classes like [:alpha:], equivalence classes like [=e=] and, in
+    /// SET2, repeats like [x*4] or [x*] filling it up to the length of SET1.
+    /// An argument @FILE stands for the arguments on the lines of FILE,
+    /// @@NAME for @NAME
+    #[arg(value_name = "SET")]
+    sets: Vec<OsString>,
+
+    /// Print help
+    #[arg(long, action=ArgAction::Help)]
+    help: (),
+
+    /// Print version
+    #[arg(long, action=ArgAction::Version)]
+    version: (),
+}
+
+pub fn command() -> clap::Command {
+    i18n::command(Args::command())
+}
+
+/// Check that there are as many sets as the operation takes
+fn check_operands(args: &Args) -> Result<(), UError> {
+    let sets = &args.sets;
+    // How many sets the operation takes, and why
+    let (count, reason) = match (args.is_delete, args.is_squeeze) {
+        (true, false) => (
+            1,
+            "Only one string may be given when deleting without squeezing repeats.",
+        ),
+        (true, true) => (
+            2,
+            "Two strings must be given when both deleting and squeezing repeats.",
+        ),
+        (false, true) => (sets.len().clamp(1, 2), ""),
+        (false, false) => (2, "Two strings must be given when translating."),
+    };
+    let message = if sets.is_empty() {
+        i18n::tr("missing operand").to_string()
+    } else if sets.len() < count {
+        let last = &sets[sets.len() - 1];
+        let message = i18n::tr("missing operand after {}").replace("{}", &shell_escape(last));
+        format!("{message}\n{}", i18n::tr(reason))
+    } else if let Some(extra) = sets.get(count) {
+        let message = i18n::tr("extra operand {}").replace("{}", &shell_escape(extra));
+        if args.is_delete && !args.is_squeeze {
+            format!("{message}\n{}", i18n::tr(reason))
+        } else {
+            message
+        }
+    } else {
+        return Ok(());
+    };
+    Err(UError::usage(message))
+}
+
+/// Set of the operand `spec`, with its warnings printed
+fn parse_set(spec: &OsString, mode: Mode) -> Result<Set, UError> {
+    let set = Set::parse(spec.as_encoded_bytes(), mode).map_err(UError::usage)?;
+    for warning in set.warnings() {
+        error::notice(UTIL, warning);
+    }
+    Ok(set)
+}
+
+/// The tables of the operation the command line asks for
+fn build(args: &Args) -> Result<Tr, UError> {
+    check_operands(args)?;
+    let mode = if args.is_utf8 {
+        Mode::Utf8(Locale::ctype())
+    } else {
+        Mode::Bytes
+    };
+    let set1 = parse_set(&args.sets[0], mode)?;
+    if set1.has_indefinite_repeat() {
+        return Err(UError::usage(
+            "the [c*] repeat construct may not appear in string1",
+        ));
+    }
+    let domain = Chars::new(set1.chars(), args.is_complement);
+    let mut tr = Tr::new(args.is_utf8);
+    let Some(spec2) = args.sets.get(1) else {
+        if args.is_delete {
+            tr.delete(&domain);
+        } else {
+            tr.squeeze(&domain);
+        }
+        return Ok(tr);
+    };
+    let set2 = parse_set(spec2, mode)?;
+    if args.is_delete {
+        tr.delete(&domain);
+        tr.squeeze(&Chars::new(set2.chars(), false));
+        return Ok(tr);
+    }
+    let translation = set::translation(&set1, &set2, args.is_complement, args.is_truncate)
+        .map_err(UError::usage)?;
+    tr.translate(&translation.from, &translation.to);
+    if let Some(rest) = translation.rest {
+        tr.translate_rest(Chars::new(set1.chars(), false), rest);
+    }
+    if args.is_squeeze {
+        tr.squeeze(&Chars::new(translation.to, false));
+    }
+    Ok(tr)
+}
+
+/// Run `tr` with the command line `args`, starting with the program name
+pub fn main(args: Vec<OsString>) -> ExitCode {
+    let mut status = Status::default();
+    let Some(args) = status.check(UTIL, response::expand(args)) else {
+        return status.into();
+    };
+    let args = posix::operands_last(&command(), args);
+    let matches = command().get_matches_from(args);
+    let args = Args::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+    trace::init(UTIL, false);
+    let Some(tr) = status.check(UTIL, build(&args)) else {
+        return status.into();
+    };
+    let mut out = OutputSink::stdout();
+    match tr.run(io::stdin().lock(), &mut out) {
+        Ok(()) => (),
+        Err(Error::Read(e)) => {
+            status.report(UTIL, &UError::from(e).with_name(i18n::tr("read error")));
+        }
+        Err(Error::Write(e)) => {
+            status.report(UTIL, &output::write_error(e));
+            return status.into();
+        }
+    }
+    status.check(UTIL, out.finish());
+    status.into()
+}
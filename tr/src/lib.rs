@@ -0,0 +1,334 @@
+//! Translating, deleting and squeezing characters with the semantics of
+//! `tr`.
+//!
+//! Each byte of the input is deleted or replaced through a table, and the
+//! bytes of the squeezed set are written once for a run of them in the
+//! output. In UTF-8 mode the same is done to the characters of UTF-8
+//! text, and bytes that aren't part of one are copied. The sets the tables
+//! are built from are parsed by [`set`].
+//!
+//! ```
+//! use nyaa_tr::{Chars, Tr};
+//!
+//! let mut tr = Tr::new(false);
+//! tr.translate(&['a', 'b', 'c'], &['x', 'y', 'z']);
+//! tr.delete(&Chars::new(['-'], false));
+//! tr.squeeze(&Chars::new(['z'], false));
+//! let mut out = Vec::new();
+//! tr.run(&b"a-b-ccc"[..], &mut out)?;
+//! assert_eq!(out, b"xyz");
+//! # Ok::<(), nyaa_tr::Error>(())
+//! ```
+
+pub mod cli;
+pub mod set;
+
+use std::collections::{HashMap, HashSet};
+use std::io::{self, Read, Write};
+
+/// Failure of [`Tr::run`], reading its input or writing the output
+#[derive(Debug)]
+pub enum Error {
+    Read(io::Error),
+    Write(io::Error),
+}
+
+/// The characters listed, or all characters but them
+#[derive(Clone, Debug, Default)]
+pub struct Chars {
+    listed: HashSet<char>,
+    is_complement: bool,
+}
+
+impl Chars {
+    pub fn new(listed: impl IntoIterator<Item = char>, is_complement: bool) -> Self {
+        Chars {
+            listed: listed.into_iter().collect(),
+            is_complement,
+        }
+    }
+
+    pub fn contains(&self, c: char) -> bool {
+        self.listed.contains(&c) != self.is_complement
+    }
+}
+
+/// Characters some operation applies to, looked up in a table below 256
+#[derive(Clone)]
+struct Table {
+    low: [bool; 256],
+    chars: Chars,
+}
+
+impl Table {
+    fn new(chars: &Chars) -> Self {
+        Table {
+            low: std::array::from_fn(|b| chars.contains(char::from(b as u8))),
+            chars: chars.clone(),
+        }
+    }
+
+    fn contains(&self, c: char) -> bool {
+        match self.low.get(c as usize) {
+            Some(&is_member) => is_member,
+            None => self.chars.contains(c),
+        }
+    }
+}
+
+/// Tables of what is done to each character
+#[derive(Clone)]
+pub struct Tr {
+    is_utf8: bool,
+    /// Replacements of the characters below 256, the bytes in byte mode
+    low: [Option<char>; 256],
+    /// Replacements of the other characters
+    high: HashMap<char, char>,
+    /// Replacement of the characters not in the set it goes with that
+    /// have no other one, for the complement of a set in UTF-8 mode
+    rest: Option<(char, Chars)>,
+    deleted: Table,
+    squeezed: Table,
+}
+
+/// What is carried from one block of input to the next
+#[derive(Default)]
+pub struct State {
+    /// The last character written, for squeezing
+    last: Option<char>,
+    /// The start of a UTF-8 sequence at the end of the last block
+    partial: Vec<u8>,
+}
+
+impl Tr {
+    /// Copies the input unchanged. In `is_utf8`, the input is taken for
+    /// UTF-8 text.
+    pub fn new(is_utf8: bool) -> Self {
+        Tr {
+            is_utf8,
+            low: [None; 256],
+            high: HashMap::new(),
+            rest: None,
+            deleted: Table::new(&Chars::default()),
+            squeezed: Table::new(&Chars::default()),
+        }
+    }
+
+    /// Replace each character of `from` with the character of `to` at the
+    /// same position. A character that is in `from` more than once is
+    /// replaced with the last of its replacements. In byte mode, the
+    /// characters are below 256.
+    pub fn translate(&mut self, from: &[char], to: &[char]) {
+        for (&c, &replacement) in from.iter().zip(to) {
+            match self.low.get_mut(c as usize) {
+                Some(low) => *low = Some(replacement),
+                None => drop(self.high.insert(c, replacement)),
+            }
+        }
+    }
+
+    /// Replace the characters that aren't in `excluded` with `to`, unless
+    /// [`Tr::translate`] replaces them
+    pub fn translate_rest(&mut self, excluded: Chars, to: char) {
+        self.rest = Some((to, excluded));
+    }
+
+    pub fn delete(&mut self, chars: &Chars) {
+        self.deleted = Table::new(chars);
+    }
+
+    /// Write runs of the same character of `chars` in the output once
+    pub fn squeeze(&mut self, chars: &Chars) {
+        self.squeezed = Table::new(chars);
+    }
+
+    /// What `c` is replaced with
+    fn replacement(&self, c: char) -> char {
+        let replacement = match self.low.get(c as usize) {
+            Some(&low) => low,
+            None => self.high.get(&c).copied(),
+        };
+        match (replacement, &self.rest) {
+            (Some(replacement), _) => replacement,
+            (None, Some((to, excluded))) if !excluded.contains(c) => *to,
+            (None, _) => c,
+        }
+    }
+
+    /// Append what `c` becomes to `out`
+    fn push(&self, c: char, state: &mut State, out: &mut Vec<u8>) {
+        if self.deleted.contains(c) {
+            return;
+        }
+        let c = self.replacement(c);
+        if self.squeezed.contains(c) && state.last == Some(c) {
+            return;
+        }
+        state.last = Some(c);
+        if self.is_utf8 {
+            out.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes());
+        } else {
+            out.push(c as u8);
+        }
+    }
+
+    /// Append what `data` becomes to `out`. The `state` is carried from
+    /// one block of input to the next, with [`Tr::finish`] after the last.
+    pub fn apply(&self, data: &[u8], state: &mut State, out: &mut Vec<u8>) {
+        if !self.is_utf8 {
+            for &b in data {
+                self.push(char::from(b), state, out);
+            }
+            return;
+        }
+        let joined;
+        let mut data = data;
+        if !state.partial.is_empty() {
+            state.partial.extend_from_slice(data);
+            joined = std::mem::take(&mut state.partial);
+            data = &joined;
+        }
+        loop {
+            match std::str::from_utf8(data) {
+                Ok(text) => {
+                    text.chars().for_each(|c| self.push(c, state, out));
+                    return;
+                }
+                Err(e) => {
+                    let (valid, rest) = data.split_at(e.valid_up_to());
+                    // SAFETY: the bytes up to `valid_up_to` are valid UTF-8
+                    let text = unsafe { std::str::from_utf8_unchecked(valid) };
+                    text.chars().for_each(|c| self.push(c, state, out));
+                    let Some(len) = e.error_len() else {
+                        // Completed by the next block, if there is one
+                        state.partial = rest.to_vec();
+                        return;
+                    };
+                    out.extend_from_slice(&rest[..len]);
+                    state.last = None;
+                    data = &rest[len..];
+                }
+            }
+        }
+    }
+
+    /// Append the end of the input, an incomplete UTF-8 sequence, to `out`
+    pub fn finish(&self, state: &mut State, out: &mut Vec<u8>) {
+        out.append(&mut state.partial);
+    }
+
+    /// Filter all of `input` into `out` by blocks
+    pub fn run(&self, mut input: impl Read, out: &mut impl Write) -> Result<(), Error> {
+        let mut buf = vec![0; 64 * 1024];
+        let mut filtered = Vec::with_capacity(buf.len());
+        let mut state = State::default();
+        loop {
+            filtered.clear();
+            match input.read(&mut buf) {
+                Ok(0) => {
+                    self.finish(&mut state, &mut filtered);
+                    return out.write_all(&filtered).map_err(Error::Write);
+                }
+                Ok(n) => self.apply(&buf[..n], &mut state, &mut filtered),
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(Error::Read(e)),
+            }
+            out.write_all(&filtered).map_err(Error::Write)?;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn apply(tr: &Tr, data: &[u8]) -> Vec<u8> {
+        let mut state = State::default();
+        let mut out = Vec::new();
+        tr.apply(data, &mut state, &mut out);
+        tr.finish(&mut state, &mut out);
+        out
+    }
+
+    fn chars(s: &str) -> Vec<char> {
+        s.chars().collect()
+    }
+
+    #[test]
+    fn translates_with_the_last_replacement() {
+        let mut tr = Tr::new(false);
+        tr.translate(&chars("aab"), &chars("xyz"));
+        assert_eq!(apply(&tr, b"abc"), b"yzc");
+    }
+
+    #[test]
+    fn squeezes_the_output() {
+        let mut tr = Tr::new(false);
+        tr.translate(&chars("ab"), &chars("xx"));
+        tr.squeeze(&Chars::new(['x'], false));
+        assert_eq!(apply(&tr, b"abba cab"), b"x cx");
+    }
+
+    #[test]
+    fn squeezes_across_blocks() {
+        let mut tr = Tr::new(false);
+        tr.squeeze(&Chars::new([' '], false));
+        let mut state = State::default();
+        let mut out = Vec::new();
+        tr.apply(b"a  ", &mut state, &mut out);
+        tr.apply(b"  b", &mut state, &mut out);
+        assert_eq!(out, b"a b");
+    }
+
+    #[test]
+    fn deletes_before_squeezing() {
+        let mut tr = Tr::new(false);
+        tr.delete(&Chars::new(['-'], false));
+        tr.squeeze(&Chars::new(['a'], false));
+        assert_eq!(apply(&tr, b"a-a-b"), b"ab");
+    }
+
+    #[test]
+    fn deletes_the_complement() {
+        let mut tr = Tr::new(true);
+        tr.delete(&Chars::new(chars("aé"), true));
+        assert_eq!(apply(&tr, "aébЖé\n".as_bytes()), "aéé".as_bytes());
+    }
+
+    #[test]
+    fn translates_utf8() {
+        let mut tr = Tr::new(true);
+        tr.translate(&chars("жé"), &chars("ЖE"));
+        assert_eq!(apply(&tr, "жжé!".as_bytes()), "ЖЖE!".as_bytes());
+    }
+
+    #[test]
+    fn translates_the_rest() {
+        let mut tr = Tr::new(true);
+        tr.translate(&['\0'], &['x']);
+        tr.translate_rest(Chars::new(['a'], false), '_');
+        assert_eq!(apply(&tr, "a\0bж".as_bytes()), b"ax__");
+    }
+
+    #[test]
+    fn copies_invalid_utf8() {
+        let mut tr = Tr::new(true);
+        tr.translate(&['a'], &['b']);
+        tr.squeeze(&Chars::new(['b'], false));
+        assert_eq!(apply(&tr, b"a\xffa\xe2\x82"), b"b\xffb\xe2\x82");
+    }
+
+    #[test]
+    fn joins_sequences_across_blocks() {
+        let mut tr = Tr::new(true);
+        tr.translate(&['ж'], &['Ж']);
+        let mut state = State::default();
+        let mut out = Vec::new();
+        let data = "жж".as_bytes();
+        tr.apply(&data[..1], &mut state, &mut out);
+        tr.apply(&data[1..3], &mut state, &mut out);
+        tr.apply(&data[3..], &mut state, &mut out);
+        tr.finish(&mut state, &mut out);
+        assert_eq!(out, "ЖЖ".as_bytes());
+    }
+}
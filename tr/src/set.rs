@@ -0,0 +1,716 @@
+//! The sets of characters `tr` translates, deletes and squeezes. A set
+//! lists characters, escapes like `\n` and `\177`, ranges like `a-z`,
+//! classes like `[:alpha:]`, equivalence classes like `[=e=]` and repeats
+//! like `[x*4]`, expanded in the order they are written.
+//!
+//! Sets are of bytes, each taken for the character of its value, with the
+//! classes of the C locale. In UTF-8 mode they are of the characters of
+//! UTF-8 text instead, with the classes of the locale, where upper and
+//! lower case pair by their case mappings rather than by position.
+//!
+//! ```
+//! use nyaa_tr::set::{self, Mode, Set};
+//!
+//! let set1 = Set::parse(b"a-c[:digit:]", Mode::Bytes).unwrap();
+//! assert_eq!(set1.chars().len(), 13);
+//! let set2 = Set::parse(b"x[y*]", Mode::Bytes).unwrap();
+//! let translation = set::translation(&set1, &set2, false, false).unwrap();
+//! assert_eq!(translation.to[..3], ['x', 'y', 'y']);
+//! ```
+
+use nyaa_core::i18n::tr;
+use nyaa_core::locale::Locale;
+
+/// What the sets and the input are made of
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Mode {
+    /// Bytes, classified like in the C locale
+    Bytes,
+    /// Characters of UTF-8 text, classified like in the locale
+    Utf8(Locale),
+}
+
+impl Mode {
+    fn locale(self) -> Locale {
+        match self {
+            Mode::Bytes => Locale::C,
+            Mode::Utf8(locale) => locale,
+        }
+    }
+
+    /// Every character, in ascending order
+    fn domain(self) -> impl Iterator<Item = char> {
+        let max = match self {
+            Mode::Bytes => u32::from(u8::MAX),
+            Mode::Utf8(_) => u32::from(char::MAX),
+        };
+        (0..=max).filter_map(char::from_u32)
+    }
+}
+
+/// Character class
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Class {
+    Alnum,
+    Alpha,
+    Blank,
+    Cntrl,
+    Digit,
+    Graph,
+    Lower,
+    Print,
+    Punct,
+    Space,
+    Upper,
+    Xdigit,
+}
+
+impl Class {
+    fn from_name(name: &[u8]) -> Option<Self> {
+        Some(match name {
+            b"alnum" => Class::Alnum,
+            b"alpha" => Class::Alpha,
+            b"blank" => Class::Blank,
+            b"cntrl" => Class::Cntrl,
+            b"digit" => Class::Digit,
+            b"graph" => Class::Graph,
+            b"lower" => Class::Lower,
+            b"print" => Class::Print,
+            b"punct" => Class::Punct,
+            b"space" => Class::Space,
+            b"upper" => Class::Upper,
+            b"xdigit" => Class::Xdigit,
+            _ => return None,
+        })
+    }
+
+    pub fn contains(self, c: char, locale: Locale) -> bool {
+        match self {
+            Class::Alnum => locale.is_alnum(c),
+            Class::Alpha => locale.is_alpha(c),
+            Class::Blank => locale.is_blank(c),
+            Class::Cntrl => locale.is_cntrl(c),
+            Class::Digit => locale.is_digit(c),
+            Class::Graph => locale.is_graph(c),
+            Class::Lower => locale.is_lower(c),
+            Class::Print => locale.is_print(c),
+            Class::Punct => locale.is_punct(c),
+            Class::Space => locale.is_space(c),
+            Class::Upper => locale.is_upper(c),
+            Class::Xdigit => c.is_ascii_hexdigit(),
+        }
+    }
+
+    /// Whether the class is a case, which translating may map to a case
+    fn is_case(self) -> bool {
+        matches!(self, Class::Lower | Class::Upper)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Item {
+    Char(char),
+    Range(char, char),
+    Class(Class),
+    /// `[=c=]`, the characters equivalent to `c`
+    Equivalence(char),
+    /// `[c*n]`, with no count for `[c*]` and `[c*0]`, which fill SET2 up
+    /// to the length of SET1
+    Repeat(char, Option<usize>),
+}
+
+impl Item {
+    /// Append the characters of the item to `out`, `fill` times a repeat
+    /// without count, and no more than `limit` characters in all
+    fn expand(&self, mode: Mode, fill: usize, limit: usize, out: &mut Vec<char>) {
+        let room = limit.saturating_sub(out.len());
+        match *self {
+            Item::Char(c) => out.push(c),
+            Item::Range(start, end) => out.extend((start..=end).take(room)),
+            Item::Class(class) => out.extend(
+                mode.domain()
+                    .filter(|&c| class.contains(c, mode.locale()))
+                    .take(room),
+            ),
+            Item::Equivalence(c) => out.extend(mode.locale().equivalents(c)),
+            Item::Repeat(c, count) => {
+                out.extend(std::iter::repeat_n(c, count.unwrap_or(fill).min(room)))
+            }
+        }
+        out.truncate(limit);
+    }
+}
+
+/// A set as written in an operand
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Set {
+    mode: Mode,
+    items: Vec<Item>,
+    /// Constructs that are taken in a way other implementations may not
+    warnings: Vec<String>,
+}
+
+impl Set {
+    pub fn parse(spec: &[u8], mode: Mode) -> Result<Self, String> {
+        let mut set = Set::new(mode);
+        let mut i = 0;
+        while i < spec.len() {
+            if spec[i] == b'[' {
+                if let Some((item, next)) = set.bracket(spec, i)? {
+                    set.items.push(item);
+                    i = next;
+                    continue;
+                }
+            }
+            let (start, next) = set.escaped(spec, i);
+            // A `-` first or last stands for itself
+            if spec.get(next) == Some(&b'-') && next + 1 < spec.len() {
+                let (end, after) = set.escaped(spec, next + 1);
+                if end < start {
+                    let range = String::from_utf8_lossy(&spec[i..after]);
+                    return Err(tr(
+                        "range-endpoints of {} are in reverse collating sequence order",
+                    )
+                    .replace("{}", &format!("'{range}'")));
+                }
+                set.items.push(Item::Range(start, end));
+                i = after;
+            } else {
+                set.items.push(Item::Char(start));
+                i = next;
+            }
+        }
+        Ok(set)
+    }
+
+    fn new(mode: Mode) -> Self {
+        Set {
+            mode,
+            items: Vec::new(),
+            warnings: Vec::new(),
+        }
+    }
+
+    /// Warnings about the constructs of the set, to print before using it
+    pub fn warnings(&self) -> &[String] {
+        &self.warnings
+    }
+
+    /// The character starting at `spec[i]`, a byte or, in UTF-8 mode, the
+    /// UTF-8 sequence starting there, and the index after it. Invalid
+    /// UTF-8 stands for the replacement character.
+    fn char_at(&self, spec: &[u8], i: usize) -> (char, usize) {
+        if self.mode == Mode::Bytes || spec[i].is_ascii() {
+            return (char::from(spec[i]), i + 1);
+        }
+        let len = match spec[i] {
+            0xf0.. => 4,
+            0xe0.. => 3,
+            _ => 2,
+        };
+        spec.get(i..i + len)
+            .and_then(|sequence| std::str::from_utf8(sequence).ok())
+            .and_then(|sequence| sequence.chars().next())
+            .map_or((char::REPLACEMENT_CHARACTER, i + 1), |c| (c, i + len))
+    }
+
+    /// The character at `spec[i]` or the escape starting there, and the
+    /// index after it
+    fn escaped(&mut self, spec: &[u8], i: usize) -> (char, usize) {
+        if spec[i] != b'\\' {
+            return self.char_at(spec, i);
+        }
+        let Some(&c) = spec.get(i + 1) else {
+            self.warnings.push(
+                tr("warning: an unescaped backslash at end of string is not portable").to_string(),
+            );
+            return ('\\', i + 1);
+        };
+        let c = match c {
+            b'a' => '\x07',
+            b'b' => '\x08',
+            b'f' => '\x0c',
+            b'n' => '\n',
+            b'r' => '\r',
+            b't' => '\t',
+            b'v' => '\x0b',
+            b'0'..=b'7' => return self.octal(spec, i + 1),
+            _ => return self.char_at(spec, i + 1),
+        };
+        (c, i + 2)
+    }
+
+    /// The byte of up to three octal digits at `spec[start..]`, and the
+    /// index after them. A third digit that would make it larger than a
+    /// byte isn't part of it. In UTF-8 mode the byte stands for the
+    /// character of its value.
+    fn octal(&mut self, spec: &[u8], start: usize) -> (char, usize) {
+        let digits = spec[start..]
+            .iter()
+            .take(3)
+            .take_while(|d| (b'0'..=b'7').contains(d))
+            .count();
+        let value = spec[start..start + digits]
+            .iter()
+            .fold(0u32, |value, d| value * 8 + u32::from(d - b'0'));
+        if let Ok(b) = u8::try_from(value) {
+            return (char::from(b), start + digits);
+        }
+        let escape = String::from_utf8_lossy(&spec[start..start + 3]);
+        self.warnings.push(
+            tr("warning: the ambiguous octal escape \\{} is being\n\tinterpreted as the 2-byte sequence \\0{}, {}")
+                .replacen("{}", &escape, 1)
+                .replacen("{}", &escape[..2], 1)
+                .replacen("{}", &escape[2..], 1),
+        );
+        (
+            char::from(u8::try_from(value / 8).unwrap_or(u8::MAX)),
+            start + 2,
+        )
+    }
+
+    /// The class, equivalence class or repeat starting with the `[` at
+    /// `spec[i]`, and the index after it, `None` when the `[` stands for
+    /// itself
+    fn bracket(&mut self, spec: &[u8], i: usize) -> Result<Option<(Item, usize)>, String> {
+        let rest = &spec[i + 1..];
+        if let Some(rest) = rest.strip_prefix(b":") {
+            let Some(end) = rest.windows(2).position(|w| w == b":]") else {
+                return Ok(None);
+            };
+            let name = &rest[..end];
+            let class = Class::from_name(name).ok_or_else(|| {
+                tr("invalid character class {}")
+                    .replace("{}", &format!("'{}'", String::from_utf8_lossy(name)))
+            })?;
+            return Ok(Some((Item::Class(class), i + 2 + end + 2)));
+        }
+        if let Some(rest) = rest.strip_prefix(b"=") {
+            let Some(end) = rest.windows(2).position(|w| w == b"=]") else {
+                return Ok(None);
+            };
+            let operand = &rest[..end];
+            if operand.is_empty() {
+                return Err(tr("missing equivalence class character {}").replace("{}", "'[==]'"));
+            }
+            let mut lookahead = Set::new(self.mode);
+            let (c, next) = lookahead.escaped(operand, 0);
+            if next < operand.len() {
+                return Err(format!(
+                    "{}: {}",
+                    String::from_utf8_lossy(operand),
+                    tr("equivalence class operand must be a single character")
+                ));
+            }
+            self.warnings.append(&mut lookahead.warnings);
+            return Ok(Some((Item::Equivalence(c), i + 2 + end + 2)));
+        }
+        if rest.is_empty() {
+            return Ok(None);
+        }
+        let mut lookahead = Set::new(self.mode);
+        let (c, star) = lookahead.escaped(spec, i + 1);
+        if spec.get(star) != Some(&b'*') {
+            return Ok(None);
+        }
+        let Some(len) = spec[star + 1..].iter().position(|&c| c == b']') else {
+            return Ok(None);
+        };
+        let count = &spec[star + 1..star + 1 + len];
+        self.warnings.append(&mut lookahead.warnings);
+        let next = star + 1 + len + 1;
+        if count.is_empty() {
+            return Ok(Some((Item::Repeat(c, None), next)));
+        }
+        // Octal with a leading zero, like C
+        let radix = if count[0] == b'0' { 8 } else { 10 };
+        let count = std::str::from_utf8(count)
+            .ok()
+            .filter(|count| count.bytes().all(|c| c.is_ascii_digit()))
+            .and_then(|count| usize::from_str_radix(count, radix).ok())
+            .ok_or_else(|| {
+                tr("invalid repeat count {} in [c*n] construct")
+                    .replace("{}", &format!("'{}'", String::from_utf8_lossy(count)))
+            })?;
+        Ok(Some((Item::Repeat(c, (count > 0).then_some(count)), next)))
+    }
+
+    /// Whether the set has a repeat without count, which only SET2 may
+    /// have
+    pub fn has_indefinite_repeat(&self) -> bool {
+        self.items
+            .iter()
+            .any(|item| matches!(item, Item::Repeat(_, None)))
+    }
+
+    /// The characters of the set in the order they are written, with
+    /// repeats without count standing for none
+    pub fn chars(&self) -> Vec<char> {
+        self.expand(0, usize::MAX)
+    }
+
+    /// The first `limit` characters that aren't in the set, in ascending
+    /// order
+    pub fn complement(&self, limit: usize) -> Vec<char> {
+        let mut chars = self.chars();
+        chars.sort_unstable();
+        self.mode
+            .domain()
+            .filter(|c| chars.binary_search(c).is_err())
+            .take(limit)
+            .collect()
+    }
+
+    fn expand(&self, fill: usize, limit: usize) -> Vec<char> {
+        let mut out = Vec::new();
+        for item in &self.items {
+            item.expand(self.mode, fill, limit, &mut out);
+        }
+        out
+    }
+
+    /// The cases of the set, each with its offset in the expanded set and
+    /// its characters
+    fn cases(&self) -> Vec<(usize, Class, Vec<char>)> {
+        let mut cases = Vec::new();
+        let mut out = Vec::new();
+        for item in &self.items {
+            let start = out.len();
+            item.expand(self.mode, 0, usize::MAX, &mut out);
+            if let Item::Class(class) = *item {
+                if class.is_case() {
+                    cases.push((start, class, out[start..].to_vec()));
+                }
+            }
+        }
+        cases
+    }
+
+    /// The characters of the set as SET2 of a translation, where a case
+    /// takes the characters of the case of SET1 at the same offset, in its
+    /// own case. Also tells whether every case is aligned with one, those
+    /// that aren't being expanded like elsewhere.
+    fn expand_translated(
+        &self,
+        cases1: &[(usize, Class, Vec<char>)],
+        fill: usize,
+        limit: usize,
+    ) -> (Vec<char>, bool) {
+        let locale = self.mode.locale();
+        let mut out = Vec::new();
+        let mut is_aligned = true;
+        for item in &self.items {
+            let Item::Class(class) = *item else {
+                item.expand(self.mode, fill, limit, &mut out);
+                continue;
+            };
+            let Some((_, class1, chars1)) = cases1.iter().find(|case| case.0 == out.len()) else {
+                is_aligned = false;
+                item.expand(self.mode, fill, limit, &mut out);
+                continue;
+            };
+            let convert = |c| match (class1, class) {
+                (Class::Lower, Class::Upper) => locale.to_upper(c),
+                (Class::Upper, Class::Lower) => locale.to_lower(c),
+                _ => c,
+            };
+            out.extend(chars1.iter().copied().map(convert));
+            out.truncate(limit);
+        }
+        (out, is_aligned)
+    }
+}
+
+/// The characters a translation replaces and their replacements
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Translation {
+    /// The characters replaced, each by the character of `to` at the same
+    /// position
+    pub from: Vec<char>,
+    pub to: Vec<char>,
+    /// Replacement of the characters of a complement in UTF-8 mode that
+    /// aren't in `from`, which can't list them all
+    pub rest: Option<char>,
+}
+
+/// The translation of `set1`, or its complement, to `set2`. A repeat
+/// without count fills `set2` up to the length of `set1`, and a shorter
+/// `set2` is extended with its last character, unless `is_truncate`
+/// truncates `set1` to the length of `set2`. Classes of `set2` have to be
+/// cases, aligned with cases in `set1`.
+pub fn translation(
+    set1: &Set,
+    set2: &Set,
+    is_complement: bool,
+    is_truncate: bool,
+) -> Result<Translation, String> {
+    let mut fills = 0;
+    for item in &set2.items {
+        match item {
+            Item::Class(class) if !class.is_case() => {
+                return Err(tr("when translating, the only character classes that may appear in\nstring2 are 'upper' and 'lower'").to_string());
+            }
+            Item::Equivalence(_) => {
+                return Err(
+                    tr("[=c=] expressions may not appear in string2 when translating").to_string(),
+                );
+            }
+            Item::Repeat(_, None) => fills += 1,
+            _ => (),
+        }
+    }
+    if fills > 1 {
+        return Err(tr("only one [c*] repeat construct may appear in string2").to_string());
+    }
+    let cases1 = if is_complement {
+        Vec::new()
+    } else {
+        set1.cases()
+    };
+    let (unfilled, _) = set2.expand_translated(&cases1, 0, usize::MAX);
+    // The complement of UTF-8 text has no end, the characters of SET2 are
+    // paired with its start and the last one replaces the rest
+    let is_endless = is_complement && matches!(set1.mode, Mode::Utf8(_));
+    let mut from = match (is_complement, is_endless) {
+        (false, _) => set1.chars(),
+        (true, false) => set1.complement(usize::MAX),
+        (true, true) => set1.complement(unfilled.len()),
+    };
+    let fill = from.len().saturating_sub(unfilled.len());
+    let (mut to, is_aligned) = set2.expand_translated(&cases1, fill, from.len());
+    if is_truncate {
+        from.truncate(to.len());
+    }
+    let is_short = to.len() < from.len() || (is_endless && !is_truncate);
+    if is_short && matches!(set2.items.last(), Some(Item::Class(_))) {
+        return Err(tr("when translating with string1 longer than string2,\nthe latter string must not end with a character class").to_string());
+    }
+    if to.is_empty() && is_short {
+        return Err(tr("when not truncating set1, string2 must be non-empty").to_string());
+    }
+    if !is_aligned {
+        return Err(tr("misaligned [:upper:] and/or [:lower:] construct").to_string());
+    }
+    let rest = to.last().copied().filter(|_| is_short);
+    if let Some(last) = rest {
+        to.resize(from.len(), last);
+    }
+    Ok(Translation {
+        from,
+        to,
+        rest: rest.filter(|_| is_endless),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(spec: &[u8]) -> Result<Set, String> {
+        Set::parse(spec, Mode::Bytes)
+    }
+
+    fn to_bytes(chars: &[char]) -> Vec<u8> {
+        chars.iter().map(|&c| u8::try_from(c).unwrap()).collect()
+    }
+
+    fn bytes(spec: &str) -> Vec<u8> {
+        to_bytes(&parse(spec.as_bytes()).unwrap().chars())
+    }
+
+    fn translation_of(set1: &str, set2: &str) -> Result<(Vec<u8>, Vec<u8>), String> {
+        let set1 = parse(set1.as_bytes()).unwrap();
+        let set2 = parse(set2.as_bytes()).unwrap();
+        let translation = translation(&set1, &set2, false, false)?;
+        Ok((to_bytes(&translation.from), to_bytes(&translation.to)))
+    }
+
+    fn utf8(spec: &str) -> Set {
+        Set::parse(spec.as_bytes(), Mode::Utf8(Locale::Unicode)).unwrap()
+    }
+
+    #[test]
+    fn literals_and_escapes() {
+        assert_eq!(bytes("abc"), b"abc");
+        assert_eq!(bytes(r"\n\t\\\a\b\f\r\v"), b"\n\t\\\x07\x08\x0c\r\x0b");
+        assert_eq!(bytes(r"\q\-"), b"q-");
+        assert_eq!(bytes(""), b"");
+    }
+
+    #[test]
+    fn octal_escapes() {
+        assert_eq!(bytes(r"\0\12\101\1010"), b"\0\nAA0");
+        assert_eq!(bytes(r"\8"), b"8");
+        assert_eq!(bytes(r"\377"), b"\xff");
+        let set = parse(br"\400").unwrap();
+        assert_eq!(to_bytes(&set.chars()), b" 0");
+        assert_eq!(set.warnings().len(), 1);
+    }
+
+    #[test]
+    fn trailing_backslash() {
+        let set = parse(br"a\").unwrap();
+        assert_eq!(to_bytes(&set.chars()), br"a\");
+        assert_eq!(set.warnings().len(), 1);
+    }
+
+    #[test]
+    fn ranges() {
+        assert_eq!(bytes("a-e"), b"abcde");
+        assert_eq!(bytes("a-a"), b"a");
+        assert_eq!(bytes(r"\060-\063"), b"0123");
+        assert_eq!(bytes("-a-c-"), b"-abc-");
+        assert_eq!(bytes("a-"), b"a-");
+        assert_eq!(bytes("0-9a-f"), b"0123456789abcdef");
+        let err = parse(b"z-a").unwrap_err();
+        assert!(err.contains("'z-a'"), "{err}");
+    }
+
+    #[test]
+    fn classes() {
+        assert_eq!(bytes("[:digit:]"), b"0123456789");
+        assert_eq!(bytes("[:xdigit:]"), b"0123456789ABCDEFabcdef");
+        assert_eq!(bytes("[:blank:]"), b"\t ");
+        assert_eq!(bytes("[:space:]"), b"\t\n\x0b\x0c\r ");
+        assert_eq!(bytes("[:upper:]").len(), 26);
+        assert_eq!(bytes("[:punct:]").len(), 32);
+        assert_eq!(bytes("[:cntrl:]").len(), 33);
+        assert_eq!(bytes("[:print:]").len(), 95);
+        assert_eq!(bytes("[:graph:]").len(), 94);
+        assert_eq!(bytes("[:alnum:]").len(), 62);
+        assert_eq!(bytes("x[:digit:]y"), b"x0123456789y");
+        let err = parse(b"[:foo:]").unwrap_err();
+        assert!(err.contains("'foo'"), "{err}");
+    }
+
+    #[test]
+    fn brackets_standing_for_themselves() {
+        assert_eq!(bytes("[:digit"), b"[:digit");
+        assert_eq!(bytes("[=a"), b"[=a");
+        assert_eq!(bytes("[a]"), b"[a]");
+        assert_eq!(bytes("[a*"), b"[a*");
+        assert_eq!(bytes("["), b"[");
+        assert_eq!(bytes("[]"), b"[]");
+    }
+
+    #[test]
+    fn repeats() {
+        assert_eq!(bytes("[a*3]"), b"aaa");
+        assert_eq!(bytes("[a*010]"), b"aaaaaaaa");
+        assert_eq!(bytes(r"[\n*2]x"), b"\n\nx");
+        assert_eq!(bytes("[a*]b"), b"b");
+        assert_eq!(bytes("[a*0]b"), b"b");
+        assert!(parse(b"[a*]").unwrap().has_indefinite_repeat());
+        assert!(parse(b"[a*0]").unwrap().has_indefinite_repeat());
+        assert!(!parse(b"[a*3]").unwrap().has_indefinite_repeat());
+        assert!(!parse(b"a*]").unwrap().has_indefinite_repeat());
+        for count in ["09", "b", "1x"] {
+            let err = parse(format!("[a*{count}]").as_bytes()).unwrap_err();
+            assert!(err.contains(&format!("'{count}'")), "{err}");
+        }
+    }
+
+    #[test]
+    fn equivalence_classes() {
+        assert_eq!(bytes("[=a=]b"), b"ab");
+        assert_eq!(bytes(r"[=\n=]"), b"\n");
+        assert_eq!(
+            utf8("[=e=]").chars(),
+            "eèéêëēĕėęěȅȇȩḕḗḙḛḝẹẻẽếềểễệ".chars().collect::<Vec<_>>()
+        );
+        assert_eq!(utf8("[=é=]").chars(), utf8("[=e=]").chars());
+        assert_eq!(utf8("[=E=]").chars()[..3], ['E', 'È', 'É']);
+        assert_eq!(utf8("[=ж=]").chars(), ['ж']);
+        assert!(parse(b"[==]").is_err());
+        assert!(parse(b"[=ab=]").is_err());
+    }
+
+    #[test]
+    fn utf8_sets() {
+        assert_eq!(utf8("äö-ø").chars(), ['ä', 'ö', '÷', 'ø']);
+        assert_eq!(utf8(r"\ä\377").chars(), ['ä', 'ÿ']);
+        assert!(utf8("[:upper:]").chars().contains(&'Ж'));
+        assert!(!utf8("[:lower:]").chars().contains(&'Ж'));
+        let c_locale = Set::parse("[:alpha:]é".as_bytes(), Mode::Utf8(Locale::C)).unwrap();
+        assert_eq!(c_locale.chars().len(), 53);
+    }
+
+    #[test]
+    fn complement() {
+        let set = parse(b"\\000-\\177").unwrap();
+        assert_eq!(
+            to_bytes(&set.complement(usize::MAX)),
+            (128..=255).collect::<Vec<u8>>()
+        );
+        assert_eq!(parse(b"").unwrap().complement(usize::MAX).len(), 256);
+        assert_eq!(utf8("\0-\u{ff}").complement(2), ['\u{100}', '\u{101}']);
+    }
+
+    #[test]
+    fn translation_pads_and_fills() {
+        assert_eq!(
+            translation_of("abc", "xy").unwrap(),
+            (b"abc".to_vec(), b"xyy".to_vec())
+        );
+        assert_eq!(translation_of("abcd", "x[y*]z").unwrap().1, b"xyyz");
+        assert_eq!(translation_of("ab", "x[y*]z").unwrap().1, b"xz");
+        assert_eq!(translation_of("ab", "wxyz").unwrap().1, b"wx");
+        assert_eq!(translation_of("ab", "[x*5]").unwrap().1, b"xx");
+        let set1 = parse(b"abc").unwrap();
+        let set2 = parse(b"x").unwrap();
+        let translation = translation(&set1, &set2, false, true).unwrap();
+        assert_eq!((translation.from, translation.to), (vec!['a'], vec!['x']));
+    }
+
+    #[test]
+    fn translation_of_complement() {
+        let set1 = parse(b"\\001-\\377").unwrap();
+        let set2 = parse(b"x").unwrap();
+        let translation = translation(&set1, &set2, true, false).unwrap();
+        assert_eq!((translation.from, translation.to), (vec!['\0'], vec!['x']));
+        assert_eq!(translation.rest, None);
+    }
+
+    #[test]
+    fn translation_of_endless_complement() {
+        let translated = translation(&utf8("a"), &utf8("xy"), true, false).unwrap();
+        assert_eq!(translated.from, ['\0', '\u{1}']);
+        assert_eq!(translated.to, ['x', 'y']);
+        assert_eq!(translated.rest, Some('y'));
+        let truncated = translation(&utf8("a"), &utf8("xy"), true, true).unwrap();
+        assert_eq!(truncated.rest, None);
+    }
+
+    #[test]
+    fn translation_of_cases() {
+        let (from, to) = translation_of("[:lower:]", "[:upper:]").unwrap();
+        assert_eq!((&from[..], &to[..]), (&bytes("a-z")[..], &bytes("A-Z")[..]));
+        assert!(translation_of("[:upper:]", "[:upper:]").is_ok());
+        assert!(translation_of("a[:upper:]", "b[:lower:]").is_ok());
+        assert!(translation_of("[:upper:]", "[:lower:]b").is_ok());
+        assert!(translation_of("[:digit:]", "[:alpha:]").is_err());
+        assert!(translation_of("a-z", "[:upper:]").is_err());
+        assert!(translation_of("[:digit:]", "[:lower:]").is_err());
+        assert!(translation_of("a[:upper:]", "[:lower:]").is_err());
+        assert!(translation_of("[:upper:]a", "[:lower:]").is_err());
+    }
+
+    #[test]
+    fn translation_of_unicode_cases() {
+        let translated =
+            translation(&utf8("[:lower:]x"), &utf8("[:upper:]y"), false, false).unwrap();
+        let pairs: Vec<_> = translated.from.iter().zip(&translated.to).collect();
+        assert!(pairs.contains(&(&'ж', &'Ж')));
+        assert!(pairs.contains(&(&'é', &'É')));
+        assert_eq!(pairs.last(), Some(&(&'x', &'y')));
+        assert_eq!(translated.from.len(), translated.to.len());
+    }
+
+    #[test]
+    fn translation_errors() {
+        assert!(translation_of("a", "").is_err());
+        assert!(translation_of("a", "[x*][y*]").is_err());
+        assert!(translation_of("a", "[=x=]").is_err());
+        assert!(translation_of("", "").is_ok());
+    }
+}
@@ -0,0 +1,41 @@
+//! Command line interface of `true`
+
+use clap::{ArgAction, CommandFactory, Parser};
+use nyaa_core::i18n;
+use std::ffi::OsString;
+use std::process::ExitCode;
+
+/// The options as help, the man page and completions show them. Only
+/// `--help` and `--version` as the only argument are options.
+#[derive(Parser)]
+#[command(name = "true", version)]
+#[command(about = "do nothing, successfully", long_about = None)]
+#[command(disable_help_flag = true, disable_version_flag = true)]
+#[allow(dead_code)]
+struct Args {
+    /// Arguments, which are ignored
+    #[arg(value_name = "ARGUMENT")]
+    arguments: Vec<OsString>,
+
+    /// Print help, when it is the only argument
+    #[arg(long, action=ArgAction::Help)]
+    help: (),
+
+    /// Print version, when it is the only argument
+    #[arg(long, action=ArgAction::Version)]
+    version: (),
+}
+
+pub fn command() -> clap::Command {
+    i18n::command(Args::command())
+}
+
+/// Run `true` with the command line `args`, starting with the program name
+pub fn main(args: Vec<OsString>) -> ExitCode {
+    if let [_, arg] = &args[..] {
+        if matches!(arg.as_encoded_bytes(), b"--help" | b"--version") {
+            command().get_matches_from(&args);
+        }
+    }
+    ExitCode::SUCCESS
+}
@@ -0,0 +1,5 @@
+//! `true` of coreutils: exits successfully whatever the arguments, which
+//! are ignored unless `--help` or `--version` is the only one, for shells
+//! without the built-in.
+
+pub mod cli;
@@ -0,0 +1,226 @@
+//! Command line interface of `uniq`
+
+use crate::{Delimit, Error, Grouping, Options};
+use clap::{ArgAction, CommandFactory, FromArgMatches, Parser};
+use nyaa_core::error::{Status, UError, UResult};
+use nyaa_core::i18n;
+use nyaa_core::output::{self, OutputSink};
+use nyaa_core::posix;
+use nyaa_core::quote::{quote, shell_escape};
+use nyaa_core::record::Terminator;
+use nyaa_core::response;
+use nyaa_core::trace;
+use nyaa_core::Input;
+use std::ffi::OsString;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+const UTIL: &str = "uniq";
+
+#[derive(Parser)]
+#[command(name = "uniq", version)]
+#[command(about = "report or omit repeated lines", long_about = None)]
+#[command(disable_help_flag = true, disable_version_flag = true)]
+#[command(args_override_self = true)]
+struct Args {
+    /// Prefix lines by the number of lines they stand for
+    #[arg(short = 'c', long = "count")]
+    is_count: bool,
+
+    /// Output only the lines repeated, one for each group
+    #[arg(short = 'd', long = "repeated")]
+    is_repeated: bool,
+
+    /// Output all lines of the groups of repeated lines, with empty lines
+    /// around the groups as METHOD says
+    #[arg(
+        long = "all-repeated",
+        value_name = "METHOD",
+        value_enum,
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "none"
+    )]
+    all_repeated: Option<Delimit>,
+
+    /// Same as --all-repeated=none
+    #[arg(short = 'D')]
+    is_all_repeated: bool,
+
+    /// Output all lines, with empty lines around the groups as METHOD says
+    #[arg(
+        long = "group",
+        value_name = "METHOD",
+        value_enum,
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "separate"
+    )]
+    grouping: Option<Grouping>,
+
+    /// Output only the lines not repeated
+    #[arg(short = 'u', long = "unique")]
+    is_unique: bool,
+
+    /// Ignore the case of ASCII letters when comparing
+    #[arg(short = 'i', long = "ignore-case")]
+    is_ignore_case: bool,
+
+    /// Leave the first N fields out of comparisons, each blanks then
+    /// non-blank characters
+    #[arg(
+        short = 'f',
+        long = "skip-fields",
+        value_name = "N",
+        allow_hyphen_values = true
+    )]
+    skip_fields: Option<String>,
+
+    /// Leave the first N bytes after the skipped fields out of comparisons
+    #[arg(
+        short = 's',
+        long = "skip-chars",
+        value_name = "N",
+        allow_hyphen_values = true
+    )]
+    skip_chars: Option<String>,
+
+    /// Compare at most N bytes of the lines after the skipped ones
+    #[arg(
+        short = 'w',
+        long = "check-chars",
+        value_name = "N",
+        allow_hyphen_values = true
+    )]
+    check_chars: Option<String>,
+
+    /// Line delimiter is NUL, not newline
+    #[arg(short = 'z', long = "zero-terminated")]
+    is_zero_terminated: bool,
+
+    /// The INPUT file and the OUTPUT file, the stdin and stdout without
+    /// them or for -. An argument @FILE stands for the arguments on the
+    /// lines of FILE, @@NAME for the file @NAME
+    #[arg(value_name = "INPUT [OUTPUT]")]
+    operands: Vec<PathBuf>,
+
+    /// Print help
+    #[arg(long, action=ArgAction::Help)]
+    help: (),
+
+    /// Print version
+    #[arg(long, action=ArgAction::Version)]
+    version: (),
+}
+
+pub fn command() -> clap::Command {
+    i18n::command(Args::command())
+}
+
+/// The count of an option, non-negative and at most the largest one
+fn count(spec: Option<&str>, message: &str) -> UResult<usize> {
+    let Some(spec) = spec else {
+        return Ok(0);
+    };
+    let digits = spec.trim_start();
+    let digits = digits.strip_prefix('+').unwrap_or(digits);
+    if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(UError::failure(message).with_name(spec));
+    }
+    Ok(digits.bytes().fold(0usize, |n, digit| {
+        n.saturating_mul(10)
+            .saturating_add(usize::from(digit - b'0'))
+    }))
+}
+
+fn options(args: &Args) -> UResult<Options> {
+    let is_all_repeated = args.is_all_repeated || args.all_repeated.is_some();
+    if args.grouping.is_some()
+        && (args.is_count || args.is_repeated || is_all_repeated || args.is_unique)
+    {
+        return Err(UError::usage(
+            "--group is mutually exclusive with -c/-d/-D/-u",
+        ));
+    }
+    if args.is_count && is_all_repeated {
+        return Err(UError::usage(
+            "printing all duplicated lines and repeat counts is meaningless",
+        ));
+    }
+    let check_chars = match &args.check_chars {
+        Some(spec) => count(Some(spec), "invalid number of bytes to compare")?,
+        None => usize::MAX,
+    };
+    Ok(Options {
+        skip_fields: count(
+            args.skip_fields.as_deref(),
+            "invalid number of fields to skip",
+        )?,
+        skip_chars: count(
+            args.skip_chars.as_deref(),
+            "invalid number of bytes to skip",
+        )?,
+        check_chars,
+        is_ignore_case: args.is_ignore_case,
+        is_count: args.is_count,
+        prints_unique: !(args.is_repeated || is_all_repeated),
+        prints_repeated: !args.is_unique,
+        prints_later_repeated: is_all_repeated,
+        delimit: args.all_repeated.unwrap_or_default(),
+        grouping: args.grouping,
+        terminator: Terminator::line(args.is_zero_terminated),
+    })
+}
+
+/// Filter the lines of the `input` operand into the `output` one
+fn run(input: &Path, output: &Path, options: &Options) -> UResult<()> {
+    let input = Input::from_operand(input);
+    let name = input
+        .quoted_name()
+        .map_or_else(|| "-".to_string(), |name| name.into_owned());
+    let reader = input
+        .open_buffered()
+        .map_err(|e| UError::from(e).with_name(&name))?;
+    let mut out = if output == Path::new("-") {
+        OutputSink::stdout()
+    } else {
+        let file = File::create(output)
+            .map_err(|e| UError::from(e).with_name(quote(output.as_os_str())))?;
+        OutputSink::new(Box::new(file), false)
+    };
+    match crate::uniq(reader, options, &mut out) {
+        Ok(()) => out.finish(),
+        Err(Error::Read(e)) => {
+            let operand = input.name().unwrap_or("-".into()).into_owned();
+            let name = i18n::tr("error reading {}").replace("{}", &shell_escape(operand.as_ref()));
+            Err(UError::from(e).with_name(name))
+        }
+        Err(Error::Write(e)) => Err(output::write_error(e)),
+    }
+}
+
+/// Run `uniq` with the command line `args`, starting with the program name
+pub fn main(args: Vec<OsString>) -> ExitCode {
+    let mut status = Status::default();
+    let Some(args) = status.check(UTIL, response::expand(args)) else {
+        return status.into();
+    };
+    let args = posix::operands_last(&command(), args);
+    let matches = command().get_matches_from(args);
+    let args = Args::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+    trace::init(UTIL, false);
+    let Some(options) = status.check(UTIL, options(&args)) else {
+        return status.into();
+    };
+    if let Some(extra) = args.operands.get(2) {
+        let message = i18n::tr("extra operand {}").replace("{}", &shell_escape(extra.as_os_str()));
+        status.report(UTIL, &UError::usage(message));
+        return status.into();
+    }
+    let stdio = Path::new("-");
+    let input = args.operands.first().map_or(stdio, PathBuf::as_path);
+    let output = args.operands.get(1).map_or(stdio, PathBuf::as_path);
+    status.check(UTIL, run(input, output, &options));
+    status.into()
+}
@@ -0,0 +1,363 @@
+//! Filtering of adjacent matching lines with the semantics of `uniq`.
+//!
+//! Lines are read one at a time and compared with the one before them as
+//! raw bytes, so a group of adjacent matching lines is output as its
+//! first line when it ends, whatever the size of the input. Lines match by
+//! the part of them after the skipped fields and characters, up to the
+//! compared width. With `-D` all lines of the repeated groups are output
+//! instead, and with `--group` all lines, with empty lines around the
+//! groups.
+//!
+//! ```
+//! let options = nyaa_uniq::Options {
+//!     is_count: true,
+//!     ..Default::default()
+//! };
+//! let mut out = Vec::new();
+//! nyaa_uniq::uniq(&b"a\na\nb\n"[..], &options, &mut out).unwrap();
+//! assert_eq!(out, b"      2 a\n      1 b\n");
+//! ```
+
+pub mod cli;
+
+use nyaa_core::record::{Records, Terminator};
+use std::io::{self, BufRead, Write};
+
+/// How lines are compared and which of them are output
+#[derive(Clone, Debug)]
+pub struct Options {
+    /// Fields at the start of lines left out of comparisons, each blanks
+    /// then non-blank characters
+    pub skip_fields: usize,
+    /// Bytes left out of comparisons after the skipped fields
+    pub skip_chars: usize,
+    /// Most bytes compared after the skipped ones
+    pub check_chars: usize,
+    /// Whether ASCII letters match in either case
+    pub is_ignore_case: bool,
+    /// Whether output lines start with the number of lines of their group
+    pub is_count: bool,
+    /// Whether lines matching none around them are output
+    pub prints_unique: bool,
+    /// Whether the first line of groups of several lines is output
+    pub prints_repeated: bool,
+    /// Whether the lines of groups of several lines are output, like the
+    /// last one was by `prints_repeated`, before the next line of the group
+    pub prints_later_repeated: bool,
+    /// Where empty lines go around the groups of several lines
+    pub delimit: Delimit,
+    /// Where empty lines go around every group, all of whose lines are
+    /// output, instead of the lines selected by the options above
+    pub grouping: Option<Grouping>,
+    /// What ends the lines of the input and the output
+    pub terminator: Terminator,
+}
+
+/// Where `--all-repeated` puts empty lines around the groups output
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum Delimit {
+    /// No empty lines
+    #[default]
+    None,
+    /// An empty line before every group
+    Prepend,
+    /// An empty line between groups
+    Separate,
+}
+
+/// Where `--group` puts empty lines around the groups
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum Grouping {
+    /// An empty line between groups
+    Separate,
+    /// An empty line before every group
+    Prepend,
+    /// An empty line after every group
+    Append,
+    /// An empty line before and after every group
+    Both,
+}
+
+impl Default for Options {
+    /// Every group output as its first line, compared whole
+    fn default() -> Self {
+        Options {
+            skip_fields: 0,
+            skip_chars: 0,
+            check_chars: usize::MAX,
+            is_ignore_case: false,
+            is_count: false,
+            prints_unique: true,
+            prints_repeated: true,
+            prints_later_repeated: false,
+            delimit: Delimit::None,
+            grouping: None,
+            terminator: Terminator::Newline,
+        }
+    }
+}
+
+impl Options {
+    /// The part of `line`, without its terminator, that is compared
+    fn key<'a>(&self, line: &'a [u8]) -> &'a [u8] {
+        let mut start = 0;
+        for _ in 0..self.skip_fields {
+            if start == line.len() {
+                break;
+            }
+            start += line[start..].iter().take_while(|&&b| is_blank(b)).count();
+            start += line[start..].iter().take_while(|&&b| !is_blank(b)).count();
+        }
+        start += self.skip_chars.min(line.len() - start);
+        let end = start + self.check_chars.min(line.len() - start);
+        &line[start..end]
+    }
+
+    /// Whether the lines `a` and `b` match
+    fn matches(&self, a: &[u8], b: &[u8]) -> bool {
+        let (a, b) = (self.key(a), self.key(b));
+        if self.is_ignore_case {
+            a.eq_ignore_ascii_case(b)
+        } else {
+            a == b
+        }
+    }
+}
+
+/// Whether `b` separates fields
+fn is_blank(b: u8) -> bool {
+    matches!(b, b' ' | b'\t' | b'\n')
+}
+
+/// Failure of [`uniq`], reading its input or writing the output
+#[derive(Debug)]
+pub enum Error {
+    Read(io::Error),
+    Write(io::Error),
+}
+
+/// Output `line` if `options` select it, as a line that doesn't match any
+/// before it if `repeats` is 0, else as one of its group that matches the
+/// next line or not
+fn write_line(
+    line: &[u8],
+    is_match: bool,
+    repeats: u64,
+    options: &Options,
+    out: &mut impl Write,
+) -> io::Result<()> {
+    let is_selected = if repeats == 0 {
+        options.prints_unique
+    } else if !is_match {
+        options.prints_repeated
+    } else {
+        options.prints_later_repeated
+    };
+    if !is_selected {
+        return Ok(());
+    }
+    if options.is_count {
+        write!(out, "{:7} ", repeats + 1)?;
+    }
+    out.write_all(line)?;
+    out.write_all(options.terminator.as_bytes())
+}
+
+/// Output every line of `records` with empty lines around the groups
+fn group<R: BufRead>(
+    records: &mut Records<R>,
+    grouping: Grouping,
+    options: &Options,
+    out: &mut impl Write,
+) -> Result<(), Error> {
+    let terminator = options.terminator.as_bytes();
+    let mut previous: Option<Vec<u8>> = None;
+    while let Some(record) = records.next_record().map_err(Error::Read)? {
+        let line = options.terminator.strip(record);
+        let is_new = previous
+            .as_ref()
+            .is_none_or(|previous| !options.matches(previous, line));
+        let is_delimited = match grouping {
+            Grouping::Prepend | Grouping::Both => true,
+            Grouping::Separate | Grouping::Append => previous.is_some(),
+        };
+        if is_new && is_delimited {
+            out.write_all(terminator).map_err(Error::Write)?;
+        }
+        out.write_all(line).map_err(Error::Write)?;
+        out.write_all(terminator).map_err(Error::Write)?;
+        let previous = previous.get_or_insert_with(Vec::new);
+        previous.clear();
+        previous.extend_from_slice(line);
+    }
+    if matches!(grouping, Grouping::Append | Grouping::Both) && previous.is_some() {
+        out.write_all(terminator).map_err(Error::Write)?;
+    }
+    Ok(())
+}
+
+/// Output the lines of `input` with the adjacent matching ones merged into
+/// the first of them, as `options` ask. The last line is terminated in the
+/// output even if it isn't in the input.
+pub fn uniq(input: impl BufRead, options: &Options, out: &mut impl Write) -> Result<(), Error> {
+    let mut records = Records::new(input, options.terminator);
+    if let Some(grouping) = options.grouping {
+        return group(&mut records, grouping, options, out);
+    }
+    let Some(first) = records.next_record().map_err(Error::Read)? else {
+        return Ok(());
+    };
+    // The line output next, with how many lines of its group matched it
+    let mut previous = options.terminator.strip(first).to_vec();
+    let mut repeats = 0;
+    let mut has_repeated_group = false;
+    while let Some(record) = records.next_record().map_err(Error::Read)? {
+        let line = options.terminator.strip(record);
+        let is_match = options.matches(&previous, line);
+        if is_match {
+            repeats += 1;
+        }
+        if !is_match && repeats > 0 {
+            has_repeated_group = true;
+        } else if is_match && repeats == 1 {
+            let is_delimited = match options.delimit {
+                Delimit::None => false,
+                Delimit::Prepend => true,
+                Delimit::Separate => has_repeated_group,
+            };
+            if is_delimited {
+                out.write_all(options.terminator.as_bytes())
+                    .map_err(Error::Write)?;
+            }
+        }
+        if !is_match || options.prints_later_repeated {
+            write_line(&previous, is_match, repeats, options, out).map_err(Error::Write)?;
+            previous.clear();
+            previous.extend_from_slice(line);
+            if !is_match {
+                repeats = 0;
+            }
+        }
+    }
+    write_line(&previous, false, repeats, options, out).map_err(Error::Write)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn uniq_str(input: &str, options: &Options) -> String {
+        let mut out = Vec::new();
+        uniq(input.as_bytes(), options, &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn merges_adjacent_lines() {
+        let options = Options::default();
+        assert_eq!(uniq_str("a\na\nb\na\na", &options), "a\nb\na\n");
+        assert_eq!(uniq_str("", &options), "");
+        assert_eq!(uniq_str("\n\n", &options), "\n");
+    }
+
+    #[test]
+    fn selects_and_counts_groups() {
+        let input = "a\na\nb\nc\nc\nc\n";
+        let mut options = Options {
+            is_count: true,
+            ..Options::default()
+        };
+        assert_eq!(
+            uniq_str(input, &options),
+            "      2 a\n      1 b\n      3 c\n"
+        );
+        options.prints_unique = false;
+        assert_eq!(uniq_str(input, &options), "      2 a\n      3 c\n");
+        options.prints_unique = true;
+        options.prints_repeated = false;
+        assert_eq!(uniq_str(input, &options), "      1 b\n");
+        options.prints_unique = false;
+        assert_eq!(uniq_str(input, &options), "");
+    }
+
+    #[test]
+    fn compares_parts_of_lines() {
+        let options = Options {
+            skip_fields: 1,
+            ..Options::default()
+        };
+        assert_eq!(
+            uniq_str("a x\nb x\nc  x\n\tx\n", &options),
+            "a x\nc  x\n\tx\n"
+        );
+        let options = Options {
+            skip_chars: 1,
+            check_chars: 2,
+            ..Options::default()
+        };
+        assert_eq!(uniq_str("axyz\nbxyw\nbx\n", &options), "axyz\nbx\n");
+        let options = Options {
+            skip_fields: usize::MAX,
+            skip_chars: usize::MAX,
+            ..Options::default()
+        };
+        assert_eq!(uniq_str("a\nb\n", &options), "a\n");
+    }
+
+    #[test]
+    fn ignores_ascii_case() {
+        let options = Options {
+            is_ignore_case: true,
+            ..Options::default()
+        };
+        assert_eq!(uniq_str("aé\nAé\nAÉ\n", &options), "aé\nAÉ\n");
+    }
+
+    #[test]
+    fn all_repeated() {
+        let input = "a\na\nb\nc\nc\nc\nd\n";
+        let mut options = Options {
+            prints_unique: false,
+            prints_later_repeated: true,
+            ..Options::default()
+        };
+        assert_eq!(uniq_str(input, &options), "a\na\nc\nc\nc\n");
+        options.delimit = Delimit::Separate;
+        assert_eq!(uniq_str(input, &options), "a\na\n\nc\nc\nc\n");
+        options.delimit = Delimit::Prepend;
+        assert_eq!(uniq_str(input, &options), "\na\na\n\nc\nc\nc\n");
+        options.delimit = Delimit::None;
+        options.prints_repeated = false;
+        assert_eq!(uniq_str(input, &options), "a\nc\nc\n");
+    }
+
+    #[test]
+    fn groups() {
+        let input = "a\na\nb\n";
+        let grouped = |grouping| {
+            let options = Options {
+                grouping: Some(grouping),
+                ..Options::default()
+            };
+            uniq_str(input, &options)
+        };
+        assert_eq!(grouped(Grouping::Separate), "a\na\n\nb\n");
+        assert_eq!(grouped(Grouping::Prepend), "\na\na\n\nb\n");
+        assert_eq!(grouped(Grouping::Append), "a\na\n\nb\n\n");
+        assert_eq!(grouped(Grouping::Both), "\na\na\n\nb\n\n");
+        let options = Options {
+            grouping: Some(Grouping::Both),
+            ..Options::default()
+        };
+        assert_eq!(uniq_str("", &options), "");
+    }
+
+    #[test]
+    fn nul_terminated() {
+        let options = Options {
+            terminator: Terminator::Nul,
+            ..Options::default()
+        };
+        assert_eq!(uniq_str("a\nb\0a\nb\0c", &options), "a\nb\0c\0");
+    }
+}
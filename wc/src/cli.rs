@@ -0,0 +1,856 @@
+//! Command line interface of `wc`
+
+use crate::{Count, Encoding, Options, Selection, WordRule};
+use clap::{ArgAction, CommandFactory, FromArgMatches, Parser, ValueEnum};
+use nyaa_core::error::{Status, UError};
+use nyaa_core::glob::{self, Pattern};
+use nyaa_core::i18n;
+use nyaa_core::input;
+use nyaa_core::jobs::{self, JobsArg};
+use nyaa_core::output::{self, OutputSink};
+use nyaa_core::platform;
+use nyaa_core::posix;
+use nyaa_core::progress::Progress;
+use nyaa_core::quote::quote;
+use nyaa_core::response;
+use nyaa_core::sandbox::SandboxArg;
+use nyaa_core::size::{self, Base};
+use nyaa_core::trace::{self, VerboseArg};
+use nyaa_core::walk::{self, Control, Event, Follow};
+use nyaa_core::Input;
+use std::borrow::Cow;
+use std::ffi::OsString;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+const UTIL: &str = "wc";
+
+#[derive(Parser)]
+#[command(name = "wc", version)]
+#[command(about = "word, line, and byte or character count", long_about = None)]
+#[command(disable_help_flag = true, disable_version_flag = true)]
+struct Args {
+    /// Write to the stdout the number of characters in each input file
+    #[arg(short = 'm', long = "chars")]
+    is_char: bool,
+
+    /// Write to the stdout the number of bytes in each input file
+    #[arg(short = 'c', long = "bytes")]
+    is_byte: bool,
+
+    /// Write to the stdout the number of <newline> characters in each input file
+    #[arg(short = 'l', long = "lines")]
+    is_line: bool,
+
+    /// Write to the stdout the number of words in each input file
+    #[arg(short = 'w', long = "words")]
+    is_word: bool,
+
+    /// Write to the stdout the display width of the longest line in each
+    /// input file, with tabs expanded to multiples of 8
+    #[arg(short = 'L', long = "max-line-length")]
+    is_max_line_length: bool,
+
+    /// Write to the stdout the number of grapheme clusters in each input file
+    #[arg(short = 'g', long = "graphemes")]
+    is_grapheme: bool,
+
+    /// Write to the stdout the number of sentences in each input file
+    #[arg(long = "sentences")]
+    is_sentence: bool,
+
+    /// Write to the stdout the number of blank-line separated paragraphs
+    /// in each input file
+    #[arg(long = "paragraphs")]
+    is_paragraph: bool,
+
+    /// Write to the stdout the number of lines followed by the number of
+    /// blank lines and comment lines among them in each input file
+    #[arg(long)]
+    code: bool,
+
+    /// Prefix of comment lines in --code mode, by default guessed from the
+    /// file name extension
+    #[arg(long, value_name = "PREFIX", requires = "code")]
+    comment_prefix: Option<String>,
+
+    /// Count NUL-terminated records instead of newline-terminated lines
+    #[arg(short = 'z', long = "zero-terminated")]
+    is_zero_terminated: bool,
+
+    /// Write to the stdout the number of occurrences of the character or
+    /// byte sequence C, which may contain \t, \n, \r, \0, \\ and \xHH escapes
+    #[arg(long, value_name = "C", value_parser = parse_needle)]
+    count_char: Option<Needle>,
+
+    /// How words are delimited, by default POSIX in the C locale and
+    /// Unicode otherwise, following LC_ALL, LC_CTYPE and LANG
+    #[arg(long, value_enum, value_name = "RULE")]
+    word_rule: Option<WordRule>,
+
+    /// Character encoding of the input used for counting characters,
+    /// words and line lengths
+    #[arg(long, value_enum, value_name = "ENCODING", default_value_t = Encoding::Utf8)]
+    encoding: Encoding,
+
+    /// Fail on invalid input in the encoding instead of counting each invalid sequence as
+    /// one character
+    #[arg(long = "strict")]
+    is_strict: bool,
+
+    /// Count the decompressed contents of compressed inputs, detected by
+    /// their magic bytes: gzip, xz, zstd, and bzip2 when built in
+    #[arg(long)]
+    decompress: bool,
+
+    /// Print the counts as a JSON array of objects
+    #[arg(long, group = "output")]
+    json: bool,
+
+    /// Print the counts as comma-separated values with a header row
+    #[arg(long, group = "output")]
+    csv: bool,
+
+    /// Print the counts as tab-separated values with a header row
+    #[arg(long, group = "output")]
+    tsv: bool,
+
+    /// Print the counts using a template: %l lines, %w words, %c bytes,
+    /// %m characters, %g grapheme clusters, %L maximum line length,
+    /// %s sentences, %p paragraphs, %o occurrences of --count-char,
+    /// %f file name, %% a literal %
+    #[arg(long, group = "output", value_name = "TEMPLATE", value_parser = parse_template)]
+    format: Option<Template>,
+
+    /// Print counts in human readable form with k, M, G... suffixes for
+    /// powers of 1000, rounded up
+    #[arg(short = 'h', long = "human-readable")]
+    is_human: bool,
+
+    /// Show the progress of counting on the stderr
+    #[arg(long)]
+    progress: bool,
+
+    /// When to print a line with total counts
+    #[arg(long, value_enum, value_name = "WHEN", default_value_t = Total::Auto)]
+    total: Total,
+
+    /// Name printed for the counts of the standard input
+    #[arg(long, value_name = "NAME")]
+    stdin_label: Option<String>,
+
+    /// Count every regular file under directory operands
+    #[arg(short = 'r', long)]
+    recursive: bool,
+
+    /// Skip files and directories whose name matches GLOB when recursing
+    #[arg(long, value_name = "GLOB", requires = "recursive")]
+    exclude: Vec<String>,
+
+    /// Read the input file names from the NUL-terminated names in file F,
+    /// - for the stdin
+    #[arg(long, value_name = "F", conflicts_with_all = ["files", "files_from"])]
+    files0_from: Option<PathBuf>,
+
+    /// Read the input file names from the lines of file F, - for the
+    /// stdin, skipping blank lines
+    #[arg(long, value_name = "F", conflicts_with = "files")]
+    files_from: Option<PathBuf>,
+
+    #[command(flatten)]
+    jobs: JobsArg,
+
+    #[command(flatten)]
+    sandbox: SandboxArg,
+
+    #[command(flatten)]
+    verbose: VerboseArg,
+
+    /// Input files. An argument @FILE stands for the arguments on the
+    /// lines of FILE, @@NAME for the file @NAME
+    files: Vec<PathBuf>,
+
+    /// Print help
+    #[arg(long, action=ArgAction::Help)]
+    help: (),
+
+    /// Print version
+    #[arg(long, action=ArgAction::Version)]
+    version: (),
+}
+
+#[derive(Clone)]
+struct Needle(Vec<u8>);
+
+fn parse_needle(s: &str) -> Result<Needle, String> {
+    let mut needle = Vec::new();
+    let mut bytes = s.bytes();
+    while let Some(b) = bytes.next() {
+        if b != b'\\' {
+            needle.push(b);
+            continue;
+        }
+        needle.push(match bytes.next() {
+            Some(b't') => b'\t',
+            Some(b'n') => b'\n',
+            Some(b'r') => b'\r',
+            Some(b'0') => b'\0',
+            Some(b'\\') => b'\\',
+            Some(b'x') => {
+                let hex = [bytes.next(), bytes.next()];
+                let hex: Option<Vec<u8>> = hex.into_iter().collect();
+                hex.and_then(|hex| u8::from_str_radix(std::str::from_utf8(&hex).ok()?, 16).ok())
+                    .ok_or("\\x must be followed by two hexadecimal digits")?
+            }
+            Some(b) => return Err(format!("unknown escape '\\{}'", char::from(b))),
+            None => return Err("trailing '\\'".to_string()),
+        });
+    }
+    if needle.is_empty() {
+        return Err("the character to count can't be empty".to_string());
+    }
+    Ok(Needle(needle))
+}
+
+#[derive(Clone)]
+enum Token {
+    Literal(String),
+    Lines,
+    Words,
+    Bytes,
+    Chars,
+    Graphemes,
+    MaxLineLength,
+    Sentences,
+    Paragraphs,
+    Occurrences,
+    File,
+}
+
+#[derive(Clone)]
+struct Template(Vec<Token>);
+
+fn parse_template(s: &str) -> Result<Template, String> {
+    let mut tokens = Vec::new();
+    let mut literal = String::new();
+    let mut chars = s.chars().peekable();
+    while let Some(c) = chars.next() {
+        let token = match c {
+            '%' => match chars.next() {
+                Some('l') => Token::Lines,
+                Some('w') => Token::Words,
+                Some('c') => Token::Bytes,
+                Some('m') => Token::Chars,
+                Some('g') => Token::Graphemes,
+                Some('L') => Token::MaxLineLength,
+                Some('o') => Token::Occurrences,
+                Some('s') => Token::Sentences,
+                Some('p') => Token::Paragraphs,
+                Some('f') => Token::File,
+                Some('%') => {
+                    literal.push('%');
+                    continue;
+                }
+                Some(c) => return Err(format!("unknown directive '%{c}'")),
+                None => return Err("template ends with a lone '%'".to_string()),
+            },
+            '\\' => {
+                let escaped = match chars.peek() {
+                    Some('t') => '\t',
+                    Some('n') => '\n',
+                    Some('\\') => '\\',
+                    _ => {
+                        literal.push('\\');
+                        continue;
+                    }
+                };
+                chars.next();
+                literal.push(escaped);
+                continue;
+            }
+            c => {
+                literal.push(c);
+                continue;
+            }
+        };
+        if !literal.is_empty() {
+            tokens.push(Token::Literal(std::mem::take(&mut literal)));
+        }
+        tokens.push(token);
+    }
+    if !literal.is_empty() {
+        tokens.push(Token::Literal(literal));
+    }
+    Ok(Template(tokens))
+}
+
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+enum Total {
+    /// Print the total only when more than one file is given
+    Auto,
+    /// Always print the total
+    Always,
+    /// Print only the total, without per-file counts
+    Only,
+    /// Never print the total
+    Never,
+}
+
+impl From<&Args> for Selection {
+    fn from(args: &Args) -> Self {
+        Selection {
+            chars: args.is_char,
+            bytes: args.is_byte,
+            lines: args.is_line,
+            words: args.is_word,
+            graphemes: args.is_grapheme,
+            max_line_length: args.is_max_line_length,
+            sentences: args.is_sentence,
+            paragraphs: args.is_paragraph,
+            code: args.code,
+            occurrences: args.count_char.is_some(),
+        }
+    }
+}
+
+impl<'a> From<&'a Args> for Options<'a> {
+    fn from(args: &'a Args) -> Self {
+        Options {
+            word_rule: args.word_rule.unwrap_or_else(WordRule::from_env),
+            needle: args
+                .count_char
+                .as_ref()
+                .map(|Needle(needle)| needle.as_slice()),
+            is_strict: args.is_strict,
+            encoding: args.encoding,
+            comment_prefix: args.comment_prefix.as_deref(),
+            decompress: args.decompress,
+            is_posix: posix::is_enabled(),
+            progress: None,
+            delimiter: if args.is_zero_terminated {
+                b'\0'
+            } else {
+                b'\n'
+            },
+        }
+    }
+}
+
+fn format_count(n: usize, is_human: bool) -> String {
+    if is_human {
+        size::human(n as u64, Base::Si)
+    } else {
+        n.to_string()
+    }
+}
+
+fn format_row(cnt: &Count, name: Option<&str>, width: usize, is_human: bool) -> String {
+    let fields: Vec<String> = cnt
+        .fields()
+        .map(|(_, value)| format!("{:>width$}", format_count(value, is_human)))
+        .collect();
+    let mut row = fields.join(" ");
+    if let Some(name) = name {
+        row.push(' ');
+        row.push_str(name);
+    }
+    row
+}
+
+fn json_string(s: &str) -> String {
+    let mut json = String::with_capacity(s.len() + 2);
+    json.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            c if c.is_control() => json.push_str(&format!("\\u{:04x}", c as u32)),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    json
+}
+
+fn csv_field(s: &str) -> Cow<'_, str> {
+    if s.contains([',', '"', '\n', '\r']) {
+        Cow::from(format!("\"{}\"", s.replace('"', "\"\"")))
+    } else {
+        Cow::from(s)
+    }
+}
+
+fn tsv_field(s: &str) -> Cow<'_, str> {
+    if s.contains(['\t', '\n', '\r', '\\']) {
+        Cow::from(
+            s.replace('\\', "\\\\")
+                .replace('\t', "\\t")
+                .replace('\n', "\\n")
+                .replace('\r', "\\r"),
+        )
+    } else {
+        Cow::from(s)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Format {
+    Table,
+    Json,
+    Csv,
+    Tsv,
+    Template,
+}
+
+/// Prints the per-file and total rows in the requested output format.
+/// Rows are written whole to a buffered stdout, which is flushed before
+/// diagnostics go to the stderr and at the end.
+struct Printer<'a> {
+    out: OutputSink,
+    format: Format,
+    template: Option<&'a Template>,
+    width: usize,
+    is_human: bool,
+    rows: usize,
+}
+
+impl<'a> Printer<'a> {
+    fn new(args: &'a Args, init_cnt: &Count, width: usize) -> Result<Self, io::Error> {
+        let format = if args.json {
+            Format::Json
+        } else if args.csv {
+            Format::Csv
+        } else if args.tsv {
+            Format::Tsv
+        } else if args.format.is_some() {
+            Format::Template
+        } else {
+            Format::Table
+        };
+        let mut printer = Printer {
+            out: OutputSink::stdout(),
+            format,
+            template: args.format.as_ref(),
+            // Human readable counts are at most four characters wide
+            width: if args.is_human { width.min(4) } else { width },
+            is_human: args.is_human,
+            rows: 0,
+        };
+        match format {
+            Format::Json => write!(printer.out, "[")?,
+            Format::Csv | Format::Tsv => {
+                let header: Vec<&str> = init_cnt.fields().map(|(name, _)| name).collect();
+                let row = printer.format_delimited("file", &header);
+                writeln!(printer.out, "{row}")?;
+            }
+            Format::Table | Format::Template => (),
+        }
+        Ok(printer)
+    }
+
+    /// Print the counts of `input`. The plain formats quote hostile names,
+    /// the others escape them in their own way.
+    fn print(&mut self, cnt: &Count, input: &Input) -> Result<(), io::Error> {
+        let row = match self.format {
+            Format::Table => {
+                let name = input.quoted_name();
+                format_row(cnt, name.as_deref(), self.width, self.is_human) + "\n"
+            }
+            Format::Json => {
+                let file = input
+                    .name()
+                    .map_or_else(|| "null".to_string(), |name| json_string(&name));
+                self.format_json(cnt, &format!("\"file\": {file}"))
+            }
+            Format::Csv | Format::Tsv => {
+                self.format_delimited_count(cnt, input.name().as_deref().unwrap_or(""))
+            }
+            Format::Template => {
+                self.format_template(cnt, input.quoted_name().as_deref().unwrap_or(""))
+            }
+        };
+        self.rows += 1;
+        self.out.write_all(row.as_bytes())
+    }
+
+    fn print_total(&mut self, cnt: &Count, name: Option<&str>) -> Result<(), io::Error> {
+        let row = match self.format {
+            Format::Table => format_row(cnt, name, self.width, self.is_human) + "\n",
+            Format::Json => self.format_json(cnt, "\"total\": true"),
+            Format::Csv | Format::Tsv => self.format_delimited_count(cnt, "total"),
+            Format::Template => self.format_template(cnt, name.unwrap_or("")),
+        };
+        self.rows += 1;
+        self.out.write_all(row.as_bytes())
+    }
+
+    fn format_json(&self, cnt: &Count, head: &str) -> String {
+        let fields: Vec<String> = cnt
+            .fields()
+            .map(|(name, value)| format!(", \"{name}\": {value}"))
+            .collect();
+        let sep = if self.rows == 0 { "\n" } else { ",\n" };
+        format!("{sep}  {{{head}{}}}", fields.concat())
+    }
+
+    fn format_delimited_count(&self, cnt: &Count, name: &str) -> String {
+        let values: Vec<String> = cnt.fields().map(|(_, value)| value.to_string()).collect();
+        self.format_delimited(name, &values) + "\n"
+    }
+
+    fn format_delimited<S: AsRef<str>>(&self, name: &str, values: &[S]) -> String {
+        let (name, sep) = match self.format {
+            Format::Tsv => (tsv_field(name), "\t"),
+            _ => (csv_field(name), ","),
+        };
+        let mut row = name.into_owned();
+        for value in values {
+            row.push_str(sep);
+            row.push_str(value.as_ref());
+        }
+        row
+    }
+
+    fn format_template(&self, cnt: &Count, name: &str) -> String {
+        let Some(Template(tokens)) = self.template else {
+            return String::new();
+        };
+        let mut line = String::new();
+        for token in tokens {
+            let value = match token {
+                Token::Literal(s) => {
+                    line.push_str(s);
+                    continue;
+                }
+                Token::File => {
+                    line.push_str(name);
+                    continue;
+                }
+                Token::Lines => cnt.lines,
+                Token::Words => cnt.words,
+                Token::Bytes => cnt.bytes,
+                Token::Chars => cnt.chars,
+                Token::Graphemes => cnt.graphemes,
+                Token::MaxLineLength => cnt.max_line_length,
+                Token::Sentences => cnt.sentences,
+                Token::Paragraphs => cnt.paragraphs,
+                Token::Occurrences => cnt.occurrences,
+            };
+            line.push_str(&format_count(value.unwrap_or(0), self.is_human));
+        }
+        line.push('\n');
+        line
+    }
+
+    /// Write out the buffered rows, so a diagnostic doesn't get ahead of them
+    fn flush(&mut self) -> Result<(), io::Error> {
+        self.out.flush()
+    }
+
+    fn finish(mut self) -> Result<(), io::Error> {
+        if self.format == Format::Json {
+            writeln!(self.out, "{}]", if self.rows == 0 { "" } else { "\n" })?;
+        }
+        self.out.flush()
+    }
+}
+
+fn count_input(input: &Input, init_cnt: &Count, options: &Options) -> Result<Count, io::Error> {
+    match input {
+        Input::Stdin(_) => {
+            nyaa_core::debug!("reading the stdin");
+            crate::count_reader(io::stdin().lock(), init_cnt, options)
+        }
+        Input::Descriptor(fd, path) => {
+            nyaa_core::debug!("reading descriptor {fd}");
+            input::open_descriptor(*fd, path)
+                .and_then(|file| crate::count_file(file, init_cnt, options))
+        }
+        Input::Path(path) => crate::count_path(path, init_cnt, options),
+    }
+}
+
+/// Width of the count columns, like GNU wc: wide enough for the combined
+/// size of the regular files, at least 7 when some input is not a
+/// regular file, and 1 for a single count of a single input. Decompressed
+/// inputs aren't bound by their size, so they get at least 7 as well.
+fn number_width(inputs: &[Input], init_cnt: &Count, decompress: bool) -> usize {
+    if inputs.len() == 1 && init_cnt.fields().count() == 1 {
+        return 1;
+    }
+    let mut minimum_width = if decompress { 7 } else { 1 };
+    let mut regular_total: u64 = 0;
+    for metadata in inputs.iter().filter_map(|input| input.metadata().ok()) {
+        if metadata.is_file() {
+            regular_total = regular_total.saturating_add(metadata.len());
+        } else {
+            minimum_width = 7;
+        }
+    }
+    regular_total.to_string().len().max(minimum_width)
+}
+
+/// Combined size of the inputs, unless some of them is not a regular file
+fn total_size(inputs: &[Input]) -> Option<u64> {
+    inputs
+        .iter()
+        .filter_map(|input| input.metadata().ok())
+        .map(|metadata| metadata.is_file().then_some(metadata.len()))
+        .sum()
+}
+
+/// Count and print all inputs, reporting the ones that could not be
+/// counted
+fn process_inputs(inputs: &[Input], args: &Args, status: &mut Status) {
+    let cnt = Count::new(&Selection::from(args));
+    // Operands reading the same descriptor, like several stdin ones,
+    // have to be read in order, the first one consumes the whole stream
+    let mut descriptors: Vec<i32> = inputs.iter().filter_map(Input::descriptor).collect();
+    descriptors.sort_unstable();
+    let jobs = if descriptors.windows(2).any(|pair| pair[0] == pair[1]) {
+        1
+    } else {
+        args.jobs.jobs()
+    };
+    // Progress is of the decompressed bytes, which the sizes don't tell
+    let total = total_size(inputs).filter(|_| !args.decompress);
+    let progress = args.progress.then(|| Progress::new(total));
+    let add_progress = progress
+        .as_ref()
+        .map(|progress| move |bytes: usize| progress.add(bytes as u64));
+    let options = Options {
+        progress: add_progress
+            .as_ref()
+            .map(|add| add as &(dyn Fn(usize) + Sync)),
+        ..Options::from(args)
+    };
+    let mut printer = match Printer::new(args, &cnt, number_width(inputs, &cnt, args.decompress)) {
+        Ok(printer) => printer,
+        Err(e) => return status.report(UTIL, &output::write_error(e)),
+    };
+    let mut total = cnt;
+    // The first error writing to the stdout, reported once at the end
+    let mut output = Ok(());
+    jobs::for_each_ordered(
+        inputs,
+        jobs,
+        |input| {
+            let name = input.quoted_name().unwrap_or(Cow::Borrowed("-"));
+            trace::operand(name, || match input {
+                Input::Path(path) if args.code && args.comment_prefix.is_none() => {
+                    let options = Options {
+                        comment_prefix: crate::comment_prefix(path),
+                        ..options
+                    };
+                    count_input(input, &cnt, &options)
+                }
+                _ => count_input(input, &cnt, &options),
+            })
+        },
+        |input, result| {
+            if let Some(progress) = &progress {
+                progress.clear();
+            }
+            match result {
+                Ok(cnt) => {
+                    if args.total != Total::Only && output.is_ok() {
+                        output = printer.print(&cnt, input);
+                    }
+                    total = total + cnt;
+                }
+                Err(e) => {
+                    if output.is_ok() {
+                        output = printer.flush();
+                    }
+                    let err = match input.quoted_name() {
+                        Some(name) => UError::from(e).with_name(name),
+                        None => UError::from(e),
+                    };
+                    status.report(UTIL, &err);
+                }
+            }
+        },
+    );
+    let output = output
+        .and_then(|()| match args.total {
+            Total::Auto if inputs.len() > 1 => printer.print_total(&total, Some(i18n::tr("total"))),
+            Total::Always => printer.print_total(&total, Some(i18n::tr("total"))),
+            Total::Only => printer.print_total(&total, None),
+            _ => Ok(()),
+        })
+        .and_then(|()| printer.finish());
+    if let Err(e) = output {
+        status.report(UTIL, &output::write_error(e));
+    }
+}
+
+/// Collect the regular files under `dir` in name order, skipping
+/// symbolic links and names matching one of `excludes`
+fn walk(dir: &Path, excludes: &[String], files: &mut Vec<PathBuf>, status: &mut Status) {
+    let options = walk::Options {
+        follow: Follow::Operands,
+        is_sorted: true,
+        ..walk::Options::default()
+    };
+    let excludes: Vec<Pattern> = excludes
+        .iter()
+        .map(|pattern| Pattern::new(pattern.as_bytes(), glob::Options::default()))
+        .collect();
+    let is_excluded = |path: &Path| {
+        path.file_name().is_some_and(|name| {
+            excludes
+                .iter()
+                .any(|pattern| pattern.matches(name.as_encoded_bytes()))
+        })
+    };
+    walk::walk(
+        dir,
+        &options,
+        |entry, event| match event {
+            _ if entry.depth > 0 && is_excluded(&entry.path) => {
+                nyaa_core::debug!("skipping {}, excluded", quote(entry.path.as_os_str()));
+                Control::Skip
+            }
+            Event::Leaf if entry.metadata.is_file() => {
+                files.push(entry.path.clone());
+                Control::Continue
+            }
+            Event::Leaf => {
+                let name = quote(entry.path.as_os_str());
+                nyaa_core::debug!("skipping {name}, not a regular file");
+                Control::Continue
+            }
+            _ => Control::Continue,
+        },
+        |err| status.report(UTIL, &err),
+    );
+}
+
+/// File names in the list at `path`, `-` for the stdin, terminated by
+/// `delimiter`. Empty names, like blank lines, are skipped.
+fn read_file_list(path: &Path, delimiter: u8) -> Result<Vec<PathBuf>, io::Error> {
+    let data = if path.as_os_str() == "-" {
+        let mut data = Vec::new();
+        io::Read::read_to_end(&mut io::stdin().lock(), &mut data)?;
+        data
+    } else {
+        fs::read(path)?
+    };
+    Ok(data
+        .split(|&b| b == delimiter)
+        .map(|name| match delimiter {
+            // Lists written on Windows end their lines with CRLF
+            b'\n' => name.strip_suffix(b"\r").unwrap_or(name),
+            _ => name,
+        })
+        .filter(|name| !name.is_empty())
+        .map(path_from_bytes)
+        .collect())
+}
+
+fn path_from_bytes(bytes: &[u8]) -> PathBuf {
+    PathBuf::from(platform::os_string(bytes.to_vec()))
+}
+
+/// Description of the command line of `wc`
+pub fn command() -> clap::Command {
+    i18n::command(Args::command())
+}
+
+/// Run `wc` with the command line `args`, starting with the program name
+pub fn main(args: Vec<OsString>) -> ExitCode {
+    let mut status = Status::default();
+    let Some(args) = status.check(UTIL, response::expand(args)) else {
+        return status.into();
+    };
+    let args = posix::operands_last(&command(), args);
+    let matches = command().get_matches_from(args);
+    let mut args = Args::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+    // Counts referenced by the template are requested in addition to
+    // the ones selected by the flags
+    if let Some(Template(tokens)) = &args.format {
+        for token in tokens {
+            match token {
+                Token::Lines => args.is_line = true,
+                Token::Words => args.is_word = true,
+                Token::Bytes => args.is_byte = true,
+                Token::Chars => args.is_char = true,
+                Token::Graphemes => args.is_grapheme = true,
+                Token::MaxLineLength => args.is_max_line_length = true,
+                Token::Sentences => args.is_sentence = true,
+                Token::Paragraphs => args.is_paragraph = true,
+                Token::Occurrences if args.count_char.is_none() => {
+                    let err = UError::usage("%o requires --count-char");
+                    status.report(UTIL, &err);
+                    return status.into();
+                }
+                Token::Occurrences => (),
+                Token::Literal(_) | Token::File => (),
+            }
+        }
+    }
+    args.verbose.init(UTIL);
+    if status.check(UTIL, args.sandbox.apply()).is_none() {
+        return status.into();
+    }
+    let list = match (&args.files0_from, &args.files_from) {
+        (Some(list), _) => Some((list, b'\0')),
+        (None, Some(list)) => Some((list, b'\n')),
+        (None, None) => None,
+    };
+    let operands = if let Some((list, delimiter)) = list {
+        let files = read_file_list(list, delimiter)
+            .map_err(|e| UError::from(e).with_name(quote(list.as_os_str())));
+        match status.check(UTIL, files) {
+            Some(files) => Cow::Owned(files),
+            None => return status.into(),
+        }
+    } else if cfg!(windows) {
+        // The shells of Windows pass wildcards through to the program, an
+        // operand matching nothing is kept to be reported as missing
+        Cow::Owned(
+            args.files
+                .iter()
+                .flat_map(|path| match glob::expand(path) {
+                    paths if paths.is_empty() => vec![path.clone()],
+                    paths => paths,
+                })
+                .collect(),
+        )
+    } else {
+        Cow::Borrowed(&args.files)
+    };
+    let files = if args.recursive {
+        let mut files = Vec::new();
+        for path in operands.iter() {
+            if path.as_os_str() != "-" && path.is_dir() {
+                walk(path, &args.exclude, &mut files, &mut status);
+            } else {
+                files.push(path.clone());
+            }
+        }
+        Cow::Owned(files)
+    } else {
+        operands
+    };
+    let inputs: Vec<Input> = if args.files.is_empty() && list.is_none() {
+        vec![Input::Stdin(args.stdin_label.as_deref())]
+    } else {
+        files
+            .iter()
+            .map(|path| match Input::from_operand(path) {
+                Input::Stdin(Some("-")) => {
+                    Input::Stdin(Some(args.stdin_label.as_deref().unwrap_or("-")))
+                }
+                input => input,
+            })
+            .collect()
+    };
+    process_inputs(&inputs, &args, &mut status);
+    status.into()
+}
@@ -0,0 +1,486 @@
+//! Incremental counting state machine
+
+use crate::encoding::{invalid_input, Encoding};
+use crate::{Count, BUF_SIZE};
+use clap::ValueEnum;
+use nyaa_core::locale::{self, Locale};
+use nyaa_core::posix;
+use std::io;
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthChar;
+
+/// How words are delimited
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum WordRule {
+    /// Words are separated by Unicode white space
+    Unicode,
+    /// Words are separated by the POSIX space class of the C locale
+    Posix,
+    /// Words are found by Unicode word boundary segmentation, so
+    /// punctuation separates words and is not counted
+    Segment,
+}
+
+impl WordRule {
+    /// Rule matching the character type locale of the environment
+    pub fn from_env() -> Self {
+        match Locale::ctype() {
+            Locale::C => WordRule::Posix,
+            Locale::Unicode => WordRule::Unicode,
+        }
+    }
+
+    /// Whether `c` delimits words. Like GNU wc, no-break spaces do unless
+    /// POSIX behavior is asked for by `is_posix`.
+    fn is_space(self, c: char, is_posix: bool) -> bool {
+        match self {
+            WordRule::Posix => Locale::C.is_space(c),
+            WordRule::Unicode | WordRule::Segment => {
+                Locale::Unicode.is_space(c) || (!is_posix && locale::is_no_break_space(c))
+            }
+        }
+    }
+}
+
+/// Settings of the counting core that don't select counts
+#[derive(Clone, Copy)]
+pub struct Options<'a> {
+    /// Fail on input that is invalid in the encoding instead of counting
+    /// each invalid sequence as one character
+    pub is_strict: bool,
+    /// Byte terminating the lines
+    pub delimiter: u8,
+    /// Byte sequence whose occurrences are counted
+    pub needle: Option<&'a [u8]>,
+    pub word_rule: WordRule,
+    pub encoding: Encoding,
+    /// Prefix starting comment lines in `--code` mode
+    pub comment_prefix: Option<&'a str>,
+    /// Count the decompressed contents of input compressed with gzip, xz
+    /// or zstd, detected by their magic bytes
+    pub decompress: bool,
+    /// Follow POSIX strictly, so no-break spaces don't delimit words
+    pub is_posix: bool,
+    /// Called with the number of bytes counted after every chunk
+    pub progress: Option<&'a (dyn Fn(usize) + Sync)>,
+}
+
+impl Default for Options<'_> {
+    /// Newline-terminated UTF-8 lines, with words delimited like `wc` does
+    /// in the locale of the environment
+    fn default() -> Self {
+        Options {
+            is_strict: false,
+            delimiter: b'\n',
+            needle: None,
+            word_rule: WordRule::from_env(),
+            encoding: Encoding::Utf8,
+            comment_prefix: None,
+            decompress: false,
+            is_posix: posix::is_enabled(),
+            progress: None,
+        }
+    }
+}
+
+/// Counts non-overlapping occurrences of a byte sequence, including the
+/// ones spanning chunk boundaries
+struct Occurrences<'a> {
+    finder: memchr::memmem::Finder<'a>,
+    /// End of the previous chunks after the last occurrence, up to one
+    /// byte shorter than the needle
+    tail: Vec<u8>,
+}
+
+impl<'a> Occurrences<'a> {
+    fn new(needle: &'a [u8]) -> Self {
+        Occurrences {
+            finder: memchr::memmem::Finder::new(needle),
+            tail: Vec::with_capacity(needle.len()),
+        }
+    }
+
+    fn update(&mut self, chunk: &[u8]) -> usize {
+        let needle = self.finder.needle();
+        let mut count = 0;
+        let mut last_end = None;
+        // The leftmost occurrence spanning the boundary uses the most
+        // bytes from the tail
+        for k in (1..=self.tail.len()).rev() {
+            let suffix = &self.tail[self.tail.len() - k..];
+            if needle.starts_with(suffix) && chunk.starts_with(&needle[k..]) {
+                count += 1;
+                last_end = Some(needle.len() - k);
+                break;
+            }
+        }
+        let start = last_end.unwrap_or(0);
+        for i in self.finder.find_iter(&chunk[start..]) {
+            count += 1;
+            last_end = Some(start + i + needle.len());
+        }
+        let keep = needle.len() - 1;
+        match last_end {
+            Some(end) => {
+                self.tail.clear();
+                self.tail
+                    .extend_from_slice(&chunk[end.max(chunk.len().saturating_sub(keep))..]);
+            }
+            None => {
+                self.tail.extend_from_slice(chunk);
+                let excess = self.tail.len().saturating_sub(keep);
+                self.tail.drain(..excess);
+            }
+        }
+        count
+    }
+}
+
+/// What the current line is so far in `--code` mode
+#[derive(Clone, Copy)]
+enum LineKind {
+    Blank,
+    /// Only white space and this many bytes of the comment prefix
+    Prefix(usize),
+    Comment,
+    Code,
+}
+
+/// Counting state carried across chunk boundaries.
+///
+/// Input is passed in chunks to [`Counter::update`], which may leave the
+/// end of a chunk unconsumed, and the counts are taken with
+/// [`Counter::finish`] after the last chunk.
+pub struct Counter<'a> {
+    cnt: Count,
+    options: Options<'a>,
+    in_word: bool,
+    /// Display width of the current line
+    line_length: usize,
+    /// Whether the current line has only white space so far
+    is_line_blank: bool,
+    line_kind: LineKind,
+    in_paragraph: bool,
+    in_sentence: bool,
+    /// Whether a sentence terminator was seen, which ends the sentence
+    /// when white space follows
+    is_sentence_ending: bool,
+    occurrences: Option<Occurrences<'a>>,
+    /// Whether the last chunk ended in a word longer than a chunk, which
+    /// the next one continues, for words found by segmentation
+    is_word_split: bool,
+    /// Decoded text of the current chunk for encodings other than UTF-8
+    decoded: String,
+}
+
+impl<'a> Counter<'a> {
+    /// Start counting, adding to the fields of `init_cnt` that are `Some`
+    pub fn new(init_cnt: &Count, options: &Options<'a>) -> Self {
+        Counter {
+            cnt: *init_cnt,
+            options: *options,
+            in_word: false,
+            line_length: 0,
+            is_line_blank: true,
+            line_kind: LineKind::Blank,
+            in_paragraph: false,
+            in_sentence: false,
+            is_sentence_ending: false,
+            occurrences: options.needle.map(Occurrences::new),
+            is_word_split: false,
+            decoded: String::new(),
+        }
+    }
+
+    /// Whether the input has to be decoded to produce the counts
+    fn needs_decoding(&self) -> bool {
+        (self.cnt.lines.is_some() && !self.options.encoding.is_ascii_compatible())
+            || self.cnt.chars.is_some()
+            || self.cnt.words.is_some()
+            || self.cnt.graphemes.is_some()
+            || self.cnt.max_line_length.is_some()
+            || self.cnt.sentences.is_some()
+            || self.cnt.paragraphs.is_some()
+            || self.cnt.blank_lines.is_some()
+            || self.cnt.comment_lines.is_some()
+    }
+
+    /// Count a chunk of input and return the number of bytes consumed.
+    /// Unconsumed bytes at the end of the chunk, an incomplete encoded
+    /// sequence or a grapheme cluster that may continue, have to be passed
+    /// again with the next chunk. The last chunk is consumed completely.
+    pub fn update(&mut self, chunk: &[u8], is_last: bool) -> Result<usize, io::Error> {
+        let consumed = if self.needs_decoding() {
+            let mut decoded = std::mem::take(&mut self.decoded);
+            let consumed = self.update_decoded(chunk, is_last, &mut decoded);
+            self.decoded = decoded;
+            consumed?
+        } else {
+            self.update_lines(chunk);
+            chunk.len()
+        };
+        if let Some(bytes) = self.cnt.bytes.as_mut() {
+            *bytes += consumed;
+        }
+        if let Some(progress) = self.options.progress {
+            progress(consumed);
+        }
+        if let (Some(occurrences), Some(count)) =
+            (self.occurrences.as_mut(), self.cnt.occurrences.as_mut())
+        {
+            *count += occurrences.update(&chunk[..consumed]);
+        }
+        Ok(consumed)
+    }
+
+    fn update_decoded(
+        &mut self,
+        chunk: &[u8],
+        is_last: bool,
+        buf: &mut String,
+    ) -> Result<usize, io::Error> {
+        let encoding = self.options.encoding;
+        let mut rest = chunk;
+        loop {
+            let (valid, error) = encoding.decode(rest, buf);
+            let s = match encoding {
+                // SAFETY: `decode` has validated the bytes up to `valid`
+                Encoding::Utf8 => unsafe { std::str::from_utf8_unchecked(&rest[..valid]) },
+                _ => buf.as_str(),
+            };
+            // An incomplete sequence is only invalid at the end of the input
+            let invalid = match error {
+                Some(None) if is_last => Some(rest.len() - valid),
+                Some(len) => len,
+                None => None,
+            };
+            let Some(len) = invalid else {
+                let s = match self.hold_back(s) {
+                    _ if is_last => s,
+                    0 if rest.len() >= BUF_SIZE => {
+                        self.update_str(s);
+                        self.is_word_split = s
+                            .unicode_word_indices()
+                            .next_back()
+                            .is_some_and(|(i, word)| i + word.len() == s.len());
+                        return Ok(chunk.len() - rest.len() + encoding.encoded_len(s));
+                    }
+                    end => &s[..end],
+                };
+                self.update_str(s);
+                return Ok(chunk.len() - rest.len() + encoding.encoded_len(s));
+            };
+            if self.options.is_strict {
+                return Err(invalid_input(encoding));
+            }
+            self.update_str(s);
+            self.update_invalid();
+            rest = &rest[valid + len..];
+        }
+    }
+
+    /// Length of the start of `s` that can be counted without seeing the
+    /// next chunk, the last grapheme cluster or word may continue in it
+    fn hold_back(&self, s: &str) -> usize {
+        let mut end = s.len();
+        if self.cnt.graphemes.is_some() {
+            end = end.min(s.grapheme_indices(true).next_back().map_or(0, |(i, _)| i));
+        }
+        // A word may continue after punctuation, like `e.g` or `1,000`, so
+        // the segment before the last one is held back too
+        if self.cnt.words.is_some() && self.options.word_rule == WordRule::Segment {
+            end = end.min(
+                s.split_word_bound_indices()
+                    .nth_back(1)
+                    .map_or(0, |(i, _)| i),
+            );
+        }
+        end
+    }
+
+    /// Count an invalid encoded sequence as one character, which has no
+    /// display width
+    fn update_invalid(&mut self) {
+        let cnt = &mut self.cnt;
+        if let Some(chars) = cnt.chars.as_mut() {
+            *chars += 1;
+        }
+        if let Some(graphemes) = cnt.graphemes.as_mut() {
+            *graphemes += 1;
+        }
+        self.is_word_split = false;
+        if !self.in_word && self.options.word_rule != WordRule::Segment {
+            self.in_word = true;
+            if let Some(words) = cnt.words.as_mut() {
+                *words += 1;
+            }
+        }
+    }
+
+    fn update_lines(&mut self, chunk: &[u8]) {
+        let cnt = &mut self.cnt;
+        // memchr counts with SIMD where the target supports it
+        cnt.lines = cnt
+            .lines
+            .map(|lines| lines + memchr::memchr_iter(self.options.delimiter, chunk).count());
+    }
+
+    fn update_str(&mut self, s: &str) {
+        let cnt = &mut self.cnt;
+        if let Some(graphemes) = cnt.graphemes.as_mut() {
+            *graphemes += s.graphemes(true).count();
+        }
+        let word_rule = self.options.word_rule;
+        let is_posix = self.options.is_posix;
+        if let Some(words) = cnt
+            .words
+            .as_mut()
+            .filter(|_| word_rule == WordRule::Segment)
+        {
+            *words += s.unicode_words().count();
+            // The first word continues the one counted with the last chunk
+            if self.is_word_split && !s.is_empty() {
+                self.is_word_split = false;
+                if s.unicode_word_indices().next().is_some_and(|(i, _)| i == 0) {
+                    *words -= 1;
+                }
+            }
+        }
+        let delimiter = char::from(self.options.delimiter);
+        for c in s.chars() {
+            let cnt = &mut self.cnt;
+            if let Some(chars) = cnt.chars.as_mut() {
+                *chars += 1;
+            }
+            if c == delimiter {
+                if let Some(lines) = cnt.lines.as_mut() {
+                    *lines += 1;
+                }
+            }
+            // Like in GNU wc, a carriage return or form feed also starts
+            // measuring a new line
+            if c == delimiter || c == '\r' || c == '\x0c' {
+                if let Some(max) = cnt.max_line_length.as_mut() {
+                    *max = (*max).max(self.line_length);
+                }
+                self.line_length = 0;
+            } else {
+                self.line_length = advance_column(self.line_length, c);
+            }
+            let is_space = word_rule.is_space(c, is_posix) || c == delimiter;
+            if is_space {
+                self.in_word = false;
+            } else if !self.in_word {
+                self.in_word = true;
+                if let Some(words) = cnt
+                    .words
+                    .as_mut()
+                    .filter(|_| word_rule != WordRule::Segment)
+                {
+                    *words += 1;
+                }
+            }
+            if cnt.sentences.is_some() || cnt.paragraphs.is_some() {
+                self.update_prose(c, c == '\n' || c == delimiter, is_space);
+            }
+            if self.cnt.blank_lines.is_some() || self.cnt.comment_lines.is_some() {
+                self.update_code(c, c == delimiter, is_space);
+            }
+        }
+    }
+
+    /// Classify lines as blank, comment or code lines. Only terminated
+    /// lines are counted, like for the line count.
+    fn update_code(&mut self, c: char, is_newline: bool, is_space: bool) {
+        if is_newline {
+            let count = match self.line_kind {
+                LineKind::Blank => self.cnt.blank_lines.as_mut(),
+                LineKind::Comment => self.cnt.comment_lines.as_mut(),
+                LineKind::Prefix(_) | LineKind::Code => None,
+            };
+            if let Some(count) = count {
+                *count += 1;
+            }
+            self.line_kind = LineKind::Blank;
+            return;
+        }
+        let prefix = self.options.comment_prefix.unwrap_or("");
+        let matched = match self.line_kind {
+            LineKind::Blank if is_space => return,
+            LineKind::Blank => 0,
+            LineKind::Prefix(matched) => matched,
+            LineKind::Comment | LineKind::Code => return,
+        };
+        let rest = &prefix[matched..];
+        self.line_kind = match matched + c.len_utf8() {
+            _ if rest.is_empty() || !rest.starts_with(c) => LineKind::Code,
+            matched if matched == prefix.len() => LineKind::Comment,
+            matched => LineKind::Prefix(matched),
+        };
+    }
+
+    /// Track paragraphs, blocks of lines separated by blank lines, and
+    /// sentences, ended by a terminator followed by white space or by the
+    /// end of the paragraph
+    fn update_prose(&mut self, c: char, is_newline: bool, is_space: bool) {
+        let cnt = &mut self.cnt;
+        if is_newline {
+            if self.is_line_blank {
+                self.in_paragraph = false;
+                self.in_sentence = false;
+            }
+            self.is_line_blank = true;
+        }
+        if is_space {
+            if self.is_sentence_ending {
+                self.in_sentence = false;
+                self.is_sentence_ending = false;
+            }
+            return;
+        }
+        self.is_line_blank = false;
+        if !self.in_paragraph {
+            self.in_paragraph = true;
+            if let Some(paragraphs) = cnt.paragraphs.as_mut() {
+                *paragraphs += 1;
+            }
+        }
+        match c {
+            '.' | '!' | '?' | '…' | '。' | '！' | '？' => {
+                self.is_sentence_ending = self.in_sentence
+            }
+            // Closing quotes and brackets may follow the terminator
+            '"' | '\'' | ')' | ']' | '»' | '”' | '’' => (),
+            _ => {
+                self.is_sentence_ending = false;
+                if !self.in_sentence {
+                    self.in_sentence = true;
+                    if let Some(sentences) = cnt.sentences.as_mut() {
+                        *sentences += 1;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Counts of all input passed so far
+    pub fn finish(self) -> Count {
+        let mut cnt = self.cnt;
+        if let Some(max) = cnt.max_line_length.as_mut() {
+            *max = (*max).max(self.line_length);
+        }
+        cnt
+    }
+}
+
+/// Column after displaying `c` at `column`: a tab advances to the next tab
+/// stop, East Asian wide characters take two columns and control
+/// characters none
+fn advance_column(column: usize, c: char) -> usize {
+    const TAB_WIDTH: usize = 8;
+    match c {
+        '\t' => column + TAB_WIDTH - column % TAB_WIDTH,
+        c if c.is_control() => column,
+        c => column + c.width().unwrap_or(0),
+    }
+}
@@ -0,0 +1,105 @@
+//! Character encodings of the counted input
+
+use clap::ValueEnum;
+use std::io;
+
+/// Character encoding of the input, used for counting characters, words
+/// and line lengths
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum Encoding {
+    #[default]
+    #[value(name = "utf-8", alias = "utf8")]
+    Utf8,
+    #[value(name = "utf-16le", alias = "utf16le")]
+    Utf16le,
+    #[value(name = "utf-16be", alias = "utf16be")]
+    Utf16be,
+    #[value(name = "latin1", alias = "iso-8859-1")]
+    Latin1,
+}
+
+impl Encoding {
+    /// Conventional name of the encoding, as used in error messages
+    pub fn name(self) -> &'static str {
+        match self {
+            Encoding::Utf8 => "UTF-8",
+            Encoding::Utf16le => "UTF-16LE",
+            Encoding::Utf16be => "UTF-16BE",
+            Encoding::Latin1 => "Latin-1",
+        }
+    }
+
+    /// Whether a newline is the single byte `\n`, so lines can be counted
+    /// without decoding
+    pub(crate) fn is_ascii_compatible(self) -> bool {
+        matches!(self, Encoding::Utf8 | Encoding::Latin1)
+    }
+
+    /// Number of encoded bytes for the decoded text `s`
+    pub(crate) fn encoded_len(self, s: &str) -> usize {
+        match self {
+            Encoding::Utf8 => s.len(),
+            Encoding::Utf16le | Encoding::Utf16be => s.encode_utf16().count() * 2,
+            Encoding::Latin1 => s.chars().count(),
+        }
+    }
+
+    /// Decode the longest valid start of `bytes`, into `buf` unless the
+    /// encoding is UTF-8. Returns its encoded length and, when not all of
+    /// `bytes` was decoded, the length of the invalid sequence that
+    /// follows, `None` for an incomplete sequence at the end.
+    pub(crate) fn decode(self, bytes: &[u8], buf: &mut String) -> (usize, Option<Option<usize>>) {
+        buf.clear();
+        match self {
+            Encoding::Utf8 => match std::str::from_utf8(bytes) {
+                Ok(_) => (bytes.len(), None),
+                Err(e) => (e.valid_up_to(), Some(e.error_len())),
+            },
+            Encoding::Latin1 => {
+                buf.extend(bytes.iter().map(|&b| char::from(b)));
+                (bytes.len(), None)
+            }
+            Encoding::Utf16le | Encoding::Utf16be => {
+                let unit = |i: usize| {
+                    let pair = [bytes[i], bytes[i + 1]];
+                    if self == Encoding::Utf16le {
+                        u16::from_le_bytes(pair)
+                    } else {
+                        u16::from_be_bytes(pair)
+                    }
+                };
+                let mut i = 0;
+                while i + 1 < bytes.len() {
+                    let high = unit(i);
+                    let len = match high {
+                        0xd800..=0xdbff if i + 3 >= bytes.len() => return (i, Some(None)),
+                        0xd800..=0xdbff => match unit(i + 2) {
+                            low @ 0xdc00..=0xdfff => {
+                                let c = 0x10000
+                                    + ((u32::from(high) - 0xd800) << 10)
+                                    + (u32::from(low) - 0xdc00);
+                                buf.extend(char::from_u32(c));
+                                4
+                            }
+                            _ => return (i, Some(Some(2))),
+                        },
+                        0xdc00..=0xdfff => return (i, Some(Some(2))),
+                        _ => {
+                            buf.extend(char::from_u32(u32::from(high)));
+                            2
+                        }
+                    };
+                    i += len;
+                }
+                (i, (i < bytes.len()).then_some(None))
+            }
+        }
+    }
+}
+
+pub(crate) fn invalid_input(encoding: Encoding) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("stream did not contain valid {}", encoding.name()),
+    )
+}
@@ -0,0 +1,346 @@
+//! Line, word, character and byte counting with the semantics of `wc`.
+//!
+//! The counts to produce are chosen with a [`Selection`], which maps the
+//! flags of `wc` to the fields of a [`Count`]. Whole inputs are counted
+//! with [`count_reader`] and [`count_path`], and input arriving in pieces
+//! with the incremental [`Counter`]. The `wc` utility itself is run with
+//! [`cli::main`].
+//!
+//! ```
+//! let selection = nyaa_wc::Selection {
+//!     lines: true,
+//!     words: true,
+//!     ..Default::default()
+//! };
+//! let options = nyaa_wc::Options::default();
+//! let cnt = nyaa_wc::count_reader(&b"one two\nthree\n"[..], &selection.into(), &options)?;
+//! assert_eq!((cnt.lines, cnt.words, cnt.bytes), (Some(2), Some(3), None));
+//! # Ok::<(), std::io::Error>(())
+//! ```
+
+pub mod cli;
+mod counter;
+mod encoding;
+
+pub use counter::{Counter, Options, WordRule};
+pub use encoding::Encoding;
+
+use nyaa_core::decompress;
+use nyaa_core::mmap::FileData;
+use nyaa_core::uring;
+use std::fs;
+use std::io::{self, Seek};
+use std::ops::Add;
+use std::path::Path;
+
+/// Counts to produce, one for each counting flag of `wc`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Selection {
+    /// `-m`, `--chars`
+    pub chars: bool,
+    /// `-c`, `--bytes`
+    pub bytes: bool,
+    /// `-l`, `--lines`
+    pub lines: bool,
+    /// `-w`, `--words`
+    pub words: bool,
+    /// `-g`, `--graphemes`
+    pub graphemes: bool,
+    /// `-L`, `--max-line-length`
+    pub max_line_length: bool,
+    /// `--sentences`
+    pub sentences: bool,
+    /// `--paragraphs`
+    pub paragraphs: bool,
+    /// `--count-char`, counted in addition to the default counts
+    pub occurrences: bool,
+    /// `--code`, lines with the blank and comment lines among them
+    pub code: bool,
+}
+
+/// Counts of an input, `None` for the ones that are not selected
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Count {
+    pub chars: Option<usize>,
+    pub bytes: Option<usize>,
+    pub lines: Option<usize>,
+    /// Lines with only white space
+    pub blank_lines: Option<usize>,
+    /// Lines starting with the comment prefix after white space
+    pub comment_lines: Option<usize>,
+    pub words: Option<usize>,
+    pub graphemes: Option<usize>,
+    pub max_line_length: Option<usize>,
+    pub sentences: Option<usize>,
+    pub paragraphs: Option<usize>,
+    pub occurrences: Option<usize>,
+}
+
+impl Count {
+    /// Zero counts for the selected fields, lines, words and bytes when
+    /// nothing but occurrences is selected
+    pub fn new(selection: &Selection) -> Self {
+        let zero = |is_selected: bool| is_selected.then_some(0);
+        let Selection {
+            chars,
+            bytes,
+            lines,
+            words,
+            graphemes,
+            max_line_length,
+            sentences,
+            paragraphs,
+            occurrences,
+            code,
+        } = *selection;
+        if !(chars
+            || code
+            || bytes
+            || lines
+            || words
+            || graphemes
+            || max_line_length
+            || sentences
+            || paragraphs)
+        {
+            Count {
+                lines: Some(0),
+                bytes: Some(0),
+                words: Some(0),
+                occurrences: zero(occurrences),
+                ..Count::default()
+            }
+        } else {
+            Count {
+                lines: zero(lines || code),
+                blank_lines: zero(code),
+                comment_lines: zero(code),
+                chars: zero(chars),
+                bytes: zero(bytes),
+                words: zero(words),
+                graphemes: zero(graphemes),
+                max_line_length: zero(max_line_length),
+                sentences: zero(sentences),
+                paragraphs: zero(paragraphs),
+                occurrences: zero(occurrences),
+            }
+        }
+    }
+
+    /// Requested counts with their names, in output order
+    pub fn fields(&self) -> impl Iterator<Item = (&'static str, usize)> {
+        [
+            ("lines", self.lines),
+            ("blank_lines", self.blank_lines),
+            ("comment_lines", self.comment_lines),
+            ("words", self.words),
+            ("chars", self.chars),
+            ("bytes", self.bytes),
+            ("graphemes", self.graphemes),
+            ("max_line_length", self.max_line_length),
+            ("sentences", self.sentences),
+            ("paragraphs", self.paragraphs),
+            ("occurrences", self.occurrences),
+        ]
+        .into_iter()
+        .filter_map(|(name, value)| value.map(|value| (name, value)))
+    }
+
+    /// Whether the byte count is the only one requested, so it can be
+    /// taken from the file metadata instead of reading the file
+    pub fn is_byte_only(&self) -> bool {
+        self.bytes.is_some() && self.fields().count() == 1
+    }
+}
+
+impl From<Selection> for Count {
+    fn from(selection: Selection) -> Self {
+        Count::new(&selection)
+    }
+}
+
+impl Add for Count {
+    type Output = Self;
+
+    /// Combined counts of two inputs, the longer of the longest lines
+    fn add(self, other: Self) -> Self {
+        Self {
+            chars: self.chars.zip(other.chars).map(|(a, b)| a + b),
+            bytes: self.bytes.zip(other.bytes).map(|(a, b)| a + b),
+            words: self.words.zip(other.words).map(|(a, b)| a + b),
+            lines: self.lines.zip(other.lines).map(|(a, b)| a + b),
+            blank_lines: self.blank_lines.zip(other.blank_lines).map(|(a, b)| a + b),
+            comment_lines: self
+                .comment_lines
+                .zip(other.comment_lines)
+                .map(|(a, b)| a + b),
+            graphemes: self.graphemes.zip(other.graphemes).map(|(a, b)| a + b),
+            max_line_length: self
+                .max_line_length
+                .zip(other.max_line_length)
+                .map(|(a, b)| a.max(b)),
+            sentences: self.sentences.zip(other.sentences).map(|(a, b)| a + b),
+            paragraphs: self.paragraphs.zip(other.paragraphs).map(|(a, b)| a + b),
+            occurrences: self.occurrences.zip(other.occurrences).map(|(a, b)| a + b),
+        }
+    }
+}
+
+/// Line comment prefix of the language of a source file, guessed from its
+/// extension or name
+pub fn comment_prefix(path: &Path) -> Option<&'static str> {
+    let name = path.file_name()?.to_str()?;
+    if matches!(name, "Makefile" | "makefile" | "GNUmakefile" | "Dockerfile") {
+        return Some("#");
+    }
+    let prefix = match name.rsplit_once('.')?.1 {
+        "c" | "h" | "cc" | "cpp" | "cxx" | "hpp" | "rs" | "go" | "java" | "js" | "jsx" | "ts"
+        | "tsx" | "kt" | "swift" | "cs" | "scala" | "dart" | "zig" | "proto" => "//",
+        "py" | "sh" | "bash" | "zsh" | "fish" | "rb" | "pl" | "pm" | "r" | "toml" | "yaml"
+        | "yml" | "mk" | "cmake" | "conf" | "nix" | "ex" | "exs" | "jl" | "tcl" => "#",
+        "sql" | "lua" | "hs" | "elm" | "ada" | "adb" | "ads" => "--",
+        "lisp" | "el" | "clj" | "cljs" | "scm" | "rkt" | "asm" | "s" | "ini" => ";",
+        "tex" | "erl" | "hrl" | "m" => "%",
+        "vim" => "\"",
+        _ => return None,
+    };
+    Some(prefix)
+}
+
+const BUF_SIZE: usize = 64 * 1024;
+
+/// Count everything read from `reader`, adding to the fields of
+/// `init_cnt` that are `Some`
+pub fn count_reader<R: io::Read>(
+    reader: R,
+    init_cnt: &Count,
+    options: &Options,
+) -> Result<Count, io::Error> {
+    if options.decompress {
+        count_stream(decompress::decoder(reader)?, init_cnt, options)
+    } else {
+        count_stream(reader, init_cnt, options)
+    }
+}
+
+fn count_stream<R: io::Read>(
+    mut reader: R,
+    init_cnt: &Count,
+    options: &Options,
+) -> Result<Count, io::Error> {
+    let mut counter = Counter::new(init_cnt, options);
+    let mut buf = [0; BUF_SIZE];
+    let mut pending = 0;
+    loop {
+        let n = match reader.read(&mut buf[pending..]) {
+            Ok(n) => n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        let len = pending + n;
+        let consumed = counter.update(&buf[..len], n == 0)?;
+        if n == 0 {
+            break;
+        }
+        buf.copy_within(consumed..len, 0);
+        pending = len - consumed;
+    }
+    Ok(counter.finish())
+}
+
+/// Memory-mapped files are counted in slices of this size, so progress
+/// can be reported
+const MMAP_CHUNK_SIZE: usize = 16 * BUF_SIZE;
+
+/// Count the bytes of `data`, adding to the fields of `init_cnt` that are
+/// `Some`
+pub fn count_slice(data: &[u8], init_cnt: &Count, options: &Options) -> Result<Count, io::Error> {
+    let mut counter = Counter::new(init_cnt, options);
+    let mut start = 0;
+    loop {
+        let end = data.len().min(start + MMAP_CHUNK_SIZE);
+        let is_last = end == data.len();
+        start += counter.update(&data[start..end], is_last)?;
+        if is_last {
+            break;
+        }
+    }
+    Ok(counter.finish())
+}
+
+/// Count the file at `path`, adding to the fields of `init_cnt` that are
+/// `Some`. Unless the input is decompressed, the size of a regular file is
+/// taken from its metadata when only bytes are counted, and large files
+/// are memory-mapped.
+pub fn count_path<P: AsRef<Path>>(
+    path: P,
+    init_cnt: &Count,
+    options: &Options,
+) -> Result<Count, io::Error> {
+    nyaa_core::debug!("opening");
+    fs::File::open(path).and_then(|file| count_file(file, init_cnt, options))
+}
+
+/// Count `file` from its current offset, which is not the start for a
+/// descriptor the file was duplicated from
+fn count_file(mut file: fs::File, init_cnt: &Count, options: &Options) -> Result<Count, io::Error> {
+    let metadata = file.metadata()?;
+    if metadata.is_dir() {
+        return Err(io::Error::new(
+            io::ErrorKind::IsADirectory,
+            "Is a directory",
+        ));
+    }
+    let offset = if metadata.is_file() {
+        file.stream_position()?
+    } else {
+        0
+    };
+    if metadata.is_file() && !options.decompress && init_cnt.is_byte_only() {
+        let size = metadata.len().saturating_sub(offset);
+        nyaa_core::debug!("size of {size} bytes from the metadata");
+        if let Some(progress) = options.progress {
+            progress(size as usize);
+        }
+        return Ok(Count {
+            bytes: init_cnt.bytes.map(|bytes| bytes + size as usize),
+            ..*init_cnt
+        });
+    }
+    let file = if options.decompress {
+        file
+    } else {
+        match FileData::new(file, &metadata) {
+            FileData::Mapped(mapping) => {
+                let start = mapping.len().min(offset as usize);
+                let cnt = count_slice(&mapping[start..], init_cnt, options)?;
+                mapping.check()?;
+                return Ok(cnt);
+            }
+            FileData::Stream(file) => file,
+        }
+    };
+    if metadata.is_file() {
+        advise_sequential(&file);
+        return count_reader(uring::reader(&file), init_cnt, options);
+    }
+    count_reader(file, init_cnt, options)
+}
+
+/// Tell the kernel that `file` is read from start to end, so it reads
+/// further ahead
+#[cfg(any(target_os = "linux", target_os = "android", target_os = "freebsd"))]
+fn advise_sequential(file: &fs::File) {
+    use std::os::fd::AsRawFd;
+    // SAFETY: the descriptor stays open for the lifetime of `file`, and the
+    // advice is only a hint whose failure doesn't matter
+    let result =
+        unsafe { libc::posix_fadvise(file.as_raw_fd(), 0, 0, libc::POSIX_FADV_SEQUENTIAL) };
+    match result {
+        0 => nyaa_core::debug!("advised sequential reading"),
+        errno => nyaa_core::debug!("fadvise failed: {}", io::Error::from_raw_os_error(errno)),
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android", target_os = "freebsd")))]
+fn advise_sequential(_file: &fs::File) {}
@@ -0,0 +1,213 @@
+//! Differential tests of the counting state machine on generated input,
+//! invalid UTF-8, NUL bytes, long lines and input without a final newline
+//! included, against simple reference implementations, and of its results
+//! not depending on how the input is split into chunks
+
+use nyaa_wc::{count_reader, count_slice, Count, Counter, Options, Selection, WordRule};
+use proptest::prelude::*;
+
+/// Pieces of text that the counting treats differently
+const PIECES: &[&[u8]] = &[
+    b"a",
+    b"word",
+    b" ",
+    b"\t",
+    b"\n",
+    b"\r",
+    b"\x0b",
+    b"\x0c",
+    b"\0",
+    b".",
+    b"! ",
+    "é".as_bytes(),
+    "日本".as_bytes(),
+    "\u{a0}".as_bytes(),
+    "e\u{301}".as_bytes(),
+    "👍🏽".as_bytes(),
+    b"\xff",
+    b"\xe6\x97",
+    b"\xc3",
+    b"//",
+    b"#",
+];
+
+fn input() -> impl Strategy<Value = Vec<u8>> {
+    prop_oneof![
+        prop::collection::vec(any::<u8>(), 0..512),
+        prop::collection::vec(prop::sample::select(PIECES), 0..256)
+            .prop_map(|pieces| pieces.concat()),
+        // Lines longer than the chunks of the counting
+        (1..150_000usize, any::<bool>()).prop_map(|(len, is_terminated)| {
+            let mut data = vec![b'x'; len];
+            data.extend(is_terminated.then_some(b'\n'));
+            data
+        }),
+    ]
+}
+
+/// Printable ASCII text, whose display width is its length
+fn ascii_text() -> impl Strategy<Value = Vec<u8>> {
+    prop::collection::vec(prop::sample::select(&b"ab \t\t\n\r\x0c"[..]), 0..512)
+}
+
+fn options(word_rule: WordRule) -> Options<'static> {
+    Options {
+        word_rule,
+        is_posix: false,
+        ..Options::default()
+    }
+}
+
+fn all() -> Selection {
+    Selection {
+        chars: true,
+        bytes: true,
+        lines: true,
+        words: true,
+        graphemes: true,
+        max_line_length: true,
+        sentences: true,
+        paragraphs: true,
+        occurrences: true,
+        code: true,
+    }
+}
+
+/// Count `data` passed to the counter in chunks of the `sizes`, repeated,
+/// with the unconsumed end of a chunk passed again with the next one.
+/// Long input is passed in proportionally larger chunks, a word held back
+/// across thousands of tiny ones makes the test crawl.
+fn count_chunked(data: &[u8], sizes: &[usize], init_cnt: &Count, options: &Options) -> Count {
+    let mut counter = Counter::new(init_cnt, options);
+    let mut start = 0;
+    let mut end = 0;
+    let step = data.len() / 64;
+    for &size in sizes.iter().cycle() {
+        end = data.len().min(end + step + size);
+        let is_last = end == data.len();
+        start += counter.update(&data[start..end], is_last).unwrap();
+        if is_last {
+            break;
+        }
+    }
+    assert_eq!(start, data.len());
+    counter.finish()
+}
+
+/// Words of the C locale: runs of characters other than its spaces, an
+/// invalid sequence being a character
+fn posix_words(data: &[u8]) -> usize {
+    String::from_utf8_lossy(data)
+        .split(|c: char| c.is_ascii_whitespace() || c == '\x0b')
+        .filter(|word| !word.is_empty())
+        .count()
+}
+
+/// Width of the longest line, with carriage returns and form feeds
+/// starting a new one and tabs advancing to the next multiple of 8
+fn max_line_length(data: &[u8]) -> usize {
+    data.split(|&b| matches!(b, b'\n' | b'\r' | b'\x0c'))
+        .map(|line| {
+            line.iter().fold(0, |column, &b| match b {
+                b'\t' => column + 8 - column % 8,
+                _ => column + 1,
+            })
+        })
+        .max()
+        .unwrap_or(0)
+}
+
+proptest! {
+    #[test]
+    fn lines_and_bytes(data in input(), is_zero_terminated in any::<bool>()) {
+        let delimiter = if is_zero_terminated { b'\0' } else { b'\n' };
+        let options = Options { delimiter, ..options(WordRule::Posix) };
+        let selection = Selection { lines: true, bytes: true, ..Selection::default() };
+        let cnt = count_slice(&data, &selection.into(), &options).unwrap();
+        prop_assert_eq!(cnt.lines, Some(data.iter().filter(|&&b| b == delimiter).count()));
+        prop_assert_eq!(cnt.bytes, Some(data.len()));
+    }
+
+    #[test]
+    fn chars_and_words(data in input()) {
+        let selection = Selection { chars: true, words: true, ..Selection::default() };
+        let cnt = count_slice(&data, &selection.into(), &options(WordRule::Posix)).unwrap();
+        prop_assert_eq!(cnt.chars, Some(String::from_utf8_lossy(&data).chars().count()));
+        prop_assert_eq!(cnt.words, Some(posix_words(&data)));
+    }
+
+    #[test]
+    fn max_line_length_of_ascii(data in ascii_text()) {
+        let selection = Selection { max_line_length: true, ..Selection::default() };
+        let cnt = count_slice(&data, &selection.into(), &options(WordRule::Posix)).unwrap();
+        prop_assert_eq!(cnt.max_line_length, Some(max_line_length(&data)));
+    }
+}
+
+/// Invalid sequences are characters that take no columns
+#[test]
+fn max_line_length_of_invalid_sequences() {
+    let selection = Selection {
+        max_line_length: true,
+        ..Selection::default()
+    };
+    let cnt = Count::from(selection);
+    let options = options(WordRule::Unicode);
+    let count = |data: &[u8]| count_slice(data, &cnt, &options).unwrap().max_line_length;
+    assert_eq!(count(b"a\xffb\n"), Some(2));
+    assert_eq!(count(b"\xe6\x97x\xc3\n\xff"), Some(1));
+}
+
+/// Options counting everything the chunks may split
+fn chunk_options(word_rule: WordRule) -> Options<'static> {
+    Options {
+        needle: Some(b"a."),
+        comment_prefix: Some("//"),
+        ..options(word_rule)
+    }
+}
+
+/// A word longer than a chunk, which the next chunk continues
+#[test]
+fn long_word_across_chunks() {
+    let data = vec![b'x'; 65_537];
+    let options = chunk_options(WordRule::Segment);
+    let cnt = Count::from(all());
+    let whole = count_slice(&data, &cnt, &options).unwrap();
+    assert_eq!(whole.words, Some(1));
+    assert_eq!(count_chunked(&data, &[1], &cnt, &options), whole);
+    assert_eq!(count_reader(&data[..], &cnt, &options).unwrap(), whole);
+}
+
+/// A word continuing after punctuation at the end of a chunk
+#[test]
+fn word_after_punctuation_across_chunks() {
+    let data = [
+        "日本👍🏽   日本         ! ! aaaword! a word\u{a0}!     é.é",
+        &"a".repeat(50),
+        "! e\u{301}aa! aaaaa",
+    ]
+    .concat();
+    let options = chunk_options(WordRule::Segment);
+    let cnt = Count::from(all());
+    let whole = count_slice(data.as_bytes(), &cnt, &options).unwrap();
+    assert_eq!(count_chunked(data.as_bytes(), &[5], &cnt, &options), whole);
+}
+
+proptest! {
+    // Every count of long input is slow without optimizations
+    #![proptest_config(ProptestConfig::with_cases(64))]
+
+    #[test]
+    fn chunks_dont_matter(
+        data in input(),
+        sizes in prop::collection::vec(1..64usize, 1..8),
+        word_rule in prop::sample::select(&[WordRule::Posix, WordRule::Unicode, WordRule::Segment][..]),
+    ) {
+        let options = chunk_options(word_rule);
+        let cnt = Count::from(all());
+        let whole = count_slice(&data, &cnt, &options).unwrap();
+        prop_assert_eq!(count_chunked(&data, &sizes, &cnt, &options), whole);
+        prop_assert_eq!(count_reader(&data[..], &cnt, &options).unwrap(), whole);
+    }
+}
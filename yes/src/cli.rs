@@ -0,0 +1,52 @@
+//! Command line interface of `yes`
+
+use clap::{ArgAction, CommandFactory, FromArgMatches, Parser};
+use nyaa_core::error::Status;
+use nyaa_core::i18n;
+use nyaa_core::output;
+use nyaa_core::posix;
+use nyaa_core::trace;
+use std::ffi::OsString;
+use std::io;
+use std::process::ExitCode;
+
+const UTIL: &str = "yes";
+
+#[derive(Parser)]
+#[command(name = "yes", version)]
+#[command(about = "output a string repeatedly until killed", long_about = None)]
+#[command(disable_help_flag = true, disable_version_flag = true)]
+struct Args {
+    /// Strings to repeat on each line, separated by spaces, y if there
+    /// are none
+    #[arg(value_name = "STRING")]
+    strings: Vec<OsString>,
+
+    /// Print help
+    #[arg(long, action=ArgAction::Help)]
+    help: (),
+
+    /// Print version
+    #[arg(long, action=ArgAction::Version)]
+    version: (),
+}
+
+pub fn command() -> clap::Command {
+    i18n::command(Args::command())
+}
+
+/// Run `yes` with the command line `args`, starting with the program name
+pub fn main(args: Vec<OsString>) -> ExitCode {
+    let mut status = Status::default();
+    let args = posix::operands_last(&command(), args);
+    let matches = command().get_matches_from(args);
+    let args = Args::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+    trace::init(UTIL, false);
+    let strings: Vec<&[u8]> = args.strings.iter().map(|s| s.as_encoded_bytes()).collect();
+    let buffer = crate::buffer(&crate::line(&strings));
+    // The buffer ends with a newline, so the line buffering of the stdout
+    // passes it straight through
+    let err = crate::yes(&buffer, &mut io::stdout().lock());
+    status.report(UTIL, &output::write_error(err));
+    status.into()
+}
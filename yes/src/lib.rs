@@ -0,0 +1,109 @@
+//! Repeating of a line until the output fails, like `yes` of coreutils,
+//! as fast as the output takes it. The line is copied into a buffer of
+//! whole lines once, and the buffer written again and again, several
+//! times in each vectored write, so a pipe gets gigabytes a second.
+//!
+//! ```
+//! use std::io::{Cursor, ErrorKind};
+//!
+//! let buffer = nyaa_yes::buffer(&nyaa_yes::line(&["a", "b"]));
+//! assert!(buffer.len() >= nyaa_yes::BUFFER_SIZE && buffer.starts_with(b"a b\na b\n"));
+//! let mut out = [0; 10];
+//! let err = nyaa_yes::yes(&buffer, &mut Cursor::new(&mut out[..]));
+//! assert_eq!(err.kind(), ErrorKind::WriteZero);
+//! assert_eq!(&out, b"a b\na b\na ");
+//! ```
+
+pub mod cli;
+
+use std::io::{self, ErrorKind, IoSlice, Write};
+
+/// Least size of the buffer of lines
+pub const BUFFER_SIZE: usize = 32 * 1024;
+
+/// Times the buffer is written by each vectored write
+const SLICES: usize = 8;
+
+/// The line of `args` separated by spaces, `y` without any
+pub fn line<A: AsRef<[u8]>>(args: &[A]) -> Vec<u8> {
+    let mut line = match args {
+        [] => b"y".to_vec(),
+        _ => args
+            .iter()
+            .map(AsRef::as_ref)
+            .collect::<Vec<_>>()
+            .join(&b' '),
+    };
+    line.push(b'\n');
+    line
+}
+
+/// `line` repeated to fill [`BUFFER_SIZE`] at least, in whole lines
+pub fn buffer(line: &[u8]) -> Vec<u8> {
+    line.repeat(BUFFER_SIZE.div_ceil(line.len()))
+}
+
+/// Write `buffer` to `out` again and again until that fails, and return
+/// the error
+pub fn yes(buffer: &[u8], out: &mut impl Write) -> io::Error {
+    let slices = [IoSlice::new(buffer); SLICES];
+    loop {
+        let mut slices = slices;
+        let mut bufs = &mut slices[..];
+        while !bufs.is_empty() {
+            match out.write_vectored(bufs) {
+                Ok(0) => return ErrorKind::WriteZero.into(),
+                Ok(n) => IoSlice::advance_slices(&mut bufs, n),
+                Err(e) if e.kind() == ErrorKind::Interrupted => (),
+                Err(e) => return e,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A writer taking `limit` bytes at most, a few at a time
+    struct Limited {
+        written: Vec<u8>,
+        limit: usize,
+    }
+
+    impl Write for Limited {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            let len = buf.len().min(7).min(self.limit - self.written.len());
+            if len == 0 {
+                return Err(ErrorKind::BrokenPipe.into());
+            }
+            self.written.extend_from_slice(&buf[..len]);
+            Ok(len)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn lines() {
+        assert_eq!(line::<&str>(&[]), b"y\n");
+        assert_eq!(line(&["a", "", "b c"]), b"a  b c\n");
+        let long = "x".repeat(BUFFER_SIZE * 2);
+        assert_eq!(buffer(&line(&[&long])).len(), BUFFER_SIZE * 2 + 1);
+        assert_eq!(buffer(b"ab\n").len(), BUFFER_SIZE.div_ceil(3) * 3);
+    }
+
+    #[test]
+    fn partial_writes() {
+        let buffer = buffer(b"ab\n");
+        let limit = buffer.len() * (SLICES + 1) + 5;
+        let mut out = Limited {
+            written: Vec::new(),
+            limit,
+        };
+        assert_eq!(yes(&buffer, &mut out).kind(), ErrorKind::BrokenPipe);
+        assert_eq!(out.written, b"ab\n".repeat(limit / 3 + 1)[..limit]);
+    }
+}